@@ -0,0 +1,99 @@
+use serde::Deserialize;
+use std::{
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+  process::Command,
+  thread,
+  time::{Duration, SystemTime},
+};
+
+#[derive(Deserialize)]
+struct CargoToml {
+  package: Package,
+}
+#[derive(Deserialize)]
+struct Package {
+  name: String,
+}
+
+#[derive(Deserialize)]
+struct PluginToml {
+  wasm: WasmConfig,
+}
+#[derive(Deserialize)]
+struct WasmConfig {
+  compile: String,
+  output:  String,
+}
+
+/// Builds the plugin in the current directory, and copies it into the given
+/// server. If `once` is false, this will watch the `src` directory, and
+/// rebuild whenever something changes.
+pub fn dev(server: &Path, once: bool) -> Result<(), Box<dyn Error>> {
+  let cargo: CargoToml = toml::from_str(&fs::read_to_string("Cargo.toml")?)?;
+  let plugin: PluginToml = toml::from_str(&fs::read_to_string("plugin.toml")?)?;
+  let name = cargo.package.name;
+
+  let plugins = server.join("plugins");
+  if !plugins.is_dir() {
+    return Err(format!("`{}` is not a directory", plugins.display()).into());
+  }
+  let dest = plugins.join(&name);
+  fs::create_dir_all(&dest)?;
+  // The server shouldn't compile anything, it should just load the wasm file we
+  // copy over.
+  fs::write(
+    dest.join("plugin.toml"),
+    format!("plugin-type = \"wasm\"\nenabled = true\n\n[wasm]\ncompile = \"\"\noutput = \"{name}.wasm\"\n"),
+  )?;
+
+  let mut last_change = None;
+  loop {
+    let change = latest_change(Path::new("src"))?;
+    if last_change != Some(change) {
+      last_change = Some(change);
+      match build(&plugin.wasm.compile) {
+        Ok(()) => {
+          copy(Path::new(&plugin.wasm.output), &dest.join(format!("{name}.wasm")))?;
+          println!("copied `{name}` into {}", dest.display());
+        }
+        Err(e) => eprintln!("failed to build `{name}`: {e}"),
+      }
+    }
+    if once {
+      return Ok(());
+    }
+    thread::sleep(Duration::from_millis(500));
+  }
+}
+
+fn build(compile: &str) -> Result<(), Box<dyn Error>> {
+  let status = Command::new("sh").arg("-c").arg(compile).status()?;
+  if status.success() {
+    Ok(())
+  } else {
+    Err(format!("`{compile}` exited with {status}").into())
+  }
+}
+
+/// Copies the file at `from` to `to`. This writes to a temporary file first,
+/// so that the server never reads a partially written file.
+fn copy(from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+  let tmp: PathBuf = to.with_extension("wasm.tmp");
+  fs::copy(from, &tmp)?;
+  fs::rename(&tmp, to)?;
+  Ok(())
+}
+
+/// Returns the latest modified time of any file within `dir`.
+fn latest_change(dir: &Path) -> Result<SystemTime, Box<dyn Error>> {
+  let mut latest = SystemTime::UNIX_EPOCH;
+  for entry in fs::read_dir(dir)? {
+    let entry = entry?;
+    let meta = entry.metadata()?;
+    let time = if meta.is_dir() { latest_change(&entry.path())? } else { meta.modified()? };
+    latest = latest.max(time);
+  }
+  Ok(latest)
+}
@@ -0,0 +1,76 @@
+//! A cargo subcommand for working with bamboo plugins. Once installed (with
+//! `cargo install --path bb_cargo`), this can be run with `cargo bb`.
+//!
+//! - `cargo bb new-plugin <name>` creates a new wasm plugin.
+//! - `cargo bb dev --server <path>` builds the plugin in the current directory,
+//!   and copies it into the given server. It then watches the plugin source,
+//!   and copies it again whenever it changes. The server will notice the new
+//!   wasm file, and reload all plugins.
+
+use clap::{Parser, Subcommand};
+use std::{error::Error, path::PathBuf, process};
+
+mod dev;
+mod new;
+
+/// Cargo passes `bb` as the first argument to `cargo-bb`, so we need this
+/// wrapper to parse it.
+#[derive(Parser, Debug)]
+#[clap(bin_name = "cargo")]
+enum Cargo {
+  Bb(Args),
+}
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+  #[clap(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Creates a new wasm plugin in a directory called `name`.
+  NewPlugin {
+    /// The name of the plugin. This is used for the directory name, and the
+    /// crate name.
+    name:   String,
+    /// A path to the bamboo repository. If set, `bb_plugin` will be a path
+    /// dependency. Otherwise, `bb_plugin` will be pulled from git.
+    #[clap(long)]
+    bamboo: Option<PathBuf>,
+    /// The directory to create the plugin in. Defaults to the current
+    /// directory.
+    #[clap(long, default_value = ".")]
+    path:   PathBuf,
+  },
+  /// Builds the plugin in the current directory, and copies it into a server.
+  /// This will keep running, and rebuild the plugin when the source changes.
+  Dev {
+    /// The directory of the server to copy the plugin into. This should be
+    /// the directory containing `server.toml` and `plugins`.
+    #[clap(long)]
+    server: PathBuf,
+    /// If set, the plugin will only be built and copied once.
+    #[clap(long)]
+    once:   bool,
+  },
+}
+
+fn main() {
+  let Cargo::Bb(args) = Cargo::parse();
+  match run(args) {
+    Ok(()) => {}
+    Err(e) => {
+      eprintln!("error: {e}");
+      process::exit(1);
+    }
+  }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+  match args.command {
+    Command::NewPlugin { name, bamboo, path } => new::new_plugin(&name, bamboo, &path),
+    Command::Dev { server, once } => dev::dev(&server, once),
+  }
+}
@@ -0,0 +1,116 @@
+use std::{
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+};
+
+const LIB_RS: &str = r#"use bb_plugin::{command::Command, player::Player};
+
+#[no_mangle]
+extern "C" fn init() {
+  bb_plugin::init();
+  bb_plugin::set_on_tick(on_tick);
+  bb_plugin::add_command(&Command::new("hello"), |player: Option<Player>, _args| {
+    if let Some(player) = player {
+      bb_plugin::info!("hello, {}!", player.username().unwrap());
+    }
+  });
+}
+
+fn on_tick() {}
+"#;
+
+const CARGO_CONFIG: &str = r#"[build]
+target = "wasm32-unknown-unknown"
+"#;
+
+const GITIGNORE: &str = "target/\n";
+
+/// Creates a new wasm plugin called `name` within `dir`.
+pub fn new_plugin(name: &str, bamboo: Option<PathBuf>, dir: &Path) -> Result<(), Box<dyn Error>> {
+  if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+    return Err(format!("invalid plugin name `{name}`").into());
+  }
+  let root = dir.join(name);
+  if root.exists() {
+    return Err(format!("`{}` already exists", root.display()).into());
+  }
+  let lib_name = name.replace('-', "_");
+  let dep = match bamboo {
+    Some(path) => {
+      let path = fs::canonicalize(&path)?.join("bb_plugin");
+      format!("bb_plugin = {{ path = {:?} }}", path.display().to_string())
+    }
+    None => "bb_plugin = { git = \"https://github.com/macmv/bamboo\" }".into(),
+  };
+
+  fs::create_dir_all(root.join("src"))?;
+  fs::create_dir_all(root.join(".cargo"))?;
+  fs::write(root.join("Cargo.toml"), cargo_toml(name, &lib_name, &dep))?;
+  fs::write(root.join("plugin.toml"), plugin_toml(&lib_name))?;
+  fs::write(root.join("src/lib.rs"), LIB_RS)?;
+  fs::write(root.join(".cargo/config.toml"), CARGO_CONFIG)?;
+  fs::write(root.join(".gitignore"), GITIGNORE)?;
+
+  println!("created plugin `{name}` at {}", root.display());
+  println!("run `cargo bb dev --server <path>` in that directory to load it into a server");
+  Ok(())
+}
+
+fn cargo_toml(name: &str, lib_name: &str, dep: &str) -> String {
+  format!(
+    r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+name = "{lib_name}"
+crate-type = ["cdylib"]
+
+[dependencies]
+{dep}
+
+# Separate workspace
+[workspace]
+
+[profile.release]
+lto = true
+
+[profile.rust-analyzer]
+inherits = "dev"
+opt-level = 0
+"#
+  )
+}
+
+fn plugin_toml(lib_name: &str) -> String {
+  format!(
+    r#"plugin-type = "wasm"
+enabled = true
+
+[wasm]
+compile = "cargo build --target wasm32-unknown-unknown --release --color=always"
+output = "target/wasm32-unknown-unknown/release/{lib_name}.wasm"
+"#
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn creates_plugin() {
+    let dir = std::env::temp_dir().join(format!("bb-cargo-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    new_plugin("my-plugin", None, &dir).unwrap();
+    let root = dir.join("my-plugin");
+    assert!(root.join("src/lib.rs").exists());
+    let plugin = fs::read_to_string(root.join("plugin.toml")).unwrap();
+    assert!(plugin.contains("release/my_plugin.wasm"));
+    assert!(new_plugin("my-plugin", None, &dir).is_err());
+    assert!(new_plugin("bad/name", None, &dir).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
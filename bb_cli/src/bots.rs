@@ -0,0 +1,317 @@
+//! A load testing mode. This spawns a number of simulated clients, which log
+//! in, walk around, chat, and break blocks at random. Every few seconds, the
+//! latency and throughput of all the bots are logged.
+//!
+//! Chat latency is the time between sending a chat message and receiving it
+//! back from the server. Dig latency is the time between breaking a block and
+//! receiving the block update for it. Both of these go through the proxy and
+//! the server tick loop, so they are a good measure of how loaded the server
+//! is.
+
+use super::conn::ConnStream;
+use bb_common::{
+  math::{FPos, Pos},
+  util::Chat,
+  version::ProtocolVersion,
+};
+use bb_proxy::{gnet::cb, stream::java::JavaStream, Result};
+use mio::{net::TcpStream, Events, Interest, Poll, Token};
+use rand::{rngs::ThreadRng, Rng};
+use std::{
+  collections::VecDeque,
+  error::Error,
+  io,
+  net::SocketAddr,
+  time::{Duration, Instant},
+};
+
+/// How often bots perform actions.
+const TICK: Duration = Duration::from_millis(50);
+/// If a block update doesn't come back after this long, the dig is counted as
+/// unanswered. The server might not allow the bot to break the block at all.
+const DIG_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct BotConfig {
+  pub addr:      SocketAddr,
+  /// The version the bots connect with.
+  pub ver:       ProtocolVersion,
+  /// The number of bots to spawn.
+  pub count:     u32,
+  /// How long to wait between spawning each bot.
+  pub join:      Duration,
+  /// The number of moves per second, for each bot.
+  pub move_rate: f64,
+  /// The number of chat messages per second, for each bot.
+  pub chat_rate: f64,
+  /// The number of blocks broken per second, for each bot.
+  pub dig_rate:  f64,
+  /// How often to log stats.
+  pub report:    Duration,
+  /// How long to run for. If `None`, this runs until killed.
+  pub duration:  Option<Duration>,
+}
+
+struct Bot {
+  name:      String,
+  conn:      ConnStream,
+  joined_at: Instant,
+  logged_in: bool,
+  pos:       Option<FPos>,
+  next_chat: u32,
+  /// Chat messages we have sent, which haven't come back yet.
+  chats:     VecDeque<(String, Instant)>,
+  /// Blocks we have broken, which haven't been updated yet.
+  digs:      Vec<(Pos, Instant)>,
+}
+
+/// Stats for a single report. These are reset after each report.
+#[derive(Default)]
+struct Stats {
+  logins:      u32,
+  disconnects: u32,
+  sent:        u64,
+  received:    u64,
+  login:       Vec<Duration>,
+  chat:        Vec<Duration>,
+  dig:         Vec<Duration>,
+  dig_lost:    u32,
+}
+
+pub fn run(config: BotConfig) -> std::result::Result<(), Box<dyn Error>> {
+  info!(
+    "spawning {} bots on {}, moving {}/s, chatting {}/s, and digging {}/s per bot",
+    config.count, config.ver, config.addr, config.move_rate, config.chat_rate, config.dig_rate
+  );
+  let mut poll = Poll::new()?;
+  let mut events = Events::with_capacity(1024);
+  let mut bots: Vec<Option<Bot>> = vec![];
+  let mut stats = Stats::default();
+  let mut rng = rand::thread_rng();
+
+  let start = Instant::now();
+  let mut next_join = start;
+  let mut next_tick = start;
+  let mut next_report = start + config.report;
+
+  loop {
+    let now = Instant::now();
+    if config.duration.is_some_and(|d| now >= start + d) {
+      stats.report(&bots, now - (next_report - config.report));
+      info!("finished load test");
+      return Ok(());
+    }
+    while bots.len() < config.count as usize && now >= next_join {
+      let i = bots.len();
+      let mut stream = TcpStream::connect(config.addr)?;
+      poll.registry().register(&mut stream, Token(i), Interest::READABLE | Interest::WRITABLE)?;
+      let mut conn = ConnStream::new(JavaStream::new(stream), config.ver);
+      let name = format!("bot{i}");
+      conn.start_handshake(&name);
+      bots.push(Some(Bot {
+        name,
+        conn,
+        joined_at: now,
+        logged_in: false,
+        pos: None,
+        next_chat: 0,
+        chats: VecDeque::new(),
+        digs: vec![],
+      }));
+      next_join += config.join;
+    }
+
+    poll.poll(&mut events, Some(next_tick.saturating_duration_since(now)))?;
+
+    for event in &events {
+      let Some(Some(bot)) = bots.get_mut(event.token().0) else { continue };
+      if event.is_readable() {
+        if let Err(e) = bot.read(&mut stats) {
+          error!("{} errored: {e}", bot.name);
+          bot.conn.close();
+        }
+      }
+    }
+
+    let now = Instant::now();
+    if now >= next_tick {
+      let dt = (now - next_tick + TICK).as_secs_f64();
+      for bot in bots.iter_mut().flatten() {
+        if bot.conn.in_play() {
+          bot.tick(&config, dt, now, &mut rng, &mut stats);
+        }
+      }
+      next_tick = now + TICK;
+    }
+
+    for slot in bots.iter_mut() {
+      let Some(bot) = slot else { continue };
+      while !bot.conn.closed() && bot.conn.needs_flush() {
+        match bot.conn.flush() {
+          Ok(_) => {}
+          Err(ref e) if e.io_kind() == Some(io::ErrorKind::WouldBlock) => break,
+          Err(e) => {
+            error!("{} could not flush: {e}", bot.name);
+            bot.conn.close();
+          }
+        }
+      }
+      if bot.conn.closed() {
+        warn!("{} disconnected", bot.name);
+        stats.disconnects += 1;
+        *slot = None;
+      }
+    }
+
+    if now >= next_report {
+      stats.report(&bots, config.report);
+      stats = Stats::default();
+      next_report += config.report;
+    }
+  }
+}
+
+impl Bot {
+  /// Reads all the packets we have received.
+  fn read(&mut self, stats: &mut Stats) -> Result<()> {
+    loop {
+      match self.conn.poll() {
+        Ok(_) => {}
+        Err(ref e) if e.io_kind() == Some(io::ErrorKind::WouldBlock) => break,
+        Err(e) => return Err(e),
+      }
+      while let Some(p) = self.conn.read()? {
+        stats.received += 1;
+        self.handle(p, stats)?;
+      }
+      if self.conn.closed() {
+        break;
+      }
+    }
+    if !self.logged_in && self.conn.in_play() {
+      self.logged_in = true;
+      stats.logins += 1;
+      stats.login.push(self.joined_at.elapsed());
+    }
+    Ok(())
+  }
+
+  fn handle(&mut self, p: cb::Packet, stats: &mut Stats) -> Result<()> {
+    match p {
+      cb::Packet::KeepAlive(cb::packet::KeepAlive::V8(p)) => {
+        self.conn.keep_alive(p.id.into());
+        stats.sent += 1;
+      }
+      cb::Packet::KeepAlive(cb::packet::KeepAlive::V12(p)) => {
+        self.conn.keep_alive(p.id);
+        stats.sent += 1;
+      }
+      cb::Packet::PlayerPosLook(cb::packet::PlayerPosLook::V8(p)) => {
+        self.pos = Some(FPos::new(p.x, p.y, p.z));
+      }
+      cb::Packet::Chat(cb::packet::Chat::V8(p)) => self.chat(&p.chat_component, stats),
+      cb::Packet::Chat(cb::packet::Chat::V12(p)) => self.chat(&p.chat_component, stats),
+      cb::Packet::SystemChat(cb::packet::SystemChat::V19(p)) => self.chat(&p.a, stats),
+      cb::Packet::BlockUpdate(cb::packet::BlockUpdate::V8(p)) => {
+        self.block_update(p.block_position, stats)
+      }
+      cb::Packet::BlockUpdate(cb::packet::BlockUpdate::V19(p)) => self.block_update(p.pos, stats),
+      cb::Packet::Disconnect(cb::packet::Disconnect::V8(p)) => {
+        error!("{} was disconnected: {}", self.name, p.reason);
+        self.conn.close();
+      }
+      _ => {}
+    }
+    Ok(())
+  }
+
+  fn chat(&mut self, json: &str, stats: &mut Stats) {
+    if let Ok(m) = Chat::from_json(json) {
+      let text = m.to_plain();
+      // Messages come back in the order they were sent, so anything before the
+      // message we found was dropped.
+      if let Some(i) = self.chats.iter().position(|(tag, _)| text.contains(tag.as_str())) {
+        let (_, sent) = self.chats.drain(..=i).last().unwrap();
+        stats.chat.push(sent.elapsed());
+      }
+    }
+  }
+
+  fn block_update(&mut self, pos: Pos, stats: &mut Stats) {
+    if let Some(i) = self.digs.iter().position(|(p, _)| *p == pos) {
+      let (_, sent) = self.digs.swap_remove(i);
+      stats.dig.push(sent.elapsed());
+    }
+  }
+
+  fn tick(
+    &mut self,
+    config: &BotConfig,
+    dt: f64,
+    now: Instant,
+    rng: &mut ThreadRng,
+    stats: &mut Stats,
+  ) {
+    let Some(pos) = self.pos else { return };
+    let lost = self.digs.len();
+    self.digs.retain(|(_, sent)| now - *sent < DIG_TIMEOUT);
+    stats.dig_lost += (lost - self.digs.len()) as u32;
+
+    if rng.gen_bool((config.move_rate * dt).min(1.0)) {
+      let pos =
+        FPos::new(pos.x + rng.gen_range(-1.0..=1.0), pos.y, pos.z + rng.gen_range(-1.0..=1.0));
+      self.pos = Some(pos);
+      self.conn.move_to(pos);
+      stats.sent += 1;
+    }
+    if rng.gen_bool((config.chat_rate * dt).min(1.0)) {
+      let tag = format!("[{}#{}]", self.name, self.next_chat);
+      self.next_chat += 1;
+      self.conn.chat(format!("load test {tag}"));
+      stats.sent += 1;
+      self.chats.push_back((tag, now));
+    }
+    if rng.gen_bool((config.dig_rate * dt).min(1.0)) {
+      let block = pos.block() + Pos::new(rng.gen_range(-2..=2), -1, rng.gen_range(-2..=2));
+      for status in [0, 2] {
+        self.conn.dig(status, block);
+        stats.sent += 1;
+      }
+      self.digs.push((block, now));
+    }
+  }
+}
+
+impl Stats {
+  fn report(&self, bots: &[Option<Bot>], elapsed: Duration) {
+    let secs = elapsed.as_secs_f64();
+    let online = bots.iter().flatten().filter(|b| b.logged_in).count();
+    info!(
+      "{online} online ({} logins, {} disconnects), {:.0} packets/s sent, {:.0} packets/s received",
+      self.logins,
+      self.disconnects,
+      self.sent as f64 / secs,
+      self.received as f64 / secs,
+    );
+    info!("login: {}", summary(&self.login));
+    info!("chat:  {}", summary(&self.chat));
+    info!("dig:   {} ({} unanswered)", summary(&self.dig), self.dig_lost);
+  }
+}
+
+/// Returns the p50, p95, p99, and max of the given latencies.
+fn summary(samples: &[Duration]) -> String {
+  if samples.is_empty() {
+    return "no samples".into();
+  }
+  let mut samples = samples.to_vec();
+  samples.sort_unstable();
+  let at = |p: f64| samples[((samples.len() - 1) as f64 * p).round() as usize];
+  format!(
+    "p50 {:?}, p95 {:?}, p99 {:?}, max {:?} ({} samples)",
+    at(0.5),
+    at(0.95),
+    at(0.99),
+    samples.last().unwrap(),
+    samples.len()
+  )
+}
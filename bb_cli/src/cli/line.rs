@@ -6,18 +6,23 @@ use std::{
 };
 
 pub struct SingleLineReader<'a> {
-  buf:    &'a mut ScrollBuf,
-  prompt: &'a str,
+  buf:       &'a mut ScrollBuf,
+  prompt:    &'a str,
+  completer: Option<&'a (dyn Fn(&str) -> Vec<String> + Send)>,
   // Char and byte index. This is where the cursor is on the screen. UTF8 screws this over, so if
   // UTF8 is going to be correctly implemented, this number should be where the cursor is, not a
   // byte index or anything.
-  col:    u16,
-  out:    String,
+  col:       u16,
+  out:       String,
 }
 
 impl SingleLineReader<'_> {
-  pub fn new<'a>(buf: &'a mut ScrollBuf, prompt: &'a str) -> SingleLineReader<'a> {
-    SingleLineReader { buf, prompt, col: prompt.len() as u16, out: String::new() }
+  pub fn new<'a>(
+    buf: &'a mut ScrollBuf,
+    prompt: &'a str,
+    completer: Option<&'a (dyn Fn(&str) -> Vec<String> + Send)>,
+  ) -> SingleLineReader<'a> {
+    SingleLineReader { buf, prompt, completer, col: prompt.len() as u16, out: String::new() }
   }
 
   pub fn read(mut self) -> io::Result<String> {
@@ -62,6 +67,10 @@ impl SingleLineReader<'_> {
           }
           continue;
         }
+        '\t' => {
+          self.complete()?;
+          continue;
+        }
         '\x1b' => {
           // escape (things like arrows keys)
           in_escape = true;
@@ -97,6 +106,42 @@ impl SingleLineReader<'_> {
     Ok(self.out)
   }
 
+  /// Completes the last word. If there is a single completion, the whole word
+  /// is filled in. If there are multiple, the common prefix is filled in, and
+  /// the options are logged.
+  fn complete(&mut self) -> io::Result<()> {
+    // Completing in the middle of the line isn't supported.
+    let Some(completer) = self.completer else { return Ok(()) };
+    if self.col != self.max_col() {
+      return Ok(());
+    }
+    let options = completer(&self.out);
+    let last = self.out.rsplit(' ').next().unwrap_or("");
+    // Arguments (like `<pos>`) are hints, and shouldn't be typed out.
+    let words: Vec<_> = options.iter().filter(|o| !o.starts_with('<')).collect();
+    let mut suffix = match words.as_slice() {
+      [] => String::new(),
+      [word] => format!("{} ", &word[last.len()..]),
+      [first, rest @ ..] => {
+        let mut common = first.len();
+        for word in rest {
+          common = common.min(first.bytes().zip(word.bytes()).take_while(|(a, b)| a == b).count());
+        }
+        first[last.len()..common.max(last.len())].to_string()
+      }
+    };
+    if options.len() > 1 || words.is_empty() && !options.is_empty() {
+      info!("{}", options.join(" "));
+    }
+    suffix.retain(|c| c.is_ascii());
+    for c in suffix.chars() {
+      self.out.push(c);
+      self.buf.write_all(&[c as u8])?;
+      self.col += 1;
+    }
+    self.buf.flush()
+  }
+
   fn parse_escape(&mut self, code: &str) -> io::Result<bool> {
     let bytes = code.as_bytes();
     // incomplete
@@ -93,18 +93,31 @@ pub fn setup() -> Result<(), io::Error> {
   Ok(())
 }
 
+/// Returns possible values for the last word of the given line.
+pub type Completer = Box<dyn Fn(&str) -> Vec<String> + Send>;
+
 pub struct LineReader {
-  buf:    ScrollBuf,
-  prompt: &'static str,
+  buf:       ScrollBuf,
+  prompt:    &'static str,
+  completer: Option<Completer>,
 }
 
 impl LineReader {
   pub fn new(prompt: &'static str, min: u16, len: u16) -> Self {
-    LineReader { buf: ScrollBuf::new_no_restore(min, len), prompt }
+    LineReader { buf: ScrollBuf::new_no_restore(min, len), prompt, completer: None }
+  }
+
+  /// Sets the function used to complete the line when tab is pressed.
+  pub fn with_completer(
+    mut self,
+    completer: impl Fn(&str) -> Vec<String> + Send + 'static,
+  ) -> Self {
+    self.completer = Some(Box::new(completer));
+    self
   }
 
   pub fn read_line(&mut self) -> Result<String, io::Error> {
-    line::SingleLineReader::new(&mut self.buf, self.prompt).read()
+    line::SingleLineReader::new(&mut self.buf, self.prompt, self.completer.as_deref()).read()
   }
 }
 
@@ -1,8 +1,34 @@
 use super::ConnStream;
-use crate::cli::LineReader;
-use bb_proxy::gnet::sb;
+use crate::{cli::LineReader, tree::CommandTree};
+use bb_common::math::FPos;
 use std::{io, io::Write};
 
+/// The commands handled by the cli itself. Anything starting with a `/` is
+/// sent to the server.
+const COMMANDS: &[&str] = &["move", "say", "send"];
+
+/// Returns all the possible values for the last word in `line`. Server
+/// commands are completed using the command tree the server sent us.
+pub fn complete(line: &str, tree: &CommandTree) -> Vec<String> {
+  let words: Vec<_> = line.split(' ').collect();
+  match words.as_slice() {
+    [first] => {
+      let mut out: Vec<_> =
+        COMMANDS.iter().filter(|c| c.starts_with(first)).map(|c| c.to_string()).collect();
+      if let Some(name) = first.strip_prefix('/') {
+        out.extend(tree.complete(&[name]).into_iter().map(|c| format!("/{c}")));
+      }
+      out
+    }
+    [first, ..] if first.starts_with('/') => {
+      let mut words = words.clone();
+      words[0] = &first[1..];
+      tree.complete(&words)
+    }
+    _ => vec![],
+  }
+}
+
 pub fn handle(
   command: &str,
   args: &[&str],
@@ -16,7 +42,7 @@ pub fn handle(
     "say" => {
       let saying = args.join(" ");
       writeln!(l, "saying {}", saying)?;
-      stream.send(sb::packet::ChatV8 { message: saying });
+      stream.chat(saying);
     }
     "move" => {
       if args.len() != 3 {
@@ -36,13 +62,13 @@ pub fn handle(
         Err(_) => return Ok(()),
       };
       writeln!(l, "moving to {} {} {}", x, y, z)?;
-      stream.send(sb::packet::PlayerPositionV8 { x, y, z, on_ground: true });
+      stream.move_to(FPos::new(x, y, z));
     }
     c if c.starts_with('/') => {
       let mut out = command.to_string();
       out.push_str(&args.join(" "));
       writeln!(l, "sending command {}", out)?;
-      stream.send(sb::packet::ChatV8 { message: out });
+      stream.chat(out);
     }
     _ => {
       writeln!(l, "unknown command: {}", command)?;
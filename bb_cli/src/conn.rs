@@ -1,26 +1,48 @@
-use bb_common::{math::der, util::UUID, version::ProtocolVersion};
+use bb_common::{
+  math::{der, FPos, Pos},
+  util::{Buffer, UUID},
+  version::ProtocolVersion,
+};
 use bb_proxy::{
   conn::State,
-  gnet::{cb, sb, tcp},
+  gnet::{self, cb, sb, tcp, VERSIONS},
   stream::{java::JavaStream, PacketStream},
   Result,
 };
 use rand::{rngs::OsRng, Rng};
 use rsa::PublicKey;
-use std::io;
+use std::{
+  io,
+  time::{SystemTime, UNIX_EPOCH},
+};
 
 pub struct ConnStream {
-  stream: JavaStream,
-  ver:    ProtocolVersion,
-  closed: bool,
-  state:  State,
+  stream:   JavaStream,
+  ver:      ProtocolVersion,
+  closed:   bool,
+  state:    State,
+  /// The sequence number for block changes. This is only sent on 1.19+.
+  sequence: i32,
+}
+
+/// Parses a version like `1.12` or `1.12.2`. Only the versions supported by
+/// the proxy are accepted, and a major version on its own selects the latest
+/// release of that version.
+pub fn parse_version(s: &str) -> Option<ProtocolVersion> {
+  VERSIONS
+    .iter()
+    .copied()
+    .find(|v| v.to_string() == s || v.maj().is_some_and(|maj| format!("1.{maj}") == s))
 }
 
 impl ConnStream {
-  pub fn new(stream: JavaStream) -> Self {
-    ConnStream { stream, ver: ProtocolVersion::V1_8, closed: false, state: State::Handshake }
+  pub fn new(stream: JavaStream, ver: ProtocolVersion) -> Self {
+    ConnStream { stream, ver, closed: false, state: State::Handshake, sequence: 0 }
   }
-  pub fn start_handshake(&mut self) {
+  pub fn ver(&self) -> ProtocolVersion { self.ver }
+  /// Sends the handshake and login start packets. The connection will be in
+  /// the play state once the server accepts the login.
+  pub fn start_handshake(&mut self, username: &str) {
     let mut out = tcp::Packet::new(0, self.ver);
     out.write_varint(self.ver.id() as i32);
     out.write_str("127.0.0.1");
@@ -29,7 +51,11 @@ impl ConnStream {
     self.stream.write(out);
     self.state = State::Login;
     let mut out = tcp::Packet::new(0, self.ver);
-    out.write_str("macmv");
+    out.write_str(username);
+    if self.ver >= ProtocolVersion::V1_19_3 {
+      // We don't send a uuid, so the server picks one for us.
+      out.write_bool(false);
+    }
     self.stream.write(out);
   }
   pub fn send(&mut self, p: impl Into<sb::Packet>) {
@@ -38,9 +64,97 @@ impl ConnStream {
     p.to_tcp(&mut tcp);
     self.stream.write(tcp);
   }
+  /// Sends a packet by writing its fields directly. This is used for packets
+  /// where the generated fields don't have useful names. If the packet
+  /// doesn't exist on this version, nothing is sent.
+  fn send_raw(&mut self, name: &str, write: impl FnOnce(&mut tcp::Packet)) {
+    match gnet::find_packet(sb::packets(self.ver), name) {
+      Some(p) => {
+        let mut out = tcp::Packet::new(p.id, self.ver);
+        write(&mut out);
+        self.stream.write(out);
+      }
+      None => warn!("packet {name} does not exist on {}", self.ver),
+    }
+  }
+
+  /// Responds to a keep alive from the server.
+  pub fn keep_alive(&mut self, id: i64) {
+    if self.ver >= ProtocolVersion::V1_12_2 {
+      self.send(sb::packet::KeepAliveV12 { key: id });
+    } else {
+      self.send(sb::packet::KeepAliveV8 { key: id as i32 });
+    }
+  }
+  /// Sends a chat message. If the message starts with a `/`, it is sent as a
+  /// command.
+  pub fn chat(&mut self, message: String) {
+    if self.ver < ProtocolVersion::V1_19 {
+      if self.ver >= ProtocolVersion::V1_11 {
+        self.send(sb::packet::ChatV11 { message });
+      } else {
+        self.send(sb::packet::ChatV8 { message });
+      }
+      return;
+    }
+    // 1.19+ clients sign their messages. We send the message unsigned, with an
+    // empty list of acknowledged messages.
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64;
+    let mut data = vec![];
+    let mut buf = Buffer::new(&mut data);
+    let command = message.strip_prefix('/');
+    buf.write_str(command.unwrap_or(&message));
+    buf.write_i64(timestamp);
+    buf.write_i64(rand::random());
+    if command.is_some() {
+      buf.write_varint(0); // argument signatures
+    } else {
+      buf.write_bool(false); // signature
+    }
+    buf.write_varint(0); // message count
+    buf.write_buf(&[0; 3]); // acknowledged bitset
+    if command.is_some() {
+      self.send(sb::packet::CommandExecutionV19 { unknown: data.into() });
+    } else {
+      self.send(sb::packet::ChatV19 { unknown: data.into() });
+    }
+  }
+  /// Moves the player to the given position.
+  pub fn move_to(&mut self, pos: FPos) {
+    if self.ver >= ProtocolVersion::V1_17 {
+      self.send_raw("PlayerPosition", |out| {
+        out.write_f64(pos.x);
+        out.write_f64(pos.y);
+        out.write_f64(pos.z);
+        out.write_bool(true);
+      });
+    } else {
+      self.send(sb::packet::PlayerPositionV8 {
+        x:         pos.x,
+        y:         pos.y,
+        z:         pos.z,
+        on_ground: true,
+      });
+    }
+  }
+  /// Sends a dig packet for the top face of the given block. A status of 0
+  /// starts digging, and 2 finishes digging.
+  pub fn dig(&mut self, status: i8, pos: Pos) {
+    let mut data = vec![];
+    let mut buf = Buffer::new(&mut data);
+    buf.write_varint(1); // top face
+    if self.ver >= ProtocolVersion::V1_19 {
+      self.sequence += 1;
+      buf.write_varint(self.sequence);
+    }
+    self.send(sb::packet::PlayerDigV8 { status, position: pos, unknown: data.into() });
+  }
   pub fn needs_flush(&self) -> bool { self.stream.needs_flush() }
   pub fn flush(&mut self) -> Result<()> { self.stream.flush() }
   pub fn closed(&self) -> bool { self.closed }
+  pub fn close(&mut self) { self.closed = true; }
+  /// Returns `true` once the login is complete.
+  pub fn in_play(&self) -> bool { matches!(self.state, State::Play) }
 
   pub fn poll(&mut self) -> Result<()> { self.stream.poll() }
   pub fn read(&mut self) -> Result<Option<cb::Packet>> {
@@ -49,7 +163,13 @@ impl ConnStream {
         State::Play => Ok(Some(cb::Packet::from_tcp(&mut p, self.ver)?)),
         _ => {
           self.handle_handshake(p)?;
-          Ok(None)
+          // There may be more packets buffered after this one (for example,
+          // the first play packets right after the login success).
+          if self.closed {
+            Ok(None)
+          } else {
+            self.read()
+          }
         }
       }
     } else {
@@ -91,7 +211,7 @@ impl ConnStream {
             .encrypt(&mut rng, rsa::Pkcs1v15Encrypt, &token)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
 
-          let mut out = tcp::Packet::new(1, ProtocolVersion::V1_8);
+          let mut out = tcp::Packet::new(1, self.ver);
           out.write_varint(enc_secret.len() as i32);
           out.write_buf(&enc_secret);
           out.write_varint(enc_token.len() as i32);
@@ -115,6 +235,14 @@ impl ConnStream {
           let thresh = p.read_varint()?;
           self.stream.set_compression(thresh);
         }
+        4 => {
+          // login plugin request (1.13+). We don't understand any channels.
+          let id = p.read_varint()?;
+          let mut out = tcp::Packet::new(2, self.ver);
+          out.write_varint(id);
+          out.write_bool(false);
+          self.stream.write(out);
+        }
         _ => unreachable!(),
       },
       State::Play => unreachable!(),
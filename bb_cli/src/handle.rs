@@ -1,34 +1,34 @@
-use super::{conn::ConnStream, status::Status};
+use super::{
+  conn::ConnStream,
+  status::{Entity, Status},
+  tree::CommandTree,
+};
 use bb_common::{
-  math::ChunkPos,
+  math::{ChunkPos, FPos},
   util::{Buffer, Chat},
 };
-use bb_proxy::{
-  gnet::{cb, sb},
-  Result,
-};
+use bb_proxy::{gnet::cb, Result};
 use parking_lot::Mutex;
 use std::time::Instant;
 
 pub fn handle_packet(stream: &mut ConnStream, status: &Mutex<Status>, p: cb::Packet) -> Result<()> {
   match p {
     cb::Packet::JoinGame(cb::packet::JoinGame::V8(_)) => {}
-    cb::Packet::Chat(cb::packet::Chat::V8(p)) => match Chat::from_json(&p.chat_component) {
-      Ok(m) => {
-        if p.ty == 0 {
-          info!("chat: {}", m.to_plain())
-        } else {
-          status.lock().hotbar = m.to_plain();
-        }
-      }
-      Err(e) => warn!("invalid chat: {}", e),
-    },
+    cb::Packet::Chat(cb::packet::Chat::V8(p)) => chat(status, &p.chat_component, p.ty == 2),
+    cb::Packet::Chat(cb::packet::Chat::V12(p)) => {
+      chat(status, &p.chat_component, p.unknown.first() == Some(&2))
+    }
+    cb::Packet::SystemChat(cb::packet::SystemChat::V19(p)) => chat(status, &p.a, p.b),
     cb::Packet::Disconnect(cb::packet::Disconnect::V8(p)) => {
       error!("disconnected: {}", p.reason);
       // TODO: disconnect
     }
     cb::Packet::KeepAlive(cb::packet::KeepAlive::V8(p)) => {
-      stream.send(sb::packet::KeepAliveV8 { key: p.id });
+      stream.keep_alive(p.id.into());
+      status.lock().last_keep_alive = Instant::now();
+    }
+    cb::Packet::KeepAlive(cb::packet::KeepAlive::V12(p)) => {
+      stream.keep_alive(p.id);
       status.lock().last_keep_alive = Instant::now();
     }
     cb::Packet::ChunkData(cb::packet::ChunkData::V8(p)) => {
@@ -58,7 +58,68 @@ pub fn handle_packet(stream: &mut ConnStream, status: &Mutex<Status>, p: cb::Pac
       }
     }
     cb::Packet::Particle(cb::packet::Particle::V8(_p)) => {}
-    p => warn!("unhandled packet {}...", &format!("{:?}", p)[..40]),
+    cb::Packet::PlayerPosLook(cb::packet::PlayerPosLook::V8(p)) => {
+      status.lock().pos = Some(FPos::new(p.x, p.y, p.z));
+    }
+    cb::Packet::SpawnMob(cb::packet::SpawnMob::V8(p)) => {
+      let pos = fixed_pos(p.x, p.y, p.z);
+      status.lock().entities.insert(p.entity_id, Entity { kind: format!("mob {}", p.ty), pos });
+    }
+    cb::Packet::SpawnObject(cb::packet::SpawnObject::V8(p)) => {
+      let pos = fixed_pos(p.x, p.y, p.z);
+      status.lock().entities.insert(p.entity_id, Entity { kind: format!("object {}", p.ty), pos });
+    }
+    cb::Packet::SpawnPlayer(cb::packet::SpawnPlayer::V8(p)) => {
+      let mut lock = status.lock();
+      let kind = match lock.players.get(&p.player_id) {
+        Some(player) => format!("player {}", player.username),
+        None => "player".into(),
+      };
+      lock.entities.insert(p.entity_id, Entity { kind, pos: fixed_pos(p.x, p.y, p.z) });
+    }
+    cb::Packet::EntityTeleport(cb::packet::EntityTeleport::V8(p)) => {
+      if let Some(e) = status.lock().entities.get_mut(&p.entity_id) {
+        e.pos = fixed_pos(p.pos_x, p.pos_y, p.pos_z);
+      }
+    }
+    cb::Packet::DestroyEntities(cb::packet::DestroyEntities::V8(p)) => {
+      let mut buf = Buffer::new(p.unknown);
+      let mut lock = status.lock();
+      for eid in buf.read_varint_arr()? {
+        lock.entities.remove(&eid);
+      }
+    }
+    cb::Packet::CommandTree(cb::packet::CommandTree::V14(p)) => set_commands(status, &p.unknown),
+    cb::Packet::CommandTree(cb::packet::CommandTree::V16(p)) => set_commands(status, &p.unknown),
+    // Most packets are only handled in their 1.8 form, so this is quite noisy
+    // on newer versions.
+    p => debug!("unhandled packet {}...", &format!("{:?}", p)[..40]),
   }
   Ok(())
 }
+
+/// Logs a chat message, or shows it above the hotbar if `overlay` is set.
+fn chat(status: &Mutex<Status>, json: &str, overlay: bool) {
+  match Chat::from_json(json) {
+    Ok(m) => {
+      if overlay {
+        status.lock().hotbar = m.to_plain();
+      } else {
+        info!("chat: {}", m.to_plain())
+      }
+    }
+    Err(e) => warn!("invalid chat: {}", e),
+  }
+}
+
+/// Converts a 1.8 fixed point position (1/32 of a block) to a float position.
+fn fixed_pos(x: i32, y: i32, z: i32) -> FPos {
+  FPos::new(x as f64 / 32.0, y as f64 / 32.0, z as f64 / 32.0)
+}
+
+fn set_commands(status: &Mutex<Status>, data: &[u8]) {
+  match CommandTree::parse(data) {
+    Ok(tree) => status.lock().commands = tree,
+    Err(e) => warn!("could not parse command tree: {}", e),
+  }
+}
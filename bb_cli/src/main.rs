@@ -1,24 +1,98 @@
 #[macro_use]
 extern crate log;
 
+use bb_common::version::ProtocolVersion;
 use bb_proxy::stream::java::JavaStream;
+use clap::Parser;
 use conn::ConnStream;
 use crossterm::{execute, terminal};
 use mio::{net::TcpStream, Events, Interest, Poll, Token, Waker};
 use parking_lot::Mutex;
-use std::{env, error::Error, io, sync::Arc, thread};
+use std::{error::Error, io, sync::Arc, thread, time::Duration};
 
+mod bots;
 mod cli;
 mod command;
 mod conn;
 mod handle;
 mod status;
+mod tree;
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+  /// The address of the proxy to connect to.
+  #[clap(default_value = "127.0.0.1:25565")]
+  ip: String,
+
+  /// The version to connect with, like `1.8` or `1.12.2`. A major version on
+  /// its own uses the latest release of that version.
+  #[clap(long, default_value = "1.8")]
+  protocol: String,
+
+  /// Instead of opening an interactive client, spawn this many bots, and log
+  /// their latency and throughput.
+  #[clap(long, value_name = "N")]
+  bots:          Option<u32>,
+  /// The number of milliseconds between each bot joining.
+  #[clap(long, default_value_t = 50)]
+  join_ms:       u64,
+  /// The number of moves per second, for each bot.
+  #[clap(long, default_value_t = 1.0)]
+  move_rate:     f64,
+  /// The number of chat messages per second, for each bot.
+  #[clap(long, default_value_t = 0.1)]
+  chat_rate:     f64,
+  /// The number of blocks broken per second, for each bot.
+  #[clap(long, default_value_t = 0.2)]
+  dig_rate:      f64,
+  /// The number of seconds between each stats report.
+  #[clap(long, default_value_t = 5)]
+  report_secs:   u64,
+  /// Stops the bots after this many seconds. If not set, the bots will run
+  /// until the cli is killed.
+  #[clap(long)]
+  duration_secs: Option<u64>,
+}
 
 fn main() {
+  let args = Args::parse();
+  let Some(ver) = conn::parse_version(&args.protocol) else {
+    let versions: Vec<_> = bb_proxy::gnet::VERSIONS.iter().map(|v| v.to_string()).collect();
+    eprintln!("unsupported version {}, expected one of {}", args.protocol, versions.join(", "));
+    std::process::exit(1);
+  };
+  if let Some(count) = args.bots {
+    bb_common::init("cli");
+    let addr = match args.ip.parse() {
+      Ok(v) => v,
+      Err(e) => {
+        error!("invalid address: {e}");
+        std::process::exit(1);
+      }
+    };
+    let config = bots::BotConfig {
+      addr,
+      ver,
+      count,
+      join: Duration::from_millis(args.join_ms),
+      move_rate: args.move_rate,
+      chat_rate: args.chat_rate,
+      dig_rate: args.dig_rate,
+      report: Duration::from_secs(args.report_secs),
+      duration: args.duration_secs.map(Duration::from_secs),
+    };
+    if let Err(e) = bots::run(config) {
+      error!("error: {}", e);
+      std::process::exit(1);
+    }
+    return;
+  }
+
   let (_cols, rows) = terminal::size().unwrap();
   cli::setup().unwrap();
   bb_common::init_with_writer("cli", cli::skip_appender(15, rows - 30));
-  match run(rows) {
+  match run(&args.ip, ver, rows) {
     Ok(_) => (),
     Err(e) => {
       terminal::disable_raw_mode().unwrap();
@@ -29,14 +103,8 @@ fn main() {
   };
 }
 
-fn run(rows: u16) -> Result<(), Box<dyn Error>> {
-  let mut args = env::args();
-  args.next(); // current process
-  let ip = args.next().unwrap_or_else(|| "127.0.0.1:25565".into());
-
-  // let ver = ProtocolVersion::V1_8;
-
-  info!("connecting to {}", ip);
+fn run(ip: &str, ver: ProtocolVersion, rows: u16) -> Result<(), Box<dyn Error>> {
+  info!("connecting to {} with {}", ip, ver);
   let mut stream = TcpStream::connect(ip.parse()?)?;
   info!("connection established");
 
@@ -50,8 +118,8 @@ fn run(rows: u16) -> Result<(), Box<dyn Error>> {
 
   poll.registry().register(&mut stream, Token(0), Interest::READABLE | Interest::WRITABLE)?;
 
-  let mut conn = ConnStream::new(JavaStream::new(stream));
-  conn.start_handshake();
+  let mut conn = ConnStream::new(JavaStream::new(stream), ver);
+  conn.start_handshake("macmv");
   let conn = Arc::new(Mutex::new(conn));
 
   let status = Arc::new(Mutex::new(status::Status::new()));
@@ -60,9 +128,11 @@ fn run(rows: u16) -> Result<(), Box<dyn Error>> {
   // let mut next_token = 0;
 
   let c = conn.clone();
+  let s = status.clone();
   let w = waker;
   thread::spawn(move || {
-    let mut lr = cli::LineReader::new("> ", rows - 15, 15);
+    let mut lr = cli::LineReader::new("> ", rows - 15, 15)
+      .with_completer(move |line| command::complete(line, &s.lock().commands));
     while let Ok(line) = lr.read_line() {
       if line.is_empty() {
         continue;
@@ -2,15 +2,19 @@ use crossterm::terminal;
 use std::{io, io::Write};
 
 pub struct Lines {
-  left:  Vec<String>,
-  right: Vec<String>,
+  left:   Vec<String>,
+  center: Vec<String>,
+  right:  Vec<String>,
 }
 
 impl Lines {
-  pub fn new() -> Lines { Lines { left: vec![], right: vec![] } }
+  pub fn new() -> Lines { Lines { left: vec![], center: vec![], right: vec![] } }
 
   pub fn push_left(&mut self, v: String) { self.left.push(v); }
   pub fn push_right(&mut self, v: String) { self.right.push(v); }
+  /// Adds a line to the center column. The column is centered based on the
+  /// width of the first line, so all lines should be the same width.
+  pub fn push_center(&mut self, v: String) { self.center.push(v); }
 
   pub fn draw(&self) -> io::Result<()> {
     let (cols, _rows) = terminal::size()?;
@@ -18,7 +22,8 @@ impl Lines {
     let mut writer = stdout.lock();
 
     write!(writer, "\x1b[s")?; // save pos
-    for i in 0..self.left.len().max(self.right.len()) {
+    let center_col = cols / 2 - self.center.first().map(|l| l.len() as u16 / 2).unwrap_or(0) + 1;
+    for i in 0..self.left.len().max(self.right.len()).max(self.center.len()) {
       // go to start and clear line
       write!(writer, "\x1b[{};1H\x1b[K", i + 1)?;
       match (self.left.get(i), self.right.get(i)) {
@@ -34,7 +39,11 @@ impl Lines {
         (Some(left), None) => {
           write!(writer, "{}", left)?;
         }
-        (None, None) => unreachable!(),
+        (None, None) => {}
+      }
+      if let Some(center) = self.center.get(i) {
+        write!(writer, "\x1b[{};{}H", i + 1, center_col)?;
+        write!(writer, "{}", center)?;
       }
     }
     write!(writer, "\x1b[u")?; // restore pos
@@ -1,5 +1,9 @@
+use crate::tree::CommandTree;
 use ansi_term::Colour;
-use bb_common::{math::ChunkPos, util::UUID};
+use bb_common::{
+  math::{ChunkPos, FPos},
+  util::UUID,
+};
 use lines::Lines;
 use parking_lot::Mutex;
 use std::{
@@ -12,11 +16,20 @@ use std::{
 
 mod lines;
 
+/// How many chunks to show on each side of the player in the chunk map.
+const MAP_RADIUS: i32 = 5;
+/// How many entities to show in the entity list.
+const MAX_ENTITIES: usize = 10;
+
 pub struct Status {
   pub players:         HashMap<UUID, Player>,
   pub loaded_chunks:   HashSet<ChunkPos>,
   pub last_keep_alive: Instant,
   pub render_distance: i32,
+  /// Our position. This is `None` until the server teleports us.
+  pub pos:             Option<FPos>,
+  pub entities:        HashMap<i32, Entity>,
+  pub commands:        CommandTree,
 
   pub header: String,
   pub footer: String,
@@ -28,6 +41,12 @@ pub struct Player {
   pub uuid:     UUID,
 }
 
+pub struct Entity {
+  /// A short description of the entity, like `mob 54`.
+  pub kind: String,
+  pub pos:  FPos,
+}
+
 impl Status {
   pub fn new() -> Self {
     Status {
@@ -35,6 +54,9 @@ impl Status {
       loaded_chunks:   HashSet::new(),
       last_keep_alive: Instant::now(),
       render_distance: 10,
+      pos:             None,
+      entities:        HashMap::new(),
+      commands:        CommandTree::default(),
       header:          String::new(),
       footer:          String::new(),
       hotbar:          String::new(),
@@ -68,13 +90,65 @@ impl Status {
       }
     ));
 
+    match self.pos {
+      Some(pos) => lines.push_left(format!("position: {:.1} {:.1} {:.1}", pos.x, pos.y, pos.z)),
+      None => lines.push_left("position: unknown".into()),
+    }
+    lines.push_left(format!("entities: {}", self.entities.len()));
+
     lines.push_right(format!("header: {}", self.header));
     lines.push_right(format!("footer: {}", self.footer));
     lines.push_right(format!("hotbar: {}", self.hotbar));
+    for line in self.entity_list() {
+      lines.push_right(line);
+    }
+    for line in self.chunk_map() {
+      lines.push_center(line);
+    }
 
     lines.draw()
   }
 
+  /// Draws a top down map of the chunks around us. `@` is the chunk we are in,
+  /// `#` is a loaded chunk, and `.` is an unloaded chunk.
+  fn chunk_map(&self) -> Vec<String> {
+    let center = match self.pos {
+      Some(pos) => pos.chunk(),
+      None => return vec![],
+    };
+    (-MAP_RADIUS..=MAP_RADIUS)
+      .map(|z| {
+        (-MAP_RADIUS..=MAP_RADIUS)
+          .map(|x| {
+            let pos = ChunkPos::new(center.x() + x, center.z() + z);
+            if x == 0 && z == 0 {
+              '@'
+            } else if self.loaded_chunks.contains(&pos) {
+              '#'
+            } else {
+              '.'
+            }
+          })
+          .map(String::from)
+          .collect::<Vec<_>>()
+          .join(" ")
+      })
+      .collect()
+  }
+
+  /// Lists the closest entities to us.
+  fn entity_list(&self) -> Vec<String> {
+    let Some(pos) = self.pos else { return vec![] };
+    let mut entities: Vec<_> =
+      self.entities.iter().map(|(eid, e)| (eid, e, e.pos.dist(pos))).collect();
+    entities.sort_by(|a, b| a.2.total_cmp(&b.2));
+    entities
+      .into_iter()
+      .take(MAX_ENTITIES)
+      .map(|(eid, e, dist)| format!("{} #{eid}: {dist:.1}m", e.kind))
+      .collect()
+  }
+
   pub fn enable_drawing(status: Arc<Mutex<Status>>) {
     thread::spawn(move || {
       let tick = Duration::from_millis(50);
@@ -0,0 +1,136 @@
+//! The command tree sent by the server. This is used to tab complete commands.
+//!
+//! This is only sent to 1.13+ clients. On older versions, the tree will be
+//! empty, and only the cli commands are completed.
+
+use bb_common::util::Buffer;
+use bb_proxy::Result;
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeType {
+  Root,
+  Literal,
+  Argument,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+  ty:       NodeType,
+  name:     String,
+  children: Vec<usize>,
+  redirect: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandTree {
+  nodes: Vec<Node>,
+  root:  usize,
+}
+
+impl CommandTree {
+  /// Parses the contents of a command tree packet. This fails if the tree
+  /// uses an argument parser that we don't know about, as we can't tell how
+  /// long the properties for that parser are.
+  pub fn parse(data: &[u8]) -> Result<Self> {
+    let mut buf = Buffer::new(data);
+    let len = buf.read_varint()?;
+    let mut nodes = Vec::with_capacity(len.clamp(0, 1024) as usize);
+    for _ in 0..len {
+      let flags = buf.read_u8()?;
+      let ty = match flags & 0x03 {
+        0 => NodeType::Root,
+        1 => NodeType::Literal,
+        _ => NodeType::Argument,
+      };
+      let children = buf.read_list(|buf| Ok(buf.read_varint()? as usize))?;
+      let redirect = if flags & 0x08 != 0 { Some(buf.read_varint()? as usize) } else { None };
+      let name = if ty == NodeType::Root { String::new() } else { buf.read_str(32767)? };
+      if ty == NodeType::Argument {
+        let parser = buf.read_str(32767)?;
+        skip_properties(&mut buf, &parser)?;
+      }
+      if flags & 0x10 != 0 {
+        buf.read_str(32767)?;
+      }
+      nodes.push(Node { ty, name, children, redirect });
+    }
+    let root = buf.read_varint()? as usize;
+    Ok(CommandTree { nodes, root })
+  }
+
+  /// Returns every possible value for the last word of `words`. Arguments are
+  /// returned as `<name>`, and literals are returned as is. The last word is
+  /// the word being typed, so it is used as a prefix.
+  pub fn complete(&self, words: &[&str]) -> Vec<String> {
+    let Some((last, path)) = words.split_last() else { return vec![] };
+    let mut node = match self.nodes.get(self.root) {
+      Some(n) => n,
+      None => return vec![],
+    };
+    for word in path {
+      let next = self.children(node).find(|child| match child.ty {
+        NodeType::Literal => child.name == *word,
+        _ => true,
+      });
+      match next {
+        Some(n) => node = n,
+        None => return vec![],
+      }
+    }
+    self
+      .children(node)
+      .filter_map(|child| match child.ty {
+        NodeType::Literal if child.name.starts_with(last) => Some(child.name.clone()),
+        NodeType::Argument if last.is_empty() => Some(format!("<{}>", child.name)),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Returns the children of the given node, following redirects.
+  fn children<'a>(&'a self, node: &'a Node) -> impl Iterator<Item = &'a Node> + 'a {
+    let node = match node.redirect {
+      Some(r) => self.nodes.get(r).unwrap_or(node),
+      None => node,
+    };
+    node.children.iter().filter_map(|&i| self.nodes.get(i))
+  }
+}
+
+/// Skips the properties for the given parser. This is the 1.13-1.18 format,
+/// where parsers are sent as strings.
+fn skip_properties(buf: &mut Buffer<&[u8]>, parser: &str) -> Result<()> {
+  match parser {
+    "brigadier:double" | "brigadier:float" | "brigadier:integer" | "brigadier:long" => {
+      let size = match parser {
+        "brigadier:double" | "brigadier:long" => 8,
+        _ => 4,
+      };
+      let flags = buf.read_u8()?;
+      if flags & 0x01 != 0 {
+        buf.read_buf(size)?;
+      }
+      if flags & 0x02 != 0 {
+        buf.read_buf(size)?;
+      }
+    }
+    "brigadier:string" => {
+      buf.read_varint()?;
+    }
+    "minecraft:entity" | "minecraft:score_holder" | "minecraft:range" => {
+      buf.read_u8()?;
+    }
+    "minecraft:resource" | "minecraft:resource_or_tag" => {
+      buf.read_str(32767)?;
+    }
+    "brigadier:bool" => {}
+    p if p.starts_with("minecraft:") => {}
+    p => {
+      return Err(
+        io::Error::new(io::ErrorKind::InvalidData, format!("unknown command parser `{p}`")).into(),
+      )
+    }
+  }
+  Ok(())
+}
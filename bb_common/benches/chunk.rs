@@ -1,6 +1,6 @@
 use bb_common::{
   chunk::{fixed, paletted, Section},
-  math::Pos,
+  math::SectionRelPos,
 };
 use criterion::{criterion_group, criterion_main, Criterion};
 use pprof::criterion::{Output, PProfProfiler};
@@ -14,21 +14,21 @@ pub fn paletted(c: &mut Criterion) {
   // Fill:        ~9,000 ns      ~5,000 ns       ~300ns
   //
   c.bench_function("paletted fill auto", |b| {
-    let mut s = paletted::Section::new();
+    let mut s = paletted::Section::new(15);
     let mut i = 0_u8;
     b.iter(move || {
-      s.fill(Pos::new(0, 0, 0), Pos::new(15, 15, 15), i.into()).unwrap();
+      s.fill(SectionRelPos::new(0, 0, 0), SectionRelPos::new(15, 15, 15), i.into());
       i += 1;
     })
   });
   c.bench_function("paletted fill manual", |b| {
-    let mut s = paletted::Section::new();
+    let mut s = paletted::Section::new(15);
     let mut i = 0_u8;
     b.iter(move || {
       for y in 0..16 {
         for z in 0..16 {
           for x in 0..16 {
-            s.set_block(Pos::new(x, y, z), i.into()).unwrap();
+            s.set_block(SectionRelPos::new(x, y, z), i.into());
           }
         }
       }
@@ -45,21 +45,21 @@ pub fn fixed(c: &mut Criterion) {
   // Fill manual: ~200,000ns   ~76,000ns   ~7,500ns
   //
   c.bench_function("fixed fill auto", |b| {
-    let mut s = fixed::Section::new();
+    let mut s = fixed::Section::new(0);
     let mut i = 0_u8;
     b.iter(move || {
-      s.fill(Pos::new(0, 0, 0), Pos::new(15, 15, 15), i.into()).unwrap();
+      s.fill(SectionRelPos::new(0, 0, 0), SectionRelPos::new(15, 15, 15), i.into());
       i += 1;
     })
   });
   c.bench_function("fixed fill manual", |b| {
-    let mut s = fixed::Section::new();
+    let mut s = fixed::Section::new(0);
     let mut i = 0_u8;
     b.iter(move || {
       for y in 0..16 {
         for z in 0..16 {
           for x in 0..16 {
-            s.set_block(Pos::new(x, y, z), i.into()).unwrap();
+            s.set_block(SectionRelPos::new(x, y, z), i.into());
           }
         }
       }
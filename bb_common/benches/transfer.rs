@@ -0,0 +1,97 @@
+use bb_common::{
+  chunk::{paletted, LightChunk, Section},
+  math::{ChunkPos, SectionRelPos},
+  net::cb,
+  util::Buffer,
+};
+use bb_transfer::{MessageRead, MessageReader, MessageWrite, MessageWriter};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pprof::criterion::{Output, PProfProfiler};
+
+/// Returns a full height chunk, where every section has a few different
+/// blocks in it. This is about what a generated chunk looks like.
+fn chunk() -> cb::Packet {
+  let mut s = paletted::Section::new(15);
+  for y in 0..16 {
+    for z in 0..16 {
+      for x in 0..16 {
+        s.set_block(SectionRelPos::new(x, y, z), ((x + z) % 8).into());
+      }
+    }
+  }
+  cb::packet::Chunk {
+    pos:            ChunkPos::new(0, 0),
+    full:           true,
+    sections:       vec![Some(s); 16],
+    sky_light:      Some(LightChunk::new()),
+    block_light:    LightChunk::new(),
+    block_entities: vec![],
+  }
+  .into()
+}
+
+/// Returns a multi block change for an entire chunk section.
+fn multi_block_change() -> cb::Packet {
+  cb::packet::MultiBlockChange {
+    pos:     ChunkPos::new(0, 0),
+    y:       4,
+    changes: (0..4096).map(|i| (i % 8) << 12 | i).collect(),
+  }
+  .into()
+}
+
+pub fn varint(c: &mut Criterion) {
+  let values: Vec<i32> = (0..1024).map(|i| i * i * 997).collect();
+  c.bench_function("varint write", |b| {
+    let mut data = Vec::with_capacity(5 * values.len());
+    b.iter(|| {
+      data.clear();
+      let mut buf = Buffer::new(&mut data);
+      for &v in &values {
+        buf.write_varint(v);
+      }
+    });
+  });
+  c.bench_function("varint read", |b| {
+    let mut data = vec![];
+    let mut buf = Buffer::new(&mut data);
+    for &v in &values {
+      buf.write_varint(v);
+    }
+    b.iter(|| {
+      let mut buf = Buffer::new(data.as_slice());
+      for _ in 0..values.len() {
+        black_box(buf.read_varint().unwrap());
+      }
+    });
+  });
+}
+
+pub fn transfer(c: &mut Criterion) {
+  for (name, p) in [("chunk", chunk()), ("multi block change", multi_block_change())] {
+    let mut data = vec![0; 1024 * 1024];
+    c.bench_function(&format!("transfer write {name}"), |b| {
+      b.iter(|| {
+        let mut m = MessageWriter::new(data.as_mut_slice());
+        p.write(&mut m).unwrap();
+        black_box(m.index());
+      });
+    });
+    let mut m = MessageWriter::new(data.as_mut_slice());
+    p.write(&mut m).unwrap();
+    let len = m.index();
+    c.bench_function(&format!("transfer read {name}"), |b| {
+      b.iter(|| {
+        let mut m = MessageReader::new(&data[..len]);
+        black_box(cb::Packet::read(&mut m).unwrap());
+      });
+    });
+  }
+}
+
+criterion_group! {
+  name = benches;
+  config = Criterion::default().with_profiler(PProfProfiler::new(100, Output::Flamegraph(None)));
+  targets = varint, transfer
+}
+criterion_main!(benches);
@@ -0,0 +1,291 @@
+//! The ban list. This is shared between the proxy and the server, which both
+//! read the same `bans.json` file. The proxy checks it when a client logs in,
+//! and the server checks it again when a player joins, and is the only one
+//! that writes to it.
+
+use crate::util::{
+  chat::{Chat, Color},
+  JsonFile, UUID,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+  fmt, io,
+  net::IpAddr,
+  path::Path,
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Who a ban applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BanTarget {
+  /// A single player.
+  Uuid(UUID),
+  /// Everyone connecting from this IP.
+  Ip(IpAddr),
+}
+
+/// A single entry in the ban list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ban {
+  #[serde(flatten)]
+  pub target:  BanTarget,
+  /// The username of the banned player, if known. This is only used for
+  /// display, and is never checked against.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name:    Option<String>,
+  pub reason:  String,
+  /// Who created this ban. This is a username, or `Server` for the console.
+  pub source:  String,
+  /// When this ban was created, in seconds since the unix epoch.
+  pub created: u64,
+  /// When this ban expires, in seconds since the unix epoch. If `None`, this
+  /// ban never expires.
+  #[serde(default)]
+  pub expires: Option<u64>,
+}
+
+impl fmt::Display for BanTarget {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BanTarget::Uuid(id) => write!(f, "{}", id.as_dashed_str()),
+      BanTarget::Ip(ip) => write!(f, "{ip}"),
+    }
+  }
+}
+
+impl Ban {
+  /// Creates a ban, starting now. If `duration` is `None`, the ban will never
+  /// expire.
+  pub fn new(
+    target: BanTarget,
+    reason: String,
+    source: String,
+    duration: Option<Duration>,
+  ) -> Self {
+    let created = now();
+    Ban {
+      target,
+      name: None,
+      reason,
+      source,
+      created,
+      expires: duration.map(|d| created + d.as_secs()),
+    }
+  }
+  /// Sets the username shown for this ban.
+  pub fn with_name(mut self, name: impl Into<String>) -> Self {
+    self.name = Some(name.into());
+    self
+  }
+
+  /// Returns `true` if this ban has expired.
+  pub fn is_expired(&self) -> bool { self.expires.map(|t| t <= now()).unwrap_or(false) }
+  /// Returns how long until this ban expires. Returns `None` if this ban never
+  /// expires.
+  pub fn remaining(&self) -> Option<Duration> {
+    self.expires.map(|t| Duration::from_secs(t.saturating_sub(now())))
+  }
+
+  /// Returns the name of whoever is banned. This is their username if it is
+  /// known, and their UUID or IP otherwise.
+  pub fn display_name(&self) -> String {
+    match &self.name {
+      Some(name) => name.clone(),
+      None => self.target.to_string(),
+    }
+  }
+
+  /// Returns the message shown to a banned client when they are kicked, or
+  /// when they try to join.
+  pub fn kick_message(&self) -> Chat {
+    let mut msg = Chat::empty();
+    msg.add("You are banned from this server.\n").color(Color::Red);
+    if !self.reason.is_empty() {
+      msg.add("Reason: ").color(Color::Gray);
+      msg.add(format!("{}\n", self.reason));
+    }
+    match self.remaining() {
+      Some(time) => {
+        msg.add("Expires in: ").color(Color::Gray);
+        msg.add(fmt_duration(time));
+      }
+      None => {
+        msg.add("This ban is permanent.").color(Color::Gray);
+      }
+    }
+    msg
+  }
+}
+
+/// A list of bans, stored as json.
+#[derive(Debug)]
+pub struct BanList {
+  file: JsonFile<Vec<Ban>>,
+}
+
+impl BanList {
+  /// Creates an empty ban list, which will be saved to `path`.
+  pub fn new(path: impl AsRef<Path>) -> Self { BanList { file: JsonFile::new(path) } }
+
+  /// Loads the ban list at the given path. If the file doesn't exist, the list
+  /// will be empty. If the file is invalid, an error is logged, and the list
+  /// will be empty.
+  pub fn load(path: impl AsRef<Path>) -> Self { BanList { file: JsonFile::load(path) } }
+
+  /// Reads the file again, if it has changed since it was last read or
+  /// written. This allows bans written by another process (or edited by hand)
+  /// to take effect without a restart.
+  pub fn reload_if_changed(&mut self) { self.file.reload_if_changed(); }
+
+  /// Writes the ban list to disk. Expired bans are removed first.
+  pub fn save(&mut self) -> io::Result<()> {
+    self.file.retain(|b| !b.is_expired());
+    self.file.save()
+  }
+
+  /// Adds a ban. If there is already a ban for the same target, it will be
+  /// replaced. This does not save the list.
+  pub fn add(&mut self, ban: Ban) {
+    self.file.retain(|b| b.target != ban.target);
+    self.file.push(ban);
+  }
+  /// Removes the ban for the given target. This does not save the list.
+  /// Returns the ban that was removed, if there was one.
+  pub fn remove(&mut self, target: BanTarget) -> Option<Ban> {
+    let idx = self.file.iter().position(|b| b.target == target)?;
+    Some(self.file.remove(idx))
+  }
+
+  /// Returns the ban for the given target, if it hasn't expired.
+  pub fn get(&self, target: BanTarget) -> Option<&Ban> {
+    self.file.iter().find(|b| b.target == target && !b.is_expired())
+  }
+  /// Finds a ban that applies to a client with the given UUID and IP. Bans on
+  /// the UUID are checked first.
+  pub fn find(&self, id: UUID, ip: Option<IpAddr>) -> Option<&Ban> {
+    self.get(BanTarget::Uuid(id)).or_else(|| self.get(BanTarget::Ip(ip?)))
+  }
+  /// Finds a ban with the given username. This is used when pardoning a
+  /// player that isn't online.
+  pub fn find_name(&self, name: &str) -> Option<&Ban> {
+    self
+      .file
+      .iter()
+      .find(|b| b.name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(name)) && !b.is_expired())
+  }
+
+  /// Returns every ban that hasn't expired.
+  pub fn iter(&self) -> impl Iterator<Item = &Ban> { self.file.iter().filter(|b| !b.is_expired()) }
+}
+
+fn now() -> u64 { SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() }
+
+const UNITS: [(char, u64); 5] =
+  [('w', 7 * 24 * 60 * 60), ('d', 24 * 60 * 60), ('h', 60 * 60), ('m', 60), ('s', 1)];
+
+/// Parses a duration like `30m`, `12h`, or `1d12h`. The units are `s`, `m`,
+/// `h`, `d`, and `w`. Returns `None` if the string is invalid, or if the
+/// duration is zero.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+  let mut total = 0_u64;
+  let mut num = String::new();
+  for c in s.chars() {
+    if c.is_ascii_digit() {
+      num.push(c);
+    } else {
+      let (_, secs) = UNITS.iter().find(|(unit, _)| *unit == c.to_ascii_lowercase())?;
+      let n: u64 = num.parse().ok()?;
+      total = total.checked_add(n.checked_mul(*secs)?)?;
+      num.clear();
+    }
+  }
+  if !num.is_empty() || total == 0 {
+    return None;
+  }
+  Some(Duration::from_secs(total))
+}
+
+/// Formats a duration using the two largest units, like `1d 12h` or `5m 3s`.
+pub fn fmt_duration(time: Duration) -> String {
+  let mut secs = time.as_secs();
+  let mut parts = vec![];
+  for (unit, len) in UNITS {
+    if secs >= len {
+      parts.push(format!("{}{unit}", secs / len));
+      secs %= len;
+    }
+    if parts.len() == 2 {
+      break;
+    }
+  }
+  if parts.is_empty() {
+    "0s".into()
+  } else {
+    parts.join(" ")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn durations() {
+    assert_eq!(parse_duration("30m"), Some(Duration::from_secs(30 * 60)));
+    assert_eq!(parse_duration("1d12h"), Some(Duration::from_secs(36 * 60 * 60)));
+    assert_eq!(parse_duration("1W"), Some(Duration::from_secs(7 * 24 * 60 * 60)));
+    assert_eq!(parse_duration("12"), None);
+    assert_eq!(parse_duration("5x"), None);
+    assert_eq!(parse_duration("0s"), None);
+    assert_eq!(parse_duration(""), None);
+
+    assert_eq!(fmt_duration(Duration::from_secs(36 * 60 * 60 + 5)), "1d 12h");
+    assert_eq!(fmt_duration(Duration::from_secs(3 * 60 + 5)), "3m 5s");
+    assert_eq!(fmt_duration(Duration::from_secs(0)), "0s");
+  }
+
+  #[test]
+  fn find() {
+    let id = UUID::from_u128(0x1234);
+    let ip: IpAddr = "10.0.0.1".parse().unwrap();
+    let mut list = BanList::new("bans.json");
+    list.add(Ban::new(BanTarget::Ip(ip), "spam".into(), "Server".into(), None));
+    assert_eq!(list.find(id, None), None);
+    assert_eq!(list.find(id, Some(ip)).unwrap().reason, "spam");
+
+    list.add(Ban::new(BanTarget::Uuid(id), "griefing".into(), "Server".into(), None));
+    assert_eq!(list.find(id, Some(ip)).unwrap().reason, "griefing");
+    // Banning the same target again replaces the old ban.
+    list.add(Ban::new(BanTarget::Uuid(id), "hacking".into(), "Server".into(), None));
+    assert_eq!(list.iter().count(), 2);
+    assert_eq!(list.find(id, None).unwrap().reason, "hacking");
+
+    let mut expired = Ban::new(BanTarget::Uuid(id), "".into(), "Server".into(), None);
+    expired.expires = Some(expired.created - 1);
+    list.add(expired);
+    assert_eq!(list.find(id, None), None);
+
+    assert!(list.remove(BanTarget::Ip(ip)).is_some());
+    assert_eq!(list.find(id, Some(ip)), None);
+  }
+
+  #[test]
+  fn json() {
+    let ban = Ban::new(
+      BanTarget::Uuid(UUID::from_u128(0x1234)),
+      "griefing".into(),
+      "macmv".into(),
+      Some(Duration::from_secs(60)),
+    )
+    .with_name("griefer");
+    let src = serde_json::to_string(&ban).unwrap();
+    assert!(src.contains(r#""uuid":"00000000-0000-0000-0000-000000001234""#));
+    assert_eq!(serde_json::from_str::<Ban>(&src).unwrap(), ban);
+
+    let ban = Ban::new(BanTarget::Ip("::1".parse().unwrap()), "".into(), "Server".into(), None);
+    let src = serde_json::to_string(&ban).unwrap();
+    assert!(src.contains(r#""ip":"::1""#));
+    assert_eq!(serde_json::from_str::<Ban>(&src).unwrap(), ban);
+  }
+}
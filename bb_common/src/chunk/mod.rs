@@ -2,9 +2,11 @@ pub mod fixed;
 pub mod light;
 pub mod paletted;
 mod section;
+mod stage;
 
 pub use light::LightChunk;
 pub use section::Section;
+pub use stage::{Stage, UnknownStage};
 
 use crate::math::{PosError, RelPos};
 use std::cmp;
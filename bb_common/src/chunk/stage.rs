@@ -0,0 +1,60 @@
+use std::{error::Error, fmt, str::FromStr};
+
+/// A stage of chunk generation. Chunks are generated one stage at a time, in
+/// the order of [`Stage::ALL`], and each stage only ever modifies the chunk
+/// that the previous stages produced. This allows a generator to replace one
+/// stage (like ores in [`Features`](Stage::Features)) while keeping the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+  /// Fills in the shape of the terrain, usually with stone.
+  Shape,
+  /// Replaces the top of the terrain with biome specific blocks, like grass
+  /// and dirt.
+  Surface,
+  /// Carves out caves and ravines.
+  Carvers,
+  /// Places small features, like ores, trees, and flowers.
+  Features,
+  /// Places large structures, which may span multiple chunks.
+  Structures,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownStage(pub String);
+
+impl fmt::Display for UnknownStage {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "unknown generation stage: {}", self.0)
+  }
+}
+
+impl Error for UnknownStage {}
+
+impl Stage {
+  /// Every stage, in the order they are run.
+  pub const ALL: [Stage; 5] =
+    [Stage::Shape, Stage::Surface, Stage::Carvers, Stage::Features, Stage::Structures];
+
+  /// Returns the name of this stage, like `surface`.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Stage::Shape => "shape",
+      Stage::Surface => "surface",
+      Stage::Carvers => "carvers",
+      Stage::Features => "features",
+      Stage::Structures => "structures",
+    }
+  }
+}
+
+impl fmt::Display for Stage {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str(self.name()) }
+}
+
+impl FromStr for Stage {
+  type Err = UnknownStage;
+
+  fn from_str(s: &str) -> Result<Self, UnknownStage> {
+    Stage::ALL.into_iter().find(|stage| stage.name() == s).ok_or_else(|| UnknownStage(s.into()))
+  }
+}
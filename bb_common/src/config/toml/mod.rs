@@ -1,5 +1,5 @@
 use indexmap::IndexMap;
-use std::{fmt, str::FromStr};
+use std::{borrow::Cow, fmt, str::FromStr};
 
 #[cfg(test)]
 mod tests;
@@ -253,6 +253,17 @@ impl Token<'_> {
 
 fn is_valid_key(c: char) -> bool { c.is_ascii_alphabetic() || c == '-' || c == '_' }
 
+/// Quotes the given key if it can't be written as a bare key. For example, a
+/// hostname like `play.example.com` must be quoted, so that it isn't parsed as
+/// three separate keys.
+fn quote_key(key: &str) -> Cow<'_, str> {
+  if !key.is_empty() && key.chars().all(is_valid_key) {
+    Cow::Borrowed(key)
+  } else {
+    Cow::Owned(format!("\"{key}\""))
+  }
+}
+
 impl<'a> Tokenizer<'a> {
   pub fn new(s: &'a str) -> Self {
     Tokenizer { s, peeked: None, index: 0, line: 1, allow_newlines: false }
@@ -421,6 +432,7 @@ impl<'a> Tokenizer<'a> {
           }
           let key = match self.next()? {
             Token::Word(w) => w.to_string(),
+            Token::String(s) => s,
             _ => return Err(self.err(ParseErrorKind::MissingKey)),
           };
           self.expect(TokenKind::Eq)?;
@@ -452,22 +464,23 @@ impl<'a> Tokenizer<'a> {
       None => Err(self.err(ParseErrorKind::MissingValue)),
     }
   }
-  // Parses a path between braces, like `foo.bar.baz`.
+  // Parses a single segment of a path, which is either a word or a quoted
+  // string.
+  fn parse_key(&mut self) -> Result<String, ParseError> {
+    match self.next()? {
+      Token::Word(w) => Ok(w.into()),
+      Token::String(s) => Ok(s),
+      actual => Err(
+        self.err(ParseErrorKind::Expected(vec![TokenKind::Word, TokenKind::String], actual.kind())),
+      ),
+    }
+  }
+  // Parses a path between braces, like `foo.bar.baz` or `foo."bar.baz"`.
   fn parse_path(&mut self, end: TokenKind) -> Result<Vec<String>, ParseError> {
-    let first = match self.expect(TokenKind::Word)? {
-      Token::Word(w) => w,
-      _ => unreachable!(),
-    };
-    let mut segments = vec![first.into()];
+    let mut segments = vec![self.parse_key()?];
     loop {
       match self.next()? {
-        Token::Dot => {
-          let segment = match self.expect(TokenKind::Word)? {
-            Token::Word(w) => w,
-            _ => unreachable!(),
-          };
-          segments.push(segment.into());
-        }
+        Token::Dot => segments.push(self.parse_key()?),
         t if t.kind() == end => return Ok(segments),
         actual => {
           return Err(self.err(ParseErrorKind::Expected(vec![TokenKind::Dot, end], actual.kind())))
@@ -483,8 +496,8 @@ impl<'a> Tokenizer<'a> {
     let mut map = Map::new();
     loop {
       match self.next_opt()? {
-        Some(Token::Word(key)) => {
-          self.peeked = Some(Token::Word(key));
+        Some(key @ (Token::Word(_) | Token::String(_))) => {
+          self.peeked = Some(key);
           let inner_path = self.parse_path(TokenKind::Eq)?;
           let value = self.parse_value()?;
           let line = self.line;
@@ -529,7 +542,7 @@ impl<'a> Tokenizer<'a> {
         }
         Some(actual) => {
           return Err(self.err(ParseErrorKind::Expected(
-            vec![TokenKind::Word, TokenKind::OpenArr],
+            vec![TokenKind::Word, TokenKind::String, TokenKind::OpenArr],
             actual.kind(),
           )))
         }
@@ -566,7 +579,10 @@ impl fmt::Display for ValueInner {
       Self::Table(v) => write!(
         f,
         "{{ {} }}",
-        v.iter().map(|(k, v)| format!("{k} = {v}")).collect::<Vec<String>>().join(", ")
+        v.iter()
+          .map(|(k, v)| format!("{} = {v}", quote_key(k)))
+          .collect::<Vec<String>>()
+          .join(", ")
       ),
     }
   }
@@ -598,7 +614,7 @@ impl Writer {
       if i != 0 {
         write!(self.s, ".")?;
       }
-      write!(self.s, "{segment}")?;
+      write!(self.s, "{}", quote_key(segment))?;
     }
     writeln!(self, "]")
   }
@@ -645,13 +661,13 @@ impl ValueInner {
             // short table
             Self::Table(_) => {
               v.write_comments(w)?;
-              write!(w, "{k} = ")?;
+              write!(w, "{} = ", quote_key(k))?;
               std::fmt::write(w, format_args!("{}", v.value))?;
               writeln!(w)?;
             }
             _ => {
               v.write_comments(w)?;
-              write!(w, "{k} = ")?;
+              write!(w, "{} = ", quote_key(k))?;
               v.write_toml(w)?;
               writeln!(w)?;
             }
@@ -293,3 +293,46 @@ fn write_comments() {
     .join("\n"),
   );
 }
+
+#[test]
+fn quoted_keys() {
+  let value = Value::new(
+    0,
+    indexmap! {
+      "hosts".into() => Value::new(2, indexmap! {
+        "play.example.com".into() => Value::new(2, indexmap! {
+          "server".into() => Value::new(3, "lobby"),
+        }),
+      }),
+    },
+  );
+  assert_value(
+    r#"
+    [hosts."play.example.com"]
+    server = "lobby"
+    "#,
+    value.clone(),
+  );
+  assert_value(
+    r#"
+    "a.b" = 2
+    "#,
+    Value::new(0, indexmap! { "a.b".into() => Value::new(2, 2) }),
+  );
+
+  assert_eq!(
+    value.to_toml(),
+    r#"
+
+    [hosts]
+
+    [hosts."play.example.com"]
+    server = "lobby"
+    "#
+    .lines()
+    .skip(1)
+    .map(|line| line.trim())
+    .collect::<Vec<&str>>()
+    .join("\n"),
+  );
+}
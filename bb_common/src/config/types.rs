@@ -1,8 +1,8 @@
-use super::{ConfigError, Result, TomlValue, Value};
+use super::{ConfigError, Map, Result, TomlValue, Value};
 use crate::{math::FPos, util::GameMode};
 use indexmap::indexmap;
 use log::{Level, LevelFilter};
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 impl TomlValue for bool {
   fn from_toml(v: &Value) -> Result<Self> { ConfigError::from_option(v, v.as_bool()) }
@@ -99,6 +99,31 @@ where
   fn name() -> String { format!("array of {}", T::name()) }
 }
 
+impl<T> TomlValue for HashMap<String, T>
+where
+  T: TomlValue,
+{
+  fn from_toml(v: &Value) -> Result<Self> {
+    // Prepend the key, so that the error has the correct path.
+    match v.as_table() {
+      Some(table) => table
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), T::from_toml(v).map_err(|e| e.prepend(k.as_str()))?)))
+        .collect::<Result<HashMap<String, T>>>(),
+      None => Err(ConfigError::from_value::<Self>(v)),
+    }
+  }
+
+  fn to_toml(&self) -> Value {
+    // Sort the keys, so that the same map is always written the same way.
+    let mut keys: Vec<_> = self.keys().collect();
+    keys.sort();
+    Value::new(0, keys.into_iter().map(|k| (k.clone(), self[k].to_toml())).collect::<Map>())
+  }
+
+  fn name() -> String { format!("table of {}", T::name()) }
+}
+
 macro_rules! toml_number {
   ($name:expr, $($ty:ty),*) => {
     $(
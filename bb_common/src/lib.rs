@@ -8,8 +8,12 @@ extern crate log;
 
 // use flexi_logger::{Duplicate, LogTarget, Logger};
 
+#[cfg(feature = "host")]
+pub mod ban;
 pub mod chunk;
 pub mod config;
+#[cfg(feature = "host")]
+pub mod logger;
 pub mod math;
 pub mod metadata;
 pub mod nbt;
@@ -18,6 +22,8 @@ pub mod net;
 pub mod registry;
 pub mod util;
 pub mod version;
+#[cfg(feature = "host")]
+pub mod whitelist;
 
 pub use indexmap::indexmap;
 pub use registry::Registry;
@@ -94,6 +100,7 @@ pub fn init_with_level_writer<W: std::io::Write + Send + Sync + 'static>(
 ) {
   use log::{Level, Metadata, Record};
   use parking_lot::Mutex;
+  use std::io::Write;
 
   #[cfg(unix)]
   let isatty = unsafe { libc::isatty(libc::STDOUT_FILENO) } != 0;
@@ -107,12 +114,32 @@ pub fn init_with_level_writer<W: std::io::Write + Send + Sync + 'static>(
 
   impl<W: io::Write> Logger<W> {
     fn log_inner(&self, record: &Record) -> io::Result<()> {
-      #[cfg(not(feature = "utclogs"))]
-      let now = chrono::Local::now();
-      #[cfg(feature = "utclogs")]
-      let now = chrono::Utc::now();
+      let now = logger::Tz::now();
+      logger::record(record, &now);
+
+      if logger::is_json() {
+        let mut line = vec![];
+        logger::write_json(&mut line, record, &now)?;
+        logger::write_file(&line);
+        return self.writer.lock().write_all(&line);
+      }
+      // The log file never has colors.
+      let mut line = vec![];
+      Self::write_text(&mut line, record, &now, false)?;
+      logger::write_file(&line);
+      if self.color {
+        line.clear();
+        Self::write_text(&mut line, record, &now, true)?;
+      }
+      self.writer.lock().write_all(&line)
+    }
 
-      let mut w = self.writer.lock();
+    fn write_text(
+      w: &mut Vec<u8>,
+      record: &Record,
+      now: &logger::Time,
+      color: bool,
+    ) -> io::Result<()> {
       write!(w, "{} ", now.format("%Y-%m-%d %H:%M:%S%.3f"))?;
       #[cfg(debug_assertions)]
       {
@@ -124,7 +151,7 @@ pub fn init_with_level_writer<W: std::io::Write + Send + Sync + 'static>(
         }
         write!(w, " ")?;
       }
-      if self.color {
+      if color {
         match record.level() {
           Level::Trace => write!(w, "[\x1b[36mTRACE\x1b[0m]")?,
           Level::Debug => write!(w, "[\x1b[34mDEBUG\x1b[0m]")?,
@@ -0,0 +1,364 @@
+//! Extra outputs for the logger. By default, logs are only written to the
+//! writer passed to [`init_with_level_writer`](crate::init_with_level_writer).
+//! [`set_file`] adds a log file, which is rotated once it gets too large or too
+//! old. Rotated logs can be compressed with gzip, and only the newest few are
+//! kept.
+//!
+//! [`set_json`] switches every output to JSON lines, which are easier to
+//! ingest into tools like Loki or ELK.
+//!
+//! The most recent lines are kept in memory, and can be read with
+//! [`recent`]. Anything can also [`subscribe`] to new lines, which is how the
+//! server shows warnings to players in chat.
+
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use flate2::{write::GzEncoder, Compression};
+use log::{Level, LevelFilter, Record};
+use parking_lot::Mutex;
+use serde_derive::Serialize;
+use std::{
+  collections::{BTreeMap, VecDeque},
+  fs::{self, File, OpenOptions},
+  io::{self, Write},
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicBool, Ordering},
+  thread,
+  time::{Duration, Instant},
+};
+
+#[cfg(not(feature = "utclogs"))]
+pub(crate) type Tz = chrono::Local;
+#[cfg(feature = "utclogs")]
+pub(crate) type Tz = chrono::Utc;
+
+pub(crate) type Time = chrono::DateTime<Tz>;
+
+static FILE: Mutex<Option<RotatingFile>> = parking_lot::const_mutex(None);
+static JSON: AtomicBool = AtomicBool::new(false);
+static RECENT: Mutex<VecDeque<LogLine>> = parking_lot::const_mutex(VecDeque::new());
+static SUBSCRIBERS: Mutex<Vec<(LevelFilter, Sender<LogLine>)>> =
+  parking_lot::const_mutex(Vec::new());
+
+/// The number of lines kept for [`recent`].
+const RECENT_LINES: usize = 500;
+/// The number of lines that can be waiting for a subscriber. If a subscriber
+/// falls further behind than this, new lines are dropped.
+const SUBSCRIBER_BUFFER: usize = 256;
+
+/// A single log message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+  /// When this was logged, formatted like the console output.
+  pub time:   String,
+  pub level:  Level,
+  /// The module this was logged from.
+  pub target: String,
+  pub msg:    String,
+}
+
+/// Where and when logs are written to a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileConfig {
+  /// The log file. Old logs are moved into the same directory, and are named
+  /// by the date they were rotated, like `2022-06-14-1.log`.
+  pub path:     PathBuf,
+  /// Once the log file is this many bytes, it is rotated. If this is 0, the
+  /// file is never rotated because of its size.
+  pub max_size: u64,
+  /// Once the log file has been open for this long, it is rotated. If this
+  /// is `None`, the file is never rotated because of its age.
+  pub max_age:  Option<Duration>,
+  /// The number of old logs to keep. If this is 0, all old logs are kept.
+  pub keep:     usize,
+  /// If set, old logs will be compressed with gzip.
+  pub gzip:     bool,
+}
+
+impl FileConfig {
+  /// Creates a log file config, with sizes in kilobytes and times in hours,
+  /// which is how they are written in config files.
+  pub fn new(path: &str, max_size_kb: u64, max_age_hours: u64, keep: u32, gzip: bool) -> Self {
+    FileConfig {
+      path: path.into(),
+      max_size: max_size_kb * 1024,
+      max_age: if max_age_hours == 0 {
+        None
+      } else {
+        Some(Duration::from_secs(max_age_hours * 60 * 60))
+      },
+      keep: keep as usize,
+      gzip,
+    }
+  }
+}
+
+/// Starts writing logs to a file, in addition to the writer passed to
+/// [`init_with_level_writer`](crate::init_with_level_writer). If `config` is
+/// `None`, logs will no longer be written to a file.
+///
+/// If the log file already exists, it is rotated first, so that each run
+/// starts with a new log.
+pub fn set_file(config: Option<FileConfig>) -> io::Result<()> {
+  let file = config.map(RotatingFile::open).transpose()?;
+  *FILE.lock() = file;
+  Ok(())
+}
+
+/// If set, all logs will be written as JSON lines instead of plain text.
+pub fn set_json(json: bool) { JSON.store(json, Ordering::Relaxed); }
+pub(crate) fn is_json() -> bool { JSON.load(Ordering::Relaxed) }
+
+/// Returns up to the last `n` lines that were logged, oldest first. Only the
+/// last 500 lines are kept.
+pub fn recent(n: usize) -> Vec<LogLine> {
+  let lines = RECENT.lock();
+  lines.iter().skip(lines.len().saturating_sub(n)).cloned().collect()
+}
+
+/// Returns a channel which receives every new line at `level` or higher. For
+/// example, a level of `Warn` will receive warnings and errors. Dropping the
+/// receiver unsubscribes.
+///
+/// If the receiver isn't read for a while, lines will be dropped instead of
+/// buffered forever.
+pub fn subscribe(level: LevelFilter) -> Receiver<LogLine> {
+  let (tx, rx) = crossbeam_channel::bounded(SUBSCRIBER_BUFFER);
+  SUBSCRIBERS.lock().push((level, tx));
+  rx
+}
+
+/// Stores the record in the recent lines, and sends it to any subscribers.
+pub(crate) fn record(record: &Record, now: &Time) {
+  let line = LogLine {
+    time:   now.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+    level:  record.level(),
+    target: record.target().into(),
+    msg:    record.args().to_string(),
+  };
+  SUBSCRIBERS.lock().retain(|(level, tx)| {
+    if line.level > *level {
+      return true;
+    }
+    !matches!(tx.try_send(line.clone()), Err(TrySendError::Disconnected(_)))
+  });
+  let mut lines = RECENT.lock();
+  if lines.len() >= RECENT_LINES {
+    lines.pop_front();
+  }
+  lines.push_back(line);
+}
+
+/// Writes an already formatted log line to the log file, if there is one.
+pub(crate) fn write_file(line: &[u8]) {
+  if let Some(file) = FILE.lock().as_mut() {
+    let _ = file.write_all(line);
+  }
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+  time:   String,
+  level:  &'a str,
+  target: &'a str,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  module: Option<&'a str>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  line:   Option<u32>,
+  msg:    String,
+}
+
+/// Writes the given record as a single line of JSON.
+pub(crate) fn write_json(w: &mut impl Write, record: &Record, now: &Time) -> io::Result<()> {
+  let rec = JsonRecord {
+    time:   now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+    level:  record.level().as_str(),
+    target: record.target(),
+    module: record.module_path(),
+    line:   record.line(),
+    msg:    record.args().to_string(),
+  };
+  serde_json::to_writer(&mut *w, &rec)?;
+  writeln!(w)
+}
+
+/// A log file, which will be moved and replaced with a new file once it is
+/// too large or too old.
+struct RotatingFile {
+  config: FileConfig,
+  file:   File,
+  size:   u64,
+  opened: Instant,
+}
+
+impl RotatingFile {
+  fn open(config: FileConfig) -> io::Result<Self> {
+    if let Some(dir) = config.path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    if fs::metadata(&config.path).map(|m| m.len() > 0).unwrap_or(false) {
+      archive(&config)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+    Ok(RotatingFile { config, file, size: 0, opened: Instant::now() })
+  }
+
+  fn should_rotate(&self) -> bool {
+    (self.config.max_size != 0 && self.size >= self.config.max_size)
+      || self.config.max_age.map(|age| self.opened.elapsed() >= age).unwrap_or(false)
+  }
+
+  fn rotate(&mut self) -> io::Result<()> {
+    self.file.flush()?;
+    archive(&self.config)?;
+    self.file = OpenOptions::new().create(true).append(true).open(&self.config.path)?;
+    self.size = 0;
+    self.opened = Instant::now();
+    Ok(())
+  }
+}
+
+impl Write for RotatingFile {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    if self.should_rotate() {
+      self.rotate()?;
+    }
+    let n = self.file.write(buf)?;
+    self.size += n as u64;
+    Ok(n)
+  }
+  fn flush(&mut self) -> io::Result<()> { self.file.flush() }
+}
+
+/// Moves the log file out of the way. If enabled, the old log is compressed
+/// on another thread, so that logging isn't blocked while it compresses.
+fn archive(config: &FileConfig) -> io::Result<()> {
+  let dir = log_dir(&config.path);
+  let date = Tz::now().format("%Y-%m-%d").to_string();
+  let mut i = 1;
+  let dest = loop {
+    let name = format!("{date}-{i}.log");
+    if !dir.join(&name).exists() && !dir.join(format!("{name}.gz")).exists() {
+      break dir.join(name);
+    }
+    i += 1;
+  };
+  fs::rename(&config.path, &dest)?;
+
+  let dir = dir.to_path_buf();
+  let config = config.clone();
+  thread::spawn(move || {
+    if config.gzip {
+      if let Err(e) = compress(&dest) {
+        eprintln!("could not compress log {}: {e}", dest.display());
+      }
+    }
+    if let Err(e) = prune(&dir, config.keep) {
+      eprintln!("could not remove old logs: {e}");
+    }
+  });
+  Ok(())
+}
+
+/// Returns the directory the log file is in.
+fn log_dir(path: &Path) -> &Path {
+  match path.parent() {
+    Some(dir) if !dir.as_os_str().is_empty() => dir,
+    _ => Path::new("."),
+  }
+}
+
+/// Compresses the given file, and removes the original.
+fn compress(path: &Path) -> io::Result<()> {
+  let mut out_path = path.as_os_str().to_owned();
+  out_path.push(".gz");
+  let mut enc = GzEncoder::new(File::create(out_path)?, Compression::default());
+  io::copy(&mut File::open(path)?, &mut enc)?;
+  enc.finish()?;
+  fs::remove_file(path)
+}
+
+/// Removes the oldest logs in `dir`, so that only `keep` are left. If `keep`
+/// is 0, nothing is removed.
+fn prune(dir: &Path, keep: usize) -> io::Result<()> {
+  if keep == 0 {
+    return Ok(());
+  }
+  // A log that is being compressed will show up twice, so logs are grouped by
+  // name.
+  let mut logs = BTreeMap::<_, Vec<_>>::new();
+  for entry in fs::read_dir(dir)? {
+    let entry = entry?;
+    if let Some(key) = entry.file_name().to_str().and_then(archive_key) {
+      logs.entry(key).or_default().push(entry.path());
+    }
+  }
+  let remove = logs.len().saturating_sub(keep);
+  for path in logs.into_values().take(remove).flatten() {
+    fs::remove_file(path)?;
+  }
+  Ok(())
+}
+
+/// If `name` is the name of a rotated log, this returns the date and index,
+/// which sort from oldest to newest.
+fn archive_key(name: &str) -> Option<(String, u32)> {
+  let name = name.strip_suffix(".gz").unwrap_or(name);
+  let name = name.strip_suffix(".log")?;
+  let (date, index) = name.rsplit_once('-')?;
+  if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+    return None;
+  }
+  Some((date.into(), index.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn archive_names() {
+    assert_eq!(archive_key("2022-06-14-1.log"), Some(("2022-06-14".into(), 1)));
+    assert_eq!(archive_key("2022-06-14-12.log.gz"), Some(("2022-06-14".into(), 12)));
+    assert_eq!(archive_key("latest.log"), None);
+    assert_eq!(archive_key("2022-06-14.log"), None);
+    assert_eq!(archive_key("2022-06-14-1.txt"), None);
+    assert!(archive_key("2022-06-14-2.log") < archive_key("2022-06-14-10.log"));
+  }
+
+  #[test]
+  fn subscribers() {
+    let now = Tz::now();
+    let rx = subscribe(LevelFilter::Warn);
+    for level in [Level::Info, Level::Warn, Level::Error] {
+      record(&Record::builder().args(format_args!("{level}")).level(level).build(), &now);
+    }
+    let got: Vec<_> = rx.try_iter().map(|l| l.level).collect();
+    assert_eq!(got, vec![Level::Warn, Level::Error]);
+
+    let lines = recent(2);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].msg, "ERROR");
+  }
+
+  #[test]
+  fn json_line() {
+    let now = Tz::now();
+    let mut out = vec![];
+    write_json(
+      &mut out,
+      &Record::builder()
+        .args(format_args!("hello \"world\""))
+        .level(log::Level::Warn)
+        .target("bb_server")
+        .build(),
+      &now,
+    )
+    .unwrap();
+    let line = String::from_utf8(out).unwrap();
+    assert!(line.ends_with('\n'));
+    let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(value["level"], "WARN");
+    assert_eq!(value["target"], "bb_server");
+    assert_eq!(value["msg"], "hello \"world\"");
+  }
+}
@@ -1,5 +1,4 @@
-use super::Vec3;
-use bb_common::math::FPos;
+use super::FPos;
 
 #[derive(Debug, Clone, Copy)]
 pub struct AABB {
@@ -8,14 +7,14 @@ pub struct AABB {
   /// packets.
   pub pos: FPos,
   /// Never negative
-  size:    Vec3,
+  size:    FPos,
 }
 
 #[derive(Debug, Clone)]
 pub struct CollisionResult {
   /// A unit vector in one direction, representing which direction the collision
   /// was in. This can be negative, so there are 6 possible values here.
-  pub axis:   Vec3,
+  pub axis:   FPos,
   /// How much of the delta was completed. If this is 1, then we didn't collide
   /// with anything. If this was 0, then we didn't move at all.
   pub factor: f64,
@@ -24,15 +23,21 @@ pub struct CollisionResult {
 impl AABB {
   /// Creates a new axis-aligned bounding box. All the fields of `size` will be
   /// clamped to zero (negative sizes are now valid).
-  pub fn new(pos: FPos, size: Vec3) -> Self {
-    AABB { pos, size: Vec3::new(size.x.max(0.0), size.y.max(0.0), size.z.max(0.0)) }
+  pub fn new(pos: FPos, size: FPos) -> Self {
+    AABB { pos, size: FPos::new(size.x.max(0.0), size.y.max(0.0), size.z.max(0.0)) }
+  }
+  /// Creates a box that covers everything from `min` to `max`. The two
+  /// corners don't need to be in order.
+  pub fn from_min_max(min: FPos, max: FPos) -> Self {
+    let (min, max) = min.min_max(max);
+    AABB::new(FPos::new((min.x + max.x) / 2.0, min.y, (min.z + max.z) / 2.0), max - min)
   }
 
   /// Moves this box in the given direction, and make sure that it doesn't
   /// intersect with any of the given collision boxes.
   ///
   /// Returns true if this collided with anything.
-  pub fn move_towards(&mut self, delta: Vec3, nearby: &[AABB]) -> Option<CollisionResult> {
+  pub fn move_towards(&mut self, delta: FPos, nearby: &[AABB]) -> Option<CollisionResult> {
     let mut result = None;
     let start = *self;
     let end = AABB::new(self.pos + delta, self.size);
@@ -44,12 +49,12 @@ impl AABB {
       // This two vectors store 6 numbers, each of which is a collision percentage. If
       // any of these collision percentages are within 0..1, then we have a potential
       // collision at that percentage.
-      let t_min = Vec3::new(
+      let t_min = FPos::new(
         (wall.min_x() - start.max_x()) / delta.x,
         (wall.min_y() - start.max_y()) / delta.y,
         (wall.min_z() - start.max_z()) / delta.z,
       );
-      let t_max = Vec3::new(
+      let t_max = FPos::new(
         (wall.max_x() - start.min_x()) / delta.x,
         (wall.max_y() - start.min_y()) / delta.y,
         (wall.max_z() - start.min_z()) / delta.z,
@@ -78,12 +83,12 @@ impl AABB {
         };
       }
 
-      axis!(t_min, x: Vec3::new(1.0, 0.0, 0.0), (min_y, max_y): y, (min_z, max_z): z);
-      axis!(t_min, y: Vec3::new(0.0, 1.0, 0.0), (min_x, max_x): x, (min_z, max_z): z);
-      axis!(t_min, z: Vec3::new(0.0, 0.0, 1.0), (min_x, max_x): x, (min_y, max_y): y);
-      axis!(t_max, x: Vec3::new(1.0, 0.0, 0.0), (min_y, max_y): y, (min_z, max_z): z);
-      axis!(t_max, y: Vec3::new(0.0, 1.0, 0.0), (min_x, max_x): x, (min_z, max_z): z);
-      axis!(t_max, z: Vec3::new(0.0, 0.0, 1.0), (min_x, max_x): x, (min_y, max_y): y);
+      axis!(t_min, x: FPos::new(1.0, 0.0, 0.0), (min_y, max_y): y, (min_z, max_z): z);
+      axis!(t_min, y: FPos::new(0.0, 1.0, 0.0), (min_x, max_x): x, (min_z, max_z): z);
+      axis!(t_min, z: FPos::new(0.0, 0.0, 1.0), (min_x, max_x): x, (min_y, max_y): y);
+      axis!(t_max, x: FPos::new(1.0, 0.0, 0.0), (min_y, max_y): y, (min_z, max_z): z);
+      axis!(t_max, y: FPos::new(0.0, 1.0, 0.0), (min_x, max_x): x, (min_z, max_z): z);
+      axis!(t_max, z: FPos::new(0.0, 0.0, 1.0), (min_x, max_x): x, (min_y, max_y): y);
 
       if let Some(axis) = axis {
         result = Some(CollisionResult { axis, factor: time })
@@ -112,8 +117,8 @@ impl AABB {
   /// Returns the distance from the other AABB in all axis. If the bounding
   /// boxes collide, then the value on that axis will be some negative value.
   /// The value should be ignored if it is less than zero.
-  pub fn distance_from(&self, other: AABB) -> Vec3 {
-    Vec3::new(
+  pub fn distance_from(&self, other: AABB) -> FPos {
+    FPos::new(
       if self.pos.x() > other.pos.x() {
         other.max_x() - self.min_x()
       } else {
@@ -139,9 +144,67 @@ impl AABB {
   pub fn max_y(&self) -> f64 { self.pos.y() + self.size.y }
   pub fn max_z(&self) -> f64 { self.pos.z() + self.size.z / 2.0 }
 
+  /// Returns the minimum corner of this box.
+  pub fn min(&self) -> FPos { FPos::new(self.min_x(), self.min_y(), self.min_z()) }
+  /// Returns the maximum corner of this box.
+  pub fn max(&self) -> FPos { FPos::new(self.max_x(), self.max_y(), self.max_z()) }
+  /// Returns the width, height, and depth of this box.
+  pub fn size(&self) -> FPos { self.size }
+
   /// Returns the minimum position of this bounding box. Can be used to move the
   /// box around.
   pub fn pos_mut(&mut self) -> &mut FPos { &mut self.pos }
+
+  /// Returns true if any part of this box is within the box from `min` to
+  /// `max`. Unlike [`is_colliding_with`](Self::is_colliding_with), touching
+  /// the edge of the box counts.
+  pub fn intersects(&self, min: FPos, max: FPos) -> bool {
+    self.min_x() <= max.x()
+      && self.max_x() >= min.x()
+      && self.min_y() <= max.y()
+      && self.max_y() >= min.y()
+      && self.min_z() <= max.z()
+      && self.max_z() >= min.z()
+  }
+
+  /// Finds where the line from `from` to `to` first enters this box. Returns
+  /// how far along the line that is, from 0 to 1. If `from` is inside this box,
+  /// this returns 0.
+  pub fn raycast(&self, from: FPos, to: FPos) -> Option<f64> {
+    let delta = to - from;
+    let mut enter = 0.0_f64;
+    let mut exit = 1.0_f64;
+    for (start, d, min, max) in [
+      (from.x(), delta.x(), self.min_x(), self.max_x()),
+      (from.y(), delta.y(), self.min_y(), self.max_y()),
+      (from.z(), delta.z(), self.min_z(), self.max_z()),
+    ] {
+      if d == 0.0 {
+        if start < min || start > max {
+          return None;
+        }
+        continue;
+      }
+      let a = (min - start) / d;
+      let b = (max - start) / d;
+      enter = enter.max(a.min(b));
+      exit = exit.min(a.max(b));
+      if enter > exit {
+        return None;
+      }
+    }
+    Some(enter)
+  }
+
+  /// Finds when this box, moving by `delta`, would first touch `other`.
+  /// Returns how far along `delta` that is, from 0 to 1. If the boxes are
+  /// already touching, this returns 0.
+  pub fn sweep(&self, delta: FPos, other: AABB) -> Option<f64> {
+    // Growing `other` by the size of this box means that only the position of
+    // this box needs to be traced.
+    let grown = AABB::new(other.pos - FPos::new(0.0, self.size.y, 0.0), other.size + self.size);
+    grown.raycast(self.pos, self.pos + delta)
+  }
 }
 
 fn in_range(val: (f64, f64), range: (f64, f64)) -> bool {
@@ -190,24 +253,58 @@ mod tests {
     assert!(in_range((0.0, 5.0), (2.0, 3.0)));
   }
 
+  #[test]
+  fn intersects() {
+    let b = AABB::new(FPos::new(0.0, 0.0, 0.0), FPos::new(1.0, 2.0, 1.0));
+    assert!(b.intersects(FPos::new(-1.0, -1.0, -1.0), FPos::new(1.0, 1.0, 1.0)));
+    assert!(b.intersects(FPos::new(0.5, 2.0, 0.5), FPos::new(3.0, 3.0, 3.0)));
+    assert!(!b.intersects(FPos::new(0.6, 0.0, 0.0), FPos::new(3.0, 3.0, 3.0)));
+    assert!(!b.intersects(FPos::new(-3.0, -3.0, -3.0), FPos::new(0.0, -0.1, 0.0)));
+  }
+
+  #[test]
+  fn raycast() {
+    let b = AABB::new(FPos::new(0.0, 0.0, 0.0), FPos::new(1.0, 2.0, 1.0));
+    assert_eq!(b.raycast(FPos::new(-4.5, 1.0, 0.0), FPos::new(5.5, 1.0, 0.0)), Some(0.4));
+    assert_eq!(b.raycast(FPos::new(0.0, 1.0, 0.0), FPos::new(5.0, 1.0, 0.0)), Some(0.0));
+    assert_eq!(b.raycast(FPos::new(-4.5, 3.0, 0.0), FPos::new(5.5, 3.0, 0.0)), None);
+    assert_eq!(b.raycast(FPos::new(-4.5, 1.0, 0.0), FPos::new(-1.0, 1.0, 0.0)), None);
+  }
+
+  #[test]
+  fn sweep() {
+    let b = AABB::new(FPos::new(0.0, 0.0, 0.0), FPos::new(1.0, 1.0, 1.0));
+    let wall = AABB::new(FPos::new(5.0, 0.0, 0.0), FPos::new(1.0, 1.0, 1.0));
+    assert_eq!(b.sweep(FPos::new(10.0, 0.0, 0.0), wall), Some(0.4));
+    assert_eq!(b.sweep(FPos::new(-10.0, 0.0, 0.0), wall), None);
+    // Falling onto the top of the wall.
+    let above = AABB::new(FPos::new(5.0, 3.0, 0.0), FPos::new(1.0, 1.0, 1.0));
+    assert_eq!(above.sweep(FPos::new(0.0, -4.0, 0.0), wall), Some(0.5));
+
+    let b = AABB::from_min_max(FPos::new(2.0, 1.0, 2.0), FPos::new(0.0, 3.0, 1.0));
+    assert_eq!(b.pos, FPos::new(1.0, 1.0, 1.5));
+    assert_eq!(b.min(), FPos::new(0.0, 1.0, 1.0));
+    assert_eq!(b.max(), FPos::new(2.0, 3.0, 2.0));
+  }
+
   #[test]
   fn collisions() {
-    let mut b = AABB::new(FPos::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
+    let mut b = AABB::new(FPos::new(0.0, 0.0, 0.0), FPos::new(1.0, 1.0, 1.0));
     let walls = vec![
-      AABB::new(FPos::new(5.0, 0.0, 0.0), Vec3::new(1.0, 10.0, 10.0)),
-      AABB::new(FPos::new(0.0, 0.0, 10.0), Vec3::new(10.0, 10.0, 1.0)),
+      AABB::new(FPos::new(5.0, 0.0, 0.0), FPos::new(1.0, 10.0, 10.0)),
+      AABB::new(FPos::new(0.0, 0.0, 10.0), FPos::new(10.0, 10.0, 1.0)),
     ];
 
-    assert!(b.move_towards(Vec3::new(1.0, 0.0, 0.0), &walls).is_none());
+    assert!(b.move_towards(FPos::new(1.0, 0.0, 0.0), &walls).is_none());
     assert_eq!(b.pos, FPos::new(1.0, 0.0, 0.0));
 
-    assert!(b.move_towards(Vec3::new(1.0, 0.0, 0.0), &walls).is_none());
+    assert!(b.move_towards(FPos::new(1.0, 0.0, 0.0), &walls).is_none());
     assert_eq!(b.pos, FPos::new(2.0, 0.0, 0.0));
 
-    assert!(b.move_towards(Vec3::new(1.0, 0.0, 0.0), &walls).is_none());
+    assert!(b.move_towards(FPos::new(1.0, 0.0, 0.0), &walls).is_none());
     assert_eq!(b.pos, FPos::new(3.0, 0.0, 0.0));
 
-    let res = b.move_towards(Vec3::new(2.0, 0.0, 2.0), &walls).unwrap();
+    let res = b.move_towards(FPos::new(2.0, 0.0, 2.0), &walls).unwrap();
     assert_eq!(res.factor, 0.5);
     assert_eq!(b.pos, FPos::new(4.0, 0.0, 1.0));
   }
@@ -1,6 +1,6 @@
 use bb_macros::Transfer;
 use std::{
-  fmt,
+  fmt, mem,
   ops::{Add, AddAssign, Sub, SubAssign},
 };
 
@@ -75,6 +75,93 @@ impl ChunkPos {
   /// coordinate in the position will always be 0.
   #[inline(always)]
   pub fn columns(&self) -> PosIter { self.block().to(self.block() + Pos::new(15, 0, 15)) }
+
+  /// Creates an iterator from self to end. Both self and end are inclusive.
+  /// See [`ChunkIter::new`] for more.
+  #[inline(always)]
+  pub fn to(&self, end: ChunkPos) -> ChunkIter { ChunkIter::new(*self, end) }
+
+  /// Creates an iterator over every chunk within `radius` chunks of self,
+  /// including self. This is a square, so `radius` 1 will return 9 chunks.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// # use bb_common::math::ChunkPos;
+  /// let chunks: Vec<_> = ChunkPos::new(3, 4).around(1).collect();
+  /// assert_eq!(chunks.len(), 9);
+  /// assert_eq!(chunks[0], ChunkPos::new(2, 3));
+  /// assert_eq!(chunks[8], ChunkPos::new(4, 5));
+  /// ```
+  #[inline(always)]
+  pub fn around(&self, radius: u32) -> ChunkIter {
+    let r = radius as i32;
+    ChunkIter::new(self.add_x(-r).add_z(-r), self.add_x(r).add_z(r))
+  }
+}
+
+/// An iterator over a rectangle of chunks. This is created with
+/// [`ChunkPos::to`] or [`ChunkPos::around`].
+#[derive(Debug, Clone)]
+pub struct ChunkIter {
+  curr:  ChunkPos,
+  start: ChunkPos,
+  end:   ChunkPos,
+}
+
+impl ChunkIter {
+  /// Creates a new inclusive iterator. Like [`PosIter`], this will swap around
+  /// the values in start and end so that it will always iterate from least to
+  /// most on x, then z.
+  #[inline(always)]
+  pub fn new(mut start: ChunkPos, mut end: ChunkPos) -> Self {
+    if start.x > end.x {
+      mem::swap(&mut start.x, &mut end.x);
+    }
+    if start.z > end.z {
+      mem::swap(&mut start.z, &mut end.z);
+    }
+    ChunkIter { curr: start, start, end }
+  }
+
+  /// Returns true if the given chunk is within the iterator. Like the
+  /// iterator, this is inclusive for the minimum and maximum.
+  pub fn contains(&self, pos: ChunkPos) -> bool {
+    pos.x >= self.start.x && pos.z >= self.start.z && pos.x <= self.end.x && pos.z <= self.end.z
+  }
+}
+
+impl Iterator for ChunkIter {
+  type Item = ChunkPos;
+
+  fn next(&mut self) -> Option<ChunkPos> {
+    if self.curr.z > self.end.z {
+      return None;
+    }
+    let ret = self.curr;
+    self.curr.x += 1;
+    if self.curr.x > self.end.x {
+      self.curr.x = self.start.x;
+      self.curr.z += 1;
+    }
+    Some(ret)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let len = self.len();
+    (len, Some(len))
+  }
+}
+
+impl ExactSizeIterator for ChunkIter {
+  fn len(&self) -> usize {
+    if self.curr.z > self.end.z {
+      return 0;
+    }
+    let width = (self.end.x - self.start.x + 1) as usize;
+    let rows = (self.end.z - self.curr.z) as usize;
+    rows * width + (self.end.x - self.curr.x + 1) as usize
+  }
 }
 
 impl Add for ChunkPos {
@@ -132,4 +219,21 @@ mod tests {
     }
     assert_eq!(total, 256);
   }
+
+  #[test]
+  fn chunk_iter() {
+    let mut iter = ChunkPos::new(1, 1).to(ChunkPos::new(-1, 0));
+    assert_eq!(iter.len(), 6);
+    assert!(iter.contains(ChunkPos::new(0, 1)));
+    assert!(!iter.contains(ChunkPos::new(0, 2)));
+    assert_eq!(iter.next(), Some(ChunkPos::new(-1, 0)));
+    assert_eq!(iter.next(), Some(ChunkPos::new(0, 0)));
+    assert_eq!(iter.len(), 4);
+    assert_eq!(
+      iter.collect::<Vec<_>>(),
+      vec![ChunkPos::new(1, 0), ChunkPos::new(-1, 1), ChunkPos::new(0, 1), ChunkPos::new(1, 1)]
+    );
+    assert_eq!(ChunkPos::new(5, 5).around(0).collect::<Vec<_>>(), vec![ChunkPos::new(5, 5)]);
+    assert_eq!(ChunkPos::new(0, 0).around(10).count(), 21 * 21);
+  }
 }
@@ -1,4 +1,4 @@
-use super::{ChunkPos, Pos};
+use super::{ChunkPos, Pos, Quat};
 use bb_macros::Transfer;
 use std::{
   error::Error,
@@ -184,6 +184,75 @@ impl FPos {
       (self.x * other.y) - (self.y * other.x),
     )
   }
+
+  /// Returns the dot product of `self` and `other`.
+  pub fn dot(self, other: FPos) -> f64 { self.x * other.x + self.y * other.y + self.z * other.z }
+
+  /// Returns this vector scaled to a length of 1. If this vector is zero, this
+  /// returns zero.
+  pub fn normalize(self) -> FPos {
+    let len = self.size();
+    if len == 0.0 {
+      self
+    } else {
+      self / len
+    }
+  }
+
+  /// Returns a unit vector that is perpendicular to this one. There are an
+  /// infinite number of these, so this just returns one of them. This is
+  /// useful with [`rotate_around`](Self::rotate_around), to get a circle of
+  /// points around a line.
+  pub fn perpendicular(self) -> FPos {
+    // Cross with whichever axis is furthest from this vector, so that the
+    // result is never zero.
+    let axis = if self.x.abs() < self.y.abs() && self.x.abs() < self.z.abs() {
+      FPos::new(1.0, 0.0, 0.0)
+    } else if self.y.abs() < self.z.abs() {
+      FPos::new(0.0, 1.0, 0.0)
+    } else {
+      FPos::new(0.0, 0.0, 1.0)
+    };
+    self.cross(axis).normalize()
+  }
+
+  /// Rotates this vector by `angle` radians around `axis`. See
+  /// [`Quat::from_axis_angle`].
+  pub fn rotate_around(self, axis: FPos, angle: f64) -> FPos {
+    Quat::from_axis_angle(axis, angle).rotate(self)
+  }
+
+  /// Returns a unit vector pointing in the direction of the given yaw and
+  /// pitch, in degrees. This uses the same angles as a player's look
+  /// direction, so a yaw of 0 is +Z, and a pitch of 90 is straight down.
+  ///
+  /// ```
+  /// # use bb_common::math::FPos;
+  /// let v = FPos::from_yaw_pitch(90.0, 0.0);
+  /// assert!(v.dist(FPos::new(-1.0, 0.0, 0.0)) < 1e-6);
+  /// ```
+  pub fn from_yaw_pitch(yaw: f32, pitch: f32) -> FPos {
+    let yaw = (yaw as f64).to_radians();
+    let pitch = (pitch as f64).to_radians();
+    let m = pitch.cos();
+    // The coordinate system of minecraft means that we need to do this hell to get
+    // the axis to line up correctly.
+    FPos::new(-yaw.sin() * m, -pitch.sin(), yaw.cos() * m)
+  }
+
+  /// Returns the yaw and pitch, in degrees, of this vector. This is the
+  /// opposite of [`from_yaw_pitch`](Self::from_yaw_pitch), and is what you
+  /// would use to make an entity look in this direction. The yaw is in the
+  /// range `-180..=180`, and the pitch is in the range `-90..=90`.
+  pub fn yaw_pitch(self) -> (f32, f32) {
+    let len = self.size();
+    if len == 0.0 {
+      return (0.0, 0.0);
+    }
+    let yaw = (-self.x).atan2(self.z).to_degrees();
+    let pitch = -(self.y / len).clamp(-1.0, 1.0).asin().to_degrees();
+    (yaw as f32, pitch as f32)
+  }
 }
 
 impl Add for FPos {
@@ -1,3 +1,4 @@
+mod aabb;
 mod chunk_pos;
 #[cfg(feature = "host")]
 pub mod der;
@@ -7,20 +8,23 @@ mod pos;
 mod rel_pos;
 #[cfg(feature = "host")]
 mod rng;
+mod rotation;
 
+pub use aabb::{CollisionResult, AABB};
 pub use fast::{FastMath, EPSILON};
 pub use fpos::{FPos, FPosError};
 #[cfg(feature = "host")]
 pub use rng::WyhashRng;
+pub use rotation::Quat;
 
 #[cfg(feature = "host")]
 pub use rand_core::RngCore;
 
 use sha1::{Digest, Sha1};
-use std::hash::BuildHasher;
+use std::hash::{BuildHasher, Hasher};
 use wyhash::WyHash;
 
-pub use chunk_pos::ChunkPos;
+pub use chunk_pos::{ChunkIter, ChunkPos};
 pub use pos::{Pos, PosError, PosIter};
 pub use rel_pos::{RelPos, SectionRelPos};
 // pub use bb_generated::{ChunkPos, Pos, PosError, PosIter};
@@ -53,6 +57,25 @@ pub fn hexdigest(hash: Sha1) -> String {
   }
 }
 
+/// Derives a new seed from a world seed and a name. This is how each part of
+/// the server gets its own random numbers from the world seed, and plugins
+/// should use this to seed their own generators, so that two plugins using
+/// the same world seed don't end up with the same numbers.
+///
+/// # Example
+///
+/// ```
+/// # use bb_common::math::derive_seed;
+/// assert_eq!(derive_seed(1234, "trees"), derive_seed(1234, "trees"));
+/// assert_ne!(derive_seed(1234, "trees"), derive_seed(1234, "ores"));
+/// assert_ne!(derive_seed(1234, "trees"), derive_seed(1235, "trees"));
+/// ```
+pub fn derive_seed(seed: u64, name: &str) -> u64 {
+  let mut hasher = WyHash::with_seed(seed);
+  hasher.write(name.as_bytes());
+  hasher.finish()
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -0,0 +1,142 @@
+use super::FPos;
+use std::ops::Mul;
+
+/// A rotation in 3D space, stored as a unit quaternion. Rotations can be
+/// combined with `*`, and applied to a vector with [`rotate`](Self::rotate)
+/// (or `*` as well).
+///
+/// # Example
+///
+/// ```
+/// # use bb_common::math::{FPos, Quat};
+/// use std::f64::consts::FRAC_PI_2;
+///
+/// // A quarter turn around the Y axis.
+/// let q = Quat::from_axis_angle(FPos::new(0.0, 1.0, 0.0), FRAC_PI_2);
+/// let v = q.rotate(FPos::new(1.0, 0.0, 0.0));
+/// assert!(v.dist(FPos::new(0.0, 0.0, -1.0)) < 1e-9);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quat {
+  pub w: f64,
+  pub x: f64,
+  pub y: f64,
+  pub z: f64,
+}
+
+impl Default for Quat {
+  fn default() -> Quat { Quat::IDENTITY }
+}
+
+impl Quat {
+  /// A rotation that doesn't do anything.
+  pub const IDENTITY: Quat = Quat { w: 1.0, x: 0.0, y: 0.0, z: 0.0 };
+
+  /// Creates a quaternion from its parts. This won't be a valid rotation
+  /// unless it is a unit quaternion, so you probably want
+  /// [`from_axis_angle`](Self::from_axis_angle) instead.
+  pub const fn new(w: f64, x: f64, y: f64, z: f64) -> Self { Quat { w, x, y, z } }
+
+  /// Creates a rotation of `angle` radians around `axis`. Looking down the
+  /// axis towards the origin, the rotation is counterclockwise. The axis
+  /// doesn't need to be normalized. If the axis is zero, this returns
+  /// [`IDENTITY`](Self::IDENTITY).
+  pub fn from_axis_angle(axis: FPos, angle: f64) -> Self {
+    let len = axis.size();
+    if len == 0.0 {
+      return Quat::IDENTITY;
+    }
+    let axis = axis / len;
+    let (sin, cos) = (angle / 2.0).sin_cos();
+    Quat { w: cos, x: axis.x * sin, y: axis.y * sin, z: axis.z * sin }
+  }
+
+  /// Returns the axis and angle (in radians) of this rotation. The angle is
+  /// always in the range `0..=2π`. For the identity rotation, the axis is
+  /// straight up.
+  pub fn to_axis_angle(&self) -> (FPos, f64) {
+    let angle = 2.0 * self.w.clamp(-1.0, 1.0).acos();
+    let sin = (1.0 - self.w * self.w).max(0.0).sqrt();
+    if sin < 1e-9 {
+      (FPos::new(0.0, 1.0, 0.0), angle)
+    } else {
+      (FPos::new(self.x / sin, self.y / sin, self.z / sin), angle)
+    }
+  }
+
+  /// Returns the rotation that undoes this one.
+  pub fn inverse(&self) -> Self { Quat { w: self.w, x: -self.x, y: -self.y, z: -self.z } }
+
+  /// Scales this quaternion to a length of 1. Combining a lot of rotations
+  /// will slowly add floating point error, which this removes.
+  pub fn normalize(&self) -> Self {
+    let len = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+    if len == 0.0 {
+      return Quat::IDENTITY;
+    }
+    Quat { w: self.w / len, x: self.x / len, y: self.y / len, z: self.z / len }
+  }
+
+  /// Rotates the given vector.
+  pub fn rotate(&self, v: FPos) -> FPos {
+    // This is the same as `self * v * self.inverse()`, but expanded out.
+    let u = FPos::new(self.x, self.y, self.z);
+    let t = u.cross(v) * 2.0;
+    v + t * self.w + u.cross(t)
+  }
+}
+
+/// Combines two rotations. The result rotates by `other` first, and then by
+/// `self`.
+impl Mul for Quat {
+  type Output = Quat;
+  fn mul(self, other: Quat) -> Quat {
+    Quat {
+      w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+      x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+      y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+      z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+    }
+  }
+}
+
+impl Mul<FPos> for Quat {
+  type Output = FPos;
+  fn mul(self, v: FPos) -> FPos { self.rotate(v) }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f64::consts::{FRAC_PI_2, PI};
+
+  fn close(a: FPos, b: FPos) -> bool { a.dist(b) < 1e-9 }
+
+  #[test]
+  fn rotate() {
+    let x = FPos::new(1.0, 0.0, 0.0);
+    let y = FPos::new(0.0, 1.0, 0.0);
+    let z = FPos::new(0.0, 0.0, 1.0);
+    assert!(close(Quat::from_axis_angle(z, FRAC_PI_2).rotate(x), y));
+    assert!(close(Quat::from_axis_angle(x, FRAC_PI_2) * y, z));
+    assert!(close(Quat::from_axis_angle(y * 5.0, PI) * x, x * -1.0));
+    assert_eq!(Quat::from_axis_angle(FPos::default(), PI), Quat::IDENTITY);
+
+    // Rotating by `b` and then `a` is the same as rotating by `a * b`.
+    let a = Quat::from_axis_angle(x, 0.3);
+    let b = Quat::from_axis_angle(FPos::new(1.0, 2.0, 3.0), 1.2);
+    let v = FPos::new(4.0, -1.0, 2.0);
+    assert!(close((a * b).rotate(v), a.rotate(b.rotate(v))));
+    assert!(close(b.inverse().rotate(b.rotate(v)), v));
+    assert!(close((a * a.inverse()).rotate(v), v));
+  }
+
+  #[test]
+  fn axis_angle() {
+    let (axis, angle) = Quat::from_axis_angle(FPos::new(0.0, 0.0, 2.0), 1.0).to_axis_angle();
+    assert!(close(axis, FPos::new(0.0, 0.0, 1.0)));
+    assert!((angle - 1.0).abs() < 1e-9);
+    assert_eq!(Quat::IDENTITY.to_axis_angle(), (FPos::new(0.0, 1.0, 0.0), 0.0));
+    assert_eq!(Quat::new(2.0, 0.0, 0.0, 0.0).normalize(), Quat::IDENTITY);
+  }
+}
@@ -109,6 +109,9 @@ impl Metadata {
   }
   pub fn set_item(&mut self, idx: u8, value: Item) { self.fields.insert(idx, Field::Item(value)); }
   pub fn set_pose(&mut self, idx: u8, pose: Pose) { self.fields.insert(idx, Field::Pose(pose)); }
+  pub fn set_rotation(&mut self, idx: u8, x: f32, y: f32, z: f32) {
+    self.fields.insert(idx, Field::Rotation(x, y, z));
+  }
 
   pub fn get_item(&self, idx: u8) -> Item {
     self
@@ -240,6 +240,7 @@ impl Tag {
   getter!(byte_arr -> ByteArr(&[u8]));
   getter!(list -> List(&Vec<Tag>));
   getter!(compound -> Compound(&Compound));
+  getter!(int_arr -> IntArray(&Vec<i32>));
   getter!(long_arr -> LongArray(&Vec<i64>));
 
   pub fn compound_mut(&mut self) -> Result<&mut Compound, WrongTag> {
@@ -278,4 +279,15 @@ mod tests {
       NBT::new("hello world", Tag::new_compound(&[("name", Tag::String("Bananrama".into()))]))
     );
   }
+
+  #[test]
+  fn file_roundtrip() {
+    let nbt = NBT::new(
+      "Schematic",
+      Tag::new_compound(&[("Width", Tag::Short(3)), ("Offset", Tag::IntArray(vec![1, -2, 3]))]),
+    );
+    let file = nbt.serialize_file();
+    assert_eq!(&file[..2], &[0x1f, 0x8b]);
+    assert_eq!(NBT::deserialize_file(file).unwrap(), nbt);
+  }
 }
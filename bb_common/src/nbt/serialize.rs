@@ -17,6 +17,19 @@ impl NBT {
     self.serialize_buf(&mut out);
     out.into_inner()
   }
+  /// Serializes this tag, and gzips the result. This is the format used for
+  /// nbt files, like schematics. [`deserialize_file`](Self::deserialize_file)
+  /// can read the output of this function.
+  #[cfg(feature = "host")]
+  pub fn serialize_file(&self) -> Vec<u8> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let mut e = GzEncoder::new(vec![], Compression::default());
+    // Writing to a `Vec` cannot fail.
+    e.write_all(&self.serialize()).unwrap();
+    e.finish().unwrap()
+  }
 }
 
 impl Tag {
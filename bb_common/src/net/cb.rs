@@ -2,7 +2,8 @@ use crate::{
   chunk::{paletted::Section, LightChunk},
   math::{ChunkPos, FPos, Pos},
   metadata::Metadata,
-  util::{chat::Color, Chat, GameMode, Hand, Item, SwitchMode, UUID},
+  net::protocol::Capabilities,
+  util::{chat::Color, fmt_bytes, Chat, GameMode, Hand, Item, ProfileProperty, SwitchMode, UUID},
 };
 use bb_macros::Transfer;
 use std::{collections::HashMap, net::SocketAddr};
@@ -83,14 +84,17 @@ packets! {
   ChatMessage { msg: Chat, ty: u8 },
   #[id = 3]
   Chunk {
-    pos:         ChunkPos,
-    full:        bool,
+    pos:            ChunkPos,
+    full:           bool,
     /// In order to comply with modern clients, this array must be the full
     /// length of the world height. If it is too short, a modern client will
     /// disconnect with an error.
-    sections:    Vec<Option<Section>>,
-    sky_light:   Option<LightChunk>,
-    block_light: LightChunk,
+    sections:       Vec<Option<Section>>,
+    sky_light:      Option<LightChunk>,
+    block_light:    LightChunk,
+    /// Any block entities that the client needs to render this chunk, such as
+    /// the entity inside a mob spawner.
+    block_entities: Vec<BlockEntity>,
   },
   #[id = 4]
   CommandList {
@@ -140,6 +144,10 @@ packets! {
   EntityVelocity { eid: i32, x: i16, y: i16, z: i16 },
   #[id = 29]
   EntityMetadata { eid: i32, ty: u32, meta: Metadata },
+  /// Sets the attributes of an entity, such as max health or movement speed.
+  /// Any attributes not in this list are left unchanged on the client.
+  #[id = 46]
+  EntityProperties { eid: i32, properties: Vec<EntityProperty> },
   #[id = 10]
   JoinGame {
     eid:                   i32,
@@ -176,6 +184,10 @@ packets! {
   },
   #[id = 12]
   KeepAlive { id: u32 },
+  /// A special packet. The proxy will disconnect the client with the given
+  /// message, and will not move them into limbo.
+  #[id = 47]
+  Kick { reason: Chat },
   #[id = 39]
   Particle {
     id:         i32,
@@ -207,11 +219,15 @@ packets! {
   RemoveEntities { eids: Vec<i32> },
   #[id = 44]
   Respawn {
-    game_mode:  GameMode,
-    dimension:  i8,
-    level_type: String,
-    difficulty: u8,
-    reset_meta: bool,
+    game_mode:    GameMode,
+    dimension:    i8,
+    level_type:   String,
+    difficulty:   u8,
+    reset_meta:   bool,
+    /// Only applies to 1.18+ clients. Ignored for older clients.
+    world_height: u32,
+    /// Only applies to 1.18+ clients. Ignored for older clients.
+    world_min_y:  i32,
   },
   #[id = 25]
   ScoreboardDisplay {
@@ -232,6 +248,41 @@ packets! {
     #[must_exist]
     action:    ScoreboardAction,
   },
+  /// A special packet. The server sends this instead of joining the player if
+  /// the server is full. If the proxy has a queue, it will hold the player in
+  /// limbo until there is space. Otherwise, the player is disconnected with
+  /// the given reason.
+  #[id = 50]
+  ServerFull { reason: Chat },
+  /// A special packet. The server sends this to every player every few
+  /// seconds, so that the proxy can show the real player count on the server
+  /// list. `sample` is a few of the players that are online, and `usage` is
+  /// the resources the server is using, which the proxy can show on the
+  /// server list.
+  #[id = 49]
+  ServerStatus { online: u32, max: u32, sample: Vec<StatusPlayer>, usage: Option<ServerUsage> },
+  /// A special packet. This is the reply to a
+  /// [`Heartbeat`](crate::net::sb::Packet::Heartbeat) from the proxy, with
+  /// the same id.
+  #[id = 51]
+  Heartbeat { id: u32 },
+  /// A special packet. This is the first packet the server sends, and is the
+  /// reply to the [`ProtocolInfo`](crate::net::protocol::ProtocolInfo) in
+  /// the proxy's `JoinInfo`. It contains the version and features the server
+  /// picked. Servers from before versions were negotiated don't send this.
+  #[id = 54]
+  Protocol { version: u32, capabilities: Capabilities },
+  /// Shows a cooldown over every stack of the given item. `item` is the latest
+  /// item id. Setting `ticks` to 0 removes the cooldown. This is ignored by
+  /// 1.8 clients.
+  #[id = 48]
+  SetCooldown { item: i32, ticks: u32 },
+  /// Sets the entities riding `eid`. The first passenger is the one that
+  /// controls the vehicle. Entities in `removed` have just stopped riding
+  /// `eid`. This is only needed for 1.8 clients, which attach each passenger
+  /// separately.
+  #[id = 53]
+  SetPassengers { eid: i32, passengers: Vec<i32>, removed: Vec<i32> },
   #[id = 16]
   SetPosLook {
     pos:             FPos,
@@ -289,10 +340,14 @@ packets! {
   /// a new server. If the new server accepts the connection, the proxy will
   /// simply disconnect the player from the old server. If the connection
   /// failed, then a `sb::SwitchServerFailed` packet will be sent to the server.
+  ///
+  /// If `host` is set, the proxy will resolve it (checking SRV records first),
+  /// and try those addresses before `ips`.
   #[id = 18]
-  SwitchServer { mode: SwitchMode, ips: Vec<SocketAddr> },
-  /// The ids are for the latest version. It is up to the proxy to convert them
-  /// to older versions.
+  SwitchServer { mode: SwitchMode, ips: Vec<SocketAddr>, host: Option<String> },
+  /// The ids are for the client's version, not the latest version. The server
+  /// filters out anything the client doesn't know about, so the proxy can write
+  /// these ids directly.
   #[id = 41]
   Tags {
     block:       HashMap<String, Vec<i32>>,
@@ -312,6 +367,16 @@ packets! {
     #[must_exist]
     action: TitleAction,
   },
+  /// Sent after the server has handled a traced packet. Any packets the server
+  /// sent while handling that packet will be sent before this one. Each span
+  /// is the name, and the number of microseconds since the server received the
+  /// traced packet.
+  #[id = 45]
+  TraceEnd { id: u32, spans: Vec<TraceSpan> },
+  /// Sets the trades shown in an open merchant window. This must be sent after
+  /// the window is opened.
+  #[id = 52]
+  TradeList { wid: u8, offers: Vec<TradeOffer> },
   #[id = 19]
   UnloadChunk { pos: ChunkPos },
   #[id = 40]
@@ -321,13 +386,17 @@ packets! {
 
   #[id = 22]
   WindowOpen { wid: u8, ty: String, size: u32, title: String },
+  /// The `revision` of this packet and [`WindowItem`](Packet::WindowItem) is
+  /// set by the server's connection when the packet is sent, so it can be
+  /// left as 0. 1.17+ clients send back the last revision they received when
+  /// clicking, which is how the server notices an out of date inventory.
   #[id = 23]
-  WindowItems { wid: u8, items: Vec<Item>, held: Item },
+  WindowItems { wid: u8, items: Vec<Item>, held: Item, revision: u32 },
   /// AKA SetSlot. I named it this so that in alphabetical order it would show
   /// up with the rest of
   /// the inventory packets.
   #[id = 24]
-  WindowItem { wid: u8, slot: i32, item: Item },
+  WindowItem { wid: u8, slot: i32, item: Item, revision: u32 },
 }
 
 #[derive(Transfer, Debug, Clone, PartialEq)]
@@ -422,6 +491,122 @@ pub enum TeamAction {
   RemoveEntities { entities: Vec<String> },
 }
 
+/// A block entity that is sent to the client. This only includes the data that
+/// changes how the block is rendered, or what is shown when the block is
+/// opened.
+#[derive(Transfer, Debug, Clone, PartialEq)]
+pub struct BlockEntity {
+  pub pos:  Pos,
+  pub kind: BlockEntityKind,
+}
+
+#[derive(Transfer, Debug, Clone, PartialEq)]
+pub enum BlockEntityKind {
+  /// A mob spawner. `entity` is the name of the entity that spins inside the
+  /// spawner, like `pig`. If `entity` is empty, the spawner is empty.
+  #[id = 0]
+  Spawner { entity: String, delay: i16 },
+  /// A command block. This is shown when a player opens the command block
+  /// screen.
+  #[id = 1]
+  CommandBlock {
+    command:      String,
+    auto:         bool,
+    track_output: bool,
+    last_output:  Option<Chat>,
+  },
+}
+
+/// A single attribute of an entity. `name` is the modern name of the
+/// attribute, like `generic.max_health`.
+#[derive(Transfer, Debug, Clone, PartialEq)]
+pub struct EntityProperty {
+  pub name:      String,
+  pub base:      f64,
+  pub modifiers: Vec<PropertyModifier>,
+}
+
+#[derive(Transfer, Debug, Clone, PartialEq)]
+pub struct PropertyModifier {
+  pub id:     UUID,
+  pub amount: f64,
+  /// The operation of this modifier. `0` adds to the base value, `1` adds
+  /// `amount * base`, and `2` multiplies the total by `1 + amount`.
+  pub op:     u8,
+}
+
+#[derive(Transfer, Debug, Clone, PartialEq)]
+pub struct StatusPlayer {
+  pub name: String,
+  pub id:   UUID,
+}
+
+/// The resources the server is using. This is sent to the proxy in the
+/// [`ServerStatus`](packet::ServerStatus), and shown by `/status`.
+#[derive(Transfer, Debug, Default, Clone, PartialEq)]
+pub struct ServerUsage {
+  /// The resident memory of the server process, in bytes. This is 0 if it
+  /// isn't known, which is the case everywhere but Linux.
+  pub rss:     u64,
+  /// The number of plugins loaded.
+  pub plugins: u32,
+  pub worlds:  Vec<WorldUsage>,
+}
+
+/// The resources used by a single world. See [`ServerUsage`].
+#[derive(Transfer, Debug, Default, Clone, PartialEq)]
+pub struct WorldUsage {
+  pub name:     String,
+  pub chunks:   u32,
+  pub entities: u32,
+  pub players:  u32,
+  /// The size of the world's save directory, in bytes.
+  pub disk:     u64,
+}
+
+impl ServerUsage {
+  /// Returns a line for the whole server, followed by a line for each world.
+  pub fn lines(&self) -> Vec<String> {
+    let mut lines = vec![format!(
+      "Memory: {}, {} plugins",
+      if self.rss == 0 { "unknown".into() } else { fmt_bytes(self.rss) },
+      self.plugins
+    )];
+    for w in &self.worlds {
+      lines.push(format!(
+        "{}: {} chunks, {} entities, {} players, {} on disk",
+        w.name,
+        w.chunks,
+        w.entities,
+        w.players,
+        fmt_bytes(w.disk)
+      ));
+    }
+    lines
+  }
+}
+
+#[derive(Transfer, Debug, Clone, PartialEq)]
+pub struct TraceSpan {
+  pub name:   String,
+  pub micros: u64,
+}
+
+/// A single trade in a [`TradeList`](packet::TradeList).
+#[derive(Transfer, Debug, Clone, PartialEq)]
+pub struct TradeOffer {
+  pub input:        Item,
+  /// If this is empty (an id of 0), the trade only needs one input.
+  pub second_input: Item,
+  pub output:       Item,
+  /// If set, the trade is crossed out, and cannot be used.
+  pub disabled:     bool,
+  pub uses:         u32,
+  pub max_uses:     u32,
+  /// The experience the player gets for this trade.
+  pub xp:           u32,
+}
+
 #[derive(Transfer, Debug, Clone, PartialEq)]
 pub struct TeamInfo {
   pub display_name:  Chat,
@@ -614,6 +799,9 @@ pub struct PlayerListAdd {
   /// An optional display name. If present, this will replace their username in
   /// the tab list.
   pub display_name: Option<String>,
+  /// The player's profile properties. This is how the client knows which skin
+  /// to download.
+  pub properties:   Vec<ProfileProperty>,
 }
 
 /// See [`PlayerListAdd`]
@@ -1,2 +1,3 @@
 pub mod cb;
+pub mod protocol;
 pub mod sb;
@@ -0,0 +1,158 @@
+//! Versioning for the connection between the proxy and the server. This is
+//! separate from the Minecraft protocol version: it describes the packets in
+//! [`cb`](super::cb) and [`sb`](super::sb), and is only bumped when a change
+//! would make an older peer misparse them.
+//!
+//! The proxy sends a [`ProtocolInfo`] with the [`JoinInfo`] of every
+//! connection. The server picks the highest version that both sides support,
+//! and replies with a [`Protocol`] packet before anything else. If there is no
+//! version in common, the server kicks the player with a [`ProtocolError`]
+//! instead.
+//!
+//! Peers from before this handshake existed don't send or reply with anything,
+//! and are treated as version 0.
+//!
+//! [`JoinInfo`]: crate::util::JoinInfo
+//! [`Protocol`]: super::cb::Packet::Protocol
+
+use bb_macros::Transfer;
+use std::{error::Error, fmt};
+
+/// The newest protocol version this build supports.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The oldest protocol version this build supports. Version 0 is a peer that
+/// doesn't know about this handshake.
+pub const MIN_PROTOCOL_VERSION: u32 = 0;
+
+/// Optional features of the connection. These are only used if both sides
+/// support them.
+#[derive(Transfer, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+  /// Packets are compressed before being sent.
+  pub compression:     bool,
+  /// Entity updates for a single tick are sent as one packet.
+  pub entity_batching: bool,
+}
+
+impl Capabilities {
+  /// Returns the features this build supports. Neither side implements any of
+  /// these yet, so nothing is advertised.
+  pub fn supported() -> Self { Capabilities { compression: false, entity_batching: false } }
+
+  /// Returns the features that both `self` and `other` support.
+  pub fn intersect(&self, other: &Capabilities) -> Self {
+    Capabilities {
+      compression:     self.compression && other.compression,
+      entity_batching: self.entity_batching && other.entity_batching,
+    }
+  }
+}
+
+/// The protocol versions and features one side of the connection supports.
+/// The default is a peer from before this handshake existed.
+#[derive(Transfer, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolInfo {
+  pub min_version:  u32,
+  pub max_version:  u32,
+  pub capabilities: Capabilities,
+}
+
+/// The version and features both sides agreed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Negotiated {
+  pub version:      u32,
+  pub capabilities: Capabilities,
+}
+
+/// Returned when the two sides don't support any of the same versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolError {
+  /// The versions supported on this side.
+  pub local: (u32, u32),
+  /// The versions supported by the other side.
+  pub peer:  (u32, u32),
+}
+
+impl ProtocolError {
+  /// Returns `true` if the other side is the one that needs to be updated.
+  pub fn peer_outdated(&self) -> bool { self.peer.1 < self.local.0 }
+}
+
+impl fmt::Display for ProtocolError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "incompatible internal protocol: versions {} to {} are supported here, but the other side supports {} to {} ({} out of date)",
+      self.local.0,
+      self.local.1,
+      self.peer.0,
+      self.peer.1,
+      if self.peer_outdated() { "the other side is" } else { "this side is" },
+    )
+  }
+}
+
+impl Error for ProtocolError {}
+
+impl ProtocolInfo {
+  /// Returns the versions and features this build supports.
+  pub fn current() -> Self {
+    ProtocolInfo {
+      min_version:  MIN_PROTOCOL_VERSION,
+      max_version:  PROTOCOL_VERSION,
+      capabilities: Capabilities::supported(),
+    }
+  }
+
+  /// Returns `true` if `version` is in the range this side supports.
+  pub fn supports(&self, version: u32) -> bool {
+    (self.min_version..=self.max_version).contains(&version)
+  }
+
+  /// Picks the newest version that both `self` and `peer` support, along with
+  /// the features both of them have.
+  pub fn negotiate(&self, peer: &ProtocolInfo) -> Result<Negotiated, ProtocolError> {
+    let version = self.max_version.min(peer.max_version);
+    if self.supports(version) && peer.supports(version) {
+      Ok(Negotiated { version, capabilities: self.capabilities.intersect(&peer.capabilities) })
+    } else {
+      Err(ProtocolError {
+        local: (self.min_version, self.max_version),
+        peer:  (peer.min_version, peer.max_version),
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn info(min_version: u32, max_version: u32, compression: bool) -> ProtocolInfo {
+    ProtocolInfo {
+      min_version,
+      max_version,
+      capabilities: Capabilities { compression, entity_batching: false },
+    }
+  }
+
+  #[test]
+  fn negotiate() {
+    let n = info(0, 3, true).negotiate(&info(1, 2, true)).unwrap();
+    assert_eq!(n.version, 2);
+    assert!(n.capabilities.compression);
+    let n = info(0, 3, true).negotiate(&info(0, 5, false)).unwrap();
+    assert_eq!(n.version, 3);
+    assert!(!n.capabilities.compression);
+
+    // Older peers don't send anything, so they show up as the default.
+    let n = ProtocolInfo::current().negotiate(&ProtocolInfo::default()).unwrap();
+    assert_eq!(n.version, 0);
+    assert_eq!(n.capabilities, Capabilities::default());
+
+    let err = info(3, 4, false).negotiate(&info(1, 2, false)).unwrap_err();
+    assert!(err.peer_outdated());
+    let err = info(1, 2, false).negotiate(&info(3, 4, false)).unwrap_err();
+    assert!(!err.peer_outdated());
+  }
+}
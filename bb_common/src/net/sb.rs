@@ -9,10 +9,21 @@ use bb_macros::Transfer;
 pub enum Packet {
   #[id = 17]
   Animation { hand: Hand },
+  /// The sequence will only be present on 1.19+ clients. If it is present, the
+  /// server must reply with an
+  /// [`AckBlockUpdate`](crate::net::cb::Packet::AckBlockUpdate) once this has
+  /// been handled.
   #[id = 0]
-  BlockDig { pos: Pos, status: DigStatus, face: Face },
+  BlockDig { pos: Pos, status: DigStatus, face: Face, sequence: Option<i32> },
+  /// See [`BlockDig`](Self::BlockDig) for the sequence.
   #[id = 1]
-  BlockPlace { pos: Pos, face: Face, hand: Hand, cursor: FPos },
+  BlockPlace {
+    pos:      Pos,
+    face:     Face,
+    hand:     Hand,
+    cursor:   FPos,
+    sequence: Option<i32>,
+  },
   #[id = 2]
   CreativeInventoryUpdate { slot: i8, item: Item },
   #[id = 3]
@@ -21,10 +32,16 @@ pub enum Packet {
   Chat { msg: String },
   #[id = 13]
   ClickWindow {
-    wid:  u8,
-    slot: i16,
+    wid:      u8,
+    slot:     i16,
     #[must_exist]
-    mode: ClickWindow,
+    mode:     ClickWindow,
+    /// The last inventory revision the client received. Only sent by 1.17+
+    /// clients.
+    revision: Option<u32>,
+    /// The action number of this click. This goes up by one for every click
+    /// in a window. Only sent by clients before 1.17.
+    action:   Option<i16>,
   },
   #[id = 5]
   Flying { flying: bool },
@@ -49,8 +66,9 @@ pub enum Packet {
   },
   #[id = 11]
   PluginMessage { channel: String, data: Vec<u8> },
+  /// See [`BlockDig`](Self::BlockDig) for the sequence.
   #[id = 12]
-  UseItem { hand: Hand },
+  UseItem { hand: Hand, sequence: Option<i32> },
   /// Sneaking will not be present on 1.8-1.15 clients. It should be used if it
   /// is present (it will produce more accurate results for shift-clicking on an
   /// entity).
@@ -58,6 +76,50 @@ pub enum Packet {
   UseEntity { eid: i32, action: UseEntityAction, sneaking: Option<bool> },
   #[id = 14]
   WindowClose { wid: u8 },
+  /// Sent by the proxy directly before another packet, if tracing is enabled.
+  /// The packet after this one should be traced with the given id. Once the
+  /// server has handled that packet, it will reply with a
+  /// [`TraceEnd`](crate::net::cb::Packet::TraceEnd) packet.
+  #[id = 18]
+  Trace { id: u32 },
+  /// Sent when a player clicks "Done" in the command block screen. This is only
+  /// sent by clients in creative mode.
+  #[id = 19]
+  UpdateCommandBlock {
+    pos:          Pos,
+    command:      String,
+    mode:         CommandBlockMode,
+    track_output: bool,
+    conditional:  bool,
+    auto:         bool,
+  },
+  /// A packet the proxy doesn't know how to parse. This is only sent if the
+  /// proxy has pass-through enabled for the client's version. The `id` and
+  /// `data` are exactly what the client sent, so they are for the client's
+  /// version.
+  #[id = 20]
+  Raw { id: i32, data: Vec<u8> },
+  /// Sent by the proxy every few seconds. The server replies with a
+  /// [`Heartbeat`](crate::net::cb::Packet::Heartbeat) with the same id. If
+  /// either side stops hearing from the other, the connection is assumed to
+  /// have dropped.
+  #[id = 21]
+  Heartbeat { id: u32 },
+  /// Sent by the proxy right before it closes the connection on purpose, such
+  /// as when the client leaves or switches servers. If the connection closes
+  /// without this, the server holds onto the player for a short time, in case
+  /// the proxy resumes the connection.
+  #[id = 22]
+  Leave,
+  /// Sent when a player selects a trade in a merchant window. `index` is the
+  /// index into the offers of the last
+  /// [`TradeList`](crate::net::cb::packet::TradeList).
+  #[id = 23]
+  SelectTrade { index: u32 },
+  /// Sent while a player is riding an entity. `dismount` is set when the
+  /// player presses sneak, which should make them stop riding.
+  #[id = 24]
+  SteerVehicle { sideways: f32, forward: f32, jump: bool, dismount: bool },
 }
 
 #[derive(Transfer, Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -97,6 +159,33 @@ pub enum PlayerCommand {
   LeaveBed,
 }
 
+/// The type of command block. Each mode is a different block.
+#[derive(Transfer, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommandBlockMode {
+  /// Runs when the previous command block in a chain runs.
+  #[id = 0]
+  Chain,
+  /// Runs every tick while powered.
+  #[id = 1]
+  Repeating,
+  /// Runs once each time it is powered.
+  #[id = 2]
+  #[default]
+  Impulse,
+}
+
+impl CommandBlockMode {
+  /// Parses the mode from the id sent by 1.13+ clients.
+  pub fn from_id(id: i32) -> Option<Self> {
+    match id {
+      0 => Some(Self::Chain),
+      1 => Some(Self::Repeating),
+      2 => Some(Self::Impulse),
+      _ => None,
+    }
+  }
+}
+
 impl DigStatus {
   pub fn from_id(id: u8) -> Self {
     match id {
@@ -31,6 +31,11 @@
 //! The resulting json is not very nice to look at, but it is what the Minecraft
 //! client parses.
 
+use super::{
+  lang::{self, Lang},
+  UUID,
+};
+use crate::version::ProtocolVersion;
 use bb_macros::Transfer;
 use serde::ser::{Serialize, SerializeMap, SerializeSeq, SerializeStruct, Serializer};
 use serde_derive::{Deserialize, Serialize};
@@ -55,8 +60,27 @@ pub const CODE_SEP: char = '§';
 impl Chat {
   /// Creates a new Chat message. This will contain a single section, with the
   /// given text set. No formatting will be applied.
-  pub fn new<M: Into<String>>(msg: M) -> Self {
-    Chat { sections: vec![Section { text: msg.into(), ..Default::default() }] }
+  pub fn new<M: Into<String>>(msg: M) -> Self { Chat { sections: vec![Section::text(msg.into())] } }
+  /// Creates a new Chat message, with a single translated section. The client
+  /// will look up `key` in its language file, and replace each `%s` with the
+  /// next argument, or `%1$s` with the first argument.
+  ///
+  /// ```rust
+  /// use bb_common::util::Chat;
+  ///
+  /// let msg = Chat::translate("death.attack.player", ["macmv", "Notch"]);
+  /// assert_eq!(
+  ///   msg.to_json(),
+  ///   r#"{"translate":"death.attack.player","with":[{"text":"macmv"},{"text":"Notch"}]}"#
+  /// );
+  /// assert_eq!(msg.to_plain(), "macmv was slain by Notch");
+  /// ```
+  pub fn translate<K, A>(key: K, args: impl IntoIterator<Item = A>) -> Self
+  where
+    K: Into<String>,
+    A: Into<Chat>,
+  {
+    Chat { sections: vec![Section::translated(key.into(), args)] }
   }
   /// Creates a new Chat message, with 1 empty section.
   ///
@@ -68,7 +92,17 @@ impl Chat {
   /// a reference into self, so it must be dropped before adding another
   /// section.
   pub fn add<M: Into<String>>(&mut self, msg: M) -> &mut Section {
-    let s = Section { text: msg.into(), ..Default::default() };
+    self.add_section(Section::text(msg.into()))
+  }
+  /// Adds a new translated section. See [`translate`](Self::translate).
+  pub fn add_translate<K, A>(&mut self, key: K, args: impl IntoIterator<Item = A>) -> &mut Section
+  where
+    K: Into<String>,
+    A: Into<Chat>,
+  {
+    self.add_section(Section::translated(key.into(), args))
+  }
+  fn add_section(&mut self, s: Section) -> &mut Section {
     let idx = self.sections.len();
     self.sections.push(s);
     self.sections.get_mut(idx).unwrap()
@@ -90,6 +124,8 @@ impl Chat {
   }
 
   /// Generates a string for this chat message in plain text (no formatting).
+  /// Translated sections are translated with the bundled English language
+  /// file.
   pub fn to_plain(&self) -> String {
     let mut out = String::new();
     for s in &self.sections {
@@ -98,18 +134,147 @@ impl Chat {
     out
   }
 
+  /// Adds all the sections of `other` to the end of this message.
+  pub fn append(&mut self, other: Chat) { self.sections.extend(other.sections); }
+
+  /// Serializes this message for a client on the given version. This is the
+  /// same as [`to_json`](Self::to_json), except that any click events the
+  /// client doesn't understand are replaced with the closest thing it does
+  /// understand, and hover events are written in the format the client
+  /// expects.
+  ///
+  /// 1.8 clients are missing most translation keys, so translated sections
+  /// are translated here instead, with the bundled English language file.
+  pub fn to_json_for(&self, ver: ProtocolVersion) -> String {
+    if ver >= ProtocolVersion::V1_16 {
+      return self.to_json();
+    }
+    self.for_version(ver).to_json()
+  }
+  /// Returns a copy of this message that a client on the given version can
+  /// show. This is used when the message is serialized as part of something
+  /// else. See [`to_json_for`](Self::to_json_for).
+  pub fn for_version(&self, ver: ProtocolVersion) -> Chat {
+    let mut msg = self.clone();
+    if ver < ProtocolVersion::V1_16 {
+      msg.downgrade(ver);
+    }
+    msg
+  }
+  fn downgrade(&mut self, ver: ProtocolVersion) {
+    for s in &mut self.sections {
+      s.downgrade(ver);
+    }
+  }
+
+  /// Replaces every translated section with plain text, using the given
+  /// language file. Any formatting on translated sections and their
+  /// arguments is kept.
+  pub fn resolve(&self, lang: &Lang) -> Chat {
+    Chat { sections: self.sections.iter().map(|s| s.resolve(lang)).collect() }
+  }
+
   /// Generates a color-coded string for this message. Depending on where the
   /// text is being rendered, this may be the only option that works. However,
   /// this is much less flexible than the json format, and there may be missing
   /// features.
-  pub fn to_codes(&self) -> String {
+  ///
+  /// Custom colors are replaced with the closest legacy color. Use
+  /// [`to_codes_for`](Self::to_codes_for) to keep them for 1.16+ clients.
+  pub fn to_codes(&self) -> String { self.to_codes_for(ProtocolVersion::V1_8) }
+  /// Generates a color-coded string for a client on the given version. 1.16+
+  /// clients support custom colors, which are written as `§x` followed by
+  /// six hex digits, each with a `§` in front of it.
+  pub fn to_codes_for(&self, ver: ProtocolVersion) -> String {
     let mut out = String::new();
     for s in &self.sections {
-      s.to_codes(&mut out);
+      s.to_codes(&mut out, ver < ProtocolVersion::V1_16);
     }
     out
   }
 
+  /// Parses a color-coded string, like the one returned from
+  /// [`to_codes`](Self::to_codes). Each color code starts a new section. Like
+  /// the client, a color code resets any formatting codes before it, and
+  /// unknown codes are removed.
+  ///
+  /// ```rust
+  /// use bb_common::util::Chat;
+  ///
+  /// let msg = Chat::from_codes("§aHello §lworld§r!");
+  /// assert_eq!(
+  ///   msg.to_json(),
+  ///   r#"[{"text":""},{"text":"Hello ","color":"green"},{"text":"world","bold":true,"color":"green"},{"text":"!"}]"#
+  /// );
+  /// ```
+  pub fn from_codes(src: &str) -> Chat {
+    let mut sections = vec![];
+    let mut style = Section::default();
+    let mut text = String::new();
+    let mut chars = src.chars();
+    while let Some(c) = chars.next() {
+      if c != CODE_SEP {
+        text.push(c);
+        continue;
+      }
+      let Some(code) = chars.next() else { break };
+      if !text.is_empty() {
+        let mut s = style.clone();
+        s.content = Content::Text { text: std::mem::take(&mut text) };
+        sections.push(s);
+      }
+      let code = code.to_ascii_lowercase();
+      match code {
+        'k' => style.obfuscated = Some(true),
+        'l' => style.bold = Some(true),
+        'm' => style.strikethrough = Some(true),
+        'n' => style.underlined = Some(true),
+        'o' => style.italic = Some(true),
+        'r' => style = Section::default(),
+        'x' => {
+          // `§x` is followed by six more codes, which are the hex digits of the color.
+          let mut rest = chars.clone();
+          let hex: Option<String> = (0..6)
+            .map(|_| match (rest.next(), rest.next()) {
+              (Some(CODE_SEP), Some(d)) if d.is_ascii_hexdigit() => Some(d.to_ascii_lowercase()),
+              _ => None,
+            })
+            .collect();
+          if let Some(hex) = hex {
+            chars = rest;
+            style = Section { color: Some(Color::Custom(format!("#{hex}"))), ..Default::default() };
+          }
+        }
+        _ => {
+          if let Some(color) = Color::from_code(code) {
+            style = Section { color: Some(color), ..Default::default() };
+          }
+        }
+      }
+    }
+    if !text.is_empty() {
+      style.content = Content::Text { text };
+      sections.push(style);
+    }
+    // The first section is the parent of the others, so it can't have any
+    // formatting.
+    if sections.first().map_or(true, Section::is_styled) {
+      sections.insert(0, Section::default());
+    }
+    Chat { sections }
+  }
+
+  /// Converts this message into a single section. The sections of this
+  /// message become children of an empty section, so that they don't inherit
+  /// each other's formatting.
+  fn into_section(mut self) -> Section {
+    if self.sections.len() == 1 {
+      self.sections.pop().unwrap()
+    } else {
+      Section { extra: self.sections, ..Default::default() }
+    }
+  }
+
   pub fn sections_len(&self) -> usize { self.sections.len() }
   pub fn get_section(&mut self, idx: usize) -> Option<&mut Section> { self.sections.get_mut(idx) }
 }
@@ -140,6 +305,9 @@ impl From<&str> for Chat {
 impl From<String> for Chat {
   fn from(msg: String) -> Chat { Chat::new(msg) }
 }
+impl From<&String> for Chat {
+  fn from(msg: &String) -> Chat { Chat::new(msg.as_str()) }
+}
 
 /// This is a chat message section. It has some text, and a lot of optional
 /// fields:
@@ -173,7 +341,8 @@ impl From<String> for Chat {
 #[derive(Transfer, Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Section {
   #[id = 0]
-  text:          String,
+  #[serde(flatten)]
+  content:       Content,
   #[id = 1]
   #[serde(skip_serializing_if = "Option::is_none", default)]
   bold:          Option<bool>,
@@ -205,23 +374,54 @@ pub struct Section {
   #[id = 9]
   #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
   #[serde(rename = "hoverEvent")]
-  hover_event:   Option<HoverEvent>,
+  hover_event:   Option<Hover>,
   // Any child elements. If any of their options are None, then these options should be used.
   #[id = 10]
   #[serde(skip_serializing_if = "Vec::is_empty", default)]
   extra:         Vec<Section>,
 }
 
+/// The text of a section. This is either plain text, or a key that the client
+/// translates.
+#[derive(Transfer, Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+enum Content {
+  #[id = 0]
+  Text { text: String },
+  /// A key in the client's language file, like `death.attack.player`. The
+  /// arguments replace `%s` or `%1$s` in the translated text.
+  #[id = 1]
+  Translate {
+    translate: String,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    with:      Vec<Section>,
+  },
+}
+
+impl Default for Content {
+  fn default() -> Self { Content::Text { text: String::new() } }
+}
+
+/// Something that happens when a client clicks on a section. See
+/// [`Section::on_click`].
 #[derive(Transfer, Debug, Clone, PartialEq, Eq)]
 pub enum ClickEvent {
+  /// Opens the given url, after the client confirms it. Clients will ignore
+  /// anything that isn't an `http` or `https` url.
   #[id = 0]
   OpenURL(String),
+  /// Runs the given command, as if the client typed it in chat.
   #[id = 1]
   RunCommand(String),
+  /// Replaces the text in the client's chat box with the given text.
   #[id = 2]
   SuggestCommand(String),
+  /// Changes the page of the book the client is reading. Pages start at 1.
   #[id = 3]
-  ChangePage(String),
+  ChangePage(u32),
+  /// Copies the given text to the client's clipboard. This is only supported
+  /// on 1.15+ clients. Older clients will have the text put in their chat box
+  /// instead.
   #[id = 4]
   CopyToClipboard(String),
 }
@@ -233,45 +433,145 @@ impl Serialize for ClickEvent {
   {
     let mut s = serializer.serialize_struct("clickEvent", 2)?;
     let (action, val) = match self {
-      Self::OpenURL(v) => ("open_url", v),
-      Self::RunCommand(v) => ("run_command", v),
-      Self::SuggestCommand(v) => ("suggest_command", v),
-      Self::ChangePage(v) => ("change_page", v),
-      Self::CopyToClipboard(v) => ("copy_to_clipboard", v),
+      Self::OpenURL(v) => ("open_url", v.clone()),
+      Self::RunCommand(v) => ("run_command", v.clone()),
+      Self::SuggestCommand(v) => ("suggest_command", v.clone()),
+      // Clients parse the page from a string.
+      Self::ChangePage(v) => ("change_page", v.to_string()),
+      Self::CopyToClipboard(v) => ("copy_to_clipboard", v.clone()),
     };
     s.serialize_field("action", action)?;
-    s.serialize_field("value", val)?;
+    s.serialize_field("value", &val)?;
     s.end()
   }
 }
 
-#[derive(Transfer, Debug, Clone, PartialEq, Eq)]
+/// Something that is shown when a client hovers over a section. See
+/// [`Section::on_hover`].
+#[derive(Transfer, Debug, Clone, PartialEq)]
 pub enum HoverEvent {
+  /// Shows the given message.
   #[id = 0]
-  ShowText(String),
+  ShowText(Chat),
+  /// Shows the tooltip of an item. `item` is a namespaced id, like
+  /// `minecraft:stone`.
   #[id = 1]
-  ShowItem(String),
+  ShowItem { item: String, count: u8 },
+  /// Shows the type, uuid, and name of an entity. `ty` is a namespaced id, like
+  /// `minecraft:pig`. Clients only show this with advanced tooltips (F3+H)
+  /// enabled.
   #[id = 2]
-  ShowEntity(String),
+  ShowEntity { ty: String, id: UUID, name: Option<Chat> },
 }
 
-impl Serialize for HoverEvent {
+impl HoverEvent {
+  fn action(&self) -> &'static str {
+    match self {
+      Self::ShowText(_) => "show_text",
+      Self::ShowItem { .. } => "show_item",
+      Self::ShowEntity { .. } => "show_entity",
+    }
+  }
+
+  fn downgrade(&mut self, ver: ProtocolVersion) {
+    match self {
+      Self::ShowText(text) => text.downgrade(ver),
+      Self::ShowEntity { name: Some(name), .. } => name.downgrade(ver),
+      _ => {}
+    }
+  }
+}
+
+/// A [`HoverEvent`], along with the format it should be serialized in.
+#[derive(Transfer, Debug, Clone, PartialEq)]
+struct Hover {
+  #[id = 0]
+  event:  HoverEvent,
+  #[id = 1]
+  format: HoverFormat,
+}
+
+/// The hover event json changed in 1.16. Before that, every event had a
+/// `value`, which was a chat message. Items and entities were stored in that
+/// message as SNBT. 1.16 replaced this with `contents`, which is an object for
+/// items and entities.
+#[derive(Transfer, Debug, Clone, Copy, PartialEq, Eq)]
+enum HoverFormat {
+  /// The 1.16+ format.
+  #[id = 0]
+  Contents,
+  /// The 1.13-1.15 format, where entity names are json.
+  #[id = 1]
+  Value,
+  /// The 1.8-1.12 format, where entity names are plain text.
+  #[id = 2]
+  PlainValue,
+}
+
+#[derive(Serialize)]
+struct ItemContents<'a> {
+  id:    &'a str,
+  count: u8,
+}
+
+#[derive(Serialize)]
+struct EntityContents<'a> {
+  #[serde(rename = "type")]
+  ty:   &'a str,
+  id:   UUID,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  name: Option<&'a Chat>,
+}
+
+impl Serialize for Hover {
   fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
   where
     S: Serializer,
   {
     let mut s = serializer.serialize_struct("hoverEvent", 2)?;
-    let (action, val) = match self {
-      Self::ShowText(v) => ("show_text", v),
-      Self::ShowItem(v) => ("show_item", v),
-      Self::ShowEntity(v) => ("show_entity", v),
-    };
-    s.serialize_field("action", action)?;
-    s.serialize_field("value", val)?;
+    s.serialize_field("action", self.event.action())?;
+    match (&self.event, self.format) {
+      (HoverEvent::ShowText(text), HoverFormat::Contents) => s.serialize_field("contents", text)?,
+      (HoverEvent::ShowText(text), _) => s.serialize_field("value", text)?,
+      (HoverEvent::ShowItem { item, count }, HoverFormat::Contents) => {
+        s.serialize_field("contents", &ItemContents { id: item, count: *count })?
+      }
+      (HoverEvent::ShowItem { item, count }, _) => {
+        s.serialize_field("value", &format!("{{id:{},Count:{count}b}}", snbt_str(item)))?
+      }
+      (HoverEvent::ShowEntity { ty, id, name }, HoverFormat::Contents) => {
+        s.serialize_field("contents", &EntityContents { ty, id: *id, name: name.as_ref() })?
+      }
+      (HoverEvent::ShowEntity { ty, id, name }, format) => {
+        let mut value = format!("{{type:{},id:{}", snbt_str(ty), snbt_str(&id.as_dashed_str()));
+        if let Some(name) = name {
+          let name = if format == HoverFormat::Value { name.to_json() } else { name.to_plain() };
+          value.push_str(",name:");
+          value.push_str(&snbt_str(&name));
+        }
+        value.push('}');
+        s.serialize_field("value", &value)?
+      }
+    }
     s.end()
   }
 }
 
+/// Quotes the given string for use in SNBT, escaping any quotes and
+/// backslashes in it.
+fn snbt_str(s: &str) -> String {
+  let mut out = String::with_capacity(s.len() + 2);
+  out.push('"');
+  for c in s.chars() {
+    if c == '"' || c == '\\' {
+      out.push('\\');
+    }
+    out.push(c);
+  }
+  out.push('"');
+  out
+}
+
 macro_rules! add_bool {
   (
     $(#[$meta:meta])*
@@ -286,6 +586,19 @@ macro_rules! add_bool {
 }
 
 impl Section {
+  fn text(text: String) -> Self {
+    Section { content: Content::Text { text }, ..Default::default() }
+  }
+  fn translated<A: Into<Chat>>(key: String, args: impl IntoIterator<Item = A>) -> Self {
+    Section {
+      content: Content::Translate {
+        translate: key,
+        with:      args.into_iter().map(|a| a.into().into_section()).collect(),
+      },
+      ..Default::default()
+    }
+  }
+
   add_bool!(
     /// Makes this chat section bold.
     bold
@@ -326,29 +639,159 @@ impl Section {
   }
   /// When the client hovers over this section, something will happen.
   pub fn on_hover(&mut self, e: HoverEvent) -> &mut Self {
-    self.hover_event = Some(e);
+    self.hover_event = Some(Hover { event: e, format: HoverFormat::Contents });
     self
   }
+  /// Clicking on this section will run the given command. A `/` is added to
+  /// the start if it is missing, as 1.19+ clients can't send chat messages
+  /// from a click event.
+  pub fn run_command<M: Into<String>>(&mut self, command: M) -> &mut Self {
+    let mut command = command.into();
+    if !command.starts_with('/') {
+      command.insert(0, '/');
+    }
+    self.on_click(ClickEvent::RunCommand(command))
+  }
+  /// Clicking on this section will replace the text in the chat box with the
+  /// given text.
+  pub fn suggest_command<M: Into<String>>(&mut self, text: M) -> &mut Self {
+    self.on_click(ClickEvent::SuggestCommand(text.into()))
+  }
+  /// Clicking on this section will open the given url. See
+  /// [`ClickEvent::OpenURL`].
+  pub fn open_url<M: Into<String>>(&mut self, url: M) -> &mut Self {
+    self.on_click(ClickEvent::OpenURL(url.into()))
+  }
+  /// Clicking on this section will copy the given text. See
+  /// [`ClickEvent::CopyToClipboard`].
+  pub fn copy_to_clipboard<M: Into<String>>(&mut self, text: M) -> &mut Self {
+    self.on_click(ClickEvent::CopyToClipboard(text.into()))
+  }
+  /// Hovering over this section will show the given message.
+  pub fn show_text<M: Into<Chat>>(&mut self, text: M) -> &mut Self {
+    self.on_hover(HoverEvent::ShowText(text.into()))
+  }
+  /// Hovering over this section will show the tooltip of an item. See
+  /// [`HoverEvent::ShowItem`].
+  pub fn show_item<M: Into<String>>(&mut self, item: M, count: u8) -> &mut Self {
+    self.on_hover(HoverEvent::ShowItem { item: item.into(), count })
+  }
+  /// Hovering over this section will show an entity. See
+  /// [`HoverEvent::ShowEntity`].
+  pub fn show_entity<M: Into<String>>(&mut self, ty: M, id: UUID, name: Option<Chat>) -> &mut Self {
+    self.on_hover(HoverEvent::ShowEntity { ty: ty.into(), id, name })
+  }
   /// This adds a child section to this chat section. Any properties left blank
   /// on that child will be filled in from this section. If you want multiple
   /// chat sections in a row, you probably want to use [`Chat::add`] instead.
   /// This is instead useful for something like a hyperlink, where part of it
   /// should be a different color.
   pub fn add_child<M: Into<String>>(&mut self, msg: M) -> &mut Section {
-    let s = Section { text: msg.into(), ..Default::default() };
+    let s = Section::text(msg.into());
     let idx = self.extra.len();
     self.extra.push(s);
     self.extra.get_mut(idx).unwrap()
   }
 
+  fn downgrade(&mut self, ver: ProtocolVersion) {
+    // `copy_to_clipboard` was added in 1.15. Putting the text in the chat box is
+    // the closest older clients can do.
+    if ver < ProtocolVersion::V1_15 {
+      if let Some(ClickEvent::CopyToClipboard(text)) = &self.click_event {
+        self.click_event = Some(ClickEvent::SuggestCommand(text.clone()));
+      }
+    }
+    if let Some(hover) = &mut self.hover_event {
+      if ver < ProtocolVersion::V1_16 {
+        hover.format =
+          if ver < ProtocolVersion::V1_13 { HoverFormat::PlainValue } else { HoverFormat::Value };
+      }
+      hover.event.downgrade(ver);
+    }
+    if ver < ProtocolVersion::V1_16 {
+      if let Some(c) = &self.color {
+        self.color = Some(c.to_legacy());
+      }
+    }
+    if ver < ProtocolVersion::V1_9 && matches!(self.content, Content::Translate { .. }) {
+      *self = self.resolve(Lang::bundled());
+    }
+    if let Content::Translate { with, .. } = &mut self.content {
+      for arg in with {
+        arg.downgrade(ver);
+      }
+    }
+    for e in &mut self.extra {
+      e.downgrade(ver);
+    }
+  }
+
+  /// Replaces this section with a text section, if it is translated. The
+  /// translated text and arguments become children of the new section, so
+  /// that they keep this section's formatting.
+  fn resolve(&self, lang: &Lang) -> Section {
+    let mut out = self.clone();
+    out.extra = self.extra.iter().map(|e| e.resolve(lang)).collect();
+    if let Content::Translate { translate, with } = &self.content {
+      let with: Vec<_> = with.iter().map(|a| a.resolve(lang)).collect();
+      let mut parts = vec![];
+      for part in lang::format(lang.get(translate).unwrap_or(translate), with.len()) {
+        match part {
+          lang::Part::Text(text) => parts.push(Section::text(text)),
+          lang::Part::Arg(i) => parts.push(with[i].clone()),
+        }
+      }
+      parts.append(&mut out.extra);
+      out.content = Content::Text { text: String::new() };
+      out.extra = parts;
+    }
+    out
+  }
+
   fn to_plain(&self, out: &mut String) {
-    out.push_str(&self.text);
+    match &self.content {
+      Content::Text { text } => out.push_str(text),
+      Content::Translate { .. } => {
+        self.resolve(Lang::bundled()).to_plain(out);
+        return;
+      }
+    }
     for e in &self.extra {
       e.to_plain(out);
     }
   }
 
-  fn to_codes(&self, out: &mut String) {
+  /// Returns `true` if this section has any formatting that would be
+  /// inherited by its children.
+  fn is_styled(&self) -> bool {
+    self.bold.is_some()
+      || self.italic.is_some()
+      || self.underlined.is_some()
+      || self.strikethrough.is_some()
+      || self.obfuscated.is_some()
+      || self.color.is_some()
+  }
+
+  fn to_codes(&self, out: &mut String, legacy: bool) {
+    // Color codes reset the formatting codes, so the color must come first.
+    if let Some(c) = &self.color {
+      match if legacy { c.to_legacy() } else { c.clone() } {
+        Color::White => {}
+        c @ Color::Custom(_) => {
+          let (r, g, b) = c.to_rgb();
+          out.push(CODE_SEP);
+          out.push('x');
+          for digit in format!("{r:02x}{g:02x}{b:02x}").chars() {
+            out.push(CODE_SEP);
+            out.push(digit);
+          }
+        }
+        c => {
+          out.push(CODE_SEP);
+          out.push(c.code());
+        }
+      }
+    }
     if self.bold == Some(true) {
       out.push_str("§l");
     }
@@ -356,7 +799,7 @@ impl Section {
       out.push_str("§o");
     }
     if self.underlined == Some(true) {
-      out.push_str("§u");
+      out.push_str("§n");
     }
     if self.strikethrough == Some(true) {
       out.push_str("§m");
@@ -364,15 +807,20 @@ impl Section {
     if self.obfuscated == Some(true) {
       out.push_str("§k");
     }
-    if let Some(c) = &self.color {
-      if c != &Color::White {
-        out.push('§');
-        out.push(c.code());
+    match &self.content {
+      Content::Text { text } => out.push_str(text),
+      Content::Translate { .. } => {
+        // The formatting codes for this section have already been written, so only
+        // the children of the resolved section are needed.
+        for e in &self.resolve(Lang::bundled()).extra {
+          e.to_codes(out, legacy);
+        }
+        out.push_str("§r");
+        return;
       }
     }
-    out.push_str(&self.text);
     for e in &self.extra {
-      e.to_codes(out);
+      e.to_codes(out, legacy);
     }
     // This is the lazy way, of just resetting after every section. This is
     // meant to be after subsections, so that they keep the formatting of their
@@ -428,6 +876,54 @@ impl Color {
   /// clients, this will render as white.
   pub fn rgb(r: u8, g: u8, b: u8) -> Self { Color::Custom(format!("#{r:02x}{g:02x}{b:02x}")) }
 
+  /// Returns the color for the given color code, like `a` for green. This
+  /// doesn't include formatting codes like `l`.
+  pub fn from_code(code: char) -> Option<Self> {
+    LEGACY.iter().find(|c| c.code() == code.to_ascii_lowercase()).cloned()
+  }
+
+  /// Returns the red, green, and blue values of this color. Custom colors
+  /// that aren't a valid `#rrggbb` string are white.
+  pub fn to_rgb(&self) -> (u8, u8, u8) {
+    match self {
+      Self::Black => (0x00, 0x00, 0x00),
+      Self::DarkBlue => (0x00, 0x00, 0xaa),
+      Self::DarkGreen => (0x00, 0xaa, 0x00),
+      Self::DarkAqua => (0x00, 0xaa, 0xaa),
+      Self::DarkRed => (0xaa, 0x00, 0x00),
+      Self::Purple => (0xaa, 0x00, 0xaa),
+      Self::Gold => (0xff, 0xaa, 0x00),
+      Self::Gray => (0xaa, 0xaa, 0xaa),
+      Self::DarkGray => (0x55, 0x55, 0x55),
+      Self::Blue => (0x55, 0x55, 0xff),
+      Self::BrightGreen => (0x55, 0xff, 0x55),
+      Self::Cyan => (0x55, 0xff, 0xff),
+      Self::Red => (0xff, 0x55, 0x55),
+      Self::Pink => (0xff, 0x55, 0xff),
+      Self::Yellow => (0xff, 0xff, 0x55),
+      Self::White => (0xff, 0xff, 0xff),
+      Self::Custom(v) => match v.strip_prefix('#').map(|v| u32::from_str_radix(v, 16)) {
+        Some(Ok(v)) if v <= 0xffffff => ((v >> 16) as u8, (v >> 8) as u8, v as u8),
+        _ => (0xff, 0xff, 0xff),
+      },
+    }
+  }
+
+  /// Returns the closest color that clients before 1.16 can show. This is
+  /// only different for custom colors.
+  pub fn to_legacy(&self) -> Color {
+    if !matches!(self, Self::Custom(_)) {
+      return self.clone();
+    }
+    let (r, g, b) = self.to_rgb();
+    let dist = |c: &Color| {
+      let (r2, g2, b2) = c.to_rgb();
+      let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+      d(r, r2) + d(g, g2) + d(b, b2)
+    };
+    LEGACY.iter().min_by_key(|c| dist(c)).unwrap().clone()
+  }
+
   /// Converts the color to a string. This string should be used in chat json.
   pub fn to_str(&self) -> &str {
     match self {
@@ -498,6 +994,26 @@ impl Color {
   }
 }
 
+/// Every color except [`Color::Custom`], in the order of their color codes.
+const LEGACY: [Color; 16] = [
+  Color::Black,
+  Color::DarkBlue,
+  Color::DarkGreen,
+  Color::DarkAqua,
+  Color::DarkRed,
+  Color::Purple,
+  Color::Gold,
+  Color::Gray,
+  Color::DarkGray,
+  Color::Blue,
+  Color::BrightGreen,
+  Color::Cyan,
+  Color::Red,
+  Color::Pink,
+  Color::Yellow,
+  Color::White,
+];
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ColorFromStrError(String);
 
@@ -528,6 +1044,9 @@ impl FromStr for Color {
       "pink" => Color::Pink,
       "yellow" => Color::Yellow,
       "white" => Color::White,
+      _ if s.len() == 7 && s.starts_with('#') && s[1..].chars().all(|c| c.is_ascii_hexdigit()) => {
+        Color::Custom(s.to_ascii_lowercase())
+      }
       _ => return Err(ColorFromStrError(s.into())),
     })
   }
@@ -605,7 +1124,7 @@ mod tests {
       msg.add("hover time").on_hover(HoverEvent::ShowText("big gaming".into()));
       assert_eq!(
         msg.to_json(),
-        r#"[{"text":""},{"text":"hover time","hoverEvent":{"action":"show_text","value":"big gaming"}}]"#
+        r#"[{"text":""},{"text":"hover time","hoverEvent":{"action":"show_text","contents":{"text":"big gaming"}}}]"#
       );
 
       // Children testing
@@ -619,4 +1138,137 @@ mod tests {
       );
     }
   }
+
+  #[test]
+  fn versions() {
+    let mut msg = Chat::new("a");
+    msg.add("copy").add_child("me").on_click(ClickEvent::CopyToClipboard("text".into()));
+    assert_eq!(
+      msg.to_json_for(ProtocolVersion::V1_8),
+      r#"[{"text":"a"},{"text":"copy","extra":[{"text":"me","clickEvent":{"action":"suggest_command","value":"text"}}]}]"#
+    );
+    assert_eq!(msg.to_json_for(ProtocolVersion::V1_15), msg.to_json());
+
+    let mut other = Chat::new("b");
+    other.add("c");
+    msg.append(other);
+    assert_eq!(msg.sections_len(), 4);
+    assert_eq!(msg.to_plain(), "acopymebc");
+  }
+
+  #[test]
+  fn codes() {
+    let mut msg = Chat::new("a");
+    msg.add("b").color(Color::Red).bold().underlined();
+    msg.add("c").color(Color::rgb(0x12, 0x34, 0xf0));
+    assert_eq!(msg.to_codes(), "a§r§c§l§nb§r§9c§r");
+    assert_eq!(msg.to_codes_for(ProtocolVersion::V1_16), "a§r§c§l§nb§r§x§1§2§3§4§f§0c§r");
+    assert_eq!(Chat::from_codes(&msg.to_codes_for(ProtocolVersion::V1_16)), {
+      let mut msg = Chat::new("a");
+      msg.add("b").bold().underlined().color(Color::Red);
+      msg.add("c").color(Color::Custom("#1234f0".into()));
+      msg
+    });
+
+    // Unknown codes are removed, and incomplete hex colors are read as normal
+    // color codes, like the client does. Uppercase codes work the same as
+    // lowercase codes.
+    let msg = Chat::from_codes("§lé§z日本§x§1§2§A語§");
+    assert_eq!(msg.to_plain(), "é日本語");
+    assert_eq!(msg.to_codes(), "§r§lé§r§l日本§r§a語§r");
+    assert_eq!(Chat::from_codes(""), Chat::empty());
+    assert_eq!(Chat::from_codes("plain"), Chat::new("plain"));
+
+    // Older clients get the closest legacy color.
+    assert_eq!(Color::rgb(0xff, 0x00, 0x00).to_legacy(), Color::DarkRed);
+    assert_eq!(Color::rgb(0x10, 0x10, 0x10).to_legacy(), Color::Black);
+    assert_eq!(Color::Custom("nonsense".into()).to_legacy(), Color::White);
+    assert_eq!("#1234F0".parse::<Color>(), Ok(Color::Custom("#1234f0".into())));
+    let mut msg = Chat::empty();
+    msg.add("hex").color(Color::rgb(0x00, 0x00, 0xb0));
+    assert_eq!(
+      msg.to_json_for(ProtocolVersion::V1_15),
+      r#"[{"text":""},{"text":"hex","color":"dark_blue"}]"#
+    );
+  }
+
+  #[test]
+  fn translate() {
+    let mut name = Chat::empty();
+    name.add("Notch").color(Color::Red);
+    let msg = Chat::translate("death.attack.player", [Chat::new("macmv"), name]);
+    assert_eq!(
+      msg.to_json(),
+      r#"{"translate":"death.attack.player","with":[{"text":"macmv"},{"text":"","extra":[{"text":""},{"text":"Notch","color":"red"}]}]}"#
+    );
+    assert_eq!(Chat::from_json(&msg.to_json()).unwrap().to_plain(), "macmv was slain by Notch");
+    assert_eq!(msg.to_json_for(ProtocolVersion::V1_12), msg.to_json());
+    assert_eq!(
+      msg.to_json_for(ProtocolVersion::V1_8),
+      r#"{"text":"","extra":[{"text":"macmv"},{"text":" was slain by "},{"text":"","extra":[{"text":""},{"text":"Notch","color":"red"}]}]}"#
+    );
+
+    // Unknown keys are shown as-is, like the client would.
+    let msg = Chat::translate("my.key", ["a"]);
+    assert_eq!(msg.to_plain(), "my.key");
+    let mut lang = Lang::new();
+    lang.insert("my.key", "got %s!");
+    assert_eq!(msg.resolve(&lang).to_plain(), "got a!");
+  }
+
+  #[test]
+  fn events() {
+    let id = UUID::from_u128(0x1234);
+    let mut msg = Chat::empty();
+    msg.add("a").run_command("help").show_item("minecraft:stone", 3);
+    msg.add("b").show_entity("minecraft:pig", id, Some("Bob".into()));
+    assert_eq!(
+      msg.to_json(),
+      r#"[{"text":""},{"text":"a","clickEvent":{"action":"run_command","value":"/help"},"hoverEvent":{"action":"show_item","contents":{"id":"minecraft:stone","count":3}}},{"text":"b","hoverEvent":{"action":"show_entity","contents":{"type":"minecraft:pig","id":"00000000-0000-0000-0000-000000001234","name":{"text":"Bob"}}}}]"#
+    );
+    assert_eq!(
+      msg.to_json_for(ProtocolVersion::V1_15),
+      r#"[{"text":""},{"text":"a","clickEvent":{"action":"run_command","value":"/help"},"hoverEvent":{"action":"show_item","value":"{id:\"minecraft:stone\",Count:3b}"}},{"text":"b","hoverEvent":{"action":"show_entity","value":"{type:\"minecraft:pig\",id:\"00000000-0000-0000-0000-000000001234\",name:\"{\\\"text\\\":\\\"Bob\\\"}\"}"}}]"#
+    );
+    assert_eq!(
+      msg.to_json_for(ProtocolVersion::V1_12),
+      r#"[{"text":""},{"text":"a","clickEvent":{"action":"run_command","value":"/help"},"hoverEvent":{"action":"show_item","value":"{id:\"minecraft:stone\",Count:3b}"}},{"text":"b","hoverEvent":{"action":"show_entity","value":"{type:\"minecraft:pig\",id:\"00000000-0000-0000-0000-000000001234\",name:\"Bob\"}"}}]"#
+    );
+
+    // Text inside a hover event is downgraded as well.
+    let mut msg = Chat::empty();
+    msg.add("c").show_text(Chat::translate("my.key", ["a"])).on_click(ClickEvent::ChangePage(2));
+    assert_eq!(
+      msg.to_json_for(ProtocolVersion::V1_8),
+      r#"[{"text":""},{"text":"c","clickEvent":{"action":"change_page","value":"2"},"hoverEvent":{"action":"show_text","value":{"text":"","extra":[{"text":"my.key"}]}}}]"#
+    );
+  }
+
+  #[test]
+  fn escaping() {
+    // None of this should be able to break out of the string it is in.
+    let evil = r#"a"}],"extra":[{"text":"\\b"#;
+    let mut msg = Chat::empty();
+    msg.add(evil).insertion(evil).suggest_command(evil).show_entity(
+      evil,
+      UUID::from_u128(0),
+      Some(evil.into()),
+    );
+    for ver in [ProtocolVersion::V1_8, ProtocolVersion::V1_15, ProtocolVersion::V1_16] {
+      let json: serde_json::Value = serde_json::from_str(&msg.to_json_for(ver)).unwrap();
+      let section = &json[1];
+      assert_eq!(section.as_object().unwrap().len(), 4);
+      assert_eq!(section["text"], evil);
+      assert_eq!(section["insertion"], evil);
+      assert_eq!(section["clickEvent"]["value"], evil);
+      if ver >= ProtocolVersion::V1_16 {
+        assert_eq!(section["hoverEvent"]["contents"]["type"], evil);
+        assert_eq!(section["hoverEvent"]["contents"]["name"]["text"], evil);
+      } else {
+        let value = section["hoverEvent"]["value"].as_str().unwrap();
+        assert!(value.starts_with(r#"{type:"a\"}],\"extra\":[{\"text\":\"\\\\b""#), "{value}");
+      }
+    }
+    assert_eq!(snbt_str(r#"a"\"#), r#""a\"\\""#);
+  }
 }
@@ -1,4 +1,4 @@
-use crate::util::Chat;
+use crate::util::{Chat, UUID};
 use bb_macros::Transfer;
 use std::{collections::HashMap, num::NonZeroU8};
 
@@ -20,6 +20,8 @@ pub struct ItemData {
   /// zero.
   pub enchantments: Option<HashMap<u32, NonZeroU8>>,
   pub unbreakable:  bool,
+  /// Attribute modifiers applied while this item is held or worn.
+  pub attributes:   Vec<AttributeModifier>,
 }
 #[derive(Transfer, Default, Debug, Clone, PartialEq)]
 pub struct ItemDisplay {
@@ -33,6 +35,55 @@ pub struct ItemDisplay {
   pub lore: Vec<Chat>,
 }
 
+/// Changes an attribute (like attack damage or movement speed) of the entity
+/// holding or wearing an item.
+#[derive(Transfer, Debug, Clone, PartialEq)]
+pub struct AttributeModifier {
+  /// The attribute to modify, in the latest format, without a namespace. For
+  /// example, `generic.attack_damage`. The proxy will convert this to the
+  /// camel case names that older clients use.
+  pub attribute: String,
+  pub amount:    f64,
+  pub operation: AttributeOperation,
+  /// The slot this item must be in for the modifier to apply. If `None`, it
+  /// applies in any slot. This is ignored on 1.8 clients.
+  pub slot:      Option<AttributeSlot>,
+  /// Modifiers with the same id on the same entity don't stack.
+  pub id:        UUID,
+}
+
+/// How the amount of an [`AttributeModifier`] is applied to the attribute.
+#[derive(Transfer, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperation {
+  /// Adds the amount to the base value.
+  #[id = 0]
+  #[default]
+  Add,
+  /// Multiplies the base value by `1 + amount`, and adds that.
+  #[id = 1]
+  MultiplyBase,
+  /// Multiplies the final value by `1 + amount`.
+  #[id = 2]
+  Multiply,
+}
+
+/// The slot an item must be in for an [`AttributeModifier`] to apply.
+#[derive(Transfer, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeSlot {
+  #[id = 0]
+  MainHand,
+  #[id = 1]
+  OffHand,
+  #[id = 2]
+  Head,
+  #[id = 3]
+  Chest,
+  #[id = 4]
+  Legs,
+  #[id = 5]
+  Feet,
+}
+
 impl Default for Item {
   fn default() -> Self { Item::new(0, 0, 0) }
 }
@@ -49,12 +100,86 @@ impl Item {
 
 impl ItemData {
   pub const fn new() -> Self {
-    ItemData { display: ItemDisplay::new(), enchantments: None, unbreakable: false }
+    ItemData {
+      display:      ItemDisplay::new(),
+      enchantments: None,
+      unbreakable:  false,
+      attributes:   vec![],
+    }
   }
   pub fn enchantments_mut(&mut self) -> &mut HashMap<u32, NonZeroU8> {
     self.enchantments.get_or_insert_with(HashMap::new)
   }
 }
+impl AttributeModifier {
+  /// Creates a modifier that applies in any slot. The id is derived from the
+  /// attribute name, so that two modifiers for the same attribute don't stack.
+  pub fn new(attribute: impl Into<String>, amount: f64, operation: AttributeOperation) -> Self {
+    let attribute = attribute.into();
+    let id = UUID::from_u128(name_hash(&attribute));
+    AttributeModifier { attribute, amount, operation, slot: None, id }
+  }
+  /// Makes this modifier only apply in the given slot.
+  pub fn in_slot(mut self, slot: AttributeSlot) -> Self {
+    self.slot = Some(slot);
+    self
+  }
+}
+
+impl AttributeOperation {
+  pub fn id(&self) -> u8 {
+    match self {
+      Self::Add => 0,
+      Self::MultiplyBase => 1,
+      Self::Multiply => 2,
+    }
+  }
+  pub fn from_id(id: u8) -> Option<Self> {
+    Some(match id {
+      0 => Self::Add,
+      1 => Self::MultiplyBase,
+      2 => Self::Multiply,
+      _ => return None,
+    })
+  }
+}
+
+impl AttributeSlot {
+  /// Returns the name used in item NBT, like `mainhand`.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::MainHand => "mainhand",
+      Self::OffHand => "offhand",
+      Self::Head => "head",
+      Self::Chest => "chest",
+      Self::Legs => "legs",
+      Self::Feet => "feet",
+    }
+  }
+  /// Parses a slot name, as returned from [`name`](Self::name).
+  pub fn from_name(s: &str) -> Option<Self> {
+    Some(match s {
+      "mainhand" => Self::MainHand,
+      "offhand" => Self::OffHand,
+      "head" => Self::Head,
+      "chest" => Self::Chest,
+      "legs" => Self::Legs,
+      "feet" => Self::Feet,
+      _ => return None,
+    })
+  }
+}
+
+/// FNV-1a, widened to 128 bits. This only needs to be stable, not secure.
+fn name_hash(name: &str) -> u128 {
+  let mut hash = 0x6c62272e07bb014262b821756295c58d_u128;
+  for b in name.bytes() {
+    hash ^= b as u128;
+    hash = hash.wrapping_mul(0x0000000001000000000000000000013b);
+  }
+  hash
+}
+
 impl ItemDisplay {
   pub const fn new() -> Self { ItemDisplay { name: None, lore: vec![] } }
 }
@@ -0,0 +1,83 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+  fs, io,
+  ops::{Deref, DerefMut},
+  path::{Path, PathBuf},
+  time::SystemTime,
+};
+
+/// A value stored in a json file. This is used for files that are shared
+/// between the proxy and the server (such as the ban list), so it can be read
+/// again whenever the file changes.
+#[derive(Debug)]
+pub struct JsonFile<T> {
+  path:     PathBuf,
+  value:    T,
+  /// The modified time of the file when we last read or wrote it.
+  modified: Option<SystemTime>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default> JsonFile<T> {
+  /// Creates a file with the default value, which will be saved to `path`.
+  /// This doesn't read or write anything.
+  pub fn new(path: impl AsRef<Path>) -> Self {
+    JsonFile { path: path.as_ref().into(), value: T::default(), modified: None }
+  }
+
+  /// Loads the file at the given path. If the file doesn't exist, the value
+  /// will be the default. If the file is invalid, an error is logged, and the
+  /// value will be the default.
+  pub fn load(path: impl AsRef<Path>) -> Self {
+    let mut file = JsonFile::new(path);
+    file.reload();
+    file
+  }
+
+  /// Reads the file again, if it has changed since it was last read or
+  /// written. This allows changes made by another process (or by hand) to
+  /// take effect without a restart.
+  pub fn reload_if_changed(&mut self) {
+    if self.modified_time() != self.modified {
+      self.reload();
+    }
+  }
+
+  fn reload(&mut self) {
+    self.modified = self.modified_time();
+    self.value = match fs::read_to_string(&self.path) {
+      Ok(src) => match serde_json::from_str(&src) {
+        Ok(value) => value,
+        Err(e) => {
+          error!("invalid json in {}: {e}", self.path.display());
+          T::default()
+        }
+      },
+      Err(e) if e.kind() == io::ErrorKind::NotFound => T::default(),
+      Err(e) => {
+        error!("could not read {}: {e}", self.path.display());
+        T::default()
+      }
+    };
+  }
+
+  /// Writes the value to disk.
+  pub fn save(&mut self) -> io::Result<()> {
+    let src = serde_json::to_string_pretty(&self.value)?;
+    fs::write(&self.path, src)?;
+    self.modified = self.modified_time();
+    Ok(())
+  }
+
+  fn modified_time(&self) -> Option<SystemTime> {
+    fs::metadata(&self.path).and_then(|m| m.modified()).ok()
+  }
+}
+
+impl<T> Deref for JsonFile<T> {
+  type Target = T;
+
+  fn deref(&self) -> &T { &self.value }
+}
+impl<T> DerefMut for JsonFile<T> {
+  fn deref_mut(&mut self) -> &mut T { &mut self.value }
+}
@@ -0,0 +1,125 @@
+//! Language files, used to translate chat messages on the server. Clients
+//! normally translate messages themselves (see [`Chat::translate`]), but 1.8
+//! clients are missing most of the newer keys, so messages are translated
+//! before they are sent to them.
+//!
+//! [`Chat::translate`]: super::Chat::translate
+
+use std::{collections::HashMap, sync::OnceLock};
+
+/// A map of translation keys to translated text, like `death.attack.player` to
+/// `%1$s was slain by %2$s`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lang {
+  keys: HashMap<String, String>,
+}
+
+impl Lang {
+  /// Creates an empty language file.
+  pub fn new() -> Self { Lang::default() }
+
+  /// Returns the English language file bundled with Bamboo. This only has
+  /// the most common keys, such as death messages and join messages.
+  pub fn bundled() -> &'static Lang {
+    static BUNDLED: OnceLock<Lang> = OnceLock::new();
+    BUNDLED.get_or_init(|| Lang::from_json(include_str!("en_us.json")).unwrap())
+  }
+
+  /// Parses a language file in the json format used by 1.13+ clients. This is
+  /// a single object, mapping keys to translated text.
+  pub fn from_json(src: &str) -> Result<Self, serde_json::Error> {
+    Ok(Lang { keys: serde_json::from_str(src)? })
+  }
+
+  /// Returns the translated text for the given key.
+  pub fn get(&self, key: &str) -> Option<&str> { self.keys.get(key).map(|s| s.as_str()) }
+  /// Adds a key to this language file, replacing the existing translation.
+  pub fn insert(&mut self, key: impl Into<String>, text: impl Into<String>) {
+    self.keys.insert(key.into(), text.into());
+  }
+}
+
+/// A piece of translated text.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Part {
+  Text(String),
+  /// The argument at this index.
+  Arg(usize),
+}
+
+/// Splits translated text into text and arguments. `%s` is the next argument,
+/// `%1$s` is the first argument, and `%%` is a percent sign. Anything that
+/// refers to an argument past `args` is left as text.
+pub(crate) fn format(text: &str, args: usize) -> Vec<Part> {
+  let mut parts = vec![];
+  let mut buf = String::new();
+  let mut next = 0;
+  let mut chars = text.char_indices().peekable();
+  while let Some((start, c)) = chars.next() {
+    if c != '%' {
+      buf.push(c);
+      continue;
+    }
+    let rest = &text[start + 1..];
+    let (index, len) = if rest.starts_with('%') {
+      buf.push('%');
+      chars.next();
+      continue;
+    } else if rest.starts_with('s') {
+      next += 1;
+      (next - 1, 1)
+    } else {
+      let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+      match rest[digits..].starts_with("$s").then(|| rest[..digits].parse::<usize>()) {
+        Some(Ok(n)) if n > 0 => (n - 1, digits + 2),
+        _ => {
+          buf.push('%');
+          continue;
+        }
+      }
+    };
+    if index >= args {
+      buf.push_str(&text[start..start + 1 + len]);
+    } else {
+      if !buf.is_empty() {
+        parts.push(Part::Text(std::mem::take(&mut buf)));
+      }
+      parts.push(Part::Arg(index));
+    }
+    for _ in 0..len {
+      chars.next();
+    }
+  }
+  if !buf.is_empty() {
+    parts.push(Part::Text(buf));
+  }
+  parts
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn formats() {
+    assert_eq!(
+      format("<%s> %s", 2),
+      vec![Part::Text("<".into()), Part::Arg(0), Part::Text("> ".into()), Part::Arg(1)]
+    );
+    assert_eq!(
+      format("%2$s hit %1$s", 2),
+      vec![Part::Arg(1), Part::Text(" hit ".into()), Part::Arg(0)]
+    );
+    assert_eq!(
+      format("100%% %s %3$s", 1),
+      vec![Part::Text("100% ".into()), Part::Arg(0), Part::Text(" %3$s".into())]
+    );
+    assert_eq!(format("50% off", 0), vec![Part::Text("50% off".into())]);
+  }
+
+  #[test]
+  fn bundled() {
+    assert_eq!(Lang::bundled().get("death.attack.player"), Some("%1$s was slain by %2$s"));
+    assert_eq!(Lang::bundled().get("not.a.key"), None);
+  }
+}
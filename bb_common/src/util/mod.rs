@@ -1,24 +1,45 @@
 pub mod chat;
+#[cfg(feature = "host")]
+mod json_file;
+pub mod lang;
 mod pool;
+mod trace;
 
 pub use chat::Chat;
+#[cfg(feature = "host")]
+pub use json_file::JsonFile;
 pub use pool::ThreadPool;
+pub use trace::{Trace, TraceLog};
 
 mod buffer;
 mod item;
 
-use crate::math::Pos;
+use crate::{math::Pos, net::protocol::ProtocolInfo};
 use bb_macros::Transfer;
 #[cfg(feature = "host")]
 use rand::{rngs::OsRng, RngCore};
-use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
-use std::{error::Error, fmt, io::Write, num::ParseIntError, str::FromStr};
+use serde::{
+  de::{self, Deserialize, Deserializer, Unexpected, Visitor},
+  Serialize, Serializer,
+};
+use std::{error::Error, fmt, io::Write, net::SocketAddr, num::ParseIntError, str::FromStr};
 
 pub use buffer::{Buffer, BufferError, BufferErrorKind, Mode};
-pub use item::{Item, ItemData, ItemDisplay};
+pub use item::{AttributeModifier, AttributeOperation, AttributeSlot, Item, ItemData, ItemDisplay};
 
 pub use num_cpus::get as num_cpus;
 
+/// Formats a number of bytes, using the largest unit that keeps the number
+/// above 1, like `512 B` or `3.2 MiB`.
+pub fn fmt_bytes(bytes: u64) -> String {
+  match bytes {
+    0..=1023 => format!("{bytes} B"),
+    1024..=1048575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+    1048576..=1073741823 => format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0)),
+    _ => format!("{:.1} GiB", bytes as f64 / (1024.0 * 1024.0 * 1024.0)),
+  }
+}
+
 pub fn serialize_varint(v: i32) -> Vec<u8> {
   // Need to work with u32, as >> acts differently on i32 vs u32.
   let mut val = v as u32;
@@ -64,17 +85,18 @@ pub fn read_varint(buf: &[u8]) -> (i32, isize) {
 }
 
 use crate::nbt::NBT;
-use bb_transfer::{MessageRead, MessageReader, MessageWrite, MessageWriter, ReadError, WriteError};
+use bb_transfer::{
+  MessageRead, MessageReader, MessageWrite, MessageWriter, ReadError, Uuid, WriteError,
+};
 
 impl MessageRead<'_> for UUID {
   fn read(m: &mut MessageReader) -> Result<Self, ReadError> {
-    Ok(UUID::from_le_bytes(m.read_bytes()?.try_into().unwrap()))
+    Ok(UUID::from_u128(m.read::<Uuid>()?.0))
   }
 }
 impl MessageWrite for UUID {
   fn write<W: Write>(&self, m: &mut MessageWriter<W>) -> Result<(), WriteError> {
-    m.write_bytes(&self.as_le_bytes())?;
-    Ok(())
+    m.write(&Uuid(self.0))
   }
 }
 
@@ -319,6 +341,15 @@ impl UUID {
     OsRng.fill_bytes(&mut arr);
     UUID::from_be_bytes(arr)
   }
+  /// Returns the UUID that a vanilla server gives a player with the given
+  /// name in offline mode. This is a version 3 UUID, made from the MD5 hash of
+  /// `OfflinePlayer:<name>`, so it is the same every time the player joins.
+  pub fn offline(name: &str) -> Self {
+    let mut bytes = md5::compute(format!("OfflinePlayer:{name}")).0;
+    bytes[6] = (bytes[6] & 0x0f) | 0x30; // version 3
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // IETF variant
+    UUID::from_be_bytes(bytes)
+  }
   pub fn from_le_bytes(v: [u8; 16]) -> Self { UUID(u128::from_le_bytes(v)) }
   pub fn from_be_bytes(v: [u8; 16]) -> Self { UUID(u128::from_be_bytes(v)) }
   pub fn from_u128(v: u128) -> Self { UUID(v) }
@@ -332,13 +363,13 @@ impl UUID {
   }
   /// Returns the uuid represented as a hex string, with no dashes or other
   /// characters.
-  pub fn as_str(&self) -> String { format!("{:x}", self.0) }
+  pub fn as_str(&self) -> String { format!("{:032x}", self.0) }
   /// Returns the uuid represented as a string with dashes. This is used
   /// sometimes when refering to player in json, and is a useful function to
   /// have.
   pub fn as_dashed_str(&self) -> String {
     format!(
-      "{:x}-{:x}-{:x}-{:x}-{:x}",
+      "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
       //          11111111222233334444555555555555
       (self.0 & 0xffffffff000000000000000000000000) >> (24 * 4), // 4 bits per digit
       (self.0 & 0x00000000ffff00000000000000000000) >> (20 * 4),
@@ -392,23 +423,77 @@ impl<'de> Deserialize<'de> for UUID {
       where
         E: de::Error,
       {
-        UUID::from_str(value).map_err(|_| de::Error::invalid_value(Unexpected::Str(value), &self))
+        let res =
+          if value.len() == 36 { UUID::from_dashed_str(value) } else { UUID::from_str(value) };
+        res.map_err(|_| de::Error::invalid_value(Unexpected::Str(value), &self))
       }
     }
     deserializer.deserialize_str(Inner)
   }
 }
 
+impl Serialize for UUID {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serializer.serialize_str(&self.as_dashed_str())
+  }
+}
+
 #[derive(Transfer, Debug, Clone)]
 pub struct JoinInfo {
   #[must_exist]
-  pub mode:     JoinMode,
+  pub mode:       JoinMode,
   #[must_exist]
-  pub username: String,
+  pub username:   String,
   #[must_exist]
-  pub uuid:     UUID,
+  pub uuid:       UUID,
   #[must_exist]
-  pub ver:      u32,
+  pub ver:        u32,
+  /// The properties from the player's Mojang profile, such as their skin. This
+  /// is empty for offline mode players.
+  pub properties: Vec<ProfileProperty>,
+  /// The address the client connected from. This is used for IP bans.
+  pub addr:       Option<SocketAddr>,
+  /// The mod loader the proxy detected during the handshake.
+  pub client:     ClientKind,
+  /// The ids of the mods the client has installed. This is only known for
+  /// Forge clients, and only if the proxy has `forge-mod-list` enabled.
+  pub mods:       Vec<String>,
+  /// The internal protocol versions the proxy supports. This is the default
+  /// for proxies from before versions were negotiated.
+  pub protocol:   ProtocolInfo,
+}
+
+/// The mod loader a client is running.
+#[derive(Transfer, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ClientKind {
+  /// An unmodded client, or a client that didn't tell us about its mods.
+  #[id = 0]
+  #[default]
+  Vanilla,
+  /// A Forge client. This is detected from the `FML` marker that Forge adds
+  /// to the address in the handshake.
+  #[id = 1]
+  Forge,
+  /// A Fabric client. This is detected from the client's brand, so it isn't
+  /// known until after the client joins.
+  #[id = 2]
+  Fabric,
+}
+
+/// A property from a player's Mojang profile. The only property Mojang sends
+/// is `textures`, which contains the player's skin and cape.
+#[derive(Transfer, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProfileProperty {
+  /// The name of this property, such as `textures`.
+  pub name:      String,
+  /// The base64 encoded value.
+  pub value:     String,
+  /// The base64 encoded signature, signed by Mojang. Clients will ignore
+  /// textures without a valid signature.
+  pub signature: Option<String>,
 }
 
 #[derive(Transfer, Debug, Clone)]
@@ -417,6 +502,12 @@ pub enum JoinMode {
   New,
   #[id = 1]
   Switch(SwitchMode),
+  /// Sent when the proxy reconnects after the connection to the server
+  /// dropped. If the server is still holding onto the player, they carry on
+  /// where they left off. Otherwise, this is the same as
+  /// [`SwitchMode::Loading`].
+  #[id = 2]
+  Resume,
 }
 
 #[derive(Default, Transfer, Debug, Clone, Copy, PartialEq)]
@@ -440,5 +531,23 @@ mod tests {
   pub fn uuid_dashed_str() {
     let uuid = UUID::from_u128(0x11111111222233334444555555555555);
     assert_eq!(uuid.as_dashed_str(), "11111111-2222-3333-4444-555555555555");
+    let uuid = UUID::from_u128(0x1);
+    assert_eq!(uuid.as_dashed_str(), "00000000-0000-0000-0000-000000000001");
+    assert_eq!(UUID::from_dashed_str(&uuid.as_dashed_str()).unwrap(), uuid);
+  }
+
+  #[test]
+  pub fn uuid_offline() {
+    // This is the UUID a vanilla server gives `Notch` in offline mode.
+    assert_eq!(UUID::offline("Notch").as_dashed_str(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+    assert_ne!(UUID::offline("Notch"), UUID::offline("notch"));
+  }
+
+  #[test]
+  pub fn bytes() {
+    assert_eq!(fmt_bytes(512), "512 B");
+    assert_eq!(fmt_bytes(1536), "1.5 KiB");
+    assert_eq!(fmt_bytes(3 * 1024 * 1024), "3.0 MiB");
+    assert_eq!(fmt_bytes(2 * 1024 * 1024 * 1024), "2.0 GiB");
   }
 }
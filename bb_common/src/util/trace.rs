@@ -0,0 +1,112 @@
+use std::{
+  collections::VecDeque,
+  fmt,
+  time::{Duration, Instant},
+};
+
+/// A single traced packet. This stores the time the packet was received, and
+/// a list of named spans. Each span is the time since the packet was received,
+/// so the spans will always be in increasing order.
+#[derive(Debug, Clone)]
+pub struct Trace {
+  pub id:      u32,
+  /// The name of the packet that started this trace.
+  pub packet:  String,
+  pub started: Instant,
+  pub spans:   Vec<(String, Duration)>,
+}
+
+/// A list of recent traces. Any traces older than the window passed to
+/// [`new`](Self::new) are removed whenever a new trace is added.
+#[derive(Debug, Clone)]
+pub struct TraceLog {
+  window: Duration,
+  traces: VecDeque<Trace>,
+}
+
+/// The most traces a [`TraceLog`] will store, regardless of the window. This
+/// prevents the log from growing too large if a client sends a lot of packets.
+const MAX_TRACES: usize = 1024;
+
+impl Trace {
+  pub fn new(id: u32, packet: impl Into<String>) -> Self {
+    Trace { id, packet: packet.into(), started: Instant::now(), spans: vec![] }
+  }
+
+  /// Adds a span with the given name, which ends now.
+  pub fn span(&mut self, name: impl Into<String>) {
+    self.spans.push((name.into(), self.started.elapsed()));
+  }
+  /// Adds a span with the given name and duration (since the start of this
+  /// trace). This is used for spans recorded somewhere else, like the server.
+  pub fn span_at(&mut self, name: impl Into<String>, elapsed: Duration) {
+    self.spans.push((name.into(), elapsed));
+  }
+
+  /// Returns the time between the start of this trace and the last span.
+  pub fn total(&self) -> Duration { self.spans.last().map(|(_, d)| *d).unwrap_or_default() }
+}
+
+impl fmt::Display for Trace {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "#{} {} ({:?})", self.id, self.packet, self.total())?;
+    for (name, elapsed) in &self.spans {
+      write!(f, " {name}: {elapsed:?}")?;
+    }
+    Ok(())
+  }
+}
+
+impl TraceLog {
+  /// Creates an empty log, which will keep traces for the given duration.
+  pub fn new(window: Duration) -> Self { TraceLog { window, traces: VecDeque::new() } }
+
+  /// Adds a finished trace to the log, and removes any old traces.
+  pub fn push(&mut self, trace: Trace) {
+    while let Some(first) = self.traces.front() {
+      if first.started.elapsed() > self.window || self.traces.len() >= MAX_TRACES {
+        self.traces.pop_front();
+      } else {
+        break;
+      }
+    }
+    self.traces.push_back(trace);
+  }
+
+  /// Returns all the traces within the window, oldest first.
+  pub fn recent(&self) -> impl Iterator<Item = &Trace> {
+    self.traces.iter().filter(|t| t.started.elapsed() <= self.window)
+  }
+
+  /// Returns the `n` slowest traces within the window, slowest first.
+  pub fn slowest(&self, n: usize) -> Vec<&Trace> {
+    let mut traces: Vec<_> = self.recent().collect();
+    traces.sort_by_key(|t| std::cmp::Reverse(t.total()));
+    traces.truncate(n);
+    traces
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn log_window() {
+    let mut log = TraceLog::new(Duration::from_secs(60));
+    for id in 0..MAX_TRACES as u32 + 10 {
+      let mut trace = Trace::new(id, "KeepAlive");
+      trace.span_at("handle", Duration::from_millis(id as u64));
+      log.push(trace);
+    }
+    assert_eq!(log.recent().count(), MAX_TRACES);
+    let slowest = log.slowest(2);
+    assert_eq!(slowest[0].id, MAX_TRACES as u32 + 9);
+    assert_eq!(slowest[1].id, MAX_TRACES as u32 + 8);
+
+    let mut log = TraceLog::new(Duration::ZERO);
+    log.push(Trace::new(0, "KeepAlive"));
+    std::thread::sleep(Duration::from_millis(1));
+    assert_eq!(log.recent().count(), 0);
+  }
+}
@@ -0,0 +1,205 @@
+//! The whitelist, and maintenance mode. Like the [ban list](crate::ban), this
+//! is stored in a json file that is shared between the proxy and the server.
+//!
+//! When the whitelist is enabled, only players on it can join. Maintenance
+//! mode is similar, but it applies even when the whitelist is disabled, kicks
+//! everyone who isn't on the whitelist, and changes the server list message.
+
+use crate::util::{
+  chat::{Chat, Color},
+  JsonFile, UUID,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{io, path::Path};
+
+/// A player on the whitelist. Players can be added by name before they have
+/// joined, in which case their UUID is filled in the first time they join.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WhitelistEntry {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub uuid: Option<UUID>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct WhitelistData {
+  enabled:             bool,
+  maintenance:         bool,
+  /// Shown in the server list during maintenance.
+  maintenance_motd:    String,
+  /// Shown to anyone who is kicked, or can't join, during maintenance.
+  maintenance_message: String,
+  players:             Vec<WhitelistEntry>,
+}
+
+impl Default for WhitelistData {
+  fn default() -> Self {
+    WhitelistData {
+      enabled:             false,
+      maintenance:         false,
+      maintenance_motd:    "Down for maintenance".into(),
+      maintenance_message: "The server is down for maintenance. Please try again later.".into(),
+      players:             vec![],
+    }
+  }
+}
+
+impl WhitelistEntry {
+  /// Returns `true` if this entry is for the given player. If this entry has a
+  /// UUID, only the UUID is checked. Otherwise, the name is checked.
+  pub fn matches(&self, id: UUID, name: &str) -> bool {
+    match (self.uuid, &self.name) {
+      (Some(uuid), _) => uuid == id,
+      (None, Some(n)) => n.eq_ignore_ascii_case(name),
+      (None, None) => false,
+    }
+  }
+
+  /// Returns the name of this player, or their UUID if the name isn't known.
+  pub fn display_name(&self) -> String {
+    match (&self.name, self.uuid) {
+      (Some(name), _) => name.clone(),
+      (None, Some(id)) => id.as_dashed_str(),
+      (None, None) => "unknown".into(),
+    }
+  }
+}
+
+/// The whitelist, and the maintenance mode settings.
+#[derive(Debug)]
+pub struct Whitelist {
+  file: JsonFile<WhitelistData>,
+}
+
+impl Whitelist {
+  /// Creates an empty whitelist, which will be saved to `path`.
+  pub fn new(path: impl AsRef<Path>) -> Self { Whitelist { file: JsonFile::new(path) } }
+  /// Loads the whitelist at the given path. If the file doesn't exist, the
+  /// whitelist will be empty and disabled.
+  pub fn load(path: impl AsRef<Path>) -> Self { Whitelist { file: JsonFile::load(path) } }
+  /// Reads the file again, if it has changed since it was last read or
+  /// written.
+  pub fn reload_if_changed(&mut self) { self.file.reload_if_changed(); }
+  /// Writes the whitelist to disk.
+  pub fn save(&mut self) -> io::Result<()> { self.file.save() }
+
+  pub fn enabled(&self) -> bool { self.file.enabled }
+  pub fn set_enabled(&mut self, enabled: bool) { self.file.enabled = enabled; }
+
+  pub fn maintenance(&self) -> bool { self.file.maintenance }
+  /// Turns maintenance mode on or off. If `message` is set, it will replace
+  /// the message shown to players who can't join.
+  pub fn set_maintenance(&mut self, maintenance: bool, message: Option<String>) {
+    self.file.maintenance = maintenance;
+    if let Some(message) = message {
+      self.file.maintenance_message = message;
+    }
+  }
+  /// Returns the message shown in the server list, if maintenance mode is on.
+  pub fn maintenance_motd(&self) -> Option<Chat> {
+    if self.file.maintenance {
+      let mut msg = Chat::empty();
+      msg.add(&self.file.maintenance_motd).color(Color::Red);
+      Some(msg)
+    } else {
+      None
+    }
+  }
+
+  /// Returns `true` if the given player is on the whitelist.
+  pub fn contains(&self, id: UUID, name: &str) -> bool {
+    self.file.players.iter().any(|e| e.matches(id, name))
+  }
+  /// Returns every player on the whitelist.
+  pub fn entries(&self) -> &[WhitelistEntry] { &self.file.players }
+
+  /// Adds a player. Returns `false` if they were already on the whitelist.
+  /// This does not save the whitelist.
+  pub fn add(&mut self, entry: WhitelistEntry) -> bool {
+    let exists = self.file.players.iter().any(|e| {
+      (entry.uuid.is_some() && e.uuid == entry.uuid)
+        || matches!((&e.name, &entry.name), (Some(a), Some(b)) if a.eq_ignore_ascii_case(b))
+    });
+    if !exists {
+      self.file.players.push(entry);
+    }
+    !exists
+  }
+  /// Removes the player with the given name or UUID. Returns the entry that was
+  /// removed. This does not save the whitelist.
+  pub fn remove(&mut self, name: &str) -> Option<WhitelistEntry> {
+    let id = UUID::from_dashed_str(name).or_else(|_| name.parse()).ok();
+    let idx = self.file.players.iter().position(|e| {
+      (id.is_some() && e.uuid == id)
+        || e.name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(name))
+    })?;
+    Some(self.file.players.remove(idx))
+  }
+
+  /// Fills in the UUID and name of a player that just joined. Players added by
+  /// name have their UUID stored, so that changing their name doesn't remove
+  /// them from the whitelist. Returns `true` if anything changed.
+  pub fn update(&mut self, id: UUID, name: &str) -> bool {
+    for e in &mut self.file.players {
+      if e.matches(id, name) {
+        let changed = e.uuid != Some(id) || e.name.as_deref() != Some(name);
+        e.uuid = Some(id);
+        e.name = Some(name.into());
+        return changed;
+      }
+    }
+    false
+  }
+
+  /// Checks if a player can join. Returns the message to kick them with if
+  /// they can't.
+  pub fn check(&self, id: UUID, name: &str) -> Option<Chat> {
+    if self.contains(id, name) {
+      return None;
+    }
+    let mut msg = Chat::empty();
+    if self.file.maintenance {
+      msg.add(&self.file.maintenance_message).color(Color::Red);
+    } else if self.file.enabled {
+      msg.add("You are not whitelisted on this server.").color(Color::Red);
+    } else {
+      return None;
+    }
+    Some(msg)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check() {
+    let id = UUID::from_u128(0x1234);
+    let mut list = Whitelist::new("whitelist.json");
+    assert_eq!(list.check(id, "macmv"), None);
+    list.set_enabled(true);
+    assert!(list.check(id, "macmv").is_some());
+
+    assert!(list.add(WhitelistEntry { uuid: None, name: Some("macmv".into()) }));
+    assert!(!list.add(WhitelistEntry { uuid: None, name: Some("MACMV".into()) }));
+    assert_eq!(list.check(id, "macmv"), None);
+
+    // Once the UUID is known, the name no longer matters.
+    assert!(list.update(id, "macmv"));
+    assert!(!list.update(id, "macmv"));
+    assert_eq!(list.check(id, "someone_else"), None);
+    assert!(list.check(UUID::from_u128(1), "macmv").is_some());
+
+    list.set_enabled(false);
+    list.set_maintenance(true, Some("Back soon".into()));
+    assert_eq!(list.check(id, "macmv"), None);
+    assert_eq!(list.check(UUID::from_u128(1), "other").unwrap().to_plain(), "Back soon");
+
+    assert!(list.remove("00000000-0000-0000-0000-000000001234").is_some());
+    assert!(list.remove("macmv").is_none());
+    assert!(list.check(id, "macmv").is_some());
+  }
+}
@@ -1,11 +1,13 @@
-use super::{cross::cross_version, Block, BlockDef, ItemDrop, Material, Prop, PropKind};
+use super::{
+  cross::cross_version, loot::LootTable, Block, BlockDef, ItemDrop, Material, Prop, PropKind,
+};
 use crate::{
   gen::{CodeGen, ToLit},
   Version,
 };
 use convert_case::{Case, Casing};
 
-use std::{fs, io, path::Path};
+use std::{collections::HashSet, fs, io, path::Path};
 
 #[cfg(test)]
 use super::cross::cross_test;
@@ -17,9 +19,14 @@ pub struct BlockOpts {
   pub kinds:    bool,
 }
 
-pub fn generate(def: Vec<(Version, BlockDef)>, opts: BlockOpts, dir: &Path) -> io::Result<()> {
+pub fn generate(
+  def: Vec<(Version, BlockDef)>,
+  items: &HashSet<String>,
+  opts: BlockOpts,
+  dir: &Path,
+) -> io::Result<()> {
   if opts.data || opts.kinds {
-    fs::write(dir.join("ty.rs"), generate_ty(&def.last().unwrap().1, opts))?;
+    fs::write(dir.join("ty.rs"), generate_ty(&def.last().unwrap().1, items, opts))?;
   }
   if opts.versions {
     fs::write(dir.join("version.rs"), generate_versions(&def))?;
@@ -27,7 +34,7 @@ pub fn generate(def: Vec<(Version, BlockDef)>, opts: BlockOpts, dir: &Path) -> i
   Ok(())
 }
 
-pub fn generate_ty(def: &BlockDef, opts: BlockOpts) -> String {
+pub fn generate_ty(def: &BlockDef, items: &HashSet<String>, opts: BlockOpts) -> String {
   let mut gen = CodeGen::new();
   if opts.kinds {
     gen.write_line("/// Auto generated block kind. This is directly generated");
@@ -151,7 +158,7 @@ pub fn generate_ty(def: &BlockDef, opts: BlockOpts) -> String {
       gen.write_line("(&[");
       gen.add_indent();
       for b in &def.blocks {
-        block_data(gen, b);
+        block_data(gen, b, items);
         gen.write_line(",");
       }
       gen.remove_indent();
@@ -200,7 +207,7 @@ pub fn test(versions: Vec<(Version, BlockDef)>) {
   }
 }
 
-fn block_data(gen: &mut CodeGen, b: &Block) {
+fn block_data(gen: &mut CodeGen, b: &Block, items: &HashSet<String>) {
   macro_rules! write_prop {
     ($name:ident) => {
       gen.write(concat!(stringify!($name), ": "));
@@ -242,6 +249,9 @@ fn block_data(gen: &mut CodeGen, b: &Block) {
   gen.write("drops: ");
   b.drops.to_lit(gen);
   gen.write_line(",");
+  gen.write("loot: ");
+  LootTable::new(b, items).to_lit(gen);
+  gen.write_line(",");
   if b.no_collision {
     gen.write_line("bounding_box: BoundingBoxKind::Empty,");
   } else {
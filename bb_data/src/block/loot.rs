@@ -0,0 +1,184 @@
+//! Builds loot tables for blocks. Vanilla doesn't give us loot tables directly,
+//! so this uses the block tags (things like `mineable/pickaxe` and
+//! `needs_iron_tool`) along with the item list to figure out what tool is
+//! needed, what silk touch does, and how fortune affects the drops.
+
+use super::{Block, Material};
+use crate::gen::{CodeGen, ToLit};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LootTable {
+  /// The tool used to harvest this block, and the minimum tool level.
+  harvest:       Option<(&'static str, u8)>,
+  /// If set, the block will not drop anything unless it is broken with the
+  /// correct tool.
+  requires_tool: bool,
+  /// The item dropped when this block is broken with silk touch.
+  silk_touch:    Option<String>,
+  /// How fortune changes the drop count.
+  fortune:       Fortune,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fortune {
+  /// Fortune does nothing.
+  None,
+  /// The vanilla ore formula. The drop count is multiplied by a random number
+  /// from 1 to `level + 1`.
+  Ore,
+  /// Adds a random number from 0 to `level` to the drop count, and then caps
+  /// the result at `max`.
+  Uniform { max: i32 },
+}
+
+/// Returns the name of the tag without a leading `#` or `minecraft:`.
+fn tag_name(tag: &str) -> &str {
+  let tag = tag.strip_prefix('#').unwrap_or(tag);
+  tag.strip_prefix("minecraft:").unwrap_or(tag)
+}
+
+impl LootTable {
+  /// Generates the loot table for the given block. `items` must contain all
+  /// the item names in the same version as `b`.
+  pub fn new(b: &Block, items: &HashSet<String>) -> Self {
+    let mut harvest_tool = None;
+    let mut level = 0;
+    for tag in &b.tags {
+      match tag_name(tag) {
+        "mineable/pickaxe" => harvest_tool = Some("Pickaxe"),
+        "mineable/axe" => harvest_tool = Some("Axe"),
+        "mineable/shovel" => harvest_tool = Some("Shovel"),
+        "mineable/hoe" => harvest_tool = Some("Hoe"),
+        "needs_stone_tool" => level = level.max(1),
+        "needs_iron_tool" => level = level.max(2),
+        "needs_diamond_tool" => level = level.max(3),
+        _ => {}
+      }
+    }
+    if b.name == "cobweb" {
+      harvest_tool = Some("Sword");
+    }
+    let requires_tool = level > 0
+      || harvest_tool == Some("Pickaxe")
+      || b.name == "cobweb"
+      || matches!(b.material, Material::Stone | Material::Metal | Material::SnowBlock);
+
+    let drops_self = b.drops.len() == 1 && b.drops[0].item == b.name;
+    let silk_touch = if !items.contains(&b.name) || drops_self {
+      None
+    } else if !b.drops.is_empty()
+      || matches!(b.material, Material::Glass | Material::Ice | Material::DenseIce)
+    {
+      Some(b.name.clone())
+    } else {
+      None
+    };
+
+    let is_ore = b.name.ends_with("_ore") || b.tags.iter().any(|t| tag_name(t).ends_with("_ores"));
+    let fortune = match b.name.as_str() {
+      "glowstone" => Fortune::Uniform { max: 4 },
+      "sea_lantern" => Fortune::Uniform { max: 5 },
+      "melon" => Fortune::Uniform { max: 9 },
+      _ if is_ore && !drops_self && !b.drops.is_empty() => Fortune::Ore,
+      _ => Fortune::None,
+    };
+
+    LootTable {
+      harvest: harvest_tool.map(|tool| (tool, level)),
+      requires_tool,
+      silk_touch,
+      fortune,
+    }
+  }
+}
+
+impl ToLit for LootTable {
+  fn to_lit(&self, gen: &mut CodeGen) {
+    gen.write_line("LootTable {");
+    gen.add_indent();
+
+    gen.write("harvest: ");
+    match self.harvest {
+      Some((tool, level)) => {
+        gen.write("Some(Harvest { tool: ToolType::");
+        gen.write(tool);
+        gen.write(", level: ");
+        level.to_lit(gen);
+        gen.write(" })");
+      }
+      None => gen.write("None"),
+    }
+    gen.write_line(",");
+
+    gen.write("requires_tool: ");
+    gen.write(&self.requires_tool.to_string());
+    gen.write_line(",");
+
+    gen.write("silk_touch: ");
+    match &self.silk_touch {
+      Some(item) => {
+        gen.write("Some(");
+        item.to_lit(gen);
+        gen.write(")");
+      }
+      None => gen.write("None"),
+    }
+    gen.write_line(",");
+
+    gen.write("fortune: ");
+    match self.fortune {
+      Fortune::None => gen.write("Fortune::None"),
+      Fortune::Ore => gen.write("Fortune::Ore"),
+      Fortune::Uniform { max } => {
+        gen.write("Fortune::Uniform { max: ");
+        max.to_lit(gen);
+        gen.write(" }");
+      }
+    }
+    gen.write_line(",");
+
+    gen.remove_indent();
+    gen.write("}");
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::block::ItemDrop;
+
+  fn drop(item: &str) -> ItemDrop { ItemDrop { item: item.into(), min: 1, max: 1 } }
+
+  #[test]
+  fn ore_loot() {
+    let items = ["diamond_ore", "diamond"].iter().map(|s| s.to_string()).collect();
+    let b = Block {
+      name: "diamond_ore".into(),
+      drops: vec![drop("diamond")],
+      tags: vec!["#mineable/pickaxe".into(), "#needs_iron_tool".into()],
+      ..Default::default()
+    };
+    let loot = LootTable::new(&b, &items);
+    assert_eq!(loot.harvest, Some(("Pickaxe", 2)));
+    assert!(loot.requires_tool);
+    assert_eq!(loot.silk_touch, Some("diamond_ore".into()));
+    assert_eq!(loot.fortune, Fortune::Ore);
+  }
+
+  #[test]
+  fn self_drop_loot() {
+    let items = ["dirt"].iter().map(|s| s.to_string()).collect();
+    let b = Block {
+      name: "dirt".into(),
+      drops: vec![drop("dirt")],
+      tags: vec!["mineable/shovel".into()],
+      ..Default::default()
+    };
+    let loot = LootTable::new(&b, &items);
+    assert_eq!(loot.harvest, Some(("Shovel", 0)));
+    assert!(!loot.requires_tool);
+    assert_eq!(loot.silk_touch, None);
+    assert_eq!(loot.fortune, Fortune::None);
+  }
+}
@@ -1,9 +1,10 @@
 use crate::Collector;
 use serde::{de, de::Visitor, Deserialize, Deserializer};
-use std::{fmt, fs, io};
+use std::{collections::HashSet, fmt, fs, io};
 
 mod cross;
 mod gen;
+mod loot;
 
 pub use gen::BlockOpts;
 
@@ -19,7 +20,9 @@ pub fn generate(c: &Collector, opts: BlockOpts) -> io::Result<()> {
       (ver, def)
     })
     .collect();
-  gen::generate(versions, opts, &c.out.join("block"))?;
+  let latest: crate::item::ItemDef = c.dl.get("items", *crate::VERSIONS.last().unwrap());
+  let items: HashSet<String> = latest.names().collect();
+  gen::generate(versions, &items, opts, &c.out.join("block"))?;
   Ok(())
 }
 
@@ -44,6 +47,13 @@ pub struct BlockDef {
   blocks: Vec<Block>,
 }
 
+impl BlockDef {
+  /// Returns the names of all the blocks in this version, in registry order.
+  pub fn names(&self) -> impl Iterator<Item = String> + '_ {
+    self.blocks.iter().map(|b| b.name.clone())
+  }
+}
+
 /// A block in any version. This has `#[allow(dead_code)]` because this is a
 /// 1-to-1 with the json data, and I don't want to forget about information
 /// included in the json.
@@ -0,0 +1,135 @@
+//! A content-addressed cache for downloaded data. Downloads are stored under
+//! the sha256 hash of their contents, and an index maps each url to the hash
+//! of the last download from that url.
+//!
+//! The cache lives outside of the `target` directory, so that clean builds
+//! don't need to download everything again.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+  collections::HashMap,
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+/// The last download from a url.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+  /// The hash of the downloaded data.
+  pub hash: String,
+  /// The `ETag` header of the response, if there was one. This is used to
+  /// skip downloads when the remote data hasn't changed.
+  pub etag: Option<String>,
+}
+
+pub struct Cache {
+  dir: PathBuf,
+}
+
+impl Cache {
+  /// Opens the cache. The directory can be set with `BB_DATA_CACHE`, and
+  /// otherwise defaults to `bamboo-data` in the user's cache directory.
+  pub fn new() -> Self {
+    println!("cargo:rerun-if-env-changed=BB_DATA_CACHE");
+    let dir = if let Some(dir) = std::env::var_os("BB_DATA_CACHE") {
+      PathBuf::from(dir)
+    } else if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+      PathBuf::from(dir).join("bamboo-data")
+    } else if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))
+    {
+      PathBuf::from(home).join(".cache").join("bamboo-data")
+    } else {
+      // Our current directory is the crate being built, so this is the
+      // workspace target directory.
+      PathBuf::from("../target/bamboo-data")
+    };
+    Cache { dir }
+  }
+
+  fn index_path(&self) -> PathBuf { self.dir.join("index.json") }
+  fn object_path(&self, hash: &str) -> PathBuf { self.dir.join("objects").join(hash) }
+
+  fn index(&self) -> HashMap<String, Entry> {
+    // A missing or broken index just means we need to download again.
+    fs::read(self.index_path())
+      .ok()
+      .and_then(|data| serde_json::from_slice(&data).ok())
+      .unwrap_or_default()
+  }
+
+  /// Returns the last download from the given url, if the downloaded data is
+  /// still in the cache.
+  pub fn entry(&self, url: &str) -> Option<Entry> {
+    self.index().remove(url).filter(|e| self.object_path(&e.hash).exists())
+  }
+
+  /// Reads the data with the given hash. If the stored data doesn't match the
+  /// hash, this returns an error.
+  pub fn load(&self, hash: &str) -> io::Result<Vec<u8>> {
+    let data = fs::read(self.object_path(hash))?;
+    if self::hash(&data) != hash {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("cached data {hash} is corrupted"),
+      ));
+    }
+    Ok(data)
+  }
+
+  /// Stores the data downloaded from `url`, and returns its entry.
+  pub fn store(&self, url: &str, data: &[u8], etag: Option<String>) -> io::Result<Entry> {
+    let hash = hash(data);
+    let path = self.object_path(&hash);
+    if !path.exists() {
+      fs::create_dir_all(path.parent().unwrap())?;
+      write_atomic(&path, data)?;
+    }
+    let entry = Entry { hash, etag };
+    let mut index = self.index();
+    index.insert(url.into(), entry.clone());
+    write_atomic(&self.index_path(), &serde_json::to_vec_pretty(&index)?)?;
+    Ok(entry)
+  }
+}
+
+/// Multiple crates run their build scripts at the same time, so we write to a
+/// temporary file first, to make sure no one reads a partially written file.
+fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+  let tmp = path.with_extension(format!("tmp-{}", std::process::id()));
+  fs::write(&tmp, data)?;
+  fs::rename(&tmp, path)
+}
+
+/// Returns the sha256 hash of the given data, as a hex string.
+pub fn hash(data: &[u8]) -> String { hex(&Sha256::digest(data)) }
+
+fn hex(bytes: &[u8]) -> String {
+  use std::fmt::Write;
+  bytes.iter().fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+    write!(s, "{b:02x}").unwrap();
+    s
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn store_and_load() {
+    let dir = std::env::temp_dir().join(format!("bb-data-cache-test-{}", std::process::id()));
+    let cache = Cache { dir: dir.clone() };
+    assert!(cache.entry("https://example.com").is_none());
+    let entry = cache.store("https://example.com", b"hello", Some("abc".into())).unwrap();
+    assert_eq!(entry.hash, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+    let found = cache.entry("https://example.com").unwrap();
+    assert_eq!(found.hash, entry.hash);
+    assert_eq!(found.etag.as_deref(), Some("abc"));
+    assert_eq!(cache.load(&entry.hash).unwrap(), b"hello");
+
+    fs::write(cache.object_path(&entry.hash), b"goodbye").unwrap();
+    assert!(cache.load(&entry.hash).is_err());
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
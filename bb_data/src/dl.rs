@@ -1,6 +1,6 @@
-use crate::Version;
+use crate::{cache, cache::Cache, overrides::Overrides, Version};
 use serde::{de::DeserializeOwned, Deserialize};
-use std::{collections::HashMap, fs, fs::File, io, io::Read, path::Path};
+use std::{cell::OnceCell, collections::HashMap, fs, fs::File, io, io::Read, path::Path};
 
 #[derive(Deserialize)]
 #[cfg_attr(test, allow(dead_code))]
@@ -9,8 +9,17 @@ struct Config {
 
   url:  Option<String>,
   path: Option<String>,
+
+  #[serde(default)]
+  offline: bool,
+
+  /// The directory containing override files, relative to the workspace.
+  #[serde(default = "default_overrides")]
+  overrides: String,
 }
 
+fn default_overrides() -> String { "data-overrides".into() }
+
 #[derive(Deserialize)]
 #[cfg_attr(test, allow(dead_code))]
 enum SourcesMode {
@@ -22,7 +31,13 @@ enum SourcesMode {
 
 #[cfg_attr(test, allow(dead_code))]
 pub struct Downloader {
-  files: HashMap<String, HashMap<String, serde_json::Value>>,
+  /// The sha256 hash of the compressed data and the overrides.
+  hash:       String,
+  compressed: Vec<u8>,
+  overrides:  Overrides,
+  /// The decompressed data. This is only parsed once a generator needs it, so
+  /// that we don't need to parse anything if every generator is up to date.
+  files:      OnceCell<HashMap<String, HashMap<String, serde_json::Value>>>,
 }
 
 impl Downloader {
@@ -36,56 +51,124 @@ impl Downloader {
     } else {
       toml::from_str(&fs::read_to_string(data_example_path).unwrap()).unwrap()
     };
-    let mut buf = vec![];
-    match config.sources {
+    let compressed = match config.sources {
       SourcesMode::Remote => {
         let url = config.url.as_ref().expect("url must be present for remote bamboo-data");
         let url = format!("{url}/all-releases.json.gz");
-
-        let data = ureq::get(&url).call().unwrap();
-
-        io::copy(&mut data.into_reader(), &mut buf)
-          .unwrap_or_else(|e| panic!("could not download bamboo-data json: {e}"));
+        download(&Cache::new(), &url, config.offline || offline_env())
       }
       SourcesMode::Local => {
         let path = config.path.as_ref().expect("path must be present for local bamboo-data");
         let path = Path::new("../").join(path).join("all-releases.json.gz");
 
         let mut data = File::open(&path).unwrap();
-
+        let mut buf = vec![];
         io::copy(&mut data, &mut buf)
           .unwrap_or_else(|e| panic!("could not copy bamboo-data json: {e}"));
+        buf
       }
     };
 
-    let mut reader = flate2::read::GzDecoder::new(&*buf);
-    let mut uncompressed = vec![];
-    reader.read_to_end(&mut uncompressed).expect("could not decompress bamboo-data json");
+    let overrides_dir = Path::new("../").join(&config.overrides);
+    println!("cargo:rerun-if-changed={}", overrides_dir.display());
+    let overrides = Overrides::load(&overrides_dir)
+      .unwrap_or_else(|e| panic!("could not load data overrides: {e}"));
 
-    Downloader { files: serde_json::from_reader(&*uncompressed).unwrap() }
+    let hash =
+      cache::hash(format!("{}\n{}", cache::hash(&compressed), overrides.fingerprint()).as_bytes());
+    Downloader { hash, compressed, overrides, files: OnceCell::new() }
+  }
+
+  /// Returns the hash of the data, including overrides. If this hasn't changed,
+  /// then the generated code won't change either.
+  pub fn hash(&self) -> &str { &self.hash }
+
+  fn files(&self) -> &HashMap<String, HashMap<String, serde_json::Value>> {
+    self.files.get_or_init(|| {
+      let mut reader = flate2::read::GzDecoder::new(&*self.compressed);
+      let mut uncompressed = vec![];
+      reader.read_to_end(&mut uncompressed).expect("could not decompress bamboo-data json");
+      serde_json::from_reader(&*uncompressed).unwrap()
+    })
   }
 
   #[track_caller]
   pub fn get<T: DeserializeOwned>(&self, name: &str, ver: Version) -> T {
-    /*
-    let cache_dir = self.out.join("data");
-    if !cache_dir.exists() {
-      fs::create_dir_all(&cache_dir)
-        .unwrap_or_else(|e| panic!("could not create cache dir {}: {}", cache_dir.display(), e));
+    let mut value = self.files()[&ver.to_string()][name].clone();
+    self.overrides.apply(name, ver, &mut value);
+    serde_json::from_value(value).unwrap_or_else(|e| panic!("invalid {name} data for {ver}: {e}"))
+  }
+}
+
+/// Returns `true` if offline mode is enabled through the environment. This
+/// checks `BB_DATA_OFFLINE`, and `CARGO_NET_OFFLINE`, which is what cargo
+/// itself reads for `--offline`.
+fn offline_env() -> bool {
+  println!("cargo:rerun-if-env-changed=BB_DATA_OFFLINE");
+  println!("cargo:rerun-if-env-changed=CARGO_NET_OFFLINE");
+  ["BB_DATA_OFFLINE", "CARGO_NET_OFFLINE"]
+    .iter()
+    .any(|var| std::env::var(var).is_ok_and(|v| v == "1" || v == "true"))
+}
+
+/// Downloads the given url, using the cache if possible. If the remote data
+/// hasn't changed since the last download, the cached copy is used.
+///
+/// In offline mode, or if the download fails, this falls back to the cached
+/// data. This only panics if there is nothing cached.
+fn download(cache: &Cache, url: &str, offline: bool) -> Vec<u8> {
+  let cached = cache.entry(url);
+  let load_cached = |reason: &str| {
+    let Some(entry) = &cached else {
+      panic!(
+        "{reason}, and bamboo-data has not been downloaded before. Build once with network \
+         access, or set `sources = \"local\"` in `data-config.toml`"
+      );
+    };
+    cache.load(&entry.hash).unwrap_or_else(|e| panic!("{reason}, and the cache is unusable: {e}"))
+  };
+  if offline {
+    return load_cached("offline mode is enabled");
+  }
+
+  let mut req = ureq::get(url);
+  if let Some(etag) = cached.as_ref().and_then(|e| e.etag.as_ref()) {
+    req = req.set("If-None-Match", etag);
+  }
+  let res = match req.call() {
+    Ok(res) => res,
+    Err(e) => {
+      if cached.is_some() {
+        println!("cargo:warning=could not download bamboo-data, using cached data: {e}");
+      }
+      return load_cached(&format!("could not download bamboo-data: {e}"));
     }
-    let p = cache_dir.join(format!("{}-{}.json", name, ver));
-    if p.exists() {
-      println!("file {} already exists, skipping download", p.display());
-      let f =
-        File::open(&p).unwrap_or_else(|e| panic!("cannot open file at {}: {}", p.display(), e));
-      if let Ok(res) = serde_json::from_reader(f) {
-        return res;
-      } else {
-        println!("file {} has invalid json, redownloading", p.display());
+  };
+  if res.status() == 304 {
+    if let Some(entry) = &cached {
+      if let Ok(data) = cache.load(&entry.hash) {
+        return data;
       }
     }
-    */
+    // The server thinks we have the data, but our copy is broken, so download
+    // it again without the `ETag`.
+    return download_fresh(cache, url);
+  }
+  store(cache, url, res)
+}
+
+fn download_fresh(cache: &Cache, url: &str) -> Vec<u8> {
+  let res = ureq::get(url).call().unwrap_or_else(|e| panic!("could not download bamboo-data: {e}"));
+  store(cache, url, res)
+}
 
-    serde_json::from_value(self.files[&ver.to_string()][name].clone()).unwrap()
+fn store(cache: &Cache, url: &str, res: ureq::Response) -> Vec<u8> {
+  let etag = res.header("ETag").map(String::from);
+  let mut buf = vec![];
+  io::copy(&mut res.into_reader(), &mut buf)
+    .unwrap_or_else(|e| panic!("could not download bamboo-data json: {e}"));
+  if let Err(e) = cache.store(url, &buf, etag) {
+    println!("cargo:warning=could not cache bamboo-data: {e}");
   }
+  buf
 }
@@ -0,0 +1,224 @@
+//! Entity attribute data. Attributes are registered in code in vanilla, so
+//! the list of attributes and the base values for each entity are written out
+//! here.
+
+use super::EntityDef;
+use crate::gen::CodeGen;
+use convert_case::{Case, Casing};
+
+/// An attribute: `(name, default, min, max)`.
+type Attr = (&'static str, f64, f64, f64);
+
+const ATTRIBUTES: &[Attr] = &[
+  ("generic.max_health", 20.0, 1.0, 1024.0),
+  ("generic.follow_range", 32.0, 0.0, 2048.0),
+  ("generic.knockback_resistance", 0.0, 0.0, 1.0),
+  ("generic.movement_speed", 0.7, 0.0, 1024.0),
+  ("generic.flying_speed", 0.4, 0.0, 1024.0),
+  ("generic.attack_damage", 2.0, 0.0, 2048.0),
+  ("generic.attack_knockback", 0.0, 0.0, 5.0),
+  ("generic.attack_speed", 4.0, 0.0, 1024.0),
+  ("generic.armor", 0.0, 0.0, 30.0),
+  ("generic.armor_toughness", 0.0, 0.0, 20.0),
+  ("generic.luck", 0.0, -1024.0, 1024.0),
+  ("horse.jump_strength", 0.7, 0.0, 2.0),
+  ("zombie.spawn_reinforcements", 0.0, 0.0, 1.0),
+];
+
+/// Base values that differ from the attribute default, for a single entity.
+type Base = (&'static str, &'static [(&'static str, f64)]);
+
+const ZOMBIE: &[(&str, f64)] = &[
+  ("generic.follow_range", 35.0),
+  ("generic.movement_speed", 0.23),
+  ("generic.attack_damage", 3.0),
+  ("generic.armor", 2.0),
+];
+const SKELETON: &[(&str, f64)] = &[("generic.movement_speed", 0.25)];
+
+const BASE_VALUES: &[Base] = &[
+  (
+    "player",
+    &[
+      ("generic.movement_speed", 0.1),
+      ("generic.attack_damage", 1.0),
+      ("generic.flying_speed", 0.02),
+    ],
+  ),
+  ("zombie", ZOMBIE),
+  ("husk", ZOMBIE),
+  ("drowned", ZOMBIE),
+  ("zombie_villager", ZOMBIE),
+  ("zombified_piglin", ZOMBIE),
+  ("skeleton", SKELETON),
+  ("stray", SKELETON),
+  ("wither_skeleton", &[("generic.movement_speed", 0.25), ("generic.attack_damage", 4.0)]),
+  ("creeper", &[("generic.movement_speed", 0.25)]),
+  ("spider", &[("generic.max_health", 16.0), ("generic.movement_speed", 0.3)]),
+  ("cave_spider", &[("generic.max_health", 12.0), ("generic.movement_speed", 0.3)]),
+  (
+    "enderman",
+    &[
+      ("generic.max_health", 40.0),
+      ("generic.movement_speed", 0.3),
+      ("generic.attack_damage", 7.0),
+      ("generic.follow_range", 64.0),
+    ],
+  ),
+  ("witch", &[("generic.max_health", 26.0), ("generic.movement_speed", 0.25)]),
+  (
+    "iron_golem",
+    &[
+      ("generic.max_health", 100.0),
+      ("generic.movement_speed", 0.25),
+      ("generic.knockback_resistance", 1.0),
+      ("generic.attack_damage", 15.0),
+    ],
+  ),
+  ("villager", &[("generic.movement_speed", 0.5), ("generic.follow_range", 48.0)]),
+  ("pig", &[("generic.max_health", 10.0), ("generic.movement_speed", 0.25)]),
+  ("cow", &[("generic.max_health", 10.0), ("generic.movement_speed", 0.2)]),
+  ("sheep", &[("generic.max_health", 8.0), ("generic.movement_speed", 0.23)]),
+  ("chicken", &[("generic.max_health", 4.0), ("generic.movement_speed", 0.25)]),
+  ("rabbit", &[("generic.max_health", 3.0), ("generic.movement_speed", 0.3)]),
+  (
+    "wolf",
+    &[("generic.max_health", 8.0), ("generic.movement_speed", 0.3), ("generic.attack_damage", 2.0)],
+  ),
+  ("bat", &[("generic.max_health", 6.0)]),
+];
+
+/// Converts an attribute name like `generic.max_health` into a variant name
+/// like `MaxHealth`. The namespace is dropped for `generic` attributes.
+fn variant(name: &str) -> String {
+  match name.split_once('.') {
+    Some(("generic", name)) => name.to_case(Case::Pascal),
+    _ => name.replace('.', "_").to_case(Case::Pascal),
+  }
+}
+
+pub fn generate_attributes(def: &EntityDef) -> String {
+  let mut gen = CodeGen::new();
+  gen.write_line("/// An entity attribute. Attributes store things like max health and");
+  gen.write_line("/// movement speed. They have a base value, which can be changed by");
+  gen.write_line("/// modifiers.");
+  gen.write_line("///");
+  gen.write_line("/// This enum is generated at compile time. See");
+  gen.write_line("/// `data/src/entity/attribute.rs` and `build.rs` for more.");
+  gen.write_line("#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]");
+  gen.write("pub enum Attribute");
+  gen.write_block(|gen| {
+    for (name, ..) in ATTRIBUTES {
+      gen.write(&variant(name));
+      gen.write_line(",");
+    }
+  });
+  gen.write_line("");
+
+  gen.write("impl Attribute");
+  gen.write_block(|gen| {
+    gen.write_line("/// Every attribute, in registry order.");
+    gen.write("pub const ALL: &'static [Attribute] = &[");
+    for (name, ..) in ATTRIBUTES {
+      gen.write("Attribute::");
+      gen.write(&variant(name));
+      gen.write(", ");
+    }
+    gen.write_line("];");
+    gen.write_line("");
+
+    gen.write_line("/// Returns the name of this attribute, like `generic.max_health`.");
+    gen.write("pub fn name(&self) -> &'static str");
+    gen.write_block(|gen| {
+      gen.write("match self");
+      gen.write_block(|gen| {
+        for (name, ..) in ATTRIBUTES {
+          gen.write(&format!("Self::{} => \"{name}\",", variant(name)));
+          gen.write_line("");
+        }
+      });
+    });
+    gen.write_line("/// Parses an attribute name, like `generic.max_health`.");
+    gen.write("pub fn from_name(name: &str) -> Option<Self>");
+    gen.write_block(|gen| {
+      gen.write("Some(match name");
+      gen.write_block(|gen| {
+        for (name, ..) in ATTRIBUTES {
+          gen.write(&format!("\"{name}\" => Self::{},", variant(name)));
+          gen.write_line("");
+        }
+        gen.write_line("_ => return None,");
+      });
+      gen.write_line(")");
+    });
+    for (func, doc, idx) in [
+      ("default_value", "the value used when an entity has no base value set", 1),
+      ("min", "the smallest value this attribute can have", 2),
+      ("max", "the largest value this attribute can have", 3),
+    ] {
+      gen.write_line(&format!("/// Returns {doc}."));
+      gen.write(&format!("pub fn {func}(&self) -> f64"));
+      gen.write_block(|gen| {
+        gen.write("match self");
+        gen.write_block(|gen| {
+          for attr in ATTRIBUTES {
+            let value = match idx {
+              1 => attr.1,
+              2 => attr.2,
+              _ => attr.3,
+            };
+            gen.write(&format!("Self::{} => {value:?},", variant(attr.0)));
+            gen.write_line("");
+          }
+        });
+      });
+    }
+  });
+  gen.write_line("");
+
+  gen.write_line("/// Returns the base values for the given entity. Any attribute not in");
+  gen.write_line("/// this list should use [`Attribute::default_value`].");
+  gen.write("pub fn base_attributes(ty: Type) -> &'static [(Attribute, f64)]");
+  gen.write_block(|gen| {
+    gen.write("match ty");
+    gen.write_block(|gen| {
+      for (entity, values) in BASE_VALUES {
+        // Older data may be missing some entities, so we just skip them.
+        if def.entity_map.get(*entity).is_none() {
+          continue;
+        }
+        gen.write("Type::");
+        gen.write(&entity.to_case(Case::Pascal));
+        gen.write(" => &[");
+        for (name, value) in values.iter() {
+          gen.write(&format!("(Attribute::{}, {value:?}), ", variant(name)));
+        }
+        gen.write_line("],");
+      }
+      gen.write_line("_ => &[],");
+    });
+  });
+  gen.into_output()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn variants() {
+    assert_eq!(variant("generic.max_health"), "MaxHealth");
+    assert_eq!(variant("horse.jump_strength"), "HorseJumpStrength");
+    assert_eq!(variant("zombie.spawn_reinforcements"), "ZombieSpawnReinforcements");
+  }
+
+  #[test]
+  fn base_values_are_valid() {
+    for (_, values) in BASE_VALUES {
+      for (name, value) in values.iter() {
+        let attr = ATTRIBUTES.iter().find(|a| a.0 == *name).unwrap();
+        assert!(*value >= attr.2 && *value <= attr.3, "{name} is out of range");
+      }
+    }
+  }
+}
@@ -1,4 +1,4 @@
-use super::{cross::cross_version, Entity, EntityDef};
+use super::{attribute, cross::cross_version, spawn, Entity, EntityDef};
 use crate::{
   gen::{CodeGen, ToLit},
   Version,
@@ -9,6 +9,8 @@ use std::{fs, io, path::Path};
 pub fn generate(def: Vec<(Version, EntityDef)>, dir: &Path) -> io::Result<()> {
   fs::write(dir.join("ty.rs"), generate_ty(&def.last().unwrap().1))?;
   fs::write(dir.join("version.rs"), generate_versions(&def))?;
+  fs::write(dir.join("spawn.rs"), spawn::generate_spawns(&def.last().unwrap().1))?;
+  fs::write(dir.join("attribute.rs"), attribute::generate_attributes(&def.last().unwrap().1))?;
   Ok(())
 }
 
@@ -134,6 +136,10 @@ fn entity_data(gen: &mut CodeGen, b: &Entity) {
   write_prop!(width);
   write_prop!(height);
 
+  gen.write("category: Category::");
+  gen.write(spawn::category(&b.category));
+  gen.write_line(",");
+
   gen.remove_indent();
   gen.write("}");
 }
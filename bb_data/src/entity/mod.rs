@@ -3,8 +3,10 @@ use convert_case::{Case, Casing};
 use serde::Deserialize;
 use std::{collections::HashMap, fs, io};
 
+mod attribute;
 mod cross;
 mod gen;
+mod spawn;
 
 pub fn generate(c: &Collector) -> io::Result<()> {
   fs::create_dir_all(c.out.join("entity"))?;
@@ -27,6 +29,15 @@ pub struct EntityDef {
   entity_map: HashMap<String, usize>,
 }
 
+impl EntityDef {
+  /// Returns the names of all the entities in this version, in registry order.
+  /// Missing entities are returned as an empty string, so that the index of
+  /// each name is still the entity id.
+  pub fn names(&self) -> impl Iterator<Item = String> + '_ {
+    self.entities.iter().map(|e| e.as_ref().map(|e| e.name.clone()).unwrap_or_default())
+  }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Default, Deserialize)]
 pub struct Entity {
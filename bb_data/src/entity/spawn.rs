@@ -0,0 +1,131 @@
+//! Natural spawning data. The vanilla spawn lists live in the datapack, which
+//! we don't download, so the lists for the biomes that bamboo generates are
+//! written out here.
+
+use super::EntityDef;
+use crate::gen::CodeGen;
+use convert_case::{Case, Casing};
+
+/// A single entry in a biome's spawn list: `(entity, weight, min, max)`.
+type Entry = (&'static str, u32, u32, u32);
+
+/// Spawns shared by most overworld biomes.
+const MONSTERS: &[Entry] = &[
+  ("spider", 100, 4, 4),
+  ("zombie", 95, 4, 4),
+  ("zombie_villager", 5, 1, 1),
+  ("skeleton", 100, 4, 4),
+  ("creeper", 100, 4, 4),
+  ("slime", 100, 4, 4),
+  ("enderman", 10, 1, 4),
+  ("witch", 5, 1, 1),
+];
+const FARM_ANIMALS: &[Entry] =
+  &[("sheep", 12, 4, 4), ("pig", 10, 4, 4), ("chicken", 10, 4, 4), ("cow", 8, 4, 4)];
+const AMBIENT: &[Entry] = &[("bat", 10, 8, 8)];
+
+/// Returns the spawn list for the given biome. Unknown biomes use the plains
+/// spawn list.
+fn biome_spawns(biome: &str) -> Vec<Entry> {
+  let mut out = vec![];
+  match biome {
+    "desert" => {
+      out.push(("rabbit", 4, 2, 3));
+      // Deserts replace most zombies with husks.
+      for &(name, weight, min, max) in MONSTERS {
+        match name {
+          "zombie" => out.push((name, 19, min, max)),
+          "zombie_villager" => out.push((name, 1, min, max)),
+          _ => out.push((name, weight, min, max)),
+        }
+      }
+      out.push(("husk", 80, 4, 4));
+    }
+    "forest" => {
+      out.extend(FARM_ANIMALS);
+      out.push(("wolf", 5, 4, 4));
+      out.extend(MONSTERS);
+    }
+    "mountains" => {
+      out.extend(FARM_ANIMALS);
+      out.push(("llama", 5, 4, 6));
+      out.extend(MONSTERS);
+    }
+    _ => {
+      out.extend(FARM_ANIMALS);
+      out.push(("horse", 5, 2, 6));
+      out.push(("donkey", 1, 1, 3));
+      out.extend(MONSTERS);
+    }
+  }
+  out.extend(AMBIENT);
+  out
+}
+
+/// Converts a spawn group name from the data into a `Category` variant.
+pub fn category(name: &str) -> &'static str {
+  match name.to_case(Case::Snake).as_str() {
+    "monster" => "Monster",
+    "creature" => "Creature",
+    "ambient" => "Ambient",
+    "water_creature" | "underground_water_creature" | "axolotls" => "WaterCreature",
+    "water_ambient" => "WaterAmbient",
+    _ => "Misc",
+  }
+}
+
+pub fn generate_spawns(def: &EntityDef) -> String {
+  let mut gen = CodeGen::new();
+  gen.write_line("/// Generates the natural spawn list for every biome that bamboo can");
+  gen.write_line("/// generate. Any biome not in this list should use the `plains` list.");
+  gen.write_line("///");
+  gen.write_line("/// This function is generated at compile time. See");
+  gen.write_line("/// `data/src/entity/spawn.rs` and `build.rs` for more.");
+  gen.write("pub fn generate_spawn_lists() -> &'static [BiomeSpawns]");
+  gen.write_block(|gen| {
+    gen.write_line("&[");
+    gen.add_indent();
+    for biome in ["plains", "desert", "forest", "mountains"] {
+      gen.write("BiomeSpawns { biome: \"");
+      gen.write(biome);
+      gen.write_line("\", spawns: &[");
+      gen.add_indent();
+      for (name, weight, min, max) in biome_spawns(biome) {
+        // Older data may be missing some entities, so we just skip them.
+        if def.entity_map.get(name).is_none() {
+          continue;
+        }
+        gen.write("SpawnEntry { ty: Type::");
+        gen.write(&name.to_case(Case::Pascal));
+        gen.write(&format!(", weight: {weight}, min: {min}, max: {max} }},"));
+        gen.write_line("");
+      }
+      gen.remove_indent();
+      gen.write_line("] },");
+    }
+    gen.remove_indent();
+    gen.write_line("]");
+  });
+  gen.into_output()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn categories() {
+    assert_eq!(category("monster"), "Monster");
+    assert_eq!(category("MONSTER"), "Monster");
+    assert_eq!(category("water_creature"), "WaterCreature");
+    assert_eq!(category("misc"), "Misc");
+    assert_eq!(category(""), "Misc");
+  }
+
+  #[test]
+  fn desert_has_husks() {
+    let spawns = biome_spawns("desert");
+    assert!(spawns.iter().any(|e| e.0 == "husk"));
+    assert!(biome_spawns("unknown").iter().any(|e| e.0 == "horse"));
+  }
+}
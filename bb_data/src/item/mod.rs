@@ -23,6 +23,15 @@ pub struct ItemDef {
   items: Vec<Item>,
 }
 
+impl ItemDef {
+  /// Returns the names of all the items in this version, in registry order.
+  pub fn names(&self) -> impl Iterator<Item = String> + '_ {
+    let mut items: Vec<_> = self.items.iter().collect();
+    items.sort_unstable_by_key(|it| it.id);
+    items.into_iter().map(|it| it.name.clone())
+  }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[allow(dead_code)]
 pub struct Item {
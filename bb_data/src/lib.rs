@@ -1,13 +1,15 @@
 use dl::Downloader;
-use std::{fmt, path::PathBuf};
+use std::{fmt, fs, path::PathBuf};
 
 mod block;
+mod cache;
 mod command;
 mod dl;
 mod enchantment;
 mod entity;
 pub mod gen;
 mod item;
+mod overrides;
 mod particle;
 mod protocol;
 mod tag;
@@ -42,8 +44,11 @@ pub enum Target {
 }
 
 pub struct Collector {
-  dl:  Downloader,
-  out: PathBuf,
+  dl:    Downloader,
+  out:   PathBuf,
+  /// The hash of the running build script. The generated code depends on
+  /// bb_data itself, so this changes whenever the generators change.
+  build: String,
 }
 
 impl Collector {
@@ -55,17 +60,45 @@ impl Collector {
     let out = PathBuf::new().join(&std::env::var("OUT_DIR").expect("could not get out dir"));
     #[cfg(test)]
     let out = PathBuf::new();
-    Collector { dl: Downloader::new(data_path, data_example_path), out }
+    let build =
+      std::env::current_exe().and_then(fs::read).map(|exe| cache::hash(&exe)).unwrap_or_default();
+    Collector { dl: Downloader::new(data_path, data_example_path), out, build }
   }
 
-  pub fn generate_blocks(&self, opts: BlockOpts) { block::generate(self, opts).unwrap(); }
-  pub fn generate_commands(&self, target: Target) { command::generate(self, target).unwrap(); }
-  pub fn generate_items(&self) { item::generate(self).unwrap(); }
-  pub fn generate_entities(&self) { entity::generate(self).unwrap(); }
-  pub fn generate_protocol(&self) { protocol::generate(self).unwrap(); }
-  pub fn generate_particles(&self, target: Target) { particle::generate(self, target).unwrap(); }
-  pub fn generate_enchantments(&self) { enchantment::generate(self).unwrap(); }
-  pub fn generate_tags(&self) { tag::generate(self).unwrap(); }
+  /// Runs the given generator, unless it has already been run with the same
+  /// data, options, and build script. `opts` should contain everything that
+  /// changes the output of the generator.
+  fn generate(
+    &self,
+    name: &str,
+    opts: impl fmt::Debug,
+    f: impl FnOnce(&Self) -> std::io::Result<()>,
+  ) {
+    let fingerprint =
+      cache::hash(format!("{name}\n{opts:?}\n{}\n{}", self.dl.hash(), self.build).as_bytes());
+    let path = self.out.join("fingerprint").join(name);
+    if !self.build.is_empty() && fs::read_to_string(&path).is_ok_and(|f| f == fingerprint) {
+      return;
+    }
+    f(self).unwrap_or_else(|e| panic!("could not generate {name}: {e}"));
+    // If this fails, we will just generate everything again next time.
+    let _ = fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(&path, fingerprint));
+  }
+
+  pub fn generate_blocks(&self, opts: BlockOpts) {
+    self.generate("blocks", opts, |c| block::generate(c, opts));
+  }
+  pub fn generate_commands(&self, target: Target) {
+    self.generate("commands", target, |c| command::generate(c, target));
+  }
+  pub fn generate_items(&self) { self.generate("items", (), item::generate); }
+  pub fn generate_entities(&self) { self.generate("entities", (), entity::generate); }
+  pub fn generate_protocol(&self) { self.generate("protocol", (), protocol::generate); }
+  pub fn generate_particles(&self, target: Target) {
+    self.generate("particles", target, |c| particle::generate(c, target));
+  }
+  pub fn generate_enchantments(&self) { self.generate("enchantments", (), enchantment::generate); }
+  pub fn generate_tags(&self) { self.generate("tags", (), tag::generate); }
 }
 
 pub static VERSIONS: &[Version] = &[
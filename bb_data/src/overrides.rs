@@ -0,0 +1,182 @@
+//! Local patches for the downloaded data. These are used to fix mistakes in the
+//! data (like a wrong hardness or a missing drop) without waiting for an
+//! update to bamboo-data.
+//!
+//! Overrides are json files in the overrides directory, named after the data
+//! they patch (for example, `blocks.json`). Files at the top of the directory
+//! apply to every version, and files in a version directory (like `1.12.2/`)
+//! only apply to that version. Version specific overrides are applied last.
+//!
+//! Each override is merged into the data like a json merge patch: objects are
+//! merged recursively, `null` removes a field, and any other value replaces
+//! the original. Lists of named objects (like the `blocks` list) are merged
+//! by name, so an override only needs to list the blocks it changes:
+//!
+//! ```json
+//! { "blocks": [{ "name": "stone", "hardness": 1.5 }] }
+//! ```
+//!
+//! Entries with a new name are added to the end of the list. Any other list
+//! is replaced entirely.
+
+use crate::Version;
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+#[derive(Debug, Default)]
+pub struct Overrides {
+  /// Overrides for every version, keyed by data name.
+  all:      BTreeMap<String, Value>,
+  /// Overrides for a single version, keyed by version and then data name.
+  versions: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+impl Overrides {
+  /// Loads all the overrides in the given directory. If the directory doesn't
+  /// exist, there are no overrides.
+  pub fn load(dir: &Path) -> io::Result<Self> {
+    let mut overrides = Overrides::default();
+    if !dir.exists() {
+      return Ok(overrides);
+    }
+    for entry in fs::read_dir(dir)? {
+      let path = entry?.path();
+      let name = path.file_name().unwrap().to_string_lossy().to_string();
+      if path.is_dir() {
+        if !crate::VERSIONS.iter().any(|v| v.to_string() == name) {
+          println!("cargo:warning=unknown version in data overrides: {}", path.display());
+          continue;
+        }
+        overrides.versions.insert(name, load_dir(&path)?);
+      } else if let Some((name, value)) = load_file(&path)? {
+        overrides.all.insert(name, value);
+      }
+    }
+    Ok(overrides)
+  }
+
+  /// Applies the overrides for the given data name and version.
+  pub fn apply(&self, name: &str, ver: Version, value: &mut Value) {
+    if let Some(patch) = self.all.get(name) {
+      merge(value, patch);
+    }
+    if let Some(patch) = self.versions.get(&ver.to_string()).and_then(|v| v.get(name)) {
+      merge(value, patch);
+    }
+  }
+
+  /// Returns a string that changes whenever the overrides change.
+  pub fn fingerprint(&self) -> String {
+    format!(
+      "{}\n{}",
+      serde_json::to_string(&self.all).unwrap(),
+      serde_json::to_string(&self.versions).unwrap()
+    )
+  }
+}
+
+fn load_dir(dir: &Path) -> io::Result<BTreeMap<String, Value>> {
+  let mut out = BTreeMap::new();
+  for entry in fs::read_dir(dir)? {
+    if let Some((name, value)) = load_file(&entry?.path())? {
+      out.insert(name, value);
+    }
+  }
+  Ok(out)
+}
+
+/// Loads a single override file. Returns `None` for files that aren't json.
+fn load_file(path: &Path) -> io::Result<Option<(String, Value)>> {
+  if path.extension().is_none_or(|ext| ext != "json") {
+    return Ok(None);
+  }
+  let name = path.file_stem().unwrap().to_string_lossy().to_string();
+  let value = serde_json::from_slice(&fs::read(path)?).map_err(|e| {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid override {}: {e}", path.display()))
+  })?;
+  Ok(Some((name, value)))
+}
+
+/// Returns the `name` field of the given value, if it has one.
+fn name(value: &Value) -> Option<&str> { value.get("name")?.as_str() }
+
+/// Merges `patch` into `base`. See the module docs for details.
+fn merge(base: &mut Value, patch: &Value) {
+  match (base, patch) {
+    (Value::Object(base), Value::Object(patch)) => {
+      for (key, value) in patch {
+        if value.is_null() {
+          base.remove(key);
+        } else {
+          merge(base.entry(key).or_insert(Value::Null), value);
+        }
+      }
+    }
+    (Value::Array(base), Value::Array(patch))
+      if !patch.is_empty() && patch.iter().all(|v| name(v).is_some()) =>
+    {
+      for value in patch {
+        match base.iter_mut().find(|b| name(b) == name(value)) {
+          Some(b) => merge(b, value),
+          None => base.push(value.clone()),
+        }
+      }
+    }
+    (base, patch) => *base = patch.clone(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn merge_objects() {
+    let mut base = json!({ "a": 1, "b": { "c": 2, "d": 3 }, "e": [1, 2] });
+    merge(&mut base, &json!({ "a": 5, "b": { "c": null, "f": 4 }, "e": [3] }));
+    assert_eq!(base, json!({ "a": 5, "b": { "d": 3, "f": 4 }, "e": [3] }));
+  }
+
+  #[test]
+  fn merge_named() {
+    let mut base = json!({ "blocks": [
+      { "name": "air", "hardness": 0.0 },
+      { "name": "stone", "hardness": 1.0, "drops": [{ "item": "cobblestone" }] },
+    ]});
+    merge(
+      &mut base,
+      &json!({ "blocks": [
+        { "name": "stone", "hardness": 1.5, "drops": [{ "item": "stone" }] },
+        { "name": "dirt", "hardness": 0.5 },
+      ]}),
+    );
+    assert_eq!(
+      base,
+      json!({ "blocks": [
+        { "name": "air", "hardness": 0.0 },
+        { "name": "stone", "hardness": 1.5, "drops": [{ "item": "stone" }] },
+        { "name": "dirt", "hardness": 0.5 },
+      ]})
+    );
+  }
+
+  #[test]
+  fn apply_versions() {
+    let mut overrides = Overrides::default();
+    overrides.all.insert("items".into(), json!({ "a": 1, "b": 1 }));
+    overrides.versions.insert("1.12.2".into(), [("items".into(), json!({ "b": 2 }))].into());
+
+    let mut value = json!({});
+    overrides.apply("items", Version::new(12, 2, 340), &mut value);
+    assert_eq!(value, json!({ "a": 1, "b": 2 }));
+
+    let mut value = json!({});
+    overrides.apply("items", Version::new(8, 9, 47), &mut value);
+    assert_eq!(value, json!({ "a": 1, "b": 1 }));
+
+    let mut value = json!({});
+    overrides.apply("blocks", Version::new(12, 2, 340), &mut value);
+    assert_eq!(value, json!({}));
+  }
+}
@@ -207,6 +207,7 @@ pub fn reader_func_to_ty(field: &str, name: &str) -> RType {
     "read_nbt" => "NBT",
     "remaining" => "i32",
     "read_buf" | "read_byte_arr" | "read_all" => return RType::new("Vec").generic("u8"),
+    "read_rest" => "Bytes",
     "read_i32_arr" => return RType::new("Vec").generic("i32"),
     "read_varint_arr" => return RType::new("Vec").generic("i32"),
     "read_bits" => "BitSet",
@@ -243,7 +244,7 @@ pub fn reader_to_writer(read: &str) -> &'static str {
     "read_uuid" => "write_uuid",
     "read_str" => "write_str",
     "read_nbt" => "write_nbt",
-    "read_buf" | "read_byte_arr" | "read_all" => "write_buf",
+    "read_buf" | "read_byte_arr" | "read_all" | "read_rest" => "write_buf",
     "read_i32_arr" => "write_i32_arr",
     "read_varint_arr" => "write_varint_arr",
     "read_bits" => "write_bits",
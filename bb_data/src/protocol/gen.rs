@@ -5,7 +5,7 @@ use crate::{
   gen::{CodeGen, FuncArg},
   Version,
 };
-use std::{collections::HashMap, fs, fs::File, io, io::Write, path::Path};
+use std::{collections::HashMap, fmt::Write as _, fs, fs::File, io, io::Write, path::Path};
 
 pub fn generate(def: Vec<(Version, PacketDef)>, dir: &Path) -> io::Result<()> {
   let mut all_cb_packets = PacketCollection::new();
@@ -27,11 +27,52 @@ pub fn generate(def: Vec<(Version, PacketDef)>, dir: &Path) -> io::Result<()> {
   all_sb_packets.finish_simplify();
 
   fs::create_dir_all(dir)?;
+  let mut report = String::new();
+  writeln!(report, "# Protocol report").unwrap();
+  writeln!(report).unwrap();
+  writeln!(
+    report,
+    "Every packet on every version, as the protocol generator understands them. Ids are \
+     the ids on that version, and fields are listed in the order they are read."
+  )
+  .unwrap();
+  for &ver in crate::VERSIONS {
+    writeln!(report).unwrap();
+    writeln!(report, "## {ver} (protocol {})", ver.protocol).unwrap();
+    write_report(&mut report, "Clientbound", &all_cb_packets.table(ver));
+    write_report(&mut report, "Serverbound", &all_sb_packets.table(ver));
+  }
+  File::create(dir.join("report.md"))?.write_all(report.as_bytes())?;
   File::create(dir.join("cb.rs"))?.write_all(all_cb_packets.gen().as_bytes())?;
   File::create(dir.join("sb.rs"))?.write_all(all_sb_packets.gen().as_bytes())?;
 
   Ok(())
 }
+
+/// A packet on a single version. This is used for the report, and for the id
+/// tables in the generated code.
+struct TableEntry {
+  id:     i32,
+  name:   String,
+  /// The first version with this definition of the packet.
+  since:  Version,
+  /// The name, rust type, and java type of each field.
+  fields: Vec<(String, String, String)>,
+}
+
+/// Writes the packets in `table` to the report, under the given heading.
+fn write_report(out: &mut String, heading: &str, table: &[TableEntry]) {
+  writeln!(out).unwrap();
+  writeln!(out, "### {heading}").unwrap();
+  writeln!(out).unwrap();
+  for e in table {
+    writeln!(out, "- `{:#04x}` **{}** (since {})", e.id, e.name, e.since).unwrap();
+    for (name, ty, java) in &e.fields {
+      writeln!(out, "  - `{name}: {ty}` (java `{java}`)").unwrap();
+    }
+  }
+}
+
 #[derive(Debug)]
 struct PacketCollection {
   // Maps packet names to [version, packet]
@@ -89,22 +130,41 @@ impl PacketCollection {
     for versions in self.packets.values_mut() {
       for (_ver, p) in versions {
         simplify::finish(p);
+        p.find_reader_types_gen_writer();
       }
     }
   }
+  /// Returns every packet on the given version, sorted by id. This must be
+  /// called after [`finish_simplify`](Self::finish_simplify), so that the
+  /// field types are known.
+  fn table(&self, ver: Version) -> Vec<TableEntry> {
+    let mut out: Vec<_> = self.versions[&ver]
+      .iter()
+      .filter_map(|(name, &id)| {
+        // This is the same definition that `tcp_id` uses.
+        let (since, p) = self.packets[name].iter().rev().find(|(v, _)| v.maj <= ver.maj)?;
+        Some(TableEntry {
+          id,
+          name: name.clone(),
+          since: *since,
+          fields: p
+            .fields
+            .iter()
+            .map(|f| (f.name.clone(), field_ty(f), format!("{:?}", f.ty)))
+            .collect(),
+        })
+      })
+      .collect();
+    out.sort_unstable_by_key(|e| e.id);
+    out
+  }
   pub fn gen(self) -> String {
     let mut gen = CodeGen::new();
 
+    let tables: Vec<_> = crate::VERSIONS.iter().map(|&ver| (ver, self.table(ver))).collect();
     let mut packets: Vec<_> = self.packets.into_iter().collect();
     packets.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
-    let mut packets: Vec<Vec<(_, _)>> = packets.into_iter().map(|(_, v)| v).collect();
-    for versions in &mut packets {
-      for (_v, p) in versions {
-        // eprintln!("finding reader type of {} for ver {}", p.name, v);
-        // dbg!(&_v, &p);
-        p.find_reader_types_gen_writer();
-      }
-    }
+    let packets: Vec<Vec<(_, _)>> = packets.into_iter().map(|(_, v)| v).collect();
 
     gen.write_line("// Some imports are used on clientbound packets, but not on serverbound");
     gen.write_line("// packets. This is to remove those warnings.");
@@ -172,7 +232,31 @@ impl PacketCollection {
           gen.write(&first.name);
           gen.write_line("\",");
         }
-        gen.write_line(r#"v => panic!("invalid protocol version {}", v),"#);
+        gen.write_line(r#"_ => "unknown","#);
+      });
+    });
+
+    gen.write_line("/// Returns every packet on the given version, sorted by id. This is empty");
+    gen.write_line("/// for versions that packets aren't generated for.");
+    gen.write("pub fn packets(ver: ProtocolVersion) -> &'static [PacketInfo] ");
+    gen.write_block(|gen| {
+      gen.write_match("ver.id()", |gen| {
+        for (ver, table) in &tables {
+          gen.write_comment(&ver.to_string());
+          gen.write(&ver.protocol.to_string());
+          gen.write_line(" => &[");
+          gen.add_indent();
+          for e in table {
+            gen.write(&format!("PacketInfo {{ id: {}, name: {:?}, fields: &[", e.id, e.name));
+            for (name, ty, _) in &e.fields {
+              gen.write(&format!("FieldInfo {{ name: {name:?}, ty: {ty:?} }}, "));
+            }
+            gen.write_line("] },");
+          }
+          gen.remove_indent();
+          gen.write_line("],");
+        }
+        gen.write_line("_ => &[],");
       });
     });
 
@@ -217,6 +301,9 @@ impl PacketCollection {
       write_to_tcp_all(gen, &packets);
     });
 
+    gen.write_line("/// Returns the index of the packet with the given id, or -1 if the id is");
+    gen.write_line("/// unknown.");
+    gen.write_line("///");
     gen.write_line("/// Unreachable patterns mean the packet has been removed in that version.");
     gen.write_line("/// This is most likely because I messed up the naming. However, some packets");
     gen.write_line("/// have been removed in practice. TODO: Handle removed packets.");
@@ -245,10 +332,10 @@ impl PacketCollection {
                   }
                 }
               }
-              gen.write_line("_ => 0,");
+              gen.write_line("_ => -1,");
             });
           }
-          gen.write_line("_ => 0,");
+          gen.write_line("_ => -1,");
         });
       },
     );
@@ -412,27 +499,28 @@ fn write_def(gen: &mut CodeGen, name: &str, p: &Packet) {
     gen.write("pub ");
     gen.write(&f.name);
     gen.write(": ");
-    if f.option {
-      gen.write("Option<");
-      if f.ty == Type::Void {
-        gen.write(&f.reader_type.as_ref().unwrap().to_string());
-      } else {
-        gen.write(&f.ty.to_rust().to_string());
-      }
-      gen.write(">");
-    } else {
-      if f.ty == Type::Void {
-        gen.write(&f.reader_type.as_ref().unwrap().to_string());
-      } else {
-        gen.write(&f.ty.to_rust().to_string());
-      }
-    }
+    gen.write(&field_ty(f));
     gen.write_line(",");
   }
   gen.remove_indent();
   gen.write_line("}");
 }
 
+/// Returns the rust type of the given field, as it is written in the
+/// generated struct.
+fn field_ty(f: &Field) -> String {
+  let ty = if f.ty == Type::Void {
+    f.reader_type.as_ref().unwrap().to_string()
+  } else {
+    f.ty.to_rust().to_string()
+  };
+  if f.option {
+    format!("Option<{ty}>")
+  } else {
+    ty
+  }
+}
+
 pub fn write_general_from_tcp(gen: &mut CodeGen, versions: &[(Version, Packet)]) {
   gen.write_line(
     "pub fn from_tcp(p: &mut tcp::Packet, ver: ProtocolVersion) -> Result<Self, Error> {",
@@ -606,7 +694,7 @@ pub fn write_from_tcp_all(gen: &mut CodeGen, packets: &[Vec<(Version, Packet)>])
         gen.write(&first.name);
         gen.write_line("::from_tcp(p, ver)?),");
       }
-      gen.write_line(r#"v => panic!("invalid protocol version {}", v),"#);
+      gen.write_line("_ => return Err(Error::UnknownId { id: p.id(), ver }),");
     });
     gen.write(")"); // Close the `Ok(` from above the match
   });
@@ -17,7 +17,13 @@ pub fn generate(c: &Collector) -> io::Result<()> {
     let def: PacketDef = c.dl.get("protocol", ver);
     versions.push((ver, def));
   }
-  gen::generate(versions, &c.out.join("protocol"))?;
+  let out = c.out.join("protocol");
+  gen::generate(versions, &out)?;
+  // The out dir is `target/<profile>/build/<crate>-<hash>/out`, which is hard to
+  // find, so the report is also copied to `target/<profile>`.
+  if let Some(target) = c.out.ancestors().nth(3) {
+    let _ = std::fs::copy(out.join("report.md"), target.join("protocol-report.md"));
+  }
   Ok(())
 }
 
@@ -9,7 +9,7 @@ pub fn pass(p: &mut Packet) {
     p.reader.block = p.reader.block[..l].to_vec();
     p.fields.push(Field {
       name:        "unknown".into(),
-      ty:          Type::Rust(RType::new("Vec").generic("u8")),
+      ty:          unknown_ty(),
       reader_type: None,
       option:      false,
       initialized: false,
@@ -28,7 +28,7 @@ pub fn finish(p: &mut Packet) {
     p.fields.clear();
     p.fields.push(Field {
       name:        "unknown".into(),
-      ty:          Type::Rust(RType::new("Vec").generic("u8")),
+      ty:          unknown_ty(),
       reader_type: None,
       option:      false,
       initialized: true,
@@ -42,7 +42,7 @@ pub fn finish(p: &mut Packet) {
     p.reader.block.push(set_unknown());
     p.fields.push(Field {
       name:        "unknown".into(),
-      ty:          Type::Rust(RType::new("Vec").generic("u8")),
+      ty:          unknown_ty(),
       reader_type: None,
       option:      false,
       initialized: false,
@@ -147,12 +147,17 @@ fn simplify_instr(instr: &mut [Instr]) -> Option<usize> {
   }
   None
 }
+/// Reads the rest of the packet into the `unknown` field. This is usually most
+/// of the packet (all of the chunk data, for example), so it is read with
+/// `read_rest`, which doesn't copy it.
 fn set_unknown() -> Instr {
   Instr::Set(
     "unknown".into(),
-    Expr::new(Value::packet_var()).op(Op::Call("tcp::Packet".into(), "read_all".into(), vec![])),
+    Expr::new(Value::packet_var()).op(Op::Call("tcp::Packet".into(), "read_rest".into(), vec![])),
   )
 }
+/// The type of the `unknown` field. See `gnet::Bytes` in the proxy.
+fn unknown_ty() -> Type { Type::Rust(RType::new("Bytes")) }
 fn simplify_cond(cond: &mut Cond) { simplify_cond_overwrite(cond); }
 fn simplify_cond_overwrite(cond: &mut Cond) -> (bool, Option<Instr>) {
   match cond {
@@ -333,3 +333,23 @@ fn multi_conditional_writer_test() {
   assert_eq!(p.writer.block, writer);
   assert_eq!(p.fields, fields);
 }
+
+#[test]
+fn unknown_writer_test() {
+  // Packets without any known fields read the whole packet into `unknown`.
+  let writer = vec![Instr::Expr(packet().op(call!(write_buf[field("unknown").op(Op::Ref)])))];
+  let mut p = Packet {
+    extends: "".into(),
+    class:   "".into(),
+    name:    "Bar".into(),
+    fields:  vec![],
+    reader:  block(vec![], 0),
+    writer:  block(vec![], 0),
+  };
+  generate(&mut p, writer.clone());
+
+  assert_eq!(p.reader.block, vec![Instr::Set("unknown".into(), packet().op(call!(read_rest[])))]);
+  assert_eq!(p.writer.block, writer);
+  assert_eq!(p.fields.len(), 1);
+  assert_eq!(p.fields[0].ty, Type::Rust(RType::new("Bytes")));
+}
@@ -259,6 +259,7 @@ impl<'a> ReaderTypes<'a> {
       "read_str" | "read_ident" => RType::new("String"),
       "read_uuid" => RType::new("UUID"),
       "read_byte_arr" | "read_all" => RType::new("Vec").generic("u8"),
+      "read_rest" => RType::new("Bytes"),
       "read_i32_arr" => RType::new("Vec").generic("i32"),
       "read_varint_arr" => RType::new("Vec").generic("i32"),
       "read_block_hit" => RType::new("BlockHit"),
@@ -1,13 +1,65 @@
-use super::TagsDef;
+use super::{Registries, TagsDef};
 use crate::{gen::CodeGen, Version};
 
 use std::{fs, io, path::Path};
 
-pub fn generate(def: Vec<(Version, TagsDef)>, dir: &Path) -> io::Result<()> {
+pub fn generate(
+  def: Vec<(Version, TagsDef)>,
+  registries: Vec<Registries>,
+  dir: &Path,
+) -> io::Result<()> {
   fs::write(dir.join("tags.rs"), generate_tags(&def.last().unwrap().1))?;
+  fs::write(dir.join("registries.rs"), generate_registries(&registries))?;
   Ok(())
 }
 
+pub fn generate_registries(registries: &[Registries]) -> String {
+  let mut gen = CodeGen::new();
+  gen.write_line("/// The block, item, and entity registries for a single version. The index");
+  gen.write_line("/// of each name is the id that version uses for it.");
+  gen.write_line("pub struct Registries {");
+  gen.write_line("  pub ver: BlockVersion,");
+  gen.write_line("  pub block: &'static [&'static str],");
+  gen.write_line("  pub item: &'static [&'static str],");
+  gen.write_line("  pub entity_type: &'static [&'static str],");
+  gen.write_line("}");
+
+  gen.write_line("/// Returns the registries for every version that supports tags.");
+  gen.write("pub fn generate_registries() -> &'static [Registries] ");
+  gen.write_block(|gen| {
+    gen.write_line("&[");
+    gen.add_indent();
+    for reg in registries {
+      gen.write_line("Registries {");
+      gen.add_indent();
+      gen.write("ver: ");
+      gen.write(&reg.ver.to_block());
+      gen.write_line(",");
+      for (name, values) in
+        [("block", &reg.block), ("item", &reg.item), ("entity_type", &reg.entity_type)]
+      {
+        gen.write(name);
+        gen.write(": &[");
+        for (i, val) in values.iter().enumerate() {
+          gen.write("\"");
+          gen.write(val);
+          gen.write("\"");
+          if i != values.len() - 1 {
+            gen.write(", ");
+          }
+        }
+        gen.write_line("],");
+      }
+      gen.remove_indent();
+      gen.write_line("},");
+    }
+    gen.remove_indent();
+    gen.write_line("]");
+  });
+
+  gen.into_output()
+}
+
 pub fn generate_tags(def: &TagsDef) -> String {
   let mut gen = CodeGen::new();
   gen.write_line("pub struct TagCategories {");
@@ -1,4 +1,4 @@
-use crate::Collector;
+use crate::{block::BlockDef, entity::EntityDef, item::ItemDef, Collector, Version};
 use serde::Deserialize;
 use std::{fs, io};
 
@@ -13,10 +13,36 @@ pub fn generate(c: &Collector) -> io::Result<()> {
       (ver, def)
     })
     .collect();
-  gen::generate(versions, &c.out.join("tag"))?;
+  let registries = crate::VERSIONS
+    .iter()
+    .filter(|ver| ver.maj >= 14)
+    .map(|&ver| {
+      let blocks: BlockDef = c.dl.get("blocks", ver);
+      let items: ItemDef = c.dl.get("items", ver);
+      let entities: EntityDef = c.dl.get("entities", ver);
+      Registries {
+        ver,
+        block: blocks.names().collect(),
+        item: items.names().collect(),
+        entity_type: entities.names().collect(),
+      }
+    })
+    .collect();
+  gen::generate(versions, registries, &c.out.join("tag"))?;
   Ok(())
 }
 
+/// All the names in each registry for a single version. The index of each name
+/// is the id that version uses for it. Tags are sent as ids, so these are
+/// needed to convert tags to older versions.
+#[derive(Clone, Debug)]
+pub struct Registries {
+  ver:         Version,
+  block:       Vec<String>,
+  item:        Vec<String>,
+  entity_type: Vec<String>,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct TagsDef {
   categories: Vec<TagCategory>,
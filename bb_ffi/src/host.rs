@@ -0,0 +1,55 @@
+//! Types used by the server to refer to a plugin's memory. Plugins run in their
+//! own address space, so on the host every pointer is an offset into the
+//! plugin's memory.
+
+use std::{fmt, marker::PhantomData};
+
+/// A type that can be copied in and out of a plugin's memory.
+///
+/// # Safety
+///
+/// Plugins can write anything into their memory, so every bit pattern must be
+/// a valid value of this type.
+pub unsafe trait ValueType: Copy {}
+
+macro_rules! value_type {
+  ( $($ty:ty),* ) => {
+    $(
+      unsafe impl ValueType for $ty {}
+    )*
+  }
+}
+
+value_type!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+unsafe impl<T: ValueType, const N: usize> ValueType for [T; N] {}
+
+/// Marks a [`WasmPtr`] as pointing to a single value.
+#[derive(Debug, Clone, Copy)]
+pub struct Item;
+/// Marks a [`WasmPtr`] as pointing to the first value in an array.
+#[derive(Debug, Clone, Copy)]
+pub struct Array;
+
+/// A pointer into a plugin's memory. This is only an offset, so it must be
+/// read through the plugin's memory.
+#[repr(transparent)]
+pub struct WasmPtr<T, Ty = Item> {
+  offset:  u32,
+  _marker: PhantomData<fn() -> (T, Ty)>,
+}
+
+impl<T, Ty> WasmPtr<T, Ty> {
+  pub fn new(offset: u32) -> Self { WasmPtr { offset, _marker: PhantomData } }
+  /// Returns the offset of this pointer, in bytes.
+  pub fn offset(&self) -> u32 { self.offset }
+  pub fn is_null(&self) -> bool { self.offset == 0 }
+}
+
+impl<T, Ty> Clone for WasmPtr<T, Ty> {
+  fn clone(&self) -> Self { *self }
+}
+impl<T, Ty> Copy for WasmPtr<T, Ty> {}
+impl<T, Ty> fmt::Debug for WasmPtr<T, Ty> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "WasmPtr({:#x})", self.offset) }
+}
+unsafe impl<T, Ty> ValueType for WasmPtr<T, Ty> {}
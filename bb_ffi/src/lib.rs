@@ -3,11 +3,16 @@
 use bb_ffi_macros::{cenum, ctype};
 use std::{fmt, mem::MaybeUninit};
 
+#[cfg(feature = "host")]
+mod host;
+#[cfg(feature = "host")]
+pub use host::{Array, Item, ValueType, WasmPtr};
+
 #[repr(C)]
 #[cfg_attr(feature = "host", derive(Debug, Clone))]
 pub struct CStr {
   #[cfg(feature = "host")]
-  pub ptr: wasmer::WasmPtr<u8, wasmer::Array>,
+  pub ptr: WasmPtr<u8, Array>,
   #[cfg(not(feature = "host"))]
   pub ptr: *mut u8,
   pub len: u32,
@@ -16,7 +21,7 @@ pub struct CStr {
 #[cfg(feature = "host")]
 impl Copy for CStr {}
 #[cfg(feature = "host")]
-unsafe impl wasmer::ValueType for CStr {}
+unsafe impl ValueType for CStr {}
 
 /// A boolean, except every bit configuration is valid. Use
 /// [`as_bool`](CBool::as_bool) to convert it to a `bool`.
@@ -25,7 +30,7 @@ unsafe impl wasmer::ValueType for CStr {}
 pub struct CBool(pub u8);
 
 #[cfg(feature = "host")]
-unsafe impl wasmer::ValueType for CBool {}
+unsafe impl ValueType for CBool {}
 
 #[ctype]
 #[derive(Debug)]
@@ -54,6 +59,18 @@ pub struct CPos {
   pub z: i32,
 }
 
+/// A single block change, used to add a plugin's edits to a player's history.
+#[ctype]
+#[derive(Debug)]
+#[cfg_attr(not(feature = "host"), derive(Copy))]
+pub struct CBlockChange {
+  pub pos: CPos,
+  /// The block id before the change.
+  pub old: u32,
+  /// The block id after the change.
+  pub new: u32,
+}
+
 #[ctype]
 #[derive(Debug)]
 #[cfg_attr(not(feature = "host"), derive(Copy))]
@@ -62,6 +79,16 @@ pub struct CFPos {
   pub y: f64,
   pub z: f64,
 }
+/// Something hit by a raycast.
+#[ctype]
+#[derive(Debug)]
+#[cfg_attr(not(feature = "host"), derive(Copy))]
+pub struct CRaycastHit {
+  /// The exact position the ray hit.
+  pub pos:    CFPos,
+  /// A handle to the entity that was hit, or 0 if a block was hit.
+  pub entity: u64,
+}
 #[ctype]
 #[derive(Debug)]
 #[cfg_attr(not(feature = "host"), derive(Copy))]
@@ -201,7 +228,7 @@ pub enum CBlockPropValue {
 #[derive(Clone, Debug)]
 pub struct CList<T: Copy> {
   /// The pointer to the first element in this list.
-  pub first: wasmer::WasmPtr<T, wasmer::Array>,
+  pub first: WasmPtr<T, Array>,
   /// The length of this list.
   pub len:   u32,
 }
@@ -218,7 +245,7 @@ pub struct CList<T> {
 #[cfg(feature = "host")]
 impl<T: Copy> Copy for CList<T> {}
 #[cfg(feature = "host")]
-unsafe impl<T: Copy> wasmer::ValueType for CList<T> {}
+unsafe impl<T: Copy> ValueType for CList<T> {}
 
 #[cfg(not(feature = "host"))]
 #[repr(C)]
@@ -237,7 +264,30 @@ pub struct COpt<T: Copy> {
 #[cfg(feature = "host")]
 impl<T: Copy> Copy for COpt<T> {}
 #[cfg(feature = "host")]
-unsafe impl<T: Copy> wasmer::ValueType for COpt<T> {}
+unsafe impl<T: Copy> ValueType for COpt<T> {}
+
+/// Either a value, or an error. If `ok` is set, `value` is valid. Otherwise,
+/// `err` is valid.
+#[cfg(not(feature = "host"))]
+#[repr(C)]
+pub struct CResult<T> {
+  pub ok:    CBool,
+  pub value: MaybeUninit<T>,
+  pub err:   MaybeUninit<CError>,
+}
+#[cfg(feature = "host")]
+#[repr(C)]
+#[derive(Clone)]
+pub struct CResult<T: Copy> {
+  pub ok:    CBool,
+  pub value: MaybeUninit<T>,
+  pub err:   MaybeUninit<CError>,
+}
+
+#[cfg(feature = "host")]
+impl<T: Copy> Copy for CResult<T> {}
+#[cfg(feature = "host")]
+unsafe impl<T: Copy> ValueType for CResult<T> {}
 
 #[cenum]
 pub enum CCommandArg {
@@ -276,6 +326,68 @@ pub enum CCommandStringType {
   Greedy,
 }
 
+/// The kind of error in a [`CError`].
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CErrorCode {
+  /// One of the arguments was invalid, such as a null pointer, or an unknown
+  /// name.
+  InvalidArgument = 1,
+  /// The player is not online.
+  NoPlayer        = 2,
+  /// There is no world with the given id.
+  NoWorld         = 3,
+  /// There is no entity with the given id.
+  NoEntity        = 4,
+  /// The position is outside of the world.
+  InvalidPos      = 5,
+  /// The world is locked, so no blocks can be changed.
+  Locked          = 6,
+  /// This plugin is not allowed to change this world. Plugins can only change
+  /// the worlds listed in `worlds` in their `plugin.toml`.
+  NotAllowed      = 7,
+  /// The player hasn't selected a region.
+  NoSelection     = 8,
+  /// A file couldn't be read or written.
+  Io              = 9,
+  /// The entity handle is for an entity that has been removed, or that is in
+  /// a different world.
+  StaleEntity     = 10,
+}
+
+impl CErrorCode {
+  /// Converts the `code` of a [`CError`] back into a `CErrorCode`. Returns
+  /// `None` if the code is unknown, which can happen if the plugin is older
+  /// than the server.
+  pub fn from_i32(code: i32) -> Option<Self> {
+    Some(match code {
+      1 => Self::InvalidArgument,
+      2 => Self::NoPlayer,
+      3 => Self::NoWorld,
+      4 => Self::NoEntity,
+      5 => Self::InvalidPos,
+      6 => Self::Locked,
+      7 => Self::NotAllowed,
+      8 => Self::NoSelection,
+      9 => Self::Io,
+      10 => Self::StaleEntity,
+      _ => return None,
+    })
+  }
+}
+
+/// An error from a host function. Functions that can only fail return a
+/// pointer to one of these, which is null on success. Functions that return a
+/// value use a [`CResult`] instead.
+#[ctype]
+#[derive(Debug)]
+pub struct CError {
+  /// One of the values of [`CErrorCode`].
+  pub code:    i32,
+  /// A message describing what went wrong, which can be shown to a person.
+  pub message: CStr,
+}
+
 #[cenum]
 pub enum CCommandParser {
   // Simple types:
@@ -428,33 +540,294 @@ extern "C" {
   /// Broadcasts the given chat message to all players.
   pub fn bb_broadcast(message: *const CChat);
   /// Returns the player's username.
-  pub fn bb_player_username(player: *const CUUID) -> *mut CStr;
+  pub fn bb_player_username(player: *const CUUID) -> *mut CResult<CStr>;
   /// Returns the player's position.
-  pub fn bb_player_pos(player: *const CUUID) -> *mut CFPos;
+  pub fn bb_player_pos(player: *const CUUID) -> *mut CResult<CFPos>;
   /// Returns the player's looking direction as a unit vector.
-  pub fn bb_player_look_as_vec(player: *const CUUID) -> *mut CVec3;
-  /// Returns the current world for this player.
-  pub fn bb_player_world(player: *const CUUID) -> i32;
+  pub fn bb_player_look_as_vec(player: *const CUUID) -> *mut CResult<CVec3>;
+  /// Returns the id of the world this player is in.
+  pub fn bb_player_world(player: *const CUUID) -> *mut CResult<u32>;
+  /// Moves the player into the given world, at the given position.
+  pub fn bb_player_teleport_world(player: *const CUUID, wid: u32, pos: *const CFPos)
+    -> *mut CError;
   /// Sends the given chat message to the player.
   pub fn bb_player_send_message(player: *const CUUID, message: *const CChat);
   /// Sends the given particle to the player.
   pub fn bb_player_send_particle(player: *const CUUID, particle: *const CParticle);
+  /// Returns the value of the given attribute for the player, after all
+  /// modifiers have been applied.
+  pub fn bb_player_attribute(
+    player: *const CUUID,
+    name_ptr: *const u8,
+    name_len: u32,
+  ) -> *mut CResult<f64>;
+  /// Sets the base value of the given attribute for the player.
+  pub fn bb_player_set_attribute_base(
+    player: *const CUUID,
+    name_ptr: *const u8,
+    name_len: u32,
+    value: f64,
+  ) -> *mut CError;
+  /// Changes the player's skin, from a base64 encoded `textures` property. If
+  /// `sig_len` is 0, the skin will be unsigned.
+  pub fn bb_player_set_skin(
+    player: *const CUUID,
+    value_ptr: *const u8,
+    value_len: u32,
+    sig_ptr: *const u8,
+    sig_len: u32,
+  ) -> *mut CError;
+  /// Resets the player's skin to the one from their Mojang profile.
+  pub fn bb_player_reset_skin(player: *const CUUID) -> *mut CError;
+  /// Returns the brand the player's client sent, or an empty string if it
+  /// hasn't been sent yet.
+  pub fn bb_player_brand(player: *const CUUID) -> *mut CResult<CStr>;
+  /// Returns the ids of the mods the player has installed. This is only known
+  /// for Forge clients, and is empty otherwise.
+  pub fn bb_player_mods(player: *const CUUID) -> *mut CResult<CList<CStr>>;
+  /// Hides `other` from `player` if `hidden` is true, or shows `other` if
+  /// `hidden` is false.
+  pub fn bb_player_set_hidden(
+    player: *const CUUID,
+    other: *const CUUID,
+    hidden: CBool,
+  ) -> *mut CError;
+  /// Sets both corners of the player's selection.
+  pub fn bb_player_set_selection(
+    player: *const CUUID,
+    pos1: *const CPos,
+    pos2: *const CPos,
+  ) -> *mut CError;
+  /// Edits the player's selection, and adds the edit to their history. `op` is
+  /// 0 for set, 1 for replace, 2 for walls, and 3 for hollow. `filter` is the
+  /// kind to replace, and is ignored for every other operation. Returns the
+  /// number of blocks changed.
+  pub fn bb_player_edit_selection(
+    player: *const CUUID,
+    op: u8,
+    ty: u32,
+    filter: u32,
+  ) -> *mut CResult<u64>;
+  /// Adds the given block changes to the player's history, so that they can
+  /// be undone.
+  pub fn bb_player_remember(
+    player: *const CUUID,
+    changes: *const CList<CBlockChange>,
+  ) -> *mut CError;
+  /// Undoes the player's last edit. Returns the number of blocks changed, or
+  /// `None` if there is nothing to undo.
+  pub fn bb_player_undo(player: *const CUUID) -> *mut CResult<COpt<u64>>;
+  /// Redoes the player's last undone edit. Returns the number of blocks
+  /// changed, or `None` if there is nothing to redo.
+  pub fn bb_player_redo(player: *const CUUID) -> *mut CResult<COpt<u64>>;
+  /// Bans the player, and kicks them if they are online. The player doesn't
+  /// need to be online. If `duration` is 0, the ban is permanent. Otherwise,
+  /// the ban expires after `duration` seconds. Returns -1 if the reason is not
+  /// valid utf8.
+  pub fn bb_player_ban(
+    player: *const CUUID,
+    reason_ptr: *const u8,
+    reason_len: u32,
+    duration: u64,
+  ) -> i32;
+  /// Removes the player's ban. Returns -1 if the player is not banned.
+  pub fn bb_player_pardon(player: *const CUUID) -> i32;
+  /// Returns the reason the player was banned for. Returns null if the player
+  /// is not banned.
+  pub fn bb_player_ban_reason(player: *const CUUID) -> *mut CStr;
+  /// Returns the number of seconds until the player's ban expires. Returns 0
+  /// if the ban is permanent, and -1 if the player is not banned.
+  pub fn bb_player_ban_expires(player: *const CUUID) -> i64;
 
-  /// Sets a block in the world. Returns -1 if the block position is invalid.
-  pub fn bb_world_set_block(wid: u32, pos: *const CPos, id: u32) -> i32;
-  /// Sets a block in the world, using a kind id. Returns -1 if the block
-  /// position is invalid.
-  pub fn bb_world_set_block_kind(wid: u32, pos: *const CPos, kind: u32) -> i32;
-  /// Gets a block in the world. Returns `u32::MAX` if the block position is
-  /// invalid.
-  pub fn bb_world_get_block(wid: u32, pos: *const CPos) -> u32;
+  /// Sets a block in the world.
+  pub fn bb_world_set_block(wid: u32, pos: *const CPos, id: u32) -> *mut CError;
+  /// Sets a block in the world, using a kind id.
+  pub fn bb_world_set_block_kind(wid: u32, pos: *const CPos, kind: u32) -> *mut CError;
+  /// Gets a block in the world.
+  pub fn bb_world_get_block(wid: u32, pos: *const CPos) -> *mut CResult<u32>;
+  /// Sets a block in the world to a mob spawner, which will spawn the given
+  /// entity.
+  pub fn bb_world_set_spawner(
+    wid: u32,
+    pos: *const CPos,
+    entity_ptr: *const u8,
+    entity_len: u32,
+  ) -> *mut CError;
+  /// Sets the minimum and maximum delay in ticks of the spawner at the given
+  /// position. If there is no spawner at the given position, this returns
+  /// [`CErrorCode::InvalidArgument`].
+  pub fn bb_world_set_spawner_delay(wid: u32, pos: *const CPos, min: u32, max: u32) -> *mut CError;
+  /// Spawns floating text in the world. Returns a handle to the entity.
+  ///
+  /// Entity handles have the entity id in the lower 32 bits, and a
+  /// generation in the upper 32 bits. If the entity is removed, functions
+  /// called with the handle will return [`CErrorCode::StaleEntity`]. A handle
+  /// of 0 is never valid.
+  pub fn bb_world_spawn_text(wid: u32, pos: *const CFPos, text: *const CChat) -> *mut CResult<u64>;
+  /// Changes the text of an entity created with `bb_world_spawn_text`.
+  pub fn bb_world_set_text(wid: u32, entity: u64, text: *const CChat) -> *mut CError;
+  /// Removes the given entity.
+  pub fn bb_world_remove_entity(wid: u32, entity: u64) -> *mut CError;
+  /// Spawns an armor stand at the given position, and returns a handle to it.
+  pub fn bb_world_spawn_armor_stand(wid: u32, pos: *const CFPos) -> *mut CResult<u64>;
+  /// Rotates part of an armor stand, like `left_arm`. The angles are in
+  /// degrees.
+  pub fn bb_world_set_armor_stand_pose(
+    wid: u32,
+    entity: u64,
+    part_ptr: *const u8,
+    part_len: u32,
+    x: f32,
+    y: f32,
+    z: f32,
+  ) -> *mut CError;
+  /// Sets or clears an armor stand flag, like `small`.
+  pub fn bb_world_set_armor_stand_flag(
+    wid: u32,
+    entity: u64,
+    flag_ptr: *const u8,
+    flag_len: u32,
+    value: CBool,
+  ) -> *mut CError;
+  /// Sets the item in an entity's equipment slot, like `helmet`.
+  pub fn bb_world_set_equipment(
+    wid: u32,
+    entity: u64,
+    slot_ptr: *const u8,
+    slot_len: u32,
+    item_ptr: *const u8,
+    item_len: u32,
+    amount: u8,
+  ) -> *mut CError;
+  /// Disguises an entity or player as another entity type, like `cow`.
+  pub fn bb_world_disguise(wid: u32, entity: u64, ty_ptr: *const u8, ty_len: u32) -> *mut CError;
+  /// Removes the disguise from an entity. If the entity wasn't disguised,
+  /// this returns [`CErrorCode::InvalidArgument`].
+  pub fn bb_world_undisguise(wid: u32, entity: u64) -> *mut CError;
+  /// Pastes the schematic file at the given path. The schematic is mirrored
+  /// (0 for none, 1 for the X axis, 2 for the Z axis), and then rotated
+  /// clockwise by `rotation` degrees around `pos`. Returns the number of
+  /// blocks changed.
+  pub fn bb_world_paste_schematic(
+    wid: u32,
+    path_ptr: *const u8,
+    path_len: u32,
+    pos: *const CPos,
+    rotation: i32,
+    mirror: u8,
+    skip_air: CBool,
+  ) -> *mut CResult<u64>;
+  /// Copies the blocks between `min` and `max`, and stores them as a named
+  /// structure. Pasting the structure at `origin` will place the blocks back
+  /// where they were.
+  pub fn bb_world_save_structure(
+    wid: u32,
+    name_ptr: *const u8,
+    name_len: u32,
+    min: *const CPos,
+    max: *const CPos,
+    origin: *const CPos,
+  ) -> *mut CError;
+  /// Pastes a named structure. This mirrors and rotates the structure like
+  /// [`bb_world_paste_schematic`], and then places each block with a chance
+  /// of `integrity`, using `seed` to pick the blocks. Returns the number of
+  /// blocks changed, or [`CErrorCode::InvalidArgument`] if there is no
+  /// structure with that name.
+  pub fn bb_world_paste_structure(
+    wid: u32,
+    name_ptr: *const u8,
+    name_len: u32,
+    pos: *const CPos,
+    rotation: i32,
+    mirror: u8,
+    integrity: f32,
+    seed: u64,
+  ) -> *mut CResult<u64>;
+  /// Reads a schematic or structure file, and stores it as a named structure.
+  pub fn bb_load_structure(
+    name_ptr: *const u8,
+    name_len: u32,
+    path_ptr: *const u8,
+    path_len: u32,
+  ) -> *mut CError;
+  /// Writes a named structure to a file. The format is picked from the
+  /// extension of the path.
+  pub fn bb_write_structure(
+    name_ptr: *const u8,
+    name_len: u32,
+    path_ptr: *const u8,
+    path_len: u32,
+  ) -> *mut CError;
+  /// Removes a named structure. Returns [`CErrorCode::InvalidArgument`] if
+  /// there is no structure with that name.
+  pub fn bb_remove_structure(name_ptr: *const u8, name_len: u32) -> *mut CError;
+  /// Copies every chunk that contains part of the region between `min` and
+  /// `max`, and stores them as a named snapshot in this world.
+  pub fn bb_world_snapshot_region(
+    wid: u32,
+    name_ptr: *const u8,
+    name_len: u32,
+    min: *const CPos,
+    max: *const CPos,
+  ) -> *mut CError;
+  /// Restores a named snapshot. Returns [`CErrorCode::InvalidArgument`] if
+  /// there is no snapshot with that name.
+  pub fn bb_world_restore_snapshot(wid: u32, name_ptr: *const u8, name_len: u32) -> *mut CError;
+  /// Removes a named snapshot. Returns [`CErrorCode::InvalidArgument`] if
+  /// there is no snapshot with that name.
+  pub fn bb_world_remove_snapshot(wid: u32, name_ptr: *const u8, name_len: u32) -> *mut CError;
+  /// Fills the nether portal frame around `pos` with portal blocks. Returns
+  /// [`CErrorCode::InvalidArgument`] if there is no valid frame.
+  pub fn bb_world_light_portal(wid: u32, pos: *const CPos) -> *mut CError;
+  /// Adds a named ticket to the chunk at `x` and `z`, which keeps it loaded.
+  /// If `ttl` is 0, the ticket lasts until it is removed. Otherwise, it is
+  /// removed after `ttl` ticks.
+  pub fn bb_world_add_chunk_ticket(
+    wid: u32,
+    x: i32,
+    z: i32,
+    name_ptr: *const u8,
+    name_len: u32,
+    ttl: u32,
+  ) -> *mut CError;
+  /// Removes a ticket added with [`bb_world_add_chunk_ticket`]. Returns
+  /// [`CErrorCode::InvalidArgument`] if the chunk has no ticket with that
+  /// name.
+  pub fn bb_world_remove_chunk_ticket(
+    wid: u32,
+    x: i32,
+    z: i32,
+    name_ptr: *const u8,
+    name_len: u32,
+  ) -> *mut CError;
+  /// Sets if the chunk at `x` and `z` is force loaded, like `/forceload`.
+  pub fn bb_world_set_force_loaded(wid: u32, x: i32, z: i32, forced: CBool) -> *mut CError;
   /// Gets a list of all the players in the world.
-  pub fn bb_world_players(wid: u32) -> *mut CList<CUUID>;
+  pub fn bb_world_players(wid: u32) -> *mut CResult<CList<CUUID>>;
   /// Spawns a particle in the world.
   pub fn bb_world_spawn_particle(wid: u32, particle: *const CParticle);
   /// Raycasts from the `from` position to `to`. Returns null if there is no
   /// collision.
   pub fn bb_world_raycast(from: *const CFPos, to: *const CFPos, water: CBool) -> *mut CFPos;
+  /// Raycasts from the `from` position to `to`, and returns a handle to the
+  /// first entity hit. The entity `exclude` will be skipped, which can be 0 to
+  /// not skip anything. Returns 0 if no entities were hit.
+  pub fn bb_world_raycast_entity(from: *const CFPos, to: *const CFPos, exclude: u64) -> u64;
+  /// Raycasts from the `from` position to `to`, and returns the first thing
+  /// hit. `mode` is 0 to only hit blocks, 1 to only hit entities, or 2 to hit
+  /// both, in which case whichever is closer is returned. Water is only hit
+  /// if `water` is set, and the entity `exclude` is skipped (0 to not skip
+  /// anything). Returns null if nothing was hit.
+  pub fn bb_world_raycast_hit(
+    from: *const CFPos,
+    to: *const CFPos,
+    mode: u8,
+    water: CBool,
+    exclude: u64,
+  ) -> *mut CRaycastHit;
+  /// Returns the seed of the given world. This is set with `world.seed` in the
+  /// server config.
+  pub fn bb_world_seed(wid: u32) -> *mut CResult<u64>;
 
   /// Returns the number of nanoseconds since this function was called first.
   /// This is used to find the duration of a function.
@@ -473,6 +846,22 @@ impl<T> fmt::Debug for CPtr<T> {
 }
 */
 
+impl CUUID {
+  /// Splits the UUID into 4 `u32`s, with the least significant bits first.
+  /// This is the only place that decides the layout, so the server and plugins
+  /// always agree on it.
+  pub fn from_u128(n: u128) -> Self {
+    CUUID { bytes: [n as u32, (n >> 32) as u32, (n >> (2 * 32)) as u32, (n >> (3 * 32)) as u32] }
+  }
+  /// Joins the 4 `u32`s back into a UUID. See [`from_u128`](Self::from_u128).
+  pub fn as_u128(&self) -> u128 {
+    (self.bytes[0] as u128)
+      | (self.bytes[1] as u128) << 32
+      | (self.bytes[2] as u128) << (2 * 32)
+      | (self.bytes[3] as u128) << (3 * 32)
+  }
+}
+
 impl CBool {
   /// Creates a `CBool` of `1` if `true`, and `0` if `false`.
   pub fn new(val: bool) -> Self { CBool(if val { 1 } else { 0 }) }
@@ -531,6 +920,42 @@ impl<T: Copy> COpt<T> {
   }
 }
 
+#[cfg(feature = "host")]
+impl<T: Copy> CResult<T> {
+  pub fn ok(val: T) -> Self {
+    CResult { ok: CBool::new(true), value: MaybeUninit::new(val), err: MaybeUninit::uninit() }
+  }
+  pub fn err(err: CError) -> Self {
+    CResult { ok: CBool::new(false), value: MaybeUninit::uninit(), err: MaybeUninit::new(err) }
+  }
+}
+
+#[cfg(not(feature = "host"))]
+impl<T> CResult<T> {
+  pub fn into_result(self) -> Result<T, CError> {
+    let me = std::mem::ManuallyDrop::new(self);
+    if me.ok.as_bool() {
+      Ok(unsafe { me.value.assume_init_read() })
+    } else {
+      Err(unsafe { me.err.assume_init_read() })
+    }
+  }
+}
+
+// On the host, this refers to data in wasm, so we don't want to free it.
+#[cfg(not(feature = "host"))]
+impl<T> Drop for CResult<T> {
+  fn drop(&mut self) {
+    unsafe {
+      if self.ok.as_bool() {
+        self.value.assume_init_drop();
+      } else {
+        self.err.assume_init_drop();
+      }
+    }
+  }
+}
+
 // On the host, this refers to data in wasm, so we don't want to free it.
 #[cfg(not(feature = "host"))]
 impl<T> Drop for COpt<T> {
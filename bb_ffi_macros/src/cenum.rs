@@ -199,7 +199,7 @@ impl CEnum {
             }
 
             #[cfg(feature = "host")]
-            unsafe impl wasmer::ValueType for #name {}
+            unsafe impl crate::ValueType for #name {}
           })
         }
         _ => None,
@@ -582,7 +582,7 @@ pub fn cenum(_args: TokenStream, input: TokenStream) -> TokenStream {
     /// In order for this to truly be valid in every bit configuration, the variant can be
     /// changed without modifying the union. This means that every type in the union must
     /// be valid in any bit configuration. I don't enforce this, but this means that every
-    /// variant should implement `wasmer::ValueType`.
+    /// variant should implement `ValueType`.
     ///
     #[doc = "Original enum:"]
     #[doc = #original_docs]
@@ -605,9 +605,9 @@ pub fn cenum(_args: TokenStream, input: TokenStream) -> TokenStream {
     #[cfg(feature = "host")]
     impl Copy for #data_name {}
     #[cfg(feature = "host")]
-    unsafe impl wasmer::ValueType for #name {}
+    unsafe impl crate::ValueType for #name {}
     #[cfg(feature = "host")]
-    unsafe impl wasmer::ValueType for #data_name {}
+    unsafe impl crate::ValueType for #data_name {}
 
     impl #enum_name {
       /// Converts this enum into a C safe version, using a union.
@@ -73,7 +73,7 @@ pub fn ctype(_args: TokenStream, input: TokenStream) -> TokenStream {
     #[cfg(feature = "host")]
     impl Copy for #ty {}
     #[cfg(feature = "host")]
-    unsafe impl wasmer::ValueType for #ty {}
+    unsafe impl crate::ValueType for #ty {}
   }
   .into()
 }
@@ -95,7 +95,7 @@ fn host_type(ty: &Type) -> Option<Type> {
       path:  {
         let mut segments = Punctuated::new();
         segments.push(syn::PathSegment {
-          ident:     Ident::new("wasmer", Span::call_site()),
+          ident:     Ident::new("crate", Span::call_site()),
           arguments: syn::PathArguments::None,
         });
         segments.push(syn::PathSegment {
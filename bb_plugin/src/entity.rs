@@ -1 +1,33 @@
+//! Entities in the world, such as armor stands and floating text.
 
+use crate::{FromFfi, IntoFfi};
+
+/// A handle to an entity. This is returned when spawning entities, and can be
+/// passed to functions like
+/// [`World::set_equipment`](crate::world::World::set_equipment).
+///
+/// Once the entity is removed, this handle becomes invalid, and functions
+/// called with it will return
+/// [`ErrorKind::StaleEntity`](crate::ErrorKind::StaleEntity). A handle will
+/// never refer to a different entity, even if that entity has the same id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+  handle: u64,
+}
+
+impl FromFfi for Entity {
+  type Ffi = u64;
+
+  fn from_ffi(handle: u64) -> Self { Entity { handle } }
+}
+impl IntoFfi for Entity {
+  type Ffi = u64;
+
+  fn into_ffi(self) -> u64 { self.handle }
+}
+
+impl Entity {
+  /// Returns the id of this entity. Entities in different worlds may have the
+  /// same id, so the handle itself should be stored instead.
+  pub fn eid(&self) -> i32 { self.handle as u32 as i32 }
+}
@@ -0,0 +1,106 @@
+//! Errors returned by the server. Most functions that talk to the server can
+//! fail, for example if a player logs off, or if a position is outside the
+//! world. These functions return an [`Error`], which says what went wrong.
+
+use bb_ffi::{CError, CErrorCode, CResult};
+use std::fmt;
+
+/// The kind of an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+  /// One of the arguments was invalid, such as an unknown entity or item name.
+  InvalidArgument,
+  /// The player is not online.
+  NoPlayer,
+  /// The world doesn't exist.
+  NoWorld,
+  /// The entity doesn't exist.
+  NoEntity,
+  /// The entity handle refers to an entity that has been removed.
+  StaleEntity,
+  /// The position is outside of the world.
+  InvalidPos,
+  /// The world is locked, so no blocks can be changed.
+  Locked,
+  /// This plugin isn't allowed to change this world. Plugins can only change
+  /// the worlds listed in `worlds` in their `plugin.toml`.
+  NotAllowed,
+  /// The player hasn't selected a region.
+  NoSelection,
+  /// A file couldn't be read or written.
+  Io,
+  /// The server returned an error this plugin doesn't know about. This can
+  /// happen if the server is newer than the plugin.
+  Other,
+}
+
+/// An error from the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+  kind:    ErrorKind,
+  message: String,
+}
+
+impl From<CErrorCode> for ErrorKind {
+  fn from(code: CErrorCode) -> Self {
+    match code {
+      CErrorCode::InvalidArgument => ErrorKind::InvalidArgument,
+      CErrorCode::NoPlayer => ErrorKind::NoPlayer,
+      CErrorCode::NoWorld => ErrorKind::NoWorld,
+      CErrorCode::NoEntity => ErrorKind::NoEntity,
+      CErrorCode::InvalidPos => ErrorKind::InvalidPos,
+      CErrorCode::Locked => ErrorKind::Locked,
+      CErrorCode::NotAllowed => ErrorKind::NotAllowed,
+      CErrorCode::NoSelection => ErrorKind::NoSelection,
+      CErrorCode::Io => ErrorKind::Io,
+      CErrorCode::StaleEntity => ErrorKind::StaleEntity,
+    }
+  }
+}
+
+impl From<CError> for Error {
+  fn from(e: CError) -> Self {
+    Error {
+      kind:    CErrorCode::from_i32(e.code).map(Into::into).unwrap_or(ErrorKind::Other),
+      message: e.message.into_string(),
+    }
+  }
+}
+
+impl Error {
+  /// Returns what kind of error this is.
+  pub fn kind(&self) -> ErrorKind { self.kind }
+  /// Returns a message from the server, describing what went wrong.
+  pub fn message(&self) -> &str { &self.message }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.message) }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Converts the return value of a host function that can only fail. A null
+/// pointer means the function succeeded.
+///
+/// # Safety
+///
+/// `ptr` must be null, or be returned from a host function.
+pub(crate) unsafe fn check(ptr: *mut CError) -> Result<()> {
+  if ptr.is_null() {
+    Ok(())
+  } else {
+    Err((*Box::from_raw(ptr)).into())
+  }
+}
+
+/// Converts the return value of a host function that returns a [`CResult`].
+///
+/// # Safety
+///
+/// `ptr` must be returned from a host function.
+pub(crate) unsafe fn unwrap<T>(ptr: *mut CResult<T>) -> Result<T> {
+  Box::from_raw(ptr).into_result().map_err(Into::into)
+}
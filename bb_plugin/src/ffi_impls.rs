@@ -58,20 +58,11 @@ impl<T> IntoFfi for Vec<T> {
 impl FromFfi for UUID {
   type Ffi = CUUID;
 
-  fn from_ffi(c: CUUID) -> UUID {
-    let n = (c.bytes[0] as u128)
-      | (c.bytes[1] as u128) << 32
-      | (c.bytes[2] as u128) << (2 * 32)
-      | (c.bytes[3] as u128) << (3 * 32);
-    UUID::from_u128(n)
-  }
+  fn from_ffi(c: CUUID) -> UUID { UUID::from_u128(c.as_u128()) }
 }
 
 impl IntoFfi for UUID {
   type Ffi = CUUID;
 
-  fn into_ffi(self) -> CUUID {
-    let n = self.as_u128();
-    CUUID { bytes: [n as u32, (n >> 32) as u32, (n >> (2 * 32)) as u32, (n >> (3 * 32)) as u32] }
-  }
+  fn into_ffi(self) -> CUUID { CUUID::from_u128(self.as_u128()) }
 }
@@ -1,6 +1,6 @@
 use crate::sync::{ConstLock, LazyLock};
 use bb_common::{
-  chunk::{paletted, Chunk},
+  chunk::{paletted, Chunk, Stage},
   math::ChunkPos,
   transfer::MessageWriter,
 };
@@ -10,7 +10,12 @@ type GeneratorFn = Box<dyn Fn(&mut Chunk<paletted::Section>, ChunkPos) + Send>;
 
 static CHUNK_BUF: ConstLock<Vec<u8>> = ConstLock::new(vec![]);
 static GENERATORS: LazyLock<HashMap<String, GeneratorFn>> = LazyLock::new(|| HashMap::new());
+static STAGES: LazyLock<HashMap<(String, Stage), Vec<GeneratorFn>>> =
+  LazyLock::new(|| HashMap::new());
 
+/// Adds a world generator with the given name. Worlds with `generator` set to
+/// this name in the server config will call `func` to generate each chunk.
+/// Any stages added with [`add_world_generator_stage`] are run after `func`.
 pub fn add_world_generator(
   name: &str,
   func: impl Fn(&mut Chunk<paletted::Section>, ChunkPos) + Send + 'static,
@@ -19,18 +24,67 @@ pub fn add_world_generator(
   map.insert(name.into(), Box::new(func));
 }
 
+/// Adds a stage to the world generator with the given name. The stage is run
+/// after every stage already added to `stage`, and is passed the chunk that
+/// all of the previous stages produced. A generator can be built entirely out
+/// of stages, in which case [`add_world_generator`] doesn't need to be called.
+///
+/// # Example
+///
+/// ```no_run
+/// use bb_plugin::{block, chunk::Stage, math::RelPos};
+///
+/// bb_plugin::add_world_generator_stage("my-world", Stage::Shape, |chunk, _pos| {
+///   let stone = block::Kind::Stone.data().default_type().id();
+///   for x in 0..16 {
+///     for z in 0..16 {
+///       chunk.set_block(RelPos::new(x, 60, z), stone).unwrap();
+///     }
+///   }
+/// });
+/// ```
+pub fn add_world_generator_stage(
+  name: &str,
+  stage: Stage,
+  func: impl Fn(&mut Chunk<paletted::Section>, ChunkPos) + Send + 'static,
+) {
+  let mut map = STAGES.lock();
+  map.entry((name.into(), stage)).or_default().push(Box::new(func));
+}
+
+/// Replaces all the stages added to `stage` for the world generator with the
+/// given name. This can be used to override a single stage of a generator,
+/// such as replacing the ores in [`Stage::Features`].
+pub fn replace_world_generator_stage(
+  name: &str,
+  stage: Stage,
+  func: impl Fn(&mut Chunk<paletted::Section>, ChunkPos) + Send + 'static,
+) {
+  let mut map = STAGES.lock();
+  map.insert((name.into(), stage), vec![Box::new(func)]);
+}
+
 #[no_mangle]
 extern "C" fn generate_chunk_and_lock(name: *const i8, x: i32, z: i32) -> *const u8 {
   let generator_name = unsafe { std::ffi::CStr::from_ptr(name as _) };
   let mut sections = vec![];
+  let name = generator_name.to_str().unwrap();
+  let pos = ChunkPos::new(x, z);
   let map = GENERATORS.lock();
-  let chunk = if let Some(gen) = map.get(generator_name.to_str().unwrap()) {
-    let mut chunk = Chunk::<paletted::Section>::new(8);
-    gen(&mut chunk, ChunkPos::new(x, z));
-    chunk
-  } else {
+  let stages = STAGES.lock();
+  let gen = map.get(name);
+  if gen.is_none() && !Stage::ALL.iter().any(|s| stages.contains_key(&(name.into(), *s))) {
     return 0 as _;
-  };
+  }
+  let mut chunk = Chunk::<paletted::Section>::new(8);
+  if let Some(gen) = gen {
+    gen(&mut chunk, pos);
+  }
+  for stage in Stage::ALL {
+    for func in stages.get(&(name.into(), stage)).into_iter().flatten() {
+      func(&mut chunk, pos);
+    }
+  }
   for section in chunk.sections().flatten() {
     sections.push(section);
   }
@@ -14,8 +14,10 @@ mod store;
 pub mod block;
 pub mod command;
 pub mod entity;
+pub mod error;
 pub mod item;
 pub mod math;
+pub mod noise;
 pub mod particle;
 pub mod player;
 pub mod sync;
@@ -23,7 +25,10 @@ pub mod time;
 pub mod world;
 
 pub use command::add_command;
-pub use internal::gen::add_world_generator;
+pub use error::{Error, ErrorKind};
+pub use internal::gen::{
+  add_world_generator, add_world_generator_stage, replace_world_generator_stage,
+};
 pub use store::{store, PlayerStore, PluginStore};
 
 pub struct Bamboo {
@@ -140,6 +145,59 @@ extern "C" fn on_block_place(id: ffi::CUUID, x: i32, y: i32, z: i32) -> bool {
   }
 }
 
+callback!(set_on_entity_attack, ON_ENTITY_ATTACK, Fn(player::Player, entity::Entity, f32) -> bool);
+/// Called when a player attacks an entity, with the entity and the damage
+/// being dealt. If the callback returns `false`, the entity won't be damaged.
+#[no_mangle]
+extern "C" fn on_entity_attack(id: ffi::CUUID, entity: u64, damage: f32) -> bool {
+  if let Some(cb) = ON_ENTITY_ATTACK.lock().as_ref() {
+    cb(player::Player::from_ffi(id), entity::Entity::from_ffi(entity), damage)
+  } else {
+    true
+  }
+}
+
+callback!(set_on_item_pickup, ON_ITEM_PICKUP, Fn(player::Player, entity::Entity, u8) -> bool);
+/// Called when a player is about to pick up an item, with the item entity and
+/// the amount of items. If the callback returns `false`, the item will stay on
+/// the ground.
+#[no_mangle]
+extern "C" fn on_item_pickup(id: ffi::CUUID, entity: u64, amount: i32) -> bool {
+  if let Some(cb) = ON_ITEM_PICKUP.lock().as_ref() {
+    cb(player::Player::from_ffi(id), entity::Entity::from_ffi(entity), amount as u8)
+  } else {
+    true
+  }
+}
+
+callback!(set_on_projectile_hit, ON_PROJECTILE_HIT, Fn(entity::Entity, entity::Entity) -> bool);
+/// Called when a projectile hits an entity, with the projectile and the entity
+/// hit. If the callback returns `false`, the entity won't be damaged.
+#[no_mangle]
+extern "C" fn on_projectile_hit(projectile: u64, hit: u64) -> bool {
+  if let Some(cb) = ON_PROJECTILE_HIT.lock().as_ref() {
+    cb(entity::Entity::from_ffi(projectile), entity::Entity::from_ffi(hit))
+  } else {
+    true
+  }
+}
+
+callback!(set_on_player_pre_join, ON_PLAYER_PRE_JOIN, Fn(util::UUID, &str) -> bool);
+/// Called before a player joins, with their UUID and username. If the callback
+/// returns `false`, the player will be kicked.
+#[no_mangle]
+extern "C" fn on_player_pre_join(id: ffi::CUUID, name: *const u8, name_len: u32) -> bool {
+  if let Some(cb) = ON_PLAYER_PRE_JOIN.lock().as_ref() {
+    // SAFETY: The server writes a valid utf8 string at `name`, and frees it
+    // after this function returns.
+    let name =
+      unsafe { std::str::from_utf8_unchecked(std::slice::from_raw_parts(name, name_len as usize)) };
+    cb(util::UUID::from_ffi(id), name)
+  } else {
+    true
+  }
+}
+
 callback!(set_on_tick, ON_TICK, Fn());
 #[no_mangle]
 extern "C" fn on_tick() {
@@ -7,3 +7,7 @@ pub struct Vec3 {
 }
 
 impl Vec3 {}
+
+impl From<Vec3> for FPos {
+  fn from(v: Vec3) -> FPos { FPos::new(v.x, v.y, v.z) }
+}
@@ -0,0 +1,88 @@
+use super::Noise;
+
+/// Layers multiple octaves of another noise function, each at a higher
+/// frequency and lower amplitude than the last. This adds detail to the
+/// noise, which is what makes terrain look natural.
+///
+/// The defaults are 4 octaves, a lacunarity of 2, and a persistence of 0.5.
+#[derive(Debug, Clone)]
+pub struct Fractal<N> {
+  noise:       N,
+  octaves:     u32,
+  lacunarity:  f64,
+  persistence: f64,
+  ridged:      bool,
+}
+
+/// Each octave is offset by this much, so that every octave isn't zero at the
+/// origin.
+const OCTAVE_OFFSET: f64 = 19.19;
+
+impl<N: Noise> Fractal<N> {
+  /// Creates fractal noise from the given noise.
+  pub fn new(noise: N) -> Self {
+    Fractal { noise, octaves: 4, lacunarity: 2.0, persistence: 0.5, ridged: false }
+  }
+
+  /// Sets the number of octaves. More octaves add more detail, but each
+  /// octave is another sample of the noise function. This must be at least 1.
+  pub fn octaves(mut self, octaves: u32) -> Self {
+    self.octaves = octaves.max(1);
+    self
+  }
+  /// Sets how much the frequency is multiplied by for each octave.
+  pub fn lacunarity(mut self, lacunarity: f64) -> Self {
+    self.lacunarity = lacunarity;
+    self
+  }
+  /// Sets how much the amplitude is multiplied by for each octave.
+  pub fn persistence(mut self, persistence: f64) -> Self {
+    self.persistence = persistence;
+    self
+  }
+  /// Makes this ridged noise. This folds each octave at zero, which produces
+  /// sharp ridges, like mountain ranges.
+  pub fn ridged(mut self) -> Self {
+    self.ridged = true;
+    self
+  }
+
+  fn sum(&self, sample: impl Fn(f64, f64) -> f64) -> f64 {
+    let mut total = 0.0;
+    let mut max = 0.0;
+    let mut freq = 1.0;
+    let mut amp = 1.0;
+    // For ridged noise, valleys in one octave smooth out the next octave.
+    let mut weight = 1.0;
+    for i in 0..self.octaves {
+      let v = sample(freq, i as f64 * OCTAVE_OFFSET);
+      if self.ridged {
+        let v = (1.0 - v.abs()).powi(2) * weight;
+        weight = v.clamp(0.0, 1.0);
+        total += v * amp;
+      } else {
+        total += v * amp;
+      }
+      max += amp;
+      freq *= self.lacunarity;
+      amp *= self.persistence;
+    }
+    if self.ridged {
+      // Ridged octaves are in the range 0..1, so move them to -1..1.
+      total / max * 2.0 - 1.0
+    } else {
+      total / max
+    }
+  }
+}
+
+impl<N: Noise> Noise for Fractal<N> {
+  fn get_2d(&self, x: f64, z: f64) -> f64 {
+    self.sum(|freq, offset| self.noise.get_2d(x * freq + offset, z * freq + offset))
+  }
+  fn get_3d(&self, x: f64, y: f64, z: f64) -> f64 {
+    self.sum(|freq, offset| {
+      self.noise.get_3d(x * freq + offset, y * freq + offset, z * freq + offset)
+    })
+  }
+}
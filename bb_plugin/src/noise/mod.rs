@@ -0,0 +1,125 @@
+//! Noise functions for terrain generators. All of these are deterministic, so
+//! the same seed will always produce the same noise. Seed them with
+//! [`World::seed_for`](crate::world::World::seed_for), so that your generator
+//! changes along with the world seed.
+//!
+//! # Example
+//!
+//! ```
+//! use bb_plugin::noise::{Fractal, Noise, Perlin};
+//!
+//! let noise = Fractal::new(Perlin::new(1234)).octaves(5);
+//! let height = |x: i32, z: i32| 64.0 + noise.get_2d(x as f64 / 128.0, z as f64 / 128.0) * 24.0;
+//! assert_eq!(height(10, 20), height(10, 20));
+//! ```
+
+mod fractal;
+mod perlin;
+mod simplex;
+
+pub use fractal::Fractal;
+pub use perlin::Perlin;
+pub use simplex::Simplex;
+
+/// A noise function. Every sample is roughly in the range `-1.0..=1.0`, and
+/// changes smoothly as the position changes. Noise repeats every 256 units,
+/// so positions should be scaled down before sampling.
+pub trait Noise {
+  /// Samples the noise at the given position.
+  fn get_2d(&self, x: f64, z: f64) -> f64;
+  /// Samples the noise at the given position.
+  fn get_3d(&self, x: f64, y: f64, z: f64) -> f64;
+}
+
+impl<N: Noise + ?Sized> Noise for &N {
+  fn get_2d(&self, x: f64, z: f64) -> f64 { (**self).get_2d(x, z) }
+  fn get_3d(&self, x: f64, y: f64, z: f64) -> f64 { (**self).get_3d(x, y, z) }
+}
+
+/// Creates a shuffled table of the numbers `0..256`, repeated twice so that
+/// lookups can overflow past 255 without wrapping.
+fn permutation(seed: u64) -> [u8; 512] {
+  let mut perm = [0; 512];
+  for (i, v) in perm.iter_mut().take(256).enumerate() {
+    *v = i as u8;
+  }
+  // Splitmix64. This only needs to shuffle a table, so it doesn't need to be
+  // very good.
+  let mut state = seed;
+  let mut next = || {
+    state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+  };
+  for i in (1..256).rev() {
+    let j = (next() % (i as u64 + 1)) as usize;
+    perm.swap(i, j);
+  }
+  let (a, b) = perm.split_at_mut(256);
+  b.copy_from_slice(a);
+  perm
+}
+
+/// Returns the integer part and fractional part of `v`, with the integer part
+/// wrapped to `0..256`.
+#[inline(always)]
+fn split(v: f64) -> (usize, f64) {
+  let floor = v.floor();
+  ((floor as i64 & 255) as usize, v - floor)
+}
+
+#[inline(always)]
+fn lerp(t: f64, a: f64, b: f64) -> f64 { a + t * (b - a) }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn check(noise: impl Noise, other: impl Noise) {
+    let mut same = true;
+    for i in 0..1000 {
+      let x = i as f64 * 0.173;
+      let y = i as f64 * 0.311 - 40.0;
+      let z = i as f64 * -0.097;
+      let v2 = noise.get_2d(x, z);
+      let v3 = noise.get_3d(x, y, z);
+      assert!((-1.0..=1.0).contains(&v2), "{v2} out of range");
+      assert!((-1.0..=1.0).contains(&v3), "{v3} out of range");
+      if v3 != other.get_3d(x, y, z) {
+        same = false;
+      }
+    }
+    assert!(!same, "different seeds should produce different noise");
+  }
+
+  #[test]
+  fn permutation_table() {
+    let perm = permutation(5);
+    let mut seen = [false; 256];
+    for &v in &perm[..256] {
+      seen[v as usize] = true;
+    }
+    assert!(seen.iter().all(|&v| v));
+    assert_eq!(perm[..256], perm[256..]);
+    assert_eq!(perm, permutation(5));
+    assert_ne!(perm, permutation(6));
+  }
+
+  #[test]
+  fn ranges() {
+    check(Perlin::new(1), Perlin::new(2));
+    check(Simplex::new(1), Simplex::new(2));
+    check(Fractal::new(Perlin::new(1)), Fractal::new(Perlin::new(2)));
+    check(Fractal::new(Simplex::new(1)).ridged(), Fractal::new(Simplex::new(2)).ridged());
+  }
+
+  #[test]
+  fn deterministic() {
+    let a = Fractal::new(Simplex::new(99)).octaves(6);
+    let b = Fractal::new(Simplex::new(99)).octaves(6);
+    assert_eq!(a.get_3d(1.5, 2.5, 3.5), b.get_3d(1.5, 2.5, 3.5));
+    assert_eq!(a.get_2d(-7.25, 100.0), b.get_2d(-7.25, 100.0));
+  }
+}
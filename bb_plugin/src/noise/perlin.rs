@@ -0,0 +1,107 @@
+use super::{lerp, permutation, split, Noise};
+use crate::{error::Result, world::World};
+
+/// Perlin noise. This is the classic gradient noise, which is a bit cheaper
+/// than [`Simplex`](super::Simplex), but has more visible grid artifacts.
+#[derive(Debug, Clone)]
+pub struct Perlin {
+  perm: [u8; 512],
+}
+
+impl Perlin {
+  /// Creates perlin noise with the given seed.
+  pub fn new(seed: u64) -> Self { Perlin { perm: permutation(seed) } }
+  /// Creates perlin noise seeded from the world seed. `name` should be unique
+  /// to each noise function in a generator, so that they don't all produce
+  /// the same values.
+  pub fn from_world(world: &World, name: &str) -> Result<Self> {
+    Ok(Perlin::new(world.seed_for(name)?))
+  }
+
+  #[inline(always)]
+  fn hash(&self, i: usize) -> usize { self.perm[i] as usize }
+}
+
+impl Noise for Perlin {
+  fn get_2d(&self, x: f64, z: f64) -> f64 {
+    let (xi, xf) = split(x);
+    let (zi, zf) = split(z);
+    let u = fade(xf);
+    let v = fade(zf);
+
+    let a = self.hash(xi) + zi;
+    let b = self.hash(xi + 1) + zi;
+    let g = |i: usize, x: f64, z: f64| grad_2d(self.hash(i), x, z);
+    lerp(
+      v,
+      lerp(u, g(a, xf, zf), g(b, xf - 1.0, zf)),
+      lerp(u, g(a + 1, xf, zf - 1.0), g(b + 1, xf - 1.0, zf - 1.0)),
+    )
+  }
+
+  fn get_3d(&self, x: f64, y: f64, z: f64) -> f64 {
+    let (xi, xf) = split(x);
+    let (yi, yf) = split(y);
+    let (zi, zf) = split(z);
+    let u = fade(xf);
+    let v = fade(yf);
+    let w = fade(zf);
+
+    let a = self.hash(xi) + yi;
+    let aa = self.hash(a) + zi;
+    let ab = self.hash(a + 1) + zi;
+    let b = self.hash(xi + 1) + yi;
+    let ba = self.hash(b) + zi;
+    let bb = self.hash(b + 1) + zi;
+    let g = |i: usize, x: f64, y: f64, z: f64| grad_3d(self.hash(i), x, y, z);
+    let value = lerp(
+      w,
+      lerp(
+        v,
+        lerp(u, g(aa, xf, yf, zf), g(ba, xf - 1.0, yf, zf)),
+        lerp(u, g(ab, xf, yf - 1.0, zf), g(bb, xf - 1.0, yf - 1.0, zf)),
+      ),
+      lerp(
+        v,
+        lerp(u, g(aa + 1, xf, yf, zf - 1.0), g(ba + 1, xf - 1.0, yf, zf - 1.0)),
+        lerp(u, g(ab + 1, xf, yf - 1.0, zf - 1.0), g(bb + 1, xf - 1.0, yf - 1.0, zf - 1.0)),
+      ),
+    );
+    // The edge gradients can add up to slightly more than 1 in rare cases.
+    value.clamp(-1.0, 1.0)
+  }
+}
+
+#[inline(always)]
+fn fade(t: f64) -> f64 { t * t * t * (t * (t * 6.0 - 15.0) + 10.0) }
+
+/// One of 8 gradients, pointing to the corners and edges of a square.
+#[inline(always)]
+fn grad_2d(hash: usize, x: f64, z: f64) -> f64 {
+  match hash & 7 {
+    0 => x + z,
+    1 => -x + z,
+    2 => x - z,
+    3 => -x - z,
+    4 => x,
+    5 => -x,
+    6 => z,
+    _ => -z,
+  }
+}
+
+/// One of 12 gradients, pointing to the edges of a cube. This uses 16 cases,
+/// so that a hash can be used directly, and the 4 extra cases repeat.
+#[inline(always)]
+fn grad_3d(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+  let h = hash & 15;
+  let u = if h < 8 { x } else { y };
+  let v = if h < 4 {
+    y
+  } else if h == 12 || h == 14 {
+    x
+  } else {
+    z
+  };
+  (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
@@ -0,0 +1,138 @@
+use super::{permutation, Noise};
+use crate::{error::Result, world::World};
+
+/// The edges of a cube, used as gradients for both 2D and 3D noise. For 2D
+/// noise, only `x` and `y` are used.
+const GRAD: [[f64; 3]; 12] = [
+  [1.0, 1.0, 0.0],
+  [-1.0, 1.0, 0.0],
+  [1.0, -1.0, 0.0],
+  [-1.0, -1.0, 0.0],
+  [1.0, 0.0, 1.0],
+  [-1.0, 0.0, 1.0],
+  [1.0, 0.0, -1.0],
+  [-1.0, 0.0, -1.0],
+  [0.0, 1.0, 1.0],
+  [0.0, -1.0, 1.0],
+  [0.0, 1.0, -1.0],
+  [0.0, -1.0, -1.0],
+];
+
+/// Simplex noise. This has fewer grid artifacts than [`Perlin`](super::Perlin)
+/// noise, and is faster in 3D.
+#[derive(Debug, Clone)]
+pub struct Simplex {
+  perm: [u8; 512],
+}
+
+impl Simplex {
+  /// Creates simplex noise with the given seed.
+  pub fn new(seed: u64) -> Self { Simplex { perm: permutation(seed) } }
+  /// Creates simplex noise seeded from the world seed. `name` should be unique
+  /// to each noise function in a generator, so that they don't all produce
+  /// the same values.
+  pub fn from_world(world: &World, name: &str) -> Result<Self> {
+    Ok(Simplex::new(world.seed_for(name)?))
+  }
+
+  #[inline(always)]
+  fn grad(&self, i: usize) -> &[f64; 3] { &GRAD[self.perm[i] as usize % 12] }
+}
+
+/// Wraps `v` to `0..256`.
+#[inline(always)]
+fn wrap(v: f64) -> usize { (v as i64 & 255) as usize }
+
+impl Noise for Simplex {
+  fn get_2d(&self, x: f64, z: f64) -> f64 {
+    const F2: f64 = 0.366_025_403_784_438_6; // (sqrt(3) - 1) / 2
+    const G2: f64 = 0.211_324_865_405_187_1; // (3 - sqrt(3)) / 6
+
+    // Find which simplex we are in.
+    let s = (x + z) * F2;
+    let i = (x + s).floor();
+    let j = (z + s).floor();
+    let t = (i + j) * G2;
+    let x0 = x - (i - t);
+    let z0 = z - (j - t);
+    let (i1, j1) = if x0 > z0 { (1, 0) } else { (0, 1) };
+    let x1 = x0 - i1 as f64 + G2;
+    let z1 = z0 - j1 as f64 + G2;
+    let x2 = x0 - 1.0 + 2.0 * G2;
+    let z2 = z0 - 1.0 + 2.0 * G2;
+
+    let ii = wrap(i);
+    let jj = wrap(j);
+    let corner = |x: f64, z: f64, g: &[f64; 3]| {
+      let t = 0.5 - x * x - z * z;
+      if t < 0.0 {
+        0.0
+      } else {
+        t.powi(4) * (g[0] * x + g[1] * z)
+      }
+    };
+    let n0 = corner(x0, z0, self.grad(ii + self.perm[jj] as usize));
+    let n1 = corner(x1, z1, self.grad(ii + i1 + self.perm[jj + j1] as usize));
+    let n2 = corner(x2, z2, self.grad(ii + 1 + self.perm[jj + 1] as usize));
+    // Scales the result to about -1..1.
+    (70.0 * (n0 + n1 + n2)).clamp(-1.0, 1.0)
+  }
+
+  fn get_3d(&self, x: f64, y: f64, z: f64) -> f64 {
+    const F3: f64 = 1.0 / 3.0;
+    const G3: f64 = 1.0 / 6.0;
+
+    // Find which simplex we are in.
+    let s = (x + y + z) * F3;
+    let i = (x + s).floor();
+    let j = (y + s).floor();
+    let k = (z + s).floor();
+    let t = (i + j + k) * G3;
+    let x0 = x - (i - t);
+    let y0 = y - (j - t);
+    let z0 = z - (k - t);
+    let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+      if y0 >= z0 {
+        (1, 0, 0, 1, 1, 0)
+      } else if x0 >= z0 {
+        (1, 0, 0, 1, 0, 1)
+      } else {
+        (0, 0, 1, 1, 0, 1)
+      }
+    } else if y0 < z0 {
+      (0, 0, 1, 0, 1, 1)
+    } else if x0 < z0 {
+      (0, 1, 0, 0, 1, 1)
+    } else {
+      (0, 1, 0, 1, 1, 0)
+    };
+    let x1 = x0 - i1 as f64 + G3;
+    let y1 = y0 - j1 as f64 + G3;
+    let z1 = z0 - k1 as f64 + G3;
+    let x2 = x0 - i2 as f64 + 2.0 * G3;
+    let y2 = y0 - j2 as f64 + 2.0 * G3;
+    let z2 = z0 - k2 as f64 + 2.0 * G3;
+    let x3 = x0 - 1.0 + 3.0 * G3;
+    let y3 = y0 - 1.0 + 3.0 * G3;
+    let z3 = z0 - 1.0 + 3.0 * G3;
+
+    let ii = wrap(i);
+    let jj = wrap(j);
+    let kk = wrap(k);
+    let p = |i: usize| self.perm[i] as usize;
+    let corner = |x: f64, y: f64, z: f64, g: &[f64; 3]| {
+      let t = 0.6 - x * x - y * y - z * z;
+      if t < 0.0 {
+        0.0
+      } else {
+        t.powi(4) * (g[0] * x + g[1] * y + g[2] * z)
+      }
+    };
+    let n0 = corner(x0, y0, z0, self.grad(ii + p(jj + p(kk))));
+    let n1 = corner(x1, y1, z1, self.grad(ii + i1 + p(jj + j1 + p(kk + k1))));
+    let n2 = corner(x2, y2, z2, self.grad(ii + i2 + p(jj + j2 + p(kk + k2))));
+    let n3 = corner(x3, y3, z3, self.grad(ii + 1 + p(jj + 1 + p(kk + 1))));
+    // Scales the result to about -1..1.
+    (32.0 * (n0 + n1 + n2 + n3)).clamp(-1.0, 1.0)
+  }
+}
@@ -1,6 +1,16 @@
-use crate::{math::Vec3, particle::Particle, world::World, FromFfi, IntoFfi};
-use bb_common::{math::FPos, util::UUID};
-use bb_ffi::CUUID;
+use crate::{
+  error::{check, unwrap, Result},
+  math::Vec3,
+  particle::Particle,
+  world::{BlockChange, RegionEdit, World},
+  FromFfi, IntoFfi,
+};
+use bb_common::{
+  math::{FPos, Pos},
+  util::UUID,
+};
+use bb_ffi::{COpt, CUUID};
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct Player {
@@ -15,15 +25,14 @@ impl FromFfi for Player {
 
 impl Player {
   /// Returns the world this player is in.
-  pub fn world(&self) -> World {
-    // TODO: What to do if the player has disconnected?
+  pub fn world(&self) -> Result<World> {
+    unsafe { unwrap(bb_ffi::bb_player_world(&self.id.into_ffi())).map(World::new) }
+  }
+  /// Moves this player into another world, at the given position. The client
+  /// will see a loading screen while the new world is sent.
+  pub fn teleport_world(&self, world: &World, pos: FPos) -> Result<()> {
     unsafe {
-      let wid = bb_ffi::bb_player_world(&self.id.into_ffi());
-      if wid >= 0 {
-        World::new(wid as u32)
-      } else {
-        panic!("player is not in a world")
-      }
+      check(bb_ffi::bb_player_teleport_world(&self.id.into_ffi(), world.wid, &pos.into_ffi()))
     }
   }
   /// Returns the id of this player.
@@ -31,12 +40,8 @@ impl Player {
   /// This will always return their UUID, even if this player has disconnected.
   pub fn id(&self) -> UUID { self.id }
   /// Returns the username of this player.
-  pub fn username(&self) -> String {
-    // TODO: What to do if the player has disconnected?
-    unsafe {
-      let cstr = Box::from_raw(bb_ffi::bb_player_username(&self.id.into_ffi()));
-      cstr.into_string()
-    }
+  pub fn username(&self) -> Result<String> {
+    unsafe { unwrap(bb_ffi::bb_player_username(&self.id.into_ffi())).map(|s| s.into_string()) }
   }
   /// Spawns a particle for this player. Other players will not be able to see
   /// this particle.
@@ -48,19 +53,167 @@ impl Player {
     }
   }
   /// Returns the player's position.
-  pub fn pos(&self) -> FPos {
-    // TODO: What to do if the player has disconnected?
+  pub fn pos(&self) -> Result<FPos> {
+    unsafe { unwrap(bb_ffi::bb_player_pos(&self.id.into_ffi())).map(FPos::from_ffi) }
+  }
+  /// Returns the player's looking direction, as a unit vector.
+  pub fn look_as_vec(&self) -> Result<Vec3> {
+    unsafe { unwrap(bb_ffi::bb_player_look_as_vec(&self.id.into_ffi())).map(Vec3::from_ffi) }
+  }
+  /// Returns the value of the given attribute, like `generic.max_health`,
+  /// after all modifiers have been applied. Returns
+  /// [`ErrorKind::InvalidArgument`](crate::ErrorKind::InvalidArgument) if the
+  /// attribute doesn't exist.
+  pub fn attribute(&self, name: &str) -> Result<f64> {
     unsafe {
-      let cpos = Box::from_raw(bb_ffi::bb_player_pos(&self.id.into_ffi()));
-      FPos::from_ffi(*cpos)
+      unwrap(bb_ffi::bb_player_attribute(&self.id.into_ffi(), name.as_ptr(), name.len() as u32))
     }
   }
-  /// Returns the player's looking direction, as a unit vector.
-  pub fn look_as_vec(&self) -> Vec3 {
-    // TODO: What to do if the player has disconnected?
+  /// Sets the base value of the given attribute.
+  pub fn set_attribute_base(&self, name: &str, value: f64) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_player_set_attribute_base(
+        &self.id.into_ffi(),
+        name.as_ptr(),
+        name.len() as u32,
+        value,
+      ))
+    }
+  }
+  /// Changes the player's skin. `value` and `signature` are the base64 encoded
+  /// `textures` property from a Mojang profile. Clients will not show skins
+  /// without a valid signature.
+  pub fn set_skin(&self, value: &str, signature: Option<&str>) -> Result<()> {
+    let signature = signature.unwrap_or("");
+    unsafe {
+      check(bb_ffi::bb_player_set_skin(
+        &self.id.into_ffi(),
+        value.as_ptr(),
+        value.len() as u32,
+        signature.as_ptr(),
+        signature.len() as u32,
+      ))
+    }
+  }
+  /// Resets the player's skin to the one from their Mojang profile.
+  pub fn reset_skin(&self) -> Result<()> {
+    unsafe { check(bb_ffi::bb_player_reset_skin(&self.id.into_ffi())) }
+  }
+  /// Returns the brand this player's client sent, like `vanilla` or
+  /// `fabric`. This is empty until the client sends it.
+  pub fn brand(&self) -> Result<String> {
+    unsafe { unwrap(bb_ffi::bb_player_brand(&self.id.into_ffi())).map(|s| s.into_string()) }
+  }
+  /// Returns the ids of the mods this player has installed. This is only
+  /// known for Forge clients, when the proxy has `forge-mod-list` enabled.
+  pub fn mods(&self) -> Result<Vec<String>> {
+    unsafe {
+      let mods = unwrap(bb_ffi::bb_player_mods(&self.id.into_ffi()))?.into_vec();
+      Ok(mods.into_iter().map(|s| s.into_string()).collect())
+    }
+  }
+  /// Hides `other` from this player. `other` will be removed from the tab
+  /// list, and will no longer be shown in the world.
+  pub fn hide(&self, other: &Player) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_player_set_hidden(
+        &self.id.into_ffi(),
+        &other.id.into_ffi(),
+        bb_ffi::CBool::new(true),
+      ))
+    }
+  }
+  /// Shows `other` to this player, after they were hidden with
+  /// [`hide`](Self::hide).
+  pub fn show(&self, other: &Player) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_player_set_hidden(
+        &self.id.into_ffi(),
+        &other.id.into_ffi(),
+        bb_ffi::CBool::new(false),
+      ))
+    }
+  }
+
+  /// Selects the region between the two corners. This selection is used by
+  /// [`edit_selection`](Self::edit_selection).
+  pub fn set_selection(&self, pos1: Pos, pos2: Pos) -> Result<()> {
     unsafe {
-      let cpos = Box::from_raw(bb_ffi::bb_player_look_as_vec(&self.id.into_ffi()));
-      Vec3::from_ffi(*cpos)
+      check(bb_ffi::bb_player_set_selection(
+        &self.id.into_ffi(),
+        &pos1.into_ffi(),
+        &pos2.into_ffi(),
+      ))
+    }
+  }
+  /// Applies the given edit to the player's selection. The edit is added to
+  /// the player's history, so that it can be undone with
+  /// [`undo`](Self::undo). Returns the number of blocks changed.
+  pub fn edit_selection(&self, edit: RegionEdit) -> Result<u64> {
+    let (op, ty, filter) = match edit {
+      RegionEdit::Set(ty) => (0, ty, 0),
+      RegionEdit::Replace(kind, ty) => (1, ty, kind.id()),
+      RegionEdit::Walls(ty) => (2, ty, 0),
+      RegionEdit::Hollow(ty) => (3, ty, 0),
+    };
+    unsafe { unwrap(bb_ffi::bb_player_edit_selection(&self.id.into_ffi(), op, ty.id(), filter)) }
+  }
+  /// Adds the given changes to the player's history, so that the player can
+  /// undo them. This should be called after the blocks have been changed.
+  pub fn remember(&self, changes: &[BlockChange]) -> Result<()> {
+    let list = bb_ffi::CList::new(
+      changes
+        .iter()
+        .map(|c| bb_ffi::CBlockChange { pos: c.pos.into_ffi(), old: c.old.id(), new: c.new.id() })
+        .collect(),
+    );
+    let res = unsafe { check(bb_ffi::bb_player_remember(&self.id.into_ffi(), &list)) };
+    drop(list.into_vec());
+    res
+  }
+  /// Undoes the player's last edit. Returns the number of blocks changed, or
+  /// `None` if there is nothing to undo.
+  pub fn undo(&self) -> Result<Option<u64>> {
+    unsafe { unwrap(bb_ffi::bb_player_undo(&self.id.into_ffi())).map(COpt::into_option) }
+  }
+  /// Redoes the player's last undone edit. Returns the number of blocks
+  /// changed, or `None` if there is nothing to redo.
+  pub fn redo(&self) -> Result<Option<u64>> {
+    unsafe { unwrap(bb_ffi::bb_player_redo(&self.id.into_ffi())).map(COpt::into_option) }
+  }
+  /// Bans this player, and kicks them if they are online. If `duration` is
+  /// `None`, the ban is permanent.
+  ///
+  /// This works even if the player has logged off.
+  pub fn ban(&self, reason: &str, duration: Option<Duration>) {
+    // A duration of 0 means permanent, so round short bans up to a second.
+    let secs = duration.map(|d| d.as_secs().max(1)).unwrap_or(0);
+    unsafe {
+      bb_ffi::bb_player_ban(&self.id.into_ffi(), reason.as_ptr(), reason.len() as u32, secs);
+    }
+  }
+  /// Removes this player's ban. Returns `false` if they weren't banned.
+  pub fn pardon(&self) -> bool { unsafe { bb_ffi::bb_player_pardon(&self.id.into_ffi()) == 0 } }
+  /// Returns the reason this player was banned for, or `None` if they aren't
+  /// banned.
+  pub fn ban_reason(&self) -> Option<String> {
+    unsafe {
+      let ptr = bb_ffi::bb_player_ban_reason(&self.id.into_ffi());
+      if ptr.is_null() {
+        None
+      } else {
+        Some(Box::from_raw(ptr).into_string())
+      }
+    }
+  }
+  /// Returns how long until this player's ban expires. The outer `Option` is
+  /// `None` if they aren't banned, and the inner `Option` is `None` if the ban
+  /// is permanent.
+  pub fn ban_expires(&self) -> Option<Option<Duration>> {
+    match unsafe { bb_ffi::bb_player_ban_expires(&self.id.into_ffi()) } {
+      -1 => None,
+      0 => Some(None),
+      secs => Some(Some(Duration::from_secs(secs as u64))),
     }
   }
 }
@@ -1,40 +1,121 @@
-use crate::{block, particle::Particle, player::Player, FromFfi, IntoFfi};
-use bb_common::math::{FPos, Pos, PosError};
+use crate::{
+  block,
+  entity::Entity,
+  error::{check, unwrap, Result},
+  particle::Particle,
+  player::Player,
+  FromFfi, IntoFfi,
+};
+use bb_common::{
+  math::{derive_seed, ChunkPos, FPos, Pos},
+  util::Chat,
+};
+use bb_ffi::CChat;
 
 pub struct World {
-  wid: u32,
+  pub(crate) wid: u32,
+}
+
+/// An operation on every block in a player's selection. See
+/// [`Player::edit_selection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionEdit {
+  /// Sets every block to the given type.
+  Set(block::Type),
+  /// Replaces every block of the given kind.
+  Replace(block::Kind, block::Type),
+  /// Sets the four vertical sides, and leaves everything else alone.
+  Walls(block::Type),
+  /// Sets all six sides, and replaces the inside with air.
+  Hollow(block::Type),
+}
+
+/// A single block change. Plugins can add their own edits to a player's
+/// history with [`Player::remember`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockChange {
+  pub pos: Pos,
+  /// The block before the change.
+  pub old: block::Type,
+  /// The block after the change.
+  pub new: block::Type,
+}
+
+/// Mirrors a schematic along an axis when pasting it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+  None,
+  /// Flips the X axis, so that east and west are swapped.
+  X,
+  /// Flips the Z axis, so that north and south are swapped.
+  Z,
+}
+
+/// What a raycast can hit. See [`World::raycast_hit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaycastMode {
+  /// Only blocks are hit.
+  Blocks,
+  /// Only entities and players are hit.
+  Entities,
+  /// Both blocks and entities are hit. Whichever one is closer is returned.
+  Both,
+}
+
+/// Something hit by a raycast. See [`World::raycast_hit`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+  /// The exact position the ray hit.
+  pub pos:    FPos,
+  /// The entity that was hit, or `None` if a block was hit.
+  pub entity: Option<Entity>,
 }
 
 impl World {
   pub fn new(wid: u32) -> Self { World { wid } }
 
-  pub fn get_block(&self, pos: Pos) -> Result<block::Type, PosError> {
+  pub fn get_block(&self, pos: Pos) -> Result<block::Type> {
     unsafe {
-      let id =
-        bb_ffi::bb_world_get_block(self.wid, &bb_ffi::CPos { x: pos.x(), y: pos.y(), z: pos.z() });
-      if id == u32::MAX {
-        Err(pos.err("invalid position".into()))
-      } else {
-        // If the kind is invalid here, then `id` must be invalid, so we can panic.
-        let kind = block::Kind::from_id(bb_ffi::bb_block_kind_for_type(id)).unwrap();
-        Ok(block::Type { kind, state: id })
-      }
+      let id = unwrap(bb_ffi::bb_world_get_block(self.wid, &pos.into_ffi()))?;
+      // If the kind is invalid here, then `id` must be invalid, so we can panic.
+      let kind = block::Kind::from_id(bb_ffi::bb_block_kind_for_type(id)).unwrap();
+      Ok(block::Type { kind, state: id })
     }
   }
-  pub fn set_block(&self, pos: Pos, ty: block::Type) {
-    unsafe {
-      bb_ffi::bb_world_set_block(self.wid, &pos.into_ffi(), ty.id());
-    }
+  /// Sets the block at `pos`. This fails if the position is outside the
+  /// world, or if this plugin can't change this world.
+  pub fn set_block(&self, pos: Pos, ty: block::Type) -> Result<()> {
+    unsafe { check(bb_ffi::bb_world_set_block(self.wid, &pos.into_ffi(), ty.id())) }
+  }
+  /// Sets the block at `pos` to the default type of `kind`. This fails if the
+  /// position is outside the world, or if this plugin can't change this world.
+  pub fn set_block_kind(&self, pos: Pos, kind: block::Kind) -> Result<()> {
+    unsafe { check(bb_ffi::bb_world_set_block_kind(self.wid, &pos.into_ffi(), kind.id())) }
   }
-  pub fn set_block_kind(&self, pos: Pos, kind: block::Kind) {
+  /// Sets the block at `pos` to a mob spawner, which will spawn the given
+  /// entity. The entity should be a name like `zombie`. An unknown entity
+  /// returns [`ErrorKind::InvalidArgument`](crate::ErrorKind::InvalidArgument).
+  pub fn set_spawner(&self, pos: Pos, entity: &str) -> Result<()> {
     unsafe {
-      bb_ffi::bb_world_set_block_kind(self.wid, &pos.into_ffi(), kind.id());
+      check(bb_ffi::bb_world_set_spawner(
+        self.wid,
+        &pos.into_ffi(),
+        entity.as_ptr(),
+        entity.len() as u32,
+      ))
     }
   }
-  pub fn players(&self) -> impl Iterator<Item = Player> {
+  /// Changes how often the spawner at `pos` spawns entities. After spawning,
+  /// the spawner will wait a random number of ticks between `min` and `max`
+  /// before spawning again. If there is no spawner at `pos`, this returns
+  /// [`ErrorKind::InvalidArgument`](crate::ErrorKind::InvalidArgument).
+  pub fn set_spawner_delay(&self, pos: Pos, min: u32, max: u32) -> Result<()> {
+    unsafe { check(bb_ffi::bb_world_set_spawner_delay(self.wid, &pos.into_ffi(), min, max)) }
+  }
+  pub fn players(&self) -> Result<impl Iterator<Item = Player>> {
     unsafe {
-      let players = Box::from_raw(bb_ffi::bb_world_players(self.wid)).into_vec();
-      players.into_iter().map(Player::from_ffi)
+      let players = unwrap(bb_ffi::bb_world_players(self.wid))?.into_vec();
+      Ok(players.into_iter().map(Player::from_ffi))
     }
   }
   /// Spawns a particle in the world. Everyone in render distance will be able
@@ -45,6 +126,174 @@ impl World {
       bb_ffi::bb_world_spawn_particle(self.wid, &cparticle);
     }
   }
+  /// Spawns floating text at the given position. Returns the text entity,
+  /// which can be passed to [`set_text`](Self::set_text) or
+  /// [`remove_entity`](Self::remove_entity).
+  pub fn spawn_text(&self, pos: FPos, text: Chat) -> Result<Entity> {
+    unsafe {
+      let ctext = CChat { message: bb_ffi::CStr::new(text.to_codes()) };
+      unwrap(bb_ffi::bb_world_spawn_text(self.wid, &pos.into_ffi(), &ctext)).map(Entity::from_ffi)
+    }
+  }
+  /// Changes the text of an entity created with
+  /// [`spawn_text`](Self::spawn_text).
+  pub fn set_text(&self, entity: Entity, text: Chat) -> Result<()> {
+    unsafe {
+      let ctext = CChat { message: bb_ffi::CStr::new(text.to_codes()) };
+      check(bb_ffi::bb_world_set_text(self.wid, entity.into_ffi(), &ctext))
+    }
+  }
+  /// Removes the given entity.
+  pub fn remove_entity(&self, entity: Entity) -> Result<()> {
+    unsafe { check(bb_ffi::bb_world_remove_entity(self.wid, entity.into_ffi())) }
+  }
+  /// Spawns an armor stand at the given position, and returns it.
+  pub fn spawn_armor_stand(&self, pos: FPos) -> Result<Entity> {
+    unsafe {
+      unwrap(bb_ffi::bb_world_spawn_armor_stand(self.wid, &pos.into_ffi())).map(Entity::from_ffi)
+    }
+  }
+  /// Rotates part of an armor stand. The part is one of `head`, `body`,
+  /// `left_arm`, `right_arm`, `left_leg`, or `right_leg`, and the angles are
+  /// in degrees.
+  pub fn set_armor_stand_pose(
+    &self,
+    entity: Entity,
+    part: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+  ) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_set_armor_stand_pose(
+        self.wid,
+        entity.into_ffi(),
+        part.as_ptr(),
+        part.len() as u32,
+        x,
+        y,
+        z,
+      ))
+    }
+  }
+  /// Sets or clears an armor stand flag. The flag is one of `small`, `arms`,
+  /// `no_base_plate`, or `marker`.
+  pub fn set_armor_stand_flag(&self, entity: Entity, flag: &str, value: bool) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_set_armor_stand_flag(
+        self.wid,
+        entity.into_ffi(),
+        flag.as_ptr(),
+        flag.len() as u32,
+        bb_ffi::CBool::new(value),
+      ))
+    }
+  }
+  /// Sets the item held or worn by an entity. The slot is one of `main_hand`,
+  /// `off_hand`, `helmet`, `chestplate`, `leggings`, or `boots`, and `item` is
+  /// an item name like `diamond_sword`.
+  pub fn set_equipment(&self, entity: Entity, slot: &str, item: &str, amount: u8) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_set_equipment(
+        self.wid,
+        entity.into_ffi(),
+        slot.as_ptr(),
+        slot.len() as u32,
+        item.as_ptr(),
+        item.len() as u32,
+        amount,
+      ))
+    }
+  }
+  /// Disguises an entity or player as another entity type, like `cow`. Other
+  /// players will see the disguise.
+  pub fn disguise(&self, entity: Entity, ty: &str) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_disguise(self.wid, entity.into_ffi(), ty.as_ptr(), ty.len() as u32))
+    }
+  }
+  /// Removes the disguise from an entity. Returns
+  /// [`ErrorKind::InvalidArgument`](crate::ErrorKind::InvalidArgument) if the
+  /// entity wasn't disguised.
+  pub fn undisguise(&self, entity: Entity) -> Result<()> {
+    unsafe { check(bb_ffi::bb_world_undisguise(self.wid, entity.into_ffi())) }
+  }
+  /// Pastes the schematic file at `path` into the world. Both Sponge `.schem`
+  /// and legacy `.schematic` files are supported. The schematic is mirrored,
+  /// and then rotated clockwise around `pos` by `rotation` degrees, which must
+  /// be a multiple of 90. If `skip_air` is set, air in the schematic won't
+  /// replace any blocks.
+  ///
+  /// Returns the number of blocks changed. If the file couldn't be read, this
+  /// returns [`ErrorKind::Io`](crate::ErrorKind::Io).
+  pub fn paste_schematic(
+    &self,
+    path: &str,
+    pos: Pos,
+    rotation: i32,
+    mirror: Mirror,
+    skip_air: bool,
+  ) -> Result<u64> {
+    unsafe {
+      unwrap(bb_ffi::bb_world_paste_schematic(
+        self.wid,
+        path.as_ptr(),
+        path.len() as u32,
+        &pos.into_ffi(),
+        rotation,
+        mirror as u8,
+        bb_ffi::CBool::new(skip_air),
+      ))
+    }
+  }
+  /// Copies the blocks between `min` and `max`, and stores them as a named
+  /// structure. Structures are kept in memory, and are shared between all
+  /// worlds and plugins. Pasting the structure at `origin` will place the
+  /// blocks back where they were, which makes this useful for resetting
+  /// arenas.
+  pub fn save_structure(&self, name: &str, min: Pos, max: Pos, origin: Pos) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_save_structure(
+        self.wid,
+        name.as_ptr(),
+        name.len() as u32,
+        &min.into_ffi(),
+        &max.into_ffi(),
+        &origin.into_ffi(),
+      ))
+    }
+  }
+  /// Pastes a named structure, like a structure block would. The structure is
+  /// mirrored and rotated like [`paste_schematic`](Self::paste_schematic).
+  /// Structure voids are skipped, and every other block is only placed
+  /// `integrity` of the time (between 0 and 1). The blocks are picked using
+  /// `seed`, so the same seed will always place the same blocks.
+  ///
+  /// Returns the number of blocks changed. If there is no structure with this
+  /// name, this returns
+  /// [`ErrorKind::InvalidArgument`](crate::ErrorKind::InvalidArgument).
+  pub fn paste_structure(
+    &self,
+    name: &str,
+    pos: Pos,
+    rotation: i32,
+    mirror: Mirror,
+    integrity: f32,
+    seed: u64,
+  ) -> Result<u64> {
+    unsafe {
+      unwrap(bb_ffi::bb_world_paste_structure(
+        self.wid,
+        name.as_ptr(),
+        name.len() as u32,
+        &pos.into_ffi(),
+        rotation,
+        mirror as u8,
+        integrity,
+        seed,
+      ))
+    }
+  }
   pub fn raycast(&self, from: FPos, to: FPos, water: bool) -> Option<FPos> {
     unsafe {
       let ptr = bb_ffi::bb_world_raycast(
@@ -60,4 +309,171 @@ impl World {
       }
     }
   }
+  /// Raycasts from `from` to `to`, and returns the first entity hit. The
+  /// entity `exclude` is skipped, which is useful when raycasting from an
+  /// entity's eyes.
+  pub fn raycast_entity(&self, from: FPos, to: FPos, exclude: Option<Entity>) -> Option<Entity> {
+    unsafe {
+      let handle = bb_ffi::bb_world_raycast_entity(
+        &bb_ffi::CFPos { x: from.x(), y: from.y(), z: from.z() },
+        &bb_ffi::CFPos { x: to.x(), y: to.y(), z: to.z() },
+        exclude.map(Entity::into_ffi).unwrap_or(0),
+      );
+      if handle == 0 {
+        None
+      } else {
+        Some(Entity::from_ffi(handle))
+      }
+    }
+  }
+  /// Raycasts from `from` to `to`, and returns the first block or entity hit,
+  /// depending on `mode`. The entity `exclude` is skipped, and water is only
+  /// hit if `water` is set.
+  ///
+  /// Unlike [`raycast_entity`](Self::raycast_entity), this returns the exact
+  /// position the entity was hit, which is useful for custom projectiles.
+  pub fn raycast_hit(
+    &self,
+    from: FPos,
+    to: FPos,
+    mode: RaycastMode,
+    water: bool,
+    exclude: Option<Entity>,
+  ) -> Option<RaycastHit> {
+    unsafe {
+      let ptr = bb_ffi::bb_world_raycast_hit(
+        &bb_ffi::CFPos { x: from.x(), y: from.y(), z: from.z() },
+        &bb_ffi::CFPos { x: to.x(), y: to.y(), z: to.z() },
+        mode as u8,
+        bb_ffi::CBool::new(water),
+        exclude.map(Entity::into_ffi).unwrap_or(0),
+      );
+      if ptr.is_null() {
+        None
+      } else {
+        let hit = Box::from_raw(ptr);
+        Some(RaycastHit {
+          pos:    FPos { x: hit.pos.x, y: hit.pos.y, z: hit.pos.z },
+          entity: if hit.entity == 0 { None } else { Some(Entity::from_ffi(hit.entity)) },
+        })
+      }
+    }
+  }
+
+  /// Copies every chunk that contains part of the region between `min` and
+  /// `max`, and stores them as a named snapshot. Restoring the snapshot with
+  /// [`restore_snapshot`](Self::restore_snapshot) is much faster than pasting
+  /// a structure, so this is the best way to reset a minigame arena between
+  /// rounds. Entities are not saved.
+  pub fn snapshot_region(&self, name: &str, min: Pos, max: Pos) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_snapshot_region(
+        self.wid,
+        name.as_ptr(),
+        name.len() as u32,
+        &min.into_ffi(),
+        &max.into_ffi(),
+      ))
+    }
+  }
+  /// Restores a snapshot saved with [`snapshot_region`](Self::snapshot_region).
+  /// The snapshot is kept, so it can be restored again later.
+  pub fn restore_snapshot(&self, name: &str) -> Result<()> {
+    unsafe { check(bb_ffi::bb_world_restore_snapshot(self.wid, name.as_ptr(), name.len() as u32)) }
+  }
+  /// Removes a snapshot saved with [`snapshot_region`](Self::snapshot_region).
+  pub fn remove_snapshot(&self, name: &str) -> Result<()> {
+    unsafe { check(bb_ffi::bb_world_remove_snapshot(self.wid, name.as_ptr(), name.len() as u32)) }
+  }
+
+  /// Fills the nether portal frame around `pos` with portal blocks, like
+  /// using flint and steel. Returns
+  /// [`ErrorKind::InvalidArgument`](crate::ErrorKind::InvalidArgument) if
+  /// there is no valid frame at `pos`.
+  pub fn light_portal(&self, pos: Pos) -> Result<()> {
+    unsafe { check(bb_ffi::bb_world_light_portal(self.wid, &pos.into_ffi())) }
+  }
+
+  /// Adds a named ticket to the given chunk, which keeps it loaded, even if
+  /// no players are nearby. If `ttl` is `Some`, the ticket is removed after
+  /// that many ticks. Adding a ticket with the same name again replaces it.
+  pub fn add_chunk_ticket(&self, chunk: ChunkPos, name: &str, ttl: Option<u32>) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_add_chunk_ticket(
+        self.wid,
+        chunk.x(),
+        chunk.z(),
+        name.as_ptr(),
+        name.len() as u32,
+        ttl.unwrap_or(0),
+      ))
+    }
+  }
+  /// Removes a ticket added with [`add_chunk_ticket`](Self::add_chunk_ticket).
+  /// Returns [`ErrorKind::InvalidArgument`](crate::ErrorKind::InvalidArgument)
+  /// if the chunk has no ticket with that name.
+  pub fn remove_chunk_ticket(&self, chunk: ChunkPos, name: &str) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_remove_chunk_ticket(
+        self.wid,
+        chunk.x(),
+        chunk.z(),
+        name.as_ptr(),
+        name.len() as u32,
+      ))
+    }
+  }
+  /// Sets if the given chunk is force loaded, like `/forceload`. Forced chunks
+  /// stay loaded across restarts.
+  pub fn set_force_loaded(&self, chunk: ChunkPos, forced: bool) -> Result<()> {
+    unsafe {
+      check(bb_ffi::bb_world_set_force_loaded(
+        self.wid,
+        chunk.x(),
+        chunk.z(),
+        bb_ffi::CBool::new(forced),
+      ))
+    }
+  }
+
+  /// Returns the seed of this world. This is set with `world.seed` in the
+  /// server config.
+  pub fn seed(&self) -> Result<u64> { unsafe { unwrap(bb_ffi::bb_world_seed(self.wid)) } }
+  /// Returns a seed derived from the world seed and `name`. Custom generators
+  /// should seed their RNGs with this, so that they generate the same terrain
+  /// for the same world seed, without matching any other plugin's numbers.
+  pub fn seed_for(&self, name: &str) -> Result<u64> { Ok(derive_seed(self.seed()?, name)) }
+}
+
+/// Reads a schematic or structure file, and stores it as a named structure,
+/// which can be pasted with [`World::paste_structure`]. Sponge `.schem`,
+/// legacy `.schematic`, and vanilla `.nbt` files are supported.
+pub fn load_structure(name: &str, path: &str) -> Result<()> {
+  unsafe {
+    check(bb_ffi::bb_load_structure(
+      name.as_ptr(),
+      name.len() as u32,
+      path.as_ptr(),
+      path.len() as u32,
+    ))
+  }
+}
+/// Writes a named structure to a file. If the path ends in `.nbt`, this writes
+/// a vanilla structure, and if it ends in `.schematic`, this uses the legacy
+/// format. Otherwise, this writes a Sponge `.schem` file.
+pub fn write_structure(name: &str, path: &str) -> Result<()> {
+  unsafe {
+    check(bb_ffi::bb_write_structure(
+      name.as_ptr(),
+      name.len() as u32,
+      path.as_ptr(),
+      path.len() as u32,
+    ))
+  }
+}
+/// Removes a named structure. Returns
+/// [`ErrorKind::InvalidArgument`](crate::ErrorKind::InvalidArgument) if there
+/// is no structure with that name.
+pub fn remove_structure(name: &str) -> Result<()> {
+  unsafe { check(bb_ffi::bb_remove_structure(name.as_ptr(), name.len() as u32)) }
 }
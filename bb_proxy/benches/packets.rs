@@ -0,0 +1,47 @@
+use bb_common::version::ProtocolVersion;
+use bb_proxy::gnet::{tcp, Bytes};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use pprof::criterion::{Output, PProfProfiler};
+
+/// About the size of a full chunk data packet.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub fn read_unknown(c: &mut Criterion) {
+  let data = vec![5; CHUNK_SIZE];
+  // This is what the `unknown` field of generated packets used to do.
+  c.bench_function("read chunk with read_all", |b| {
+    b.iter_batched(
+      || tcp::Packet::from_buf_id(data.clone(), 0x21, ProtocolVersion::V1_8),
+      |mut p| {
+        p.read_u32().unwrap();
+        black_box(p.read_all())
+      },
+      BatchSize::LargeInput,
+    );
+  });
+  c.bench_function("read chunk with read_rest", |b| {
+    b.iter_batched(
+      || tcp::Packet::from_buf_id(data.clone(), 0x21, ProtocolVersion::V1_8),
+      |mut p| {
+        p.read_u32().unwrap();
+        black_box(p.read_rest())
+      },
+      BatchSize::LargeInput,
+    );
+  });
+}
+
+pub fn clone_unknown(c: &mut Criterion) {
+  let owned = Bytes::from(vec![5; CHUNK_SIZE]);
+  let mut shared = owned.clone();
+  shared.share();
+  c.bench_function("clone owned chunk", |b| b.iter(|| black_box(owned.clone())));
+  c.bench_function("clone shared chunk", |b| b.iter(|| black_box(shared.clone())));
+}
+
+criterion_group! {
+  name = benches;
+  config = Criterion::default().with_profiler(PProfProfiler::new(100, Output::Flamegraph(None)));
+  targets = read_unknown, clone_unknown
+}
+criterion_main!(benches);
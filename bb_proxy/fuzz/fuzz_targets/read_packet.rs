@@ -0,0 +1,22 @@
+//! Reads random data as a packet. The readers should return an error for any
+//! invalid data, and never panic.
+//!
+//! Run with `cargo fuzz run read_packet` from the `bb_proxy` directory.
+
+#![no_main]
+
+use bb_proxy::gnet::{cb, sb, tcp, VERSIONS};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+  // The first byte picks the version and direction, and the rest is the packet,
+  // starting with the packet id.
+  let Some((&first, data)) = data.split_first() else { return };
+  let ver = VERSIONS[(first >> 1) as usize % VERSIONS.len()];
+  let Ok(mut p) = tcp::Packet::from_buf(data.to_vec(), ver) else { return };
+  if first & 1 == 0 {
+    let _ = cb::Packet::from_tcp(&mut p, ver);
+  } else {
+    let _ = sb::Packet::from_tcp(&mut p, ver);
+  }
+});
@@ -1,5 +1,7 @@
+use bb_common::{logger::FileConfig, version::ProtocolVersion};
 use bb_macros::{Config, Default};
 use log::LevelFilter;
+use std::collections::HashMap;
 
 #[derive(Clone, Debug, Config, Default, PartialEq)]
 pub struct Config {
@@ -7,12 +9,59 @@ pub struct Config {
   #[default(LevelFilter::Info)]
   pub log_level: LevelFilter,
 
-  /// The Bamboo server's IP.
+  /// If set, logs will be written as JSON lines instead of plain text. This
+  /// is easier to ingest into tools like Loki or ELK.
+  #[default(false)]
+  pub log_json: bool,
+  /// Settings for writing logs to a file.
+  pub log_file: LogFileConfig,
+
+  /// The Bamboo server's IP. This can also be a hostname, which will be
+  /// resolved again every `server-refresh-secs` seconds.
   #[default("0.0.0.0:8483".into())]
-  pub server:  String,
+  pub server:              String,
+  /// How often the `server` hostname is resolved, in seconds. This does
+  /// nothing if `server` is an IP.
+  #[default(60)]
+  pub server_refresh_secs: u64,
   /// The IP of the proxy. This is the IP that all clients will connect to.
   #[default("0.0.0.0:25565".into())]
-  pub address: String,
+  pub address:             String,
+
+  /// How often a heartbeat is sent to the server, in seconds. If the server
+  /// doesn't reply for three heartbeats in a row, the connection is assumed
+  /// to have dropped.
+  #[default(5)]
+  pub heartbeat_secs:  u64,
+  /// When the connection to the server drops, the proxy tries to resume it
+  /// for this many seconds. Clients won't notice anything if it comes back in
+  /// time. After this, clients are moved into limbo, or disconnected if limbo
+  /// is disabled. This should be shorter than the server's `link-grace-secs`.
+  /// Set to 0 to never resume connections.
+  #[default(10)]
+  pub link_grace_secs: u64,
+
+  /// If set, clients will be held in an empty world when the server goes
+  /// down, and will be reconnected once it comes back. If not set, clients
+  /// will be disconnected when the server goes down.
+  #[default(true)]
+  pub limbo:              bool,
+  /// How often to try reconnecting clients in limbo, in seconds.
+  #[default(5)]
+  pub limbo_retry_secs:   u64,
+  /// Clients that are in limbo for longer than this many seconds will be
+  /// disconnected. Set to 0 to keep them in limbo forever.
+  #[default(300)]
+  pub limbo_timeout_secs: u64,
+  /// Servers that clients in limbo can be sent to, if the main server is
+  /// still down. Each reconnect tries the next server in this list, and then
+  /// starts over with the main server.
+  pub fallback:           Vec<String>,
+  /// If set, clients that join while the server is full are held in limbo
+  /// until there is space, instead of being disconnected. This requires
+  /// `limbo` to be enabled.
+  #[default(false)]
+  pub queue:              bool,
 
   /// This enables authentication with Mojang's servers. This should only be
   /// disabled if you know what you are doing.
@@ -21,14 +70,145 @@ pub struct Config {
   /// This is for receiving player data from another proxy such as Velocity.
   #[default(Forwarding::None)]
   pub forwarding:         Forwarding,
+  /// If set, Forge clients on 1.13 and newer are asked for their mod list
+  /// while logging in, which is passed on to the server. The server can
+  /// require mods with its `required-mods` option.
+  ///
+  /// Forge clients with mods that must also be installed on the server will
+  /// not be able to join while this is enabled.
+  #[default(false)]
+  pub forge_mod_list:     bool,
   /// This is the packet compression threshold. Vanilla clients will perform
   /// far worse if this is turned off. Compression can be disabled by setting
   /// this to -1. The proxy will compress all packets if this is set to 0.
   #[default(256)]
   pub compression_thresh: i32,
-  /// The path to the icon.
+  /// The path to the ban list. This should be the same file as the server's
+  /// `bans` option, so that banned players are kicked before they reach the
+  /// server. Set to an empty string to disable ban checks on the proxy.
+  #[default("bans.json".into())]
+  pub bans:               String,
+  /// The path to the whitelist. Like `bans`, this should be the same file as
+  /// the server's `whitelist` option. This is also used for maintenance mode.
+  /// Set to an empty string to disable whitelist checks on the proxy.
+  #[default("whitelist.json".into())]
+  pub whitelist:          String,
+  /// The path to the icon. If this can't be loaded, an icon is generated from
+  /// `icon-text` and `icon-color` instead.
   #[default("icon.png".into())]
   pub icon:               String,
+  /// The text drawn on the generated icon. Only the first three letters or
+  /// numbers are drawn.
+  #[default("BB".into())]
+  pub icon_text:          String,
+  /// The background color of the generated icon, as a hex color.
+  #[default("#3d8c40".into())]
+  pub icon_color:         String,
+
+  /// The messages shown on the server list. If there is more than one, the
+  /// server list shows the next one every `motd-rotate-secs`. If this is
+  /// empty, a default message is shown. Color codes like `§a` can be used,
+  /// along with hex colors like `§x§3§d§8§c§4§0` for 1.16+ clients.
+  pub motd:             Vec<String>,
+  /// How long each message in `motd` is shown for, in seconds.
+  #[default(10)]
+  pub motd_rotate_secs: u64,
+  /// The players listed when hovering over the player count on the server
+  /// list.
+  #[default(Sample::Online)]
+  pub sample:           Sample,
+  /// The names listed when `sample` is "fake".
+  pub sample_names:     Vec<String>,
+  /// If set, the server's memory usage, and the chunks and entities loaded in
+  /// each world, are listed after the players when hovering over the player
+  /// count. This is useful for private servers, but shouldn't be enabled on
+  /// public ones.
+  #[default(false)]
+  pub status_usage:     bool,
+
+  /// If set, the proxy will be advertised to clients on the local network, so
+  /// that it shows up in the LAN section of the server list.
+  #[default(false)]
+  pub lan:      bool,
+  /// The name shown in the LAN section of the server list.
+  #[default("Bamboo".into())]
+  pub lan_motd: String,
+
+  /// If set, every packet from a client will be traced through the server.
+  /// This adds a small amount of overhead to every packet.
+  #[default(false)]
+  pub trace:         bool,
+  /// When tracing is enabled, any packets that take longer than this many
+  /// milliseconds to be handled will be logged.
+  #[default(100)]
+  pub trace_slow_ms: u64,
+
+  /// Unknown packets from clients on these versions will be passed through to
+  /// the server with a warning, instead of closing the connection. This is
+  /// useful when a new version adds packets the proxy doesn't know about yet.
+  /// Versions are written like "1.20" or "1.19.4". Add "all" to enable this
+  /// for every version.
+  pub pass_through: Vec<String>,
+
+  /// Virtual hosts. Clients that connect with one of these hostnames will be
+  /// shown this host's status, and will be sent to this host's server. Any
+  /// other clients use the options above. For example:
+  ///
+  /// [hosts."play.example.com"]
+  /// server = "10.0.0.2:8483"
+  /// motd = "Welcome to Example!"
+  pub hosts: HashMap<String, HostConfig>,
+}
+
+/// Settings for writing logs to a file.
+#[derive(Clone, Debug, Config, Default, PartialEq)]
+pub struct LogFileConfig {
+  /// If set, logs will be written to `path` as well as the console.
+  #[default(false)]
+  pub enabled:       bool,
+  /// The log file. Old logs are moved into the same directory, and are named
+  /// by the date they were rotated, like `2022-06-14-1.log.gz`.
+  #[default("logs/latest.log".into())]
+  pub path:          String,
+  /// Once the log file is this many kilobytes, it is rotated. Set to 0 to
+  /// never rotate the log because of its size.
+  #[default(10240)]
+  pub max_size_kb:   u64,
+  /// Once the log file has been open for this many hours, it is rotated. Set
+  /// to 0 to never rotate the log because of its age.
+  #[default(24)]
+  pub max_age_hours: u64,
+  /// The number of old logs to keep. Set to 0 to keep all of them.
+  #[default(10)]
+  pub keep:          u32,
+  /// If set, old logs will be compressed with gzip.
+  #[default(true)]
+  pub gzip:          bool,
+}
+
+impl LogFileConfig {
+  /// Returns the settings for the logger, or `None` if logging to a file is
+  /// disabled.
+  pub fn to_logger(&self) -> Option<FileConfig> {
+    if !self.enabled {
+      return None;
+    }
+    Some(FileConfig::new(&self.path, self.max_size_kb, self.max_age_hours, self.keep, self.gzip))
+  }
+}
+
+/// The options for a single virtual host. Any empty option uses the main
+/// option instead.
+#[derive(Clone, Debug, Config, Default, PartialEq)]
+pub struct HostConfig {
+  /// The Bamboo server for this host. Like the main `server`, this can be a
+  /// hostname.
+  pub server: String,
+  /// The message shown on the server list. This can use color codes, like
+  /// the main `motd`.
+  pub motd:   String,
+  /// The path to the icon shown on the server list.
+  pub icon:   String,
 }
 
 #[derive(Clone, Debug, Config, Default, PartialEq)]
@@ -41,3 +221,74 @@ pub enum Forwarding {
   /// This should only be used if you know what you are doing.
   Legacy,
 }
+
+/// The players listed on the server list.
+#[derive(Clone, Debug, Config, Default, PartialEq)]
+pub enum Sample {
+  /// No players are listed.
+  None,
+  /// The players online on the server are listed. This is the default.
+  #[default]
+  Online,
+  /// The names in `sample-names` are listed, whether or not they are online.
+  Fake,
+}
+
+/// The client versions that have unknown packets passed through to the server.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum PassThrough {
+  /// Unknown packets will close the connection. This is the default.
+  #[default]
+  None,
+  /// Unknown packets are passed through for every version.
+  All,
+  /// Unknown packets are passed through for these versions only.
+  Versions(Vec<ProtocolVersion>),
+}
+
+impl PassThrough {
+  /// Parses the `pass-through` list from the config. Invalid versions are
+  /// logged and skipped.
+  pub fn from_config(names: &[String]) -> Self {
+    let mut versions = vec![];
+    for name in names {
+      if name == "all" {
+        return PassThrough::All;
+      }
+      match ProtocolVersion::parse_str(&format!("V{}", name.replace('.', "_"))) {
+        ProtocolVersion::Invalid => warn!("invalid pass-through version `{name}`"),
+        ver => versions.push(ver),
+      }
+    }
+    if versions.is_empty() {
+      PassThrough::None
+    } else {
+      PassThrough::Versions(versions)
+    }
+  }
+
+  /// Returns `true` if unknown packets should be passed through for the given
+  /// version.
+  pub fn matches(&self, ver: ProtocolVersion) -> bool {
+    match self {
+      PassThrough::None => false,
+      PassThrough::All => true,
+      PassThrough::Versions(versions) => versions.contains(&ver),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn pass_through() {
+    let p = PassThrough::from_config(&["1.19.4".into(), "1.20".into(), "1.99".into()]);
+    assert_eq!(p, PassThrough::Versions(vec![ProtocolVersion::V1_19_4, ProtocolVersion::V1_20]));
+    assert!(p.matches(ProtocolVersion::V1_20));
+    assert!(!p.matches(ProtocolVersion::V1_8));
+    assert!(PassThrough::from_config(&["all".into()]).matches(ProtocolVersion::V1_8));
+    assert_eq!(PassThrough::from_config(&[]), PassThrough::None);
+  }
+}
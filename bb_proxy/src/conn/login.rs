@@ -0,0 +1,468 @@
+//! Handles everything before the play state: the handshake, server list pings,
+//! and logging in.
+
+use super::{
+  state::{ConnState, Handshake, Login, LoginPacket, Play, StatusPacket},
+  Conn, JsonStatus, LoginInfo, State,
+};
+use crate::{config, gnet::tcp, login_plugin::LoginContext, stream::PacketStream, Error, Result};
+use bb_common::{
+  math,
+  util::{chat::Color, Chat, ClientKind, UUID},
+  version::ProtocolVersion,
+};
+use mio::Registry;
+use rand::{rngs::OsRng, RngCore};
+use sha1::{Digest, Sha1};
+use std::{
+  collections::HashMap,
+  convert::TryInto,
+  io,
+  io::ErrorKind,
+  net::{IpAddr, SocketAddr},
+  str::FromStr,
+};
+
+impl<S: PacketStream + Send + Sync> Conn<'_, S> {
+  /// Handles the handshake, and moves to either the status or login state.
+  pub(super) fn handle_handshake(&mut self, p: Handshake) -> Result<()> {
+    self.ver = p.ver;
+    self.host = self.hosts.get(&p.addr);
+    // Forge adds `\0FML\0` to the address (`FML2` on 1.13 to 1.16, and `FML3`
+    // on 1.17 and newer).
+    if p.addr.split('\0').skip(1).any(|s| s.starts_with("FML")) {
+      self.client_kind = ClientKind::Forge;
+    }
+
+    let forwarded = match self.forwarding {
+      config::Forwarding::Legacy => {
+        // The second section is the client's IP.
+        if let Some(ip) = p.addr.split('\0').nth(1).and_then(|ip| ip.parse::<IpAddr>().ok()) {
+          self.client_addr = Some(SocketAddr::new(ip, 0));
+        }
+        Some(self.read_bungeecord_info(&p.addr))
+      }
+      config::Forwarding::None => None,
+    };
+
+    self.state = match p.next {
+      // We can still reply to server list pings if the forwarded info is invalid.
+      State::Status => ConnState::Status,
+      State::Login => {
+        if self.ver == ProtocolVersion::Invalid {
+          return Err(
+            io::Error::new(ErrorKind::InvalidInput, "client sent an invalid version").into(),
+          );
+        }
+        ConnState::Login(Login::Start { forwarded: forwarded.transpose()? })
+      }
+      next => {
+        return Err(
+          io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("client tried to switch to state {next:?}"),
+          )
+          .into(),
+        )
+      }
+    };
+    Ok(())
+  }
+
+  /// Replies to a server list ping. The connection is closed once the client
+  /// has sent a ping.
+  pub(super) fn handle_status(&mut self, p: StatusPacket) -> Result<()> {
+    match p {
+      StatusPacket::Request => {
+        let status = self.build_status();
+        let mut out = tcp::Packet::new(0, self.ver);
+        out.write_str(&serde_json::to_string(&status).unwrap());
+        self.client_stream.write(out);
+      }
+      StatusPacket::Ping { id } => {
+        // Send pong
+        let mut out = tcp::Packet::new(1, self.ver);
+        out.write_u64(id);
+        self.client_stream.write(out);
+        // Client is done sending packets, we can close now.
+        self.closed = true;
+      }
+    }
+    self.client_stream.flush()?;
+    Ok(())
+  }
+
+  /// Runs the login process with the client. If encryption is enabled, the
+  /// client is authenticated with mojang, and otherwise, either the info
+  /// forwarded from another proxy is used, or the client is logged in as an
+  /// offline player. Then, any login plugin requests are sent, and once the
+  /// client has answered all of them, the client is moved to the play state,
+  /// and we connect to the server.
+  ///
+  /// This will return an error if anything goes wrong. The client should
+  /// always be kicked if an error is returned.
+  pub(super) fn handle_login(&mut self, p: LoginPacket, reg: &Registry) -> Result<()> {
+    let ConnState::Login(login) = &mut self.state else {
+      unreachable!("handle_login called in state {:?}", self.state.state())
+    };
+    match login {
+      Login::Start { forwarded } => {
+        let LoginPacket::Start { name } = p else {
+          return Err(
+            io::Error::new(
+              ErrorKind::InvalidInput,
+              "client did not send login start before sending encryption response",
+            )
+            .into(),
+          );
+        };
+        let forwarded = forwarded.take();
+        match &self.der_key {
+          Some(key) => {
+            // Make sure to actually generate a token
+            let mut verify_token = [0; 4];
+            OsRng.fill_bytes(&mut verify_token);
+
+            // Encryption request
+            let mut out = tcp::Packet::new(1, self.ver);
+            out.write_str(""); // Server id, should be empty
+            out.write_varint(key.len() as i32); // Key len
+            out.write_buf(key); // DER encoded RSA key
+            out.write_varint(4); // Token len
+            out.write_buf(&verify_token); // Verify token
+            self.client_stream.write(out);
+            // Wait for encryption response to enable encryption
+            self.state = ConnState::Login(Login::Encrypt { username: name, verify_token });
+          }
+          None => {
+            let info = forwarded.unwrap_or_else(|| LoginInfo::offline(&name));
+            self.send_compression();
+            self.start_login_plugins(reg, name, info)?;
+          }
+        }
+      }
+      Login::Encrypt { username, verify_token } => {
+        let LoginPacket::EncryptionResponse { secret, token } = p else {
+          return Err(
+            io::Error::new(ErrorKind::InvalidInput, "client sent two login packets").into(),
+          );
+        };
+        let (username, verify_token) = (username.clone(), *verify_token);
+        let Some(info) = self.authenticate(&username, verify_token, secret, token)? else {
+          return Ok(());
+        };
+        self.send_compression();
+        self.start_login_plugins(reg, username, info)?;
+      }
+      Login::Plugin { .. } => {
+        let LoginPacket::PluginResponse { id, data } = p else {
+          return Err(
+            io::Error::new(
+              ErrorKind::InvalidInput,
+              "client sent a login packet while answering login plugin requests",
+            )
+            .into(),
+          );
+        };
+        let ConnState::Login(Login::Plugin { mut login, mut pending }) =
+          std::mem::replace(&mut self.state, ConnState::Handshake)
+        else {
+          unreachable!()
+        };
+        let Some(idx) = pending.remove(&id) else {
+          return Err(
+            io::Error::new(
+              ErrorKind::InvalidInput,
+              format!("client answered unknown login plugin request {id}"),
+            )
+            .into(),
+          );
+        };
+        let handler = self.login_handlers[idx].clone();
+        match handler.response(&mut login, data.as_deref()) {
+          Ok(Some(data)) => {
+            let id = self.send_login_request(handler.channel(), &data);
+            pending.insert(id, idx);
+          }
+          Ok(None) => {}
+          Err(msg) => {
+            info!("{} was denied by the login handler for `{}`", login.username, handler.channel());
+            self.send_disconnect(msg);
+            self.closed = true;
+            self.client_stream.flush()?;
+            return Ok(());
+          }
+        }
+        self.continue_login(reg, login, pending)?;
+      }
+    }
+
+    self.client_stream.flush()?;
+    Ok(())
+  }
+
+  /// Enables encryption, and authenticates the client with mojang. If the
+  /// client isn't authenticated, this disconnects them and returns `Ok(None)`.
+  fn authenticate(
+    &mut self,
+    username: &str,
+    verify_token: [u8; 4],
+    received_secret: Vec<u8>,
+    received_token: Vec<u8>,
+  ) -> Result<Option<LoginInfo>> {
+    let decrypted_secret =
+      self.key.decrypt(rsa::Pkcs1v15Encrypt, &received_secret).map_err(|e| {
+        io::Error::new(ErrorKind::InvalidInput, format!("unable to decrypt secret: {e}"))
+      })?;
+    let decrypted_token = self.key.decrypt(rsa::Pkcs1v15Encrypt, &received_token).map_err(|e| {
+      io::Error::new(ErrorKind::InvalidInput, format!("unable to decrypt token: {e}"))
+    })?;
+
+    // Make sure the client sent the correct verify token back
+    if decrypted_token != verify_token {
+      return Err(
+        io::Error::new(
+          ErrorKind::InvalidInput,
+          format!("invalid verify token received from client (len: {})", decrypted_token.len()),
+        )
+        .into(),
+      );
+    }
+    let len = decrypted_secret.len();
+    let secret = match decrypted_secret.try_into() {
+      Ok(v) => v,
+      Err(_) => {
+        return Err(
+          io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid secret received from client (len: {len}, expected len 16)",),
+          )
+          .into(),
+        )
+      }
+    };
+
+    // If we need to disconnect the client, the client is expecting encrypted
+    // packets from now on, so we need to enable encryption here.
+    self.client_stream.enable_encryption(&secret);
+
+    let mut hash = Sha1::new();
+    hash.update("");
+    hash.update(secret);
+    hash.update(self.der_key.as_ref().unwrap());
+    match ureq::get(&format!(
+      "https://sessionserver.mojang.com/session/minecraft/hasJoined?username={}&serverId={}",
+      username,
+      math::hexdigest(hash)
+    ))
+    .call()
+    {
+      Ok(v) => {
+        info!("got status code: {}", v.status());
+        // No content
+        if v.status() == 204 {
+          self.send_disconnect(
+            "Invalid auth token! Please re-login (restart your game and launcher)",
+          );
+          self.client_stream.flush()?;
+          // Disconnect client; they are not authenticated
+          self.closed = true;
+          return Ok(None);
+        }
+        match serde_json::from_reader(v.into_reader()) {
+          Ok(v) => Ok(Some(v)),
+          Err(e) => Err(
+            io::Error::new(
+              ErrorKind::InvalidData,
+              format!("invalid json data received from session server: {e}"),
+            )
+            .into(),
+          ),
+        }
+      }
+      Err(e) => {
+        Err(io::Error::new(ErrorKind::Other, format!("failed to authenticate client: {e}")).into())
+      }
+    }
+  }
+
+  /// Sends the first request from every login handler. If there are no
+  /// requests to send, the client finishes logging in right away.
+  fn start_login_plugins(
+    &mut self,
+    reg: &Registry,
+    username: String,
+    info: LoginInfo,
+  ) -> Result<()> {
+    let login = LoginContext {
+      username,
+      ver: self.ver,
+      addr: self.client_addr,
+      info,
+      client: self.client_kind,
+      mods: vec![],
+    };
+    let mut pending = HashMap::new();
+    // Login plugin requests were added in 1.13.
+    if self.ver >= ProtocolVersion::V1_13 {
+      for (idx, handler) in self.login_handlers.clone().iter().enumerate() {
+        if let Some(data) = handler.request(&login) {
+          let id = self.send_login_request(handler.channel(), &data);
+          pending.insert(id, idx);
+        }
+      }
+    }
+    self.continue_login(reg, login, pending)
+  }
+
+  /// Finishes logging in if every login plugin request has been answered, and
+  /// otherwise waits for the rest of the responses.
+  fn continue_login(
+    &mut self,
+    reg: &Registry,
+    login: LoginContext,
+    pending: HashMap<i32, usize>,
+  ) -> Result<()> {
+    if pending.is_empty() {
+      // A handler may have changed the address, if it was forwarded.
+      self.client_addr = login.addr;
+      self.finish_login(reg, login)
+    } else {
+      self.state = ConnState::Login(Login::Plugin { login, pending });
+      Ok(())
+    }
+  }
+
+  /// Sends a login plugin request, and returns its id. The stream will not be
+  /// flushed.
+  fn send_login_request(&mut self, channel: &str, data: &[u8]) -> i32 {
+    let id = self.next_login_message;
+    self.next_login_message += 1;
+    let mut out = tcp::Packet::new(4, self.ver);
+    out.write_varint(id);
+    out.write_str(channel);
+    out.write_buf(data);
+    self.client_stream.write(out);
+    id
+  }
+
+  /// Sends the set compression packet, using self.compression_target. The
+  /// stream will not be flushed.
+  fn send_compression(&mut self) {
+    // Set compression, only if the thresh hold is non-zero
+    if self.compression_target != 0 {
+      let mut out = tcp::Packet::new(3, self.ver());
+      out.write_varint(self.compression_target);
+      self.client_stream.write(out);
+      // Must happen after the packet has been sent
+      self.client_stream.set_compression(self.compression_target);
+    }
+  }
+
+  /// Sends the login success packet, and moves to the play state. The stream
+  /// will not be flushed.
+  fn finish_login(&mut self, reg: &Registry, login: LoginContext) -> Result<()> {
+    let LoginContext { username, info, client, mods, .. } = login;
+    if let Some(msg) = self.ban_message(&info) {
+      info!("{username} tried to join, but is banned");
+      self.send_disconnect(msg);
+      self.closed = true;
+      return Ok(());
+    }
+    if let Some(msg) = self.whitelist_message(&username, &info) {
+      info!("{username} tried to join, but is not whitelisted");
+      self.send_disconnect(msg);
+      self.closed = true;
+      return Ok(());
+    }
+
+    // Login success
+    let ver = self.ver();
+    let mut out = tcp::Packet::new(2, ver);
+    if ver >= ProtocolVersion::V1_19 {
+      out.write_uuid(info.id);
+      out.write_str(&info.name);
+      out.write_varint(0); // no properties. TODO: Skins
+    } else if ver >= ProtocolVersion::V1_16 {
+      out.write_uuid(info.id);
+      out.write_str(&info.name);
+    } else {
+      out.write_str(&info.id.as_dashed_str());
+      out.write_str(&info.name);
+    }
+    self.client_stream.write(out);
+
+    self.online.add(&username, info.id);
+    if !mods.is_empty() {
+      info!("{username} joined with {} mod(s): {}", mods.len(), mods.join(", "));
+    }
+    self.state = ConnState::Play(Play { username, info, client, mods });
+    match self.connect_to_server(reg) {
+      Ok(()) => {}
+      Err(e) => {
+        let mut msg = Chat::empty();
+        msg.add("Couldn't connect to server: ").color(Color::Red);
+        msg.add(e.to_string());
+        self.send_disconnect(msg);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Returns the kick message if this client is banned.
+  fn ban_message(&self, info: &LoginInfo) -> Option<Chat> {
+    let mut bans = self.bans.as_ref()?.lock();
+    bans.reload_if_changed();
+    bans.find(info.id, self.client_addr.map(|a| a.ip())).map(|b| b.kick_message())
+  }
+
+  /// Returns the kick message if this client isn't allowed by the whitelist.
+  fn whitelist_message(&self, username: &str, info: &LoginInfo) -> Option<Chat> {
+    let mut whitelist = self.whitelist.as_ref()?.lock();
+    whitelist.reload_if_changed();
+    whitelist.check(info.id, username)
+  }
+  /// Generates the json status for the server. The virtual host's motd and icon
+  /// are used if it has them, but the maintenance motd always takes priority.
+  fn build_status(&self) -> JsonStatus {
+    let icon = self.host.as_ref().and_then(|h| h.icon.as_deref()).unwrap_or(self.icon);
+    let mut status = (self.status_builder)(icon, self.ver);
+    if let Some(motd) = self.host.as_ref().and_then(|h| h.motd.clone()) {
+      status.description = motd;
+    }
+    if let Some(whitelist) = &self.whitelist {
+      let mut whitelist = whitelist.lock();
+      whitelist.reload_if_changed();
+      if let Some(motd) = whitelist.maintenance_motd() {
+        status.description = motd;
+      }
+    }
+    status.description = status.description.for_version(self.ver);
+    status
+  }
+
+  /// Parse BungeeCord's player info from address string
+  fn read_bungeecord_info(&self, addr: &str) -> Result<LoginInfo> {
+    let mut id = None;
+    let mut properties = None;
+
+    for (i, section) in addr.split('\0').enumerate() {
+      match i {
+        2 => id = Some(UUID::from_str(section).map_err(|_| Error::Bungeecord("invalid UUID"))?),
+        3 => {
+          properties = Some(
+            serde_json::from_str(section).map_err(|_| Error::Bungeecord("invalid properties"))?,
+          )
+        }
+        _ => {}
+      }
+    }
+
+    match (id, properties) {
+      (Some(id), Some(properties)) => Ok(LoginInfo { id, name: "".to_string(), properties }),
+      // Client sent bad data, just give them some generic message.
+      _ => Err(Error::Bungeecord("invalid data")),
+    }
+  }
+}
@@ -0,0 +1,1214 @@
+use crate::{
+  config,
+  gnet::{cb as gcb, sb as gsb, tcp},
+  host::{Hosts, VirtualHost},
+  limbo::{Limbo, LimboConfig, KEEP_ALIVE_INTERVAL},
+  link::{Link, LinkConfig, Resume, RESUME_RETRY},
+  login_plugin::LoginHandler,
+  packet::{FromTcp, ToTcp, TypeConverter},
+  queue::Queue,
+  resolve,
+  status::Online,
+  stream::PacketStream,
+  Error, Result,
+};
+use bb_common::{
+  ban::BanList,
+  math::FPos,
+  net::{
+    cb as ccb,
+    protocol::{Capabilities, Negotiated, ProtocolError, ProtocolInfo},
+    sb as csb,
+  },
+  util::{
+    chat::Color, Chat, ClientKind, GameMode, JoinInfo, JoinMode, ProfileProperty, SwitchMode,
+    Trace, TraceLog, UUID,
+  },
+  version::ProtocolVersion,
+  whitelist::Whitelist,
+};
+use bb_transfer::{
+  InvalidReadError, MessageRead, MessageReader, MessageWrite, MessageWriter, ReadError, ReadLimits,
+};
+use mio::{net::TcpStream, Interest, Registry, Token};
+use parking_lot::Mutex;
+use rsa::RsaPrivateKey;
+use serde::{Deserialize, Serialize};
+use state::{ConnState, Handshake, LoginPacket, StatusPacket};
+use std::{
+  cell::RefCell,
+  collections::{HashMap, HashSet},
+  convert::TryInto,
+  fmt, io,
+  io::{ErrorKind, Read, Write},
+  net::SocketAddr,
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+mod login;
+mod state;
+
+/// How long finished traces are kept for each connection.
+const TRACE_WINDOW: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Copy, Clone)]
+pub enum State {
+  Handshake,
+  Status,
+  Login,
+  Play,
+  Invalid,
+}
+
+impl State {
+  fn from_next(next: i32) -> Self {
+    if next == 1 {
+      Self::Status
+    } else if next == 2 {
+      Self::Login
+    } else {
+      Self::Invalid
+    }
+  }
+}
+
+pub struct Conn<'a, S> {
+  client_stream: S,
+  /// The current state, along with everything we know about the client in
+  /// that state.
+  state:         ConnState,
+  ver:           ProtocolVersion,
+  icon:          &'a str,
+  /// The address of the client. With legacy forwarding, this is the address
+  /// forwarded by the other proxy.
+  client_addr:   Option<SocketAddr>,
+  /// The mod loader detected from the handshake.
+  client_kind:   ClientKind,
+
+  /// The private key. Always present, even if encryption is disabled.
+  key:                Arc<RsaPrivateKey>,
+  /// The der encoded public key. None if we don't want encryption.
+  der_key:            Option<Vec<u8>>,
+  /// Used in handshake. This will skip mojang auth and use a bungee-cord
+  /// formatted login message for player info.
+  forwarding:         config::Forwarding,
+  /// Used in handshake. This is different from `stream.compression`
+  compression_target: i32,
+  /// The versions where unknown packets are sent to the server as-is.
+  pass_through:       config::PassThrough,
+  /// Unknown packet ids we have already warned about.
+  passed_ids:         HashSet<i32>,
+
+  /// Set when the connection is closed.
+  closed: bool,
+
+  /// Returns the server address. Used when we are done handshaking, and need
+  /// to connect to a server.
+  server_addr:    Arc<dyn Fn() -> SocketAddr>,
+  /// The virtual hosts. The host the client connected with is looked up in
+  /// this during the handshake.
+  hosts:          Arc<Hosts>,
+  /// The virtual host the client connected with, if any. This overrides the
+  /// server address and the status.
+  host:           Option<Arc<VirtualHost>>,
+  /// The players logged in through the proxy. This client is added once they
+  /// log in, and removed once this connection is dropped.
+  online:         Arc<Online>,
+  /// Used when we create the tcp stream connected to the server.
+  server_token:   Token,
+  /// A connection to the server. If none, then we haven't finished handshaking.
+  server_stream:  Option<TcpStream>,
+  /// Bytes that need to be written to the server. Will be empty if we have
+  /// written everything.
+  to_server:      Vec<u8>,
+  /// Bytes that have been read from the server, but don't form a complete
+  /// packet yet.
+  from_server:    Vec<u8>,
+  /// Set when the server asks us to switch to a hostname. The listener will
+  /// take this, and resolve the hostname in the background.
+  pending_switch: Option<resolve::Switch>,
+  /// Set once the server has sent us anything other than `ServerFull`. Before
+  /// this, the client is still on the loading screen, so they need to be sent
+  /// a world before they can be moved into limbo.
+  in_game:        bool,
+  /// Settings for heartbeats, and for resuming the connection to the server.
+  link_config:    Arc<LinkConfig>,
+  /// The state of the connection to the server.
+  link:           Link,
+
+  /// If set, clients will be checked against this ban list when they log in.
+  bans:      Option<Arc<Mutex<BanList>>>,
+  /// If set, clients must be allowed by this whitelist to log in, and the
+  /// status will show the maintenance message during maintenance.
+  whitelist: Option<Arc<Mutex<Whitelist>>>,
+
+  /// Handlers that send login plugin requests to the client before it
+  /// finishes logging in.
+  login_handlers:     Vec<Arc<dyn LoginHandler>>,
+  /// The id of the next login plugin request.
+  next_login_message: i32,
+
+  /// If set, the client will be moved into limbo when the server goes down.
+  limbo_config: Option<Arc<LimboConfig>>,
+  /// Set while the client is in limbo.
+  limbo:        Option<Limbo>,
+  /// If set, clients are held in limbo when the server is full, and wait in
+  /// this queue.
+  queue:        Option<Arc<Queue>>,
+
+  conv:           Arc<TypeConverter>,
+  status_builder: Arc<dyn for<'b> Fn(&'b str, ProtocolVersion) -> JsonStatus<'b>>,
+
+  /// If set, every packet from the client will be traced, and any traces that
+  /// take longer than this will be logged.
+  trace_slow:     Option<Duration>,
+  next_trace:     u32,
+  /// Traces that have been sent to the server, but haven't gotten a
+  /// `TraceEnd` packet back yet.
+  pending_traces: HashMap<u32, Trace>,
+  traces:         TraceLog,
+}
+thread_local! {
+  // Used when reading from the server.
+  static READ_GARBAGE: RefCell<Vec<u8>> = RefCell::new(vec![0; 64 * 1024]);
+  // Used when writing to the server.
+  static WRITE_GARBAGE: RefCell<Vec<u8>> = RefCell::new(vec![]);
+}
+
+impl<S> Drop for Conn<'_, S> {
+  fn drop(&mut self) {
+    // Let the server know the client left on purpose, so that it doesn't hold
+    // onto the player. If this doesn't get through, the server will remove the
+    // player once its grace period is over.
+    if let Some(stream) = &mut self.server_stream {
+      self.to_server.extend_from_slice(&encode(&csb::Packet::Leave));
+      let _ = stream.write(&self.to_server);
+    }
+    if let ConnState::Play(play) = &self.state {
+      self.online.remove(play.info.id);
+      if let Some(queue) = &self.queue {
+        queue.leave(play.info.id);
+      }
+    }
+  }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Conn<'_, S> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("JavaStream")
+      .field("client_stream", &self.client_stream)
+      .field("state", &self.state.state())
+      .field("ver", &self.ver)
+      .field("username", &self.state.username())
+      .field("closed", &self.closed)
+      .finish()
+  }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoginInfo {
+  pub id:         UUID,
+  // Player's username
+  pub name:       String,
+  // Things like textures
+  pub properties: Vec<LoginProperty>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoginProperty {
+  // Example: "textures"
+  pub name:      String,
+  // Example: base64 encoded png
+  pub value:     String,
+  // Example: base64 signature, signed with Yggdrasil's private key
+  pub signature: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct JsonStatus<'a> {
+  pub version:     JsonVersion,
+  pub players:     JsonPlayers,
+  pub description: Chat,
+  pub favicon:     &'a str,
+}
+
+#[derive(Serialize)]
+pub struct JsonVersion {
+  pub name:     String,
+  pub protocol: i32,
+}
+
+#[derive(Serialize)]
+pub struct JsonPlayers {
+  pub max:    i32,
+  pub online: i32,
+  pub sample: Vec<JsonPlayer>,
+}
+
+#[derive(Serialize)]
+pub struct JsonPlayer {
+  pub name: String,
+  pub id:   String,
+}
+
+impl LoginInfo {
+  pub fn offline(name: &str) -> Self {
+    Self { id: UUID::offline(name), name: name.to_string(), properties: vec![] }
+  }
+
+  /// Returns the profile properties, which are passed on to the server as-is,
+  /// so that the signatures stay valid.
+  pub fn profile_properties(&self) -> Vec<ProfileProperty> {
+    self
+      .properties
+      .iter()
+      .map(|p| ProfileProperty {
+        name:      p.name.clone(),
+        value:     p.value.clone(),
+        signature: p.signature.clone(),
+      })
+      .collect()
+  }
+}
+
+impl<'a, S: PacketStream + Send + Sync> Conn<'a, S> {
+  pub fn new(
+    client_stream: S,
+    server_addr: Arc<dyn Fn() -> SocketAddr>,
+    key: Arc<RsaPrivateKey>,
+    der_key: Option<Vec<u8>>,
+    server_token: Token,
+    conv: Arc<TypeConverter>,
+    forwarding: config::Forwarding,
+    status_builder: Arc<dyn for<'b> Fn(&'b str, ProtocolVersion) -> JsonStatus<'b>>,
+  ) -> Self {
+    Conn {
+      client_stream,
+      state: ConnState::Handshake,
+      ver: ProtocolVersion::Invalid,
+      icon: "",
+      client_addr: None,
+      client_kind: ClientKind::Vanilla,
+      key,
+      der_key,
+      forwarding,
+      compression_target: 0,
+      pass_through: config::PassThrough::None,
+      passed_ids: HashSet::new(),
+      closed: false,
+      server_addr,
+      hosts: Arc::new(Hosts::new()),
+      host: None,
+      online: Arc::new(Online::new()),
+      server_stream: None,
+      server_token,
+      to_server: Vec::with_capacity(16 * 1024),
+      from_server: Vec::with_capacity(16 * 1024),
+      pending_switch: None,
+      in_game: false,
+      link_config: Arc::new(LinkConfig::default()),
+      link: Link::new(),
+      bans: None,
+      whitelist: None,
+      login_handlers: vec![],
+      next_login_message: 0,
+      limbo_config: None,
+      limbo: None,
+      queue: None,
+      conv,
+      status_builder,
+      trace_slow: None,
+      next_trace: 0,
+      pending_traces: HashMap::new(),
+      traces: TraceLog::new(TRACE_WINDOW),
+    }
+  }
+  pub fn with_compression(mut self, compression_target: i32) -> Self {
+    self.compression_target = compression_target;
+    self
+  }
+  pub fn with_icon(mut self, icon: &'a str) -> Self {
+    self.icon = icon;
+    self
+  }
+  /// Enables tracing for this connection. Any packets that take longer than
+  /// `slow` to handle will be logged.
+  pub fn with_tracing(mut self, slow: Option<Duration>) -> Self {
+    self.trace_slow = slow;
+    self
+  }
+  /// Sets the versions where unknown packets from the client are sent to the
+  /// server as-is, instead of closing the connection.
+  pub fn with_pass_through(mut self, pass_through: config::PassThrough) -> Self {
+    self.pass_through = pass_through;
+    self
+  }
+  /// Sets the address the client connected from.
+  pub fn with_client_addr(mut self, addr: SocketAddr) -> Self {
+    self.client_addr = Some(addr);
+    self
+  }
+  /// Sets the ban list. Banned clients will be disconnected when they log in.
+  pub fn with_bans(mut self, bans: Option<Arc<Mutex<BanList>>>) -> Self {
+    self.bans = bans;
+    self
+  }
+  /// Sets the whitelist. Clients that aren't allowed will be disconnected when
+  /// they log in.
+  pub fn with_whitelist(mut self, whitelist: Option<Arc<Mutex<Whitelist>>>) -> Self {
+    self.whitelist = whitelist;
+    self
+  }
+  /// Sets the login plugin handlers. See [`login_plugin`](crate::login_plugin).
+  pub fn with_login_handlers(mut self, handlers: Vec<Arc<dyn LoginHandler>>) -> Self {
+    self.login_handlers = handlers;
+    self
+  }
+  /// Sets the virtual hosts. Clients that connect with one of these hostnames
+  /// will see that host's status, and will be sent to that host's server.
+  pub fn with_hosts(mut self, hosts: Arc<Hosts>) -> Self {
+    self.hosts = hosts;
+    self
+  }
+  /// Sets the list of online players. This client will be added to it once
+  /// they log in.
+  pub fn with_online(mut self, online: Arc<Online>) -> Self {
+    self.online = online;
+    self
+  }
+  /// Sets how often heartbeats are sent to the server, and how long to try
+  /// resuming the connection to the server if it drops.
+  pub fn with_link(mut self, link: Arc<LinkConfig>) -> Self {
+    self.link_config = link;
+    self
+  }
+  /// Enables limbo for this connection. If the server goes down, the client
+  /// will be held in an empty world until it comes back.
+  pub fn with_limbo(mut self, limbo: Option<Arc<LimboConfig>>) -> Self {
+    self.limbo_config = limbo;
+    self
+  }
+  /// Sets the join queue. If the server is full, the client will wait in this
+  /// queue in limbo, instead of being disconnected. This does nothing if limbo
+  /// is disabled.
+  pub fn with_queue(mut self, queue: Option<Arc<Queue>>) -> Self {
+    self.queue = queue;
+    self
+  }
+
+  pub fn ver(&self) -> ProtocolVersion { self.ver }
+  pub fn state(&self) -> State { self.state.state() }
+  pub fn closed(&self) -> bool { self.closed }
+  /// Returns the username of this client, or `unknown` if they haven't sent it
+  /// yet.
+  pub fn username(&self) -> &str { self.state.username().unwrap_or("unknown") }
+  /// Returns the packets from this client that have been traced in the last 30
+  /// seconds. This will be empty unless tracing is enabled.
+  pub fn traces(&self) -> &TraceLog { &self.traces }
+
+  /// Returns the address of the server this client should be sent to. This is
+  /// the virtual host's server, if the client connected with one.
+  fn server_addr(&self) -> SocketAddr {
+    match self.host.as_ref().and_then(|h| h.server()) {
+      Some(addr) => addr,
+      None => (self.server_addr)(),
+    }
+  }
+
+  fn connect_to_server(&mut self, reg: &Registry) -> Result<()> {
+    let addr = self.server_addr();
+    info!("connecting to server at {:?}", addr);
+    let mut stream = TcpStream::connect(addr)?;
+    reg.register(&mut stream, self.server_token, Interest::READABLE | Interest::WRITABLE).unwrap();
+    self.server_stream = Some(stream);
+    self.link.connected(addr);
+
+    let info = self.join_info(JoinMode::New);
+    self.write_data_to_server(|_, m| {
+      m.write(&info)?;
+      Ok(())
+    })
+  }
+
+  /// Returns the info the server needs when this client joins it. This can only
+  /// be called once the client is in the play state.
+  fn join_info(&self, mode: JoinMode) -> JoinInfo {
+    match &self.state {
+      ConnState::Play(play) => play.join_info(mode, self.ver, self.client_addr),
+      s => panic!("cannot join a server in state {:?}", s.state()),
+    }
+  }
+
+  pub fn write_server(&mut self) -> Result<()> {
+    // This is `None` while in limbo.
+    let Some(stream) = &mut self.server_stream else { return Ok(()) };
+    let n = stream.write(&self.to_server)?;
+    self.to_server.drain(0..n);
+    Ok(())
+  }
+
+  /// Reads as much data as possible from the server. Returns Ok(true) or Err(_)
+  /// if the connection should be terminated.
+  pub fn read_server(&mut self, reg: &Registry) -> Result<bool> {
+    loop {
+      // This is `None` while in limbo, and the stream is dropped if the server
+      // is full.
+      if self.server_stream.is_none() {
+        return Ok(false);
+      }
+      match self.poll_server() {
+        Ok(true) => return Ok(true),
+        Ok(false) => loop {
+          match self.read_server_packet(reg) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => return Err(e),
+          };
+          if self.closed() {
+            return Ok(true);
+          }
+        },
+        Err(ref e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+        Err(e) => return Err(e.into()),
+      }
+    }
+  }
+
+  /// Returns true if the connection should close.
+  fn poll_server(&mut self) -> io::Result<bool> {
+    READ_GARBAGE.with(|g| {
+      let mut garbage = g.borrow_mut();
+      let n = self.server_stream.as_mut().unwrap().read(&mut garbage)?;
+      if n == 0 {
+        return Ok(true);
+      }
+      self.link.heard();
+      self.from_server.extend_from_slice(&garbage[..n]);
+      Ok(false)
+    })
+  }
+
+  /// Returns true if there are more packets, false if there is an empty or
+  /// partial packet.
+  fn read_server_packet(&mut self, reg: &Registry) -> Result<bool> {
+    let mut m = MessageReader::new(&self.from_server);
+    match m.read_u32() {
+      Ok(len) => {
+        if len as usize + m.index() <= self.from_server.len() {
+          let idx = m.index();
+          self.from_server.drain(0..idx);
+          let mut m =
+            MessageReader::with_limits(&self.from_server[..len as usize], ReadLimits::untrusted());
+          let common = match ccb::Packet::read(&mut m) {
+            Ok(v) => v,
+            Err(ReadError::Valid(e)) => {
+              warn!("invalid message from server: {}", e);
+              let parsed = m.index();
+              self.from_server.drain(0..parsed);
+              return Ok(true);
+            }
+            Err(e @ ReadError::Invalid(_)) => return Err(e.into()),
+          };
+          let parsed = m.index();
+          self.from_server.drain(0..parsed);
+          // `Protocol` is only for us, so the client never sees it.
+          if self.link.protocol.is_none()
+            && (!self.negotiate_protocol(&common) || matches!(common, ccb::Packet::Protocol(_)))
+          {
+            return Ok(true);
+          }
+          if !matches!(common, ccb::Packet::ServerFull(_)) {
+            self.in_game = true;
+            if self.limbo.is_some() {
+              self.leave_limbo()?;
+            }
+            if self.link.resume.take().is_some() {
+              info!("resumed the connection to the server for {}", self.username());
+            }
+          }
+          match common {
+            ccb::Packet::SwitchServer(p) => self.switch_to(reg, p),
+            ccb::Packet::ServerFull(p) => self.server_full(reg, p.reason)?,
+            ccb::Packet::TraceEnd(p) => self.finish_trace(p),
+            ccb::Packet::ServerStatus(p) => self.online.set_report(p),
+            // We only need to know that the server is still there, which `poll_server`
+            // already recorded.
+            ccb::Packet::Heartbeat(_) => {}
+            // The version can't change once it has been picked.
+            ccb::Packet::Protocol(_) => warn!("server sent a second protocol packet, ignoring it"),
+            ccb::Packet::Kick(p) => {
+              self.send_disconnect(p.reason);
+              self.closed = true;
+              // The client is removed as soon as we return, so this is our only chance
+              // to send the message.
+              let _ = self.write_client();
+            }
+            common => {
+              let packets = common.to_tcp(self).unwrap();
+              if len as usize != parsed {
+                return Err(io::Error::new(
+                  ErrorKind::InvalidData,
+                  format!(
+                    "did not read all the packet data (expected to read {len} bytes, but only read {parsed} bytes)"
+                  ),
+                ).into());
+              }
+              for p in packets {
+                self.send_to_client(p)?;
+              }
+            }
+          }
+          Ok(true)
+        } else {
+          Ok(false)
+        }
+      }
+      // There are no bytes waiting for us, or an invalid varint.
+      Err(e) => {
+        if matches!(e, ReadError::Invalid(InvalidReadError::EOF)) {
+          Ok(false)
+        } else {
+          Err(io::Error::new(ErrorKind::InvalidData, e.to_string()).into())
+        }
+      }
+    }
+  }
+
+  /// Checks the protocol version the server picked, using the first packet it
+  /// sent. Servers from before versions were negotiated don't send
+  /// [`Protocol`](ccb::Packet::Protocol), so any other packet means version 0.
+  /// If the version isn't supported, the client is disconnected, and this
+  /// returns `false`.
+  fn negotiate_protocol(&mut self, first: &ccb::Packet) -> bool {
+    let (version, capabilities) = match first {
+      ccb::Packet::Protocol(p) => (p.version, p.capabilities),
+      // The server rejected us, and the kick explains why.
+      ccb::Packet::Kick(_) => return true,
+      _ => (0, Capabilities::default()),
+    };
+    let ours = ProtocolInfo::current();
+    if !ours.supports(version) {
+      let err =
+        ProtocolError { local: (ours.min_version, ours.max_version), peer: (version, version) };
+      warn!("server at {:?} cannot be used by {}: {err}", self.link.addr, self.username());
+      let mut msg = Chat::empty();
+      msg.add("The proxy and the server are running incompatible versions.").color(Color::Red);
+      self.send_disconnect(msg);
+      self.closed = true;
+      let _ = self.write_client();
+      return false;
+    }
+    self.link.protocol =
+      Some(Negotiated { version, capabilities: ours.capabilities.intersect(&capabilities) });
+    true
+  }
+
+  /// Writes all the data possible to the client. Returns Err(WouldBlock) or
+  /// Ok(()) if everything worked as expected.
+  pub fn write_client(&mut self) -> Result<()> {
+    while self.client_stream.needs_flush() {
+      self.client_stream.flush()?;
+    }
+    Ok(())
+  }
+
+  /// Reads as much data as possible, without blocking. Returns Ok(true) or
+  /// Err(_) if the connection should be closed.
+  pub fn read_client(&mut self, reg: &Registry) -> Result<bool> {
+    // Poll, then read, then try and poll again. If we have no data left, we return
+    // Ok(false). If we have closed the connection, we return Ok(true). If we error
+    // at all, we close the connection.
+    loop {
+      match self.poll_client() {
+        Ok(_) => match self.read_client_packet(reg) {
+          Ok(_) => {
+            if self.closed() {
+              return Ok(true);
+            }
+          }
+          Err(e) => return Err(e),
+        },
+        Err(ref e) if e.io_kind() == Some(ErrorKind::WouldBlock) => return Ok(false),
+        Err(e) => return Err(e),
+      }
+    }
+  }
+  /// Reads data from the client tcp connection, and buffers that to be read in
+  /// `read_client_packet`.
+  fn poll_client(&mut self) -> Result<()> { self.client_stream.poll() }
+  /// Reads a packet from the internal buffer from the client. Does not interact
+  /// with the tcp connection at all.
+  fn read_client_packet(&mut self, reg: &Registry) -> Result<()> {
+    loop {
+      match self.client_stream.read(self.ver) {
+        Ok(Some(mut p)) => match self.state {
+          ConnState::Handshake => {
+            let p = Handshake::read(&mut p, &self.forwarding)?;
+            self.handle_handshake(p)?;
+          }
+          ConnState::Status => {
+            let p = StatusPacket::read(&mut p)?;
+            self.handle_status(p)?;
+          }
+          ConnState::Login(_) => {
+            let p = LoginPacket::read(&mut p)?;
+            self.handle_login(p, reg)?;
+          }
+          // The server isn't there to handle anything, so we drop everything.
+          ConnState::Play(_) if self.limbo.is_some() || self.link.resume.is_some() => {}
+          ConnState::Play(_) => match gsb::Packet::from_tcp(&mut p, self.ver) {
+            Ok(packet) => self.send_to_server(packet)?,
+            Err(Error::UnknownId { id, .. }) if self.pass_through.matches(self.ver) => {
+              let data = p.read_all();
+              self.send_raw_to_server(id, data)?;
+            }
+            Err(e) => return Err(e),
+          },
+        },
+        Ok(None) => break,
+        Err(e) => return Err(e),
+      }
+    }
+    Ok(())
+  }
+
+  /// Tries to send the packet to the client, and buffers it if that is not
+  /// possible.
+  fn send_to_client(&mut self, p: gcb::Packet) -> Result<()> {
+    if log::max_level() >= log::LevelFilter::Debug {
+      let debugged = format!("{p:?}");
+      let msg = if debugged.len() > 100 { format!("{}...", &debugged[..100]) } else { debugged };
+      debug!("sending packet (tcp id: {0} {0:#x}) {1}", p.tcp_id(self.ver), msg);
+    }
+
+    let mut tcp = tcp::Packet::new(p.tcp_id(self.ver).try_into().unwrap(), self.ver);
+    p.to_tcp(&mut tcp);
+    // debug!("sending bytes {:?}", tcp);
+    self.client_stream.write(tcp);
+    self.write_client()
+  }
+
+  /// Tries to send the packet to the server, and buffers it if that is not
+  /// possible.
+  fn send_to_server(&mut self, p: gsb::Packet) -> Result<()> {
+    // An error here is for an unimplemented packet
+    let common = match csb::Packet::from_tcp(p, self.ver, self.conv.as_ref()) {
+      Ok(p) => p,
+      Err(e) => {
+        warn!("{e}");
+        return Ok(());
+      }
+    };
+    if self.trace_slow.is_some() {
+      let id = self.start_trace(&common);
+      self.write_data_to_server(|_, m| {
+        csb::Packet::Trace { id }.write(m).unwrap();
+        Ok(())
+      })?;
+    }
+    self.write_data_to_server(|_, m| {
+      // The only error here is EOF, which means the garbage buffer was not enough
+      // space for this packet.
+      common.write(m).unwrap();
+      Ok(())
+    })
+  }
+
+  /// Sends a packet the proxy doesn't know about directly to the server. This
+  /// logs a warning the first time each packet id is passed through.
+  fn send_raw_to_server(&mut self, id: i32, data: Vec<u8>) -> Result<()> {
+    if self.passed_ids.insert(id) {
+      warn!(
+        "passing through unknown packet {id:#x} from {} on version {}",
+        self.username(),
+        self.ver
+      );
+    }
+    self.write_data_to_server(|_, m| {
+      csb::Packet::Raw { id, data }.write(m).unwrap();
+      Ok(())
+    })
+  }
+
+  /// Starts a new trace for the given packet, and returns the trace id.
+  fn start_trace(&mut self, p: &csb::Packet) -> u32 {
+    // If the server never replies (for example, if it is an older server), we
+    // don't want to store traces forever.
+    if self.pending_traces.len() >= 1024 {
+      self.pending_traces.clear();
+    }
+    let id = self.next_trace;
+    self.next_trace = self.next_trace.wrapping_add(1);
+    let debugged = format!("{p:?}");
+    let name = match debugged.find([' ', '{', '(']) {
+      Some(idx) => &debugged[..idx],
+      None => &debugged,
+    };
+    self.pending_traces.insert(id, Trace::new(id, name));
+    id
+  }
+
+  /// Finishes a trace, after the server has handled the traced packet. All of
+  /// the packets the server sent while handling it have already been sent to
+  /// the client at this point.
+  fn finish_trace(&mut self, p: ccb::packet::TraceEnd) {
+    let mut trace = match self.pending_traces.remove(&p.id) {
+      Some(t) => t,
+      None => return,
+    };
+    // The server spans start when the server received the packet, not when we
+    // received it, so they are offset by the time it took to get to the server.
+    for span in p.spans {
+      trace.span_at(format!("server {}", span.name), Duration::from_micros(span.micros));
+    }
+    trace.span("done");
+    if let Some(slow) = self.trace_slow {
+      if trace.total() >= slow {
+        info!("slow packet from {}: {trace}", self.username());
+      }
+    }
+    self.traces.push(trace);
+  }
+
+  fn write_data_to_server(
+    &mut self,
+    f: impl FnOnce(&mut Self, &mut MessageWriter<&mut Vec<u8>>) -> Result<()>,
+  ) -> Result<()> {
+    WRITE_GARBAGE.with(|g| {
+      let mut garbage = g.borrow_mut();
+      garbage.clear();
+      let mut m = MessageWriter::new(garbage.as_mut());
+      f(self, &mut m)?;
+      let len = m.index();
+      // No data was written, so we don't send this.
+      if len == 0 {
+        return Ok(());
+      }
+
+      let mut prefix = [0; 5];
+      let mut m = MessageWriter::new(prefix.as_mut_slice());
+      m.write_u32(len as u32).unwrap();
+      let prefix_len = m.index();
+      self.to_server.extend_from_slice(&prefix[..prefix_len]);
+      self.to_server.extend_from_slice(&garbage); // We don't need `[..len]`, as we called `clear` above.
+
+      self.write_server()
+    })
+  }
+
+  /// Switches this connection to a new server. If the packet has a hostname,
+  /// this will only store the switch, and the listener will call
+  /// [`switch_to_addrs`](Self::switch_to_addrs) once the hostname is resolved.
+  pub fn switch_to(&mut self, reg: &Registry, p: ccb::packet::SwitchServer) {
+    match p.host {
+      Some(host) => self.pending_switch = Some(resolve::Switch { mode: p.mode, host, ips: p.ips }),
+      None => self.switch_to_addrs(reg, p.mode, p.ips),
+    }
+  }
+
+  /// Returns the server switch that is waiting on a hostname lookup, if any.
+  pub fn take_pending_switch(&mut self) -> Option<resolve::Switch> { self.pending_switch.take() }
+
+  /// Switches this connection to the first server in `ips` that accepts the
+  /// connection. If all of the ips are bad, this doesn't change anything.
+  pub fn switch_to_addrs(&mut self, reg: &Registry, mode: SwitchMode, ips: Vec<SocketAddr>) {
+    for addr in ips {
+      let conn = match TcpStream::connect(addr) {
+        Ok(v) => v,
+        Err(_) => continue,
+      };
+
+      let mut old_stream = std::mem::replace(&mut self.server_stream, Some(conn));
+      let new_stream = self.server_stream.as_mut().unwrap();
+      reg.deregister(old_stream.as_mut().unwrap()).unwrap();
+      reg.register(new_stream, self.server_token, Interest::READABLE | Interest::WRITABLE).unwrap();
+      let old_to_server = self.to_server.clone();
+      let old_from_server = self.from_server.clone();
+      self.to_server.clear();
+      self.from_server.clear();
+
+      let info = self.join_info(JoinMode::Switch(mode));
+      match self.write_data_to_server(|_, m| {
+        m.write(&info)?;
+        Ok(())
+      }) {
+        Ok(()) => {
+          self.link.connected(addr);
+          // The old server should remove the player right away, instead of waiting
+          // for us to resume the connection.
+          if let Some(old) = &mut old_stream {
+            let mut data = old_to_server;
+            data.extend_from_slice(&encode(&csb::Packet::Leave));
+            let _ = old.write(&data);
+          }
+          break;
+        }
+        Err(_) => {
+          // new_stream is the one we created above, and we now want to deregister it.
+          let mut new_stream = std::mem::replace(&mut self.server_stream, old_stream);
+          let old_stream = self.server_stream.as_mut().unwrap();
+          reg.deregister(new_stream.as_mut().unwrap()).unwrap();
+          reg
+            .register(old_stream, self.server_token, Interest::READABLE | Interest::WRITABLE)
+            .unwrap();
+          self.to_server.clear();
+          self.from_server.clear();
+          self.to_server.extend_from_slice(&old_to_server);
+          self.from_server.extend_from_slice(&old_from_server);
+          continue;
+        }
+      }
+    }
+  }
+
+  /// Called when the connection to the server has been lost. This first tries
+  /// to resume the connection, and the client is moved into limbo if that
+  /// doesn't work. In either case, this returns `true`. If neither is
+  /// possible, this returns `false`, and the client should be disconnected.
+  pub fn server_lost(&mut self, reg: &Registry) -> bool {
+    if !self.in_game || self.closed {
+      return false;
+    }
+    // If we are already resuming, then a retry failed, and we just wait for the
+    // next one.
+    if self.link.resume.is_some() {
+      self.drop_server(reg);
+      return true;
+    }
+    if let (None, Some(addr)) = (&self.limbo, self.link.addr) {
+      if !self.link_config.grace.is_zero() {
+        info!("lost connection to the server, trying to resume it for {}", self.username());
+        self.drop_server(reg);
+        self.link.resume = Some(Resume::new(addr));
+        return true;
+      }
+    }
+    if self.limbo_config.is_none() {
+      return false;
+    }
+    self.drop_server(reg);
+    // If we are already in limbo, then a reconnect failed, and we just wait for
+    // the next one.
+    if self.limbo.is_none() {
+      info!("lost connection to the server, moving {} into limbo", self.username());
+      if let Err(e) = self.enter_restart_limbo() {
+        warn!("could not send limbo screen: {e}");
+        return false;
+      }
+    }
+    true
+  }
+
+  /// Moves the client into limbo, and tells them the server is restarting.
+  fn enter_restart_limbo(&mut self) -> Result<()> {
+    let mut title = Chat::empty();
+    title.add("Server restarting").color(Color::Yellow);
+    let mut subtitle = Chat::empty();
+    subtitle.add("You will be reconnected soon").color(Color::Gray);
+    self.enter_limbo(title, subtitle, None)
+  }
+
+  /// Sends heartbeats to the server, and checks that the server is still
+  /// replying. If the connection is being resumed, this retries it, and gives
+  /// up once the grace period is over. This should be called about once a
+  /// second.
+  pub fn tick_link(&mut self, reg: &Registry) -> Result<()> {
+    let now = Instant::now();
+    if let Some(resume) = &mut self.link.resume {
+      if now - resume.since >= self.link_config.grace {
+        self.link.resume = None;
+        self.drop_server(reg);
+        if self.limbo_config.is_some() {
+          info!("could not resume the connection for {}, moving them into limbo", self.username());
+          return self.enter_restart_limbo();
+        }
+        info!("could not resume the connection for {}, disconnecting them", self.username());
+        let mut msg = Chat::empty();
+        msg.add("Lost connection to the server").color(Color::Red);
+        self.send_disconnect(msg);
+        self.closed = true;
+        return self.write_client();
+      }
+      if self.server_stream.is_none() && now >= resume.next_retry {
+        resume.next_retry = now + RESUME_RETRY;
+        let addr = resume.addr;
+        if let Err(e) = self.reconnect(reg, addr, JoinMode::Resume) {
+          debug!("could not resume the connection to {addr}: {e}");
+          if let Some(mut stream) = self.server_stream.take() {
+            let _ = reg.deregister(&mut stream);
+          }
+          self.to_server.clear();
+        }
+      }
+      return Ok(());
+    }
+    // While in limbo, the server stream is only there for reconnecting.
+    if self.server_stream.is_none()
+      || self.limbo.is_some()
+      || !matches!(self.state, ConnState::Play(_))
+    {
+      return Ok(());
+    }
+    if self.link.timed_out(&self.link_config, now) {
+      warn!("the server stopped responding for {}", self.username());
+      return self.lost_or_disconnect(reg);
+    }
+    if let Some(id) = self.link.heartbeat(&self.link_config, now) {
+      let res = self.write_data_to_server(|_, m| {
+        csb::Packet::Heartbeat { id }.write(m).unwrap();
+        Ok(())
+      });
+      match res {
+        Err(e) if e.io_kind() != Some(ErrorKind::WouldBlock) => {
+          return self.lost_or_disconnect(reg);
+        }
+        _ => {}
+      }
+    }
+    Ok(())
+  }
+
+  /// Calls [`server_lost`](Self::server_lost), and disconnects the client if
+  /// that doesn't work.
+  fn lost_or_disconnect(&mut self, reg: &Registry) -> Result<()> {
+    if self.server_lost(reg) {
+      return Ok(());
+    }
+    self.drop_server(reg);
+    let mut msg = Chat::empty();
+    msg.add("Lost connection to the server").color(Color::Red);
+    self.send_disconnect(msg);
+    self.closed = true;
+    self.write_client()
+  }
+
+  /// Called when the server sends `ServerFull` instead of joining the client.
+  /// If the queue is enabled, this moves the client into limbo, where they
+  /// wait in the queue. Otherwise, the client is disconnected.
+  fn server_full(&mut self, reg: &Registry, reason: Chat) -> Result<()> {
+    let (Some(queue), Some(_), ConnState::Play(play)) =
+      (&self.queue, &self.limbo_config, &self.state)
+    else {
+      self.send_disconnect(reason);
+      self.closed = true;
+      // The client is removed as soon as we return, so this is our only chance
+      // to send the message.
+      let _ = self.write_client();
+      return Ok(());
+    };
+    let pos = queue.join(play.info.id);
+    self.drop_server(reg);
+    // If we are already in the queue, then this was our turn to join, and the
+    // server is still full. We just try again later.
+    if self.limbo.is_none() {
+      info!("server is full, moving {} into the queue", self.username());
+      let mut title = Chat::empty();
+      title.add("Server is full").color(Color::Yellow);
+      self.enter_limbo(title, queue_message(pos), Some(pos))?;
+    }
+    Ok(())
+  }
+
+  /// Drops the connection to the server, along with anything that was going
+  /// to be sent to the server.
+  fn drop_server(&mut self, reg: &Registry) {
+    if let Some(mut stream) = self.server_stream.take() {
+      let _ = reg.deregister(&mut stream);
+    }
+    self.to_server.clear();
+    self.from_server.clear();
+    self.pending_traces.clear();
+    self.pending_switch = None;
+  }
+
+  /// Moves the client into limbo, and shows them the given title. If the
+  /// client hasn't joined a world yet, they are sent an empty one.
+  fn enter_limbo(&mut self, title: Chat, subtitle: Chat, queue_pos: Option<usize>) -> Result<()> {
+    let mut limbo = Limbo::new(self.limbo_config.as_ref().unwrap().retry);
+    limbo.queue_pos = queue_pos;
+    self.limbo = Some(limbo);
+    if self.in_game {
+      // Moving to the end and back makes the client forget the old world.
+      self.send_common(ccb::packet::Respawn {
+        game_mode:    GameMode::Adventure,
+        dimension:    1,
+        level_type:   "default".into(),
+        difficulty:   1,
+        reset_meta:   true,
+        world_height: 256,
+        world_min_y:  0,
+      })?;
+    } else {
+      // Like the server, we use EID 1, so that the server's `JoinGame` isn't
+      // needed once the client gets in.
+      self.send_common(ccb::packet::JoinGame {
+        eid:                   1,
+        hardcore_mode:         false,
+        game_mode:             GameMode::Adventure,
+        dimension:             1,
+        level_type:            "default".into(),
+        difficulty:            1,
+        view_distance:         2,
+        reduced_debug_info:    false,
+        enable_respawn_screen: false,
+        world_height:          256,
+        world_min_y:           0,
+      })?;
+      // This closes the loading screen.
+      self.send_common(ccb::packet::SetPosLook {
+        pos:             FPos::new(0.0, 64.0, 0.0),
+        yaw:             0.0,
+        pitch:           0.0,
+        flags:           0,
+        teleport_id:     0,
+        should_dismount: false,
+      })?;
+      self.in_game = true;
+    }
+    self.send_common(ccb::packet::Title {
+      action: ccb::TitleAction::Times { fade_in: 10, stay: 72000, fade_out: 10 },
+    })?;
+    self.send_common(ccb::packet::Title { action: ccb::TitleAction::Title(title) })?;
+    self.send_common(ccb::packet::Title { action: ccb::TitleAction::Subtitle(subtitle) })
+  }
+
+  /// Returns this client's position in the join queue, if they are in it.
+  fn queue_position(&self) -> Option<usize> {
+    match (&self.queue, &self.state) {
+      (Some(queue), ConnState::Play(play)) => queue.position(play.info.id),
+      _ => None,
+    }
+  }
+
+  /// Sends keep alives to the client, and tries to reconnect to a server, if
+  /// needed. This does nothing if the client isn't in limbo.
+  pub fn tick_limbo(&mut self, reg: &Registry) -> Result<()> {
+    let Some(limbo) = &mut self.limbo else { return Ok(()) };
+    let config = self.limbo_config.clone().unwrap();
+    let now = Instant::now();
+    // Clients in the queue are waiting for space, not for the server to come
+    // back, so they never time out.
+    if limbo.queue_pos.is_none() && config.timeout.map_or(false, |t| now - limbo.since >= t) {
+      let mut msg = Chat::empty();
+      msg.add("The server is offline").color(Color::Red);
+      self.send_disconnect(msg);
+      self.closed = true;
+      return self.write_client();
+    }
+    if now >= limbo.next_keep_alive {
+      limbo.next_keep_alive = now + KEEP_ALIVE_INTERVAL;
+      self.send_common(ccb::packet::KeepAlive { id: 0 })?;
+    }
+    let server = self.server_addr();
+    let queue_pos = self.queue_position();
+    let Some(limbo) = &mut self.limbo else { return Ok(()) };
+    if let (Some(shown), Some(pos)) = (limbo.queue_pos, queue_pos) {
+      if shown != pos {
+        limbo.queue_pos = Some(pos);
+        self.send_common(ccb::packet::Title {
+          action: ccb::TitleAction::Subtitle(queue_message(pos)),
+        })?;
+      }
+    }
+    let Some(limbo) = &mut self.limbo else { return Ok(()) };
+    // Only the front of the queue tries to join, so that clients get in in the
+    // order they joined the queue.
+    let waiting = limbo.queue_pos.map_or(false, |pos| pos != 0);
+    if self.server_stream.is_none() && !waiting && now >= limbo.next_retry {
+      let addr = config.target(limbo.attempt, || server);
+      limbo.attempt += 1;
+      limbo.next_retry = now + config.retry;
+      if let Err(e) = self.reconnect(reg, addr, JoinMode::Switch(SwitchMode::Loading)) {
+        debug!("could not reconnect to {addr}: {e}");
+        if let Some(mut stream) = self.server_stream.take() {
+          let _ = reg.deregister(&mut stream);
+        }
+        self.to_server.clear();
+      }
+    }
+    Ok(())
+  }
+
+  /// Connects to the given server while in limbo, or while resuming the
+  /// connection. We don't know if the connection worked until the server sends
+  /// us something, so the client stays in limbo until then.
+  fn reconnect(&mut self, reg: &Registry, addr: SocketAddr, mode: JoinMode) -> Result<()> {
+    debug!("trying to reconnect to {addr}");
+    let mut stream = TcpStream::connect(addr)?;
+    reg.register(&mut stream, self.server_token, Interest::READABLE | Interest::WRITABLE)?;
+    self.server_stream = Some(stream);
+    self.link.connected(addr);
+    let info = self.join_info(mode);
+    let res = self.write_data_to_server(|_, m| {
+      m.write(&info)?;
+      Ok(())
+    });
+    match res {
+      // The connection hasn't finished yet, so we will flush once it is writable.
+      Err(e) if e.io_kind() == Some(ErrorKind::WouldBlock) => Ok(()),
+      res => res,
+    }
+  }
+
+  /// Called once the server sends us something after a reconnect.
+  fn leave_limbo(&mut self) -> Result<()> {
+    self.limbo = None;
+    if let (Some(queue), ConnState::Play(play)) = (&self.queue, &self.state) {
+      queue.leave(play.info.id);
+    }
+    info!("reconnected {} to the server", self.username());
+    self.send_common(ccb::packet::Title { action: ccb::TitleAction::Clear(true) })
+  }
+
+  /// Converts the given packet for the client, and sends it.
+  fn send_common(&mut self, p: impl Into<ccb::Packet>) -> Result<()> {
+    let packets =
+      p.into().to_tcp(self).map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+    for p in packets {
+      self.send_to_client(p)?;
+    }
+    Ok(())
+  }
+
+  // Disconnects the client during authentication. The stream will not be flushed.
+  fn send_disconnect<C: Into<Chat>>(&mut self, reason: C) {
+    match self.state {
+      ConnState::Login(_) => {
+        let mut out = tcp::Packet::new(0, self.ver);
+        out.write_str(&reason.into().to_json_for(self.ver));
+        self.client_stream.write(out);
+      }
+      ConnState::Play(_) => {
+        let out = gcb::Packet::from(gcb::packet::DisconnectV8 {
+          reason: reason.into().to_json_for(self.ver),
+        });
+        let mut tcp = tcp::Packet::new(out.tcp_id(self.ver) as i32, self.ver);
+        out.to_tcp(&mut tcp);
+        self.client_stream.write(tcp);
+      }
+      ref s => panic!("cannot send disconnect for state {:?}", s.state()),
+    }
+  }
+
+  pub fn conv(&self) -> &TypeConverter { self.conv.as_ref() }
+}
+
+/// Serializes a packet for the server, with a length prefix.
+fn encode(p: &csb::Packet) -> Vec<u8> {
+  let mut body = vec![];
+  p.write(&mut MessageWriter::new(&mut body)).unwrap();
+  let mut out = Vec::with_capacity(body.len() + 5);
+  MessageWriter::new(&mut out).write_u32(body.len() as u32).unwrap();
+  out.extend_from_slice(&body);
+  out
+}
+
+/// The subtitle shown to clients waiting in the queue. `pos` starts at 0.
+fn queue_message(pos: usize) -> Chat {
+  let mut msg = Chat::empty();
+  msg.add("You are ").color(Color::Gray);
+  msg.add(format!("#{}", pos + 1)).color(Color::Gold);
+  msg.add(" in the queue").color(Color::Gray);
+  msg
+}
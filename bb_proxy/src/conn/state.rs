@@ -0,0 +1,207 @@
+//! The states a connection goes through. A client always starts in
+//! [`Handshake`](ConnState::Handshake), and then moves to either
+//! [`Status`](ConnState::Status), or [`Login`](ConnState::Login) and then
+//! [`Play`](ConnState::Play). Each state only stores what is needed in that
+//! state, and each state has its own set of packets the client may send, so
+//! that a packet can't be handled in the wrong state.
+
+use super::{LoginInfo, State};
+use crate::{config, gnet::tcp, login_plugin::LoginContext, Result};
+use bb_common::{
+  net::protocol::ProtocolInfo,
+  util::{ClientKind, JoinInfo, JoinMode},
+  version::ProtocolVersion,
+};
+use std::{collections::HashMap, convert::TryInto, io, io::ErrorKind, net::SocketAddr};
+
+#[derive(Debug)]
+pub(super) enum ConnState {
+  Handshake,
+  Status,
+  Login(Login),
+  Play(Play),
+}
+
+/// The login state. Once the client sends a login start, we either ask for
+/// encryption, or go straight to [`Play`]. If there are any login plugin
+/// requests to send, we wait for the client to answer all of them before
+/// moving to [`Play`].
+#[derive(Debug)]
+pub(super) enum Login {
+  /// Waiting for the client to send a login start.
+  Start {
+    /// The player info from the other proxy, if legacy forwarding is enabled.
+    forwarded: Option<LoginInfo>,
+  },
+  /// Waiting for the client to send an encryption response.
+  Encrypt {
+    /// The name sent from the client. The mojang auth server also sends us a
+    /// username; we use this to validate the client info with the mojang auth
+    /// info.
+    username:     String,
+    /// The four byte verify token, which the client must send back to us.
+    verify_token: [u8; 4],
+  },
+  /// Waiting for the client to reply to login plugin requests.
+  Plugin {
+    login:   LoginContext,
+    /// The ids of the requests that haven't been answered, mapped to the
+    /// index of the handler that sent them.
+    pending: HashMap<i32, usize>,
+  },
+}
+
+/// The play state. The client has logged in, and is connected to a server (or
+/// in limbo).
+#[derive(Debug)]
+pub(super) struct Play {
+  /// The name sent from the client.
+  pub username: String,
+  pub info:     LoginInfo,
+  pub client:   ClientKind,
+  /// The client's mods, if they were sent during login.
+  pub mods:     Vec<String>,
+}
+
+impl ConnState {
+  /// Returns the protocol state that the client is in.
+  pub fn state(&self) -> State {
+    match self {
+      Self::Handshake => State::Handshake,
+      Self::Status => State::Status,
+      Self::Login(_) => State::Login,
+      Self::Play(_) => State::Play,
+    }
+  }
+
+  /// Returns the username of this client, if they have sent it yet.
+  pub fn username(&self) -> Option<&str> {
+    match self {
+      Self::Login(Login::Encrypt { username, .. }) => Some(username),
+      Self::Login(Login::Plugin { login, .. }) => Some(&login.username),
+      Self::Play(play) => Some(&play.username),
+      _ => None,
+    }
+  }
+}
+
+impl Play {
+  /// Returns the info the server needs when this client joins it.
+  pub fn join_info(
+    &self,
+    mode: JoinMode,
+    ver: ProtocolVersion,
+    addr: Option<SocketAddr>,
+  ) -> JoinInfo {
+    JoinInfo {
+      mode,
+      username: self.username.clone(),
+      uuid: self.info.id,
+      ver: ver.id(),
+      properties: self.info.profile_properties(),
+      addr,
+      client: self.client,
+      mods: self.mods.clone(),
+      protocol: ProtocolInfo::current(),
+    }
+  }
+}
+
+/// The only packet a client can send in the handshake state.
+#[derive(Debug)]
+pub(super) struct Handshake {
+  pub ver:  ProtocolVersion,
+  /// The address the client connected to. With legacy forwarding, this also
+  /// contains the player info from the other proxy.
+  pub addr: String,
+  pub next: State,
+}
+
+/// Packets the client can send in the status state.
+#[derive(Debug)]
+pub(super) enum StatusPacket {
+  Request,
+  Ping { id: u64 },
+}
+
+/// Packets the client can send in the login state.
+#[derive(Debug)]
+pub(super) enum LoginPacket {
+  Start {
+    name: String,
+  },
+  EncryptionResponse {
+    secret: Vec<u8>,
+    token:  Vec<u8>,
+  },
+  /// A reply to a login plugin request. `data` is `None` if the client didn't
+  /// understand the request.
+  PluginResponse {
+    id:   i32,
+    data: Option<Vec<u8>>,
+  },
+}
+
+impl Handshake {
+  pub fn read(p: &mut tcp::Packet, forwarding: &config::Forwarding) -> Result<Self> {
+    if p.id() != 0 {
+      return Err(unknown("handshake", p.id()));
+    }
+    let ver = ProtocolVersion::from(p.read_varint()?);
+    let addr = match forwarding {
+      // FIXME: not sure what the correct maximum length is
+      config::Forwarding::Legacy => p.read_str(2048)?,
+      // Max len according to 1.17.1
+      config::Forwarding::None => p.read_str(255)?,
+    };
+    let _port = p.read_u16()?;
+    let next = State::from_next(p.read_varint()?);
+    Ok(Handshake { ver, addr, next })
+  }
+}
+
+impl StatusPacket {
+  pub fn read(p: &mut tcp::Packet) -> Result<Self> {
+    Ok(match p.id() {
+      0 => StatusPacket::Request,
+      1 => StatusPacket::Ping { id: p.read_u64()? },
+      id => return Err(unknown("status", id)),
+    })
+  }
+}
+
+impl LoginPacket {
+  pub fn read(p: &mut tcp::Packet) -> Result<Self> {
+    Ok(match p.id() {
+      // Max length according to 1.17.1
+      0 => LoginPacket::Start { name: p.read_str(16)? },
+      1 => {
+        let len = p.read_varint()?;
+        let secret = p.read_buf(len.try_into().unwrap())?;
+        let len = p.read_varint()?;
+        let token = p.read_buf(len.try_into().unwrap())?;
+        LoginPacket::EncryptionResponse { secret, token }
+      }
+      2 => {
+        let id = p.read_varint()?;
+        let data = if p.read_bool()? {
+          // Max length according to 1.17.1
+          if p.remaining() > 1048576 {
+            return Err(
+              io::Error::new(ErrorKind::InvalidInput, "login plugin response is too long").into(),
+            );
+          }
+          Some(p.read_all())
+        } else {
+          None
+        };
+        LoginPacket::PluginResponse { id, data }
+      }
+      id => return Err(unknown("login", id)),
+    })
+  }
+}
+
+fn unknown(state: &str, id: i32) -> crate::Error {
+  io::Error::new(ErrorKind::InvalidInput, format!("unknown {state} packet {id}")).into()
+}
@@ -0,0 +1,113 @@
+//! The type used for large blobs in generated packets. Most packets the
+//! generator doesn't fully understand end with an `unknown` field, which holds
+//! the rest of the packet. For chunks and windows, this is almost all of the
+//! packet, so copying it around is expensive.
+//!
+//! [`Bytes`] can borrow constant data, own its data, or share its data with
+//! other packets, so that none of these need a copy.
+
+use std::{fmt, ops::Deref, sync::Arc};
+
+/// A blob of bytes in a generated packet. This derefs to `[u8]`.
+#[derive(Clone)]
+pub enum Bytes {
+  /// Constant data, such as an empty chunk.
+  Static(&'static [u8]),
+  /// Data only this packet uses.
+  Owned(Vec<u8>),
+  /// Data shared with other packets. Cloning this is cheap.
+  Shared(Arc<[u8]>),
+}
+
+impl Bytes {
+  /// Borrows constant data, without copying it.
+  pub const fn from_static(data: &'static [u8]) -> Self { Bytes::Static(data) }
+
+  /// Moves owned data into an `Arc`, so that clones of this packet share the
+  /// same data. This should be called before sending the same packet to many
+  /// clients.
+  pub fn share(&mut self) -> Arc<[u8]> {
+    let shared: Arc<[u8]> = match self {
+      Bytes::Shared(data) => return data.clone(),
+      Bytes::Static(data) => (*data).into(),
+      Bytes::Owned(data) => std::mem::take(data).into(),
+    };
+    *self = Bytes::Shared(shared.clone());
+    shared
+  }
+
+  /// Returns the data as a `Vec`. This only copies if the data isn't owned.
+  pub fn into_vec(self) -> Vec<u8> {
+    match self {
+      Bytes::Owned(data) => data,
+      data => data.to_vec(),
+    }
+  }
+}
+
+impl Default for Bytes {
+  fn default() -> Self { Bytes::Static(&[]) }
+}
+
+impl Deref for Bytes {
+  type Target = [u8];
+
+  fn deref(&self) -> &[u8] {
+    match self {
+      Bytes::Static(data) => data,
+      Bytes::Owned(data) => data,
+      Bytes::Shared(data) => data,
+    }
+  }
+}
+
+impl AsRef<[u8]> for Bytes {
+  fn as_ref(&self) -> &[u8] { self }
+}
+
+impl PartialEq for Bytes {
+  fn eq(&self, other: &Bytes) -> bool { **self == **other }
+}
+impl Eq for Bytes {}
+
+/// Chunk data is tens of kilobytes, so only short blobs are printed in full.
+impl fmt::Debug for Bytes {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.len() <= 32 {
+      fmt::Debug::fmt(&**self, f)
+    } else {
+      write!(f, "<{} bytes>", self.len())
+    }
+  }
+}
+
+impl From<Vec<u8>> for Bytes {
+  fn from(data: Vec<u8>) -> Self { Bytes::Owned(data) }
+}
+impl From<&'static [u8]> for Bytes {
+  fn from(data: &'static [u8]) -> Self { Bytes::Static(data) }
+}
+impl From<Arc<[u8]>> for Bytes {
+  fn from(data: Arc<[u8]>) -> Self { Bytes::Shared(data) }
+}
+impl From<Bytes> for Vec<u8> {
+  fn from(data: Bytes) -> Self { data.into_vec() }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn share() {
+    let mut data = Bytes::from(vec![1, 2, 3]);
+    let shared = data.share();
+    assert!(matches!(data, Bytes::Shared(_)));
+    assert!(Arc::ptr_eq(&shared, &data.share()));
+    assert_eq!(data.clone(), Bytes::from_static(&[1, 2, 3]));
+    assert_eq!(data.into_vec(), vec![1, 2, 3]);
+
+    assert_eq!(format!("{:?}", Bytes::from(vec![5; 4])), "[5, 5, 5, 5]");
+    assert_eq!(format!("{:?}", Bytes::from(vec![5; 100])), "<100 bytes>");
+  }
+}
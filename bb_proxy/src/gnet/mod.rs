@@ -11,9 +11,87 @@ pub mod sb {
   include!(concat!(env!("OUT_DIR"), "/protocol/sb.rs"));
 }
 
+mod bytes;
 mod other;
 pub mod tcp;
 
+pub use bytes::Bytes;
+
+#[cfg(test)]
+mod tests;
+
+use bb_common::version::ProtocolVersion;
+use serde_json::{json, Map, Value};
+use std::{fs, io, path::Path};
+
+/// Every version the generated packets can read and write. Each of these is
+/// the latest version of a major release.
+pub const VERSIONS: &[ProtocolVersion] = &[
+  ProtocolVersion::V1_8,
+  ProtocolVersion::V1_9_4,
+  ProtocolVersion::V1_10_2,
+  ProtocolVersion::V1_11_2,
+  ProtocolVersion::V1_12_2,
+  ProtocolVersion::V1_14_4,
+  ProtocolVersion::V1_15_2,
+  ProtocolVersion::V1_16_5,
+  ProtocolVersion::V1_17_1,
+  ProtocolVersion::V1_18_2,
+  ProtocolVersion::V1_19_4,
+  ProtocolVersion::V1_20,
+];
+
+/// A generated packet on a single version. These are listed by
+/// [`cb::packets`] and [`sb::packets`]. The full list, along with each field's
+/// java type, is also written to `target/<profile>/protocol-report.md` when
+/// the proxy is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketInfo {
+  /// The id of this packet on the version it was listed for.
+  pub id:     i32,
+  pub name:   &'static str,
+  pub fields: &'static [FieldInfo],
+}
+
+/// A field of a generated packet, as the generator understands it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldInfo {
+  pub name: &'static str,
+  /// The rust type of this field, such as `Option<i32>`.
+  pub ty:   &'static str,
+}
+
+/// Finds the packet with the given name in a list from [`cb::packets`] or
+/// [`sb::packets`].
+pub fn find_packet(packets: &'static [PacketInfo], name: &str) -> Option<&'static PacketInfo> {
+  packets.iter().find(|p| p.name == name)
+}
+
+/// Writes the id, name, and fields of every generated packet, for every
+/// version, to a json file at `path`. This is used by `--dump-packets`.
+pub fn dump_packets(path: &Path) -> io::Result<()> {
+  let ids = |packets: &[PacketInfo]| -> Value {
+    let mut out = Map::new();
+    for p in packets {
+      let fields: Vec<_> = p.fields.iter().map(|f| format!("{}: {}", f.name, f.ty)).collect();
+      out.insert(format!("{:#04x}", p.id), json!({ "name": p.name, "fields": fields }));
+    }
+    out.into()
+  };
+  let mut versions = Map::new();
+  for &ver in VERSIONS {
+    versions.insert(
+      ver.to_string(),
+      json!({
+        "protocol": ver.id(),
+        "clientbound": ids(cb::packets(ver)),
+        "serverbound": ids(sb::packets(ver)),
+      }),
+    );
+  }
+  fs::write(path, serde_json::to_string_pretty(&Value::from(versions))?)
+}
+
 pub fn clamp<T: PartialOrd + Copy, N: Into<T>>(a: T, min: N, max: N) -> T {
   let min = min.into();
   let max = max.into();
@@ -1,8 +1,15 @@
-use crate::{packet::TypeConverter, Error, Result};
+use super::Bytes;
+use crate::{
+  packet::{attribute_name, TypeConverter},
+  Error, Result,
+};
 use bb_common::{
   math::{ChunkPos, Pos},
   nbt::{Compound, Tag, WrongTag, NBT},
-  util::{Buffer, BufferErrorKind, Chat, Item, ItemData, Mode, UUID},
+  util::{
+    AttributeModifier, AttributeOperation, AttributeSlot, Buffer, BufferErrorKind, Chat, Item,
+    ItemData, Mode, UUID,
+  },
   version::ProtocolVersion,
 };
 use std::{
@@ -50,6 +57,18 @@ fn item_from_nbt(
         );
       }
     }
+    if let Some(tag) = tag.inner.get("Enchantments") {
+      let enchantments = data.enchantments_mut();
+      for tag in tag.list()? {
+        let t = tag.compound()?;
+        // Unknown enchantments and level 0 are both ignored by vanilla.
+        if let (Some(id), Some(level)) =
+          (conv.enchantment_from_name(t["id"].string()?), NonZeroU8::new(t["lvl"].short()? as u8))
+        {
+          enchantments.insert(id, level);
+        }
+      }
+    }
     if tag.inner.get("Unbreakable").map(|t| t.byte().map(|v| v != 0)) == Some(Ok(true)) {
       data.unbreakable = true;
     }
@@ -59,11 +78,18 @@ fn item_from_nbt(
         data.display.lore = lore
           .list()?
           .iter()
-          .map(|msg| Ok(Chat::new(msg.string()?)))
+          .map(|msg| chat_from_nbt(msg, ver >= ProtocolVersion::V1_14))
           .collect::<std::result::Result<_, _>>()?;
       }
       if let Some(name) = tag.inner.get("Name") {
-        data.display.name = Some(Chat::new(name.string()?));
+        data.display.name = Some(chat_from_nbt(name, ver >= ProtocolVersion::V1_13)?);
+      }
+    }
+    if let Some(tag) = tag.inner.get("AttributeModifiers") {
+      for tag in tag.list()? {
+        if let Some(modifier) = attribute_from_nbt(tag.compound()?)? {
+          data.attributes.push(modifier);
+        }
       }
     }
   }
@@ -72,8 +98,8 @@ fn item_from_nbt(
 fn item_to_nbt(data: &ItemData, ver: ProtocolVersion, conv: &TypeConverter) -> NBT {
   let mut tag = Compound::new();
   if let Some(ench) = &data.enchantments {
+    let mut enchantments = vec![];
     if ver.maj().unwrap() <= 12 {
-      let mut enchantments = vec![];
       for (new_id, level) in ench {
         if let Some(old_id) = conv.enchantment_to_old(*new_id, ver.block()) {
           enchantments.push(Tag::new_compound(&[
@@ -84,25 +110,131 @@ fn item_to_nbt(data: &ItemData, ver: ProtocolVersion, conv: &TypeConverter) -> N
       }
       tag.insert("ench", Tag::List(enchantments));
     } else {
+      for (new_id, level) in ench {
+        // Skip any enchantments this client doesn't have.
+        if conv.enchantment_to_old(*new_id, ver.block()).is_none() {
+          continue;
+        }
+        if let Some(name) = conv.enchantment_name(*new_id) {
+          enchantments.push(Tag::new_compound(&[
+            ("id", Tag::String(format!("minecraft:{name}"))),
+            ("lvl", Tag::Short(level.get().into())),
+          ]));
+        }
+      }
+      tag.insert("Enchantments", Tag::List(enchantments));
     }
   }
   if data.unbreakable {
     tag.insert("Unbreakable", true);
   }
+  if !data.attributes.is_empty() {
+    tag.insert(
+      "AttributeModifiers",
+      Tag::List(data.attributes.iter().map(|a| attribute_to_nbt(a, ver)).collect()),
+    );
+  }
   let display = tag.get_or_create_compound("display");
   if !data.display.lore.is_empty() {
     let mut lore = vec![];
     for line in &data.display.lore {
-      lore.push(line.to_codes().into());
+      lore.push(chat_to_nbt(line, ver, ver >= ProtocolVersion::V1_14));
     }
     display.inner.insert("Lore".into(), Tag::List(lore));
   }
   if let Some(name) = &data.display.name {
-    display.inner.insert("Name".into(), Tag::String(name.to_codes()));
+    display.inner.insert("Name".into(), chat_to_nbt(name, ver, ver >= ProtocolVersion::V1_13));
   }
   NBT::new("", Tag::Compound(tag))
 }
 
+/// Names and lore are stored as json on newer clients, and as color codes on
+/// older clients. Clients may still send us plain strings in json fields, so
+/// those are parsed as plain text.
+fn chat_from_nbt(tag: &Tag, json: bool) -> std::result::Result<Chat, WrongTag> {
+  let msg = tag.string()?;
+  Ok(if json { Chat::from_json(msg).unwrap_or_else(|_| Chat::new(msg)) } else { Chat::new(msg) })
+}
+fn chat_to_nbt(chat: &Chat, ver: ProtocolVersion, json: bool) -> Tag {
+  if json {
+    Tag::String(chat.to_json_for(ver))
+  } else {
+    Tag::String(chat.to_codes())
+  }
+}
+
+/// Reads a single attribute modifier. Modifiers with an unknown operation are
+/// skipped, like vanilla does.
+fn attribute_from_nbt(t: &Compound) -> std::result::Result<Option<AttributeModifier>, WrongTag> {
+  let operation = match AttributeOperation::from_id(t["Operation"].int()? as u8) {
+    Some(op) => op,
+    None => return Ok(None),
+  };
+  // 1.16+ clients store the uuid as 4 ints, and older clients use 2 longs.
+  let id = match t.inner.get("UUID") {
+    Some(tag) => {
+      UUID::from_u128(tag.int_arr()?.iter().fold(0, |acc, v| (acc << 32) | *v as u32 as u128))
+    }
+    None => UUID::from_u128(
+      ((t["UUIDMost"].long()? as u64 as u128) << 64) | t["UUIDLeast"].long()? as u64 as u128,
+    ),
+  };
+  let slot = match t.inner.get("Slot") {
+    Some(tag) => AttributeSlot::from_name(tag.string()?),
+    None => None,
+  };
+  Ok(Some(AttributeModifier {
+    attribute: attribute_to_new(t["AttributeName"].string()?),
+    amount: t["Amount"].double()?,
+    operation,
+    slot,
+    id,
+  }))
+}
+fn attribute_to_nbt(a: &AttributeModifier, ver: ProtocolVersion) -> Tag {
+  let mut tag = Compound::new();
+  let id = a.id.as_u128();
+  tag.insert("AttributeName", attribute_name(&a.attribute, ver));
+  if ver >= ProtocolVersion::V1_16 {
+    tag.insert(
+      "UUID",
+      Tag::IntArray((0..4).rev().map(|i| (id >> (i * 32)) as u32 as i32).collect()),
+    );
+  } else {
+    tag.insert("UUIDMost", Tag::Long((id >> 64) as u64 as i64));
+    tag.insert("UUIDLeast", Tag::Long(id as u64 as i64));
+  }
+  // This is the name of the modifier, not the attribute. It is required, but
+  // clients don't display it.
+  tag.insert("Name", a.attribute.as_str());
+  tag.insert("Amount", Tag::Double(a.amount));
+  tag.insert("Operation", Tag::Int(a.operation.id().into()));
+  // 1.8 doesn't have slots, so modifiers always apply.
+  if let Some(slot) = a.slot {
+    if ver >= ProtocolVersion::V1_9 {
+      tag.insert("Slot", slot.name());
+    }
+  }
+  Tag::Compound(tag)
+}
+
+/// Converts an attribute name from a client into the latest format. Before
+/// 1.16, names were camel case, like `generic.attackDamage`. After 1.16, they
+/// are snake case, like `minecraft:generic.attack_damage`. This is the reverse
+/// of [`attribute_name`].
+fn attribute_to_new(name: &str) -> String {
+  let name = name.strip_prefix("minecraft:").unwrap_or(name);
+  let mut out = String::with_capacity(name.len() + 4);
+  for c in name.chars() {
+    if c.is_ascii_uppercase() {
+      out.push('_');
+      out.push(c.to_ascii_lowercase());
+    } else {
+      out.push(c);
+    }
+  }
+  out
+}
 impl Packet {
   /// Creates a new packet. Writes the given id into the internal buffer.
   pub fn new(id: i32, ver: ProtocolVersion) -> Self {
@@ -171,6 +303,16 @@ impl Packet {
   add_reader!(read_varint, i32);
   add_reader!(read_bool, bool);
   pub fn read_all(&mut self) -> Vec<u8> { self.buf().read_all() }
+  /// Reads the rest of the packet. Unlike [`read_all`](Self::read_all), this
+  /// takes the packet's buffer instead of copying it, so nothing can be read
+  /// from this packet afterwards. This is used for the `unknown` field of
+  /// generated packets.
+  pub fn read_rest(&mut self) -> Bytes {
+    let idx = self.buf.index();
+    let mut data = std::mem::replace(&mut self.buf, Buffer::new(vec![])).into_inner();
+    data.drain(..idx.min(data.len()));
+    data.into()
+  }
 
   pub fn read_str(&mut self, max_len: u64) -> Result<String> {
     self.buf().read_str(max_len).map_err(|e| self.err(e, "read_str"))
@@ -486,3 +628,55 @@ fn test_index_update() {
   assert_eq!(p.read_u8().unwrap(), 3);
   p.read_u8().unwrap_err();
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn attribute_names() {
+    assert_eq!(
+      attribute_name("generic.attack_damage", ProtocolVersion::V1_12_2),
+      "generic.attackDamage"
+    );
+    assert_eq!(attribute_to_new("generic.attackDamage"), "generic.attack_damage");
+    assert_eq!(attribute_to_new("minecraft:generic.max_health"), "generic.max_health");
+  }
+
+  #[test]
+  fn read_rest() {
+    let mut p = Packet::from_buf_id(vec![1, 2, 3, 4], 0, ProtocolVersion::V1_8);
+    assert_eq!(p.read_u8().unwrap(), 1);
+    assert_eq!(*p.read_rest(), [2, 3, 4]);
+    assert_eq!(p.remaining(), 0);
+    assert!(p.read_rest().is_empty());
+  }
+
+  #[test]
+  fn item_nbt() {
+    let conv = TypeConverter::new();
+    let mut data = ItemData::new();
+    data.unbreakable = true;
+    data
+      .enchantments_mut()
+      .insert(conv.enchantment_from_name("sharpness").unwrap(), NonZeroU8::new(3).unwrap());
+    data.attributes.push(
+      AttributeModifier::new("generic.attack_damage", 5.0, AttributeOperation::Add)
+        .in_slot(AttributeSlot::MainHand),
+    );
+    for ver in [ProtocolVersion::V1_12_2, ProtocolVersion::V1_16, ProtocolVersion::latest()] {
+      let nbt = item_to_nbt(&data, ver, &conv);
+      assert_eq!(item_from_nbt(&nbt, ver, &conv).unwrap(), data, "{ver}");
+    }
+    // Names are sent as color codes before 1.13, which won't parse back into
+    // the same message.
+    data.display.name = Some(Chat::new("Sword"));
+    let nbt = item_to_nbt(&data, ProtocolVersion::V1_16, &conv);
+    assert_eq!(item_from_nbt(&nbt, ProtocolVersion::V1_16, &conv).unwrap(), data);
+    let tag = nbt.compound().unwrap();
+    assert_eq!(
+      tag["Enchantments"].list().unwrap()[0].compound().unwrap()["id"].string(),
+      Ok("minecraft:sharpness")
+    );
+  }
+}
@@ -0,0 +1,181 @@
+//! Round trip tests for the generated packets.
+//!
+//! Instead of building packets field by field, we read them from random bytes:
+//! any packet that parses is a valid random instance of that packet. Each one
+//! is then written, read back, and written again, and both writes must produce
+//! the same bytes.
+//!
+//! Readers are allowed to fail (or panic) on random data here. Finding
+//! readers that panic on bad input is the job of the `read_packet` fuzz target
+//! in `bb_proxy/fuzz`.
+
+use super::{cb, find_packet, sb, tcp, VERSIONS};
+use crate::Result;
+use bb_common::version::ProtocolVersion;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+  fmt,
+  panic::{self, AssertUnwindSafe},
+};
+
+/// How many random byte strings are tried for each packet.
+const ITERATIONS: usize = 200;
+/// The maximum length of each random byte string.
+const MAX_LEN: usize = 64;
+
+trait Generated: fmt::Debug + Sized {
+  fn name(id: i32, ver: ProtocolVersion) -> &'static str;
+  fn read(p: &mut tcp::Packet, ver: ProtocolVersion) -> Result<Self>;
+  fn id(&self, ver: ProtocolVersion) -> u32;
+  fn write(&self, p: &mut tcp::Packet);
+}
+
+macro_rules! generated {
+  ($module:ident) => {
+    impl Generated for $module::Packet {
+      fn name(id: i32, ver: ProtocolVersion) -> &'static str { $module::tcp_name(id, ver) }
+      fn read(p: &mut tcp::Packet, ver: ProtocolVersion) -> Result<Self> {
+        $module::Packet::from_tcp(p, ver)
+      }
+      fn id(&self, ver: ProtocolVersion) -> u32 { self.tcp_id(ver) }
+      fn write(&self, p: &mut tcp::Packet) { self.to_tcp(p) }
+    }
+  };
+}
+
+generated!(cb);
+generated!(sb);
+
+/// Generates random bytes that are likely to parse. Most bytes are small, so
+/// that lengths and enum ids are valid, and varints are never longer than two
+/// bytes, so that we never try to allocate a huge list.
+fn random_bytes(rng: &mut StdRng) -> Vec<u8> {
+  let len = rng.gen_range(0..MAX_LEN);
+  let mut out = Vec::with_capacity(len);
+  let mut prev_high = false;
+  for _ in 0..len {
+    let b = match rng.gen_range(0..10) {
+      0..=5 => 0,
+      6..=8 => rng.gen_range(1..=4),
+      _ if prev_high => rng.gen_range(0..0x80),
+      _ => rng.gen(),
+    };
+    prev_high = b >= 0x80;
+    out.push(b);
+  }
+  out
+}
+
+fn write<P: Generated>(packet: &P, ver: ProtocolVersion) -> std::result::Result<Vec<u8>, String> {
+  panic::catch_unwind(AssertUnwindSafe(|| {
+    let mut p = tcp::Packet::new(packet.id(ver) as i32, ver);
+    packet.write(&mut p);
+    p.serialize()
+  }))
+  .map_err(|_| format!("writer panicked on {packet:?}"))
+}
+
+/// Writes the packet, reads it back, and writes it again. Returns an error if
+/// anything doesn't match.
+fn check<P: Generated>(
+  first: &P,
+  id: i32,
+  ver: ProtocolVersion,
+) -> std::result::Result<(), String> {
+  let written_id = panic::catch_unwind(AssertUnwindSafe(|| first.id(ver)))
+    .map_err(|_| format!("packet {first:?} has no id"))?;
+  if written_id as i32 != id {
+    return Err(format!("read with id {id:#x}, but writes id {written_id:#x}"));
+  }
+  let bytes = write(first, ver)?;
+  let mut p = tcp::Packet::from_buf(bytes.clone(), ver).map_err(|e| e.to_string())?;
+  let second = panic::catch_unwind(AssertUnwindSafe(|| P::read(&mut p, ver)))
+    .map_err(|_| format!("reader panicked on the written bytes of {first:?}"))?
+    .map_err(|e| format!("could not read back {first:?}: {e}"))?;
+  if p.remaining() != 0 {
+    return Err(format!("{} bytes left over after reading back {first:?}", p.remaining()));
+  }
+  let again = write(&second, ver)?;
+  if again != bytes && !same_bytes(&again, &bytes) {
+    return Err(format!(
+      "{first:?} was written as {bytes:?}, but was read back as {second:?}, which was written as {again:?}"
+    ));
+  }
+  Ok(())
+}
+
+/// Maps and sets are written in iteration order, so reading and writing them
+/// can reorder the bytes. This checks that both lists have the same bytes, in
+/// any order.
+fn same_bytes(a: &[u8], b: &[u8]) -> bool {
+  let mut a = a.to_vec();
+  let mut b = b.to_vec();
+  a.sort_unstable();
+  b.sort_unstable();
+  a == b
+}
+
+/// Round trips every packet on every version. Returns a list of failures.
+fn round_trip_all<P: Generated>(dir: &str) -> Vec<String> {
+  let mut rng = StdRng::seed_from_u64(0);
+  let mut failures = vec![];
+  let mut total = 0;
+  let mut parsed = 0;
+  for &ver in VERSIONS {
+    for id in 0..=0xff {
+      let name = P::name(id, ver);
+      if name == "unknown" {
+        continue;
+      }
+      total += 1;
+      let mut found = false;
+      for _ in 0..ITERATIONS {
+        let mut p = tcp::Packet::from_buf_id(random_bytes(&mut rng), id, ver);
+        let Ok(Ok(first)) = panic::catch_unwind(AssertUnwindSafe(|| P::read(&mut p, ver))) else {
+          continue;
+        };
+        found = true;
+        if let Err(e) = check(&first, id, ver) {
+          failures.push(format!("{dir} {name} ({id:#x}) on {ver}: {e}"));
+          break;
+        }
+      }
+      if found {
+        parsed += 1;
+      }
+    }
+  }
+  println!("{dir}: round tripped {parsed} out of {total} packets");
+  failures
+}
+
+#[test]
+fn round_trip() {
+  // Readers panic a lot on random data, so we don't want to print all of those.
+  let hook = panic::take_hook();
+  panic::set_hook(Box::new(|_| {}));
+  let mut failures = round_trip_all::<cb::Packet>("clientbound");
+  failures.extend(round_trip_all::<sb::Packet>("serverbound"));
+  panic::set_hook(hook);
+
+  if !failures.is_empty() {
+    panic!("{} packets failed to round trip:\n{}", failures.len(), failures.join("\n"));
+  }
+}
+
+#[test]
+fn packet_tables() {
+  for &ver in VERSIONS {
+    for (packets, name) in [
+      (cb::packets(ver), cb::tcp_name as fn(i32, ProtocolVersion) -> &'static str),
+      (sb::packets(ver), sb::tcp_name),
+    ] {
+      assert!(!packets.is_empty(), "no packets on {ver}");
+      assert!(packets.windows(2).all(|w| w[0].id < w[1].id), "packets on {ver} are not sorted");
+      for p in packets {
+        assert_eq!(name(p.id, ver), p.name, "wrong name for {:#x} on {ver}", p.id);
+        assert_eq!(find_packet(packets, p.name), Some(p));
+      }
+    }
+  }
+}
@@ -0,0 +1,97 @@
+//! Virtual hosts. The handshake includes the hostname the client used to
+//! connect, so a single proxy can show a different status, and send clients to
+//! a different server, depending on which address they joined with.
+
+use crate::{config::HostConfig, icon, resolve::Watched};
+use bb_common::util::Chat;
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc, time::Duration};
+
+/// The settings for a single hostname. Anything that is `None` uses the
+/// proxy's main setting instead.
+#[derive(Default)]
+pub struct VirtualHost {
+  /// The server clients on this host are sent to.
+  pub server: Option<Watched>,
+  /// The description shown on the server list.
+  pub motd:   Option<Chat>,
+  /// The base64 encoded icon shown on the server list.
+  pub icon:   Option<String>,
+}
+
+/// All of the virtual hosts, keyed by hostname.
+#[derive(Default)]
+pub struct Hosts {
+  hosts: HashMap<String, Arc<VirtualHost>>,
+}
+
+impl VirtualHost {
+  /// Returns the current address of this host's server, if it has one.
+  pub fn server(&self) -> Option<SocketAddr> { self.server.as_ref().map(|s| s.get()) }
+}
+
+impl Hosts {
+  pub fn new() -> Self { Hosts::default() }
+
+  /// Loads the `hosts` section of the config. Server hostnames are resolved
+  /// every `refresh`, like the main server.
+  pub fn from_config(config: &HashMap<String, HostConfig>, refresh: Duration) -> io::Result<Self> {
+    let mut hosts = Hosts::new();
+    for (name, host) in config {
+      hosts.insert(
+        name,
+        VirtualHost {
+          server: match host.server.as_str() {
+            "" => None,
+            server => Some(Watched::new(server, refresh)?),
+          },
+          motd:   (!host.motd.is_empty()).then(|| Chat::from_codes(&host.motd)),
+          icon:   match host.icon.as_str() {
+            "" => None,
+            path => icon::load(path),
+          },
+        },
+      );
+    }
+    Ok(hosts)
+  }
+
+  /// Adds a virtual host. Hostnames are not case sensitive.
+  pub fn insert(&mut self, hostname: &str, host: VirtualHost) {
+    self.hosts.insert(hostname.to_lowercase(), Arc::new(host));
+  }
+
+  /// Finds the virtual host for the address a client sent in their handshake.
+  pub fn get(&self, addr: &str) -> Option<Arc<VirtualHost>> {
+    if self.hosts.is_empty() {
+      return None;
+    }
+    self.hosts.get(&hostname(addr)).cloned()
+  }
+}
+
+/// Returns the hostname from the server address in a handshake. Modded
+/// clients and other proxies add extra sections after a null byte (for example,
+/// `play.example.com\0FML\0`), and clients may also add a trailing dot, so
+/// both of these are removed.
+pub fn hostname(addr: &str) -> String {
+  let host = addr.split('\0').next().unwrap_or_default();
+  host.trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn hostnames() {
+    assert_eq!(hostname("play.example.com"), "play.example.com");
+    assert_eq!(hostname("Play.Example.com."), "play.example.com");
+    assert_eq!(hostname("play.example.com\0FML\0"), "play.example.com");
+    assert_eq!(hostname("play.example.com\0127.0.0.1\0uuid\0[]"), "play.example.com");
+
+    let mut hosts = Hosts::new();
+    hosts.insert("Play.Example.com", VirtualHost::default());
+    assert!(hosts.get("play.example.com.").is_some());
+    assert!(hosts.get("other.example.com").is_none());
+  }
+}
@@ -0,0 +1,148 @@
+//! Server list icons. The icon is sent to clients as a base64 encoded 64x64
+//! png. If the configured icon can't be loaded, a simple icon is generated
+//! instead, so that the server list never shows the default dirt icon by
+//! accident.
+
+use base64::{engine::general_purpose, Engine};
+use image::{DynamicImage, Rgba, RgbaImage};
+use std::io::{self, Cursor};
+
+/// The width and height of the icon, in pixels. Clients will not show icons of
+/// any other size.
+const SIZE: u32 = 64;
+/// The most characters drawn on a generated icon. Any more than this will not
+/// fit.
+const MAX_CHARS: usize = 3;
+
+/// Loads the icon at the given path, and resizes it to 64x64. Returns `None`
+/// if the icon doesn't exist or can't be decoded.
+pub fn load(path: &str) -> Option<String> {
+  match image::open(path) {
+    Ok(icon) => Some(encode(icon.resize_exact(SIZE, SIZE, image::imageops::FilterType::Triangle))),
+    Err(image::ImageError::IoError(e)) if e.kind() == io::ErrorKind::NotFound => {
+      info!("no icon found at `{path}`");
+      None
+    }
+    Err(e) => {
+      error!("error loading icon `{path}`: {e}");
+      None
+    }
+  }
+}
+
+/// Generates an icon with the given text drawn in white on a solid
+/// background. Only letters and numbers are drawn, and anything past the
+/// first three characters is ignored.
+pub fn generate(text: &str, color: [u8; 3]) -> String {
+  let [r, g, b] = color;
+  let mut img = RgbaImage::from_pixel(SIZE, SIZE, Rgba([r, g, b, 255]));
+  let glyphs: Vec<_> = text.chars().filter_map(glyph).take(MAX_CHARS).collect();
+  if !glyphs.is_empty() {
+    // Each glyph is 5 pixels wide, with a 1 pixel gap between glyphs.
+    let width = glyphs.len() as u32 * 6 - 1;
+    let scale = (56 / width).min(6);
+    let x0 = (SIZE - width * scale) / 2;
+    let y0 = (SIZE - 7 * scale) / 2;
+    for (i, glyph) in glyphs.iter().enumerate() {
+      for (row, bits) in glyph.iter().enumerate() {
+        for col in 0..5 {
+          if bits & (0b10000 >> col) == 0 {
+            continue;
+          }
+          let x = x0 + (i as u32 * 6 + col) * scale;
+          let y = y0 + row as u32 * scale;
+          for dy in 0..scale {
+            for dx in 0..scale {
+              img.put_pixel(x + dx, y + dy, Rgba([255, 255, 255, 255]));
+            }
+          }
+        }
+      }
+    }
+  }
+  encode(DynamicImage::ImageRgba8(img))
+}
+
+/// Parses a hex color, like `#3d8c40`. The `#` is optional.
+pub fn parse_color(s: &str) -> Option<[u8; 3]> {
+  let s = s.strip_prefix('#').unwrap_or(s);
+  if s.len() != 6 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+    return None;
+  }
+  let channel = |i: usize| u8::from_str_radix(&s[i..i + 2], 16).unwrap();
+  Some([channel(0), channel(2), channel(4)])
+}
+
+fn encode(icon: DynamicImage) -> String {
+  let mut image = Vec::new();
+  icon.write_to(&mut Cursor::new(&mut image), image::ImageFormat::Png).unwrap();
+  let mut result = "data:image/png;base64,".to_owned();
+  general_purpose::STANDARD.encode_string(image, &mut result);
+  result
+}
+
+/// Returns the 5x7 bitmap for the given character. Each row is stored in the
+/// lowest 5 bits, with the leftmost pixel in the highest bit.
+#[rustfmt::skip]
+fn glyph(c: char) -> Option<[u8; 7]> {
+  Some(match c.to_ascii_uppercase() {
+    'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+    'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+    'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+    'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+    'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+    'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+    'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+    'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+    'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+    'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+    'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+    'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+    'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+    'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+    'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+    'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+    'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+    'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+    'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+    'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+    'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+    'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+    'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+    'Y' => [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100],
+    'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+    '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+    '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+    '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+    '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+    '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+    '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+    '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+    '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+    '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn colors() {
+    assert_eq!(parse_color("#3d8c40"), Some([0x3d, 0x8c, 0x40]));
+    assert_eq!(parse_color("FFFFFF"), Some([255, 255, 255]));
+    assert_eq!(parse_color("#fff"), None);
+    assert_eq!(parse_color("#gggggg"), None);
+  }
+
+  #[test]
+  fn generated() {
+    // These should never panic, no matter how much text there is.
+    for text in ["", "B", "BB", "Bamboo", "??", "a1Z"] {
+      assert!(generate(text, [0, 0, 0]).starts_with("data:image/png;base64,"));
+    }
+  }
+}
@@ -0,0 +1,52 @@
+//! LAN discovery. Vanilla clients listen for these messages on a multicast
+//! address, and list any servers they hear about in the LAN section of the
+//! server list. This is the same message a singleplayer world sends when it is
+//! opened to LAN.
+
+use std::{
+  io,
+  net::{Ipv4Addr, SocketAddr, UdpSocket},
+  thread,
+  time::Duration,
+};
+
+/// The multicast address that clients listen on.
+const LAN_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 2, 60), 4445);
+/// How often the message is sent. This is the same as vanilla.
+const INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Returns the message clients expect. The port is the port clients should
+/// connect to, and the client uses the address the message came from as the
+/// host.
+fn message(motd: &str, port: u16) -> String {
+  // The client doesn't handle the closing tag appearing within the motd.
+  let motd = motd.replace("[/MOTD]", "");
+  format!("[MOTD]{motd}[/MOTD][AD]{port}[/AD]")
+}
+
+/// Starts a thread that advertises the proxy on the local network forever. The
+/// port should be the port that the proxy is listening on.
+pub fn spawn(motd: &str, port: u16) -> io::Result<()> {
+  let socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))?;
+  socket.set_multicast_ttl_v4(1)?;
+  let message = message(motd, port);
+  info!("advertising on the local network as `{motd}`");
+  thread::Builder::new().name("lan broadcast".into()).spawn(move || loop {
+    if let Err(e) = socket.send_to(message.as_bytes(), LAN_ADDR) {
+      warn!("could not send lan broadcast: {e}");
+    }
+    thread::sleep(INTERVAL);
+  })?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn messages() {
+    assert_eq!(message("Bamboo", 25565), "[MOTD]Bamboo[/MOTD][AD]25565[/AD]");
+    assert_eq!(message("a[/MOTD]b", 1234), "[MOTD]ab[/MOTD][AD]1234[/AD]");
+  }
+}
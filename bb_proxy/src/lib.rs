@@ -9,46 +9,59 @@ pub mod config;
 pub mod conn;
 mod error;
 pub mod gnet;
+pub mod host;
+pub mod icon;
+mod lan;
+pub mod limbo;
+pub mod link;
+pub mod login_plugin;
 pub mod packet;
+pub mod queue;
 mod registry;
+pub mod resolve;
+pub mod status;
 pub mod stream;
 
 pub use conn::{JsonPlayer, JsonPlayers, JsonStatus, JsonVersion};
 pub use error::{Error, Result};
 
-use base64::{engine::general_purpose, Engine};
-use bb_common::{
-  math::der,
-  util::chat::{Chat, Color},
-  version::ProtocolVersion,
-};
+use bb_common::{ban::BanList, math::der, version::ProtocolVersion, whitelist::Whitelist};
 use config::Config;
 use mio::{
   event::Event,
   net::{TcpListener, TcpStream},
-  Events, Interest, Poll, Token,
+  Events, Interest, Poll, Registry, Token, Waker,
 };
+use parking_lot::Mutex;
 use rand::rngs::OsRng;
 use rsa::RsaPrivateKey;
-use std::{collections::HashMap, io, io::Cursor, net::SocketAddr, sync::Arc};
-
-use crate::{conn::Conn, packet::TypeConverter, stream::java::stream::JavaStream};
+use std::{
+  collections::HashMap,
+  io,
+  net::SocketAddr,
+  sync::Arc,
+  time::{Duration, Instant},
+};
 
-pub fn load_icon(path: &str) -> String {
-  let mut icon = match image::open(path).map_err(|e| error!("error loading icon: {}", e)) {
-    Ok(icon) => icon,
-    Err(_) => return "".into(),
-  };
-  icon = icon.resize_exact(64, 64, image::imageops::FilterType::Triangle);
-  let mut image = Vec::new();
-  icon.write_to(&mut Cursor::new(&mut image), image::ImageFormat::Png).unwrap();
-  let mut result = "data:image/png;base64,".to_owned();
-  general_purpose::STANDARD.encode_string(image, &mut result);
-  result
-}
+use crate::{
+  conn::Conn,
+  host::Hosts,
+  limbo::LimboConfig,
+  link::LinkConfig,
+  login_plugin::LoginHandler,
+  packet::TypeConverter,
+  queue::Queue,
+  resolve::Resolver,
+  status::{Online, Status},
+  stream::java::stream::JavaStream,
+};
 
 const JAVA_LISTENER: Token = Token(0xffffffff);
 const BEDROCK_LISTENER: Token = Token(0xfffffffe);
+const RESOLVER: Token = Token(0xfffffffd);
+
+/// How often [`Listener::tick`] is called.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
 
 type ClientMap<'listener> = HashMap<Token, Conn<'listener, JavaStream>>;
 
@@ -57,6 +70,7 @@ pub struct Listener<'listener> {
   poll:          Poll,
   next_token:    usize,
   clients:       ClientMap<'listener>,
+  resolver:      Resolver,
 }
 
 struct TokenHandler<'listener, 'a> {
@@ -79,9 +93,22 @@ pub struct Proxy {
   key:            Arc<RsaPrivateKey>,
   der_key:        Option<Vec<u8>>,
   addr:           SocketAddr,
-  server_addr:    Box<dyn Fn() -> SocketAddr>,
+  server_addr:    Arc<dyn Fn() -> SocketAddr>,
+  hosts:          Arc<Hosts>,
+  /// The players logged in through this proxy.
+  online:         Arc<Online>,
+  /// If set, the proxy is advertised on the local network with this motd.
+  lan:            Option<String>,
+  link:           Arc<LinkConfig>,
+  limbo:          Option<Arc<LimboConfig>>,
+  queue:          Option<Arc<Queue>>,
+  bans:           Option<Arc<Mutex<BanList>>>,
+  whitelist:      Option<Arc<Mutex<Whitelist>>>,
+  login_handlers: Vec<Arc<dyn LoginHandler>>,
   forwarding:     config::Forwarding,
+  pass_through:   config::PassThrough,
   compression:    i32,
+  trace:          Option<Duration>,
   conv:           Arc<TypeConverter>,
   status_builder: Arc<dyn for<'a> Fn(&'a str, ProtocolVersion) -> JsonStatus<'a>>,
 }
@@ -89,36 +116,32 @@ pub struct Proxy {
 impl Proxy {
   /// Creates a proxy with default settings.
   pub fn new(addr: SocketAddr, server_addr: SocketAddr) -> Self {
+    let online = Arc::new(Online::new());
     Proxy {
       icon: None,
       key: Arc::new(RsaPrivateKey::new(&mut OsRng, 1024).expect("failed to generate a key")),
       der_key: None,
       addr,
-      server_addr: Box::new(move || server_addr),
+      server_addr: Arc::new(move || server_addr),
+      hosts: Arc::new(Hosts::new()),
+      online: online.clone(),
+      lan: None,
+      link: Arc::new(LinkConfig::default()),
+      limbo: None,
+      queue: None,
+      bans: None,
+      whitelist: None,
+      login_handlers: vec![],
       forwarding: config::Forwarding::default(),
+      pass_through: config::PassThrough::default(),
       compression: 256,
+      trace: None,
       conv: Arc::new(TypeConverter::new()),
-      status_builder: Arc::new(|icon, ver| {
-        let mut description = Chat::empty();
-        description.add("Bamboo").color(Color::BrightGreen);
-        description.add(" -- ").color(Color::Gray);
-        #[cfg(debug_assertions)]
-        description.add("Development mode").color(Color::Blue);
-        #[cfg(not(debug_assertions))]
-        description.add("Release mode").color(Color::Red);
-        JsonStatus {
-          version: JsonVersion {
-            name:     format!("1.8 - {}", ProtocolVersion::latest()),
-            protocol: if ver == ProtocolVersion::Invalid {
-              ProtocolVersion::latest().id()
-            } else {
-              ver.id()
-            } as i32,
-          },
-          players: JsonPlayers { max: 0, online: 0, sample: vec![] },
-          description,
-          favicon: icon,
-        }
+      status_builder: Arc::new(move |icon, ver| JsonStatus {
+        version:     status::version(ver),
+        players:     online.players(status::MAX_SAMPLE),
+        description: status::default_description(),
+        favicon:     icon,
       }),
     }
     .with_encryption(true)
@@ -129,12 +152,56 @@ impl Proxy {
       return Err(Error::Bungeecord("cannot enable forwarding and encryption"));
     }
 
+    let refresh = Duration::from_secs(config.server_refresh_secs.max(1));
+    let server = resolve::Watched::new(&config.server, refresh)?;
+    let limbo = if config.limbo {
+      Some(LimboConfig {
+        retry:    Duration::from_secs(config.limbo_retry_secs.max(1)),
+        timeout:  (config.limbo_timeout_secs != 0)
+          .then(|| Duration::from_secs(config.limbo_timeout_secs)),
+        fallback: config
+          .fallback
+          .iter()
+          .map(|host| resolve::Watched::new(host, refresh))
+          .collect::<io::Result<_>>()?,
+      })
+    } else {
+      None
+    };
+    let icon = icon::load(&config.icon).unwrap_or_else(|| {
+      let color = icon::parse_color(&config.icon_color).unwrap_or_else(|| {
+        warn!("invalid icon color `{}`", config.icon_color);
+        [0, 0, 0]
+      });
+      icon::generate(&config.icon_text, color)
+    });
+    let proxy = Self::new(config.address.parse()?, server.get());
+    let status = Status::from_config(&config, proxy.online.clone());
+    let proxy = if config.forge_mod_list {
+      proxy.with_login_handler(login_plugin::ForgeModList)
+    } else {
+      proxy
+    };
     Ok(
-      Self::new(config.address.parse()?, config.server.parse()?)
+      proxy
+        .with_server_addr(move || server.get())
+        .with_hosts(Hosts::from_config(&config.hosts, refresh)?)
+        .with_link(LinkConfig::new(
+          Duration::from_secs(config.heartbeat_secs.max(1)),
+          Duration::from_secs(config.link_grace_secs),
+        ))
+        .with_limbo(limbo)
+        .with_queue(config.queue)
+        .with_bans((!config.bans.is_empty()).then(|| BanList::load(&config.bans)))
+        .with_whitelist((!config.whitelist.is_empty()).then(|| Whitelist::load(&config.whitelist)))
         .with_encryption(config.encryption)
         .with_forwarding(config.forwarding)
+        .with_pass_through(config::PassThrough::from_config(&config.pass_through))
         .with_compression(config.compression_thresh)
-        .with_icon(&config.icon),
+        .with_tracing(config.trace.then(|| Duration::from_millis(config.trace_slow_ms)))
+        .with_lan(config.lan.then_some(config.lan_motd))
+        .with_icon_data(icon)
+        .with_status_builder(move |icon, ver| status.build(icon, ver)),
     )
   }
   /// Enables or disables encryption for this connection.
@@ -151,16 +218,42 @@ impl Proxy {
     self.forwarding = forwarding;
     self
   }
+  /// Sets the client versions where unknown packets are passed through to the
+  /// server, instead of closing the connection.
+  pub fn with_pass_through(mut self, pass_through: config::PassThrough) -> Self {
+    self.pass_through = pass_through;
+    self
+  }
   /// Sets the compression threshold for the proxy. Set to `-1` to disable
   /// compression, and set to `0` to compress all packets.
   pub fn with_compression(mut self, compression: i32) -> Self {
     self.compression = compression;
     self
   }
+  /// Enables tracing for all connections. If set, every packet a client sends
+  /// will be traced through the server, and any packets that take longer than
+  /// the given duration will be logged.
+  pub fn with_tracing(mut self, slow: Option<Duration>) -> Self {
+    self.trace = slow;
+    self
+  }
   /// Sets the icon path for the proxy. This will be shown to all clients on
-  /// the server list screen.
+  /// the server list screen. If the icon can't be loaded, no icon is shown.
   pub fn with_icon(mut self, path: &str) -> Self {
-    self.icon = Some(load_icon(path));
+    self.icon = icon::load(path);
+    self
+  }
+  /// Sets the icon for the proxy. This should be a base64 encoded png, like
+  /// the ones returned from [`icon::load`] and [`icon::generate`].
+  pub fn with_icon_data(mut self, icon: String) -> Self {
+    self.icon = Some(icon);
+    self
+  }
+  /// Advertises the proxy on the local network with the given motd, so that
+  /// it shows up in the LAN section of the server list. Set to `None` to
+  /// disable this.
+  pub fn with_lan(mut self, motd: Option<String>) -> Self {
+    self.lan = motd;
     self
   }
   /// Sets the status builder. This will be called every time a client requests
@@ -176,15 +269,73 @@ impl Proxy {
   /// called every time a client connects, and the address will be used as the
   /// backend server ip.
   pub fn with_server_addr(mut self, addr: impl Fn() -> SocketAddr + 'static) -> Self {
-    self.server_addr = Box::new(addr);
+    self.server_addr = Arc::new(addr);
+    self
+  }
+  /// Sets the virtual hosts. Clients that connect with one of these hostnames
+  /// will be shown that host's status, and will be sent to that host's server
+  /// instead of the main server.
+  pub fn with_hosts(mut self, hosts: Hosts) -> Self {
+    self.hosts = Arc::new(hosts);
+    self
+  }
+  /// Sets how often heartbeats are sent to the server, and how long to try
+  /// resuming a dropped connection to the server before giving up.
+  pub fn with_link(mut self, link: LinkConfig) -> Self {
+    self.link = Arc::new(link);
+    self
+  }
+  /// Enables or disables limbo. If enabled, clients will be held in an empty
+  /// world when the server goes down, and will be reconnected once it comes
+  /// back up. If disabled, clients are disconnected when the server goes down.
+  pub fn with_limbo(mut self, limbo: Option<LimboConfig>) -> Self {
+    self.limbo = limbo.map(Arc::new);
+    self
+  }
+
+  /// Enables or disables the join queue. If enabled, clients that join while
+  /// the server is full will be held in limbo until there is space. This does
+  /// nothing if limbo is disabled.
+  pub fn with_queue(mut self, queue: bool) -> Self {
+    self.queue = queue.then(|| Arc::new(Queue::new()));
+    self
+  }
+
+  /// Sets the ban list. Clients will be checked against this list when they
+  /// log in. The list is read again whenever the file changes, so bans added
+  /// by the server will apply here as well.
+  pub fn with_bans(mut self, bans: Option<BanList>) -> Self {
+    self.bans = bans.map(|b| Arc::new(Mutex::new(b)));
+    self
+  }
+
+  /// Sets the whitelist. Clients will be checked against this list when they
+  /// log in, and the status will show the maintenance message during
+  /// maintenance. Like the ban list, this is read again whenever the file
+  /// changes.
+  pub fn with_whitelist(mut self, whitelist: Option<Whitelist>) -> Self {
+    self.whitelist = whitelist.map(|w| Arc::new(Mutex::new(w)));
+    self
+  }
+
+  /// Adds a login plugin handler. Every client that logs in on 1.13 or newer
+  /// will be sent this handler's requests before it is sent to the server.
+  /// See [`login_plugin`] for more.
+  pub fn with_login_handler(mut self, handler: impl LoginHandler + 'static) -> Self {
+    self.login_handlers.push(Arc::new(handler));
     self
   }
 
   /// Creates a new connection for the given stream.
-  fn new_conn(&self, stream: JavaStream, server_token: Token) -> Conn<JavaStream> {
+  fn new_conn(
+    &self,
+    stream: JavaStream,
+    addr: SocketAddr,
+    server_token: Token,
+  ) -> Conn<JavaStream> {
     let conn = Conn::new(
       stream,
-      (self.server_addr)(),
+      self.server_addr.clone(),
       self.key.clone(),
       self.der_key.clone(),
       server_token,
@@ -192,7 +343,18 @@ impl Proxy {
       self.forwarding.clone(),
       self.status_builder.clone(),
     )
-    .with_compression(self.compression);
+    .with_compression(self.compression)
+    .with_tracing(self.trace)
+    .with_pass_through(self.pass_through.clone())
+    .with_client_addr(addr)
+    .with_bans(self.bans.clone())
+    .with_whitelist(self.whitelist.clone())
+    .with_login_handlers(self.login_handlers.clone())
+    .with_hosts(self.hosts.clone())
+    .with_online(self.online.clone())
+    .with_link(self.link.clone())
+    .with_limbo(self.limbo.clone())
+    .with_queue(self.queue.clone());
     if let Some(icon) = &self.icon {
       conn.with_icon(icon)
     } else {
@@ -205,6 +367,11 @@ impl Proxy {
   pub fn run(&self) -> Result<()> {
     info!("listening for java clients on {}", self.addr);
     let mut listener = Listener::new(self.addr)?;
+    if let Some(motd) = &self.lan {
+      if let Err(e) = lan::spawn(motd, self.addr.port()) {
+        warn!("could not start lan broadcast: {e}");
+      }
+    }
 
     // let addr = "0.0.0.0:19132";
     // info!("listening for bedrock clients on {}", addr);
@@ -212,19 +379,25 @@ impl Proxy {
 
     // The vanilla server uses 1024 bits for this.
     let mut events = Events::with_capacity(1024);
+    let mut next_tick = Instant::now() + TICK_INTERVAL;
 
     loop {
       loop {
-        match listener.poll.poll(&mut events, None) {
+        let timeout = next_tick.saturating_duration_since(Instant::now());
+        match listener.poll.poll(&mut events, Some(timeout)) {
           Ok(()) => break,
           Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
           Err(e) => return Err(e.into()),
         }
       }
+      if Instant::now() >= next_tick {
+        listener.tick();
+        next_tick = Instant::now() + TICK_INTERVAL;
+      }
 
       for event in &events {
-        listener.handle(event, |client, server_token| {
-          self.new_conn(JavaStream::new(client), server_token)
+        listener.handle(event, |client, addr, server_token| {
+          self.new_conn(JavaStream::new(client), addr, server_token)
         })?;
       }
     }
@@ -236,18 +409,34 @@ impl<'a> Listener<'a> {
     let mut java_listener = TcpListener::bind(addr)?;
     let poll = Poll::new()?;
     poll.registry().register(&mut java_listener, JAVA_LISTENER, Interest::READABLE)?;
-    Ok(Listener { java_listener, poll, next_token: 0, clients: HashMap::new() })
+    let resolver = Resolver::new(Waker::new(poll.registry(), RESOLVER)?);
+    Ok(Listener { java_listener, poll, next_token: 0, clients: HashMap::new(), resolver })
   }
+  /// Sends heartbeats to the server, and updates any clients that are in limbo
+  /// or are being resumed. This should be called about once a second.
+  pub fn tick(&mut self) {
+    let reg = self.poll.registry();
+    self.clients.retain(|token, conn| {
+      if let Err(e) = conn.tick_link(reg).and_then(|()| conn.tick_limbo(reg)) {
+        if e.io_kind() != Some(io::ErrorKind::WouldBlock) {
+          error!("error while ticking client {token:?}: {e}");
+          return false;
+        }
+      }
+      !conn.closed()
+    });
+  }
+
   pub fn handle(
     &mut self,
     event: &Event,
-    new_client: impl Fn(TcpStream, Token) -> Conn<'a, JavaStream>,
+    new_client: impl Fn(TcpStream, SocketAddr, Token) -> Conn<'a, JavaStream>,
   ) -> io::Result<()> {
     match event.token() {
       JAVA_LISTENER => {
         loop {
           match self.java_listener.accept() {
-            Ok((mut client, _)) => {
+            Ok((mut client, addr)) => {
               // This is the tcp stream connected to the client
               let client_token = Token(self.next_token);
               // This is the tcp stream connected to the server
@@ -262,7 +451,7 @@ impl<'a> Listener<'a> {
               )?;
               // We will register the server tcp connection later, once we are done
               // handshaking.
-              self.clients.insert(client_token, new_client(client, server_token));
+              self.clients.insert(client_token, new_client(client, addr, server_token));
             }
             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
               // Socket is not ready anymore, stop accepting
@@ -275,6 +464,19 @@ impl<'a> Listener<'a> {
       BEDROCK_LISTENER => {
         unimplemented!();
       }
+      RESOLVER => {
+        for res in self.resolver.finished() {
+          // The client may have disconnected while we were resolving.
+          let Some(conn) = self.clients.get_mut(&res.token) else { continue };
+          match res.addrs {
+            Ok(mut addrs) => {
+              addrs.extend(res.switch.ips);
+              conn.switch_to_addrs(self.poll.registry(), res.switch.mode, addrs);
+            }
+            Err(e) => warn!("could not resolve `{}` for {:?}: {e}", res.switch.host, res.token),
+          }
+        }
+      }
       token => {
         let is_server = token.0 % 2 != 0;
         let token = Token(token.0 / 2 * 2);
@@ -285,14 +487,19 @@ impl<'a> Listener<'a> {
           if event.is_readable() {
             if let Some(conn) = handler.get() {
               let res = conn.read_server(self.poll.registry());
+              if let Some(switch) = conn.take_pending_switch() {
+                self.resolver.lookup(token, switch);
+              }
+              let res = server_lost(conn, self.poll.registry(), res);
               handler.handle_bool(res);
             }
           }
 
           if event.is_writable() {
             if let Some(conn) = handler.get() {
-              let res = conn.write_server();
-              handler.handle_unit(res);
+              let res = conn.write_server().map(|()| false);
+              let res = server_lost(conn, self.poll.registry(), res);
+              handler.handle_bool(res);
             }
           }
         } else {
@@ -318,6 +525,22 @@ impl<'a> Listener<'a> {
   }
 }
 
+/// Checks the result of reading from or writing to the server. If the server
+/// connection was lost, this tries to move the client into limbo. The
+/// returned result is `Ok(true)` or `Err(_)` if the client should be
+/// disconnected.
+fn server_lost(conn: &mut Conn<JavaStream>, reg: &Registry, res: Result<bool>) -> Result<bool> {
+  let lost = match &res {
+    Ok(closed) => *closed,
+    Err(e) => e.io_kind() != Some(io::ErrorKind::WouldBlock),
+  };
+  if lost && conn.server_lost(reg) {
+    Ok(false)
+  } else {
+    res
+  }
+}
+
 impl<'listener: 'b, 'b> TokenHandler<'listener, 'b> {
   pub fn get(&mut self) -> Option<&mut Conn<'listener, JavaStream>> {
     self.clients.get_mut(&self.token)
@@ -0,0 +1,83 @@
+//! Limbo is where clients are held when the server they are connected to goes
+//! down. Instead of disconnecting them, the proxy moves them into an empty
+//! world, keeps them alive, and reconnects them once the server comes back
+//! (or once a fallback server accepts them).
+
+use crate::resolve::Watched;
+use std::{
+  net::SocketAddr,
+  time::{Duration, Instant},
+};
+
+/// How often keep alive packets are sent to clients in limbo. Clients will
+/// time out after 30 seconds without one.
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Settings for holding clients in limbo.
+pub struct LimboConfig {
+  /// How long to wait between reconnect attempts.
+  pub retry:    Duration,
+  /// How long a client can stay in limbo before they are disconnected. If
+  /// `None`, clients will be held forever.
+  pub timeout:  Option<Duration>,
+  /// Servers to try after the main server. Each reconnect attempt tries the
+  /// next server in this list, and then starts over with the main server.
+  pub fallback: Vec<Watched>,
+}
+
+impl LimboConfig {
+  /// Returns the address to use for the given reconnect attempt. `server` is
+  /// the address of the main server.
+  pub fn target(&self, attempt: usize, server: impl FnOnce() -> SocketAddr) -> SocketAddr {
+    match attempt % (self.fallback.len() + 1) {
+      0 => server(),
+      i => self.fallback[i - 1].get(),
+    }
+  }
+}
+
+/// The state of a single client in limbo.
+#[derive(Debug)]
+pub struct Limbo {
+  /// When the client entered limbo.
+  pub since:           Instant,
+  pub next_retry:      Instant,
+  pub next_keep_alive: Instant,
+  /// The number of reconnects we have tried.
+  pub attempt:         usize,
+  /// Set if the client is waiting in the join queue. This is the last
+  /// position we showed them.
+  pub queue_pos:       Option<usize>,
+}
+
+impl Limbo {
+  pub fn new(retry: Duration) -> Self {
+    let now = Instant::now();
+    Limbo {
+      since:           now,
+      next_retry:      now + retry,
+      next_keep_alive: now,
+      attempt:         0,
+      queue_pos:       None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn targets() {
+    let main: SocketAddr = "127.0.0.1:8483".parse().unwrap();
+    let fallback: SocketAddr = "127.0.0.1:8484".parse().unwrap();
+    let config = LimboConfig {
+      retry:    Duration::from_secs(1),
+      timeout:  None,
+      fallback: vec![Watched::new("127.0.0.1:8484", Duration::from_secs(1)).unwrap()],
+    };
+    assert_eq!(config.target(0, || main), main);
+    assert_eq!(config.target(1, || main), fallback);
+    assert_eq!(config.target(2, || main), main);
+  }
+}
@@ -0,0 +1,132 @@
+//! The link between a client's connection and the server. The proxy sends the
+//! server a heartbeat every few seconds, so that a connection which dropped
+//! without closing is noticed. When the connection drops, the proxy tries to
+//! resume it for a short time before giving up, so that clients don't notice
+//! short outages.
+
+use bb_common::net::protocol::Negotiated;
+use std::{
+  net::SocketAddr,
+  time::{Duration, Instant},
+};
+
+/// How long to wait between attempts to resume a connection.
+pub const RESUME_RETRY: Duration = Duration::from_secs(1);
+
+/// Settings for the connection to the server.
+#[derive(Debug, Clone)]
+pub struct LinkConfig {
+  /// How often a heartbeat is sent to the server.
+  pub heartbeat: Duration,
+  /// If nothing is received from the server for this long, the connection is
+  /// assumed to have dropped.
+  pub timeout:   Duration,
+  /// How long to try resuming a dropped connection before giving up. If this
+  /// is zero, dropped connections are never resumed.
+  pub grace:     Duration,
+}
+
+impl Default for LinkConfig {
+  fn default() -> Self { LinkConfig::new(Duration::from_secs(5), Duration::from_secs(10)) }
+}
+
+impl LinkConfig {
+  /// Creates a config that sends a heartbeat every `heartbeat`. The connection
+  /// times out after three heartbeats without a reply.
+  pub fn new(heartbeat: Duration, grace: Duration) -> Self {
+    LinkConfig { heartbeat, timeout: heartbeat * 3, grace }
+  }
+}
+
+/// The state of a single client's connection to the server.
+#[derive(Debug)]
+pub struct Link {
+  /// The server we last connected to. This is the server we try to resume the
+  /// connection with.
+  pub addr:       Option<SocketAddr>,
+  /// The last time we received anything from the server.
+  last_heard:     Instant,
+  next_heartbeat: Instant,
+  next_id:        u32,
+  /// Set while we are trying to resume the connection.
+  pub resume:     Option<Resume>,
+  /// The internal protocol agreed on with the server. This is `None` until
+  /// the server sends its first packet.
+  pub protocol:   Option<Negotiated>,
+}
+
+/// The state of a connection that is being resumed.
+#[derive(Debug)]
+pub struct Resume {
+  /// When the connection dropped.
+  pub since:      Instant,
+  pub next_retry: Instant,
+  pub addr:       SocketAddr,
+}
+
+impl Default for Link {
+  fn default() -> Self { Link::new() }
+}
+
+impl Link {
+  pub fn new() -> Self {
+    let now = Instant::now();
+    Link {
+      addr:           None,
+      last_heard:     now,
+      next_heartbeat: now,
+      next_id:        0,
+      resume:         None,
+      protocol:       None,
+    }
+  }
+
+  /// Records that we connected to the given server. The protocol is
+  /// negotiated again for every connection.
+  pub fn connected(&mut self, addr: SocketAddr) {
+    self.addr = Some(addr);
+    self.protocol = None;
+    self.heard();
+  }
+  /// Records that we just received something from the server.
+  pub fn heard(&mut self) { self.last_heard = Instant::now(); }
+  /// Returns `true` if the server hasn't sent anything for longer than the
+  /// timeout.
+  pub fn timed_out(&self, config: &LinkConfig, now: Instant) -> bool {
+    now.saturating_duration_since(self.last_heard) >= config.timeout
+  }
+  /// Returns the id of the next heartbeat, if one should be sent now.
+  pub fn heartbeat(&mut self, config: &LinkConfig, now: Instant) -> Option<u32> {
+    if now < self.next_heartbeat {
+      return None;
+    }
+    self.next_heartbeat = now + config.heartbeat;
+    let id = self.next_id;
+    self.next_id = self.next_id.wrapping_add(1);
+    Some(id)
+  }
+}
+
+impl Resume {
+  pub fn new(addr: SocketAddr) -> Self {
+    let now = Instant::now();
+    Resume { since: now, next_retry: now, addr }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn heartbeats() {
+    let config = LinkConfig::new(Duration::from_secs(5), Duration::from_secs(10));
+    let mut link = Link::new();
+    let now = Instant::now();
+    assert_eq!(link.heartbeat(&config, now), Some(0));
+    assert_eq!(link.heartbeat(&config, now + Duration::from_secs(1)), None);
+    assert_eq!(link.heartbeat(&config, now + Duration::from_secs(5)), Some(1));
+    assert!(!link.timed_out(&config, now + Duration::from_secs(14)));
+    assert!(link.timed_out(&config, now + Duration::from_secs(15)));
+  }
+}
@@ -0,0 +1,163 @@
+//! Custom steps in the login process. Since 1.13, the server can send login
+//! plugin requests before the client finishes logging in. Each request is sent
+//! on a channel, and the client must reply to every request, even if it
+//! doesn't know the channel. This is how modern Velocity forwarding, and mod
+//! loaders like Forge, exchange data with the server during login.
+//!
+//! Handlers are added to the proxy with
+//! [`Proxy::with_login_handler`](crate::Proxy::with_login_handler). Once a
+//! client has been authenticated, every handler is asked if it wants to send a
+//! request, and the client isn't sent to the server until every request has
+//! been answered.
+//!
+//! [`ForgeModList`] is a built-in handler, which finds out which mods Forge
+//! clients have installed.
+
+use crate::conn::LoginInfo;
+use bb_common::{
+  util::{Buffer, BufferError, Chat, ClientKind},
+  version::ProtocolVersion,
+};
+use std::net::SocketAddr;
+
+/// Everything known about a client while it is logging in. Handlers may
+/// change any of this, for example to replace the profile with one forwarded
+/// from another proxy.
+#[derive(Debug)]
+pub struct LoginContext {
+  /// The name the client sent in the login start packet.
+  pub username: String,
+  pub ver:      ProtocolVersion,
+  /// The address the client connected from.
+  pub addr:     Option<SocketAddr>,
+  /// The profile the client will log in with.
+  pub info:     LoginInfo,
+  /// The mod loader detected from the handshake.
+  pub client:   ClientKind,
+  /// The ids of the client's mods. Handlers that find out which mods the
+  /// client has should add them here, so that they are passed on to the
+  /// server.
+  pub mods:     Vec<String>,
+}
+
+/// A handler for a single login plugin channel.
+pub trait LoginHandler: Send + Sync {
+  /// The channel this handler sends requests on, like `velocity:player_info`.
+  fn channel(&self) -> &str;
+
+  /// Returns the data to send in the first request. If this returns `None`,
+  /// nothing is sent to this client.
+  fn request(&self, login: &LoginContext) -> Option<Vec<u8>>;
+
+  /// Handles the client's reply to a request. `data` is `None` if the client
+  /// doesn't understand this channel, which is what vanilla clients reply
+  /// with.
+  ///
+  /// Returning `Ok(Some(data))` sends another request on the same channel,
+  /// which is useful for handshakes that take more than one round trip.
+  /// Returning an error kicks the client with the given message.
+  fn response(
+    &self,
+    login: &mut LoginContext,
+    data: Option<&[u8]>,
+  ) -> Result<Option<Vec<u8>>, Chat>;
+}
+
+/// The channel that Forge wraps all of its login messages in.
+const FML_WRAPPER: &str = "fml:loginwrapper";
+/// The channel inside the wrapper that the mod list is sent on.
+const FML_HANDSHAKE: &str = "fml:handshake";
+/// The id of the mod list that the server sends.
+const S2C_MOD_LIST: i32 = 1;
+/// The id of the client's reply to the mod list.
+const C2S_MOD_LIST_REPLY: i32 = 2;
+
+/// Asks Forge clients on 1.13 and newer which mods they have installed. The
+/// mod ids are stored in [`LoginContext::mods`], and are passed on to the
+/// server.
+///
+/// This sends an empty mod list to the client. Forge clients with mods that
+/// must also be installed on the server will refuse to join, just like they
+/// would when joining a vanilla server.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ForgeModList;
+
+impl LoginHandler for ForgeModList {
+  fn channel(&self) -> &str { FML_WRAPPER }
+
+  fn request(&self, login: &LoginContext) -> Option<Vec<u8>> {
+    if login.client != ClientKind::Forge {
+      return None;
+    }
+    let mut inner = vec![];
+    let mut buf = Buffer::new(&mut inner);
+    buf.write_varint(S2C_MOD_LIST);
+    buf.write_varint(0); // mods
+    buf.write_varint(0); // channels
+    buf.write_varint(0); // registries
+                         // FML3 (used on 1.18 and newer) added data pack registries.
+    if login.ver >= ProtocolVersion::V1_18 {
+      buf.write_varint(0);
+    }
+
+    let mut data = vec![];
+    let mut buf = Buffer::new(&mut data);
+    buf.write_str(FML_HANDSHAKE);
+    buf.write_varint(inner.len() as i32);
+    buf.write_buf(&inner);
+    Some(data)
+  }
+
+  fn response(
+    &self,
+    login: &mut LoginContext,
+    data: Option<&[u8]>,
+  ) -> Result<Option<Vec<u8>>, Chat> {
+    // A client that doesn't understand the channel isn't running Forge, so we
+    // treat it as vanilla.
+    let Some(data) = data else {
+      login.client = ClientKind::Vanilla;
+      return Ok(None);
+    };
+    match read_mod_list(data) {
+      Ok(mods) => login.mods = mods,
+      Err(e) => warn!("{} sent an invalid forge mod list: {e}", login.username),
+    }
+    Ok(None)
+  }
+}
+
+/// Reads the mod ids from a wrapped `C2SModListReply`. Any other message
+/// is ignored, and an empty list is returned.
+fn read_mod_list(data: &[u8]) -> Result<Vec<String>, BufferError> {
+  let mut buf = Buffer::new(data);
+  if buf.read_str(32767)? != FML_HANDSHAKE {
+    return Ok(vec![]);
+  }
+  let _len = buf.read_varint()?;
+  if buf.read_varint()? != C2S_MOD_LIST_REPLY {
+    return Ok(vec![]);
+  }
+  buf.read_list(|buf| buf.read_str(256))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mod_list() {
+    let mut inner = vec![];
+    let mut buf = Buffer::new(&mut inner);
+    buf.write_varint(C2S_MOD_LIST_REPLY);
+    buf.write_varint(2);
+    buf.write_str("forge");
+    buf.write_str("jei");
+    let mut data = vec![];
+    let mut buf = Buffer::new(&mut data);
+    buf.write_str(FML_HANDSHAKE);
+    buf.write_varint(inner.len() as i32);
+    buf.write_buf(&inner);
+    assert_eq!(read_mod_list(&data).unwrap(), vec!["forge".to_string(), "jei".to_string()]);
+  }
+}
@@ -3,6 +3,7 @@ extern crate log;
 
 use bb_proxy::Proxy;
 use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
@@ -11,6 +12,11 @@ struct Args {
   /// the existing config.
   #[clap(long)]
   write_default_config: bool,
+
+  /// Writes the id, name, and fields of every packet for every supported
+  /// version to the given json file, and then exits.
+  #[clap(long, value_name = "FILE")]
+  dump_packets: Option<PathBuf>,
 }
 
 fn main() {
@@ -30,6 +36,14 @@ fn main() {
   bb_common::init_with_level("proxy", log::LevelFilter::Info);
 
   let args = Args::parse();
+  if let Some(path) = args.dump_packets {
+    match bb_proxy::gnet::dump_packets(&path) {
+      Ok(()) => info!("wrote {}", path.display()),
+      Err(e) => error!("could not dump packets: {e}"),
+    }
+    return;
+  }
+
   let config = if args.write_default_config {
     bb_proxy::load_config_write_default("proxy.toml", "proxy-default.toml")
   } else {
@@ -37,6 +51,10 @@ fn main() {
   };
 
   log::set_max_level(config.log_level);
+  bb_common::logger::set_json(config.log_json);
+  if let Err(e) = bb_common::logger::set_file(config.log_file.to_logger()) {
+    error!("could not open log file: {e}");
+  }
 
   let proxy = match Proxy::from_config(config) {
     Ok(v) => v,
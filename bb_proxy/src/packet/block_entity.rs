@@ -0,0 +1,101 @@
+use bb_common::{
+  nbt::{Tag, NBT},
+  net::cb::{BlockEntity, BlockEntityKind},
+  util::Buffer,
+  version::ProtocolVersion,
+};
+
+/// Writes the length prefixed list of block entities at the end of a chunk
+/// data packet. Before 1.9.4, block entities were not part of the chunk
+/// packet, so this won't write anything on those versions.
+pub fn write_block_entities(
+  buf: &mut Buffer<&mut Vec<u8>>,
+  block_entities: &[BlockEntity],
+  ver: ProtocolVersion,
+) {
+  if ver < ProtocolVersion::V1_9_4 {
+    return;
+  }
+  buf.write_varint(block_entities.len() as i32);
+  for be in block_entities {
+    // 1.18 moved the position and type out of the NBT data.
+    if ver >= ProtocolVersion::V1_18 {
+      buf.write_u8((be.pos.chunk_rel_x() as u8) << 4 | be.pos.chunk_rel_z() as u8);
+      buf.write_i16(be.pos.y() as i16);
+      buf.write_varint(type_id(&be.kind, ver));
+    }
+    buf.write_buf(&nbt(be, ver).serialize());
+  }
+}
+
+/// Returns the id of the block entity type in the block entity registry. This
+/// is only sent on 1.18+.
+fn type_id(kind: &BlockEntityKind, ver: ProtocolVersion) -> i32 {
+  match kind {
+    // Hanging signs were added before spawners in 1.19.3.
+    BlockEntityKind::Spawner { .. } => {
+      if ver >= ProtocolVersion::V1_19_3 {
+        9
+      } else {
+        8
+      }
+    }
+    BlockEntityKind::CommandBlock { .. } => {
+      if ver >= ProtocolVersion::V1_19_3 {
+        22
+      } else {
+        21
+      }
+    }
+  }
+}
+
+/// Returns the name of the given entity, as it is stored in NBT. 1.11 switched
+/// to namespaced ids, and before that, entities used names like `CaveSpider`.
+fn entity_id(name: &str, ver: ProtocolVersion) -> String {
+  if ver >= ProtocolVersion::V1_11 {
+    format!("minecraft:{name}")
+  } else {
+    name
+      .split('_')
+      .map(|word| {
+        let mut chars = word.chars();
+        match chars.next() {
+          Some(c) => c.to_ascii_uppercase().to_string() + chars.as_str(),
+          None => String::new(),
+        }
+      })
+      .collect()
+  }
+}
+
+fn nbt(be: &BlockEntity, ver: ProtocolVersion) -> NBT {
+  let mut tags =
+    vec![("x", Tag::Int(be.pos.x())), ("y", Tag::Int(be.pos.y())), ("z", Tag::Int(be.pos.z()))];
+  match &be.kind {
+    BlockEntityKind::Spawner { entity, delay } => {
+      let id = if ver >= ProtocolVersion::V1_11 { "minecraft:mob_spawner" } else { "MobSpawner" };
+      tags.push(("id", Tag::String(id.into())));
+      tags.push(("Delay", Tag::Short(*delay)));
+      if !entity.is_empty() {
+        let data = Tag::new_compound(&[("id", Tag::String(entity_id(entity, ver)))]);
+        if ver >= ProtocolVersion::V1_18 {
+          tags.push(("SpawnData", Tag::new_compound(&[("entity", data)])));
+        } else {
+          tags.push(("SpawnData", data));
+        }
+      }
+    }
+    BlockEntityKind::CommandBlock { command, auto, track_output, last_output } => {
+      let id = if ver >= ProtocolVersion::V1_11 { "minecraft:command_block" } else { "Control" };
+      tags.push(("id", Tag::String(id.into())));
+      tags.push(("Command", Tag::String(command.clone())));
+      tags.push(("auto", Tag::Byte(*auto as i8)));
+      tags.push(("TrackOutput", Tag::Byte(*track_output as i8)));
+      if let Some(output) = last_output {
+        tags.push(("LastOutput", Tag::String(output.to_json_for(ver))));
+      }
+    }
+  }
+  NBT::new("", Tag::new_compound(&tags))
+}
@@ -1,8 +1,8 @@
 use super::{super::metadata, ToTcp, WriteError};
 use crate::{
   gnet::{
-    cb::{packet as gpacket, Packet as GPacket},
-    tcp,
+    cb::{packet as gpacket, packets, Packet as GPacket},
+    find_packet, tcp, Bytes,
   },
   stream::PacketStream,
   Conn,
@@ -13,6 +13,7 @@ use bb_common::{
   version::ProtocolVersion,
 };
 use smallvec::SmallVec;
+use std::collections::HashMap;
 
 macro_rules! gpacket {
   ( $name:ident $ver:ident { $( $field:ident $(: $value:expr)? ),* $(,)? } ) => {
@@ -70,6 +71,15 @@ macro_rules! to_tcp_manual {
   };
 }
 
+/// Reads a generated packet from raw data. This is used for packets that the
+/// generator doesn't understand the fields of, so they are written by hand.
+/// This returns `InvalidVer` if the packet doesn't exist on the given version.
+fn raw_packet(name: &str, ver: ProtocolVersion, data: Vec<u8>) -> Result<GPacket, WriteError> {
+  let info = find_packet(packets(ver), name).ok_or(WriteError::InvalidVer)?;
+  let mut p = tcp::Packet::from_buf_id(data, info.id, ver);
+  GPacket::from_tcp(&mut p, ver).map_err(|_| WriteError::InvalidVer)
+}
+
 to_tcp!(Abilities => (self, conn, ver) {
   if ver < ProtocolVersion::V1_16_5 {
     gpacket!(PlayerAbilities V8 {
@@ -119,6 +129,14 @@ to_tcp!(Animation => (self, conn, ver) {
     })
   }
 });
+to_tcp!(AckBlockUpdate => (self, conn, ver) {
+  // Older clients don't predict block changes, so there is nothing to
+  // acknowledge.
+  if ver < ProtocolVersion::V1_19 {
+    return Ok(smallvec![]);
+  }
+  gpacket!(PlayerActionResponse V19 { sequence: self.sequence })
+});
 to_tcp_manual!(Chunk => (self, conn, ver) {
   Ok(super::super::chunk(
     self,
@@ -133,7 +151,7 @@ to_tcp!(BlockUpdate => (self, conn, ver) {
     let mut data = vec![];
     let mut buf = Buffer::new(&mut data);
     buf.write_varint(self.state as i32);
-    gpacket!(BlockUpdate V8 { block_position: self.pos, unknown: data })
+    gpacket!(BlockUpdate V8 { block_position: self.pos, unknown: data.into() })
   }
 });
 to_tcp!(ChangeGameState => (self, conn, ver) {
@@ -189,7 +207,7 @@ to_tcp!(ChangeGameState => (self, conn, ver) {
     let mut buf = Buffer::new(&mut data);
     buf.write_u8(reason);
     buf.write_f32(value);
-    gpacket!(ChangeGameState V16 { unknown: data })
+    gpacket!(ChangeGameState V16 { unknown: data.into() })
   } else {
     gpacket!(ChangeGameState V8 { state: reason.into(), field_149141_c: value })
   }
@@ -197,19 +215,21 @@ to_tcp!(ChangeGameState => (self, conn, ver) {
 to_tcp!(ChatMessage => (self, conn, ver) {
   if ver >= ProtocolVersion::V1_19 {
     gpacket!(SystemChat V19 {
-      a: self.msg.to_json(), // content
-      b: true,               // type
+      a: self.msg.to_json_for(ver), // content
+      // On 1.19, this is the chat type, where 1 is a system message. On 1.19.1
+      // and later, this is `overlay`, which shows the message above the hotbar.
+      b: if ver >= ProtocolVersion::V1_19_2 { self.ty == 2 } else { true },
     })
   } else if ver >= ProtocolVersion::V1_16_5 {
     let mut data = vec![];
     let mut buf = Buffer::new(&mut data);
     buf.write_u8(self.ty);
     buf.write_uuid(UUID::from_u128(0));
-    gpacket!(Chat V12 { chat_component: self.msg.to_json(), unknown: data })
+    gpacket!(Chat V12 { chat_component: self.msg.to_json_for(ver), unknown: data.into() })
   } else if ver >= ProtocolVersion::V1_12_2 {
-    gpacket!(Chat V12 { chat_component: self.msg.to_json(), unknown: vec![self.ty] })
+    gpacket!(Chat V12 { chat_component: self.msg.to_json_for(ver), unknown: vec![self.ty].into() })
   } else {
-    gpacket!(Chat V8 { chat_component: self.msg.to_json(), ty: self.ty as i8 })
+    gpacket!(Chat V8 { chat_component: self.msg.to_json_for(ver), ty: self.ty as i8 })
   }
 });
 to_tcp!(CommandList => (self, conn, ver) {
@@ -261,11 +281,11 @@ to_tcp!(CommandList => (self, conn, ver) {
   });
   buf.write_varint(self.root as i32);
   if ver >= ProtocolVersion::V1_19 {
-    gpacket!(CommandTree V19 { unknown: data })
+    gpacket!(CommandTree V19 { unknown: data.into() })
   } else if ver >= ProtocolVersion::V1_16_5 {
-    gpacket!(CommandTree V16 { unknown: data })
+    gpacket!(CommandTree V16 { unknown: data.into() })
   } else {
-    gpacket!(CommandTree V14 { unknown: data })
+    gpacket!(CommandTree V14 { unknown: data.into() })
   }
 });
 to_tcp!(CollectItem => (self, conn, ver) {
@@ -301,7 +321,7 @@ to_tcp!(EntityEquipment => (mut self, conn, ver) {
       EquipmentSlot::Armor(ArmorSlot::Helmet) => 5,
     });
     buf.write_item(&self.item, conn.conv());
-    gpacket!(EntityEquipment V16 { id: self.eid, unknown: buf.serialize() })
+    gpacket!(EntityEquipment V16 { id: self.eid, unknown: buf.serialize().into() })
   } else if ver >= ProtocolVersion::V1_9_4 {
     let mut buf = tcp::Packet::from_buf_id(vec![], 0, ver);
     buf.write_item(&self.item, conn.conv());
@@ -315,7 +335,7 @@ to_tcp!(EntityEquipment => (mut self, conn, ver) {
         EquipmentSlot::Armor(ArmorSlot::Chestplate) => 4,
         EquipmentSlot::Armor(ArmorSlot::Helmet) => 5,
       },
-      unknown:        buf.serialize(),
+      unknown:        buf.serialize().into(),
     })
   } else {
     let mut buf = tcp::Packet::from_buf_id(vec![], 0, ver);
@@ -331,7 +351,7 @@ to_tcp!(EntityEquipment => (mut self, conn, ver) {
         EquipmentSlot::Armor(ArmorSlot::Chestplate) => 3,
         EquipmentSlot::Armor(ArmorSlot::Helmet) => 4,
       },
-      unknown:        buf.serialize(),
+      unknown:        buf.serialize().into(),
     })
   }
 });
@@ -346,7 +366,7 @@ to_tcp!(EntityLook => (self, conn, ver) {
     buf.write_i8(self.yaw);
     buf.write_i8(self.pitch);
     buf.write_bool(self.on_ground);
-    gpacket!(EntityLook V17 { unknown: data, v_1: 0, v_2: 0, v_3: 0, v_4: 0 })
+    gpacket!(EntityLook V17 { unknown: data.into(), v_1: 0, v_2: 0, v_3: 0, v_4: 0 })
   } else {
     gpacket!(EntityLook V8 {
       entity_id: self.eid,
@@ -366,7 +386,7 @@ to_tcp!(EntityMove => (self, conn, ver) {
     buf.write_i16(self.z);
     buf.write_bool(self.on_ground);
     gpacket!(EntityRelMove V17 {
-      unknown: data,
+      unknown: data.into(),
       v_1:     0,
       v_2:     0,
       v_3:     0,
@@ -403,7 +423,7 @@ to_tcp!(EntityMoveLook => (self, conn, ver) {
     buf.write_i8(self.pitch);
     buf.write_bool(self.on_ground);
     gpacket!(EntityLookMove V17 {
-      unknown: data,
+      unknown: data.into(),
       v_1:     0,
       v_2:     0,
       v_3:     0,
@@ -464,11 +484,34 @@ to_tcp!(EntityMetadata => (self, conn, ver) {
   gpacket!(EntityMetadata V8 {
     entity_id: self.eid,
     unknown:   match metadata(self.ty, &self.meta, ver, conn.conv()) {
-      Some(m) => m,
+      Some(m) => m.into(),
       None => return Ok(smallvec![]),
     },
   })
 });
+to_tcp!(EntityProperties => (self, conn, ver) {
+  let mut data = vec![];
+  let mut buf = Buffer::new(&mut data);
+  if ver >= ProtocolVersion::V1_17 {
+    buf.write_varint(self.properties.len() as i32);
+  } else {
+    buf.write_i32(self.properties.len() as i32);
+  }
+  for prop in &self.properties {
+    buf.write_str(&super::attribute_name(&prop.name, ver));
+    buf.write_f64(prop.base);
+    buf.write_list(&prop.modifiers, |buf, m| {
+      buf.write_uuid(m.id);
+      buf.write_f64(m.amount);
+      buf.write_u8(m.op);
+    });
+  }
+  if ver >= ProtocolVersion::V1_17 {
+    gpacket!(EntityProperties V17 { entity_id: self.eid, unknown: data.into() })
+  } else {
+    gpacket!(EntityProperties V8 { entity_id: self.eid, unknown: data.into() })
+  }
+});
 to_tcp!(EntityVelocity => (self, conn, _ver) {
   gpacket!(EntityVelocity V8 {
     entity_id: self.eid,
@@ -544,7 +587,7 @@ to_tcp!(JoinGame => (self, conn, ver) {
       difficulty:    self.difficulty.into(),
       max_players:   0,
       world_type:    self.level_type,
-      unknown:       data,
+      unknown:       data.into(),
     }),
     9..=13 => gpacket!(JoinGame V9 {
       player_id:     self.eid,
@@ -554,23 +597,23 @@ to_tcp!(JoinGame => (self, conn, ver) {
       difficulty:    self.difficulty.into(),
       max_players:   0,
       world_type:    self.level_type,
-      unknown:       data,
+      unknown:       data.into(),
     }),
     14..=15 => gpacket!(JoinGame V14 {
       player_entity_id: self.eid,
       hardcore:         self.hardcore_mode,
-      unknown:          data,
+      unknown:          data.into(),
       v_2:              0,
     }),
     16 => gpacket!(JoinGame V16 {
       player_entity_id: self.eid,
       hardcore:         self.hardcore_mode,
-      unknown:          data,
+      unknown:          data.into(),
     }),
     17.. => gpacket!(JoinGame V17 {
       player_entity_id: self.eid,
       hardcore:         self.hardcore_mode,
-      unknown:          data,
+      unknown:          data.into(),
     }),
     _ => unimplemented!(),
   }
@@ -604,7 +647,7 @@ to_tcp!(Particle => (self, conn, ver) {
     buf.write_f32(self.data_float);
     buf.write_i32(self.count);
     buf.write_buf(&self.data);
-    gpacket!(Particle V14 { unknown: data })
+    gpacket!(Particle V14 { unknown: data.into() })
   } else {
     buf.write_i32(old_id);
     buf.write_bool(self.long);
@@ -617,7 +660,7 @@ to_tcp!(Particle => (self, conn, ver) {
     buf.write_f32(self.data_float);
     buf.write_i32(self.count);
     buf.write_buf(&self.data);
-    gpacket!(Particle V8 { unknown: data })
+    gpacket!(Particle V8 { unknown: data.into() })
   }
 });
 to_tcp!(PlayerHeader => (self, conn, _ver) {
@@ -636,7 +679,11 @@ to_tcp!(PlayerList => (self, conn, ver) {
       buf.write_list(&v, |buf, v| {
         buf.write_uuid(v.id);
         buf.write_str(&v.name);
-        buf.write_varint(0);
+        buf.write_list(&v.properties, |buf, p| {
+          buf.write_str(&p.name);
+          buf.write_str(&p.value);
+          buf.write_option(&p.signature, |buf, s| buf.write_str(s));
+        });
         // This info is no longer sent as of 1.19.3.
         if ver < ProtocolVersion::V1_19_3 {
           buf.write_varint(v.game_mode.id().into());
@@ -678,7 +725,7 @@ to_tcp!(PlayerList => (self, conn, ver) {
       }
       buf.write_list(&v, |buf, v| {
         buf.write_uuid(v.id);
-        buf.write_option(&v.display_name, |buf, v| buf.write_str(&v.to_json()));
+        buf.write_option(&v.display_name, |buf, v| buf.write_str(&v.to_json_for(ver)));
       });
     }
     cb::PlayerListAction::Remove(v) => {
@@ -693,11 +740,11 @@ to_tcp!(PlayerList => (self, conn, ver) {
     }
   }
   if ver >= ProtocolVersion::V1_19_3 {
-    gpacket!(PlayerList V19 { unknown: data })
+    gpacket!(PlayerList V19 { unknown: data.into() })
   } else if ver >= ProtocolVersion::V1_17_1 {
-    gpacket!(PlayerList V17 { action: id, unknown: data })
+    gpacket!(PlayerList V17 { action: id, unknown: data.into() })
   } else {
-    gpacket!(PlayerList V8 { action: id, unknown: data, v_2: 0 })
+    gpacket!(PlayerList V8 { action: id, unknown: data.into(), v_2: 0 })
   }
 });
 to_tcp!(PlaySound => (self, conn, ver) {
@@ -780,9 +827,9 @@ to_tcp!(PlaySound => (self, conn, ver) {
 to_tcp!(PluginMessage => (self, conn, ver) {
   // No length prefix for data, it is inferred from packet length.
   if ver < ProtocolVersion::V1_14_4 {
-    gpacket!(CustomPayload V8 { channel: self.channel, unknown: self.data, v_2: 0 })
+    gpacket!(CustomPayload V8 { channel: self.channel, unknown: self.data.into(), v_2: 0 })
   } else {
-    gpacket!(CustomPayload V14 { channel: self.channel, unknown: self.data, v_2: 0 })
+    gpacket!(CustomPayload V14 { channel: self.channel, unknown: self.data.into(), v_2: 0 })
   }
 });
 to_tcp!(RemoveEntities => (self, conn, ver) {
@@ -792,7 +839,7 @@ to_tcp!(RemoveEntities => (self, conn, ver) {
     let mut data = vec![];
     let mut buf = Buffer::new(&mut data);
     buf.write_list(&self.eids, |buf, &e| buf.write_varint(e));
-    gpacket!(DestroyEntities V8 { unknown: data })
+    gpacket!(DestroyEntities V8 { unknown: data.into() })
   }
 });
 to_tcp!(Respawn => (self, conn, ver) {
@@ -801,8 +848,14 @@ to_tcp!(Respawn => (self, conn, ver) {
     let mut buf = Buffer::new(&mut data);
     buf.write_i32(self.dimension.into());
     if ver >= ProtocolVersion::V1_16_5 {
-      crate::registry::write_single_dimension(&mut buf, ver, 0, 256);
-      buf.write_str("minecraft:overworld");
+      crate::registry::write_single_dimension(&mut buf, ver, self.world_min_y, self.world_height);
+      // Newer clients only clear their chunks when the world name changes, so
+      // each dimension needs a different name.
+      buf.write_str(match self.dimension {
+        -1 => "minecraft:the_nether",
+        1 => "minecraft:the_end",
+        _ => "minecraft:overworld",
+      });
     }
     if ver >= ProtocolVersion::V1_15_2 {
       // hashed seed, same as join game
@@ -819,7 +872,7 @@ to_tcp!(Respawn => (self, conn, ver) {
       buf.write_str(&self.level_type);
     }
     gpacket!(Respawn V14 {
-      unknown: data,
+      unknown: data.into(),
     })
   } else {
     gpacket!(Respawn V8 {
@@ -827,7 +880,7 @@ to_tcp!(Respawn => (self, conn, ver) {
       difficulty:   self.difficulty.into(),
       game_type:    self.game_mode.id(),
       world_type:   self.level_type,
-      unknown:      vec![],
+      unknown:      Bytes::default(),
     })
   }
 });
@@ -860,7 +913,7 @@ to_tcp!(ScoreboardObjective => (self, conn, ver) {
       if ver <= ProtocolVersion::V1_12_2 {
         buf.write_str(&value.to_codes());
       } else {
-        buf.write_str(&value.to_json());
+        buf.write_str(&value.to_json_for(ver));
       }
       buf.write_varint(match ty {
         ObjectiveType::Integer => 0,
@@ -873,10 +926,10 @@ to_tcp!(ScoreboardObjective => (self, conn, ver) {
     gpacket!(ScoreboardObjective V8 {
       objective_name: self.objective,
       field_149342_c: m,
-      unknown:        data,
+      unknown:        data.into(),
     })
   } else {
-    gpacket!(ScoreboardObjective V18 { name: self.objective, mode: m, unknown: data })
+    gpacket!(ScoreboardObjective V18 { name: self.objective, mode: m, unknown: data.into() })
   }
 });
 to_tcp!(ScoreboardUpdate => (self, conn, ver) {
@@ -896,7 +949,7 @@ to_tcp!(ScoreboardUpdate => (self, conn, ver) {
         ScoreboardAction::Create(_) => 0,
         ScoreboardAction::Remove => 1,
       },
-      unknown:     data,
+      unknown:     data.into(),
       v_2:         "".into(),
     })
   } else if ver >= ProtocolVersion::V1_14_4 {
@@ -911,7 +964,7 @@ to_tcp!(ScoreboardUpdate => (self, conn, ver) {
         ScoreboardAction::Create(_) => 0,
         ScoreboardAction::Remove => 1,
       },
-      unknown:     data,
+      unknown:     data.into(),
       v_2:         "".into(),
     })
   } else {
@@ -926,10 +979,50 @@ to_tcp!(ScoreboardUpdate => (self, conn, ver) {
         ScoreboardAction::Create(_) => 0,
         ScoreboardAction::Remove => 1,
       },
-      unknown:   data,
+      unknown:   data.into(),
     })
   }
 });
+to_tcp!(SetCooldown => (self, conn, ver) {
+  if ver >= ProtocolVersion::V1_9 {
+    let (item, _) = conn.conv().item_to_old(self.item as u32, ver.block());
+    gpacket!(Cooldown V9 { item: item as i32, ticks: self.ticks as i32 })
+  } else {
+    // 1.8 has no item cooldowns. The server still enforces them.
+    return Ok(smallvec![]);
+  }
+});
+to_tcp_manual!(SetPassengers => (self, conn, ver) {
+  if ver >= ProtocolVersion::V1_9 {
+    let mut buf = tcp::Packet::from_buf_id(vec![], 0, ver);
+    buf.write_varint(self.eid);
+    buf.write_varint(self.passengers.len() as i32);
+    for eid in self.passengers {
+      buf.write_varint(eid);
+    }
+    Ok(smallvec![raw_packet("EntityPassengersSet", ver, buf.serialize())?])
+  } else {
+    // 1.8 only allows one passenger per vehicle, so only the first passenger is
+    // attached. Every other passenger is detached, in case it was the first
+    // passenger before.
+    let attach = |eid: i32, vehicle: i32| {
+      let mut buf = tcp::Packet::from_buf_id(vec![], 0, ver);
+      buf.write_i32(eid);
+      buf.write_i32(vehicle);
+      // Not a leash
+      buf.write_bool(false);
+      raw_packet("EntityAttach", ver, buf.serialize())
+    };
+    let mut out = SmallVec::new();
+    for eid in self.removed.iter().chain(self.passengers.iter().skip(1)) {
+      out.push(attach(*eid, -1)?);
+    }
+    if let Some(&first) = self.passengers.first() {
+      out.push(attach(first, self.eid)?);
+    }
+    Ok(out)
+  }
+});
 to_tcp!(SetPosLook => (self, conn, ver) {
   let mut data = vec![];
   let mut buf = Buffer::new(&mut data);
@@ -946,7 +1039,7 @@ to_tcp!(SetPosLook => (self, conn, ver) {
     z:       self.pos.z(),
     yaw:     self.yaw,
     pitch:   self.pitch,
-    unknown: data,
+    unknown: data.into(),
   })
 });
 to_tcp!(SpawnEntity => (self, conn, ver) {
@@ -989,7 +1082,7 @@ to_tcp!(SpawnEntity => (self, conn, ver) {
           Some(data) => {
             return Ok(smallvec![
               spawn,
-              gpacket!(EntityMetadata V8 { entity_id: self.eid, unknown: data })
+              gpacket!(EntityMetadata V8 { entity_id: self.eid, unknown: data.into() })
             ])
           }
           None => spawn,
@@ -1012,7 +1105,7 @@ to_tcp!(SpawnEntity => (self, conn, ver) {
         pitch: self.pitch,
         head_pitch: self.head_yaw,
         unknown: match metadata(self.ty, &self.meta, ver, conn.conv()) {
-          Some(m) => m,
+          Some(m) => m.into(),
           None => return Ok(smallvec![]),
         },
       })
@@ -1031,7 +1124,7 @@ to_tcp!(SpawnEntity => (self, conn, ver) {
         pitch: self.pitch,
         head_pitch: self.head_yaw,
         unknown: match metadata(self.ty, &self.meta, ver, conn.conv()) {
-          Some(m) => m,
+          Some(m) => m.into(),
           None => return Ok(smallvec![]),
         },
       })
@@ -1049,7 +1142,7 @@ to_tcp!(SpawnEntity => (self, conn, ver) {
         pitch: self.pitch,
         head_pitch: self.head_yaw,
         unknown: match metadata(self.ty, &self.meta, ver, conn.conv()) {
-          Some(m) => m,
+          Some(m) => m.into(),
           None => return Ok(smallvec![]),
         },
       })
@@ -1069,7 +1162,7 @@ to_tcp!(SpawnEntity => (self, conn, ver) {
       buf.write_i16(self.vel_x);
       buf.write_i16(self.vel_y);
       buf.write_i16(self.vel_z);
-      gpacket!(SpawnObject V14 { id: self.eid, uuid :self.id, unknown: data })
+      gpacket!(SpawnObject V14 { id: self.eid, uuid :self.id, unknown: data.into() })
     } else if ver >= ProtocolVersion::V1_9 {
       gpacket!(SpawnObject V9 {
         entity_id: self.eid,
@@ -1100,7 +1193,7 @@ to_tcp!(SpawnEntity => (self, conn, ver) {
         yaw:            self.yaw.into(),
         pitch:          self.pitch.into(),
         field_149020_k: self.data,
-        unknown:        data,
+        unknown:        data.into(),
       })
     };
     if !self.meta.fields.is_empty() {
@@ -1108,7 +1201,7 @@ to_tcp!(SpawnEntity => (self, conn, ver) {
         Some(data) => {
           return Ok(smallvec![
             spawn,
-            gpacket!(EntityMetadata V8 { entity_id: self.eid, unknown: data })
+            gpacket!(EntityMetadata V8 { entity_id: self.eid, unknown: data.into() })
           ])
         }
         None => spawn,
@@ -1134,7 +1227,7 @@ to_tcp!(SpawnPlayer => (self, conn, ver) {
         Some(data) => {
           return Ok(smallvec![
             spawn,
-            gpacket!(EntityMetadata V8 { entity_id: self.eid, unknown: data })
+            gpacket!(EntityMetadata V8 { entity_id: self.eid, unknown: data.into() })
           ])
         }
         None => spawn,
@@ -1152,7 +1245,7 @@ to_tcp!(SpawnPlayer => (self, conn, ver) {
       yaw:       self.yaw,
       pitch:     self.pitch,
       unknown:   match metadata(self.ty, &self.meta, ver, conn.conv()) {
-        Some(m) => m,
+        Some(m) => m.into(),
         None => return Ok(smallvec![]),
       },
     })
@@ -1167,7 +1260,7 @@ to_tcp!(SpawnPlayer => (self, conn, ver) {
       pitch:        self.pitch,
       current_item: 0,
       unknown:      match metadata(self.ty, &self.meta, ver, conn.conv()) {
-        Some(m) => m,
+        Some(m) => m.into(),
         None => return Ok(smallvec![]),
       },
     })
@@ -1177,27 +1270,37 @@ to_tcp_manual!(Tags => (self, conn, ver) {
   if ver >= ProtocolVersion::V1_14_4 {
     let mut data = vec![];
     let mut buf = Buffer::new(&mut data);
-    buf.write_varint(5);
-    macro_rules! tag {
-      ( $name:expr, $tag:expr ) => {
-        buf.write_str($name);
-        buf.write_varint($tag.len() as i32);
-        for (name, tag) in &$tag {
-          buf.write_str(name);
-          buf.write_varint(tag.len() as i32);
-          for elem in tag {
-            buf.write_varint(*elem);
-          }
+    fn write_tags(buf: &mut Buffer<&mut Vec<u8>>, tags: &HashMap<String, Vec<i32>>) {
+      buf.write_varint(tags.len() as i32);
+      for (name, tag) in tags {
+        buf.write_str(name);
+        buf.write_varint(tag.len() as i32);
+        for elem in tag {
+          buf.write_varint(*elem);
         }
-      };
+      }
+    }
+    if ver >= ProtocolVersion::V1_17_1 {
+      // 1.17+ sends a list of registries, each with an identifier.
+      buf.write_varint(5);
+      for (name, tags) in [
+        ("minecraft:block", &self.block),
+        ("minecraft:item", &self.item),
+        ("minecraft:fluid", &self.fluid),
+        ("minecraft:entity_type", &self.entity_type),
+        ("minecraft:game_event", &self.game_event),
+      ] {
+        buf.write_str(name);
+        write_tags(&mut buf, tags);
+      }
+    } else {
+      // Older versions have a fixed set of registries, in this order.
+      write_tags(&mut buf, &self.block);
+      write_tags(&mut buf, &self.item);
+      write_tags(&mut buf, &self.fluid);
+      write_tags(&mut buf, &self.entity_type);
     }
-    tag!("minecraft:block", self.block);
-    tag!("minecraft:item", self.item);
-    tag!("minecraft:fluid", self.fluid);
-    tag!("minecraft:entity_type", self.entity_type);
-    tag!("minecraft:game_event", self.game_event);
-    // gpacket!(SynchronizeTagsV14 { unknown: data }
-    Ok(smallvec![])
+    Ok(smallvec![gpacket!(SynchronizeTags V14 { unknown: data.into() })])
   } else {
     Err(WriteError::InvalidVer)
   }
@@ -1220,8 +1323,8 @@ to_tcp!(Title => (self, conn, ver) {
     let mut data = vec![];
     let mut buf = Buffer::new(&mut data);
     match self.action {
-      TitleAction::Title(ref chat) => buf.write_str(&chat.to_json()),
-      TitleAction::Subtitle(ref chat) => buf.write_str(&chat.to_json()),
+      TitleAction::Title(ref chat) => buf.write_str(&chat.to_json_for(ver)),
+      TitleAction::Subtitle(ref chat) => buf.write_str(&chat.to_json_for(ver)),
       TitleAction::Times { fade_in, stay, fade_out } => {
         buf.write_i32(fade_in as i32);
         buf.write_i32(stay as i32);
@@ -1247,7 +1350,7 @@ to_tcp!(Title => (self, conn, ver) {
           TitleAction::Clear(true) => 4,
         }
       },
-      unknown: data,
+      unknown: data.into(),
     })
   }
 });
@@ -1259,9 +1362,23 @@ to_tcp!(Teams => (self, conn, ver) {
   fn write_entities(buf: &mut Buffer<&mut Vec<u8>>, entities: &[String]) {
     buf.write_list(entities, |buf, n| buf.write_str(n.as_str()));
   }
+  // Clients before 1.13 will disconnect if the prefix or suffix is longer than
+  // 16 characters, or if the display name is longer than 32 characters.
+  fn truncate(mut s: String, len: usize) -> String {
+    if let Some((idx, _)) = s.char_indices().nth(len) {
+      s.truncate(idx);
+    }
+    s
+  }
+  fn legacy_prefix(info: &TeamInfo) -> String {
+    let mut prefix = truncate(info.prefix.to_codes(), 14);
+    prefix.push(chat::CODE_SEP);
+    prefix.push(info.color.code());
+    prefix
+  }
   fn write_info(ver: ProtocolVersion, buf: &mut Buffer<&mut Vec<u8>>, info: &TeamInfo) {
     if ver >= ProtocolVersion::V1_14_4 {
-      buf.write_str(&info.display_name.to_json());
+      buf.write_str(&info.display_name.to_json_for(ver));
       buf.write_u8(
         if info.friendly_fire { 0x01 } else { 0x00 }
           | if info.see_invis { 0x02 } else { 0x00 },
@@ -1274,22 +1391,19 @@ to_tcp!(Teams => (self, conn, ver) {
       });
       buf.write_str(match info.collisions {
         TeamRule::Always => "always",
-        TeamRule::ForOtherTeams => "pushrOtherTeams",
+        TeamRule::ForOtherTeams => "pushOtherTeams",
         TeamRule::ForOwnTeam => "pushOwnTeam",
         TeamRule::Never => "never",
       });
       buf.write_varint(info.color.id().into());
-      buf.write_str(&info.prefix.to_json());
-      buf.write_str(&info.postfix.to_json());
+      buf.write_str(&info.prefix.to_json_for(ver));
+      buf.write_str(&info.postfix.to_json_for(ver));
     } else if ver >= ProtocolVersion::V1_9_4 {
-      buf.write_str(&info.display_name.to_codes());
+      buf.write_str(&truncate(info.display_name.to_codes(), 32));
       // Team colors are broken. This code makes titles match the functionality of
       // 1.14+ clients.
-      let mut prefix = info.prefix.to_codes();
-      prefix.push(chat::CODE_SEP);
-      prefix.push(info.color.code());
-      buf.write_str(&prefix);
-      buf.write_str(&info.postfix.to_codes());
+      buf.write_str(&legacy_prefix(info));
+      buf.write_str(&truncate(info.postfix.to_codes(), 16));
       buf.write_u8(
         if info.friendly_fire { 0x01 } else { 0x00 }
           | if info.see_invis { 0x02 } else { 0x00 },
@@ -1302,7 +1416,7 @@ to_tcp!(Teams => (self, conn, ver) {
       });
       buf.write_str(match info.collisions {
         TeamRule::Always => "always",
-        TeamRule::ForOtherTeams => "pushrOtherTeams",
+        TeamRule::ForOtherTeams => "pushOtherTeams",
         TeamRule::ForOwnTeam => "pushOwnTeam",
         TeamRule::Never => "never",
       });
@@ -1310,14 +1424,11 @@ to_tcp!(Teams => (self, conn, ver) {
       // reason not to send it.
       buf.write_varint(info.color.id().into());
     } else {
-      buf.write_str(&info.display_name.to_codes());
+      buf.write_str(&truncate(info.display_name.to_codes(), 32));
       // Team colors are broken. This code makes titles match the functionality of
       // 1.14+ clients.
-      let mut prefix = info.prefix.to_codes();
-      prefix.push(chat::CODE_SEP);
-      prefix.push(info.color.code());
-      buf.write_str(&prefix);
-      buf.write_str(&info.postfix.to_codes());
+      buf.write_str(&legacy_prefix(info));
+      buf.write_str(&truncate(info.postfix.to_codes(), 16));
       buf.write_u8(
         if info.friendly_fire { 0x01 } else { 0x00 }
           | if info.see_invis { 0x02 } else { 0x00 },
@@ -1357,11 +1468,61 @@ to_tcp!(Teams => (self, conn, ver) {
     TeamAction::RemoveEntities { .. } => 4,
   };
   if ver >= ProtocolVersion::V1_18 {
-    gpacket!(Teams V18 { packet_type: ty, team_name: self.team, unknown: data })
+    gpacket!(Teams V18 { packet_type: ty, team_name: self.team, unknown: data.into() })
   } else if ver >= ProtocolVersion::V1_17_1 {
-    gpacket!(Teams V17 { packet_type: ty, team_name: self.team, unknown: data })
+    gpacket!(Teams V17 { packet_type: ty, team_name: self.team, unknown: data.into() })
   } else {
-    gpacket!(Teams V8 { field_149314_f: ty, field_149320_a: self.team, unknown: data })
+    gpacket!(Teams V8 { field_149314_f: ty, field_149320_a: self.team, unknown: data.into() })
+  }
+});
+to_tcp!(TradeList => (self, conn, ver) {
+  let mut buf = tcp::Packet::from_buf_id(vec![], 0, ver);
+  if ver >= ProtocolVersion::V1_14_4 {
+    buf.write_varint(self.wid.into());
+  } else {
+    buf.write_i32(self.wid.into());
+  }
+  if ver >= ProtocolVersion::V1_19 {
+    buf.write_varint(self.offers.len() as i32);
+  } else {
+    buf.write_u8(self.offers.len() as u8);
+  }
+  for offer in self.offers {
+    let has_second = offer.second_input.id() != 0;
+    let [mut input, mut output, mut second] = [offer.input, offer.output, offer.second_input];
+    for it in [&mut input, &mut output, &mut second] {
+      conn.conv().item(it, ver.block());
+    }
+    buf.write_item(&input, conn.conv());
+    buf.write_item(&output, conn.conv());
+    buf.write_bool(has_second);
+    if has_second {
+      buf.write_item(&second, conn.conv());
+    }
+    buf.write_bool(offer.disabled);
+    buf.write_i32(offer.uses.min(i32::MAX as u32) as i32);
+    buf.write_i32(offer.max_uses.min(i32::MAX as u32) as i32);
+    if ver >= ProtocolVersion::V1_14_4 {
+      buf.write_i32(offer.xp.min(i32::MAX as u32) as i32);
+      // Special price, price multiplier, and demand. These are only used by
+      // vanilla to change prices, and the server sends the final price.
+      buf.write_i32(0);
+      buf.write_f32(0.0);
+      buf.write_i32(0);
+    }
+  }
+  if ver >= ProtocolVersion::V1_14_4 {
+    // The villager level and experience. A level of 0 hides the level and the
+    // experience bar, which is what wandering traders do.
+    buf.write_varint(0);
+    buf.write_varint(0);
+    // Regular villager (shows the experience bar), and can restock.
+    buf.write_bool(false);
+    buf.write_bool(false);
+    raw_packet("SetTradeOffers", ver, buf.serialize())?
+  } else {
+    // Before 1.14, this was a plugin message.
+    gpacket!(CustomPayload V8 { channel: "MC|TrList".into(), unknown: buf.serialize().into(), v_2: 0 })
   }
 });
 to_tcp!(UnloadChunk => (self, conn, ver) {
@@ -1373,7 +1534,7 @@ to_tcp!(UnloadChunk => (self, conn, ver) {
       chunk_z:        self.pos.z(),
       field_149279_g: true,
       // Zero bit mask, then zero length varint
-      unknown:        vec![0, 0, 0],
+      unknown:        Bytes::from_static(&[0, 0, 0]),
     })
   }
 });
@@ -1436,10 +1597,13 @@ to_tcp!(WindowOpen => (self, conn, ver) {
   } else {
     gpacket!(OpenWindow V8 {
       window_id:      self.wid.into(),
-      inventory_type: self.ty,
+      inventory_type: match self.ty.as_str() {
+        "minecraft:merchant" => "minecraft:villager".into(),
+        _ => self.ty,
+      },
       window_title:   self.title,
       slot_count:     self.size as i32,
-      unknown:        vec![],
+      unknown:        Bytes::default(),
     })
   }
 });
@@ -1452,7 +1616,11 @@ to_tcp!(WindowItems => (self, conn, ver) {
       buf.write_item(&it, conn.conv());
     }
     buf.write_item(&self.held, conn.conv());
-    gpacket!(WindowItems V17 { sync_id: self.wid.into(), revision: 0, unknown: buf.serialize() })
+    gpacket!(WindowItems V17 {
+      sync_id:  self.wid.into(),
+      revision: self.revision as i32,
+      unknown:  buf.serialize().into(),
+    })
   } else {
     let mut buf = tcp::Packet::from_buf_id(vec![], 0, ver);
     buf.write_i16(self.items.len() as i16);
@@ -1460,7 +1628,7 @@ to_tcp!(WindowItems => (self, conn, ver) {
       conn.conv().item(&mut it, ver.block());
       buf.write_item(&it, conn.conv());
     }
-    gpacket!(WindowItems V8 { window_id: self.wid.into(), unknown: buf.serialize(), v_2: 0 })
+    gpacket!(WindowItems V8 { window_id: self.wid.into(), unknown: buf.serialize().into(), v_2: 0 })
   }
 });
 to_tcp!(WindowItem => (mut self, conn, ver) {
@@ -1470,15 +1638,15 @@ to_tcp!(WindowItem => (mut self, conn, ver) {
   if ver >= ProtocolVersion::V1_17_1 {
     gpacket!(SetSlot V17 {
       sync_id: self.wid.into(),
-      revision: 0,
+      revision: self.revision as i32,
       slot: self.slot,
-      unknown: buf.serialize(),
+      unknown: buf.serialize().into(),
     })
   } else {
     gpacket!(SetSlot V8 {
       window_id: self.wid.into(),
       slot: self.slot,
-      unknown: buf.serialize(),
+      unknown: buf.serialize().into(),
     })
   }
 });
@@ -1,5 +1,5 @@
 use crate::{gnet::cb::Packet as GPacket, stream::PacketStream, Conn};
-use bb_common::net::cb::Packet;
+use bb_common::{net::cb::Packet, version::ProtocolVersion};
 
 use smallvec::SmallVec;
 use std::{error::Error, fmt};
@@ -36,6 +36,7 @@ impl ToTcp for Packet {
     match self {
       Packet::Abilities(p) => p.to_tcp(conn),
       Packet::Animation(p) => p.to_tcp(conn),
+      Packet::AckBlockUpdate(p) => p.to_tcp(conn),
       Packet::Chunk(p) => p.to_tcp(conn),
       Packet::BlockUpdate(p) => p.to_tcp(conn),
       Packet::ChangeGameState(p) => p.to_tcp(conn),
@@ -50,6 +51,7 @@ impl ToTcp for Packet {
       Packet::EntityPos(p) => p.to_tcp(conn),
       Packet::EntityStatus(p) => p.to_tcp(conn),
       Packet::EntityMetadata(p) => p.to_tcp(conn),
+      Packet::EntityProperties(p) => p.to_tcp(conn),
       Packet::EntityVelocity(p) => p.to_tcp(conn),
       Packet::JoinGame(p) => p.to_tcp(conn),
       Packet::KeepAlive(p) => p.to_tcp(conn),
@@ -64,12 +66,15 @@ impl ToTcp for Packet {
       Packet::ScoreboardDisplay(p) => p.to_tcp(conn),
       Packet::ScoreboardObjective(p) => p.to_tcp(conn),
       Packet::ScoreboardUpdate(p) => p.to_tcp(conn),
+      Packet::SetCooldown(p) => p.to_tcp(conn),
+      Packet::SetPassengers(p) => p.to_tcp(conn),
       Packet::SetPosLook(p) => p.to_tcp(conn),
       Packet::SpawnEntity(p) => p.to_tcp(conn),
       Packet::SpawnPlayer(p) => p.to_tcp(conn),
       Packet::Tags(p) => p.to_tcp(conn),
       Packet::Title(p) => p.to_tcp(conn),
       Packet::Teams(p) => p.to_tcp(conn),
+      Packet::TradeList(p) => p.to_tcp(conn),
       Packet::UnloadChunk(p) => p.to_tcp(conn),
       Packet::UpdateHealth(p) => p.to_tcp(conn),
       Packet::UpdateViewPos(p) => p.to_tcp(conn),
@@ -116,3 +121,31 @@ fn object_ty(entity: i32) -> i32 {
     _ => panic!("not an object: {entity}"),
   }
 }
+
+/// Converts a modern attribute name, like `generic.max_health`, into the name
+/// the client expects. 1.16 switched to namespaced snake case names, and before
+/// that, attributes were named like `generic.maxHealth`.
+pub fn attribute_name(name: &str, ver: ProtocolVersion) -> String {
+  if ver >= ProtocolVersion::V1_16 {
+    format!("minecraft:{name}")
+  } else {
+    match name.split_once('.') {
+      Some((namespace, name)) => {
+        let mut out = format!("{namespace}.");
+        let mut upper = false;
+        for c in name.chars() {
+          if c == '_' {
+            upper = true;
+          } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+          } else {
+            out.push(c);
+          }
+        }
+        out
+      }
+      None => name.into(),
+    }
+  }
+}
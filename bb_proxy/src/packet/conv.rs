@@ -67,8 +67,12 @@ mod particle {
   include!(concat!(env!("OUT_DIR"), "/particle/version.rs"));
 }
 
+// The proxy only needs enchantment names (for 1.13+ item NBT), so most of the
+// generated type code is unused.
+#[allow(dead_code)]
 mod enchantment {
   use bb_common::version::BlockVersion;
+  use std::str::FromStr;
 
   #[derive(Debug)]
   pub struct Version {
@@ -78,6 +82,17 @@ mod enchantment {
   }
 
   include!(concat!(env!("OUT_DIR"), "/enchantment/version.rs"));
+
+  #[derive(Debug)]
+  pub struct Data {
+    name: &'static str,
+    id:   u32,
+  }
+
+  #[derive(Debug)]
+  pub struct InvalidEnchantment(String);
+
+  include!(concat!(env!("OUT_DIR"), "/enchantment/ty.rs"));
 }
 
 impl TypeConverter {
@@ -146,7 +161,7 @@ impl TypeConverter {
   }
   /// Converts an item id into an id for the given version. It should work the
   /// same as [`block_to_old`](Self::block_to_old).
-  fn item_to_old(&self, id: u32, ver: BlockVersion) -> (u32, u32) {
+  pub fn item_to_old(&self, id: u32, ver: BlockVersion) -> (u32, u32) {
     if ver == BlockVersion::latest() {
       return (id, 0);
     }
@@ -310,6 +325,18 @@ impl TypeConverter {
     }
     self.enchantments[ver.to_index() as usize].to_old.get(id as usize).copied().unwrap_or(None)
   }
+  /// Returns the name of the given enchantment, like `sharpness`. The id is a
+  /// latest version id. 1.13+ clients use these names in item NBT.
+  pub fn enchantment_name(&self, id: u32) -> Option<&'static str> {
+    enchantment::Type::from_id(id).map(|ty| ty.to_str())
+  }
+  /// Parses an enchantment name, and returns the latest version id. This is
+  /// the reverse of [`enchantment_name`](Self::enchantment_name). A
+  /// `minecraft:` namespace is allowed.
+  pub fn enchantment_from_name(&self, name: &str) -> Option<u32> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    name.parse::<enchantment::Type>().ok().map(|ty| ty.id())
+  }
 }
 
 mod entity_types {
@@ -1,7 +1,7 @@
 use super::{conv::entity::MetadataType, TypeConverter};
 use bb_common::{
   metadata::{Field, Metadata, Pose},
-  util::{Buffer, Face},
+  util::{Buffer, Chat, Face},
   version::ProtocolVersion,
 };
 use std::mem;
@@ -24,8 +24,15 @@ pub fn metadata(
 ) -> Option<Vec<u8>> {
   let mut data = vec![];
   let mut out = Buffer::new(&mut data);
-  for (&id, field) in &meta.fields {
-    let (id, new_ty, old_ty) = conv.entity_metadata_types(ty, id, ver.block());
+  for (&new_id, field) in &meta.fields {
+    let (id, new_ty, old_ty) = conv.entity_metadata_types(ty, new_id, ver.block());
+    // Fields that don't exist on this version are mapped to 0. The only field
+    // that is actually at index 0 is the entity flags, so we skip everything
+    // else. Without this, fields like `no_gravity` would overwrite the flags on
+    // 1.8.
+    if id == 0 && new_id != 0 {
+      continue;
+    }
 
     debug_assert!(is_ty(field, new_ty), "expected field to have type {new_ty:?}, got {field:?}");
 
@@ -325,7 +332,15 @@ fn convert_field(field: &mut Field, ty: MetadataType) -> bool {
   // Replace `field` with a temporary, so that we can move out of the old data.
   let old_field = mem::replace(field, Field::Bool(false));
   match (old_field, ty) {
-    (Field::OptChat(msg), MetadataType::String) => *field = Field::String(msg.unwrap_or_default()),
+    // Custom names were plain strings with color codes before 1.13.
+    (Field::OptChat(msg), MetadataType::String) => {
+      *field = Field::String(match msg {
+        Some(msg) => Chat::from_json(&msg).map(|c| c.to_codes()).unwrap_or(msg),
+        None => String::new(),
+      })
+    }
+    // 1.8 used bytes for all boolean fields.
+    (Field::Bool(v), MetadataType::Byte) => *field = Field::Byte(v as u8),
     _ => return false,
   }
   true
@@ -14,19 +14,23 @@ mod v1_20;
 mod v1_8;
 mod v1_9;
 
+mod block_entity;
 mod cb;
 mod conv;
 mod metadata;
 mod sb;
 
-pub use cb::{ToTcp, WriteError};
+pub use cb::{attribute_name, ToTcp, WriteError};
 pub use conv::TypeConverter;
 pub use sb::FromTcp;
 
 pub use metadata::metadata;
 
+use block_entity::write_block_entities;
+
 pub struct ChunkWithPos {
   packet: bb_common::net::cb::packet::Chunk,
+  ver:    ProtocolVersion,
 }
 
 pub fn chunk(
@@ -34,7 +38,7 @@ pub fn chunk(
   ver: ProtocolVersion,
   conv: &TypeConverter,
 ) -> SmallVec<[Packet; 2]> {
-  let chunk = ChunkWithPos { packet };
+  let chunk = ChunkWithPos { packet, ver };
   smallvec![match ver.block() {
     BlockVersion::V1_8 => v1_8::chunk(chunk, conv),
     BlockVersion::V1_9 | BlockVersion::V1_12 => v1_9::chunk(chunk, ver, conv),
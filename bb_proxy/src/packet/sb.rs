@@ -1,14 +1,16 @@
 use super::TypeConverter;
 use crate::{
   gnet::{
-    sb::{packet as gpacket, Packet as GPacket},
+    sb::{packet as gpacket, tcp_name, Packet as GPacket},
     tcp,
   },
   Error, Result,
 };
 use bb_common::{
   math::{FPos, Pos},
-  net::sb::{Button, ClickWindow, DigStatus, Packet, PlayerCommand, UseEntityAction},
+  net::sb::{
+    Button, ClickWindow, CommandBlockMode, DigStatus, Packet, PlayerCommand, UseEntityAction,
+  },
   util::{Face, Hand},
   version::ProtocolVersion,
 };
@@ -48,11 +50,102 @@ impl FromTcp<GPacket> for Packet {
       GPacket::PlayerPosition(g) => Packet::from_tcp(g, ver, conv),
       GPacket::PlayerAbilities(g) => Packet::from_tcp(g, ver, conv),
       GPacket::UpdatePlayerAbilities(g) => Packet::from_tcp(g, ver, conv),
-      gpacket => Err(Error::UnknownSB(Box::new(gpacket))),
+      gpacket => match tcp_name(gpacket.tcp_id(ver) as i32, ver) {
+        name @ ("UpdateCommandBlock"
+        | "SelectMerchantTrade"
+        | "SelectVillagerTrade"
+        | "CustomPayload"
+        | "PlayerInput"
+        | "SteerVehicle") => raw_packet(gpacket, name, ver),
+        _ => Err(Error::UnknownSB(Box::new(gpacket))),
+      },
     }
   }
 }
 
+/// Parses the packets sent when a player edits a command block, selects a
+/// trade, or steers a vehicle. Before 1.13, the first two were sent as plugin
+/// messages, so this reads the raw packet data instead of using the generated
+/// fields.
+fn raw_packet(p: GPacket, name: &str, ver: ProtocolVersion) -> Result<Packet> {
+  let mut buf = tcp::Packet::new(p.tcp_id(ver) as i32, ver);
+  p.to_tcp(&mut buf);
+  let mut buf = tcp::Packet::from_buf(buf.serialize(), ver)?;
+  if name == "UpdateCommandBlock" {
+    let pos = buf.read_pos()?;
+    let command = buf.read_str(32767)?;
+    let mode = CommandBlockMode::from_id(buf.read_varint()?)
+      .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid command block mode"))?;
+    let flags = buf.read_u8()?;
+    return Ok(Packet::UpdateCommandBlock {
+      pos,
+      command,
+      mode,
+      track_output: flags & 0x01 != 0,
+      conditional: flags & 0x02 != 0,
+      auto: flags & 0x04 != 0,
+    });
+  }
+  if name == "PlayerInput" || name == "SteerVehicle" {
+    let sideways = buf.read_f32()?;
+    let forward = buf.read_f32()?;
+    let flags = buf.read_u8()?;
+    return Ok(Packet::SteerVehicle {
+      sideways,
+      forward,
+      jump: flags & 0x01 != 0,
+      dismount: flags & 0x02 != 0,
+    });
+  }
+  if name != "CustomPayload" {
+    return Ok(Packet::SelectTrade { index: buf.read_varint()? as u32 });
+  }
+  let channel = buf.read_str(20)?;
+  match channel.as_str() {
+    // 1.8 only has impulse command blocks, and this is also used for command block
+    // minecarts, which we don't support.
+    "MC|AdvCdm" if ver < ProtocolVersion::V1_9 => {
+      if buf.read_u8()? != 0 {
+        return Err(io::Error::new(ErrorKind::Other, "command block minecarts").into());
+      }
+      let pos = Pos::new(buf.read_i32()?, buf.read_i32()?, buf.read_i32()?);
+      Ok(Packet::UpdateCommandBlock {
+        pos,
+        command: buf.read_str(32767)?,
+        mode: CommandBlockMode::Impulse,
+        track_output: buf.read_bool()?,
+        conditional: false,
+        auto: false,
+      })
+    }
+    "MC|AutoCmd" => {
+      let pos = Pos::new(buf.read_i32()?, buf.read_i32()?, buf.read_i32()?);
+      let command = buf.read_str(32767)?;
+      let track_output = buf.read_bool()?;
+      let mode = match buf.read_str(16)?.as_str() {
+        "SEQUENCE" => CommandBlockMode::Chain,
+        "AUTO" => CommandBlockMode::Repeating,
+        _ => CommandBlockMode::Impulse,
+      };
+      Ok(Packet::UpdateCommandBlock {
+        pos,
+        command,
+        mode,
+        track_output,
+        conditional: buf.read_bool()?,
+        auto: buf.read_bool()?,
+      })
+    }
+    "MC|TrSel" => Ok(Packet::SelectTrade { index: buf.read_i32()? as u32 }),
+    // Every client sends its brand after joining, which is how modded clients are
+    // detected.
+    "MC|Brand" | "minecraft:brand" => {
+      Ok(Packet::PluginMessage { channel: "minecraft:brand".into(), data: buf.read_all() })
+    }
+    _ => Err(Error::UnknownSB(Box::new(p))),
+  }
+}
+
 macro_rules! from_tcp {
   (
     $packet:ident, $ver:ident, $conv:ident,
@@ -68,7 +161,7 @@ macro_rules! from_tcp {
         Ok(match p {
           $(
             gpacket::$packet::$match($match_var) => {
-              $( let mut $buf = tcp::Packet::from_buf_id($unknown, _tcp_id, $ver); )?
+              $( let mut $buf = tcp::Packet::from_buf_id($unknown.into_vec(), _tcp_id, $ver); )?
               $value
             }
           )*
@@ -85,17 +178,15 @@ from_tcp!(Animation, _ver, _conv, {
 from_tcp!(Chat, _ver, _conv, {
   V8(g) => Packet::Chat { msg: g.message },
   V11(g) => Packet::Chat { msg: g.message },
-  V19(g) => Packet::Chat { msg: String::from_utf8_lossy(&g.unknown).into() },
+  // The message is followed by a timestamp, salt, signature, and (depending on
+  // the version) a preview flag or a list of acknowledged messages. We don't
+  // verify signatures, and the layout of these fields changes with every 1.19.x
+  // release, so we only read the message and ignore everything after it.
+  V19(g) buf = g.unknown => Packet::Chat { msg: buf.read_str(256)? },
 });
 from_tcp!(CommandExecution, ver, _conv, {
-  V19(g) buf = g.unknown => {
-    let msg = buf.read_str(256)?;
-    let _time = buf.read_u64()?;
-    let _salt = buf.read_u64()?;
-    let _args_map_len = buf.read_varint()?;
-    let _signed = buf.read_bool()?;
-    Packet::Chat { msg: format!("/{msg}") }
-  },
+  // Same as chat messages, the signatures of the command arguments are ignored.
+  V19(g) buf = g.unknown => Packet::Chat { msg: format!("/{}", buf.read_str(256)?) },
 });
 from_tcp!(ClickWindow, ver, conv, {
   V8(mut g) buf = g.unknown => {
@@ -104,9 +195,11 @@ from_tcp!(ClickWindow, ver, conv, {
       g.slot_id = -999;
     }
     Packet::ClickWindow {
-      wid:  g.window_id.try_into().unwrap(),
-      slot: g.slot_id.try_into().unwrap(),
-      mode: click_window(g.mode, g.used_button)?,
+      wid:      g.window_id.try_into().unwrap(),
+      slot:     g.slot_id.try_into().unwrap(),
+      mode:     click_window(g.mode, g.used_button)?,
+      revision: None,
+      action:   Some(g.action_number),
     }
   },
   V9(mut g) buf = g.unknown => {
@@ -115,9 +208,11 @@ from_tcp!(ClickWindow, ver, conv, {
       g.slot_id = -999;
     }
     Packet::ClickWindow {
-      wid:  g.window_id.try_into().unwrap(),
-      slot: g.slot_id.try_into().unwrap(),
-      mode: click_window(g.mode, g.used_button)?,
+      wid:      g.window_id.try_into().unwrap(),
+      slot:     g.slot_id.try_into().unwrap(),
+      mode:     click_window(g.mode, g.used_button)?,
+      revision: None,
+      action:   Some(g.action_number),
     }
   },
 });
@@ -139,9 +234,11 @@ from_tcp!(ClickSlot, ver, conv, {
       g.slot = -999;
     }
     Packet::ClickWindow {
-      wid:  g.sync_id.try_into().unwrap(),
-      slot: g.slot.try_into().unwrap(),
-      mode: click_window(g.action_type, g.button)?,
+      wid:      g.sync_id.try_into().unwrap(),
+      slot:     g.slot.try_into().unwrap(),
+      mode:     click_window(g.action_type, g.button)?,
+      revision: None,
+      action:   Some(g.action_id),
     }
   },
   V17(mut g) buf = g.unknown => {
@@ -155,9 +252,11 @@ from_tcp!(ClickSlot, ver, conv, {
       g.slot = -999;
     }
     Packet::ClickWindow {
-      wid:  g.sync_id.try_into().unwrap(),
-      slot: g.slot.try_into().unwrap(),
-      mode: click_window(g.action_type, g.button)?,
+      wid:      g.sync_id.try_into().unwrap(),
+      slot:     g.slot.try_into().unwrap(),
+      mode:     click_window(g.action_type, g.button)?,
+      revision: Some(g.revision as u32),
+      action:   None,
     }
   },
 });
@@ -177,12 +276,25 @@ from_tcp!(KeepAlive, _ver, _conv, {
 from_tcp!(PlayerDig, ver, _conv, {
   V8(g) buf = g.unknown => match g.status {
     0 | 1 | 2 => Packet::BlockDig {
-      pos:    g.position,
-      status: DigStatus::from_id(g.status as u8),
-      face:   Face::from_id(buf.read_varint()? as u8),
+      pos:      g.position,
+      status:   DigStatus::from_id(g.status as u8),
+      face:     Face::from_id(buf.read_varint()? as u8),
+      sequence: if ver >= ProtocolVersion::V1_19 { Some(buf.read_varint()?) } else { None },
+    },
+    3 => Packet::ClickWindow {
+      wid:      u8::MAX,
+      slot:     0,
+      mode:     ClickWindow::DropAll,
+      revision: None,
+      action:   None,
+    },
+    4 => Packet::ClickWindow {
+      wid:      u8::MAX,
+      slot:     0,
+      mode:     ClickWindow::Drop,
+      revision: None,
+      action:   None,
     },
-    3 => Packet::ClickWindow { wid: u8::MAX, slot: 0, mode: ClickWindow::DropAll },
-    4 => Packet::ClickWindow { wid: u8::MAX, slot: 0, mode: ClickWindow::Drop },
     5 => {
       return Err(io::Error::new(ErrorKind::Other, "need to implement eating packet").into())
     }
@@ -197,37 +309,40 @@ from_tcp!(PlayerDig, ver, _conv, {
 from_tcp!(PlayerBlockPlacement, ver, conv, {
   V8(g) buf = g.unknown => {
     if g.position == Pos::new(-1, -1, -1) && g.placed_block_direction == 255 {
-      Packet::UseItem { hand: Hand::Main }
+      Packet::UseItem { hand: Hand::Main, sequence: None }
     } else {
       let _slot = buf.read_item(conv)?;
       let cursor_x = buf.read_u8()?;
       let cursor_y = buf.read_u8()?;
       let cursor_z = buf.read_u8()?;
       Packet::BlockPlace {
-        pos:    g.position,
-        face:   Face::from_id(g.placed_block_direction as u8),
-        hand:   Hand::Main,
-        cursor: FPos::new(
+        pos:      g.position,
+        face:     Face::from_id(g.placed_block_direction as u8),
+        hand:     Hand::Main,
+        cursor:   FPos::new(
           cursor_x as f64 / 16.0,
           cursor_y as f64 / 16.0,
           cursor_z as f64 / 16.0,
         ),
+        sequence: None,
       }
     }
   },
 });
 from_tcp!(PlayerInteractBlock, ver, _conv, {
   V9(g) => Packet::BlockPlace {
-    pos:    g.position,
-    face:   Face::from_id(g.placed_block_direction as u8),
-    hand:   Hand::from_id(g.hand as u8),
-    cursor: FPos::new(g.facing_x.into(), g.facing_y.into(), g.facing_z.into()),
+    pos:      g.position,
+    face:     Face::from_id(g.placed_block_direction as u8),
+    hand:     Hand::from_id(g.hand as u8),
+    cursor:   FPos::new(g.facing_x.into(), g.facing_y.into(), g.facing_z.into()),
+    sequence: None,
   },
   V11(g) => Packet::BlockPlace {
-    pos:    g.position,
-    face:   Face::from_id(g.placed_block_direction as u8),
-    hand:   Hand::from_id(g.hand as u8),
-    cursor: FPos::new(g.facing_x.into(), g.facing_y.into(), g.facing_z.into()),
+    pos:      g.position,
+    face:     Face::from_id(g.placed_block_direction as u8),
+    hand:     Hand::from_id(g.hand as u8),
+    cursor:   FPos::new(g.facing_x.into(), g.facing_y.into(), g.facing_z.into()),
+    sequence: None,
   },
   V14(g) buf = g.unknown => {
     let pos = buf.read_pos()?;
@@ -235,8 +350,8 @@ from_tcp!(PlayerInteractBlock, ver, _conv, {
     let cursor =
       FPos::new(buf.read_f32()?.into(), buf.read_f32()?.into(), buf.read_f32()?.into());
     let _in_head = buf.read_bool()?;
-    let _sequence = if ver >= ProtocolVersion::V1_19 { Some(buf.read_varint()?) } else { None };
-    Packet::BlockPlace { hand: Hand::from_id(g.hand as u8), pos, face, cursor }
+    let sequence = if ver >= ProtocolVersion::V1_19 { Some(buf.read_varint()?) } else { None };
+    Packet::BlockPlace { hand: Hand::from_id(g.hand as u8), pos, face, cursor, sequence }
   },
 });
 from_tcp!(UseEntity, ver, _conv, {
@@ -287,9 +402,8 @@ from_tcp!(PlayerCommand, _ver, _conv, {
   },
 });
 from_tcp!(PlayerInteractItem, _ver, _conv, {
-  V9(g) => Packet::UseItem { hand: Hand::from_id(g.hand as u8) },
-  // TODO: Use `g.sequence`.
-  V19(g) => Packet::UseItem { hand: Hand::from_id(g.hand as u8) },
+  V9(g) => Packet::UseItem { hand: Hand::from_id(g.hand as u8), sequence: None },
+  V19(g) => Packet::UseItem { hand: Hand::from_id(g.hand as u8), sequence: Some(g.sequence) },
 });
 from_tcp!(Player, _ver, _conv, {
   V8(g) => Packet::PlayerOnGround { on_ground: g.on_ground },
@@ -1,4 +1,4 @@
-use super::{ChunkWithPos, TypeConverter};
+use super::{write_block_entities, ChunkWithPos, TypeConverter};
 use crate::gnet::cb::{packet, Packet};
 use bb_common::{
   nbt::{Tag, NBT},
@@ -48,14 +48,14 @@ pub fn chunk(chunk: ChunkWithPos, conv: &TypeConverter) -> Packet {
   buf.write_buf(&heightmap.serialize());
   buf.write_varint(chunk_buf.len() as i32);
   buf.write_buf(&chunk_data);
-  buf.write_varint(0); // No block entities
+  write_block_entities(&mut buf, &chunk.packet.block_entities, chunk.ver);
 
   packet::ChunkDataV14 {
     chunk_x:                chunk.pos.x(),
     chunk_z:                chunk.pos.z(),
     is_full_chunk:          chunk.full,
     vertical_strip_bitmask: chunk.old_bit_map().into(),
-    unknown:                data,
+    unknown:                data.into(),
   }
   .into()
 }
@@ -1,4 +1,4 @@
-use super::{ChunkWithPos, TypeConverter};
+use super::{write_block_entities, ChunkWithPos, TypeConverter};
 use crate::gnet::cb::{packet, Packet};
 use bb_common::{
   nbt::{Tag, NBT},
@@ -54,6 +54,7 @@ pub fn chunk(chunk: ChunkWithPos, conv: &TypeConverter) -> Packet {
   buf.write_buf(&biome_data);
   buf.write_varint(chunk_buf.len() as i32);
   buf.write_buf(&chunk_data);
-  buf.write_varint(0); // No block entities
-  packet::ChunkDataV17 { chunk_x: chunk.pos.x(), chunk_z: chunk.pos.z(), unknown: data }.into()
+  write_block_entities(&mut buf, &chunk.packet.block_entities, chunk.ver);
+  packet::ChunkDataV17 { chunk_x: chunk.pos.x(), chunk_z: chunk.pos.z(), unknown: data.into() }
+    .into()
 }
@@ -1,4 +1,4 @@
-use super::{ChunkWithPos, TypeConverter};
+use super::{write_block_entities, ChunkWithPos, TypeConverter};
 use crate::gnet::cb::{packet, Packet};
 use bb_common::{
   chunk::Chunk,
@@ -91,7 +91,7 @@ pub fn chunk(chunk: ChunkWithPos, ver: ProtocolVersion, conv: &TypeConverter) ->
 
   buf.write_varint(chunk_buf.len() as i32);
   buf.write_buf(&chunk_data);
-  buf.write_varint(0); // No block entities
+  write_block_entities(&mut buf, &chunk.packet.block_entities, chunk.ver);
 
   // Light update stuff
   buf.write_bool(true); // This is a non-edge chunk
@@ -158,7 +158,7 @@ pub fn chunk(chunk: ChunkWithPos, ver: ProtocolVersion, conv: &TypeConverter) ->
   packet::ChunkDataV17 {
     chunk_x: chunk.packet.pos.x(),
     chunk_z: chunk.packet.pos.z(),
-    unknown: data,
+    unknown: data.into(),
   }
   .into()
 }
@@ -1,4 +1,4 @@
-use super::{ChunkWithPos, TypeConverter};
+use super::{write_block_entities, ChunkWithPos, TypeConverter};
 use crate::gnet::cb::{packet, Packet};
 use bb_common::{
   chunk::Chunk,
@@ -88,7 +88,7 @@ pub fn chunk(chunk: ChunkWithPos, ver: ProtocolVersion, conv: &TypeConverter) ->
 
   buf.write_varint(chunk_buf.len() as i32);
   buf.write_buf(&chunk_data);
-  buf.write_varint(0); // No block entities
+  write_block_entities(&mut buf, &chunk.packet.block_entities, chunk.ver);
 
   let mut sky_bitmap: u64 = 0;
   let mut sky_empty_bitmap: u64 = 0;
@@ -152,7 +152,7 @@ pub fn chunk(chunk: ChunkWithPos, ver: ProtocolVersion, conv: &TypeConverter) ->
   packet::ChunkDataV17 {
     chunk_x: chunk.packet.pos.x(),
     chunk_z: chunk.packet.pos.z(),
-    unknown: data,
+    unknown: data.into(),
   }
   .into()
 }
@@ -109,7 +109,7 @@ pub fn chunk(chunk: ChunkWithPos, conv: &TypeConverter) -> Packet {
     chunk_x:        chunk.pos.x(),
     chunk_z:        chunk.pos.z(),
     field_149279_g: chunk.full,
-    unknown:        chunk_data,
+    unknown:        chunk_data.into(),
   }))
 }
 
@@ -137,5 +137,5 @@ pub fn multi_block_change(
     buf.write_u8(y as u8);
     buf.write_varint(old_id as i32);
   }
-  packet::MultiBlockChangeV8 { unknown: data }.into()
+  packet::MultiBlockChangeV8 { unknown: data.into() }.into()
 }
@@ -1,4 +1,4 @@
-use super::{ChunkWithPos, TypeConverter};
+use super::{write_block_entities, ChunkWithPos, TypeConverter};
 use crate::gnet::cb::{packet, Packet};
 use bb_common::{util::Buffer, version::ProtocolVersion};
 
@@ -78,17 +78,14 @@ pub fn chunk(chunk: ChunkWithPos, ver: ProtocolVersion, conv: &TypeConverter) ->
   buf.write_varint(chunk_buf.len() as i32);
   buf.write_buf(&chunk_data);
 
-  // No block entities
-  if ver >= ProtocolVersion::V1_9_4 {
-    buf.write_varint(0);
-  }
+  write_block_entities(&mut buf, &chunk.packet.block_entities, ver);
 
   packet::ChunkDataV9 {
     chunk_x:            chunk.pos.x(),
     chunk_z:            chunk.pos.z(),
     load_chunk:         chunk.full,
     available_sections: chunk.old_bit_map().into(),
-    unknown:            data,
+    unknown:            data.into(),
     v_2:                0,
   }
   .into()
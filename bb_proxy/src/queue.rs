@@ -0,0 +1,59 @@
+//! The join queue. When the server is full, clients are held in limbo, and
+//! only the client at the front of the queue tries to join. Once they get in,
+//! everyone else moves up one spot.
+
+use bb_common::util::UUID;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+
+/// The clients waiting to join a full server, in the order they joined the
+/// queue.
+#[derive(Debug, Default)]
+pub struct Queue {
+  players: Mutex<VecDeque<UUID>>,
+}
+
+impl Queue {
+  pub fn new() -> Self { Queue::default() }
+
+  /// Adds a player to the back of the queue, if they aren't already in it.
+  /// Returns their position, where 0 is the front of the queue.
+  pub fn join(&self, id: UUID) -> usize {
+    let mut players = self.players.lock();
+    match players.iter().position(|p| *p == id) {
+      Some(pos) => pos,
+      None => {
+        players.push_back(id);
+        players.len() - 1
+      }
+    }
+  }
+
+  /// Removes a player from the queue. This does nothing if they aren't in it.
+  pub fn leave(&self, id: UUID) { self.players.lock().retain(|p| *p != id); }
+
+  /// Returns the position of the given player, where 0 is the front of the
+  /// queue.
+  pub fn position(&self, id: UUID) -> Option<usize> {
+    self.players.lock().iter().position(|p| *p == id)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn positions() {
+    let queue = Queue::new();
+    let (a, b, c) = (UUID::from_u128(1), UUID::from_u128(2), UUID::from_u128(3));
+    assert_eq!(queue.join(a), 0);
+    assert_eq!(queue.join(b), 1);
+    assert_eq!(queue.join(c), 2);
+    assert_eq!(queue.join(b), 1);
+    queue.leave(a);
+    assert_eq!(queue.position(b), Some(0));
+    assert_eq!(queue.position(c), Some(1));
+    assert_eq!(queue.position(a), None);
+  }
+}
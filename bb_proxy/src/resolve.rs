@@ -0,0 +1,229 @@
+//! Hostname resolution. DNS lookups can take a long time, so they are never
+//! done on the thread that handles packets. Instead, a [`Resolver`] runs
+//! lookups on its own thread, and wakes up the listener once a lookup is done.
+//!
+//! Hostnames are resolved like the vanilla client does: if no port is given,
+//! the `_minecraft._tcp` SRV record is checked first, and then we fall back to
+//! a normal A/AAAA lookup.
+
+use bb_common::util::SwitchMode;
+use crossbeam_channel::{Receiver, Sender};
+use mio::{Token, Waker};
+use parking_lot::RwLock;
+use std::{
+  io,
+  net::{SocketAddr, ToSocketAddrs},
+  sync::Arc,
+  thread,
+  time::Duration,
+};
+use trust_dns_resolver::Resolver as DnsResolver;
+
+/// The port used when a hostname doesn't specify one, and doesn't have an SRV
+/// record.
+pub const DEFAULT_PORT: u16 = 8483;
+
+/// A pending server switch, which is waiting on a hostname lookup.
+#[derive(Debug, Clone)]
+pub struct Switch {
+  pub mode: SwitchMode,
+  pub host: String,
+  /// Addresses to try after the resolved addresses.
+  pub ips:  Vec<SocketAddr>,
+}
+
+/// A finished lookup for a [`Switch`].
+pub struct Resolved {
+  /// The token of the client that is switching.
+  pub token:  Token,
+  pub switch: Switch,
+  pub addrs:  io::Result<Vec<SocketAddr>>,
+}
+
+/// Runs DNS lookups on a background thread. Once a lookup completes, the
+/// [`Waker`] passed to [`Resolver::new`] is woken, and the result can be read
+/// with [`Resolver::finished`].
+pub struct Resolver {
+  tx: Sender<(Token, Switch)>,
+  rx: Receiver<Resolved>,
+}
+
+impl Resolver {
+  pub fn new(waker: Waker) -> Self {
+    let (tx, requests) = crossbeam_channel::unbounded::<(Token, Switch)>();
+    let (results, rx) = crossbeam_channel::unbounded();
+    thread::Builder::new()
+      .name("dns resolver".into())
+      .spawn(move || {
+        let dns = Dns::new();
+        for (token, switch) in requests {
+          let addrs = dns.lookup(&switch.host);
+          if results.send(Resolved { token, switch, addrs }).is_err() {
+            break;
+          }
+          if let Err(e) = waker.wake() {
+            error!("could not wake listener after dns lookup: {e}");
+          }
+        }
+      })
+      .expect("failed to spawn dns resolver thread");
+    Resolver { tx, rx }
+  }
+
+  /// Starts looking up the host for the given client's switch. This will not
+  /// block.
+  pub fn lookup(&self, token: Token, switch: Switch) {
+    // The thread only stops when we are dropped, so this can't fail.
+    self.tx.send((token, switch)).unwrap();
+  }
+
+  /// Returns every lookup that has finished since the last call.
+  pub fn finished(&self) -> impl Iterator<Item = Resolved> + '_ { self.rx.try_iter() }
+}
+
+/// An address which is resolved again every so often, so that the host it
+/// points to can change without restarting the proxy.
+#[derive(Clone)]
+pub struct Watched {
+  addr: Arc<RwLock<SocketAddr>>,
+}
+
+impl Watched {
+  /// Resolves the given host, and then resolves it again on a background
+  /// thread every `interval`. If `host` is already an ip address, no thread is
+  /// started.
+  ///
+  /// This blocks until the first lookup is done, and returns an error if that
+  /// lookup fails. Failed lookups after that are logged, and the last address
+  /// is kept.
+  pub fn new(host: &str, interval: Duration) -> io::Result<Self> {
+    if let Ok(addr) = host.parse() {
+      return Ok(Watched { addr: Arc::new(RwLock::new(addr)) });
+    }
+    let dns = Dns::new();
+    let addr = first(host, dns.lookup(host))?;
+    let watched = Watched { addr: Arc::new(RwLock::new(addr)) };
+
+    let w = watched.clone();
+    let host = host.to_string();
+    thread::Builder::new().name("dns refresh".into()).spawn(move || loop {
+      thread::sleep(interval);
+      match first(&host, dns.lookup(&host)) {
+        Ok(addr) => {
+          let mut old = w.addr.write();
+          if *old != addr {
+            info!("server `{host}` moved from {} to {addr}", *old);
+            *old = addr;
+          }
+        }
+        Err(e) => warn!("could not resolve server `{host}`, keeping {}: {e}", *w.addr.read()),
+      }
+    })?;
+    Ok(watched)
+  }
+
+  /// Returns the latest address.
+  pub fn get(&self) -> SocketAddr { *self.addr.read() }
+}
+
+fn first(host: &str, addrs: io::Result<Vec<SocketAddr>>) -> io::Result<SocketAddr> {
+  addrs?.into_iter().next().ok_or_else(|| {
+    io::Error::new(io::ErrorKind::NotFound, format!("no addresses found for `{host}`"))
+  })
+}
+
+/// Splits a `host:port` string. The port is optional, and ipv6 addresses must
+/// be wrapped in brackets if a port is given.
+fn split_host(host: &str) -> io::Result<(&str, Option<u16>)> {
+  let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid host `{host}`"));
+  let (name, port) = match host.strip_prefix('[') {
+    Some(rest) => match rest.split_once(']') {
+      Some((name, "")) => (name, None),
+      Some((name, port)) => (name, Some(port.strip_prefix(':').ok_or_else(invalid)?)),
+      None => return Err(invalid()),
+    },
+    None => match host.rsplit_once(':') {
+      // More than one colon means this is an ipv6 address without a port.
+      Some((name, _)) if name.contains(':') => (host, None),
+      Some((name, port)) => (name, Some(port)),
+      None => (host, None),
+    },
+  };
+  if name.is_empty() {
+    return Err(invalid());
+  }
+  match port {
+    Some(port) => Ok((name, Some(port.parse().map_err(|_| invalid())?))),
+    None => Ok((name, None)),
+  }
+}
+
+/// A DNS client. If the system DNS config cannot be loaded, SRV lookups are
+/// skipped, and hosts are resolved through the standard library.
+struct Dns {
+  resolver: Option<DnsResolver>,
+}
+
+impl Dns {
+  pub fn new() -> Self {
+    let resolver = match DnsResolver::from_system_conf() {
+      Ok(r) => Some(r),
+      Err(e) => {
+        warn!("could not load system dns config, SRV records will be ignored: {e}");
+        None
+      }
+    };
+    Dns { resolver }
+  }
+
+  /// Resolves the given host. This will block until the lookup is done.
+  pub fn lookup(&self, host: &str) -> io::Result<Vec<SocketAddr>> {
+    if let Ok(addr) = host.parse() {
+      return Ok(vec![addr]);
+    }
+    let (name, port) = split_host(host)?;
+    let Some(resolver) = &self.resolver else {
+      return Ok((name, port.unwrap_or(DEFAULT_PORT)).to_socket_addrs()?.collect());
+    };
+    if let Ok(ip) = name.parse() {
+      return Ok(vec![SocketAddr::new(ip, port.unwrap_or(DEFAULT_PORT))]);
+    }
+
+    let mut addrs = vec![];
+    if port.is_none() {
+      if let Ok(srv) = resolver.srv_lookup(format!("_minecraft._tcp.{name}")) {
+        let mut records: Vec<_> = srv.iter().collect();
+        // Lowest priority first, and then highest weight first.
+        records.sort_by_key(|r| (r.priority(), u16::MAX - r.weight()));
+        for r in records {
+          match resolver.lookup_ip(r.target().to_utf8()) {
+            Ok(ips) => addrs.extend(ips.iter().map(|ip| SocketAddr::new(ip, r.port()))),
+            Err(e) => warn!("could not resolve SRV target `{}` for `{host}`: {e}", r.target()),
+          }
+        }
+      }
+    }
+    if addrs.is_empty() {
+      let ips = resolver.lookup_ip(name).map_err(io::Error::from)?;
+      addrs.extend(ips.iter().map(|ip| SocketAddr::new(ip, port.unwrap_or(DEFAULT_PORT))));
+    }
+    Ok(addrs)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn split() {
+    assert_eq!(split_host("example.com").unwrap(), ("example.com", None));
+    assert_eq!(split_host("example.com:25565").unwrap(), ("example.com", Some(25565)));
+    assert_eq!(split_host("::1").unwrap(), ("::1", None));
+    assert_eq!(split_host("[::1]").unwrap(), ("::1", None));
+    assert_eq!(split_host("[::1]:80").unwrap(), ("::1", Some(80)));
+    assert!(split_host("example.com:port").is_err());
+    assert!(split_host(":80").is_err());
+    assert!(split_host("[::1").is_err());
+  }
+}
@@ -0,0 +1,260 @@
+//! The server list status. This builds the status from the config: the motd
+//! rotates through the configured lines, and the player list shows either the
+//! players that are online, or a configured list of names.
+
+use crate::{
+  config::{Config, Sample},
+  JsonPlayer, JsonPlayers, JsonStatus, JsonVersion,
+};
+use bb_common::{
+  net::cb,
+  util::{
+    chat::{Chat, Color},
+    UUID,
+  },
+  version::ProtocolVersion,
+};
+use parking_lot::Mutex;
+use std::{
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+/// The most players clients will show when hovering over the player count.
+/// This is the same as vanilla.
+pub const MAX_SAMPLE: usize = 12;
+/// The server sends a status every 5 seconds. If we haven't heard from the
+/// server in this long, we only count the players on this proxy.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The players that are online. This is the latest
+/// [`ServerStatus`](cb::packet::ServerStatus) from the server, along with the
+/// players that are logged in through this proxy, which are used if the server
+/// hasn't sent a status recently.
+#[derive(Debug, Default)]
+pub struct Online {
+  players: Mutex<Vec<(String, UUID)>>,
+  report:  Mutex<Option<(Instant, cb::packet::ServerStatus)>>,
+}
+
+impl Online {
+  pub fn new() -> Self { Online::default() }
+
+  /// Adds a player. This is called once the player has logged in.
+  pub fn add(&self, name: &str, id: UUID) { self.players.lock().push((name.into(), id)); }
+  /// Removes a player. This is called once the player's connection closes.
+  pub fn remove(&self, id: UUID) {
+    let mut players = self.players.lock();
+    if let Some(idx) = players.iter().position(|(_, p)| *p == id) {
+      players.swap_remove(idx);
+    }
+  }
+  /// Stores a status sent from the server. If there are multiple servers, the
+  /// latest status from any of them is used.
+  pub fn set_report(&self, status: cb::packet::ServerStatus) {
+    *self.report.lock() = Some((Instant::now(), status));
+  }
+  /// Returns the resource usage from the latest status, if the server has sent
+  /// one recently.
+  pub fn usage(&self) -> Option<cb::ServerUsage> {
+    match &*self.report.lock() {
+      Some((received, status)) if received.elapsed() < REPORT_TIMEOUT => status.usage.clone(),
+      _ => None,
+    }
+  }
+  /// Returns the player counts, and up to `max_sample` of the players online.
+  pub fn players(&self, max_sample: usize) -> JsonPlayers {
+    if let Some((received, status)) = &*self.report.lock() {
+      if received.elapsed() < REPORT_TIMEOUT {
+        return JsonPlayers {
+          max:    status.max as i32,
+          online: status.online as i32,
+          sample: status
+            .sample
+            .iter()
+            .take(max_sample)
+            .map(|p| JsonPlayer { name: p.name.clone(), id: p.id.as_dashed_str() })
+            .collect(),
+        };
+      }
+    }
+    let players = self.players.lock();
+    JsonPlayers {
+      max:    0,
+      online: players.len() as i32,
+      sample: players
+        .iter()
+        .take(max_sample)
+        .map(|(name, id)| JsonPlayer { name: name.clone(), id: id.as_dashed_str() })
+        .collect(),
+    }
+  }
+}
+
+/// Builds the status from the config. See [`Proxy::from_config`].
+///
+/// [`Proxy::from_config`]: crate::Proxy::from_config
+pub struct Status {
+  motds:  Vec<Chat>,
+  rotate: Duration,
+  start:  Instant,
+  sample: Sample,
+  /// The names listed when `sample` is [`Sample::Fake`].
+  names:  Vec<String>,
+  /// If set, the server's resource usage is listed after the sample.
+  usage:  bool,
+  online: Arc<Online>,
+}
+
+impl Status {
+  pub fn from_config(config: &Config, online: Arc<Online>) -> Self {
+    Status {
+      motds: config.motd.iter().map(|m| Chat::from_codes(m)).collect(),
+      rotate: Duration::from_secs(config.motd_rotate_secs.max(1)),
+      start: Instant::now(),
+      sample: config.sample.clone(),
+      names: config.sample_names.clone(),
+      usage: config.status_usage,
+      online,
+    }
+  }
+
+  /// Returns the motd that should be shown right now. Each motd is shown for
+  /// `rotate`, and then the next one is shown.
+  pub fn motd(&self) -> Chat {
+    if self.motds.is_empty() {
+      return default_description();
+    }
+    let idx = self.start.elapsed().as_secs() / self.rotate.as_secs();
+    self.motds[idx as usize % self.motds.len()].clone()
+  }
+
+  /// Returns the player counts, and the players listed when hovering over
+  /// the player count. If `status-usage` is set, this also lists the server's
+  /// resource usage.
+  pub fn players(&self) -> JsonPlayers {
+    let mut players = self.online.players(MAX_SAMPLE);
+    match self.sample {
+      Sample::None => players.sample.clear(),
+      Sample::Online => {}
+      Sample::Fake => {
+        players.sample = self
+          .names
+          .iter()
+          .take(MAX_SAMPLE)
+          // The client doesn't do anything with these ids, so they don't need to
+          // be real.
+          .map(|name| JsonPlayer { name: name.clone(), id: UUID::from_u128(0).as_dashed_str() })
+          .collect()
+      }
+    }
+    if self.usage {
+      if let Some(usage) = self.online.usage() {
+        players.sample.extend(usage.lines().into_iter().map(|line| JsonPlayer {
+          name: format!("§7{line}"),
+          id:   UUID::from_u128(0).as_dashed_str(),
+        }));
+      }
+    }
+    players
+  }
+
+  pub fn build<'a>(&self, icon: &'a str, ver: ProtocolVersion) -> JsonStatus<'a> {
+    JsonStatus {
+      version:     version(ver),
+      players:     self.players(),
+      description: self.motd(),
+      favicon:     icon,
+    }
+  }
+}
+
+/// The description shown when no motd is configured.
+pub fn default_description() -> Chat {
+  let mut description = Chat::empty();
+  description.add("Bamboo").color(Color::BrightGreen);
+  description.add(" -- ").color(Color::Gray);
+  #[cfg(debug_assertions)]
+  description.add("Development mode").color(Color::Blue);
+  #[cfg(not(debug_assertions))]
+  description.add("Release mode").color(Color::Red);
+  description
+}
+
+/// Returns the version shown on the server list. The proxy supports every
+/// version, so this always matches the client's version.
+pub fn version(ver: ProtocolVersion) -> JsonVersion {
+  JsonVersion {
+    name:     format!("1.8 - {}", ProtocolVersion::latest()),
+    protocol: if ver == ProtocolVersion::Invalid {
+      ProtocolVersion::latest().id()
+    } else {
+      ver.id()
+    } as i32,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn online() {
+    let online = Online::new();
+    let (a, b) = (UUID::from_u128(1), UUID::from_u128(2));
+    online.add("a", a);
+    online.add("b", b);
+    assert_eq!(online.players(12).online, 2);
+    online.remove(a);
+    let players = online.players(12);
+    assert_eq!(players.online, 1);
+    assert_eq!(players.sample[0].name, "b");
+
+    // Once the server sends a status, that is used instead.
+    online.set_report(cb::packet::ServerStatus {
+      online: 30,
+      max:    100,
+      sample: vec![cb::StatusPlayer { name: "c".into(), id: UUID::from_u128(3) }],
+      usage:  None,
+    });
+    let players = online.players(12);
+    assert_eq!((players.online, players.max), (30, 100));
+    assert_eq!(players.sample[0].name, "c");
+  }
+
+  #[test]
+  fn fake_sample() {
+    let config = Config {
+      sample: Sample::Fake,
+      sample_names: (0..20).map(|i| format!("player{i}")).collect(),
+      ..Default::default()
+    };
+    let status = Status::from_config(&config, Arc::new(Online::new()));
+    let sample = status.players().sample;
+    assert_eq!(sample.len(), MAX_SAMPLE);
+    assert_eq!(sample[0].name, "player0");
+  }
+  #[test]
+  fn usage() {
+    let config = Config { status_usage: true, ..Default::default() };
+    let online = Arc::new(Online::new());
+    let status = Status::from_config(&config, online.clone());
+    assert!(status.players().sample.is_empty());
+
+    online.set_report(cb::packet::ServerStatus {
+      online: 1,
+      max:    20,
+      sample: vec![cb::StatusPlayer { name: "a".into(), id: UUID::from_u128(1) }],
+      usage:  Some(cb::ServerUsage {
+        rss:     64 * 1024 * 1024,
+        plugins: 2,
+        worlds:  vec![cb::WorldUsage { name: "default".into(), chunks: 100, ..Default::default() }],
+      }),
+    });
+    let sample = status.players().sample;
+    assert_eq!(sample.len(), 3);
+    assert_eq!(sample[0].name, "a");
+    assert_eq!(sample[1].name, "§7Memory: 64.0 MiB, 2 plugins");
+    assert!(sample[2].name.starts_with("§7default: 100 chunks"));
+  }
+}
@@ -0,0 +1,41 @@
+use bb_common::version::BlockVersion;
+use bb_server::block;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pprof::criterion::{Output, PProfProfiler};
+
+pub fn convert(c: &mut Criterion) {
+  let conv = block::TypeConverter::new();
+  let states = conv.vanilla_states();
+  // Every chunk we send to an old client goes through `to_old`, so this is the
+  // lookup that matters most.
+  for ver in [BlockVersion::V1_8, BlockVersion::V1_12, BlockVersion::V1_16] {
+    c.bench_function(&format!("block to_old {ver:?}"), |b| {
+      let mut id = 0;
+      b.iter(|| {
+        black_box(conv.to_old(id, ver));
+        id = (id + 1) % states;
+      });
+    });
+    c.bench_function(&format!("block to_latest {ver:?}"), |b| {
+      let mut id = 0;
+      b.iter(|| {
+        black_box(conv.to_latest(id, ver));
+        id = (id + 1) % 4096;
+      });
+    });
+  }
+  c.bench_function("block kind_from_id", |b| {
+    let mut id = 0;
+    b.iter(|| {
+      black_box(conv.kind_from_id(id, BlockVersion::latest()));
+      id = (id + 1) % states;
+    });
+  });
+}
+
+criterion_group! {
+  name = benches;
+  config = Criterion::default().with_profiler(PProfProfiler::new(100, Output::Flamegraph(None)));
+  targets = convert
+}
+criterion_main!(benches);
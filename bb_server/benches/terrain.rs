@@ -1,7 +1,7 @@
 use bb_common::math::ChunkPos;
 use bb_server::{
-  block,
-  world::{chunk::MultiChunk, gen::WorldGen},
+  config::Config,
+  world::{gen::WorldGen, MultiChunk, WorldManager},
 };
 use criterion::{criterion_group, criterion_main, Criterion};
 use pprof::criterion::{Output, PProfProfiler};
@@ -11,9 +11,10 @@ pub fn generate_chunk(c: &mut Criterion) {
   c.bench_function("single chunk", |b| {
     let mut x = 0_i32;
     let mut z = 0_i32;
-    let mut g = WorldGen::new();
-    let types = Arc::new(block::TypeConverter::new());
-    let mut c = MultiChunk::new(types);
+    let wm = Arc::new(WorldManager::new_with_config(Config::default()));
+    let config = wm.config().world.clone();
+    let g = WorldGen::from_config(&config);
+    let mut c = MultiChunk::new(wm, true, config.height, config.min_y);
     b.iter(move || {
       g.generate(ChunkPos::new(x, z), &mut c);
       x += 1;
@@ -6,15 +6,17 @@ use crate::{
   entity,
   event::EventFlow::{self, *},
   item::SharedInventory,
-  math::{Vec3, AABB},
+  math::AABB,
   player::{BlockClick, Player, Window},
   world::World,
 };
 use bb_common::{
   math::{FPos, Pos},
-  util::{Chat, Face},
+  net::cb,
+  util::{Chat, Face, GameMode},
 };
 use bb_transfer::{MessageRead, MessageWrite, MessageWriter};
+use parking_lot::{Mutex, MutexGuard};
 use std::{any::Any, sync::Arc};
 
 pub struct Log;
@@ -34,18 +36,37 @@ impl Behavior for Log {
   }
 }
 
+/// Sand, gravel, anvils, and anything else that falls when there is nothing
+/// below it.
 pub struct Falling;
-impl Behavior for Falling {
-  fn update_place(&self, world: &Arc<World>, block: Block) {
-    if let Ok(Kind::Air) = world.get_kind(block.pos.add_y(-1)) {
+impl Falling {
+  fn try_fall(&self, world: &Arc<World>, block: Block) {
+    if entity::behavior::can_fall_through(world, block.pos.add_y(-1)) {
       let _ = world.set_kind(block.pos, Kind::Air);
       world.summon_data(entity::Type::FallingBlock, block.pos.center(), block.ty.id() as i32);
     }
   }
+}
+impl Behavior for Falling {
+  fn update_place(&self, world: &Arc<World>, block: Block) { self.try_fall(world, block); }
   fn update(&self, world: &Arc<World>, block: Block, _: Block, _: Block) {
-    if world.get_kind(block.pos.add_y(-1)) == Ok(Kind::Air) {
+    self.try_fall(world, block);
+  }
+}
+
+/// Portal blocks break whenever a block next to them, in the same plane as the
+/// portal, is replaced with something other than obsidian or another portal
+/// block. This breaks the whole portal, one block at a time.
+pub struct NetherPortal;
+impl Behavior for NetherPortal {
+  fn update(&self, world: &Arc<World>, block: Block, _: Block, new: Block) {
+    let in_plane = if block.ty.prop("axis") == "x" {
+      new.pos.z == block.pos.z
+    } else {
+      new.pos.x == block.pos.x
+    };
+    if in_plane && !matches!(new.kind(), Kind::NetherPortal | Kind::Obsidian) {
       let _ = world.set_kind(block.pos, Kind::Air);
-      world.summon_data(entity::Type::FallingBlock, block.pos.center(), block.ty.id() as i32);
     }
   }
 }
@@ -141,6 +162,173 @@ impl TileEntity for ChestTE {
   fn as_any(&self) -> &dyn Any { self }
 }
 
+pub struct Spawner;
+/// A mob spawner. The settings are stored behind a lock, so that they can be
+/// changed by plugins, and so that the delay can be updated every tick.
+#[derive(Debug, Default)]
+pub struct SpawnerTE {
+  data: Mutex<SpawnerData>,
+}
+/// The settings for a mob spawner. The defaults match a vanilla spawner.
+#[derive(bb_macros::Transfer, Debug, Clone, PartialEq, Eq)]
+pub struct SpawnerData {
+  /// The name of the entity to spawn, like `zombie`. If this is empty, the
+  /// spawner will not spawn anything.
+  pub entity:       String,
+  /// The number of ticks until the next spawn.
+  pub delay:        i16,
+  /// After spawning, the delay is set to a random value between `min_delay`
+  /// and `max_delay` (inclusive).
+  pub min_delay:    i16,
+  pub max_delay:    i16,
+  /// The number of entities to try to spawn each time the delay runs out.
+  pub spawn_count:  u16,
+  /// Entities will spawn within this many blocks of the spawner on the X and
+  /// Z axis.
+  pub spawn_range:  u16,
+  /// The spawner is only active when a player is within this many blocks.
+  pub player_range: u16,
+  /// The spawner will stop spawning if there are this many entities of the
+  /// same type near it.
+  pub max_nearby:   u16,
+}
+impl Default for SpawnerData {
+  fn default() -> Self {
+    SpawnerData {
+      entity:       String::new(),
+      delay:        20,
+      min_delay:    200,
+      max_delay:    800,
+      spawn_count:  4,
+      spawn_range:  4,
+      player_range: 16,
+      max_nearby:   6,
+    }
+  }
+}
+impl SpawnerTE {
+  pub fn new(data: SpawnerData) -> Self { SpawnerTE { data: Mutex::new(data) } }
+  /// Returns the settings of this spawner. Any changes made here will not be
+  /// sent to clients; use [`World::set_spawner`] to change a spawner and
+  /// update clients.
+  pub fn data(&self) -> MutexGuard<'_, SpawnerData> { self.data.lock() }
+}
+impl Behavior for Spawner {
+  fn create_te(&self) -> Option<Arc<dyn TileEntity>> { Some(Arc::new(SpawnerTE::default())) }
+  fn load_te(
+    &self,
+    r: &mut bb_transfer::MessageReader,
+  ) -> Option<Result<Arc<dyn TileEntity>, bb_transfer::ReadError>> {
+    Some(match SpawnerData::read(r) {
+      Ok(v) => Ok(Arc::new(SpawnerTE::new(v))),
+      Err(e) => Err(e),
+    })
+  }
+}
+impl TileEntity for SpawnerTE {
+  fn save(&self, w: &mut MessageWriter<&mut Vec<u8>>) -> Result<(), bb_transfer::WriteError> {
+    self.data.lock().write(w)
+  }
+  fn as_any(&self) -> &dyn Any { self }
+  fn client_data(&self) -> Option<cb::BlockEntityKind> {
+    let data = self.data.lock();
+    Some(cb::BlockEntityKind::Spawner { entity: data.entity.clone(), delay: data.delay })
+  }
+}
+
+pub struct CommandBlock;
+/// A command block. Like spawners, the data is stored behind a lock so that it
+/// can be updated when the block is powered or run.
+#[derive(Debug, Default)]
+pub struct CommandBlockTE {
+  data: Mutex<CommandBlockData>,
+}
+/// The settings and state of a command block. The mode of a command block is
+/// stored in the block kind, and whether it is conditional is stored in the
+/// `conditional` property.
+#[derive(bb_macros::Transfer, Debug, Clone, Default, PartialEq)]
+pub struct CommandBlockData {
+  /// The command to run. This may start with a `/`.
+  pub command:       String,
+  /// If set, the command block acts like it is always powered.
+  pub auto:          bool,
+  /// If set, the last message sent by the command is stored in
+  /// `last_output`.
+  pub track_output:  bool,
+  /// The last message sent by the command.
+  pub last_output:   Option<Chat>,
+  /// 1 if the last command succeeded, and 0 otherwise. Conditional command
+  /// blocks only run if the command block behind them succeeded.
+  pub success_count: i32,
+  /// If set, the command block is receiving redstone power.
+  pub powered:       bool,
+  /// Set when an impulse command block is activated. The command will run on
+  /// the next tick.
+  pub pending:       bool,
+}
+impl CommandBlockTE {
+  pub fn new(data: CommandBlockData) -> Self { CommandBlockTE { data: Mutex::new(data) } }
+  /// Returns the data of this command block. Any changes made here will not
+  /// be sent to clients; use [`World::modify_command_block`] to change a
+  /// command block and update clients.
+  pub fn data(&self) -> MutexGuard<'_, CommandBlockData> { self.data.lock() }
+}
+impl Behavior for CommandBlock {
+  fn place<'a>(&self, data: &'a Data, _: Pos, click: BlockClick) -> TypeOrStore<'a> {
+    // Command blocks face away from the player.
+    let facing = match click.dir.as_face().opposite() {
+      Face::Top => "up",
+      Face::Bottom => "down",
+      Face::North => "north",
+      Face::South => "south",
+      Face::West => "west",
+      Face::East => "east",
+    };
+    data.default_type().with("facing", facing).into()
+  }
+  fn update_place(&self, world: &Arc<World>, block: Block) {
+    world.update_command_block_power(block.pos);
+  }
+  fn update(&self, world: &Arc<World>, block: Block, _: Block, _: Block) {
+    world.update_command_block_power(block.pos);
+  }
+  fn create_te(&self) -> Option<Arc<dyn TileEntity>> { Some(Arc::new(CommandBlockTE::default())) }
+  fn load_te(
+    &self,
+    r: &mut bb_transfer::MessageReader,
+  ) -> Option<Result<Arc<dyn TileEntity>, bb_transfer::ReadError>> {
+    Some(match CommandBlockData::read(r) {
+      Ok(v) => Ok(Arc::new(CommandBlockTE::new(v))),
+      Err(e) => Err(e),
+    })
+  }
+  fn interact(&self, _: Block, player: &Arc<Player>) -> EventFlow {
+    // The client opens the command block screen by itself, so we only need to
+    // make sure no block gets placed.
+    if player.game_mode() == GameMode::Creative {
+      Handled
+    } else {
+      Continue
+    }
+  }
+  fn drops(&self, _: Block) -> BlockDrops { BlockDrops::Custom(Drops::empty()) }
+}
+impl TileEntity for CommandBlockTE {
+  fn save(&self, w: &mut MessageWriter<&mut Vec<u8>>) -> Result<(), bb_transfer::WriteError> {
+    self.data.lock().write(w)
+  }
+  fn as_any(&self) -> &dyn Any { self }
+  fn client_data(&self) -> Option<cb::BlockEntityKind> {
+    let data = self.data.lock();
+    Some(cb::BlockEntityKind::CommandBlock {
+      command:      data.command.clone(),
+      auto:         data.auto,
+      track_output: data.track_output,
+      last_output:  data.last_output.clone(),
+    })
+  }
+}
+
 pub struct Trapdoor;
 impl Behavior for Trapdoor {
   fn place<'a>(&self, data: &'a Data, _: Pos, click: BlockClick) -> TypeOrStore<'a> {
@@ -191,9 +379,9 @@ pub struct Slab;
 impl Behavior for Slab {
   fn hitbox(&self, block: Block) -> AABB {
     match block.ty.prop("type").str() {
-      "top" => AABB::new(FPos::new(0.5, 0.5, 0.5), Vec3::new(1.0, 0.5, 1.0)),
-      "bottom" => AABB::new(FPos::new(0.5, 0.0, 0.5), Vec3::new(1.0, 0.5, 1.0)),
-      "double" => AABB::new(FPos::new(0.5, 0.0, 0.5), Vec3::new(1.0, 1.0, 1.0)),
+      "top" => AABB::new(FPos::new(0.5, 0.5, 0.5), FPos::new(1.0, 0.5, 1.0)),
+      "bottom" => AABB::new(FPos::new(0.5, 0.0, 0.5), FPos::new(1.0, 0.5, 1.0)),
+      "double" => AABB::new(FPos::new(0.5, 0.0, 0.5), FPos::new(1.0, 1.0, 1.0)),
       v => unreachable!("slab type {v}"),
     }
   }
@@ -2,16 +2,21 @@ use super::{Block, Data, Kind, TypeOrStore};
 use crate::{
   event::EventFlow,
   item::Stack,
-  math::{Vec3, AABB},
+  math::AABB,
   player::{BlockClick, Player},
   world::World,
 };
-use bb_common::math::{FPos, Pos};
+use bb_common::{
+  math::{FPos, Pos},
+  net::cb,
+};
 use bb_transfer::{MessageReader, MessageWriter};
 use std::{any::Any, sync::Arc};
 
 mod impls;
 
+pub use impls::{CommandBlockData, CommandBlockTE, SpawnerData, SpawnerTE};
+
 pub trait Behavior: Send + Sync {
   /// Called when a block is about to be placed.
   ///
@@ -75,18 +80,18 @@ pub trait Behavior: Send + Sync {
   /// position 0, 0, 0. AABBs are centered, so a full block hitbox would be
   /// this:
   /// ```rust
-  /// # use bb_server::math::{AABB, Vec3};
+  /// # use bb_server::math::AABB;
   /// # use bb_common::math::FPos;
-  /// AABB::new(FPos::new(0.5, 0.0, 0.5), Vec3::new(1.0, 1.0, 1.0));
+  /// AABB::new(FPos::new(0.5, 0.0, 0.5), FPos::new(1.0, 1.0, 1.0));
   /// ```
   fn hitbox(&self, block: Block) -> AABB {
     let data = block.world.block_converter().get(block.ty.kind());
     match data.bounding_box {
       super::ty::BoundingBoxKind::Block => {
-        AABB::new(FPos::new(0.5, 0.0, 0.5), Vec3::new(1.0, 1.0, 1.0))
+        AABB::new(FPos::new(0.5, 0.0, 0.5), FPos::new(1.0, 1.0, 1.0))
       }
       super::ty::BoundingBoxKind::Empty => {
-        AABB::new(FPos::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0))
+        AABB::new(FPos::new(0.0, 0.0, 0.0), FPos::new(0.0, 0.0, 0.0))
       }
     }
   }
@@ -98,6 +103,10 @@ impl Behavior for DefaultBehavior {}
 pub trait TileEntity: Any + Send + Sync {
   fn save(&self, w: &mut MessageWriter<&mut Vec<u8>>) -> Result<(), bb_transfer::WriteError>;
   fn as_any(&self) -> &dyn Any;
+  /// Returns the data that clients need to render this tile entity. This is
+  /// sent along with the chunk. Most tile entities (like chests) don't change
+  /// how the block looks, so this returns `None` by default.
+  fn client_data(&self) -> Option<cb::BlockEntityKind> { None }
 }
 
 #[derive(Default)]
@@ -136,12 +145,17 @@ impl BehaviorList {
       *wood*Slab | StoneSlab | SmoothStoneSlab => impls::Slab;
 
       Sand | RedSand | Gravel => impls::Falling;
+      Anvil | ChippedAnvil | DamagedAnvil => impls::Falling;
+      *color*ConcretePowder => impls::Falling;
 
       CraftingTable => impls::CraftingTable;
+      NetherPortal => impls::NetherPortal;
 
       *color*Bed => impls::Bed;
 
       Chest => impls::Chest;
+      Spawner => impls::Spawner;
+      CommandBlock | RepeatingCommandBlock | ChainCommandBlock => impls::CommandBlock;
 
       _ => DefaultBehavior;
     }
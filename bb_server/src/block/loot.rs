@@ -0,0 +1,154 @@
+//! Block loot tables. These control what a block drops when broken, based on
+//! the tool used to break it.
+
+use super::Data;
+use crate::{
+  enchantment, item,
+  item::{Stack, ToolType},
+};
+use rand::Rng;
+use std::str::FromStr;
+
+/// The loot table for a block. This is generated from the vanilla drops and
+/// tags, and is used alongside [`Data::drops`] to decide what a block drops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LootTable {
+  /// The tool that harvests this block fastest, along with the minimum tool
+  /// level needed.
+  pub harvest:       Option<Harvest>,
+  /// If set, this block will drop nothing unless it is broken with the correct
+  /// tool (see [`harvest`](Self::harvest)).
+  pub requires_tool: bool,
+  /// The item to drop when this block is broken with silk touch. If this is
+  /// `None`, silk touch does nothing.
+  pub silk_touch:    Option<&'static str>,
+  /// How the fortune enchantment changes the amount dropped.
+  pub fortune:       Fortune,
+}
+
+/// The tool needed to harvest a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Harvest {
+  pub tool:  ToolType,
+  /// The minimum [`mining_level`](item::ToolGrade::mining_level) of the tool.
+  pub level: u8,
+}
+
+/// The way fortune affects a drop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fortune {
+  /// Fortune does nothing.
+  None,
+  /// The vanilla ore formula. The drop count is multiplied by a random number
+  /// from 1 to `level + 1`, weighted towards 1.
+  Ore,
+  /// Adds a random number from 0 to `level` to the drop count, and then caps
+  /// the result at `max`. This is used for things like glowstone.
+  Uniform { max: i32 },
+}
+
+impl LootTable {
+  /// Returns `true` if breaking a block with the given tool will produce
+  /// drops.
+  pub fn can_harvest(&self, tool: &Stack) -> bool {
+    if !self.requires_tool {
+      return true;
+    }
+    match (self.harvest, tool.item().tool()) {
+      (Some(harvest), Some(tool)) => {
+        tool.ty() == harvest.tool && tool.grade().mining_level() >= harvest.level
+      }
+      _ => false,
+    }
+  }
+
+  /// Rolls the drops for the given block. If `tool` is `None`, the block is
+  /// treated as harvested with no enchantments.
+  pub fn roll(&self, data: &Data, tool: Option<&Stack>, rng: &mut impl Rng) -> Vec<Stack> {
+    let (silk_touch, fortune) = match tool {
+      Some(tool) => {
+        if !self.can_harvest(tool) {
+          return vec![];
+        }
+        (
+          tool.enchantment(enchantment::Type::SilkTouch) > 0,
+          tool.enchantment(enchantment::Type::Fortune) as i32,
+        )
+      }
+      None => (false, 0),
+    };
+    if silk_touch {
+      if let Some(it) = self.silk_touch.and_then(|name| item::Type::from_str(name).ok()) {
+        return vec![Stack::new(it)];
+      }
+    }
+    data
+      .drops
+      .iter()
+      .filter_map(|drop| {
+        let it = item::Type::from_str(drop.item).ok()?;
+        let mut count =
+          if drop.max > drop.min { rng.gen_range(drop.min..=drop.max) } else { drop.min };
+        count = self.fortune.apply(count, fortune, rng);
+        if count <= 0 {
+          None
+        } else {
+          Some(Stack::new(it).with_amount(count.min(64) as u8))
+        }
+      })
+      .collect()
+  }
+}
+
+impl Fortune {
+  /// Applies this fortune bonus to the given count, with the given fortune
+  /// level.
+  pub fn apply(&self, count: i32, level: i32, rng: &mut impl Rng) -> i32 {
+    if level <= 0 {
+      return count;
+    }
+    match self {
+      Fortune::None => count,
+      Fortune::Ore => {
+        let bonus = (rng.gen_range(0..level + 2) - 1).max(0);
+        count * (bonus + 1)
+      }
+      Fortune::Uniform { max } => (count + rng.gen_range(0..=level)).min(*max),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{super::TypeConverter, *};
+  use crate::block::Kind;
+  use rand::{rngs::StdRng, SeedableRng};
+
+  #[test]
+  fn can_harvest() {
+    let conv = TypeConverter::new();
+    let stone = conv.get(Kind::Stone);
+    assert!(!stone.loot.can_harvest(&Stack::empty()));
+    assert!(stone.loot.can_harvest(&Stack::new(item::Type::WoodenPickaxe)));
+    assert!(!stone.loot.can_harvest(&Stack::new(item::Type::WoodenShovel)));
+
+    let diamond = conv.get(Kind::DiamondOre);
+    assert!(!diamond.loot.can_harvest(&Stack::new(item::Type::StonePickaxe)));
+    assert!(diamond.loot.can_harvest(&Stack::new(item::Type::IronPickaxe)));
+
+    let dirt = conv.get(Kind::Dirt);
+    assert!(dirt.loot.can_harvest(&Stack::empty()));
+  }
+
+  #[test]
+  fn fortune() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..100 {
+      let count = Fortune::Ore.apply(1, 3, &mut rng);
+      assert!((1..=4).contains(&count));
+      let count = Fortune::Uniform { max: 4 }.apply(2, 3, &mut rng);
+      assert!((2..=4).contains(&count));
+    }
+    assert_eq!(Fortune::Ore.apply(1, 0, &mut rng), 1);
+  }
+}
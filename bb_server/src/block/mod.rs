@@ -1,19 +1,26 @@
 mod behavior;
 mod custom;
 pub mod light;
+mod loot;
 mod material;
 mod store;
 mod ty;
 mod version;
 
-#[cfg(feature = "wasm_plugins")]
+#[cfg(feature = "wasm_host")]
 mod ffi;
 
-pub use behavior::{Behavior, BlockDrops, TileEntity};
+pub use behavior::{
+  Behavior, BlockDrops, CommandBlockData, CommandBlockTE, SpawnerData, SpawnerTE, TileEntity,
+};
 pub use custom::{CustomBlockBuilder, CustomData, CustomKind, CustomProp, CustomPropValue};
+pub use loot::{Fortune, Harvest, LootTable};
 pub use material::Material;
 pub use store::TypeStore;
-pub use ty::{Data, ItemDrop, Kind, Prop, PropKind, PropValue, PropValueStore, Type};
+pub use ty::{
+  BoundingBoxKind, Data, InvalidBlock, ItemDrop, Kind, Prop, PropKind, PropValue, PropValueStore,
+  Type,
+};
 pub use version::TypeConverter;
 
 use crate::world::World;
@@ -1,4 +1,5 @@
-use super::{CustomKind, Material, TypeStore};
+use super::{CustomKind, Fortune, Harvest, LootTable, Material, TypeStore};
+use crate::item::ToolType;
 use std::{collections::HashMap, error::Error, fmt, str::FromStr};
 
 pub(super) const STATE_PROPS_LEN: usize = 8;
@@ -128,6 +129,34 @@ impl Type<'_> {
     }
     Ok(())
   }
+  /// Sets the property `name` from a string, like the `north` in
+  /// `facing=north`. The string is parsed based on the kind of the property.
+  /// Returns `false` if the property doesn't exist, or if the value is invalid
+  /// for that property.
+  pub fn set_prop_str(&mut self, name: &str, val: &str) -> bool {
+    let idx = match self.props.iter().position(|p| p.name == name) {
+      Some(idx) => idx,
+      None => return false,
+    };
+    let id = match self.props[idx].kind {
+      PropKind::Bool => match val {
+        "true" => Some(0),
+        "false" => Some(1),
+        _ => None,
+      },
+      PropKind::Enum(variants) => variants.iter().position(|v| *v == val).map(|i| i as u32),
+      PropKind::Int { min, max } => {
+        val.parse::<u32>().ok().filter(|v| *v >= min && *v <= max).map(|v| v - min)
+      }
+    };
+    match id {
+      Some(id) => {
+        self.state_props[idx] = id;
+        true
+      }
+      None => false,
+    }
+  }
   pub fn with<'a>(mut self, name: &str, val: impl Into<PropValue<'a>>) -> Self {
     self.set_prop(name, val);
     self
@@ -205,6 +234,9 @@ pub struct Data {
   pub resistance:   f32,
   /// A list of item ids this block can drop.
   pub drops:        &'static [ItemDrop],
+  /// Controls which tools can harvest this block, and how silk touch and
+  /// fortune change the [`drops`](Self::drops).
+  pub loot:         LootTable,
   /// If this is true, then clients can (at least partially) see through this
   /// block.
   pub transparent:  bool,
@@ -388,6 +420,12 @@ impl Data {
     Type { kind: self.kind, state: self.state, props: self.props, state_props }
   }
 
+  /// Returns `true` if this block has the given vanilla tag. The tag should
+  /// not start with `#` or `minecraft:`, so it looks like `mineable/pickaxe`.
+  pub fn has_tag(&self, tag: &str) -> bool {
+    self.tags.iter().any(|t| t.strip_prefix('#').unwrap_or(t) == tag)
+  }
+
   /// Returns the type
   pub fn type_from_id(&self, mut id: u32) -> Type {
     let mut state_props = [0; STATE_PROPS_LEN];
@@ -1,4 +1,4 @@
-use super::{ty, CustomData, CustomKind, Data, Kind, Type};
+use super::{ty, CustomData, CustomKind, Data, InvalidBlock, Kind, Type};
 use bb_common::version::BlockVersion;
 
 /// This is the conversion table for a single old version of the game and the
@@ -122,6 +122,10 @@ impl TypeConverter {
   pub fn get_custom(&self, k: CustomKind) -> &CustomData {
     &self.custom_kinds[k.kind_id() as usize]
   }
+  /// Returns the data for every vanilla block kind, in id order.
+  pub fn all(&self) -> &[Data] { self.kinds }
+  /// Returns the number of vanilla block states in the latest version.
+  pub fn vanilla_states(&self) -> u32 { self.block_states.len() as u32 }
 
   pub fn ty(&self, kind: Kind) -> Type {
     let data = self.get(kind);
@@ -144,6 +148,25 @@ impl TypeConverter {
     data.type_from_id(id - data.state)
   }
 
+  /// Parses a block state string, like `minecraft:oak_stairs[facing=north]`.
+  /// This is the format used by commands and schematic palettes. Any
+  /// properties that aren't listed will use the default for that block, and
+  /// any invalid properties are ignored.
+  pub fn parse_type(&self, s: &str) -> Result<Type, InvalidBlock> {
+    let s = s.strip_prefix("minecraft:").unwrap_or(s);
+    let (name, props) = match s.split_once('[') {
+      Some((name, props)) => (name, props.strip_suffix(']').unwrap_or(props)),
+      None => (s, ""),
+    };
+    let mut ty = self.get(name.parse()?).default_type();
+    for prop in props.split(',') {
+      if let Some((key, val)) = prop.split_once('=') {
+        ty.set_prop_str(key.trim(), val.trim());
+      }
+    }
+    Ok(ty)
+  }
+
   /// Gets a block kind from the given id.
   pub fn kind_from_id(&self, mut id: u32, ver: BlockVersion) -> Kind {
     if ver != BlockVersion::latest() {
@@ -165,6 +188,19 @@ impl TypeConverter {
 mod tests {
   use super::*;
 
+  #[test]
+  fn parse_type() {
+    let conv = TypeConverter::new();
+    let ty = conv.parse_type("minecraft:oak_stairs[facing=east,half=top]").unwrap();
+    assert_eq!(ty.kind(), Kind::OakStairs);
+    assert_eq!(ty.prop("facing"), "east");
+    assert_eq!(ty.to_string(), "oak_stairs[facing=east,half=top,shape=straight,waterlogged=false]");
+    // Invalid properties are ignored.
+    let ty = conv.parse_type("oak_stairs[facing=up,foo=bar]").unwrap();
+    assert_eq!(ty, conv.get(Kind::OakStairs).default_type());
+    assert!(conv.parse_type("minecraft:not_a_block").is_err());
+  }
+
   #[test]
   fn test_convert() {
     let conv = TypeConverter::new();
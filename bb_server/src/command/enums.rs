@@ -56,7 +56,8 @@ pub enum Parser {
   GameProfile,
   /// location, represented as 3 numbers (which must be integers)
   BlockPos,
-  /// column location, represented as 3 numbers (which must be integers)
+  /// column location, represented as 2 numbers (which must be integers). This
+  /// is parsed into the chunk containing the column.
   ColumnPos,
   /// A location, represented as 3 numbers
   Vec3,
@@ -253,6 +254,7 @@ impl Arg {
   unwrapper_copy!(float, Float, f32);
   unwrapper_copy!(int, Int, i32);
   unwrapper_copy!(pos, BlockPos, Pos);
+  unwrapper_copy!(column, ColumnPos, ChunkPos);
   pub fn lit(&self) -> &str {
     match self {
       Arg::Literal(v) => v,
@@ -23,7 +23,7 @@ pub mod reader;
 mod sender;
 mod serialize;
 
-#[cfg(feature = "wasm_plugins")]
+#[cfg(feature = "wasm_host")]
 mod ffi;
 
 pub use enums::{Arg, EntitySelector, Parser, StringType};
@@ -64,12 +64,20 @@ impl CommandTree {
   /// Called whenever a command should be executed. This can also be used to act
   /// like a player sent a command, even if they didn't. The text passed in
   /// should not contain a `/` at the start.
-  pub fn execute<S: CommandSender>(&self, world: &Arc<WorldManager>, sender: &mut S, text: &str) {
+  ///
+  /// Returns `true` if the command was run. This will return `false` if the
+  /// command doesn't exist, couldn't be parsed, or was cancelled by a plugin.
+  pub fn execute<S: CommandSender>(
+    &self,
+    world: &Arc<WorldManager>,
+    sender: &mut S,
+    text: &str,
+  ) -> bool {
     let mut reader = CommandReader::new(text);
     let commands = self.commands.lock();
     let command_name = match reader.word(StringType::Word) {
       Ok(v) => v,
-      Err(_) => return,
+      Err(_) => return false,
     };
     let (command, handler) = match &commands.get(&command_name) {
       Some(v) => v,
@@ -79,7 +87,7 @@ impl CommandTree {
         msg.add("Unknown command: ").color(Color::Red);
         msg.add(text);
         sender.send_message(msg);
-        return;
+        return false;
       }
     };
     let args = match command.parse(text, sender) {
@@ -87,7 +95,7 @@ impl CommandTree {
       Err(e) => {
         let format = sender.error_format();
         sender.send_message(e.to_chat(text, format));
-        return;
+        return false;
       }
     };
     if let Some(player) = sender.as_player() {
@@ -98,16 +106,17 @@ impl CommandTree {
           args:   args
             .iter()
             .map(|arg| {
-              types::command::sl_from_arg(arg.clone(), player.world(), Some(player)).into()
+              types::command::sl_from_arg(arg.clone(), &player.world(), Some(player)).into()
             })
             .collect(),
         })
         .is_handled()
       {
-        return;
+        return false;
       }
     }
     handler(world, sender.as_player(), args);
+    true
   }
 }
 
@@ -138,7 +147,7 @@ impl Command {
   pub fn new<N: Into<String>>(name: N) -> Self { Self::lit(name.into()) }
   /// Creates a new command, with all the given parameters. This is used when
   /// interacting with plugins.
-  #[cfg(feature = "wasm_plugins")]
+  #[cfg(feature = "wasm_host")]
   pub(crate) fn new_from_plugin(
     name: String,
     ty: NodeType,
@@ -152,6 +161,12 @@ impl Command {
   fn lit(name: String) -> Self {
     Command { name, ty: NodeType::Literal, children: vec![], optional: false }
   }
+  /// Creates a new optional literal node. Use
+  /// [`add_lit_opt`](Self::add_lit_opt) if you want to add an optional literal
+  /// node to the current command.
+  fn lit_opt(name: String) -> Self {
+    Command { name, ty: NodeType::Literal, children: vec![], optional: true }
+  }
   /// Creates a new argument node. Use [`add_arg`](Self::add_arg) if you want to
   /// add an argument node to the current command.
   fn arg(name: String, parser: Parser) -> Self {
@@ -201,6 +216,13 @@ impl Command {
     let index = self.children.len() - 1;
     self.children.get_mut(index).unwrap()
   }
+  /// Adds an optional literal. This is the same as [`add_lit`](Self::add_lit),
+  /// but allows the command to be parsed without this literal.
+  pub fn add_lit_opt(&mut self, name: &str) -> &mut Command {
+    self.children.push(Command::lit_opt(name.into()));
+    let index = self.children.len() - 1;
+    self.children.get_mut(index).unwrap()
+  }
   /// Adds a new argument to this command. Unlike literal arguments, the name
   /// does not matter here. It will only be show to the client when they are
   /// autocompleting the command. Other than that, it is never used. The
@@ -409,4 +431,31 @@ pub mod tests {
     );
     Ok(())
   }
+
+  #[test]
+  fn parse_optional_lit() -> Result<(), ParseError> {
+    let mut c = Command::new("fill");
+    let block = c
+      .add_arg("min", Parser::BlockPos)
+      .add_arg("max", Parser::BlockPos)
+      .add_arg("block", Parser::BlockState);
+    block.add_lit_opt("keep");
+    block.add_lit_opt("replace").add_arg_opt("filter", Parser::BlockState);
+    let start = vec![
+      Arg::Literal("fill".into()),
+      Arg::BlockPos(Pos::new(0, 0, 0)),
+      Arg::BlockPos(Pos::new(1, 1, 1)),
+      Arg::BlockState(block::Kind::Stone, HashMap::new(), None),
+    ];
+    assert_eq!(c.parse("fill 0 0 0 1 1 1 stone", &NoneSender {})?, start);
+    let mut expected = start.clone();
+    expected.push(Arg::Literal("keep".into()));
+    assert_eq!(c.parse("fill 0 0 0 1 1 1 stone keep", &NoneSender {})?, expected);
+    let mut expected = start;
+    expected.push(Arg::Literal("replace".into()));
+    expected.push(Arg::BlockState(block::Kind::Dirt, HashMap::new(), None));
+    assert_eq!(c.parse("fill 0 0 0 1 1 1 stone replace dirt", &NoneSender {})?, expected);
+    assert!(c.parse("fill 0 0 0 1 1 1 stone outline", &NoneSender {}).is_err());
+    Ok(())
+  }
 }
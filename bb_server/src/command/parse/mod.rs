@@ -111,6 +111,24 @@ impl Parser {
           Ok(Arg::BlockPos(Pos::new(x, y, z)))
         }
       }
+      Self::ColumnPos => {
+        // Relative coordinates are only allowed if the sender has a position.
+        let origin = sender.block_pos();
+        let mut coord = |base: Option<i32>| -> Result<i32> {
+          let mut w = tokens.read_spaced_text()?;
+          match base {
+            Some(base) if w.starts_with('~') => {
+              w.set_text(w[1..].to_string());
+              Ok(if w.is_empty() { base } else { base + parse_num::<i32>(&w, &None, &None)? })
+            }
+            _ => parse_num(&w, &None, &None),
+          }
+        };
+        let x = coord(origin.map(|p| p.x()))?;
+        let z = coord(origin.map(|p| p.z()))?;
+        // Columns are only used to find chunks, so the chunk is stored.
+        Ok(Arg::ColumnPos(Pos::new(x, 0, z).chunk()))
+      }
       Self::BlockState => {
         let w = tokens.read_spaced_word()?;
         Ok(Arg::BlockState(
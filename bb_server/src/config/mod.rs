@@ -1,4 +1,4 @@
-use bb_common::{math::FPos, util::GameMode};
+use bb_common::{logger::FileConfig, math::FPos, util::GameMode};
 use bb_macros::{Config, Default};
 use log::LevelFilter;
 
@@ -17,11 +17,52 @@ pub struct Config {
   /// packets with that name.
   pub log_packets: Vec<String>,
 
+  /// If set, logs will be written as JSON lines instead of plain text. This
+  /// is easier to ingest into tools like Loki or ELK.
+  #[default(false)]
+  pub log_json: bool,
+  /// Settings for writing logs to a file.
+  pub log_file: LogFileConfig,
+
+  /// Players who are sent warnings and errors in chat, and who can use
+  /// `/logs`. Each entry is either a username or a UUID.
+  pub log_forward:       Vec<String>,
+  /// The lowest level of logs sent to the players in `log-forward`.
+  #[default(LevelFilter::Warn)]
+  pub log_forward_level: LevelFilter,
+
   /// The address the server will listen for connections on. Vanilla clients
   /// cannot connect to this address! The proxy must be configured to connect
   /// to this address instead.
   #[default("0.0.0.0:8483".into())]
-  pub address: String,
+  pub address:           String,
+  /// How long a player is held onto after the connection to the proxy drops,
+  /// in seconds. If the proxy reconnects within this time, the player carries
+  /// on where they left off. This should be longer than the proxy's
+  /// `link-grace-secs`. Set to 0 to remove players as soon as the connection
+  /// drops.
+  #[default(15)]
+  pub link_grace_secs:   u64,
+  /// The proxy sends a heartbeat every few seconds. If nothing is received
+  /// from the proxy for this many seconds, the connection is assumed to have
+  /// dropped.
+  #[default(20)]
+  pub link_timeout_secs: u64,
+
+  /// The maximum number of players that can be online at once. Players who
+  /// join once the server is full are disconnected, or held in the proxy's
+  /// queue if it has `queue` enabled. Set to 0 for no limit.
+  #[default(20)]
+  pub max_players: u32,
+  /// Players who can join even when the server is full. Each entry is either
+  /// a username or a UUID.
+  pub full_bypass: Vec<String>,
+
+  /// The ids of mods that clients must have installed to join, like `jei`.
+  /// Only Forge clients send their mod list, and only when the proxy has
+  /// `forge-mod-list` enabled, so if this is set, every other client will be
+  /// kicked.
+  pub required_mods: Vec<String>,
 
   /// The default view distance. Note that this can be changed for a single
   /// player via a plugin at runtime.
@@ -57,11 +98,42 @@ pub struct Config {
   #[default(true)]
   pub leave_messages: bool,
 
+  /// The format of chat messages. `%player%`, `%team_prefix%`,
+  /// `%team_postfix%`, `%world%` and `%message%` will be replaced with the
+  /// values for the player who sent the message.
+  #[default("<%team_prefix%%player%%team_postfix%> %message%".into())]
+  pub chat_format: String,
+
+  /// The maximum number of blocks that a single `/fill` or `/clone` command
+  /// can change. This is the same as vanilla's default.
+  #[default(32768)]
+  pub max_fill_volume:   u32,
+  /// The maximum amount of memory used to store each player's edit history,
+  /// in kilobytes. Once this is full, the oldest edits can no longer be
+  /// undone.
+  #[default(16384)]
+  pub edit_history_size: u32,
+
   /// The path for the vanilla data directory. If not found, an error will be
   /// logged, and there will be no crafting recipes.
   #[default("data/".into())]
   pub data_path: String,
 
+  /// The path to the ban list. This is a json file, which is written every
+  /// time someone is banned or pardoned. The proxy should be pointed at the
+  /// same file.
+  #[default("bans.json".into())]
+  pub bans:      String,
+  /// The path to the whitelist. This also stores whether the whitelist and
+  /// maintenance mode are enabled, which can be changed with `/whitelist` and
+  /// `/maintenance`. The proxy should be pointed at the same file.
+  #[default("whitelist.json".into())]
+  pub whitelist: String,
+
+  /// Configs for the profile cache, which is used to find the UUIDs of
+  /// players who aren't online.
+  pub profiles: ProfileConfig,
+
   /// Toggle debug info in the player list.
   #[default(true)]
   pub debug_playerlist: bool,
@@ -70,10 +142,54 @@ pub struct Config {
   /// to execute commands on the server.
   pub rcon: RconConfig,
 
+  /// Configs for clients that can't keep up with the packets they are sent.
+  pub backpressure: BackpressureConfig,
+
+  /// Configs for how far players can reach when breaking blocks, placing
+  /// blocks, and attacking entities.
+  pub reach: ReachConfig,
+
   /// Configs for world generation/loading.
   pub world: WorldConfig,
 }
 
+/// Settings for writing logs to a file.
+#[derive(Clone, Debug, Config, Default, PartialEq)]
+pub struct LogFileConfig {
+  /// If set, logs will be written to `path` as well as the console.
+  #[default(false)]
+  pub enabled:       bool,
+  /// The log file. Old logs are moved into the same directory, and are named
+  /// by the date they were rotated, like `2022-06-14-1.log.gz`.
+  #[default("logs/latest.log".into())]
+  pub path:          String,
+  /// Once the log file is this many kilobytes, it is rotated. Set to 0 to
+  /// never rotate the log because of its size.
+  #[default(10240)]
+  pub max_size_kb:   u64,
+  /// Once the log file has been open for this many hours, it is rotated. Set
+  /// to 0 to never rotate the log because of its age.
+  #[default(24)]
+  pub max_age_hours: u64,
+  /// The number of old logs to keep. Set to 0 to keep all of them.
+  #[default(10)]
+  pub keep:          u32,
+  /// If set, old logs will be compressed with gzip.
+  #[default(true)]
+  pub gzip:          bool,
+}
+
+impl LogFileConfig {
+  /// Returns the settings for the logger, or `None` if logging to a file is
+  /// disabled.
+  pub fn to_logger(&self) -> Option<FileConfig> {
+    if !self.enabled {
+      return None;
+    }
+    Some(FileConfig::new(&self.path, self.max_size_kb, self.max_age_hours, self.keep, self.gzip))
+  }
+}
+
 #[derive(Clone, Debug, Config, Default, PartialEq)]
 pub struct RconConfig {
   /// By default this is disabled. This is for security. Anyone with access
@@ -92,8 +208,66 @@ pub struct RconConfig {
   pub password: String,
 }
 
+#[derive(Clone, Debug, Config, Default, PartialEq)]
+pub struct ProfileConfig {
+  /// This should be the same as `online-mode` in the proxy. If set, names
+  /// that aren't cached are looked up with the Mojang API. If not set,
+  /// names are converted to offline UUIDs, which are the same UUIDs that the
+  /// proxy gives players in offline mode.
+  #[default(true)]
+  pub online_mode:         bool,
+  /// The path to the profile cache. Every player who joins is stored here.
+  #[default("usercache.json".into())]
+  pub cache:               String,
+  /// Cached profiles are looked up again after this many days. Set to 0 to
+  /// keep profiles forever.
+  #[default(30)]
+  pub expire_days:         u32,
+  /// The maximum number of requests made to the Mojang API each minute.
+  /// Mojang allows about 60 per minute from a single IP.
+  #[default(60)]
+  pub requests_per_minute: u32,
+  /// If set, players who join without a skin are given the skin of the
+  /// Mojang account with the same name. This is only useful in offline mode.
+  #[default(false)]
+  pub offline_skins:       bool,
+}
+
+#[derive(Clone, Debug, Config, Default, PartialEq)]
+pub struct BackpressureConfig {
+  /// Once this many bytes are waiting to be sent to a client, that client is
+  /// behind. Chunk resends will be dropped for clients that are behind, and
+  /// particles will be dropped once twice this many bytes are waiting.
+  #[default(4 * 1024 * 1024)]
+  pub buffer_size: u32,
+  /// If a client is behind for this many seconds, they will be kicked. Set
+  /// this to 0 to never kick anyone.
+  #[default(30)]
+  pub kick_after:  u32,
+}
+
+#[derive(Clone, Debug, Config, Default, PartialEq)]
+pub struct ReachConfig {
+  /// If this is disabled, players can break blocks, place blocks, and attack
+  /// entities at any distance.
+  #[default(true)]
+  pub enabled:   bool,
+  /// How much further than vanilla players can reach, in blocks. Players in
+  /// survival can reach blocks 4.5 blocks away, and entities 3 blocks away.
+  /// In creative, this is 5 blocks for blocks, and 6 blocks for entities.
+  ///
+  /// The server sees players a little behind where they actually are, so
+  /// this should be high enough to cover a few ticks of movement.
+  #[default(1.0)]
+  pub tolerance: f64,
+}
+
 #[derive(Clone, Debug, Config, Default, PartialEq)]
 pub struct WorldConfig {
+  /// The name of this world. This is shown in chat with the `%world%`
+  /// placeholder.
+  #[default("world".into())]
+  pub name:   String,
   /// If set, the world cannot be modified. This can be used in minigame
   /// lobbies, for example.
   #[default(false)]
@@ -101,6 +275,35 @@ pub struct WorldConfig {
   /// If set, the world will be saved to disk.
   #[default(true)]
   pub save:   bool,
+  /// The directory that this world's regions are saved in.
+  #[default("world/chunks".into())]
+  pub chunks: String,
+
+  /// If set, players can protect areas of this world with `/claim`. Claims
+  /// are stored in `world/claims/<name>.json`.
+  #[default(false)]
+  pub claims: bool,
+
+  /// If set, mobs will spawn naturally around players, and despawn when they
+  /// get too far away.
+  #[default(true)]
+  pub mob_spawning: bool,
+
+  /// The number of times this world ticks every second. This can be changed
+  /// while the server is running with `/tick rate`.
+  #[default(20.0)]
+  pub tick_rate:         f64,
+  /// If the world falls behind (because of a slow tick, for example), the
+  /// next ticks run right away until it has caught up. If it falls more than
+  /// this many ticks behind, the missed ticks are skipped instead. Set to 0 to
+  /// skip any missed ticks.
+  #[default(20)]
+  pub max_catch_up:      u32,
+  /// If set, mob spawning and mob ticking will run less often when ticks get
+  /// close to taking too long, and go back to normal once the load drops.
+  /// The current throttle level is shown in `/tps`.
+  #[default(false)]
+  pub adaptive_throttle: bool,
 
   /// Generation settings
 
@@ -110,11 +313,17 @@ pub struct WorldConfig {
   /// If set, the whole world will be void.
   #[default(false)]
   pub void:      bool,
-  /// This can be set to change the world's terrain generator. Generators are
-  /// added by plugins. If the generator is not present, the server will fail
-  /// to load.
+  /// This can be set to change the world's terrain generator. `flat` and
+  /// `void` are built in, and are configured in `[world.flat]`. Any other
+  /// generators are added by plugins. If the generator is not present, the
+  /// server will fail to load.
   #[default("".into())]
   pub generator: String,
+  /// The seed for this world. Terrain generation, mob spawning, and loot are
+  /// all based on this seed, so two worlds with the same seed and the same
+  /// plugins will generate the same terrain.
+  #[default(3210471203948712039)]
+  pub seed:      i64,
 
   /// The height of this world. This is 1 block larger than the maximum block.
   #[default(256)]
@@ -123,6 +332,9 @@ pub struct WorldConfig {
   #[default(0)]
   pub min_y:  i32,
 
+  /// Superflat settings. These are only used if `generator` is `flat`.
+  pub flat: FlatConfig,
+
   /// Vanilla world loading settings
   pub vanilla: VanillaConfig,
 
@@ -130,6 +342,17 @@ pub struct WorldConfig {
   pub schematic: SchematicConfig,
 }
 
+#[derive(Clone, Debug, Config, Default, PartialEq)]
+pub struct FlatConfig {
+  /// The layers of the world, from the bottom up. This is a list of blocks,
+  /// where each block can be prefixed with a count, like `2*dirt`.
+  #[default("bedrock,2*dirt,grass_block".into())]
+  pub layers: String,
+  /// The biome of the entire world. This controls which mobs spawn.
+  #[default("plains".into())]
+  pub biome:  String,
+}
+
 #[derive(Clone, Debug, Config, Default, PartialEq)]
 pub struct VanillaConfig {
   /// If set, then the world will be a void world, and a vanilla world will
@@ -0,0 +1,129 @@
+//! Writes all the generated registries to json files. This is used by
+//! `--dump-registries`, and is meant for external tools, plugin authors, and
+//! for debugging the version converters.
+//!
+//! Each file lists every vanilla entry in the latest version, along with the
+//! id it maps to in every older version. Custom blocks from plugins are not
+//! included.
+
+use crate::{block, entity, item, particle};
+use bb_common::version::BlockVersion;
+use serde_json::{json, Map, Value};
+use std::{fs, io, path::Path};
+
+/// Writes `blocks.json`, `items.json`, `entities.json`, and `particles.json`
+/// into the given directory. The directory is created if it doesn't exist.
+pub fn dump_registries(dir: &Path) -> io::Result<()> {
+  fs::create_dir_all(dir)?;
+  write(dir, "blocks", blocks(&block::TypeConverter::new()))?;
+  write(dir, "items", items(&item::TypeConverter::new()))?;
+  write(dir, "entities", entities(&entity::TypeConverter::new()))?;
+  write(dir, "particles", particles(&particle::TypeConverter::new()))?;
+  Ok(())
+}
+
+fn write(dir: &Path, name: &str, value: Value) -> io::Result<()> {
+  let path = dir.join(format!("{name}.json"));
+  fs::write(&path, serde_json::to_string_pretty(&value)?)?;
+  info!("wrote {}", path.display());
+  Ok(())
+}
+
+/// Returns every supported block version, oldest first.
+fn versions() -> impl Iterator<Item = BlockVersion> {
+  (0..BlockVersion::len()).map(BlockVersion::from_index)
+}
+
+/// Builds a map of version names to the id returned by `f` for that version.
+/// Versions where `f` returns `None` are left out.
+fn by_version(mut f: impl FnMut(BlockVersion) -> Option<u32>) -> Value {
+  let mut out = Map::new();
+  for ver in versions() {
+    if let Some(id) = f(ver) {
+      out.insert(ver.protocol().to_string(), id.into());
+    }
+  }
+  out.into()
+}
+
+fn blocks(conv: &block::TypeConverter) -> Value {
+  let kinds = conv.all();
+  let blocks: Vec<_> = kinds
+    .iter()
+    .enumerate()
+    .map(|(i, data)| {
+      let end = kinds.get(i + 1).map(|d| d.state).unwrap_or_else(|| conv.vanilla_states());
+      let states: Vec<_> = (data.state..end)
+        .map(|id| {
+          json!({
+            "id": id,
+            "props": data.type_from_id(id - data.state).props(),
+            "versions": by_version(|ver| Some(conv.to_old(id, ver))),
+          })
+        })
+        .collect();
+      json!({
+        "id": data.kind.id(),
+        "name": data.name,
+        "hardness": data.hardness,
+        "resistance": data.resistance,
+        "transparent": data.transparent,
+        "filter_light": data.filter_light,
+        "emit_light": data.emit_light,
+        "tags": data.tags,
+        "default_state": data.default_type().id(),
+        "states": states,
+      })
+    })
+    .collect();
+  json!({ "blocks": blocks })
+}
+
+fn items(conv: &item::TypeConverter) -> Value {
+  let items: Vec<_> = conv
+    .all()
+    .iter()
+    .map(|data| {
+      let mut versions = Map::new();
+      for ver in versions() {
+        let (id, damage) = conv.to_old(data.id(), ver);
+        versions.insert(ver.protocol().to_string(), json!({ "id": id, "damage": damage }));
+      }
+      json!({ "id": data.id(), "name": data.name(), "versions": versions })
+    })
+    .collect();
+  json!({ "items": items })
+}
+
+fn entities(conv: &entity::TypeConverter) -> Value {
+  let entities: Vec<_> = conv
+    .all()
+    .iter()
+    .map(|data| {
+      json!({
+        "id": data.id,
+        "name": data.name,
+        "width": data.width,
+        "height": data.height,
+        "category": format!("{:?}", data.category),
+        "versions": by_version(|ver| Some(conv.to_old(data.id, ver))),
+      })
+    })
+    .collect();
+  json!({ "entities": entities })
+}
+
+fn particles(conv: &particle::TypeConverter) -> Value {
+  let particles: Vec<_> = conv
+    .all()
+    .iter()
+    .map(|data| {
+      json!({
+        "id": data.id(),
+        "name": data.name(),
+        "versions": by_version(|ver| conv.to_old(data.id(), ver)),
+      })
+    })
+    .collect();
+  json!({ "particles": particles })
+}
@@ -0,0 +1,185 @@
+//! Armor stand poses and flags. These are stored in metadata, and only change
+//! how armor stands are rendered.
+
+use super::{EntityData, Equipment};
+use crate::item::Stack;
+use bb_common::{
+  metadata::{Field, Metadata},
+  net::cb::{ArmorSlot, EquipmentSlot},
+  util::Hand,
+};
+
+/// A part of an armor stand that can be rotated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmorStandPart {
+  Head,
+  Body,
+  LeftArm,
+  RightArm,
+  LeftLeg,
+  RightLeg,
+}
+
+impl ArmorStandPart {
+  /// Parses a part name, like `left_arm`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "head" => Self::Head,
+      "body" => Self::Body,
+      "left_arm" => Self::LeftArm,
+      "right_arm" => Self::RightArm,
+      "left_leg" => Self::LeftLeg,
+      "right_leg" => Self::RightLeg,
+      _ => return None,
+    })
+  }
+  /// Returns the rotation of this part on a newly spawned armor stand, in
+  /// degrees.
+  pub fn default_rotation(&self) -> (f32, f32, f32) {
+    match self {
+      Self::Head | Self::Body => (0.0, 0.0, 0.0),
+      Self::LeftArm => (-10.0, 0.0, -10.0),
+      Self::RightArm => (-15.0, 0.0, 10.0),
+      Self::LeftLeg => (-1.0, 0.0, -1.0),
+      Self::RightLeg => (1.0, 0.0, 1.0),
+    }
+  }
+  fn meta_index(&self) -> u8 {
+    16 + match self {
+      Self::Head => 0,
+      Self::Body => 1,
+      Self::LeftArm => 2,
+      Self::RightArm => 3,
+      Self::LeftLeg => 4,
+      Self::RightLeg => 5,
+    }
+  }
+}
+
+/// A flag that changes how an armor stand looks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmorStandFlag {
+  /// Makes the armor stand half the size.
+  Small,
+  /// Shows the armor stand's arms, which allows it to hold items.
+  Arms,
+  /// Hides the stone slab under the armor stand.
+  NoBasePlate,
+  /// Removes the hitbox of the armor stand, so that players cannot interact
+  /// with it. This is mostly used for floating text.
+  Marker,
+}
+
+impl ArmorStandFlag {
+  /// Parses a flag name, like `no_base_plate`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "small" => Self::Small,
+      "arms" => Self::Arms,
+      "no_base_plate" => Self::NoBasePlate,
+      "marker" => Self::Marker,
+      _ => return None,
+    })
+  }
+  fn mask(&self) -> u8 {
+    match self {
+      Self::Small => 0x01,
+      Self::Arms => 0x04,
+      Self::NoBasePlate => 0x08,
+      Self::Marker => 0x10,
+    }
+  }
+}
+
+impl EntityData {
+  /// Returns the rotation of the given part of this armor stand, in degrees.
+  pub fn armor_stand_pose(&self, part: ArmorStandPart) -> (f32, f32, f32) {
+    match self.metadata().fields.get(&part.meta_index()) {
+      Some(Field::Rotation(x, y, z)) => (*x, *y, *z),
+      _ => part.default_rotation(),
+    }
+  }
+  /// Sets the rotation of the given part of this armor stand, in degrees. This
+  /// does nothing to other entities.
+  pub fn set_armor_stand_pose(&self, part: ArmorStandPart, x: f32, y: f32, z: f32) {
+    let mut meta = Metadata::new();
+    meta.set_rotation(part.meta_index(), x, y, z);
+    self.update_metadata(meta);
+  }
+
+  /// Returns `true` if the given armor stand flag is set.
+  pub fn armor_stand_flag(&self, flag: ArmorStandFlag) -> bool {
+    self.meta_byte(15) & flag.mask() != 0
+  }
+  /// Sets or clears the given armor stand flag. This does nothing to other
+  /// entities.
+  pub fn set_armor_stand_flag(&self, flag: ArmorStandFlag, value: bool) {
+    self.set_meta_bit(15, flag.mask(), value);
+  }
+
+  /// Returns the slot that a player will swap with when they right click on
+  /// this armor stand at the given height (relative to the armor stand),
+  /// while holding `held`. If the interaction shouldn't do anything, this
+  /// returns `None`.
+  pub fn armor_stand_clicked_slot(&self, held: &Stack, y: f64) -> Option<EquipmentSlot> {
+    if self.armor_stand_flag(ArmorStandFlag::Marker) {
+      return None;
+    }
+    let eq = self.equipment.lock();
+    if held.is_empty() {
+      let slot = clicked_slot(&eq, self.armor_stand_flag(ArmorStandFlag::Small), y);
+      return if eq.has(slot) { Some(slot) } else { None };
+    }
+    match held.item().armor() {
+      Some(armor) => Some(EquipmentSlot::Armor(armor.slot)),
+      None if self.armor_stand_flag(ArmorStandFlag::Arms) => Some(EquipmentSlot::Hand(Hand::Main)),
+      None => None,
+    }
+  }
+}
+
+/// Returns the slot at the given height on an armor stand. This is the same
+/// logic as vanilla, so empty slots will be skipped when possible.
+fn clicked_slot(eq: &Equipment, small: bool, y: f64) -> EquipmentSlot {
+  let y = if small { y * 2.0 } else { y };
+  // The `(min, max)` heights of each armor slot.
+  let (boots, chestplate, leggings) = if small {
+    ((0.1, 0.9), (1.2, 1.9), (0.4, 1.4))
+  } else {
+    ((0.1, 0.55), (0.9, 1.6), (0.4, 1.2))
+  };
+  let in_range = |(min, max): (f64, f64)| y >= min && y < max;
+  let armor = EquipmentSlot::Armor;
+  if in_range(boots) && eq.has(armor(ArmorSlot::Boots)) {
+    armor(ArmorSlot::Boots)
+  } else if in_range(chestplate) && eq.has(armor(ArmorSlot::Chestplate)) {
+    armor(ArmorSlot::Chestplate)
+  } else if in_range(leggings) && eq.has(armor(ArmorSlot::Leggings)) {
+    armor(ArmorSlot::Leggings)
+  } else if y >= 1.6 && eq.has(armor(ArmorSlot::Helmet)) {
+    armor(ArmorSlot::Helmet)
+  } else if !eq.has(EquipmentSlot::Hand(Hand::Main)) && eq.has(EquipmentSlot::Hand(Hand::Off)) {
+    EquipmentSlot::Hand(Hand::Off)
+  } else {
+    EquipmentSlot::Hand(Hand::Main)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Type;
+
+  #[test]
+  fn slots() {
+    let mut eq = Equipment::new();
+    assert_eq!(clicked_slot(&eq, false, 1.8), EquipmentSlot::Hand(Hand::Main));
+    *eq.get_mut(EquipmentSlot::Armor(ArmorSlot::Helmet)) = Stack::new(Type::IronHelmet);
+    *eq.get_mut(EquipmentSlot::Armor(ArmorSlot::Boots)) = Stack::new(Type::IronBoots);
+    assert_eq!(clicked_slot(&eq, false, 1.8), EquipmentSlot::Armor(ArmorSlot::Helmet));
+    assert_eq!(clicked_slot(&eq, false, 0.2), EquipmentSlot::Armor(ArmorSlot::Boots));
+    assert_eq!(clicked_slot(&eq, false, 1.0), EquipmentSlot::Hand(Hand::Main));
+    // Small armor stands are half the height.
+    assert_eq!(clicked_slot(&eq, true, 0.9), EquipmentSlot::Armor(ArmorSlot::Helmet));
+  }
+}
@@ -0,0 +1,352 @@
+//! Entity attributes. Each entity has a set of attributes, like max health or
+//! movement speed. Attributes have a base value, and a list of modifiers, which
+//! can be added by plugins, potion effects, or equipment.
+
+use super::Type;
+use bb_common::{net::cb, util::UUID};
+use std::collections::BTreeMap;
+
+// Creates the Attribute enum, and the base_attributes function.
+include!(concat!(env!("OUT_DIR"), "/entity/attribute.rs"));
+
+/// How a modifier changes the value of an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+  /// Adds `amount` to the base value.
+  Add,
+  /// Adds `amount * base` to the value, after all `Add` modifiers have been
+  /// applied.
+  MultiplyBase,
+  /// Multiplies the value by `1 + amount`, after all other modifiers have been
+  /// applied.
+  MultiplyTotal,
+}
+
+impl Operation {
+  /// Returns the id of this operation, as sent to the client.
+  pub fn id(&self) -> u8 {
+    match self {
+      Self::Add => 0,
+      Self::MultiplyBase => 1,
+      Self::MultiplyTotal => 2,
+    }
+  }
+  /// Parses an operation name, like `add`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "add" => Self::Add,
+      "multiply_base" => Self::MultiplyBase,
+      "multiply_total" => Self::MultiplyTotal,
+      _ => return None,
+    })
+  }
+}
+
+/// A single change to an attribute. Modifiers are identified by their id, so
+/// adding a modifier with the same id twice will replace the first one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Modifier {
+  pub id:     UUID,
+  pub amount: f64,
+  pub op:     Operation,
+  /// The number of ticks until this modifier is removed. If this is `None`,
+  /// the modifier will stay until it is removed manually.
+  pub ticks:  Option<u32>,
+}
+
+impl Modifier {
+  /// Creates a modifier which will never expire.
+  pub fn new(id: UUID, amount: f64, op: Operation) -> Self {
+    Modifier { id, amount, op, ticks: None }
+  }
+  /// Creates a modifier with a random id.
+  pub fn random(amount: f64, op: Operation) -> Self { Modifier::new(UUID::random(), amount, op) }
+  /// Makes this modifier expire after the given number of ticks.
+  pub fn with_duration(mut self, ticks: u32) -> Self {
+    self.ticks = Some(ticks);
+    self
+  }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Instance {
+  base:      Option<f64>,
+  modifiers: Vec<Modifier>,
+}
+
+/// All of the attributes for an entity. Base values that have not been set
+/// will use the vanilla value for the entity type.
+#[derive(Debug, Clone)]
+pub struct Attributes {
+  ty:        Type,
+  /// Any attributes that have been changed. Only these are sent to clients,
+  /// and they are never removed, so that clients will see modifiers being
+  /// removed.
+  instances: BTreeMap<Attribute, Instance>,
+}
+
+impl Attributes {
+  /// Creates the default attributes for the given entity.
+  pub fn new(ty: Type) -> Self { Attributes { ty, instances: BTreeMap::new() } }
+
+  /// Returns the base value of the given attribute.
+  pub fn base(&self, attr: Attribute) -> f64 {
+    match self.instances.get(&attr).and_then(|i| i.base) {
+      Some(base) => base,
+      None => base_attributes(self.ty)
+        .iter()
+        .find(|(a, _)| *a == attr)
+        .map(|(_, v)| *v)
+        .unwrap_or_else(|| attr.default_value()),
+    }
+  }
+  /// Sets the base value of the given attribute. This will be clamped within
+  /// the range of the attribute.
+  pub fn set_base(&mut self, attr: Attribute, value: f64) {
+    self.instances.entry(attr).or_default().base = Some(value.clamp(attr.min(), attr.max()));
+  }
+
+  /// Returns the final value of the given attribute, after applying all
+  /// modifiers.
+  pub fn get(&self, attr: Attribute) -> f64 { self.get_with(attr, &[]) }
+
+  /// Returns the final value of the given attribute, with some extra modifiers
+  /// applied. This is used to apply modifiers from equipment, which aren't
+  /// stored here.
+  pub fn get_with(&self, attr: Attribute, extra: &[Modifier]) -> f64 {
+    let modifiers = self.instances.get(&attr).map(|i| i.modifiers.as_slice()).unwrap_or(&[]);
+    let all = || modifiers.iter().chain(extra);
+    let mut value = self.base(attr);
+    for m in all().filter(|m| m.op == Operation::Add) {
+      value += m.amount;
+    }
+    let base = value;
+    for m in all().filter(|m| m.op == Operation::MultiplyBase) {
+      value += base * m.amount;
+    }
+    for m in all().filter(|m| m.op == Operation::MultiplyTotal) {
+      value *= 1.0 + m.amount;
+    }
+    value.clamp(attr.min(), attr.max())
+  }
+
+  /// Adds the given modifier. If a modifier with the same id already exists,
+  /// it will be replaced.
+  pub fn add_modifier(&mut self, attr: Attribute, modifier: Modifier) {
+    let inst = self.instances.entry(attr).or_default();
+    inst.modifiers.retain(|m| m.id != modifier.id);
+    inst.modifiers.push(modifier);
+  }
+  /// Removes the modifier with the given id. Returns `true` if the modifier
+  /// existed.
+  pub fn remove_modifier(&mut self, attr: Attribute, id: UUID) -> bool {
+    match self.instances.get_mut(&attr) {
+      Some(inst) => {
+        let len = inst.modifiers.len();
+        inst.modifiers.retain(|m| m.id != id);
+        inst.modifiers.len() != len
+      }
+      None => false,
+    }
+  }
+  /// Returns the level of the given effect, or 0 if the effect isn't applied.
+  /// This is found from the modifier added by [`Effect::modifier`].
+  pub fn effect_level(&self, effect: Effect) -> u32 {
+    let (attr, id, amount, _) = effect.params();
+    match self.modifiers(attr).iter().find(|m| m.id == UUID::from_u128(id)) {
+      Some(m) => (m.amount / amount).round() as u32,
+      None => 0,
+    }
+  }
+  /// Returns all the modifiers for the given attribute. This does not include
+  /// equipment modifiers.
+  pub fn modifiers(&self, attr: Attribute) -> &[Modifier] {
+    self.instances.get(&attr).map(|i| i.modifiers.as_slice()).unwrap_or(&[])
+  }
+
+  /// Counts down the duration of all temporary modifiers. Returns `true` if
+  /// any modifiers expired, in which case the attributes should be resent to
+  /// clients.
+  pub(crate) fn tick(&mut self) -> bool {
+    let mut changed = false;
+    for inst in self.instances.values_mut() {
+      inst.modifiers.retain_mut(|m| match &mut m.ticks {
+        Some(0) => {
+          changed = true;
+          false
+        }
+        Some(t) => {
+          *t -= 1;
+          true
+        }
+        None => true,
+      });
+    }
+    changed
+  }
+
+  /// Returns a packet containing every attribute that has been changed. If
+  /// nothing has been changed, this returns `None`, as the client already
+  /// knows the default values.
+  pub fn to_packet(&self, eid: i32) -> Option<cb::packet::EntityProperties> {
+    if self.instances.is_empty() {
+      return None;
+    }
+    Some(cb::packet::EntityProperties {
+      eid,
+      properties: self
+        .instances
+        .iter()
+        .map(|(&attr, inst)| cb::EntityProperty {
+          name:      attr.name().into(),
+          base:      self.base(attr),
+          modifiers: inst
+            .modifiers
+            .iter()
+            .map(|m| cb::PropertyModifier { id: m.id, amount: m.amount, op: m.op.id() })
+            .collect(),
+        })
+        .collect(),
+    })
+  }
+}
+
+/// A potion effect which changes an attribute. Other effects don't change
+/// attributes, so they aren't listed here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+  Speed,
+  Slowness,
+  Haste,
+  MiningFatigue,
+  Strength,
+  Weakness,
+  HealthBoost,
+  Luck,
+  Unluck,
+}
+
+impl Effect {
+  /// Parses an effect name, like `speed`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "speed" => Self::Speed,
+      "slowness" => Self::Slowness,
+      "haste" => Self::Haste,
+      "mining_fatigue" => Self::MiningFatigue,
+      "strength" => Self::Strength,
+      "weakness" => Self::Weakness,
+      "health_boost" => Self::HealthBoost,
+      "luck" => Self::Luck,
+      "unluck" => Self::Unluck,
+      _ => return None,
+    })
+  }
+
+  /// Returns the attribute modifier for this effect. `level` starts at 1, so
+  /// Speed II has a level of 2. The modifier always has the same id for the
+  /// same effect, so applying an effect twice will replace the old modifier.
+  pub fn modifier(&self, level: u32) -> (Attribute, Modifier) {
+    let (attr, id, amount, op) = self.params();
+    (attr, Modifier::new(UUID::from_u128(id), amount * level.max(1) as f64, op))
+  }
+
+  /// Returns the attribute, modifier id, amount per level, and operation of
+  /// this effect.
+  fn params(&self) -> (Attribute, u128, f64, Operation) {
+    // These are the same ids as vanilla.
+    match self {
+      Self::Speed => (
+        Attribute::MovementSpeed,
+        0x91aeaa56_376b_4498_935b_2f7f68070635,
+        0.2,
+        Operation::MultiplyTotal,
+      ),
+      Self::Slowness => (
+        Attribute::MovementSpeed,
+        0x7107de5e_7ce8_4030_940e_514c1f160890,
+        -0.15,
+        Operation::MultiplyTotal,
+      ),
+      Self::Haste => (
+        Attribute::AttackSpeed,
+        0xaf8b6e3f_3328_4c0a_aa36_5ba2bb9dbef3,
+        0.1,
+        Operation::MultiplyTotal,
+      ),
+      Self::MiningFatigue => (
+        Attribute::AttackSpeed,
+        0x55fced67_e92a_486e_9800_b47f202c4386,
+        -0.1,
+        Operation::MultiplyTotal,
+      ),
+      Self::Strength => {
+        (Attribute::AttackDamage, 0x648d7064_6a60_4f59_8abe_c2c23a6dd7a9, 3.0, Operation::Add)
+      }
+      Self::Weakness => {
+        (Attribute::AttackDamage, 0x22653b89_116e_49dc_9b6b_9971489b5be5, -4.0, Operation::Add)
+      }
+      Self::HealthBoost => {
+        (Attribute::MaxHealth, 0x5d6f0ba2_1186_46ac_b896_c61c5cee99cc, 4.0, Operation::Add)
+      }
+      Self::Luck => (Attribute::Luck, 0x03c3c89d_7037_4b42_869f_b146bcb64d2e, 1.0, Operation::Add),
+      Self::Unluck => {
+        (Attribute::Luck, 0xcc5af142_2bd2_4215_b636_2605aed11727, -1.0, Operation::Add)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn modifiers() {
+    let mut attrs = Attributes::new(Type::Zombie);
+    assert_eq!(attrs.get(Attribute::AttackDamage), 3.0);
+    assert_eq!(attrs.get(Attribute::Luck), 0.0);
+
+    let id = UUID::from_u128(1);
+    attrs.add_modifier(Attribute::AttackDamage, Modifier::new(id, 2.0, Operation::Add));
+    assert_eq!(attrs.get(Attribute::AttackDamage), 5.0);
+    attrs.add_modifier(Attribute::AttackDamage, Modifier::new(id, 1.0, Operation::Add));
+    assert_eq!(attrs.get(Attribute::AttackDamage), 4.0);
+    attrs.add_modifier(Attribute::AttackDamage, Modifier::random(1.0, Operation::MultiplyBase));
+    assert_eq!(attrs.get(Attribute::AttackDamage), 8.0);
+    attrs.add_modifier(Attribute::AttackDamage, Modifier::random(0.5, Operation::MultiplyTotal));
+    assert_eq!(attrs.get(Attribute::AttackDamage), 12.0);
+    assert!(attrs.remove_modifier(Attribute::AttackDamage, id));
+    assert!(!attrs.remove_modifier(Attribute::AttackDamage, id));
+    assert_eq!(attrs.get(Attribute::AttackDamage), 9.0);
+
+    attrs.set_base(Attribute::MaxHealth, 5000.0);
+    assert_eq!(attrs.get(Attribute::MaxHealth), 1024.0);
+  }
+
+  #[test]
+  fn expiry() {
+    let mut attrs = Attributes::new(Type::Player);
+    assert!(attrs.to_packet(1).is_none());
+    let (attr, m) = Effect::Strength.modifier(2);
+    attrs.add_modifier(attr, m.with_duration(1));
+    assert_eq!(attrs.get(Attribute::AttackDamage), 7.0);
+    assert!(!attrs.tick());
+    assert!(attrs.tick());
+    assert_eq!(attrs.get(Attribute::AttackDamage), 1.0);
+    let packet = attrs.to_packet(1).unwrap();
+    assert_eq!(packet.properties.len(), 1);
+    assert!(packet.properties[0].modifiers.is_empty());
+  }
+  #[test]
+  fn effect_level() {
+    let mut attrs = Attributes::new(Type::Player);
+    assert_eq!(attrs.effect_level(Effect::Haste), 0);
+    let (attr, m) = Effect::Haste.modifier(2);
+    attrs.add_modifier(attr, m);
+    let (attr, m) = Effect::MiningFatigue.modifier(3);
+    attrs.add_modifier(attr, m);
+    assert_eq!(attrs.effect_level(Effect::Haste), 2);
+    assert_eq!(attrs.effect_level(Effect::MiningFatigue), 3);
+  }
+}
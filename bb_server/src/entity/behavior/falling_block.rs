@@ -1,15 +1,99 @@
 use super::{Behavior, EntityData, EntityPos, ShouldDespawn};
-use crate::{block, entity, entity::Metadata, item, item::Stack, world::World};
-use bb_common::math::FPos;
-use std::sync::Arc;
+use crate::{block, item, item::Stack, math::Vec3, world::World};
+use bb_common::{
+  math::{FPos, Pos},
+  version::BlockVersion,
+};
+use rand::Rng;
+use std::{str::FromStr, sync::Arc};
+
+/// Falling blocks that haven't landed after this many ticks will drop as an
+/// item.
+const MAX_TICKS: u32 = 600;
 
 #[derive(Default)]
-pub struct FallingBlock;
+pub struct FallingBlock {
+  ticks:         u32,
+  /// The number of blocks this has fallen. Used for anvil damage.
+  fall_distance: f64,
+}
+
+/// Returns `true` if a falling block can fall into the block at `pos`. This is
+/// true for air, liquids, fire, and plants like tall grass. This is also used
+/// to check if a falling block can be placed at `pos` once it lands.
+pub fn can_fall_through(world: &World, pos: Pos) -> bool {
+  match world.get_kind(pos) {
+    Ok(block::Kind::Air | block::Kind::Water | block::Kind::Lava | block::Kind::Fire) => true,
+    Ok(kind) => world.block_converter().get(kind).material.is_replaceable(),
+    // Blocks can fall out of the bottom of the world, but not into the top.
+    Err(_) => pos.y < world.min_y(),
+  }
+}
+
+/// Returns `true` if the given block is an anvil.
+fn is_anvil(kind: block::Kind) -> bool {
+  matches!(kind, block::Kind::Anvil | block::Kind::ChippedAnvil | block::Kind::DamagedAnvil)
+}
+
+/// Returns the damage an anvil deals after falling `distance` blocks.
+fn anvil_damage(distance: f64) -> f32 { ((distance.ceil() - 1.0) * 2.0).clamp(0.0, 40.0) as f32 }
+
+impl FallingBlock {
+  /// Called when the block hits the ground at `pos`. Anvils damage anything
+  /// they land on, and might break.
+  fn land(&self, world: &Arc<World>, ty: block::Type, pos: Pos) {
+    if !can_fall_through(world, pos) {
+      drop_block(world, ty, pos);
+      return;
+    }
+    let mut ty = ty;
+    if is_anvil(ty.kind()) {
+      let damage = anvil_damage(self.fall_distance);
+      if damage > 0.0 {
+        let min = FPos::new(pos.x as f64, pos.y as f64, pos.z as f64);
+        for ent in world.entities_in_box(min, min + FPos::new(1.0, 1.0, 1.0)) {
+          if let Some(ent) = ent.as_entity_ref(world) {
+            ent.damage(damage, false, Vec3::new(0.0, 0.0, 0.0));
+          }
+        }
+      }
+      // Vanilla has a 5% chance to damage the anvil, plus 5% for every block it
+      // fell.
+      let chance = 0.05 + self.fall_distance * 0.05;
+      if rand::thread_rng().gen_bool(chance.min(1.0)) {
+        let next = match ty.kind() {
+          block::Kind::Anvil => Some(block::Kind::ChippedAnvil),
+          block::Kind::ChippedAnvil => Some(block::Kind::DamagedAnvil),
+          _ => None,
+        };
+        match next {
+          Some(kind) => {
+            ty = world.block_converter().get(kind).default_type().with("facing", ty.prop("facing"))
+          }
+          // A damaged anvil breaks completely.
+          None => return,
+        }
+      }
+    }
+    let _ = world.set_block(pos, ty);
+  }
+}
+
+/// Drops the given block as an item. This is used when a falling block can't
+/// be placed.
+fn drop_block(world: &Arc<World>, ty: block::Type, pos: Pos) {
+  if let Ok(it) = item::Type::from_str(ty.kind().to_str()) {
+    world.drop_item(pos, Stack::new(it));
+  }
+}
 
 impl Behavior for FallingBlock {
   fn tick(&mut self, world: &Arc<World>, ent: &EntityData, p: &mut EntityPos) -> ShouldDespawn {
     let vel = p.vel;
     p.aabb.pos += vel;
+    if vel.y < 0.0 {
+      self.fall_distance -= vel.y;
+    }
     // 9.8 m/s ~= 0.5 m/tick. However, minecraft go brrr, and gravity is actually
     // 0.03 b/tick for projectiles, 0.04 b/tick for items, and 0.08 b/tick for
     // living entities.
@@ -23,27 +107,33 @@ impl Behavior for FallingBlock {
     p.vel.x *= 0.91;
     p.vel.z *= 0.91;
 
+    self.ticks += 1;
+    let ty = world.block_converter().type_from_id(ent.data() as u32, BlockVersion::latest());
+    let block_pos = p.aabb.pos.block();
     if p.grounded {
-      let ty = world
-        .block_converter()
-        .type_from_id(ent.data() as u32, bb_common::version::BlockVersion::latest());
-      // This new position is at the center of the block, which will be the most
-      // accurate for converting to a block position. If we don't add 0.5, then chains
-      // of falling blocks can sometimes overlap.
-      let block_pos = (p.aabb.pos + FPos::new(0.0, 0.0, 0.0)).block();
-      let kind = world.get_kind(block_pos).unwrap_or(block::Kind::Air);
-      if kind == block::Kind::Air || world.block_converter().get(kind).material.is_replaceable() {
-        let _ = world.set_block(block_pos, ty);
-        ShouldDespawn(true)
-      } else {
-        let mut meta = Metadata::new();
-        meta.set_item(8, Stack::new(item::Type::Sand).to_item());
-        world.summon_meta(entity::Type::Item, block_pos.center(), meta);
-
-        ShouldDespawn(true)
-      }
+      self.land(world, ty, block_pos);
+      ShouldDespawn(true)
+    } else if block_pos.y < world.min_y() - 64 {
+      // Fell out of the world.
+      ShouldDespawn(true)
+    } else if self.ticks > MAX_TICKS {
+      drop_block(world, ty, block_pos);
+      ShouldDespawn(true)
     } else {
       ShouldDespawn(false)
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn anvil_damage_amounts() {
+    assert_eq!(anvil_damage(0.5), 0.0);
+    assert_eq!(anvil_damage(1.0), 0.0);
+    assert_eq!(anvil_damage(3.2), 6.0);
+    assert_eq!(anvil_damage(100.0), 40.0);
+  }
+}
@@ -1,13 +1,16 @@
 mod falling_block;
 mod item;
 mod snowball;
+mod text;
 
-pub use falling_block::FallingBlock;
+pub use falling_block::{can_fall_through, FallingBlock};
 pub use item::ItemBehavior;
 pub use snowball::SnowballBehavior;
+pub use text::TextBehavior;
 
 use super::{EntityData, EntityPos, Type};
 use crate::world::World;
+use bb_common::math::Pos;
 use std::sync::Arc;
 
 /// A wrapper type, to make it clear that `true` means an entity should be
@@ -15,9 +18,23 @@ use std::sync::Arc;
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct ShouldDespawn(pub bool);
 
+/// A snapshot of an entity's pathfinder, which is drawn by `/debug path`.
+#[derive(Clone, Debug, Default)]
+pub struct PathDebug {
+  /// The blocks the entity is going to walk through, in order.
+  pub path:   Vec<Pos>,
+  /// The blocks that the pathfinder has found, but not visited yet.
+  pub open:   Vec<Pos>,
+  /// The blocks that the pathfinder has already visited.
+  pub closed: Vec<Pos>,
+  /// The block the entity is trying to reach.
+  pub target: Option<Pos>,
+}
+
 pub trait Behavior {
-  /// The maximum health of this entity.
-  fn max_health(&self) -> f32 { 20.0 }
+  /// The maximum health of this entity. If this returns `None`, the vanilla
+  /// max health attribute for this entity type is used.
+  fn max_health(&self) -> Option<f32> { None }
 
   /// Returns true if the entity should despawn. Called whenever the entity's
   /// health changes, or when `check_despawn` is called.
@@ -28,16 +45,21 @@ pub trait Behavior {
   /// killed.
   fn exp_count(&self) -> i32 { 1 }
 
+  /// Returns the current state of this entity's pathfinder. Entities that
+  /// don't pathfind should return `None`. This is only used for debugging, so
+  /// it doesn't need to be fast.
+  fn path_debug(&self) -> Option<PathDebug> { None }
+
   /// Any extra functionality needed. Called every tick, after movement and
   /// collision checks have been completed.
   fn tick(&mut self, world: &Arc<World>, ent: &EntityData, p: &mut EntityPos) -> ShouldDespawn {
-    let _ = (world, ent);
+    let _ = world;
     let vel = p.vel;
     p.aabb.pos += vel;
     // 9.8 m/s ~= 0.5 m/tick. However, minecraft go brrr, and gravity is actually
     // 0.03 b/tick for projectiles, 0.04 b/tick for items, and 0.08 b/tick for
     // living entities.
-    if !p.grounded {
+    if !p.grounded && !ent.no_gravity() {
       p.vel.y -= 0.08;
     }
     p.vel.y *= 0.98;
@@ -1,5 +1,5 @@
 use super::{Behavior, EntityData, EntityPos, ShouldDespawn};
-use crate::world::World;
+use crate::{event, math::Vec3, world::World};
 use std::sync::Arc;
 
 #[derive(Default)]
@@ -8,9 +8,23 @@ pub struct SnowballBehavior {
 }
 
 impl Behavior for SnowballBehavior {
-  fn tick(&mut self, _: &Arc<World>, _: &EntityData, p: &mut EntityPos) -> ShouldDespawn {
+  fn tick(&mut self, world: &Arc<World>, ent: &EntityData, p: &mut EntityPos) -> ShouldDespawn {
     let vel = p.vel;
+    let old = p.aabb.pos;
     p.aabb.pos += vel;
+    if let Some((pos, hit)) = world.raycast_entity(old, p.aabb.pos, Some(ent.eid())) {
+      if let Some(hit) = hit.as_entity_ref(world) {
+        let cancelled = world
+          .events()
+          .global_request(event::ProjectileHit { projectile: ent.handle(), hit: hit.handle(), pos })
+          .is_handled();
+        if !cancelled {
+          // Snowballs don't deal any damage, they only knock entities back.
+          hit.damage(0.0, true, Vec3::new(vel.x, 0.0, vel.z));
+        }
+      }
+      return ShouldDespawn(true);
+    }
     // This is for all projectiles. It is totally different on living entities.
     p.vel.x *= 0.99;
     p.vel.y *= 0.99;
@@ -0,0 +1,15 @@
+use super::{Behavior, EntityData, EntityPos, ShouldDespawn};
+use crate::world::World;
+use std::sync::Arc;
+
+/// Floating text, created with [`World::spawn_text`]. This never moves, and
+/// will only be removed by [`World::remove_entity`].
+#[derive(Default)]
+pub struct TextBehavior;
+
+impl Behavior for TextBehavior {
+  fn should_despawn(&self, _: f32) -> ShouldDespawn { ShouldDespawn(false) }
+  fn tick(&mut self, _: &Arc<World>, _: &EntityData, _: &mut EntityPos) -> ShouldDespawn {
+    ShouldDespawn(false)
+  }
+}
@@ -0,0 +1,167 @@
+//! A small entity component system for server-controlled entities.
+//!
+//! Each kind of component is stored in its own [`SparseSet`], keyed by entity
+//! id. The components themselves are packed into a single array, so a system
+//! can go through every entity in a world while holding one lock, instead of
+//! locking every entity one at a time. The world's tick loop uses this to read
+//! all the entity positions at the start of a tick, and to write them all back
+//! at the end.
+//!
+//! Components are added when an [`EntityData`](super::EntityData) is created,
+//! and removed when it is dropped. Players are not stored here; they keep
+//! their own state in [`Player`](crate::player::Player).
+
+use super::EntityPos;
+use crate::world::lock_order::{self, Level};
+use bb_common::metadata::Metadata;
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// A map from entity ids to components. Lookups go through `sparse`, and the
+/// components are stored contiguously in `dense`, so iterating is as fast as
+/// iterating over a `Vec`.
+pub struct SparseSet<T> {
+  /// Maps an entity id to an index in `dense`. Entity ids are never reused, so
+  /// this is a map instead of an array, which would grow forever.
+  sparse: HashMap<i32, usize>,
+  /// The entity id for each element of `dense`.
+  eids:   Vec<i32>,
+  dense:  Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+  fn default() -> Self { SparseSet::new() }
+}
+
+impl<T> SparseSet<T> {
+  pub fn new() -> Self { SparseSet { sparse: HashMap::new(), eids: vec![], dense: vec![] } }
+
+  pub fn len(&self) -> usize { self.dense.len() }
+  pub fn is_empty(&self) -> bool { self.dense.is_empty() }
+  pub fn contains(&self, eid: i32) -> bool { self.sparse.contains_key(&eid) }
+
+  /// Adds a component for the given entity. If the entity already had one, the
+  /// old component is returned.
+  pub fn insert(&mut self, eid: i32, value: T) -> Option<T> {
+    if let Some(&idx) = self.sparse.get(&eid) {
+      return Some(std::mem::replace(&mut self.dense[idx], value));
+    }
+    self.sparse.insert(eid, self.dense.len());
+    self.eids.push(eid);
+    self.dense.push(value);
+    None
+  }
+
+  /// Removes the component for the given entity. The last component is moved
+  /// into its place, so this doesn't preserve the order of the components.
+  pub fn remove(&mut self, eid: i32) -> Option<T> {
+    let idx = self.sparse.remove(&eid)?;
+    self.eids.swap_remove(idx);
+    let value = self.dense.swap_remove(idx);
+    if let Some(&moved) = self.eids.get(idx) {
+      self.sparse.insert(moved, idx);
+    }
+    Some(value)
+  }
+
+  pub fn get(&self, eid: i32) -> Option<&T> { self.sparse.get(&eid).map(|&idx| &self.dense[idx]) }
+  pub fn get_mut(&mut self, eid: i32) -> Option<&mut T> {
+    self.sparse.get(&eid).map(|&idx| &mut self.dense[idx])
+  }
+
+  /// Iterates over every entity id and component, in no particular order.
+  pub fn iter(&self) -> impl Iterator<Item = (i32, &T)> + '_ {
+    self.eids.iter().copied().zip(self.dense.iter())
+  }
+  /// Iterates over every entity id and component, in no particular order.
+  pub fn iter_mut(&mut self) -> impl Iterator<Item = (i32, &mut T)> + '_ {
+    self.eids.iter().copied().zip(self.dense.iter_mut())
+  }
+  /// Iterates over every entity id and component on the rayon thread pool.
+  pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (i32, &mut T)> + '_
+  where
+    T: Send,
+  {
+    self.eids.par_iter().copied().zip(self.dense.par_iter_mut())
+  }
+}
+
+/// All the components of the entities in a world.
+///
+/// Each component has its own lock, which is at [`Level::Components`]. Nothing
+/// else may be locked while holding one of these, so the guards should only be
+/// held long enough to copy data in or out.
+#[derive(Default)]
+pub struct Components {
+  /// The position, velocity, and rotation of each entity.
+  pos:  RwLock<SparseSet<EntityPos>>,
+  /// The metadata of each entity.
+  meta: RwLock<SparseSet<Metadata>>,
+}
+
+impl Components {
+  pub fn new() -> Self { Components::default() }
+
+  /// Adds all the components for a new entity.
+  pub(super) fn insert(&self, eid: i32, pos: EntityPos, meta: Metadata) {
+    self.pos_mut().insert(eid, pos);
+    self.meta_mut().insert(eid, meta);
+  }
+  /// Removes all the components of an entity.
+  pub(super) fn remove(&self, eid: i32) {
+    self.pos_mut().remove(eid);
+    self.meta_mut().remove(eid);
+  }
+
+  /// Locks the positions of every entity for reading.
+  pub fn pos(&self) -> RwLockReadGuard<'_, SparseSet<EntityPos>> {
+    lock_order::check(Level::Components);
+    self.pos.read()
+  }
+  /// Locks the positions of every entity for writing.
+  pub fn pos_mut(&self) -> RwLockWriteGuard<'_, SparseSet<EntityPos>> {
+    lock_order::check(Level::Components);
+    self.pos.write()
+  }
+  /// Locks the metadata of every entity for reading.
+  pub fn meta(&self) -> RwLockReadGuard<'_, SparseSet<Metadata>> {
+    lock_order::check(Level::Components);
+    self.meta.read()
+  }
+  /// Locks the metadata of every entity for writing.
+  pub fn meta_mut(&self) -> RwLockWriteGuard<'_, SparseSet<Metadata>> {
+    lock_order::check(Level::Components);
+    self.meta.write()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sparse_set() {
+    let mut set = SparseSet::new();
+    assert_eq!(set.insert(5, "a"), None);
+    assert_eq!(set.insert(7, "b"), None);
+    assert_eq!(set.insert(9, "c"), None);
+    assert_eq!(set.insert(7, "d"), Some("b"));
+    assert_eq!(set.len(), 3);
+
+    // Removing from the middle moves the last element into its place.
+    assert_eq!(set.remove(5), Some("a"));
+    assert_eq!(set.remove(5), None);
+    assert_eq!(set.get(9), Some(&"c"));
+    assert_eq!(set.get(7), Some(&"d"));
+    assert!(!set.contains(5));
+
+    *set.get_mut(9).unwrap() = "e";
+    let mut all: Vec<_> = set.iter().map(|(eid, v)| (eid, *v)).collect();
+    all.sort();
+    assert_eq!(all, [(7, "d"), (9, "e")]);
+
+    set.par_iter_mut().for_each(|(_, v)| *v = "f");
+    assert!(set.iter().all(|(_, v)| *v == "f"));
+  }
+}
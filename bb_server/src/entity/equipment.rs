@@ -0,0 +1,77 @@
+//! The items held and worn by non-player entities.
+
+use crate::item::Stack;
+use bb_common::{
+  net::cb::{ArmorSlot, EquipmentSlot},
+  util::Hand,
+};
+
+/// The items an entity is holding or wearing. Players store their equipment
+/// in their inventory, so this is only used for other entities.
+#[derive(Debug, Clone, Default)]
+pub struct Equipment {
+  main_hand:  Stack,
+  off_hand:   Stack,
+  helmet:     Stack,
+  chestplate: Stack,
+  leggings:   Stack,
+  boots:      Stack,
+}
+
+impl Equipment {
+  /// Every equipment slot, in the order they are sent to clients.
+  pub const SLOTS: [EquipmentSlot; 6] = [
+    EquipmentSlot::Hand(Hand::Main),
+    EquipmentSlot::Hand(Hand::Off),
+    EquipmentSlot::Armor(ArmorSlot::Boots),
+    EquipmentSlot::Armor(ArmorSlot::Leggings),
+    EquipmentSlot::Armor(ArmorSlot::Chestplate),
+    EquipmentSlot::Armor(ArmorSlot::Helmet),
+  ];
+
+  /// Creates an empty set of equipment.
+  pub fn new() -> Self { Equipment::default() }
+
+  /// Returns the item in the given slot.
+  pub fn get(&self, slot: EquipmentSlot) -> &Stack {
+    match slot {
+      EquipmentSlot::Hand(Hand::Main) => &self.main_hand,
+      EquipmentSlot::Hand(Hand::Off) => &self.off_hand,
+      EquipmentSlot::Armor(ArmorSlot::Helmet) => &self.helmet,
+      EquipmentSlot::Armor(ArmorSlot::Chestplate) => &self.chestplate,
+      EquipmentSlot::Armor(ArmorSlot::Leggings) => &self.leggings,
+      EquipmentSlot::Armor(ArmorSlot::Boots) => &self.boots,
+    }
+  }
+  /// Returns the item in the given slot mutably.
+  pub fn get_mut(&mut self, slot: EquipmentSlot) -> &mut Stack {
+    match slot {
+      EquipmentSlot::Hand(Hand::Main) => &mut self.main_hand,
+      EquipmentSlot::Hand(Hand::Off) => &mut self.off_hand,
+      EquipmentSlot::Armor(ArmorSlot::Helmet) => &mut self.helmet,
+      EquipmentSlot::Armor(ArmorSlot::Chestplate) => &mut self.chestplate,
+      EquipmentSlot::Armor(ArmorSlot::Leggings) => &mut self.leggings,
+      EquipmentSlot::Armor(ArmorSlot::Boots) => &mut self.boots,
+    }
+  }
+  /// Returns `true` if there is an item in the given slot.
+  pub fn has(&self, slot: EquipmentSlot) -> bool { !self.get(slot).is_empty() }
+
+  /// Iterates through all the slots that have an item in them.
+  pub fn iter(&self) -> impl Iterator<Item = (EquipmentSlot, &Stack)> {
+    Equipment::SLOTS.into_iter().map(|slot| (slot, self.get(slot))).filter(|(_, it)| !it.is_empty())
+  }
+}
+
+/// Parses an equipment slot name, like `main_hand` or `helmet`.
+pub fn slot_from_name(name: &str) -> Option<EquipmentSlot> {
+  Some(match name {
+    "main_hand" => EquipmentSlot::Hand(Hand::Main),
+    "off_hand" => EquipmentSlot::Hand(Hand::Off),
+    "helmet" => EquipmentSlot::Armor(ArmorSlot::Helmet),
+    "chestplate" => EquipmentSlot::Armor(ArmorSlot::Chestplate),
+    "leggings" => EquipmentSlot::Armor(ArmorSlot::Leggings),
+    "boots" => EquipmentSlot::Armor(ArmorSlot::Boots),
+    _ => return None,
+  })
+}
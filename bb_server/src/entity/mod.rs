@@ -1,17 +1,43 @@
+mod armor_stand;
+mod attribute;
+mod ecs;
+mod equipment;
+mod trade;
 mod ty;
 mod version;
 
-pub use ty::{Data, Type};
+pub use armor_stand::{ArmorStandFlag, ArmorStandPart};
+pub use attribute::{Attribute, Attributes, Effect, Modifier, Operation};
+pub use ecs::{Components, SparseSet};
+pub use equipment::{slot_from_name, Equipment};
+pub use trade::Trade;
+pub use ty::{Category, Data, Type};
 pub use version::TypeConverter;
 
 use crate::{
+  item::Stack,
   math::{Vec3, AABB},
+  net::broadcast::broadcast,
   player::Player,
   world::World,
 };
-use bb_common::{math::FPos, metadata::Metadata, util::UUID};
-use parking_lot::{Mutex, MutexGuard, RwLock};
-use std::sync::Arc;
+use bb_common::{
+  math::FPos,
+  metadata::{Field, Metadata},
+  net::cb,
+  util::{Chat, UUID},
+};
+use parking_lot::{
+  MappedRwLockReadGuard, MappedRwLockWriteGuard, Mutex, MutexGuard, RwLock, RwLockReadGuard,
+  RwLockWriteGuard,
+};
+use std::{
+  fmt,
+  sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+  },
+};
 
 pub mod behavior;
 
@@ -32,7 +58,7 @@ pub struct EntityPos {
 impl EntityPos {
   pub fn new(pos: FPos, size: Vec3) -> Self {
     EntityPos {
-      aabb:     AABB::new(pos, size),
+      aabb:     AABB::new(pos, size.into()),
       vel:      Vec3::new(0.0, 0.0, 0.0),
       grounded: false,
       yaw:      0.0,
@@ -65,6 +91,38 @@ pub enum Entity {
   Player(UUID),
 }
 
+/// A reference to an entity that plugins can hold onto.
+///
+/// Entity ids are only unique within a single world, and a plugin may keep an
+/// id around after the entity has been removed. So every entity is also given
+/// a generation, which is unique across the whole server. A handle is only
+/// valid if both the id and the generation match, so a stale handle will
+/// never refer to a different entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityHandle {
+  pub eid: i32,
+  pub gen: u32,
+}
+
+/// The last generation given out. This starts at 1, so that a handle of 0 is
+/// never valid.
+static GENERATION: AtomicU32 = AtomicU32::new(1);
+
+/// Returns a new, unique generation for an entity.
+pub(crate) fn next_gen() -> u32 { GENERATION.fetch_add(1, Ordering::Relaxed) }
+
+impl EntityHandle {
+  /// Packs this handle into a single number, with the generation in the upper
+  /// 32 bits. This is how handles are passed to wasm plugins.
+  pub fn to_u64(self) -> u64 { (self.gen as u64) << 32 | self.eid as u32 as u64 }
+  /// The inverse of [`to_u64`](Self::to_u64).
+  pub fn from_u64(v: u64) -> Self { EntityHandle { eid: v as u32 as i32, gen: (v >> 32) as u32 } }
+}
+
+impl fmt::Display for EntityHandle {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}v{}", self.eid, self.gen) }
+}
+
 /// The data for a server-controlled entity. This is separate from an
 /// [`Entity`], as it cannot represent a client player. It can represent an NPC
 /// (non player character) that looks like a player, but it cannot be controlled
@@ -73,8 +131,12 @@ pub struct EntityData {
   /// The unique id for this entity. This is the key used to store entities in
   /// the World.
   eid:      i32,
-  /// The position of this entity. Must be valid for all entities.
-  pos:      Mutex<EntityPos>,
+  /// The generation of this entity. See [`EntityHandle`].
+  gen:      u32,
+  /// The components of every entity in this entity's world. This entity's
+  /// position and metadata are stored here, and are removed when this entity
+  /// is dropped.
+  ecs:      Arc<Components>,
   /// The type of this entity.
   ty:       Type,
   /// For some entities, such as projectiles, this field is ignored. To make the
@@ -86,8 +148,14 @@ pub struct EntityData {
   world:    RwLock<Arc<World>>,
   behavior: Mutex<Box<dyn Behavior + Send>>,
 
-  /// Entity metadata
-  meta: Mutex<Metadata>,
+  /// Max health, movement speed, etc.
+  attrs:     Mutex<Attributes>,
+  /// Held items and armor.
+  equipment: Mutex<Equipment>,
+  /// The trades this entity offers. If this isn't empty, interacting with this
+  /// entity will open a merchant window. This is shared with any open merchant
+  /// windows, so that the number of uses is updated.
+  trades:    Arc<Mutex<Vec<Trade>>>,
 
   /// An extra int. Used for item frames and falling blocks. Appears to only be
   /// used when it changes the rendering of the base entity model.
@@ -139,15 +207,24 @@ impl EntityRef<'_> {
   /// [`pos`](Self::pos) will be easier to use.
   pub fn aabb(&self) -> EntityPos {
     match self {
-      Self::Entity(e) => *e.pos.lock(),
+      Self::Entity(e) => e.entity_pos(),
       _ => todo!(),
     }
   }
 
+  /// Returns this entity's bounding box. Unlike [`aabb`](Self::aabb), this
+  /// works for players as well.
+  pub fn hitbox(&self) -> AABB {
+    match self {
+      Self::Entity(e) => e.entity_pos().aabb,
+      Self::Player(p) => p.hitbox(),
+    }
+  }
+
   /// Returns this entity's position.
   pub fn pos(&self) -> FPos {
     match self {
-      Self::Entity(e) => e.pos.lock().aabb.pos,
+      Self::Entity(e) => e.fpos(),
       Self::Player(p) => p.pos(),
     }
   }
@@ -160,6 +237,14 @@ impl EntityRef<'_> {
     }
   }
 
+  /// Returns a handle to this entity, which plugins can use to refer to it.
+  pub fn handle(&self) -> EntityHandle {
+    match self {
+      Self::Entity(e) => e.handle(),
+      Self::Player(p) => p.handle(),
+    }
+  }
+
   /// Returns this entity's type. This can be used to send spawn packets to
   /// clients.
   pub fn ty(&self) -> Type {
@@ -177,6 +262,15 @@ impl EntityRef<'_> {
     }
   }
 
+  /// Returns the value of the given attribute, after all modifiers have been
+  /// applied. For players, this includes modifiers from their equipment.
+  pub fn attribute(&self, attr: Attribute) -> f64 {
+    match self {
+      Self::Entity(e) => e.attribute(attr),
+      Self::Player(p) => p.attribute(attr),
+    }
+  }
+
   /// Returns true if this entity should despawn.
   pub fn should_despawn(&self) -> bool {
     match self {
@@ -195,12 +289,23 @@ impl EntityRef<'_> {
     }
   }
 
+  /// Returns the state of this entity's pathfinder, for debugging. This is
+  /// `None` for players, and for entities that aren't pathfinding.
+  pub fn path_debug(&self) -> Option<behavior::PathDebug> {
+    match self {
+      Self::Entity(e) => e.behavior.lock().path_debug(),
+      Self::Player(_) => None,
+    }
+  }
+
   /// Sets this entity's velocity. This will send velocity updates to nearby
   /// players, and will affect how the entity moves on the next tick.
   pub fn set_vel(&self, vel: Vec3) {
     match self {
       Self::Entity(e) => {
-        e.pos.lock().vel = vel;
+        if let Some(p) = e.ecs.pos_mut().get_mut(e.eid) {
+          p.vel = vel;
+        }
         e.world.read().send_entity_vel(e.fpos().chunk(), e.eid, vel);
       }
       Self::Player(p) => {
@@ -209,22 +314,10 @@ impl EntityRef<'_> {
     }
   }
 
-  /// Called 20 times a second. Calling this more/less frequently will break
-  /// things.
-  pub(crate) fn tick(&self) -> bool {
-    match self {
-      Self::Entity(e) => e.tick(),
-      Self::Player(p) => {
-        p.tick();
-        false
-      }
-    }
-  }
-
   /// Returns all of this entity's metadata.
-  pub fn metadata(&self) -> MutexGuard<'_, Metadata> {
+  pub fn metadata(&self) -> MappedRwLockReadGuard<'_, Metadata> {
     match self {
-      Self::Entity(e) => e.meta.lock(),
+      Self::Entity(e) => e.metadata(),
       Self::Player(_) => todo!(),
     }
   }
@@ -257,14 +350,20 @@ impl EntityData {
   /// functionality of any kind, call [`new_custom`](Self::new_custom).
   pub fn new(eid: i32, ty: Type, world: Arc<World>, pos: FPos, meta: Metadata, data: i32) -> Self {
     let behavior = behavior::for_entity(ty);
+    let attrs = default_attributes(ty, behavior.as_ref());
+    let ecs = world.components().clone();
+    ecs.insert(eid, EntityPos::new(pos, world.entity_converter().get_data(ty).size()), meta);
     EntityData {
       eid,
-      pos: Mutex::new(EntityPos::new(pos, world.entity_converter().get_data(ty).size())),
+      gen: next_gen(),
+      ecs,
       ty,
-      health: Mutex::new(behavior.max_health()),
+      health: Mutex::new(attrs.get(Attribute::MaxHealth) as f32),
       world: RwLock::new(world),
       behavior: Mutex::new(behavior),
-      meta: Mutex::new(meta),
+      attrs: Mutex::new(attrs),
+      equipment: Mutex::new(Equipment::new()),
+      trades: Arc::new(Mutex::new(vec![])),
       data,
     }
   }
@@ -280,34 +379,185 @@ impl EntityData {
     meta: Metadata,
     data: i32,
   ) -> Self {
+    let attrs = default_attributes(ty, &behavior);
+    let ecs = world.components().clone();
+    ecs.insert(eid, EntityPos::new(pos, world.entity_converter().get_data(ty).size()), meta);
     EntityData {
       eid,
-      pos: Mutex::new(EntityPos::new(pos, world.entity_converter().get_data(ty).size())),
+      gen: next_gen(),
+      ecs,
       ty,
-      health: Mutex::new(behavior.max_health()),
+      health: Mutex::new(attrs.get(Attribute::MaxHealth) as f32),
       world: RwLock::new(world),
       behavior: Mutex::new(Box::new(behavior)),
-      meta: Mutex::new(meta),
+      attrs: Mutex::new(attrs),
+      equipment: Mutex::new(Equipment::new()),
+      trades: Arc::new(Mutex::new(vec![])),
       data,
     }
   }
 
   pub fn data(&self) -> i32 { self.data }
-  pub fn fpos(&self) -> FPos { self.pos.lock().aabb.pos }
+  pub fn fpos(&self) -> FPos { self.entity_pos().aabb.pos }
+  /// Returns this entity's position, velocity, and rotation.
+  pub fn entity_pos(&self) -> EntityPos {
+    *self.ecs.pos().get(self.eid).expect("entity has no position")
+  }
   pub fn health(&self) -> f32 { *self.health.lock() }
   pub fn eid(&self) -> i32 { self.eid }
-  pub fn metadata(&self) -> MutexGuard<'_, Metadata> { self.meta.lock() }
-  fn tick(&self) -> bool {
-    // We don't actually have a race condition here, unless tick() is called at the
-    // same time from multiple places (which would be a Bad Thing). Because we can't
-    // modify `self.pos` from anywhere else (simply because the functions don't
-    // exist), then we won't overwrite changed data by unlocking and re-locking this
-    // mutex.
-    let mut p = *self.pos.lock();
+  pub fn handle(&self) -> EntityHandle { EntityHandle { eid: self.eid, gen: self.gen } }
+  pub fn ty(&self) -> Type { self.ty }
+  /// Returns this entity's metadata. This locks the metadata of every entity
+  /// in the world, so the guard should be dropped quickly.
+  pub fn metadata(&self) -> MappedRwLockReadGuard<'_, Metadata> {
+    RwLockReadGuard::map(self.ecs.meta(), |m| m.get(self.eid).expect("entity has no metadata"))
+  }
+  /// Returns this entity's metadata for writing. Changes made here are not
+  /// sent to clients; use [`update_metadata`](Self::update_metadata) for that.
+  pub fn metadata_mut(&self) -> MappedRwLockWriteGuard<'_, Metadata> {
+    RwLockWriteGuard::map(self.ecs.meta_mut(), |m| {
+      m.get_mut(self.eid).expect("entity has no metadata")
+    })
+  }
+
+  /// Sets the custom name of this entity. If `name` is `None`, the custom name
+  /// is removed. The name will only be shown when a player looks at this
+  /// entity, unless [`set_custom_name_visible`](Self::set_custom_name_visible)
+  /// is called.
+  pub fn set_custom_name(&self, name: Option<Chat>) {
+    let mut meta = Metadata::new();
+    meta.set_opt_chat(2, name);
+    self.update_metadata(meta);
+  }
+  /// If `visible` is true, this entity's custom name will always be shown,
+  /// instead of only when a player looks at it.
+  pub fn set_custom_name_visible(&self, visible: bool) {
+    let mut meta = Metadata::new();
+    meta.set_bool(3, visible);
+    self.update_metadata(meta);
+  }
+  /// Returns this entity's custom name, if it has one.
+  pub fn custom_name(&self) -> Option<Chat> {
+    match self.metadata().fields.get(&2) {
+      Some(Field::OptChat(Some(json))) => Chat::from_json(json).ok(),
+      _ => None,
+    }
+  }
+  /// If `invisible` is true, this entity will not be rendered. Its custom name
+  /// and equipment will still be shown.
+  pub fn set_invisible(&self, invisible: bool) { self.set_meta_bit(0, 0x20, invisible); }
+  /// Returns `true` if this entity is invisible.
+  pub fn invisible(&self) -> bool { self.meta_byte(0) & 0x20 != 0 }
+  /// If `no_gravity` is true, this entity will not fall. It can still be moved
+  /// by setting its velocity.
+  pub fn set_no_gravity(&self, no_gravity: bool) {
+    let mut meta = Metadata::new();
+    meta.set_bool(5, no_gravity);
+    self.update_metadata(meta);
+  }
+  /// Returns `true` if this entity is not affected by gravity.
+  pub fn no_gravity(&self) -> bool {
+    matches!(self.metadata().fields.get(&5), Some(Field::Bool(true)))
+  }
+  /// Returns the byte metadata field at `idx`, or 0 if it isn't set.
+  fn meta_byte(&self, idx: u8) -> u8 {
+    match self.metadata().fields.get(&idx) {
+      Some(Field::Byte(v)) => *v,
+      _ => 0,
+    }
+  }
+  /// Sets or clears the bits in `mask` of the byte metadata field at `idx`,
+  /// leaving the other bits unchanged.
+  fn set_meta_bit(&self, idx: u8, mask: u8, value: bool) {
+    let old = self.meta_byte(idx);
+    let mut meta = Metadata::new();
+    meta.set_byte(idx, (if value { old | mask } else { old & !mask }) as i8);
+    self.update_metadata(meta);
+  }
+  /// Stores the given metadata fields in this entity's metadata, and sends
+  /// them to all nearby players. Fields not in `meta` are left unchanged.
+  pub fn update_metadata(&self, meta: Metadata) {
+    self.metadata_mut().fields.extend(meta.fields.iter().map(|(&k, v)| (k, v.clone())));
+    let packet = cb::packet::EntityMetadata { eid: self.eid, ty: self.ty.id(), meta };
+    broadcast(self.world.read().viewers(self.fpos().chunk()), packet);
+  }
+
+  /// Returns the item in the given equipment slot.
+  pub fn equipment(&self, slot: cb::EquipmentSlot) -> Stack {
+    self.equipment.lock().get(slot).clone()
+  }
+  /// Sets the item in the given equipment slot, and sends the change to all
+  /// nearby players. Returns the item that was in the slot before.
+  pub fn set_equipment(&self, slot: cb::EquipmentSlot, stack: Stack) -> Stack {
+    let packet = cb::packet::EntityEquipment { eid: self.eid, slot, item: stack.to_item() };
+    let old = std::mem::replace(self.equipment.lock().get_mut(slot), stack);
+    broadcast(self.world.read().viewers(self.fpos().chunk()), packet);
+    old
+  }
+  /// Sends all of this entity's equipment to the given player. This is used
+  /// when the entity is spawned for that player.
+  pub(crate) fn send_equipment(&self, player: &Player) {
+    for (slot, stack) in self.equipment.lock().iter() {
+      player.send(cb::packet::EntityEquipment { eid: self.eid, slot, item: stack.to_item() });
+    }
+  }
+
+  /// Returns a copy of the trades this entity offers.
+  pub fn trades(&self) -> Vec<Trade> { self.trades.lock().clone() }
+  /// Sets the trades this entity offers. If this is empty, interacting with
+  /// this entity won't open a merchant window.
+  pub fn set_trades(&self, trades: Vec<Trade>) { *self.trades.lock() = trades; }
+  /// Returns `true` if this entity offers any trades.
+  pub fn has_trades(&self) -> bool { !self.trades.lock().is_empty() }
+  /// Returns the shared list of trades. Merchant windows hold onto this, so
+  /// that using a trade updates this entity's trades.
+  pub(crate) fn trades_handle(&self) -> Arc<Mutex<Vec<Trade>>> { self.trades.clone() }
+
+  /// Returns the value of the given attribute, after all modifiers have been
+  /// applied.
+  pub fn attribute(&self, attr: Attribute) -> f64 { self.attrs.lock().get(attr) }
+  /// Returns this entity's attributes. Modifying these will not send any
+  /// updates to clients, so prefer
+  /// [`modify_attributes`](Self::modify_attributes).
+  pub fn attributes(&self) -> MutexGuard<'_, Attributes> { self.attrs.lock() }
+  /// Modifies this entity's attributes, and sends the changes to all nearby
+  /// players.
+  pub fn modify_attributes(&self, f: impl FnOnce(&mut Attributes)) {
+    let mut attrs = self.attrs.lock();
+    f(&mut attrs);
+    let max = attrs.get(Attribute::MaxHealth) as f32;
+    self.send_attributes(&attrs);
+    drop(attrs);
+    let mut health = self.health.lock();
+    *health = health.min(max);
+  }
+  /// Applies the given potion effect for `ticks` ticks. `level` starts at 1.
+  pub fn add_effect(&self, effect: Effect, level: u32, ticks: u32) {
+    let (attr, m) = effect.modifier(level);
+    self.modify_attributes(|a| a.add_modifier(attr, m.with_duration(ticks)));
+  }
+  fn send_attributes(&self, attrs: &Attributes) {
+    if let Some(packet) = attrs.to_packet(self.eid) {
+      broadcast(self.world.read().viewers(self.fpos().chunk()), packet);
+    }
+  }
+
+  /// Ticks this entity, starting from the position `p`. Returns the new
+  /// position, or `None` if this entity should be removed.
+  ///
+  /// The new position is not stored. The world's tick loop reads every
+  /// entity's position before ticking, and writes them all back afterwards.
+  pub(crate) fn tick(&self, mut p: EntityPos) -> Option<EntityPos> {
+    {
+      let mut attrs = self.attrs.lock();
+      if attrs.tick() {
+        self.send_attributes(&attrs);
+      }
+    }
     let old = p.aabb;
     let old_vel = p.vel;
     if self.behavior.lock().tick(&self.world.read(), self, &mut p).0 {
-      return true;
+      return None;
     }
     let w = self.world.read();
     if p.aabb.pos != old.pos {
@@ -315,7 +565,7 @@ impl EntityData {
       let nearby = w.nearby_colliders(old.pos, p.aabb.pos, 1.0, false);
       // Make tmp so that old can be used in world.send_entity_pos.
       let mut tmp = old;
-      if let Some(res) = tmp.move_towards((p.aabb.pos - old.pos).into(), &nearby) {
+      if let Some(res) = tmp.move_towards(p.aabb.pos - old.pos, &nearby) {
         if res.axis.x != 0.0 {
           p.vel.x = 0.0
         } else if res.axis.y != 0.0 {
@@ -328,14 +578,47 @@ impl EntityData {
         }
         p.aabb = tmp;
       }
-      *self.pos.lock() = p;
-      self.world.read().send_entity_pos(self.eid, old.pos, p.aabb.pos, false);
-    } else {
-      *self.pos.lock() = p;
+      w.send_entity_pos(self.eid, old.pos, p.aabb.pos, false);
+      if old.pos.chunk() != p.aabb.pos.chunk() {
+        w.move_entity(self.eid, old.pos.chunk(), p.aabb.pos.chunk());
+      }
     }
     if p.vel != old_vel {
-      self.world.read().send_entity_vel(old.pos.chunk(), self.eid, p.vel);
+      w.send_entity_vel(old.pos.chunk(), self.eid, p.vel);
+    }
+    Some(p)
+  }
+}
+
+impl Drop for EntityData {
+  fn drop(&mut self) { self.ecs.remove(self.eid); }
+}
+
+/// Returns the vanilla attributes for the given entity, with the max health
+/// overriden by the behavior.
+fn default_attributes(ty: Type, behavior: &dyn Behavior) -> Attributes {
+  let mut attrs = Attributes::new(ty);
+  if let Some(max) = behavior.max_health() {
+    attrs.set_base(Attribute::MaxHealth, max.into());
+  }
+  attrs
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn handle_packing() {
+    for handle in [
+      EntityHandle { eid: 2, gen: 1 },
+      EntityHandle { eid: -1, gen: 5 },
+      EntityHandle { eid: i32::MAX, gen: u32::MAX },
+    ] {
+      assert_eq!(EntityHandle::from_u64(handle.to_u64()), handle);
     }
-    false
+    assert_eq!(EntityHandle { eid: 3, gen: 2 }.to_u64(), 2 << 32 | 3);
+    // Generations start at 1, so 0 is never a valid handle.
+    assert_ne!(EntityHandle { eid: 0, gen: next_gen() }.to_u64(), 0);
   }
 }
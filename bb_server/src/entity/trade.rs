@@ -0,0 +1,110 @@
+//! The trades an entity offers. Any entity with trades will open a merchant
+//! window when a player interacts with it. This is normally a villager or a
+//! wandering trader, but plugins can add trades to any entity.
+
+use crate::item::Stack;
+use bb_common::net::cb;
+
+/// A single trade. The player puts `input` (and `second_input`, if it isn't
+/// empty) into the merchant window, and gets `output` back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+  pub input:        Stack,
+  /// If this is empty, the trade only needs one input.
+  pub second_input: Stack,
+  pub output:       Stack,
+  /// The number of times this trade has been used.
+  pub uses:         u32,
+  /// Once `uses` reaches this, the trade is disabled.
+  pub max_uses:     u32,
+  /// The experience shown for this trade.
+  pub xp:           u32,
+}
+
+impl Trade {
+  /// Creates a trade with a single input, that can be used any number of
+  /// times.
+  pub fn new(input: Stack, output: Stack) -> Self {
+    Trade { input, second_input: Stack::empty(), output, uses: 0, max_uses: u32::MAX, xp: 0 }
+  }
+  /// Sets the second input of this trade.
+  pub fn with_second_input(mut self, input: Stack) -> Self {
+    self.second_input = input;
+    self
+  }
+  /// Sets the number of times this trade can be used.
+  pub fn with_max_uses(mut self, max_uses: u32) -> Self {
+    self.max_uses = max_uses;
+    self
+  }
+  /// Sets the experience shown for this trade.
+  pub fn with_xp(mut self, xp: u32) -> Self {
+    self.xp = xp;
+    self
+  }
+
+  /// Returns `true` if this trade has been used `max_uses` times.
+  pub fn is_disabled(&self) -> bool { self.uses >= self.max_uses }
+
+  /// Returns `true` if the given stacks in the merchant window are enough to
+  /// pay for this trade.
+  pub fn matches(&self, a: &Stack, b: &Stack) -> bool {
+    covers(a, &self.input) && covers(b, &self.second_input)
+  }
+
+  /// Removes the price of this trade from the given stacks, and counts a use
+  /// of this trade. This does nothing if the stacks don't match.
+  pub fn apply(&mut self, a: &mut Stack, b: &mut Stack) {
+    if !self.matches(a, b) || self.is_disabled() {
+      return;
+    }
+    a.set_amount(a.amount() - self.input.amount());
+    if !self.second_input.is_empty() {
+      b.set_amount(b.amount() - self.second_input.amount());
+    }
+    self.uses += 1;
+  }
+
+  pub fn to_offer(&self) -> cb::TradeOffer {
+    cb::TradeOffer {
+      input:        self.input.to_item(),
+      second_input: self.second_input.to_item(),
+      output:       self.output.to_item(),
+      disabled:     self.is_disabled(),
+      uses:         self.uses,
+      max_uses:     self.max_uses,
+      xp:           self.xp,
+    }
+  }
+}
+
+/// Returns `true` if `stack` has at least as many items as `price`. An empty
+/// price is always covered.
+fn covers(stack: &Stack, price: &Stack) -> bool {
+  price.is_empty() || (stack.item() == price.item() && stack.amount() >= price.amount())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::item::Type;
+
+  #[test]
+  fn apply() {
+    let mut trade = Trade::new(Stack::new(Type::Emerald).with_amount(3), Stack::new(Type::Bread))
+      .with_max_uses(2);
+    let mut a = Stack::new(Type::Emerald).with_amount(7);
+    let mut b = Stack::empty();
+    assert!(trade.matches(&a, &b));
+    trade.apply(&mut a, &mut b);
+    trade.apply(&mut a, &mut b);
+    assert_eq!(a.amount(), 1);
+    assert!(trade.is_disabled());
+    assert!(!trade.matches(&a, &b));
+
+    // Disabled trades can't be used.
+    let mut a = Stack::new(Type::Emerald).with_amount(3);
+    trade.apply(&mut a, &mut b);
+    assert_eq!(a.amount(), 3);
+  }
+}
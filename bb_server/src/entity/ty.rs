@@ -123,11 +123,53 @@ include!(concat!(env!("OUT_DIR"), "/entity/ty.rs"));
 /// Any data specific to an entity.
 #[derive(Debug)]
 pub struct Data {
-  pub ty:     Type,
-  pub id:     u32,
-  pub name:   &'static str,
-  pub width:  f32,
-  pub height: f32,
+  pub ty:       Type,
+  pub id:       u32,
+  pub name:     &'static str,
+  pub width:    f32,
+  pub height:   f32,
+  /// The spawn category of this entity. Used for natural spawning.
+  pub category: Category,
+}
+
+/// The spawn category of an entity. Each category has a separate mob cap when
+/// spawning naturally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+  Monster,
+  Creature,
+  Ambient,
+  WaterCreature,
+  WaterAmbient,
+  /// Anything that never spawns naturally, like items and projectiles.
+  Misc,
+}
+
+impl Category {
+  /// All the categories that spawn naturally.
+  pub const NATURAL: [Category; 5] = [
+    Category::Monster,
+    Category::Creature,
+    Category::Ambient,
+    Category::WaterCreature,
+    Category::WaterAmbient,
+  ];
+
+  /// Returns the vanilla mob cap for this category, per player.
+  pub fn cap(&self) -> u32 {
+    match self {
+      Self::Monster => 70,
+      Self::Creature => 10,
+      Self::Ambient => 15,
+      Self::WaterCreature => 5,
+      Self::WaterAmbient => 20,
+      Self::Misc => 0,
+    }
+  }
+
+  /// Returns `true` if entities in this category should despawn when far away
+  /// from players. Creatures stay around forever, like in vanilla.
+  pub fn despawns(&self) -> bool { !matches!(self, Self::Creature | Self::Misc) }
 }
 
 impl Data {
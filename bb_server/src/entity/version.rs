@@ -50,6 +50,8 @@ impl TypeConverter {
   /// Returns any data about this item. Includes things like max stack size,
   /// display name, etc.
   pub fn get_data(&self, entity: Type) -> &Data { &self.types[entity.id() as usize] }
+  /// Returns the data for every entity, in id order.
+  pub fn all(&self) -> &[Data] { self.types }
 }
 
 // TODO: Don't include versioning data, as we don't really need it.
@@ -1,8 +1,9 @@
-//! There are three kinds of messages:
+//! There are four kinds of messages:
 //!
 //! - [GlobalEvent], for a non-cancellable event.
 //! - [PlayerEvent], for a non-cancellable event with a player.
 //! - [PlayerRequest], for a cancellable event with a player.
+//! - [GlobalRequest], for a cancellable event without a player.
 
 mod json;
 mod types;
@@ -10,7 +11,7 @@ mod world;
 
 use crate::{
   player::{Click, Player},
-  world::WorldManager,
+  world::{Action, WorldManager},
 };
 use bb_common::util::Hand;
 
@@ -41,9 +42,17 @@ impl Events<'_> {
   pub fn interact(&self, player: &Arc<Player>, _hand: Hand, click: Click) -> EventFlow {
     // self.req(player, ServerRequest::Interact { hand, click });
     let stack = player.lock_inventory().main_hand().clone();
+    player.record_interact();
     try_event!(self.player_request(Interact { player: player.clone(), slot: 36 }));
-    try_event!(self.wm.item_behaviors().call(stack.item(), |i| i.interact(click)));
+    // Items on cooldown can still be used to interact with blocks, like in
+    // vanilla.
+    if !player.has_cooldown(stack.item()) {
+      try_event!(self.wm.item_behaviors().call(stack.item(), |i| i.interact(click)));
+    }
     if let Click::Block(click) = click {
+      if !click.block.world.allows(Some(player), click.block.pos, Action::Interact) {
+        return Handled;
+      }
       try_event!(self
         .wm
         .block_behaviors()
@@ -55,18 +64,27 @@ impl Events<'_> {
   /// Send a [`GlobalEvent`]. All plugins will receive this event, and will not
   /// be able to cancel it.
   pub fn global_event(&self, ev: impl Into<GlobalEvent>) {
+    let _span = tracing::info_span!("plugin global event").entered();
     self.wm.plugins().global_event(ev.into());
   }
   /// Send an [`PlayerEvent`]. All plugins will receive this event, and cannot
   /// cancel it.
   pub fn player_event(&self, ev: impl Into<PlayerEvent>) {
+    let _span = tracing::info_span!("plugin player event").entered();
     self.wm.plugins().player_event(ev.into());
   }
   /// Send a [`PlayerRequest`]. All plugins will receive this event, and can
   /// cancel it.
   pub fn player_request(&self, req: impl Into<PlayerRequest>) -> EventFlow {
+    let _span = tracing::info_span!("plugin player request").entered();
     self.wm.plugins().player_request(req.into())
   }
+  /// Send a [`GlobalRequest`]. All plugins will receive this event, and can
+  /// cancel it.
+  pub fn global_request(&self, req: impl Into<GlobalRequest>) -> EventFlow {
+    let _span = tracing::info_span!("plugin global request").entered();
+    self.wm.plugins().global_request(req.into())
+  }
 }
 
 impl EventFlow {
@@ -1,9 +1,17 @@
 use super::json::*;
-use crate::{block, item::Stack, math::Vec3, player::Player, plugin::IntoPanda};
+use crate::{
+  block,
+  entity::EntityHandle,
+  item::Stack,
+  math::Vec3,
+  player::{Player, Window},
+  plugin::IntoPanda,
+  world::World,
+};
 use bb_common::{
   math::{ChunkPos, FPos, Pos},
   net::sb::ClickWindow,
-  util::GameMode,
+  util::{Chat, GameMode, UUID},
 };
 use panda::{
   define_ty,
@@ -11,8 +19,8 @@ use panda::{
   runtime::{PandaType, Var, VarSend},
   Panda,
 };
-
-use std::sync::Arc;
+use parking_lot::Mutex;
+use std::{fmt, sync::Arc};
 
 /// A message going from the plugin to the server.
 #[derive(Debug, Clone, serde::Deserialize)]
@@ -79,6 +87,11 @@ pub enum ServerMessage {
     #[serde(flatten)]
     request:  PlayerRequest,
   },
+  GlobalRequest {
+    reply_id: u32,
+    #[serde(flatten)]
+    request:  GlobalRequest,
+  },
   Reply {
     reply_id: u32,
     #[serde(flatten)]
@@ -259,6 +272,15 @@ event! {
     old_pos: FPos,
     new_pos: FPos,
   },
+  /// Called after a player moves to another world, with
+  /// [`Player::teleport_world`]. The new world has already been sent to the
+  /// player.
+  PlayerChangeWorld: "player_change_world" {
+    /// The world the player was in.
+    old_world: Arc<World>,
+    /// The world the player is in now.
+    new_world: Arc<World>,
+  },
 }
 event! {
   /// An event from the server to the plugin. This is very similar to
@@ -304,9 +326,34 @@ event! {
   /// Called when a client clicks on an item in an inventory.
   ClickWindowEvent: "click_window" {
     /// The inventory slot of the click.
-    slot: i32,
+    slot:  i32,
     /// The type of window click.
-    mode: ClickWindow,
+    mode:  ClickWindow,
+    /// The item in the clicked slot. For clicks outside of the window, this
+    /// is the same as `held`.
+    stack: Stack,
+    /// The item the player is holding on their cursor.
+    held:  Stack,
+  },
+  /// Called when a window is about to be shown to a player, such as when they
+  /// open a crafting table.
+  ///
+  /// Cancelling this will stop the window from opening.
+  OpenWindow: "window_open" {
+    /// The window being opened.
+    window: Window,
+  },
+  /// Called when a player moves items between an open window and their own
+  /// inventory, by shift clicking. This is called after `click_window`.
+  ///
+  /// Cancelling this will leave the items where they were.
+  ItemMove: "item_move" {
+    /// The stack being moved.
+    stack:     Stack,
+    /// If `true`, the items are moving from the window into the player's
+    /// inventory. If `false`, they are moving from the player's inventory
+    /// into the window.
+    to_player: bool,
   },
   /// Called when a player switches game mode.
   ///
@@ -324,6 +371,9 @@ event! {
   Chat: "chat" {
     /// The entire contents of the chat message, as entered on the client.
     text: String,
+    /// The formatted message, which will be sent to every player. This can be
+    /// modified to change what shows up in chat.
+    message: Arc<Mutex<Chat>>,
   },
   /// Called when a client sends a command.
   ///
@@ -345,6 +395,26 @@ event! {
     /// TODO: Fix.
     slot: i32,
   },
+  /// Called when a player left clicks on an entity, or on another player.
+  ///
+  /// Cancelling this will stop the entity from being damaged. `attack` can be
+  /// used to change the damage and knockback.
+  EntityAttack: "entity_attack" {
+    /// The entity being attacked. This may be a player.
+    entity: EntityHandle,
+    /// The damage and knockback that will be dealt.
+    attack: Arc<Mutex<Attack>>,
+  },
+  /// Called when a player right clicks on an entity.
+  ///
+  /// Cancelling this will stop the default interaction, such as swapping
+  /// items with an armor stand.
+  InteractEntity: "interact_entity" {
+    /// The entity that was clicked. This may be a player.
+    entity: EntityHandle,
+    /// Where the entity was clicked, relative to the entity's position.
+    cursor: FPos,
+  },
   /// Called when a player drops an item.
   ///
   /// Cancelling this event will keep the item in their inventory.
@@ -356,6 +426,17 @@ event! {
     /// be `true` or `false`.
     full_stack: bool,
   },
+  /// Called when a player is about to pick up an item on the ground.
+  ///
+  /// Cancelling this will leave the item on the ground. It will be checked
+  /// again on the next tick, so plugins should expect this to be called many
+  /// times for the same item.
+  ItemPickup: "item_pickup" {
+    /// The item entity.
+    entity: EntityHandle,
+    /// The stack that the player will pick up.
+    stack: Stack,
+  },
   /// Called when a player is damaged.
   ///
   /// Cancelling this will cause the player to not be damaged at all.
@@ -374,6 +455,22 @@ event! {
     /// after damage is applied.
     knockback: Vec3,
   },
+  /// Called when a player tries to break a block, place a block, or attack an
+  /// entity that is too far away. How far players can reach is set by the
+  /// `reach` section of the config.
+  ///
+  /// Cancelling this will allow the action, as if the player was in range.
+  ReachViolation: "reach_violation" {
+    /// What the player tried to do. This is one of `dig`, `place`, or
+    /// `attack`.
+    action:   String,
+    /// The closest point on the block or entity to the player's eyes.
+    target:   FPos,
+    /// How far the player's eyes are from `target`.
+    distance: f64,
+    /// How far the player is allowed to reach, including the tolerance.
+    max:      f64,
+  },
   /// Called when the server receives a packet from a client.
   ///
   /// Cancelling this packet will make it appear as if the packet were
@@ -383,6 +480,66 @@ event! {
   },
 }
 
+event! {
+  /// A request from the server to the plugin. This is very similar to
+  /// [`PlayerRequest`], but there is no player specified with this request.
+  GlobalRequest: {} -> (crate::plugin::types::event::PEventFlow)
+
+  /// Called when a player is about to join, before their player entity is
+  /// created.
+  ///
+  /// Cancelling this will kick the player. `join` can be used to kick them
+  /// with a custom message, or to change where they will spawn.
+  PlayerPreJoin: "player_pre_join" {
+    /// The username of the player joining.
+    username: String,
+    /// The UUID of the player joining.
+    id:       UUID,
+    /// Where the player will spawn, and whether they are allowed to join.
+    join:     Arc<Mutex<PreJoin>>,
+  },
+  /// Called when a projectile, such as a snowball, hits an entity.
+  ///
+  /// Cancelling this will stop the entity from being damaged. The projectile
+  /// will still be removed.
+  ProjectileHit: "projectile_hit" {
+    /// The projectile.
+    projectile: EntityHandle,
+    /// The entity that was hit. This may be a player.
+    hit:        EntityHandle,
+    /// Where the projectile hit the entity.
+    pos:        FPos,
+  },
+}
+
+/// The outcome of a [`PlayerPreJoin`] event. Plugins can change this to deny
+/// the join, or to move the player to a different world.
+#[derive(Clone)]
+pub struct PreJoin {
+  /// If set, the player will be kicked with this message.
+  pub deny:  Option<Chat>,
+  /// The world the player will spawn in.
+  pub world: Arc<World>,
+  /// The position the player will spawn at.
+  pub pos:   FPos,
+}
+
+impl fmt::Debug for PreJoin {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("PreJoin").field("deny", &self.deny).field("pos", &self.pos).finish()
+  }
+}
+
+/// The damage dealt by an [`EntityAttack`]. Plugins can change this to make
+/// an attack stronger or weaker.
+#[derive(Debug, Clone)]
+pub struct Attack {
+  /// The amount of damage, before armor is applied.
+  pub damage:    f32,
+  /// The knockback vector. This will be added to the entity's velocity.
+  pub knockback: Vec3,
+}
+
 /// A reply from the server to the plugin.
 #[non_exhaustive]
 #[derive(Debug, Clone, serde::Serialize)]
@@ -0,0 +1,127 @@
+//! Implements [`Stack::modifier`]
+
+use super::{Stack, Type};
+use crate::entity::{Attribute, Modifier, Operation};
+use bb_common::{
+  net::cb::{ArmorSlot, EquipmentSlot},
+  util::{Hand, UUID},
+};
+
+/// The values of a single piece of armor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Armor {
+  pub slot:      ArmorSlot,
+  pub points:    f64,
+  pub toughness: f64,
+}
+
+impl Type {
+  /// If this is a piece of armor, this returns the slot it is worn in, and how
+  /// much protection it gives.
+  pub fn armor(&self) -> Option<Armor> {
+    use ArmorSlot::*;
+    let (slot, points, toughness) = match self {
+      Type::LeatherHelmet => (Helmet, 1.0, 0.0),
+      Type::LeatherChestplate => (Chestplate, 3.0, 0.0),
+      Type::LeatherLeggings => (Leggings, 2.0, 0.0),
+      Type::LeatherBoots => (Boots, 1.0, 0.0),
+
+      Type::ChainmailHelmet => (Helmet, 2.0, 0.0),
+      Type::ChainmailChestplate => (Chestplate, 5.0, 0.0),
+      Type::ChainmailLeggings => (Leggings, 4.0, 0.0),
+      Type::ChainmailBoots => (Boots, 1.0, 0.0),
+
+      Type::IronHelmet => (Helmet, 2.0, 0.0),
+      Type::IronChestplate => (Chestplate, 6.0, 0.0),
+      Type::IronLeggings => (Leggings, 5.0, 0.0),
+      Type::IronBoots => (Boots, 2.0, 0.0),
+
+      Type::GoldenHelmet => (Helmet, 2.0, 0.0),
+      Type::GoldenChestplate => (Chestplate, 5.0, 0.0),
+      Type::GoldenLeggings => (Leggings, 3.0, 0.0),
+      Type::GoldenBoots => (Boots, 1.0, 0.0),
+
+      Type::DiamondHelmet => (Helmet, 3.0, 2.0),
+      Type::DiamondChestplate => (Chestplate, 8.0, 2.0),
+      Type::DiamondLeggings => (Leggings, 6.0, 2.0),
+      Type::DiamondBoots => (Boots, 3.0, 2.0),
+
+      Type::NetheriteHelmet => (Helmet, 3.0, 3.0),
+      Type::NetheriteChestplate => (Chestplate, 8.0, 3.0),
+      Type::NetheriteLeggings => (Leggings, 6.0, 3.0),
+      Type::NetheriteBoots => (Boots, 3.0, 3.0),
+
+      Type::TurtleHelmet => (Helmet, 2.0, 0.0),
+      _ => return None,
+    };
+    Some(Armor { slot, points, toughness })
+  }
+}
+
+/// The id vanilla uses for the attack damage of the held item.
+const MAIN_HAND_ID: u128 = 0xcb3f55d3_645c_4f38_a497_9c13a33db5cf;
+
+/// The ids vanilla uses for armor modifiers. Each slot has a different id, so
+/// that modifiers from different slots don't replace each other.
+fn armor_id(slot: ArmorSlot) -> u128 {
+  match slot {
+    ArmorSlot::Boots => 0x845db27c_c624_495f_8c9f_6020a9a58b6b,
+    ArmorSlot::Leggings => 0xd8499b04_0e66_4726_ab29_64469d734e0d,
+    ArmorSlot::Chestplate => 0x9f3d476d_c118_4544_8365_64846904b48e,
+    ArmorSlot::Helmet => 0x2ad3f246_fee1_4e67_b886_69fd380bb150,
+  }
+}
+
+impl Stack {
+  /// Returns the modifier this item applies to the given attribute, when it is
+  /// held or worn in the given slot. Armor only applies in the slot it is worn
+  /// in, and weapons only apply in the main hand.
+  pub fn modifier(&self, attr: Attribute, slot: EquipmentSlot) -> Option<Modifier> {
+    let (id, amount) = match (attr, slot) {
+      // Players have a base attack damage of 1, so we only add the difference.
+      (Attribute::AttackDamage, EquipmentSlot::Hand(Hand::Main)) => {
+        (MAIN_HAND_ID, self.attack_damage() as f64 - 1.0)
+      }
+      (Attribute::Armor, EquipmentSlot::Armor(slot)) => {
+        (armor_id(slot), self.item().armor().filter(|a| a.slot == slot)?.points)
+      }
+      (Attribute::ArmorToughness, EquipmentSlot::Armor(slot)) => {
+        (armor_id(slot), self.item().armor().filter(|a| a.slot == slot)?.toughness)
+      }
+      _ => return None,
+    };
+    if amount == 0.0 {
+      return None;
+    }
+    Some(Modifier::new(UUID::from_u128(id), amount, Operation::Add))
+  }
+}
+
+/// Returns the damage dealt after armor is applied. This uses the 1.9+
+/// formula, where toughness reduces the effect of large hits on armor.
+pub fn damage_after_armor(damage: f32, armor: f64, toughness: f64) -> f32 {
+  let damage = damage as f64;
+  let reduce = (armor - damage / (2.0 + toughness / 4.0)).max(armor / 5.0).min(20.0);
+  (damage * (1.0 - reduce / 25.0)) as f32
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn armor() {
+    assert_eq!(damage_after_armor(10.0, 0.0, 0.0), 10.0);
+    // Full diamond armor
+    assert_eq!(damage_after_armor(10.0, 20.0, 8.0), 3.0);
+    let chest = Stack::new(Type::IronChestplate);
+    assert_eq!(
+      chest.modifier(Attribute::Armor, EquipmentSlot::Armor(ArmorSlot::Chestplate)).unwrap().amount,
+      6.0
+    );
+    assert!(chest.modifier(Attribute::Armor, EquipmentSlot::Armor(ArmorSlot::Helmet)).is_none());
+    assert!(chest
+      .modifier(Attribute::ArmorToughness, EquipmentSlot::Armor(ArmorSlot::Chestplate))
+      .is_none());
+  }
+}
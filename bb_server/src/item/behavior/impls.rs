@@ -3,6 +3,7 @@ use crate::{
   block, entity,
   event::EventFlow::{self, *},
   player::{BlockClick, Click},
+  world::Action,
 };
 use bb_common::util::{Chat, Face};
 
@@ -80,6 +81,28 @@ impl Behavior for Snowball {
   }
 }
 
+/// Lights nether portals. If there is no portal frame where the player
+/// clicked, this places fire instead.
+pub struct FlintAndSteel;
+impl Behavior for FlintAndSteel {
+  fn interact(&self, click: Click) -> EventFlow {
+    if let Click::Block(click) = click {
+      let world = click.block.world;
+      let pos = click.block.pos + click.face;
+      if !world.allows(Some(click.player), pos, Action::Place) {
+        click.player.sync_block_at(pos);
+        return Handled;
+      }
+      if !world.light_portal(pos) && world.get_kind(pos) == Ok(block::Kind::Air) {
+        let _ = world.set_kind(pos, block::Kind::Fire);
+      }
+      Handled
+    } else {
+      Continue
+    }
+  }
+}
+
 pub struct Torch {
   pub normal: block::Kind,
   pub wall:   block::Kind,
@@ -53,6 +53,7 @@ impl BehaviorList {
       LavaBucket => impls::Bucket(Some(block::Kind::Lava));
       Bucket => impls::Bucket(None);
       Snowball => impls::Snowball;
+      FlintAndSteel => impls::FlintAndSteel;
       Torch => impls::Torch { normal: block::Kind::Torch, wall: block::Kind::WallTorch };
       SoulTorch => impls::Torch { normal: block::Kind::SoulTorch, wall: block::Kind::SoulWallTorch };
 
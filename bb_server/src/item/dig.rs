@@ -24,6 +24,7 @@ pub enum ToolGrade {
   Iron,
   Gold,
   Diamond,
+  Netherite,
 }
 
 impl Type {
@@ -35,30 +36,35 @@ impl Type {
       Type::IronSword => (ToolType::Sword, ToolGrade::Iron),
       Type::GoldenSword => (ToolType::Sword, ToolGrade::Gold),
       Type::DiamondSword => (ToolType::Sword, ToolGrade::Diamond),
+      Type::NetheriteSword => (ToolType::Sword, ToolGrade::Netherite),
 
       Type::WoodenPickaxe => (ToolType::Pickaxe, ToolGrade::Wood),
       Type::StonePickaxe => (ToolType::Pickaxe, ToolGrade::Stone),
       Type::IronPickaxe => (ToolType::Pickaxe, ToolGrade::Iron),
       Type::GoldenPickaxe => (ToolType::Pickaxe, ToolGrade::Gold),
       Type::DiamondPickaxe => (ToolType::Pickaxe, ToolGrade::Diamond),
+      Type::NetheritePickaxe => (ToolType::Pickaxe, ToolGrade::Netherite),
 
       Type::WoodenAxe => (ToolType::Axe, ToolGrade::Wood),
       Type::StoneAxe => (ToolType::Axe, ToolGrade::Stone),
       Type::IronAxe => (ToolType::Axe, ToolGrade::Iron),
       Type::GoldenAxe => (ToolType::Axe, ToolGrade::Gold),
       Type::DiamondAxe => (ToolType::Axe, ToolGrade::Diamond),
+      Type::NetheriteAxe => (ToolType::Axe, ToolGrade::Netherite),
 
       Type::WoodenShovel => (ToolType::Shovel, ToolGrade::Wood),
       Type::StoneShovel => (ToolType::Shovel, ToolGrade::Stone),
       Type::IronShovel => (ToolType::Shovel, ToolGrade::Iron),
       Type::GoldenShovel => (ToolType::Shovel, ToolGrade::Gold),
       Type::DiamondShovel => (ToolType::Shovel, ToolGrade::Diamond),
+      Type::NetheriteShovel => (ToolType::Shovel, ToolGrade::Netherite),
 
       Type::WoodenHoe => (ToolType::Hoe, ToolGrade::Wood),
       Type::StoneHoe => (ToolType::Hoe, ToolGrade::Stone),
       Type::IronHoe => (ToolType::Hoe, ToolGrade::Iron),
       Type::GoldenHoe => (ToolType::Hoe, ToolGrade::Gold),
       Type::DiamondHoe => (ToolType::Hoe, ToolGrade::Diamond),
+      Type::NetheriteHoe => (ToolType::Hoe, ToolGrade::Netherite),
       _ => return None,
     };
     Some(Tool { ty, grade })
@@ -66,27 +72,19 @@ impl Type {
 }
 
 impl Tool {
+  /// Returns the kind of tool this is.
+  pub fn ty(&self) -> ToolType { self.ty }
+  /// Returns the material this tool is made of.
+  pub fn grade(&self) -> ToolGrade { self.grade }
+
+  /// Returns `true` if this tool is the correct tool for the given block. This
+  /// uses the block's loot table if it has a harvest tool, and falls back to
+  /// the block material otherwise.
   pub fn does_mine(&self, block: &block::Data) -> bool {
-    if !self.ty.does_mine(block.material) {
-      return false;
+    match block.loot.harvest {
+      Some(harvest) => self.ty == harvest.tool && self.grade.mining_level() >= harvest.level,
+      None => self.ty.does_mine(block.material),
     }
-    let required_grade = match block.kind {
-      block::Kind::IronBlock
-      | block::Kind::IronOre
-      | block::Kind::LapisBlock
-      | block::Kind::LapisOre => ToolGrade::Stone,
-      block::Kind::DiamondBlock
-      | block::Kind::DiamondOre
-      | block::Kind::EmeraldBlock
-      | block::Kind::EmeraldOre
-      | block::Kind::GoldBlock
-      | block::Kind::GoldOre
-      | block::Kind::RedstoneOre => ToolGrade::Iron,
-      block::Kind::Obsidian => ToolGrade::Diamond,
-
-      _ => ToolGrade::Wood,
-    };
-    self.grade.mining_level() >= required_grade.mining_level()
   }
 }
 
@@ -109,6 +107,7 @@ impl ToolGrade {
       Self::Stone => 1,
       Self::Iron => 2,
       Self::Diamond => 3,
+      Self::Netherite => 4,
     }
   }
   pub fn base_speed(&self) -> f64 {
@@ -117,44 +116,106 @@ impl ToolGrade {
       ToolGrade::Stone => 4.0,
       ToolGrade::Iron => 6.0,
       ToolGrade::Diamond => 8.0,
+      ToolGrade::Netherite => 9.0,
       ToolGrade::Gold => 12.0,
     }
   }
 }
 
+impl ToolType {
+  /// Returns the block tag for blocks that this tool mines quickly, such as
+  /// `mineable/pickaxe`. Swords don't have a tag.
+  fn mineable_tag(&self) -> Option<&'static str> {
+    match self {
+      Self::Sword => None,
+      Self::Pickaxe => Some("mineable/pickaxe"),
+      Self::Axe => Some("mineable/axe"),
+      Self::Shovel => Some("mineable/shovel"),
+      Self::Hoe => Some("mineable/hoe"),
+    }
+  }
+}
+
 impl Stack {
-  /// Using the item type, the block being mined, and the efficiency of this
-  /// item stack, this returns the base speed to mine a block of the given type.
-  pub fn mining_speed(&self, block: &block::Data) -> f64 {
-    // If we can harvest the block, this is 30. Otherwise, this is 100.
-    let div;
-    let mut speed = if block.material.requires_tool() {
-      // requires tool
-      if let Some(tool) = self.item().tool() {
-        if tool.does_mine(block) {
-          div = 30.0;
-          tool.grade.base_speed()
-        } else {
-          // the tool we have isn't correct
-          div = 100.0;
-          1.0
-        }
+  /// Returns how fast this item breaks the given block, before efficiency,
+  /// effects, or the block's hardness are applied. This is 1 for items that
+  /// aren't tools, and for tools used on the wrong block.
+  pub fn destroy_speed(&self, block: &block::Data) -> f64 {
+    if self.item() == Type::Shears {
+      return if block.kind == block::Kind::Cobweb || block.has_tag("leaves") {
+        15.0
+      } else if block.has_tag("wool") {
+        5.0
+      } else if matches!(block.kind, block::Kind::Vine | block::Kind::GlowLichen) {
+        2.0
       } else {
-        // we don't have a tool
-        div = 100.0;
         1.0
-      }
-    } else {
-      // doesn't require tool
-      div = 30.0;
-      1.0
+      };
+    }
+    let tool = match self.item().tool() {
+      Some(tool) => tool,
+      None => return 1.0,
     };
+    match tool.ty.mineable_tag() {
+      Some(tag) if block.has_tag(tag) => tool.grade.base_speed(),
+      Some(_) => 1.0,
+      None if block.kind == block::Kind::Cobweb => 15.0,
+      None
+        if matches!(
+          block.material,
+          Material::Plant | Material::ReplaceablePlant | Material::Leaves | Material::SolidOrganic
+        ) =>
+      {
+        1.5
+      }
+      None => 1.0,
+    }
+  }
 
+  /// Using the item type, the block being mined, and the efficiency of this
+  /// item stack, this returns the fraction of the block that is broken each
+  /// tick. This doesn't include effects like haste, which are applied by the
+  /// player.
+  ///
+  /// Unbreakable blocks, like bedrock, will always return 0.
+  pub fn mining_speed(&self, block: &block::Data) -> f64 {
+    if block.hardness < 0.0 {
+      return 0.0;
+    }
+    let mut speed = self.destroy_speed(block);
+    // Efficiency only helps if this is the right tool.
     let efficiency = self.enchantment(enchantment::Type::Efficiency);
-    if efficiency != 0 {
+    if speed > 1.0 && efficiency != 0 {
       speed += (efficiency as i32 * efficiency as i32 + 1) as f64;
     }
-
+    // Blocks that won't drop anything take longer to break.
+    let div = if block.loot.can_harvest(self) { 30.0 } else { 100.0 };
     speed / block.hardness as f64 / div
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::block::{Kind, TypeConverter};
+
+  #[test]
+  fn mining_speed() {
+    let conv = TypeConverter::new();
+    let speed = |item, kind| Stack::new(item).mining_speed(conv.get(kind));
+    let stone = conv.get(Kind::Stone).hardness as f64;
+    assert_eq!(Stack::empty().mining_speed(conv.get(Kind::Stone)), 1.0 / stone / 100.0);
+    assert_eq!(speed(Type::WoodenPickaxe, Kind::Stone), 2.0 / stone / 30.0);
+    assert_eq!(speed(Type::NetheritePickaxe, Kind::Stone), 9.0 / stone / 30.0);
+    // The right tool, but not a high enough level to get drops.
+    let ore = conv.get(Kind::DiamondOre).hardness as f64;
+    assert_eq!(speed(Type::WoodenPickaxe, Kind::DiamondOre), 2.0 / ore / 100.0);
+    // Blocks that don't need a tool are still faster with the right one.
+    let dirt = conv.get(Kind::Dirt).hardness as f64;
+    assert_eq!(speed(Type::IronShovel, Kind::Dirt), 6.0 / dirt / 30.0);
+    assert_eq!(speed(Type::IronPickaxe, Kind::Dirt), 1.0 / dirt / 30.0);
+
+    assert!(speed(Type::Shears, Kind::OakLeaves) >= 1.0);
+    assert_eq!(speed(Type::NetheritePickaxe, Kind::Bedrock), 0.0);
+  }
+}
@@ -150,9 +150,10 @@ impl<const N: usize> SingleInventory<N> {
   #[track_caller]
   pub fn sync_raw(&self, index: u32) {
     self.conn.send(cb::packet::WindowItem {
-      wid:  self.wid,
-      slot: (index + self.offset) as i32,
-      item: self.get_raw(index).unwrap().to_item(),
+      wid:      self.wid,
+      slot:     (index + self.offset) as i32,
+      item:     self.get_raw(index).unwrap().to_item(),
+      revision: 0,
     });
   }
   /// Syncs the item at the index. The offset will first be subtracted from the
@@ -160,9 +161,10 @@ impl<const N: usize> SingleInventory<N> {
   #[track_caller]
   pub(crate) fn sync(&self, index: u32) {
     self.conn.send(cb::packet::WindowItem {
-      wid:  self.wid,
-      slot: index as i32,
-      item: self.get(index).unwrap().to_item(),
+      wid:      self.wid,
+      slot:     index as i32,
+      item:     self.get(index).unwrap().to_item(),
+      revision: 0,
     });
   }
 
@@ -179,9 +181,10 @@ impl<const N: usize> SingleInventory<N> {
     // is mutably borrowed for the entire loop.
     let sync = |index: u32, item: &Stack| {
       self.conn.send(cb::packet::WindowItem {
-        wid:  self.wid,
-        slot: (index + self.offset) as i32,
-        item: item.to_item(),
+        wid:      self.wid,
+        slot:     (index + self.offset) as i32,
+        item:     item.to_item(),
+        revision: 0,
       });
     };
     let mut remaining = stack.amount();
@@ -309,9 +312,10 @@ impl<const N: usize> WrappedInventory<N> {
   pub fn sync_raw(&self, index: u32) {
     for conn in self.viewers.values() {
       conn.send(cb::packet::WindowItem {
-        wid:  self.wid,
-        slot: (index + self.offset) as i32,
-        item: self.get_raw(index).unwrap().to_item(),
+        wid:      self.wid,
+        slot:     (index + self.offset) as i32,
+        item:     self.get_raw(index).unwrap().to_item(),
+        revision: 0,
       });
     }
   }
@@ -321,9 +325,10 @@ impl<const N: usize> WrappedInventory<N> {
   pub(crate) fn sync(&self, index: u32) {
     for conn in self.viewers.values() {
       conn.send(cb::packet::WindowItem {
-        wid:  self.wid,
-        slot: index as i32,
-        item: self.get(index).unwrap().to_item(),
+        wid:      self.wid,
+        slot:     index as i32,
+        item:     self.get(index).unwrap().to_item(),
+        revision: 0,
       });
     }
   }
@@ -342,9 +347,10 @@ impl<const N: usize> WrappedInventory<N> {
     let sync = |index: u32, item: &Stack| {
       for conn in self.viewers.values() {
         conn.send(cb::packet::WindowItem {
-          wid:  self.wid,
-          slot: (index + self.offset) as i32,
-          item: item.to_item(),
+          wid:      self.wid,
+          slot:     (index + self.offset) as i32,
+          item:     item.to_item(),
+          revision: 0,
         });
       }
     };
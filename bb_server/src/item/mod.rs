@@ -1,3 +1,4 @@
+mod armor;
 mod attack;
 mod behavior;
 mod dig;
@@ -8,7 +9,9 @@ mod ty;
 mod ui;
 mod version;
 
+pub use armor::{damage_after_armor, Armor};
 pub use behavior::Behavior;
+pub use dig::{Tool, ToolGrade, ToolType};
 pub use inventory::{Inventory, SingleInventory, WrappedInventory};
 pub use shared::SharedInventory;
 pub use stack::Stack;
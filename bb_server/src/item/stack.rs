@@ -1,6 +1,6 @@
 use super::Type;
 use crate::enchantment;
-use bb_common::util::{Item, ItemData};
+use bb_common::util::{AttributeModifier, Chat, Item, ItemData};
 use bb_transfer::{
   MessageRead, MessageReader, MessageWrite, MessageWriter, ReadError, StructRead, StructReader,
   WriteError,
@@ -103,6 +103,50 @@ impl Stack {
     self
   }
 
+  /// Sets the display name of this item. This replaces the item's default
+  /// name.
+  pub fn with_name(mut self, name: impl Into<Chat>) -> Self {
+    self.data.display.name = Some(name.into());
+    self
+  }
+  /// Adds a line of lore to this item. Lore shows up below the item name, when
+  /// hovering over the item in an inventory.
+  pub fn with_lore(mut self, line: impl Into<Chat>) -> Self {
+    self.data.display.lore.push(line.into());
+    self
+  }
+  /// Sets the given enchantment to the given level. If the level is 0, the
+  /// enchantment will be removed.
+  pub fn with_enchantment(mut self, ty: enchantment::Type, level: u8) -> Self {
+    self.set_enchantment(ty, level);
+    self
+  }
+  /// Sets the given enchantment to the given level. If the level is 0, the
+  /// enchantment will be removed.
+  pub fn set_enchantment(&mut self, ty: enchantment::Type, level: u8) {
+    match NonZeroU8::new(level) {
+      Some(level) => {
+        self.data.enchantments_mut().insert(ty.id(), level);
+      }
+      None => {
+        if let Some(ench) = &mut self.data.enchantments {
+          ench.remove(&ty.id());
+        }
+      }
+    }
+  }
+  /// Adds an attribute modifier to this item.
+  pub fn with_attribute(mut self, modifier: AttributeModifier) -> Self {
+    self.data.attributes.push(modifier);
+    self
+  }
+  /// Makes this item unbreakable (or breakable again). Unbreakable items don't
+  /// lose durability.
+  pub fn with_unbreakable(mut self, unbreakable: bool) -> Self {
+    self.data.unbreakable = unbreakable;
+    self
+  }
+
   /// Returns the level of enchantment, or `0` if the stack doesn't have that
   /// enchantment.
   pub fn enchantment(&self, ty: enchantment::Type) -> u8 {
@@ -127,6 +171,24 @@ impl Stack {
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_builders() {
+    let stack = Stack::new(Type::DiamondSword)
+      .with_name("Sword")
+      .with_lore("line 1")
+      .with_lore("line 2")
+      .with_enchantment(enchantment::Type::Sharpness, 5)
+      .with_enchantment(enchantment::Type::Knockback, 2)
+      .with_enchantment(enchantment::Type::Knockback, 0)
+      .with_unbreakable(true);
+    assert_eq!(stack.data().display.name, Some(Chat::new("Sword")));
+    assert_eq!(stack.data().display.lore.len(), 2);
+    assert_eq!(stack.enchantment(enchantment::Type::Sharpness), 5);
+    assert_eq!(stack.enchantment(enchantment::Type::Knockback), 0);
+    assert!(stack.data().unbreakable);
+    assert_eq!(stack.to_item().data, *stack.data());
+  }
+
   #[test]
   fn test_air() {
     assert_eq!(Stack::empty(), Stack::new(Type::Air));
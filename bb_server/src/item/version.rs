@@ -54,6 +54,8 @@ impl TypeConverter {
   /// Returns any data about this item. Includes things like max stack size,
   /// display name, etc.
   pub fn get_data(&self, item: Type) -> &Data { &self.types[item.id() as usize] }
+  /// Returns the data for every item, in id order.
+  pub fn all(&self) -> &[Data] { self.types }
 }
 
 /// This is the conversion table for a single old version of the game and the
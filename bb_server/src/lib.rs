@@ -5,13 +5,11 @@ extern crate log;
 #[macro_use]
 extern crate thiserror;
 
-use rand::rngs::ThreadRng;
-use std::cell::RefCell;
-
 pub mod block;
 pub mod command;
 pub mod config;
 pub mod data;
+pub mod dump;
 pub mod enchantment;
 pub mod entity;
 pub mod event;
@@ -21,6 +19,7 @@ pub mod net;
 pub mod particle;
 pub mod player;
 pub mod plugin;
+pub mod profile;
 pub mod rcon;
 pub mod tags;
 pub mod util;
@@ -70,5 +69,3 @@ mod tests {
   #[test]
   fn generate_docs() { generate_panda_docs(); }
 }
-
-thread_local!(pub(crate) static RNG: RefCell<ThreadRng> = RefCell::new(rand::thread_rng()));
@@ -3,7 +3,7 @@ extern crate log;
 
 use bb_server::{net::ConnectionManager, rcon::RCon, world::WorldManager};
 use clap::Parser;
-use std::{sync::Arc, thread};
+use std::{path::PathBuf, sync::Arc, thread};
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
@@ -22,6 +22,24 @@ struct Args {
   /// the existing config.
   #[clap(long)]
   write_default_config: bool,
+
+  /// Writes all the generated block, item, entity, and particle registries
+  /// for every supported version to json files in the given directory, and
+  /// then exits.
+  #[clap(long, value_name = "DIR")]
+  dump_registries: Option<PathBuf>,
+
+  /// Upgrades every region in `world/chunks` to the latest save format, and
+  /// then exits. The server must not be running while this is used.
+  #[clap(long)]
+  migrate_world: bool,
+
+  /// Converts the vanilla world at the given path to bamboo's format, and
+  /// then exits. The overworld is saved to `world.chunks` from the config,
+  /// and the nether and end are saved to `world/nether/chunks` and
+  /// `world/end/chunks`.
+  #[clap(long, value_name = "PATH")]
+  import_world: Option<PathBuf>,
 }
 
 // #[derive(Clone)]
@@ -69,6 +87,26 @@ fn main() {
   let args = Args::parse();
 
   bb_common::init_with_level("server", log::LevelFilter::Info);
+  bb_server::profile::init();
+
+  if let Some(dir) = args.dump_registries {
+    if let Err(e) = bb_server::dump::dump_registries(&dir) {
+      error!("could not dump registries: {e}");
+    }
+    return;
+  }
+
+  if args.migrate_world {
+    match bb_server::world::migrate_world(&PathBuf::new().join("world").join("chunks")) {
+      Ok(summary) if summary.failed > 0 => std::process::exit(1),
+      Ok(_) => {}
+      Err(e) => {
+        error!("could not migrate world: {e}");
+        std::process::exit(1);
+      }
+    }
+    return;
+  }
 
   let config = if args.write_default_config {
     bb_server::load_config_write_default("server.toml", "server-default.toml")
@@ -77,6 +115,23 @@ fn main() {
   };
 
   log::set_max_level(config.log_level);
+  bb_common::logger::set_json(config.log_json);
+  if let Err(e) = bb_common::logger::set_file(config.log_file.to_logger()) {
+    error!("could not open log file: {e}");
+  }
+
+  if let Some(path) = args.import_world {
+    let wm = Arc::new(WorldManager::new_with_config(config));
+    match bb_server::world::import_world(&wm, &path) {
+      Ok(summary) if summary.failed > 0 => std::process::exit(1),
+      Ok(_) => {}
+      Err(e) => {
+        error!("could not import world: {e}");
+        std::process::exit(1);
+      }
+    }
+    return;
+  }
 
   if !args.no_docs {
     bb_server::generate_panda_docs();
@@ -1,10 +1,9 @@
-mod aabb;
 mod point;
 mod point_grid;
 mod vec;
 mod voronoi;
 
-pub use aabb::{CollisionResult, AABB};
+pub use bb_common::math::{CollisionResult, AABB};
 pub use point::{Pdope, Point, Vector};
 pub use point_grid::PointGrid;
 pub use vec::Vec3;
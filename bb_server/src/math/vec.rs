@@ -187,7 +187,7 @@ impl Vec3 {
       axis!(t_max, z: Vec3::new(0.0, 0.0, 1.0), (min_x, max_x): x, (min_y, max_y): y);
 
       if let Some(axis) = axis {
-        result = Some(CollisionResult { axis, factor: time })
+        result = Some(CollisionResult { axis: axis.into(), factor: time })
       }
     }
 
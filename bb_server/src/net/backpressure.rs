@@ -0,0 +1,174 @@
+//! Handling for clients that can't keep up with the packets they are sent.
+//!
+//! Sending a packet never blocks. Instead, packets are buffered in the
+//! connection. Once that buffer gets too large, unimportant packets are
+//! dropped, based on the player's [`DropPolicy`]. If a client stays behind for
+//! too long, they are kicked.
+
+use bb_common::net::cb;
+use parking_lot::Mutex;
+use std::{
+  sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering},
+  time::{Duration, Instant},
+};
+
+/// How important a packet is. When a client falls behind, the least important
+/// packets are dropped first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+  /// A partial chunk, which is sent after a large block change.
+  ChunkResend,
+  /// A particle.
+  Particle,
+  /// Everything else. These are never dropped.
+  Normal,
+}
+
+impl Priority {
+  pub fn of(p: &cb::Packet) -> Self {
+    match p {
+      cb::Packet::Chunk { full: false, .. } => Priority::ChunkResend,
+      cb::Packet::Particle { .. } => Priority::Particle,
+      _ => Priority::Normal,
+    }
+  }
+}
+
+/// Which packets can be dropped when a client falls behind.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DropPolicy {
+  /// Never drop anything. The client will be kicked if they fall behind for too
+  /// long.
+  Never,
+  /// Drop chunk resends once the client is behind.
+  ChunkResends,
+  /// Drop chunk resends once the client is behind, and drop particles as well
+  /// once the client is twice as far behind.
+  #[default]
+  ChunkResendsAndParticles,
+}
+
+impl DropPolicy {
+  /// Returns `true` if a packet with the given priority should be dropped.
+  /// `buffered` is the number of bytes waiting to be sent, and `limit` is the
+  /// number of bytes where a client is considered behind.
+  pub fn should_drop(self, priority: Priority, buffered: usize, limit: usize) -> bool {
+    match priority {
+      Priority::ChunkResend => self >= DropPolicy::ChunkResends && buffered >= limit,
+      Priority::Particle => {
+        self >= DropPolicy::ChunkResendsAndParticles && buffered >= limit.saturating_mul(2)
+      }
+      Priority::Normal => false,
+    }
+  }
+
+  fn from_u8(v: u8) -> Self {
+    match v {
+      0 => DropPolicy::Never,
+      1 => DropPolicy::ChunkResends,
+      _ => DropPolicy::ChunkResendsAndParticles,
+    }
+  }
+}
+
+/// The state shared between a connection and all of its senders.
+#[derive(Debug)]
+pub(crate) struct SendState {
+  policy:       AtomicU8,
+  dropped:      AtomicU64,
+  /// When the connection went over the buffer limit. `None` if it is under
+  /// the limit.
+  behind_since: Mutex<Option<Instant>>,
+  /// Set when the connection should be closed.
+  close:        AtomicBool,
+  /// The revision of the last inventory update sent to the client.
+  revision:     AtomicU32,
+}
+
+impl Default for SendState {
+  fn default() -> Self { SendState::new() }
+}
+
+impl SendState {
+  pub fn new() -> Self {
+    SendState {
+      policy:       AtomicU8::new(DropPolicy::default() as u8),
+      dropped:      AtomicU64::new(0),
+      behind_since: Mutex::new(None),
+      close:        AtomicBool::new(false),
+      revision:     AtomicU32::new(0),
+    }
+  }
+
+  pub fn policy(&self) -> DropPolicy { DropPolicy::from_u8(self.policy.load(Ordering::Relaxed)) }
+  pub fn set_policy(&self, policy: DropPolicy) {
+    self.policy.store(policy as u8, Ordering::Relaxed);
+  }
+
+  pub fn dropped(&self) -> u64 { self.dropped.load(Ordering::Relaxed) }
+  pub fn record_drop(&self) { self.dropped.fetch_add(1, Ordering::Relaxed); }
+
+  /// Updates whether the connection is behind.
+  pub fn set_behind(&self, behind: bool) {
+    let mut since = self.behind_since.lock();
+    match (behind, *since) {
+      (true, None) => *since = Some(Instant::now()),
+      (false, Some(_)) => *since = None,
+      _ => {}
+    }
+  }
+  /// Returns how long the connection has been behind for.
+  pub fn behind_for(&self) -> Option<Duration> { self.behind_since.lock().map(|t| t.elapsed()) }
+
+  pub fn close(&self) { self.close.store(true, Ordering::Relaxed); }
+  pub fn should_close(&self) -> bool { self.close.load(Ordering::Relaxed) }
+
+  pub fn revision(&self) -> u32 { self.revision.load(Ordering::Relaxed) }
+  /// Increments the inventory revision, and returns the new value. Like
+  /// vanilla, this wraps around at 2^15.
+  pub fn next_revision(&self) -> u32 {
+    let prev = self
+      .revision
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |r| Some((r + 1) & 0x7fff))
+      .unwrap();
+    (prev + 1) & 0x7fff
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn drop_order() {
+    let policy = DropPolicy::default();
+    assert!(!policy.should_drop(Priority::ChunkResend, 99, 100));
+    assert!(policy.should_drop(Priority::ChunkResend, 100, 100));
+    assert!(!policy.should_drop(Priority::Particle, 100, 100));
+    assert!(policy.should_drop(Priority::Particle, 200, 100));
+    assert!(!policy.should_drop(Priority::Normal, 1000, 100));
+
+    assert!(policy.should_drop(Priority::ChunkResend, 200, 100));
+    assert!(!DropPolicy::ChunkResends.should_drop(Priority::Particle, 200, 100));
+    assert!(!DropPolicy::Never.should_drop(Priority::ChunkResend, 200, 100));
+  }
+
+  #[test]
+  fn policy_roundtrip() {
+    let state = SendState::new();
+    assert_eq!(state.policy(), DropPolicy::ChunkResendsAndParticles);
+    state.set_policy(DropPolicy::Never);
+    assert_eq!(state.policy(), DropPolicy::Never);
+    state.set_policy(DropPolicy::ChunkResends);
+    assert_eq!(state.policy(), DropPolicy::ChunkResends);
+  }
+
+  #[test]
+  fn revision_wraps() {
+    let state = SendState::new();
+    assert_eq!(state.next_revision(), 1);
+    assert_eq!(state.revision(), 1);
+    state.revision.store(0x7fff, Ordering::Relaxed);
+    assert_eq!(state.next_revision(), 0);
+  }
+}
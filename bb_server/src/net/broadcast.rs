@@ -0,0 +1,91 @@
+//! Sending the same packet to many players. A [`Broadcast`] serializes its
+//! packet once for each protocol version it is sent to, and every connection
+//! shares those bytes, instead of serializing the packet again.
+
+use super::{backpressure::Priority, encode};
+use crate::player::Player;
+use bb_common::{net::cb, version::ProtocolVersion};
+use std::sync::{
+  atomic::{AtomicU64, Ordering},
+  Arc,
+};
+
+static SENT: AtomicU64 = AtomicU64::new(0);
+static ENCODED: AtomicU64 = AtomicU64::new(0);
+static BYTES_SAVED: AtomicU64 = AtomicU64::new(0);
+
+/// Totals for every broadcast since the server started.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastStats {
+  /// The number of packets sent with shared bytes.
+  pub sent:        u64,
+  /// The number of times a packet was serialized. The difference between this
+  /// and `sent` is the number of serializations that were skipped.
+  pub encoded:     u64,
+  /// The total size of all the serializations that were skipped.
+  pub bytes_saved: u64,
+}
+
+/// Returns the totals for every broadcast so far.
+pub fn stats() -> BroadcastStats {
+  BroadcastStats {
+    sent:        SENT.load(Ordering::Relaxed),
+    encoded:     ENCODED.load(Ordering::Relaxed),
+    bytes_saved: BYTES_SAVED.load(Ordering::Relaxed),
+  }
+}
+
+/// A packet that is going to be sent to a number of players.
+///
+/// Players that need a modified version of this packet (because they have
+/// hidden someone, or because the packet is about a disguised entity) will be
+/// sent the packet with [`Player::send`], so they still see the right thing.
+pub struct Broadcast {
+  packet:   cb::Packet,
+  priority: Priority,
+  encoded:  Vec<(ProtocolVersion, Arc<[u8]>)>,
+}
+
+impl Broadcast {
+  pub fn new(p: impl Into<cb::Packet>) -> Self {
+    let packet = p.into();
+    Broadcast { priority: Priority::of(&packet), packet, encoded: vec![] }
+  }
+
+  /// Returns the packet being sent.
+  pub fn packet(&self) -> &cb::Packet { &self.packet }
+
+  /// Sends the packet to `player`. The first time this is called for each
+  /// protocol version, the packet is serialized. After that, the same bytes are
+  /// reused.
+  pub fn send(&mut self, player: &Player) {
+    if !player.can_share(&self.packet) {
+      player.send(self.packet.clone());
+      return;
+    }
+    let ver = player.ver();
+    let data = match self.encoded.iter().find(|(v, _)| *v == ver) {
+      Some((_, data)) => {
+        BYTES_SAVED.fetch_add(data.len() as u64, Ordering::Relaxed);
+        data.clone()
+      }
+      None => {
+        let data: Arc<[u8]> = encode(&self.packet).into();
+        ENCODED.fetch_add(1, Ordering::Relaxed);
+        self.encoded.push((ver, data.clone()));
+        data
+      }
+    };
+    SENT.fetch_add(1, Ordering::Relaxed);
+    player.send_encoded(data, self.priority);
+  }
+}
+
+/// Sends the packet `p` to all of the given players, sharing the serialized
+/// packet between them.
+pub fn broadcast<P: AsRef<Player>>(players: impl IntoIterator<Item = P>, p: impl Into<cb::Packet>) {
+  let mut b = Broadcast::new(p);
+  for player in players {
+    b.send(player.as_ref());
+  }
+}
@@ -1,17 +1,21 @@
 use crate::{player::Player, world::WorldManager};
 use bb_common::{
-  net::{cb, sb},
-  util::{JoinInfo, ThreadPool},
+  net::{
+    cb,
+    protocol::{Negotiated, ProtocolInfo},
+    sb,
+  },
+  util::{chat::Color, Chat, JoinInfo, JoinMode, ThreadPool, UUID},
   version::ProtocolVersion,
 };
 use bb_transfer::{
-  InvalidReadError, MessageRead, MessageReader, MessageWrite, MessageWriter, ReadError,
+  InvalidReadError, MessageRead, MessageReader, MessageWrite, MessageWriter, ReadError, ReadLimits,
 };
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use mio::{
   event::Event,
   net::{TcpListener, TcpStream},
-  Events, Interest, Poll, Token, Waker,
+  Events, Interest, Poll, Registry, Token, Waker,
 };
 use parking_lot::{Mutex, RwLock};
 use std::{
@@ -24,25 +28,44 @@ use std::{
     atomic::{AtomicBool, Ordering},
     Arc,
   },
+  time::{Duration, Instant},
 };
 
+pub mod backpressure;
+pub mod broadcast;
 pub mod packet;
 pub(crate) mod serialize;
 
+use backpressure::{DropPolicy, Priority, SendState};
+
 #[cfg(test)]
 mod tests;
 
 pub struct Connection {
-  stream: TcpStream,
-  ver:    Option<ProtocolVersion>,
-  closed: AtomicBool,
+  stream:   TcpStream,
+  ver:      Option<ProtocolVersion>,
+  /// The internal protocol version agreed on with the proxy. This is set
+  /// along with `ver`.
+  protocol: Option<Negotiated>,
+  closed:   AtomicBool,
 
   /// Sending on this will send a packet to the client.
-  tx:    Sender<cb::Packet>,
-  rx:    Receiver<cb::Packet>,
+  tx:    Sender<Outgoing>,
+  rx:    Receiver<Outgoing>,
   wake:  Sender<WakeEvent>,
   waker: Arc<Waker>,
   tok:   Token,
+  state: Arc<SendState>,
+
+  /// Once `outgoing` is this large, the client is behind. See the
+  /// [`backpressure`] module.
+  buffer_limit: usize,
+
+  /// The last time we received anything from the proxy.
+  last_heard: Instant,
+  /// Set once the proxy sends `Leave`. If the connection closes without this,
+  /// the player is held onto, in case the proxy resumes the connection.
+  left:       bool,
 
   incoming: Vec<u8>,
   outgoing: Vec<u8>,
@@ -51,10 +74,49 @@ pub struct Connection {
 
 #[derive(Debug, Clone)]
 pub struct ConnSender {
-  tx:    Sender<cb::Packet>,
+  tx:    Sender<Outgoing>,
   wake:  Sender<WakeEvent>,
   waker: Arc<Waker>,
   tok:   Token,
+  state: Arc<SendState>,
+}
+
+/// Something queued to be sent to a client.
+#[derive(Debug, Clone)]
+pub enum Outgoing {
+  /// A packet, which will be serialized by the connection.
+  Packet(cb::Packet),
+  /// A packet that has already been serialized, including the length prefix.
+  /// This is shared between every connection it is sent to. See
+  /// [`Broadcast`](broadcast::Broadcast).
+  Encoded(Arc<[u8]>, Priority),
+}
+
+impl Outgoing {
+  /// Returns the packet this represents. Encoded packets are deserialized
+  /// again, so this is slow, and is only meant for tests.
+  pub fn into_packet(self) -> cb::Packet {
+    match self {
+      Outgoing::Packet(p) => p,
+      Outgoing::Encoded(data, _) => {
+        let mut m = MessageReader::new(&data);
+        m.read_u32().unwrap();
+        cb::Packet::read(&mut m).unwrap()
+      }
+    }
+  }
+}
+
+/// Serializes the given packet, with a length prefix, just like a connection
+/// would.
+pub(crate) fn encode(p: &cb::Packet) -> Vec<u8> {
+  let mut body = vec![];
+  p.write(&mut MessageWriter::new(&mut body)).unwrap();
+
+  let mut out = Vec::with_capacity(body.len() + 5);
+  MessageWriter::new(&mut out).write_u32(body.len().try_into().unwrap()).unwrap();
+  out.extend_from_slice(&body);
+  out
 }
 
 #[derive(Debug, Clone)]
@@ -81,19 +143,21 @@ impl EventWrapper {
 }
 
 impl ConnSender {
-  #[cfg(test)]
-  pub(crate) fn mock(poll: &Poll) -> (Receiver<cb::Packet>, Receiver<WakeEvent>, Self) {
+  /// Creates a sender that isn't connected to a proxy. Everything sent to the
+  /// client can be read from the first receiver. This is used for tests, both
+  /// in this crate and in `bb_test`.
+  pub fn mock(poll: &Poll) -> (Receiver<Outgoing>, Receiver<WakeEvent>, Self) {
     const WAKE: Token = Token(0xfffffffe);
 
-    let (tx, rx) = crossbeam_channel::bounded(2048);
-    let (wake_tx, wake_rx) = crossbeam_channel::bounded(2048);
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let (wake_tx, wake_rx) = crossbeam_channel::unbounded();
     let waker = Arc::new(Waker::new(poll.registry(), WAKE).unwrap());
-    (rx, wake_rx, ConnSender { tx, wake: wake_tx, waker, tok: Token(0) })
+    let state = Arc::new(SendState::new());
+    (rx, wake_rx, ConnSender { tx, wake: wake_tx, waker, tok: Token(0), state })
   }
-  /// Sends the given packet to the client. Assuming there aren't too many
-  /// packets in the queue, this is a non-blocking operation. This will block if
-  /// there are too many packets queued. The limit is 512 packets before this
-  /// will block, so this should very rarely happen.
+  /// Sends the given packet to the client. This never blocks. If the client
+  /// can't keep up, the packet is buffered, or dropped if the [`DropPolicy`]
+  /// allows it.
   ///
   /// Note that this will simply drop the packet if the client has disconnected.
   ///
@@ -101,12 +165,56 @@ impl ConnSender {
   ///
   /// This will panic if the waker thread used globally has been closed. The
   /// only way for this to close is if the network manager stops working.
+  ///
+  /// Inventory updates are given the next inventory revision here. See
+  /// [`revision`](Self::revision).
   pub fn send(&self, p: impl Into<cb::Packet>) {
-    if let Ok(()) = self.tx.send(p.into()) {
-      self.wake.send(WakeEvent::Clientbound(self.tok)).unwrap();
-      self.waker.wake().unwrap();
+    let mut p = p.into();
+    match &mut p {
+      // Clients don't track the revision of their cursor, so it isn't changed.
+      cb::Packet::WindowItem(p) if p.wid == u8::MAX => p.revision = self.state.revision(),
+      cb::Packet::WindowItem(p) => p.revision = self.state.next_revision(),
+      cb::Packet::WindowItems(p) => p.revision = self.state.next_revision(),
+      _ => {}
+    }
+    self.send_outgoing(Outgoing::Packet(p));
+  }
+  /// Sends an already serialized packet to the client. `priority` should be
+  /// the priority of the packet before it was serialized.
+  pub fn send_encoded(&self, data: Arc<[u8]>, priority: Priority) {
+    self.send_outgoing(Outgoing::Encoded(data, priority));
+  }
+
+  fn send_outgoing(&self, out: Outgoing) {
+    if let Ok(()) = self.tx.send(out) {
+      self.wake();
     }
   }
+  fn wake(&self) {
+    self.wake.send(WakeEvent::Clientbound(self.tok)).unwrap();
+    self.waker.wake().unwrap();
+  }
+
+  /// Returns which packets can be dropped when this client falls behind.
+  pub fn drop_policy(&self) -> DropPolicy { self.state.policy() }
+  /// Sets which packets can be dropped when this client falls behind.
+  pub fn set_drop_policy(&self, policy: DropPolicy) { self.state.set_policy(policy); }
+  /// Returns the number of packets that have been dropped because this client
+  /// fell behind.
+  pub fn dropped(&self) -> u64 { self.state.dropped() }
+  /// Returns how long this client has been behind for, or `None` if they
+  /// are keeping up.
+  pub fn behind_for(&self) -> Option<Duration> { self.state.behind_for() }
+  /// Returns the revision of the last inventory update sent to this client.
+  /// 1.17+ clients send this back when clicking in a window, so a different
+  /// revision means the client clicked before seeing an update.
+  pub fn revision(&self) -> u32 { self.state.revision() }
+  /// Closes the connection. Any packets that haven't been sent yet will be
+  /// dropped.
+  pub fn close(&self) {
+    self.state.close();
+    self.wake();
+  }
 }
 
 impl Connection {
@@ -115,19 +223,25 @@ impl Connection {
     wake: Sender<WakeEvent>,
     waker: Arc<Waker>,
     tok: Token,
+    buffer_limit: usize,
   ) -> Self {
-    // For a 10 chunk render distance, we need to send 441 packets at once. So a
-    // limit of 512 means we don't block very much.
-    let (tx, rx) = crossbeam_channel::bounded(512);
+    // This is unbounded so that sending never blocks. Slow clients are handled in
+    // `try_send`, once packets have been moved into `outgoing`.
+    let (tx, rx) = crossbeam_channel::unbounded();
     Connection {
       stream,
       ver: None,
+      protocol: None,
       closed: false.into(),
       tx,
       rx,
       wake,
       waker,
       tok,
+      state: Arc::new(SendState::new()),
+      buffer_limit,
+      last_heard: Instant::now(),
+      left: false,
       incoming: Vec::with_capacity(1024),
       outgoing: Vec::with_capacity(1024),
       garbage: vec![0; 256 * 1024],
@@ -142,13 +256,13 @@ impl Connection {
       wake:  self.wake.clone(),
       waker: self.waker.clone(),
       tok:   self.tok,
+      state: self.state.clone(),
     }
   }
 
-  /// Sends the given packet to the client. Assuming there aren't too many
-  /// packets in the queue, this is a non-blocking operation. This will block if
-  /// there are too many packets queued. The limit is 512 packets before this
-  /// will block, so this should very rarely happen.
+  /// Sends the given packet to the client. This never blocks. If the client is
+  /// falling behind, the packet may be dropped, depending on the client's
+  /// [`DropPolicy`].
   ///
   /// Note that this will simply drop the packet if the client has disconnected.
   ///
@@ -157,7 +271,7 @@ impl Connection {
   /// This will panic if the waker thread used globally has been closed. The
   /// only way for this to close is if the network manager stops working.
   pub fn send(&self, p: cb::Packet) {
-    if let Ok(()) = self.tx.send(p) {
+    if let Ok(()) = self.tx.send(Outgoing::Packet(p)) {
       self.wake.send(WakeEvent::Clientbound(self.tok)).unwrap();
       self.waker.wake().unwrap();
     }
@@ -179,6 +293,7 @@ impl Connection {
         Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok((false, None, out)),
         Err(e) => return Err(e),
       };
+      self.last_heard = Instant::now();
       self.incoming.extend_from_slice(&self.garbage[..n]);
       let (new_conn, packets) = self.read_incoming()?;
       if new_conn.is_some() {
@@ -188,18 +303,57 @@ impl Connection {
     }
   }
 
+  /// Moves the stream from `new` into this connection, so that the player on
+  /// this connection can carry on after the proxy reconnects. Anything that was
+  /// half written to the old stream is dropped, and everything still queued is
+  /// sent on the new stream, after the new stream's protocol reply.
+  fn resume(&mut self, mut new: Connection, reg: &Registry) -> io::Result<()> {
+    let _ = reg.deregister(&mut self.stream);
+    reg.reregister(&mut new.stream, self.tok, Interest::READABLE | Interest::WRITABLE)?;
+    self.stream = new.stream;
+    self.ver = new.ver;
+    self.protocol = new.protocol;
+    self.incoming = new.incoming;
+    // This only contains the protocol reply for the new connection.
+    self.outgoing = new.outgoing;
+    self.last_heard = Instant::now();
+    self.left = false;
+    self.wake.send(WakeEvent::Clientbound(self.tok)).unwrap();
+    self.waker.wake().unwrap();
+    Ok(())
+  }
+
   fn try_send(&mut self) -> io::Result<()> {
+    if self.state.should_close() {
+      return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "closed by the server"));
+    }
+    // Everything in the queue is moved into `outgoing`, even if the socket is full,
+    // so that the queue never grows. Once `outgoing` is too large, we start
+    // dropping packets.
+    let policy = self.state.policy();
     loop {
-      match self.rx.try_recv() {
-        Ok(p) => self.send_to_client(p)?,
+      let out = match self.rx.try_recv() {
+        Ok(out) => out,
         Err(TryRecvError::Empty) => break,
         Err(_e) => unreachable!(),
+      };
+      let priority = match &out {
+        Outgoing::Packet(p) => Priority::of(p),
+        Outgoing::Encoded(_, priority) => *priority,
+      };
+      if policy.should_drop(priority, self.outgoing.len(), self.buffer_limit) {
+        self.state.record_drop();
+        continue;
+      }
+      match out {
+        Outgoing::Packet(p) => self.write_packet(&p),
+        Outgoing::Encoded(data, _) => self.outgoing.extend_from_slice(&data),
       }
     }
-    Ok(())
+    self.try_flush()
   }
 
-  fn send_to_client(&mut self, p: cb::Packet) -> io::Result<()> {
+  fn write_packet(&mut self, p: &cb::Packet) {
     let mut m = MessageWriter::new(self.garbage.as_mut_slice());
     p.write(&mut m).unwrap();
     let len = m.index();
@@ -211,10 +365,15 @@ impl Connection {
 
     self.outgoing.extend_from_slice(&prefix[..prefix_len]);
     self.outgoing.extend_from_slice(&self.garbage[..len]);
-    self.try_flush()
   }
 
   fn try_flush(&mut self) -> io::Result<()> {
+    let res = self.flush_outgoing();
+    self.state.set_behind(self.outgoing.len() >= self.buffer_limit);
+    res
+  }
+
+  fn flush_outgoing(&mut self) -> io::Result<()> {
     while !self.outgoing.is_empty() {
       let n = match self.stream.write(&self.outgoing) {
         Ok(v) => v,
@@ -238,7 +397,8 @@ impl Connection {
             self.incoming.drain(0..idx);
             // We already handshaked
             if self.ver.is_some() {
-              let mut m = MessageReader::new(&self.incoming[..len]);
+              let mut m =
+                MessageReader::with_limits(&self.incoming[..len], ReadLimits::untrusted());
               let p = sb::Packet::read(&mut m).map_err(|err| {
                 io::Error::new(
                   io::ErrorKind::InvalidData,
@@ -253,10 +413,16 @@ impl Connection {
                   format!("packet did not parse enough bytes (expected {len}, only parsed {n})"),
                 ));
               }
-              out.push(p);
+              match p {
+                // These are only for the connection, so the player never sees them.
+                sb::Packet::Heartbeat { id } => self.send(cb::packet::Heartbeat { id }.into()),
+                sb::Packet::Leave => self.left = true,
+                p => out.push(p),
+              }
             } else {
               // This is the first packet, so it must be a login packet.
-              let mut m = MessageReader::new(&self.incoming[..len]);
+              let mut m =
+                MessageReader::with_limits(&self.incoming[..len], ReadLimits::untrusted());
               let info: JoinInfo = m.read().map_err(|e| {
                 io::Error::new(io::ErrorKind::InvalidData, format!("error reading handshake: {e}"))
               })?;
@@ -268,6 +434,30 @@ impl Connection {
                   format!("handshake did not parse enough bytes (expected {len}, only parsed {n})"),
                 ));
               }
+              let protocol = match ProtocolInfo::current().negotiate(&info.protocol) {
+                Ok(v) => v,
+                Err(e) => {
+                  self.reject_protocol();
+                  return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("proxy for {} cannot join: {e}", info.username),
+                  ));
+                }
+              };
+              // Older proxies don't know about this packet, so it is only sent to proxies
+              // that told us their version. This is written directly, so that it is sent
+              // before anything the player queues, and so that it is kept if this
+              // connection is used to resume another one.
+              if info.protocol.max_version > 0 {
+                self.write_packet(
+                  &cb::packet::Protocol {
+                    version:      protocol.version,
+                    capabilities: protocol.capabilities,
+                  }
+                  .into(),
+                );
+              }
+              self.protocol = Some(protocol);
               self.ver = Some(ProtocolVersion::from(info.ver as i32));
               // We rely on the caller to set the player using this value.
               return Ok((Some(NewConn { sender: self.sender(), info }), out));
@@ -292,6 +482,16 @@ impl Connection {
     Ok((None, out))
   }
 
+  /// Kicks the player when the proxy is using an incompatible protocol. The
+  /// connection is about to be closed, so the kick is written right away,
+  /// instead of being queued.
+  fn reject_protocol(&mut self) {
+    let mut msg = Chat::empty();
+    msg.add("The proxy and the server are running incompatible versions.").color(Color::Red);
+    self.write_packet(&cb::packet::Kick { reason: msg }.into());
+    let _ = self.flush_outgoing();
+  }
+
   // This waits for the a login packet from the proxy. If any other packet is
   // received, this will panic. This should only be called right after a
   // connection is created.
@@ -319,6 +519,7 @@ impl Connection {
 
 pub struct ConnectionManager {
   connections: Arc<RwLock<HashMap<Token, ConnPlayer>>>,
+  detached:    Arc<Mutex<HashMap<UUID, Detached>>>,
   wm:          Arc<WorldManager>,
 }
 
@@ -331,9 +532,26 @@ struct ConnPlayer {
   pub player: Option<Arc<Player>>,
 }
 
+/// A player whose connection to the proxy dropped. They stay in the world
+/// until `link-grace-secs` have passed, and anything sent to them is buffered
+/// in `conn`. If the proxy resumes the connection in time, they carry on where
+/// they left off.
+struct Detached {
+  conn:   Connection,
+  player: Arc<Player>,
+  since:  Instant,
+}
+
 struct State {
-  wm:    Arc<WorldManager>,
-  conns: Arc<RwLock<HashMap<Token, ConnPlayer>>>,
+  wm:       Arc<WorldManager>,
+  conns:    Arc<RwLock<HashMap<Token, ConnPlayer>>>,
+  detached: Arc<Mutex<HashMap<UUID, Detached>>>,
+  registry: Arc<Registry>,
+  /// How long detached players are held onto.
+  grace:    Duration,
+  /// How long a connection can go without receiving anything before it is
+  /// dropped.
+  timeout:  Duration,
 }
 
 impl ConnPlayer {
@@ -342,7 +560,11 @@ impl ConnPlayer {
 
 impl ConnectionManager {
   pub fn new(wm: Arc<WorldManager>) -> ConnectionManager {
-    ConnectionManager { connections: Arc::new(RwLock::new(HashMap::new())), wm }
+    ConnectionManager {
+      connections: Arc::new(RwLock::new(HashMap::new())),
+      detached: Arc::new(Mutex::new(HashMap::new())),
+      wm,
+    }
   }
 
   pub fn run(&mut self, addr: SocketAddr) -> io::Result<()> {
@@ -359,25 +581,37 @@ impl ConnectionManager {
 
     let mut next_token = 0;
 
-    let (tx, rx) = crossbeam_channel::bounded(1024);
-
-    let write_pool = ThreadPool::auto("network writer", || State {
-      wm:    self.wm.clone(),
-      conns: self.connections.clone(),
-    });
-    let read_pool = ThreadPool::auto("network reader", || State {
-      wm:    self.wm.clone(),
-      conns: self.connections.clone(),
-    });
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let buffer_limit = self.wm.config().backpressure.buffer_size as usize;
+
+    let registry = Arc::new(poll.registry().try_clone()?);
+    let state = || State {
+      wm:       self.wm.clone(),
+      conns:    self.connections.clone(),
+      detached: self.detached.clone(),
+      registry: registry.clone(),
+      grace:    Duration::from_secs(self.wm.config().link_grace_secs),
+      timeout:  Duration::from_secs(self.wm.config().link_timeout_secs.max(1)),
+    };
+    let write_pool = ThreadPool::auto("network writer", state);
+    let read_pool = ThreadPool::auto("network reader", state);
+    let main = state();
+    let mut last_check = Instant::now();
 
     loop {
       loop {
-        match poll.poll(&mut events, None) {
+        // This has a timeout so that we can check for dropped connections, even
+        // if nothing is happening.
+        match poll.poll(&mut events, Some(Duration::from_secs(1))) {
           Ok(()) => break,
           Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
           Err(e) => return Err(e),
         }
       }
+      if last_check.elapsed() >= Duration::from_secs(1) {
+        last_check = Instant::now();
+        Self::check_links(&main);
+      }
 
       for event in events.iter() {
         match event.token() {
@@ -406,7 +640,13 @@ impl ConnectionManager {
 
             self.connections.write().insert(
               token,
-              ConnPlayer::new(Connection::new(conn, tx.clone(), waker.clone(), token)),
+              ConnPlayer::new(Connection::new(
+                conn,
+                tx.clone(),
+                waker.clone(),
+                token,
+                buffer_limit,
+              )),
             );
           },
           WAKE => {
@@ -422,14 +662,14 @@ impl ConnectionManager {
           token => {
             let e = EventWrapper::new(event);
             read_pool.execute(move |s| {
-              if Self::handle(&s.wm, &s.conns, token, e) {
+              if Self::handle(s, token, e) {
                 let mut wl = s.conns.write();
                 // Multiple threads can handle this event, so if the token has alrady been
                 // removed, we know it was another thread that called this. Therefore, we can
                 // just ignore a player that is not present.
                 if let Some(p) = wl.remove(&token) {
                   drop(wl);
-                  Self::handle_disconnect(&p.player);
+                  Self::handle_disconnect(s, p);
                 }
               }
             });
@@ -439,22 +679,119 @@ impl ConnectionManager {
     }
   }
 
-  /// Logs a disconnect, and if the player is present, it removes them.
-  fn handle_disconnect(player: &Option<Arc<Player>>) {
-    if let Some(p) = player {
-      p.remove();
-    } else {
+  /// Called once a connection has been removed. If the proxy didn't tell us
+  /// the player left, the player is held onto for `link-grace-secs`, in case
+  /// the proxy resumes the connection. Otherwise, the player is removed.
+  fn handle_disconnect(s: &State, cp: ConnPlayer) {
+    let Some(player) = cp.player else {
       info!("a client who has not finished logging in has left the game");
+      return;
+    };
+    let conn = cp.conn.into_inner();
+    // Players who were kicked have already been removed from the world, so there
+    // is nothing to hold onto.
+    let online = s.wm.get_player(player.id()).map_or(false, |p| Arc::ptr_eq(&p, &player));
+    if conn.left || s.grace.is_zero() || conn.state.should_close() || !online {
+      player.remove();
+    } else {
+      info!(
+        "lost the connection to the proxy for {}, holding them for {} seconds",
+        player.username(),
+        s.grace.as_secs()
+      );
+      s.detached.lock().insert(player.id(), Detached { conn, player, since: Instant::now() });
     }
   }
 
-  /// If this is not a normal disconnect, then this logs an error, and calls
-  /// [`disconnect_player`](Self::disconnect_player).
-  fn handle_error(e: io::Error, player: &Option<Arc<Player>>) {
-    if !matches!(e.kind(), io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset) {
+  /// Logs an error, if this is not a normal disconnect.
+  fn log_error(e: &io::Error) {
+    if !matches!(
+      e.kind(),
+      io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted
+    ) {
       error!("error in connection: {}", e);
     }
-    Self::handle_disconnect(player);
+  }
+
+  /// Removes detached players who weren't resumed in time, and drops any
+  /// connections the proxy has stopped sending heartbeats on. This is called
+  /// about once a second.
+  fn check_links(s: &State) {
+    let expired: Vec<Detached> = {
+      let mut detached = s.detached.lock();
+      let ids: Vec<UUID> =
+        detached.iter().filter(|(_, d)| d.since.elapsed() >= s.grace).map(|(id, _)| *id).collect();
+      ids.iter().filter_map(|id| detached.remove(id)).collect()
+    };
+    for d in expired {
+      info!("the connection for {} was not resumed in time, removing them", d.player.username());
+      d.player.remove();
+    }
+
+    let silent: Vec<Token> = s
+      .conns
+      .read()
+      .iter()
+      .filter(|(_, p)| p.conn.lock().last_heard.elapsed() >= s.timeout)
+      .map(|(tok, _)| *tok)
+      .collect();
+    for tok in silent {
+      let removed = s.conns.write().remove(&tok);
+      if let Some(p) = removed {
+        warn!("the proxy stopped responding on connection {tok:?}, closing it");
+        let _ = s.registry.deregister(&mut p.conn.lock().stream);
+        Self::handle_disconnect(s, p);
+      }
+    }
+  }
+
+  /// Gives the player with the given id the new connection on `token`, if we
+  /// are holding onto them. The player's old connection might not have timed
+  /// out yet, if the proxy noticed the drop before we did, so that is also
+  /// taken over. Returns `true` if the player was resumed, in which case
+  /// `token` has been removed, and the player is on their old token again.
+  fn resume(s: &State, token: Token, id: UUID) -> bool {
+    let mut wl = s.conns.write();
+    if !wl.contains_key(&token) {
+      return false;
+    }
+    let stale = wl
+      .iter()
+      .find(|(tok, p)| **tok != token && p.player.as_ref().map_or(false, |p| p.id() == id))
+      .map(|(tok, _)| *tok);
+    let old = match stale.and_then(|tok| wl.remove(&tok)) {
+      Some(p) => p.player.map(|player| (p.conn.into_inner(), player)),
+      None => s.detached.lock().remove(&id).map(|d| (d.conn, d.player)),
+    };
+    let Some((mut conn, player)) = old else { return false };
+    // Kicked players are removed from the world right away, but they might still
+    // be held onto here.
+    if !s.wm.get_player(id).map_or(false, |p| Arc::ptr_eq(&p, &player)) {
+      return false;
+    }
+    let new = wl.remove(&token).unwrap();
+    if let Err(e) = conn.resume(new.conn.into_inner(), &s.registry) {
+      error!("could not resume the connection for {}: {e}", player.username());
+      drop(wl);
+      player.remove();
+      return true;
+    }
+    // The proxy may have sent packets right after the handshake.
+    let packets = match conn.read_incoming() {
+      Ok((_, packets)) => packets,
+      Err(e) => {
+        Self::log_error(&e);
+        vec![]
+      }
+    };
+    wl.insert(conn.tok, ConnPlayer { conn: Mutex::new(conn), player: Some(player.clone()) });
+    drop(wl);
+    info!("resumed the connection for {}", player.username());
+    player.resync();
+    for p in packets {
+      packet::handle(&s.wm, &player, p);
+    }
+    true
   }
 
   fn wake_event(s: &State, ev: WakeEvent) {
@@ -466,24 +803,23 @@ impl ConnectionManager {
             Ok(()) => false,
             Err(e) if e.kind() == io::ErrorKind::WouldBlock => false,
             Err(e) => {
-              Self::handle_error(e, &player.player);
+              Self::log_error(&e);
               true
             }
           };
         }
         if remove {
-          s.conns.write().remove(&tok);
+          let removed = s.conns.write().remove(&tok);
+          if let Some(p) = removed {
+            Self::handle_disconnect(s, p);
+          }
         }
       }
     }
   }
 
-  fn handle(
-    wm: &Arc<WorldManager>,
-    c: &RwLock<HashMap<Token, ConnPlayer>>,
-    token: Token,
-    ev: EventWrapper,
-  ) -> bool {
+  fn handle(s: &State, token: Token, ev: EventWrapper) -> bool {
+    let (wm, c) = (&s.wm, &*s.conns);
     if ev.is_readable {
       loop {
         let rl = c.read();
@@ -496,12 +832,11 @@ impl ConnectionManager {
             Ok(v) => v,
             // Something else went wrong.
             Err(e) => {
-              Self::handle_error(e, &player.player);
+              Self::log_error(&e);
               return true;
             }
           };
           if disconnect {
-            Self::handle_disconnect(&player.player);
             return true;
           }
           // Don't drop our read lock yet, as we need to use the player we got from it.
@@ -517,6 +852,18 @@ impl ConnectionManager {
             // The player must be created after we drop the `conn.lock()`, so that sending
             // login packets doesn't deadlock.
             if let Some(new_conn) = new_conn {
+              if matches!(new_conn.info.mode, JoinMode::Resume) {
+                if Self::resume(s, token, new_conn.info.uuid) {
+                  return false;
+                }
+              } else {
+                // The proxy gave up on resuming the old connection, so we stop holding
+                // onto the player before they join again.
+                let held = s.detached.lock().remove(&new_conn.info.uuid);
+                if let Some(held) = held {
+                  held.player.remove();
+                }
+              }
               let new_player = wm.new_player(new_conn.sender, new_conn.info);
               {
                 let mut wl = c.write();
@@ -545,7 +892,7 @@ impl ConnectionManager {
           Ok(()) => {}
           Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
           Err(e) => {
-            Self::handle_error(e, &player.player);
+            Self::log_error(&e);
             return true;
           }
         }
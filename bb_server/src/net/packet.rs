@@ -2,21 +2,70 @@ use crate::{
   block,
   block::Block,
   entity, event, item,
-  player::{AirClick, BlockClick, Click, Player},
+  item::Stack,
+  player::{AirClick, BlockClick, Click, Player, ReachAction, Window},
   world::WorldManager,
 };
 use bb_common::{
   math::FPos,
   net::{cb, sb},
-  util::{
-    chat::{Chat, Color, HoverEvent},
-    GameMode,
-  },
+  util::{Buffer, Chat, GameMode, Hand},
 };
+use parking_lot::Mutex;
 use std::{str::FromStr, sync::Arc};
 
-/// Handles a single packet.
-pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Packet) {
+/// Handles a single packet. If the proxy asked us to trace this packet, this
+/// will record how long it took to handle, and send the timings back to the
+/// proxy.
+pub fn handle(wm: &Arc<WorldManager>, player: &Arc<Player>, p: sb::Packet) {
+  if let sb::Packet::Trace { id } = p {
+    player.start_trace(id);
+    return;
+  }
+  let traced = player.is_tracing();
+  if traced {
+    player.set_trace_packet(packet_name(&p));
+  }
+  let span = tracing::info_span!("handle packet", packet = tracing::field::Empty);
+  // Formatting the packet is slow, so only do it while profiling.
+  if !span.is_disabled() {
+    span.record("packet", packet_name(&p).as_str());
+  }
+  let sequence = block_sequence(&p);
+  span.in_scope(|| handle_packet(wm, player, p));
+  // This must be sent after any block updates from handling the packet, so that
+  // the client replaces its predicted blocks with what the server has.
+  if let Some(sequence) = sequence {
+    player.send(cb::packet::AckBlockUpdate { sequence });
+  }
+  if traced {
+    player.finish_trace();
+  }
+}
+
+/// Returns the name of the given packet, without any of its fields.
+fn packet_name(p: &sb::Packet) -> String {
+  let debugged = format!("{p:?}");
+  match debugged.find([' ', '{', '(']) {
+    Some(idx) => debugged[..idx].to_string(),
+    None => debugged,
+  }
+}
+
+/// Returns the sequence id of a packet that 1.19+ clients expect to be
+/// acknowledged. Newer clients predict the result of digging and placing
+/// blocks, and only stop predicting once the matching sequence id has been
+/// acknowledged.
+fn block_sequence(p: &sb::Packet) -> Option<i32> {
+  match *p {
+    sb::Packet::BlockDig { sequence, .. }
+    | sb::Packet::BlockPlace { sequence, .. }
+    | sb::Packet::UseItem { sequence, .. } => sequence,
+    _ => None,
+  }
+}
+
+fn handle_packet(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Packet) {
   // TODO: This depends on debug formatting, which is unstable. Also, it is slow,
   // because we allocate every time this is called.
   /*
@@ -38,11 +87,18 @@ pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Pa
   {
     return;
   }
+  player.trace_span("events");
 
   match p {
     sb::Packet::KeepAlive { id: _ } => {
       // TODO Keep alive packets
     }
+    sb::Packet::PluginMessage { channel, data } if channel == "minecraft:brand" => {
+      match Buffer::new(data.as_slice()).read_str(256) {
+        Ok(brand) => player.set_brand(brand),
+        Err(e) => warn!("{} sent an invalid brand: {e}", player.username()),
+      }
+    }
     sb::Packet::Chat { msg } => {
       /*
       player.lock_scoreboard().show();
@@ -56,27 +112,28 @@ pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Pa
       if let Some(command) = msg.strip_prefix('/') {
         player.world().commands().execute(wm, &mut player, command);
       } else {
-        let text = msg;
+        let message = Arc::new(Mutex::new(player.format_chat(&msg)));
         if wm
           .events()
-          .player_request(event::Chat { player: player.clone(), text: text.clone() })
+          .player_request(event::Chat {
+            player:  player.clone(),
+            text:    msg,
+            message: message.clone(),
+          })
           .is_handled()
         {
           return;
         }
-        let mut msg = Chat::empty();
-        msg.add("<");
-        msg.add(player.username()).color(Color::BrightGreen).on_hover(HoverEvent::ShowText(
-          format!("wow it is almost like {} sent this message", player.username()),
-        ));
-        msg.add("> ");
-        msg.add(text);
+        let msg = message.lock().clone();
         wm.broadcast(msg);
       }
     }
-    sb::Packet::BlockDig { pos, status, face } => {
-      // If the world is locked then we need to sync this block.
-      if player.world().is_locked() {
+    sb::Packet::BlockDig { pos, status, face, .. } => {
+      // If the world is locked then we need to sync this block. Cancelling is always
+      // allowed, so that players can't get stuck digging a block out of reach.
+      if player.world().is_locked()
+        || (status != sb::DigStatus::Cancel && !player.can_reach_block(pos, ReachAction::Dig))
+      {
         player.sync_block_at(pos);
       } else {
         match player.game_mode() {
@@ -86,12 +143,13 @@ pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Pa
             sb::DigStatus::Finish => player.finish_digging(pos),
           },
           GameMode::Creative => {
-            if let Ok(looking_at) = player.world().get_block(pos) {
+            let world = player.world();
+            if let Ok(looking_at) = world.get_block(pos) {
               let click = BlockClick {
                 player,
                 face,
                 dir: player.look_as_vec(),
-                block: Block::new(player.world(), pos, looking_at.ty()),
+                block: Block::new(&world, pos, looking_at.ty()),
                 cursor: FPos::new(0.0, 0.0, 0.0),
               };
               let inv = player.lock_inventory();
@@ -130,32 +188,58 @@ pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Pa
         player.lock_inventory().set(slot.into(), item.into());
       }
     }
-    sb::Packet::ClickWindow { wid, mut slot, mode } => {
+    sb::Packet::ClickWindow { wid, mut slot, mode, revision, action } => {
       if wid == u8::MAX {
         slot = i16::from(player.lock_inventory().selected_index()) + 36;
       }
-      let allow = player
-        .world()
-        .events()
+      let (stack, held, win_size) = {
+        let mut inv = player.lock_inventory();
+        if inv.check_click(wid, revision, action) {
+          debug!("{} clicked on an out of date inventory, resyncing", player.username());
+        }
+        (
+          inv.get(slot.into()).unwrap_or_else(Stack::empty),
+          inv.get(-999).unwrap(),
+          inv.win().map(|w| w.size()),
+        )
+      };
+      let world = player.world();
+      let events = world.events();
+      let mut allow = events
         .player_request(event::ClickWindowEvent {
           player: player.clone(),
-          slot:   slot.into(),
-          mode:   mode.clone(),
+          slot: slot.into(),
+          mode: mode.clone(),
+          stack: stack.clone(),
+          held,
         })
         .is_continue();
+      // Shift clicking with a window open moves items between the window and the
+      // player's inventory.
+      if let (true, sb::ClickWindow::ShiftClick(_), Some(size)) = (allow, &mode, win_size) {
+        if !stack.is_empty() {
+          allow = events
+            .player_request(event::ItemMove {
+              player: player.clone(),
+              stack,
+              to_player: slot >= 0 && (slot as u32) < size,
+            })
+            .is_continue();
+        }
+      }
       player.lock_inventory().click_window(slot.into(), mode, allow);
     }
     sb::Packet::ChangeHeldItem { slot } => {
       player.lock_inventory().set_selected(slot);
     }
-    sb::Packet::UseItem { hand } => {
+    sb::Packet::UseItem { hand, .. } => {
       wm.events().interact(
         player,
         hand,
         Click::Air(AirClick { dir: player.look_as_vec(), player }),
       );
     }
-    sb::Packet::BlockPlace { mut pos, face, hand, cursor } => {
+    sb::Packet::BlockPlace { mut pos, face, hand, cursor, .. } => {
       /*
       let direction: i32 = if player.ver() == ProtocolVersion::V1_8 {
         // direction_v1_8 is an i8 (not a u8), so the sign stays correct
@@ -165,13 +249,19 @@ pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Pa
       };
       */
 
-      match player.world().get_block(pos) {
+      if !player.can_reach_block(pos, ReachAction::Place) {
+        player.sync_block_at(pos);
+        player.sync_block_at(pos + face);
+        return;
+      }
+      let world = player.world();
+      match world.get_block(pos) {
         Ok(looking_at) => {
           let click = BlockClick {
             player,
             face,
             dir: player.look_as_vec(),
-            block: Block::new(player.world(), pos, looking_at.ty()),
+            block: Block::new(&world, pos, looking_at.ty()),
             cursor,
           };
           if !player.is_crouching() {
@@ -187,6 +277,11 @@ pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Pa
           // they place.
           let mut inv = player.lock_inventory();
           let stack = inv.in_hand(hand);
+          if player.has_cooldown(stack.item()) {
+            player.sync_block_at(pos);
+            player.sync_block_at(pos + face);
+            return;
+          }
           let item_data = player.world().item_converter().get_data(stack.item());
           let kind = block::Kind::from_str(item_data.name()).unwrap_or_else(|_| {
             player.send_message(Chat::new(format!("ah! {} is confusing", item_data.name())));
@@ -221,21 +316,23 @@ pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Pa
         }
       };
     }
-    sb::Packet::PlayerPos { x, y, z, .. } => {
+    sb::Packet::PlayerPos { x, y, z, on_ground } => {
       player.set_next_pos(x, y, z);
+      player.set_on_ground(on_ground);
     }
-    sb::Packet::PlayerPosLook { x, y, z, yaw, pitch, .. } => {
+    sb::Packet::PlayerPosLook { x, y, z, yaw, pitch, on_ground } => {
       player.set_next_pos(x, y, z);
       player.set_next_look(yaw, pitch);
+      player.set_on_ground(on_ground);
     }
-    sb::Packet::PlayerLook { yaw, pitch, .. } => {
+    sb::Packet::PlayerLook { yaw, pitch, on_ground } => {
       player.set_next_look(yaw, pitch);
+      player.set_on_ground(on_ground);
     }
     sb::Packet::Flying { flying } => {
       player.set_flying_no_send(flying);
     }
-    // Just contains on_ground
-    sb::Packet::PlayerOnGround { .. } => {}
+    sb::Packet::PlayerOnGround { on_ground } => player.set_on_ground(on_ground),
     sb::Packet::PlayerCommand { command } => player.handle_command(command),
     sb::Packet::Animation { hand } => player.send_to_in_view(cb::packet::Animation {
       eid:  player.eid(),
@@ -245,14 +342,54 @@ pub(crate) fn handle(wm: &Arc<WorldManager>, mut player: &Arc<Player>, p: sb::Pa
       if let Some(crouching) = sneaking {
         player.set_crouching(crouching);
       }
-      if let Some(ent) = player.world().entities().get(eid) {
-        match action {
-          sb::UseEntityAction::Attack => player.attack(ent),
-          _ => warn!("todo: action {action:?}"),
+      match action {
+        sb::UseEntityAction::Attack => {
+          if let Some(ent) = player.world().entities().get(eid) {
+            if player.can_reach_entity(ent.hitbox()) {
+              player.attack(ent);
+            }
+          }
         }
+        // Clients send an `InteractAt` before every `Interact`, so we only need to handle
+        // `InteractAt`. Clients also send one for each hand, and off hand interactions are
+        // rare enough that we ignore them.
+        sb::UseEntityAction::InteractAt(cursor, Hand::Main) => player.interact_entity(eid, cursor),
+        _ => {}
       }
     }
     sb::Packet::WindowClose { wid: _ } => player.lock_inventory().close_window(),
+    sb::Packet::SteerVehicle { dismount: true, .. } => {
+      player.world().dismount(player.eid());
+    }
+    sb::Packet::SelectTrade { index } => {
+      let inv = player.lock_inventory();
+      if let Some(Window::Merchant(win)) = inv.win() {
+        win.select(index as usize);
+      }
+      inv.sync_all();
+    }
+    sb::Packet::UpdateCommandBlock { pos, command, mode, track_output, conditional, auto } => {
+      // Vanilla also requires players to be op, but every player is given op when
+      // they join.
+      if player.game_mode() != GameMode::Creative {
+        return;
+      }
+      let _ = player.world().update_command_block(pos, mode, conditional, |data| {
+        if data.command != command {
+          data.success_count = 0;
+          data.last_output = None;
+        }
+        if !track_output {
+          data.last_output = None;
+        }
+        data.command = command;
+        data.track_output = track_output;
+        data.auto = auto;
+      });
+    }
+    // The proxy already warned about these, and plugins can handle them with the
+    // `ReceivePacket` event.
+    sb::Packet::Raw { .. } => {}
     _ => warn!("unknown packet: {:?}", p),
   }
 }
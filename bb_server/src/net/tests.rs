@@ -1,20 +1,20 @@
 use super::WakeEvent;
 use crate::{
-  net::{packet, ConnSender},
+  net::{broadcast::Broadcast, packet, ConnSender, Outgoing},
   player::Player,
   world::WorldManager,
 };
 use bb_common::{
   math::FPos,
-  net::{cb, sb},
-  util::{JoinInfo, JoinMode, UUID},
+  net::{cb, protocol::ProtocolInfo, sb},
+  util::{ClientKind, JoinInfo, JoinMode, UUID},
   version::ProtocolVersion,
 };
 use crossbeam_channel::Receiver;
 use std::sync::Arc;
 
 pub struct TestHandler {
-  rx:      Receiver<cb::Packet>,
+  rx:      Receiver<Outgoing>,
   wake_rx: Receiver<WakeEvent>,
   wm:      Arc<WorldManager>,
   player:  Arc<Player>,
@@ -37,10 +37,15 @@ impl TestHandler {
     let poll = mio::Poll::new().unwrap();
     let (rx, wake_rx, sender) = ConnSender::mock(&poll);
     let info = JoinInfo {
-      mode:     JoinMode::New,
-      username: "macmv".into(),
-      uuid:     UUID::from_u128(0),
-      ver:      ProtocolVersion::V1_8.id(),
+      mode:       JoinMode::New,
+      username:   "macmv".into(),
+      uuid:       UUID::from_u128(0),
+      ver:        ProtocolVersion::V1_8.id(),
+      properties: vec![],
+      addr:       None,
+      client:     ClientKind::Vanilla,
+      mods:       vec![],
+      protocol:   ProtocolInfo::current(),
     };
     let player = wm.new_player(sender, info);
     TestHandler { rx, wake_rx, wm, player }
@@ -54,17 +59,15 @@ impl TestHandler {
   pub fn assert_empty(&self) {
     if !self.rx.is_empty() {
       while let Ok(m) = self.rx.try_recv() {
-        info!("packet: {m:?}");
+        info!("packet: {:?}", m.into_packet());
       }
       panic!("got packets, but expected none");
     }
   }
-  // This is a useful but unused function.
-  #[allow(unused)]
   pub fn assert_sent(&self, expected_packets: &[cb::Packet]) {
     let mut actual_packets = vec![];
     while let Ok(p) = self.rx.try_recv() {
-      actual_packets.push(p);
+      actual_packets.push(p.into_packet());
     }
     let mut equal = actual_packets.len() == expected_packets.len();
     for p in expected_packets {
@@ -116,3 +119,13 @@ fn test_move_packets() {
     handler.assert_empty();
   }
 }
+
+#[test]
+fn test_broadcast() {
+  let handler = TestHandler::new();
+  let p: cb::Packet = cb::packet::KeepAlive { id: 5 }.into();
+  let mut b = Broadcast::new(p.clone());
+  b.send(handler.player());
+  b.send(handler.player());
+  handler.assert_sent(&[p.clone(), p]);
+}
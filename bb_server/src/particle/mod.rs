@@ -1,7 +1,7 @@
 mod ty;
 mod version;
 
-#[cfg(feature = "wasm_plugins")]
+#[cfg(feature = "wasm_host")]
 mod ffi;
 
 pub use ty::{Color, Data, Type};
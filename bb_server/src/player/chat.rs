@@ -0,0 +1,129 @@
+//! Chat formatting. When a player sends a chat message, it is placed into the
+//! `chat-format` from the config before being sent to everyone.
+//!
+//! The format can contain these placeholders:
+//! - `%player%`: The player's username. This can be clicked to copy the name.
+//! - `%team_prefix%` and `%team_postfix%`: The prefix and postfix of the
+//!   player's team. These are empty if the player isn't on a team.
+//! - `%world%`: The name of the world the player is in.
+//! - `%message%`: The message the player sent.
+//!
+//! Anything else between two `%` signs is left as is.
+
+use super::Player;
+use bb_common::util::{chat::Color, Chat};
+
+/// Everything that can be placed into a chat format.
+struct Placeholders<'a> {
+  player:       &'a str,
+  color:        Color,
+  team_prefix:  Chat,
+  team_postfix: Chat,
+  world:        &'a str,
+  message:      &'a str,
+}
+
+impl Player {
+  /// Formats a chat message sent by this player, using the `chat-format` from
+  /// the config. This is what will be shown to other players when this player
+  /// sends `text` in chat.
+  pub fn format_chat(&self, text: &str) -> Chat {
+    let world = self.world();
+    let wm = world.world_manager();
+    let (team_prefix, team_postfix, color) = match wm.team_of(self.id()) {
+      Some(team) => {
+        let team = team.lock();
+        (team.prefix().clone(), team.postfix().clone(), team.color().clone())
+      }
+      None => (Chat::empty(), Chat::empty(), Color::BrightGreen),
+    };
+    format(
+      &wm.config().chat_format,
+      Placeholders {
+        player: self.username(),
+        color,
+        team_prefix,
+        team_postfix,
+        world: &world.config().name,
+        message: text,
+      },
+    )
+  }
+}
+
+fn format(fmt: &str, p: Placeholders) -> Chat {
+  let mut msg = Chat::empty();
+  let mut rest = fmt;
+  while let Some(start) = rest.find('%') {
+    let after = &rest[start + 1..];
+    let end = match after.find('%') {
+      Some(end) => end,
+      None => break,
+    };
+    let name = &after[..end];
+    if !matches!(name, "player" | "team_prefix" | "team_postfix" | "world" | "message") {
+      // This might be the start of a real placeholder, so we only skip the first `%`.
+      add_text(&mut msg, &rest[..start + 1]);
+      rest = after;
+      continue;
+    }
+    add_text(&mut msg, &rest[..start]);
+    match name {
+      "player" => {
+        msg
+          .add(p.player)
+          .color(p.color.clone())
+          .insertion(p.player)
+          .copy_to_clipboard(p.player)
+          .show_text(format!("Click to copy {}", p.player));
+      }
+      "team_prefix" => msg.append(p.team_prefix.clone()),
+      "team_postfix" => msg.append(p.team_postfix.clone()),
+      "world" => add_text(&mut msg, p.world),
+      "message" => add_text(&mut msg, p.message),
+      _ => unreachable!(),
+    }
+    rest = &after[end + 1..];
+  }
+  add_text(&mut msg, rest);
+  msg
+}
+
+fn add_text(msg: &mut Chat, text: &str) {
+  if !text.is_empty() {
+    msg.add(text);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn placeholders<'a>(message: &'a str) -> Placeholders<'a> {
+    Placeholders {
+      player: "macmv",
+      color: Color::BrightGreen,
+      team_prefix: Chat::new("[red] "),
+      team_postfix: Chat::empty(),
+      world: "lobby",
+      message,
+    }
+  }
+
+  #[test]
+  fn placeholders_are_replaced() {
+    let msg = format("<%team_prefix%%player%%team_postfix%> %message%", placeholders("hello"));
+    assert_eq!(msg.to_plain(), "<[red] macmv> hello");
+    let msg = format("[%world%] %player%: %message%", placeholders("hi"));
+    assert_eq!(msg.to_plain(), "[lobby] macmv: hi");
+  }
+
+  #[test]
+  fn unknown_placeholders() {
+    let msg = format("100% %player% %foo% 50%", placeholders(""));
+    assert_eq!(msg.to_plain(), "100% macmv %foo% 50%");
+    // Players shouldn't be able to use placeholders in their own messages.
+    let msg = format("%message%", placeholders("%player%"));
+    assert_eq!(msg.to_plain(), "%player%");
+  }
+}
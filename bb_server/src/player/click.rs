@@ -4,6 +4,7 @@ use crate::{
   block::Block,
   event,
   math::{CollisionResult, Vec3},
+  world::Action,
 };
 use bb_common::{
   math::{FPos, Pos},
@@ -90,16 +91,17 @@ impl Player {
     placed_pos: Pos,
     ty: block::Type,
   ) {
-    if self
-      .world()
-      .events()
-      .player_request(event::BlockPlace {
-        player: self.clone(),
-        clicked_pos,
-        placed_pos,
-        block: ty.to_store(),
-      })
-      .is_handled()
+    if !self.world().allows(Some(self), placed_pos, Action::Place)
+      || self
+        .world()
+        .events()
+        .player_request(event::BlockPlace {
+          player: self.clone(),
+          clicked_pos,
+          placed_pos,
+          block: ty.to_store(),
+        })
+        .is_handled()
     {
       self.sync_block_at(placed_pos);
       inv.sync_main_hand();
@@ -0,0 +1,138 @@
+//! Item cooldowns, and interact rate tracking. Item cooldowns are shown on the
+//! client, and are also enforced on the server, so that modified clients (and
+//! 1.8 clients, which don't support cooldowns) can't use items early.
+
+use super::Player;
+use crate::item;
+use bb_common::net::cb;
+use std::collections::HashMap;
+
+/// How many ticks of interactions are kept for
+/// [`interact_rate`](Player::interact_rate).
+const RATE_WINDOW: usize = 20;
+
+#[derive(Debug)]
+pub(super) struct Cooldowns {
+  /// The ticks left for each item on cooldown.
+  items:  HashMap<item::Type, u32>,
+  /// The ticks left for each named cooldown. These are only used by plugins,
+  /// and are never sent to the client.
+  named:  HashMap<String, u32>,
+  /// The number of interactions on each of the last [`RATE_WINDOW`] ticks.
+  clicks: [u32; RATE_WINDOW],
+  /// The index into `clicks` for the current tick.
+  tick:   usize,
+}
+
+impl Cooldowns {
+  pub fn new() -> Self {
+    Cooldowns {
+      items:  HashMap::new(),
+      named:  HashMap::new(),
+      clicks: [0; RATE_WINDOW],
+      tick:   0,
+    }
+  }
+
+  /// Counts down every cooldown. Should be called once per tick.
+  pub fn tick(&mut self) {
+    self.items.retain(|_, ticks| {
+      *ticks -= 1;
+      *ticks > 0
+    });
+    self.named.retain(|_, ticks| {
+      *ticks -= 1;
+      *ticks > 0
+    });
+    self.tick = (self.tick + 1) % RATE_WINDOW;
+    self.clicks[self.tick] = 0;
+  }
+}
+
+impl Player {
+  /// Puts the given item on cooldown for `ticks` ticks. The client will show
+  /// the cooldown over every stack of this item, and the server will not run
+  /// item interactions for this item until the cooldown is over. Setting
+  /// `ticks` to 0 removes the cooldown.
+  pub fn set_cooldown(&self, item: item::Type, ticks: u32) {
+    {
+      let mut cooldowns = self.cooldowns.lock();
+      if ticks == 0 {
+        cooldowns.items.remove(&item);
+      } else {
+        cooldowns.items.insert(item, ticks);
+      }
+    }
+    self.send(cb::packet::SetCooldown { item: item.id() as i32, ticks });
+  }
+  /// Returns the number of ticks left on the cooldown for this item. Returns 0
+  /// if the item isn't on cooldown.
+  pub fn cooldown(&self, item: item::Type) -> u32 {
+    self.cooldowns.lock().items.get(&item).copied().unwrap_or(0)
+  }
+  /// Returns `true` if the given item is on cooldown.
+  pub fn has_cooldown(&self, item: item::Type) -> bool {
+    self.cooldowns.lock().items.contains_key(&item)
+  }
+
+  /// Checks the cooldown with the given name. If it is still running, this
+  /// returns `false`. Otherwise, this starts the cooldown for `ticks` ticks,
+  /// and returns `true`. This is intended for plugin abilities, which aren't
+  /// tied to a single item.
+  ///
+  /// ```ignore
+  /// if player.try_cooldown("dash", 100) {
+  ///   // Dash, and then wait 5 seconds before the next dash.
+  /// }
+  /// ```
+  pub fn try_cooldown(&self, name: &str, ticks: u32) -> bool {
+    let mut cooldowns = self.cooldowns.lock();
+    if cooldowns.named.contains_key(name) {
+      return false;
+    }
+    if ticks > 0 {
+      cooldowns.named.insert(name.into(), ticks);
+    }
+    true
+  }
+  /// Returns the number of ticks left on the cooldown with the given name.
+  /// Returns 0 if it isn't running.
+  pub fn named_cooldown(&self, name: &str) -> u32 {
+    self.cooldowns.lock().named.get(name).copied().unwrap_or(0)
+  }
+
+  /// Returns the number of times this player has interacted (right clicked)
+  /// in the last second.
+  pub fn interact_rate(&self) -> u32 { self.cooldowns.lock().clicks.iter().sum() }
+  /// Records an interaction, for [`interact_rate`](Self::interact_rate).
+  pub(crate) fn record_interact(&self) {
+    let mut cooldowns = self.cooldowns.lock();
+    let tick = cooldowns.tick;
+    cooldowns.clicks[tick] += 1;
+  }
+  /// Counts down all cooldowns. Called once per tick.
+  pub(super) fn tick_cooldowns(&self) { self.cooldowns.lock().tick(); }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tick() {
+    let mut cooldowns = Cooldowns::new();
+    cooldowns.items.insert(item::Type::Stick, 2);
+    cooldowns.named.insert("dash".into(), 1);
+    cooldowns.clicks[cooldowns.tick] += 3;
+    cooldowns.tick();
+    assert_eq!(cooldowns.items.get(&item::Type::Stick), Some(&1));
+    assert!(cooldowns.named.is_empty());
+    assert_eq!(cooldowns.clicks.iter().sum::<u32>(), 3);
+    cooldowns.tick();
+    assert!(cooldowns.items.is_empty());
+    for _ in 0..RATE_WINDOW {
+      cooldowns.tick();
+    }
+    assert_eq!(cooldowns.clicks.iter().sum::<u32>(), 0);
+  }
+}
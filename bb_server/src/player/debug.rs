@@ -0,0 +1,187 @@
+//! Debug overlays, which are toggled with `/debug`. These draw particles that
+//! only this player can see, to show things that are normally invisible, such
+//! as chunk borders, entity hitboxes, and the path an entity is following.
+
+use super::Player;
+use crate::{
+  entity::{behavior::PathDebug, EntityHandle},
+  math::AABB,
+  particle::{Color, Particle, Type},
+};
+use bb_common::{math::FPos, util::Chat};
+
+/// How often the overlays are redrawn, in ticks. Dust particles stay on screen
+/// for about a second, so this keeps the overlays visible without sending too
+/// many packets.
+const REDRAW_INTERVAL: u32 = 10;
+/// Hitboxes are drawn for every entity within this many blocks.
+const HITBOX_RADIUS: f64 = 16.0;
+/// The height of the chunk border lines, above and below the player.
+const CHUNK_BORDER_HEIGHT: i32 = 8;
+
+const CHUNK_COLOR: Color = Color { r: 255, g: 255, b: 0 };
+const HITBOX_COLOR: Color = Color { r: 255, g: 255, b: 255 };
+const PATH_COLOR: Color = Color { r: 0, g: 255, b: 0 };
+const OPEN_COLOR: Color = Color { r: 0, g: 128, b: 255 };
+const CLOSED_COLOR: Color = Color { r: 128, g: 128, b: 128 };
+const TARGET_COLOR: Color = Color { r: 255, g: 0, b: 0 };
+
+#[derive(Debug, Default)]
+pub(super) struct DebugOverlays {
+  chunks:   bool,
+  hitboxes: bool,
+  /// The entity whose path is being drawn.
+  path:     Option<EntityHandle>,
+  /// Counts up to [`REDRAW_INTERVAL`].
+  ticks:    u32,
+}
+
+impl DebugOverlays {
+  fn any(&self) -> bool { self.chunks || self.hitboxes || self.path.is_some() }
+}
+
+impl Player {
+  /// Toggles the chunk border overlay. Returns `true` if it is now enabled.
+  pub fn toggle_debug_chunks(&self) -> bool {
+    let mut debug = self.debug.lock();
+    debug.chunks = !debug.chunks;
+    debug.chunks
+  }
+  /// Toggles the hitbox overlay, which draws the hitbox of every nearby
+  /// entity. Returns `true` if it is now enabled.
+  pub fn toggle_debug_hitboxes(&self) -> bool {
+    let mut debug = self.debug.lock();
+    debug.hitboxes = !debug.hitboxes;
+    debug.hitboxes
+  }
+  /// Sets the entity whose path should be drawn. This shows the path, the
+  /// open and closed sets, and the target from the entity's
+  /// [`path_debug`](crate::entity::behavior::Behavior::path_debug). Setting
+  /// this to `None` hides the path overlay.
+  pub fn set_debug_path(&self, ent: Option<EntityHandle>) { self.debug.lock().path = ent; }
+
+  /// Draws all the enabled overlays. Called once per tick.
+  pub(super) fn tick_debug(&self) {
+    let (chunks, hitboxes, path) = {
+      let mut debug = self.debug.lock();
+      if !debug.any() {
+        return;
+      }
+      debug.ticks = (debug.ticks + 1) % REDRAW_INTERVAL;
+      if debug.ticks != 0 {
+        return;
+      }
+      (debug.chunks, debug.hitboxes, debug.path)
+    };
+    if chunks {
+      self.draw_chunk_borders();
+    }
+    if hitboxes {
+      self.draw_hitboxes();
+    }
+    if let Some(handle) = path {
+      self.draw_path(handle);
+    }
+  }
+
+  fn draw_chunk_borders(&self) {
+    let pos = self.pos();
+    let chunk = pos.chunk();
+    let (x, z) = (chunk.block_x() as f64, chunk.block_z() as f64);
+    let y = pos.block().y;
+    for (dx, dz) in [(0.0, 0.0), (16.0, 0.0), (0.0, 16.0), (16.0, 16.0)] {
+      for dy in -CHUNK_BORDER_HEIGHT..=CHUNK_BORDER_HEIGHT {
+        self.debug_particle(FPos::new(x + dx, (y + dy) as f64, z + dz), CHUNK_COLOR);
+      }
+    }
+    // The edges are drawn at the player's feet, so that they line up with the
+    // blocks the player is standing on.
+    let y = pos.y() + 0.1;
+    for i in 0..16 {
+      let i = i as f64 + 0.5;
+      self.debug_particle(FPos::new(x + i, y, z), CHUNK_COLOR);
+      self.debug_particle(FPos::new(x + i, y, z + 16.0), CHUNK_COLOR);
+      self.debug_particle(FPos::new(x, y, z + i), CHUNK_COLOR);
+      self.debug_particle(FPos::new(x + 16.0, y, z + i), CHUNK_COLOR);
+    }
+  }
+
+  fn draw_hitboxes(&self) {
+    let world = self.world();
+    for ent in world.entities_in_radius(self.pos(), HITBOX_RADIUS) {
+      if ent.eid() == self.eid {
+        continue;
+      }
+      if let Some(ent) = ent.as_entity_ref(&world) {
+        self.draw_aabb(ent.hitbox(), HITBOX_COLOR);
+      }
+    }
+  }
+
+  fn draw_path(&self, handle: EntityHandle) {
+    let res =
+      self.world().entities().get_handle(handle).map(|ent| (ent.hitbox(), ent.path_debug()));
+    let (hitbox, path) = match res {
+      Ok(v) => v,
+      Err(e) => {
+        self.set_debug_path(None);
+        self.send_message(Chat::new(format!("no longer showing a path: {e}")));
+        return;
+      }
+    };
+    // The entity is always outlined, so that it is clear which entity is
+    // selected, even if it isn't pathfinding right now.
+    self.draw_aabb(hitbox, PATH_COLOR);
+    let Some(PathDebug { path, open, closed, target }) = path else { return };
+    for pos in closed {
+      self.debug_particle(pos.center().add_y(0.5), CLOSED_COLOR);
+    }
+    for pos in open {
+      self.debug_particle(pos.center().add_y(0.5), OPEN_COLOR);
+    }
+    for pos in path {
+      self.debug_particle(pos.center().add_y(0.1), PATH_COLOR);
+    }
+    if let Some(target) = target {
+      self.draw_aabb(AABB::new(target.center(), FPos::new(1.0, 1.0, 1.0)), TARGET_COLOR);
+    }
+  }
+
+  /// Draws the 12 edges of the given box.
+  fn draw_aabb(&self, aabb: AABB, color: Color) {
+    let (min, max) = (aabb.min(), aabb.max());
+    let edges = [
+      (FPos::new(min.x(), min.y(), min.z()), FPos::new(max.x(), min.y(), min.z())),
+      (FPos::new(min.x(), min.y(), max.z()), FPos::new(max.x(), min.y(), max.z())),
+      (FPos::new(min.x(), max.y(), min.z()), FPos::new(max.x(), max.y(), min.z())),
+      (FPos::new(min.x(), max.y(), max.z()), FPos::new(max.x(), max.y(), max.z())),
+      (FPos::new(min.x(), min.y(), min.z()), FPos::new(min.x(), min.y(), max.z())),
+      (FPos::new(max.x(), min.y(), min.z()), FPos::new(max.x(), min.y(), max.z())),
+      (FPos::new(min.x(), max.y(), min.z()), FPos::new(min.x(), max.y(), max.z())),
+      (FPos::new(max.x(), max.y(), min.z()), FPos::new(max.x(), max.y(), max.z())),
+      (FPos::new(min.x(), min.y(), min.z()), FPos::new(min.x(), max.y(), min.z())),
+      (FPos::new(max.x(), min.y(), min.z()), FPos::new(max.x(), max.y(), min.z())),
+      (FPos::new(min.x(), min.y(), max.z()), FPos::new(min.x(), max.y(), max.z())),
+      (FPos::new(max.x(), min.y(), max.z()), FPos::new(max.x(), max.y(), max.z())),
+    ];
+    for (from, to) in edges {
+      // One particle every quarter block, and always at least one on each end.
+      let steps = (from.dist(to) * 4.0).ceil().max(1.0) as u32;
+      for i in 0..=steps {
+        let t = i as f64 / steps as f64;
+        self.debug_particle(from + (to - from) * t, color);
+      }
+    }
+  }
+
+  fn debug_particle(&self, pos: FPos, color: Color) {
+    self.send_particle_if_close(Particle {
+      ty: Type::Dust(color, 0.5),
+      pos,
+      long_distance: false,
+      offset: FPos::new(0.0, 0.0, 0.0),
+      count: 1,
+      data: 0.0,
+    });
+  }
+}
@@ -0,0 +1,184 @@
+//! Per-player selections and edit history. Edits made through
+//! [`edit_selection`](Player::edit_selection), `/fill`, and `/clone` are
+//! recorded, so that they can be undone with [`undo`](Player::undo).
+
+use super::Player;
+use crate::world::{ChangeSet, Cuboid, EditError, RegionEdit};
+use bb_common::math::Pos;
+use std::collections::VecDeque;
+
+#[derive(Debug)]
+pub(super) struct EditState {
+  pos1:    Option<Pos>,
+  pos2:    Option<Pos>,
+  history: History,
+}
+
+/// A player's undo and redo stacks. The memory used by both stacks is limited,
+/// and the oldest edits are forgotten once the limit is reached.
+#[derive(Debug)]
+struct History {
+  undo: VecDeque<ChangeSet>,
+  redo: Vec<ChangeSet>,
+  /// The memory used by all the change sets in `undo` and `redo`, in bytes.
+  size: usize,
+  /// The maximum value of `size`.
+  max:  usize,
+}
+
+impl EditState {
+  /// Creates an empty selection and history. The history will use at most
+  /// `max_history` bytes.
+  pub fn new(max_history: usize) -> Self {
+    EditState {
+      pos1:    None,
+      pos2:    None,
+      history: History { undo: VecDeque::new(), redo: vec![], size: 0, max: max_history },
+    }
+  }
+}
+
+impl History {
+  fn push(&mut self, changes: ChangeSet) {
+    for redo in self.redo.drain(..) {
+      self.size -= redo.memory();
+    }
+    // Anything larger than the whole history cannot be undone.
+    if changes.memory() > self.max {
+      return;
+    }
+    self.size += changes.memory();
+    self.undo.push_back(changes);
+    while self.size > self.max {
+      match self.undo.pop_front() {
+        Some(old) => self.size -= old.memory(),
+        None => break,
+      }
+    }
+  }
+}
+
+impl Player {
+  /// Sets the first corner of this player's selection.
+  pub fn set_pos1(&self, pos: Pos) { self.edit.lock().pos1 = Some(pos); }
+  /// Sets the second corner of this player's selection.
+  pub fn set_pos2(&self, pos: Pos) { self.edit.lock().pos2 = Some(pos); }
+  /// Returns this player's selection, or `None` if either corner hasn't been
+  /// set.
+  pub fn selection(&self) -> Option<Cuboid> {
+    let edit = self.edit.lock();
+    Some(Cuboid::new(edit.pos1?, edit.pos2?))
+  }
+  /// Clears both corners of this player's selection.
+  pub fn clear_selection(&self) {
+    let mut edit = self.edit.lock();
+    edit.pos1 = None;
+    edit.pos2 = None;
+  }
+
+  /// Applies the given edit to this player's selection, and records it in
+  /// this player's history. Returns the number of blocks changed.
+  pub fn edit_selection(&self, edit: RegionEdit) -> Result<u64, EditError> {
+    let region = self.selection().ok_or(EditError::NoSelection)?;
+    let changes = self.world().edit_region(region, edit)?;
+    let changed = changes.len() as u64;
+    self.remember(changes);
+    Ok(changed)
+  }
+
+  /// Adds the given changes to this player's history, so that they can be
+  /// undone with [`undo`](Self::undo). This should be called after the
+  /// changes have been made. This clears anything that could be redone.
+  ///
+  /// If the history is full, the oldest edits will be forgotten. Empty change
+  /// sets are ignored.
+  pub fn remember(&self, changes: ChangeSet) {
+    if changes.is_empty() {
+      return;
+    }
+    self.edit.lock().history.push(changes);
+  }
+  /// Forgets every edit in this player's history.
+  pub fn clear_history(&self) {
+    let mut edit = self.edit.lock();
+    edit.history.undo.clear();
+    edit.history.redo.clear();
+    edit.history.size = 0;
+  }
+
+  /// Undoes the last edit made by this player. Returns the number of blocks
+  /// changed, or `None` if there is nothing to undo.
+  pub fn undo(&self) -> Result<Option<u64>, EditError> {
+    let changes = match self.edit.lock().history.undo.pop_back() {
+      Some(c) => c,
+      None => return Ok(None),
+    };
+    let res = self.world().apply_changes(&changes.inverse());
+    let mut edit = self.edit.lock();
+    match res {
+      Ok(changed) => {
+        edit.history.redo.push(changes);
+        Ok(Some(changed))
+      }
+      Err(e) => {
+        edit.history.undo.push_back(changes);
+        Err(e)
+      }
+    }
+  }
+  /// Redoes the last edit undone by this player. Returns the number of blocks
+  /// changed, or `None` if there is nothing to redo.
+  pub fn redo(&self) -> Result<Option<u64>, EditError> {
+    let changes = match self.edit.lock().history.redo.pop() {
+      Some(c) => c,
+      None => return Ok(None),
+    };
+    let res = self.world().apply_changes(&changes);
+    let mut edit = self.edit.lock();
+    match res {
+      Ok(changed) => {
+        edit.history.undo.push_back(changes);
+        Ok(Some(changed))
+      }
+      Err(e) => {
+        edit.history.redo.push(changes);
+        Err(e)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn changes(n: i32) -> ChangeSet {
+    let mut changes = ChangeSet::new();
+    for x in 0..n {
+      changes.push(Pos::new(x, 0, 0), 0, 1);
+    }
+    changes
+  }
+
+  #[test]
+  fn history_limit() {
+    let size = changes(1).memory();
+    let mut history = History { undo: VecDeque::new(), redo: vec![], size: 0, max: size * 10 };
+    history.push(changes(4));
+    history.push(changes(4));
+    assert_eq!(history.undo.len(), 2);
+    assert_eq!(history.size, size * 8);
+    // The oldest edit is dropped to make room.
+    history.push(changes(3));
+    assert_eq!(history.undo.len(), 2);
+    assert_eq!(history.size, size * 7);
+    // This is larger than the whole history, so it is ignored.
+    history.push(changes(11));
+    assert_eq!(history.undo.len(), 2);
+    // Pushing clears the redo stack.
+    history.redo.push(history.undo.pop_back().unwrap());
+    history.push(changes(1));
+    assert!(history.redo.is_empty());
+    assert_eq!(history.size, size * 5);
+  }
+}
@@ -41,6 +41,13 @@ pub struct PlayerInventory {
   /// buttons part way through the drag, we silently ignore the whole thing.
   drag_bt:    Option<Button>,
 
+  /// The window id and action number of the last click. Clients before 1.17
+  /// number their clicks, so a gap means we missed one.
+  last_action:  Option<(u8, i16)>,
+  /// Set when the client's inventory doesn't match ours. The whole inventory
+  /// will be sent at the end of the next click.
+  needs_resync: bool,
+
   player: Weak<Player>,
 }
 
@@ -62,6 +69,8 @@ impl PlayerInventory {
       held:           Stack::empty(),
       drag_slots:     vec![],
       drag_bt:        None,
+      last_action:    None,
+      needs_resync:   false,
       player:         weak,
     }
   }
@@ -76,16 +85,26 @@ impl PlayerInventory {
     let p = self.player.upgrade().unwrap();
     win.open(p.uuid, &p.conn);
     self.window = Some(win);
+    self.last_action = None;
   }
   pub fn close_window(&mut self) {
+    let mut leftover = vec![];
     if let Some(win) = self.window.take() {
       let p = self.player.upgrade().unwrap();
       win.close(p.uuid);
+      // Anything left in the input slots goes back to the player.
+      if let Window::Merchant(win) = &win {
+        leftover.extend(win.take_inputs());
+      }
     }
     self.main.offset = 9;
     self.main.wid = 0;
     self.hotbar.offset = 36;
     self.hotbar.wid = 0;
+    self.last_action = None;
+    for stack in leftover.into_iter().filter(|s| !s.is_empty()) {
+      self.give(stack);
+    }
   }
 
   /// Gives an item to the player. Returns the number of remaining items in the
@@ -100,6 +119,8 @@ impl PlayerInventory {
   pub fn main_hand(&self) -> &Stack { self.hotbar().get_raw(self.selected_index as u32).unwrap() }
   /// Returns the item in the player's off hand.
   pub fn off_hand(&self) -> &Stack { self.off_hand.get_raw(0).unwrap() }
+  /// Returns the item in the player's helmet slot.
+  pub fn helmet(&self) -> &Stack { self.head.get_raw(0).unwrap() }
   /// Returns the item in the given hand.
   pub fn in_hand(&self, hand: Hand) -> &Stack {
     match hand {
@@ -107,6 +128,21 @@ impl PlayerInventory {
       Hand::Off => self.off_hand(),
     }
   }
+  /// Returns the modifiers that the player's armor and held item apply to the
+  /// given attribute.
+  pub fn equipment_modifiers(&self, attr: entity::Attribute) -> Vec<entity::Modifier> {
+    use cb::{ArmorSlot, EquipmentSlot};
+    [
+      (self.head.get_raw(0), EquipmentSlot::Armor(ArmorSlot::Helmet)),
+      (self.chest.get_raw(0), EquipmentSlot::Armor(ArmorSlot::Chestplate)),
+      (self.legs.get_raw(0), EquipmentSlot::Armor(ArmorSlot::Leggings)),
+      (self.feet.get_raw(0), EquipmentSlot::Armor(ArmorSlot::Boots)),
+      (Some(self.main_hand()), EquipmentSlot::Hand(Hand::Main)),
+    ]
+    .into_iter()
+    .filter_map(|(stack, slot)| stack?.modifier(attr, slot))
+    .collect()
+  }
   /// Syncs the slot in the main hand of the player.
   pub fn sync_main_hand(&self) { self.sync(self.selected_index as i32 + 36); }
 
@@ -255,7 +291,8 @@ impl PlayerInventory {
       items.push(it.to_item());
     }
     let held = self.held.to_item();
-    self.main.conn.send(cb::packet::WindowItems { wid: 1, items, held });
+    let wid = if self.window.is_some() { 1 } else { 0 };
+    self.main.conn.send(cb::packet::WindowItems { wid, items, held, revision: 0 });
   }
   /// Sends an item update for the given slot. This shouldn't every be needed,
   /// as functions like [`set`](Self::set) and [`replace`](Self::replace) will
@@ -271,9 +308,10 @@ impl PlayerInventory {
     }
     if index == -999 {
       self.main.conn.send(cb::packet::WindowItem {
-        wid:  u8::MAX,
-        slot: -1,
-        item: self.held.to_item(),
+        wid:      u8::MAX,
+        slot:     -1,
+        item:     self.held.to_item(),
+        revision: 0,
       });
       return;
     }
@@ -303,6 +341,31 @@ impl PlayerInventory {
     }
   }
 
+  /// Checks the revision or action number the client sent with a click. If
+  /// the client clicked on an out of date inventory, the whole inventory will
+  /// be resynced after the click is handled. Returns `true` if the client is
+  /// out of sync.
+  ///
+  /// 1.17+ clients send `revision`, which is the revision of the last
+  /// inventory update they received. Older clients send `action`, which goes
+  /// up by one with every click.
+  pub fn check_click(&mut self, wid: u8, revision: Option<u32>, action: Option<i16>) -> bool {
+    let mut desynced = false;
+    if let Some(revision) = revision {
+      desynced |= revision != self.main.conn.revision();
+    }
+    if let Some(action) = action {
+      if let Some((last_wid, last)) = self.last_action {
+        desynced |= last_wid == wid && action != last.wrapping_add(1);
+      }
+      self.last_action = Some((wid, action));
+    }
+    if desynced {
+      self.needs_resync = true;
+    }
+    desynced
+  }
+
   /// Handles an inventory move operation.
   pub fn click_window(&mut self, slot: i32, click: ClickWindow, allow: bool) {
     info!("handling click at slot {slot} {click:?}");
@@ -429,7 +492,12 @@ impl PlayerInventory {
       ClickWindow::DragEnd(bt) => self.drag_end(bt),
     }
 
-    self.sync_all();
+    // Windows can't always sync single slots correctly, so they are always
+    // resynced.
+    if self.needs_resync || self.window.is_some() {
+      self.needs_resync = false;
+      self.sync_all();
+    }
   }
 
   /// Takes half of the items in the slot `a` and moves them to `b`. If `b` is
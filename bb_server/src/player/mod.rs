@@ -2,11 +2,15 @@ use crate::{
   block,
   command::{CommandSender, ErrorFormat},
   entity,
-  entity::EntityRef,
-  event,
+  entity::{Attribute, Attributes, Effect, EntityHandle, EntityRef, Trade},
+  event, item,
   item::Stack,
   math::Vec3,
-  net::ConnSender,
+  net::{
+    backpressure::{DropPolicy, Priority},
+    broadcast::{broadcast, Broadcast},
+    ConnSender,
+  },
   particle::Particle,
   world::World,
 };
@@ -14,24 +18,41 @@ use bb_common::{
   math::{ChunkPos, FPos, Pos},
   metadata::{Metadata, Pose},
   net::{cb, sb::PlayerCommand},
-  util::{Chat, GameMode, JoinInfo, SwitchMode, UUID},
+  util::{Chat, ClientKind, GameMode, JoinInfo, SwitchMode, Trace, TraceLog, UUID},
   version::ProtocolVersion,
 };
-use parking_lot::{Mutex, MutexGuard};
-use std::{collections::HashSet, f64::consts, fmt, net::SocketAddr, sync::Arc, time::Instant};
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use std::{
+  collections::{HashMap, HashSet},
+  fmt,
+  net::SocketAddr,
+  sync::Arc,
+  time::{Duration, Instant},
+};
 
+mod chat;
 mod click;
+mod cooldown;
+mod debug;
+mod edit;
 mod inventory;
+mod pose;
+mod reach;
 mod scoreboard;
+mod skin;
 mod team;
 mod tick;
+mod trace;
+mod visibility;
 pub mod window;
 
 pub use click::{AirClick, BlockClick, Click};
 pub use inventory::PlayerInventory;
+pub use reach::ReachAction;
 pub use scoreboard::Scoreboard;
+pub use skin::{Skin, SkinModel, SkinParts, Textures};
 pub use team::Team;
-pub use window::Window;
+pub use window::{MerchantWindow, Window};
 
 #[derive(Debug, Clone)]
 struct DigProgress {
@@ -77,6 +98,7 @@ pub(crate) struct PlayerPosition {
   crouching: bool,
   sprinting: bool,
   swimming:  bool,
+  pose:      Pose,
 
   /// Stores the current progress. Mining time can change between when you start
   /// and when you end, so the most accurate way to track mining time is to
@@ -113,12 +135,34 @@ pub struct PlayerFood {
 pub struct Player {
   // The EID of the player. Never changes.
   eid:           i32,
+  /// The generation of this player. See [`EntityHandle`].
+  gen:           u32,
   username:      String,
   tab_name:      Mutex<Option<Chat>>,
+  /// The skin from the player's Mojang profile. This is used to reset the
+  /// skin after a plugin changes it. In offline mode, this may be fetched
+  /// after the player joins.
+  profile_skin:  Mutex<Option<Skin>>,
+  skin:          Mutex<Option<Skin>>,
+  skin_parts:    Mutex<SkinParts>,
+  /// The players this player cannot see. This maps entity ids to UUIDs.
+  hidden:        Mutex<HashMap<i32, UUID>>,
+  /// This player's selection and edit history.
+  edit:          Mutex<edit::EditState>,
   uuid:          UUID,
+  /// The address the client connected from. This is `None` if the proxy
+  /// didn't send it.
+  addr:          Option<SocketAddr>,
+  /// The mod loader the client is running. This may change once the client
+  /// sends its brand.
+  client:        Mutex<ClientKind>,
+  /// The brand the client sent after joining, like `vanilla` or `fabric`.
+  brand:         Mutex<Option<String>>,
+  /// The ids of the client's mods, if the proxy found them out.
+  mods:          Vec<String>,
   conn:          ConnSender,
   ver:           ProtocolVersion,
-  world:         Arc<World>,
+  world:         RwLock<Arc<World>>,
   view_distance: u32,
 
   game_mode: Mutex<GameMode>,
@@ -131,6 +175,11 @@ pub struct Player {
 
   health: Mutex<PlayerHealth>,
   food:   Mutex<PlayerFood>,
+  attrs:  Mutex<Attributes>,
+
+  cooldowns: Mutex<cooldown::Cooldowns>,
+  /// The debug overlays this player has enabled with `/debug`.
+  debug:     Mutex<debug::DebugOverlays>,
 
   // Not very efficient, but required, as we generate chunks in the background. Because chunk
   // generation is slow, we need to do it over multiple ticks. If the player moves into a chunk,
@@ -138,6 +187,12 @@ pub struct Player {
   // same chunk twice if the terrain generator takes too long. This means it's easiest to simply
   // store every chunk the client knows about here.
   loaded_chunks: Mutex<HashSet<ChunkPos>>,
+  /// The entities that have been spawned for this player, and not removed yet.
+  tracked:       Mutex<HashSet<i32>>,
+
+  /// The packet currently being traced. See [`trace_span`](Self::trace_span).
+  trace:  Mutex<Option<Trace>>,
+  traces: Mutex<TraceLog>,
 }
 
 impl fmt::Debug for Player {
@@ -199,18 +254,31 @@ impl Player {
     let game_mode = world.world_manager().default_game_mode();
     let mut abilities = PlayerAbilities::new();
     abilities.set_from_game_mode(game_mode);
+    let skin = Skin::from_properties(&info.properties);
     Arc::new_cyclic(|weak| Player {
       eid,
+      gen: entity::next_gen(),
       username: info.username,
       tab_name: Mutex::new(None),
+      profile_skin: Mutex::new(skin.clone()),
+      skin: Mutex::new(skin),
+      skin_parts: Mutex::new(SkinParts::default()),
+      hidden: Mutex::new(HashMap::new()),
+      edit: Mutex::new(edit::EditState::new(
+        world.world_manager().config().edit_history_size as usize * 1024,
+      )),
       uuid: info.uuid,
+      addr: info.addr,
+      client: Mutex::new(info.client),
+      brand: Mutex::new(None),
+      mods: info.mods,
       inv: PlayerInventory::new(weak.clone(), conn.clone()).into(),
       scoreboard: Scoreboard::new(conn.clone()).into(),
       conn,
       ver: ProtocolVersion::from(info.ver as i32),
       view_distance: world.world_manager().config().view_distance,
       game_mode: Mutex::new(game_mode),
-      world,
+      world: RwLock::new(world),
       pos: PlayerPosition {
         curr:         pos,
         prev:         pos,
@@ -226,13 +294,20 @@ impl Player {
         crouching:    false,
         sprinting:    false,
         swimming:     false,
+        pose:         Pose::Standing,
         dig_progress: None,
       }
       .into(),
       abilities: Mutex::new(abilities),
       health: PlayerHealth { health: 20.0, absorption: 0.0, hit_delay: 0 }.into(),
       food: PlayerFood { food: 20, saturation: 5.0 }.into(),
+      attrs: Attributes::new(entity::Type::Player).into(),
+      cooldowns: Mutex::new(cooldown::Cooldowns::new()),
+      debug: Mutex::new(debug::DebugOverlays::default()),
       loaded_chunks: Mutex::new(HashSet::new()),
+      tracked: Mutex::new(HashSet::new()),
+      trace: Mutex::new(None),
+      traces: Mutex::new(TraceLog::new(trace::TRACE_WINDOW)),
     })
   }
 
@@ -241,8 +316,38 @@ impl Player {
 
   /// Returns the player's entity id. Used to send packets about entities.
   pub fn eid(&self) -> i32 { self.eid }
+  /// Returns a handle to this player, which plugins can use to refer to them
+  /// as an entity.
+  pub fn handle(&self) -> EntityHandle { EntityHandle { eid: self.eid, gen: self.gen } }
   /// Returns the player's uuid. Used to lookup players in the world.
   pub fn id(&self) -> UUID { self.uuid }
+  /// Returns the address this player connected from. This is `None` if the
+  /// proxy didn't send it.
+  pub fn addr(&self) -> Option<SocketAddr> { self.addr }
+  /// Returns the mod loader this player's client is running.
+  pub fn client_kind(&self) -> ClientKind { *self.client.lock() }
+  /// Returns the brand this player's client sent, like `vanilla` or `fabric`.
+  /// This is `None` until the client sends it, shortly after joining.
+  pub fn brand(&self) -> Option<String> { self.brand.lock().clone() }
+  /// Returns the ids of the mods this player has installed. This is only
+  /// known for Forge clients, when the proxy has `forge-mod-list` enabled.
+  pub fn mods(&self) -> &[String] { &self.mods }
+  /// Returns `true` if this player has the given mod installed.
+  pub fn has_mod(&self, id: &str) -> bool { self.mods.iter().any(|m| m == id) }
+  /// Stores the brand the client sent. Fabric and Forge clients are detected
+  /// from this, if the proxy didn't already detect them.
+  pub(crate) fn set_brand(&self, brand: String) {
+    let lower = brand.to_lowercase();
+    let mut client = self.client.lock();
+    if *client == ClientKind::Vanilla {
+      if lower.contains("fabric") {
+        *client = ClientKind::Fabric;
+      } else if lower.contains("forge") || lower.contains("fml") {
+        *client = ClientKind::Forge;
+      }
+    }
+    *self.brand.lock() = Some(brand);
+  }
   /// Returns the player's view disstance. This is how far they can see in
   /// chunks.
   pub fn view_distance(&self) -> u32 { self.view_distance }
@@ -267,6 +372,41 @@ impl Player {
     });
   }
 
+  /// Returns the value of the given attribute, after all modifiers have been
+  /// applied. This includes modifiers from the player's armor and held item.
+  pub fn attribute(&self, attr: Attribute) -> f64 {
+    let equipment = self.lock_inventory().equipment_modifiers(attr);
+    self.attrs.lock().get_with(attr, &equipment)
+  }
+  /// Returns this player's attributes. Modifying these will not send any
+  /// updates to clients, so prefer
+  /// [`modify_attributes`](Self::modify_attributes).
+  pub fn attributes(&self) -> MutexGuard<'_, Attributes> { self.attrs.lock() }
+  /// Modifies this player's attributes, and sends the changes to this player
+  /// and everyone nearby. If the player's max health goes below their current
+  /// health, their health will be reduced.
+  pub fn modify_attributes(&self, f: impl FnOnce(&mut Attributes)) {
+    let max = {
+      let mut attrs = self.attrs.lock();
+      f(&mut attrs);
+      self.send_attributes(&attrs);
+      attrs.get(Attribute::MaxHealth) as f32
+    };
+    if self.health() > max {
+      self.set_health(max);
+    }
+  }
+  /// Applies the given potion effect for `ticks` ticks. `level` starts at 1.
+  pub fn add_effect(&self, effect: Effect, level: u32, ticks: u32) {
+    let (attr, m) = effect.modifier(level);
+    self.modify_attributes(|a| a.add_modifier(attr, m.with_duration(ticks)));
+  }
+  fn send_attributes(&self, attrs: &Attributes) {
+    if let Some(packet) = attrs.to_packet(self.eid) {
+      self.send_all_in_view(packet);
+    }
+  }
+
   /// Returns the version that this client connected with. This will only change
   /// if the player disconnects and logs in with another client.
   pub fn ver(&self) -> ProtocolVersion { self.ver }
@@ -276,8 +416,9 @@ impl Player {
   /// Returns a locked reference to the player's scoreboard.
   pub fn lock_scoreboard(&self) -> MutexGuard<Scoreboard> { self.scoreboard.lock() }
 
-  /// Returns a reference to the world the player is in.
-  pub fn world(&self) -> &Arc<World> { &self.world }
+  /// Returns the world the player is in. This will change if the player is
+  /// moved with [`teleport_world`](Self::teleport_world).
+  pub fn world(&self) -> Arc<World> { self.world.read().clone() }
 
   /// This will move the player on the next player tick. Used whenever a
   /// position packet is received.
@@ -286,6 +427,10 @@ impl Player {
     pos.next = FPos::new(x, y, z);
   }
 
+  /// Sets whether the player is on the ground. Used whenever a movement packet
+  /// is received.
+  pub(crate) fn set_on_ground(&self, on_ground: bool) { self.pos.lock().on_ground = on_ground; }
+
   /// This will set the player's look direction on the next player tick. Used
   /// whenever a player look packet is received.
   pub(crate) fn set_next_look(&self, yaw: f32, pitch: f32) {
@@ -310,6 +455,37 @@ impl Player {
     p.teleport_to = Some(pos);
   }
 
+  /// Moves the player into another world, at the given position. The client
+  /// is sent a respawn, so that it forgets about the old world, and then the
+  /// new world is sent like it would be on join. If the player is already in
+  /// `world`, this is the same as [`teleport`](Self::teleport).
+  ///
+  /// This fires a [`PlayerChangeWorld`](event::PlayerChangeWorld) event.
+  /// Returns `false` if the player is offline, or if `world` isn't loaded.
+  pub fn teleport_world(self: &Arc<Self>, world: &Arc<World>, pos: FPos) -> bool {
+    self.world().world_manager().move_player(self, world, pos)
+  }
+
+  /// Switches the world this player is in, and moves them to `pos`. This
+  /// forgets every chunk and entity that the client knew about. It should only
+  /// be called after the player has been removed from their old world.
+  pub(crate) fn set_world(&self, world: Arc<World>, pos: FPos) {
+    let old = std::mem::replace(&mut *self.world.write(), world);
+    // Chunks that are still generating in the old world should not be sent.
+    for chunk in self.pos().chunk().around(self.view_distance) {
+      old.unqueue_chunk(chunk, self);
+    }
+    self.loaded_chunks.lock().clear();
+    self.tracked.lock().clear();
+    let mut p = self.pos.lock();
+    p.prev = pos;
+    p.curr = pos;
+    p.next = pos;
+    p.teleport_to = Some(pos);
+    p.vel = Vec3::new(0.0, 0.0, 0.0);
+    p.dig_progress = None;
+  }
+
   /// Sends the player a chat message.
   pub fn send_message(&self, msg: Chat) {
     self.send(cb::packet::ChatMessage {
@@ -327,7 +503,7 @@ impl Player {
   /// Sends the particle to the player. This will always send the packet, even
   /// if the particle is too far away.
   pub fn send_particle(&self, particle: Particle) {
-    self.send(particle.to_packet(self.world.world_manager().block_converter(), self.ver));
+    self.send(particle.to_packet(self.world().world_manager().block_converter(), self.ver));
   }
   /// Sends the particle to the player, if the player can see the particle. This
   /// uses `particle.long_distance` to check if the player is in range.
@@ -355,8 +531,17 @@ impl Player {
   }
 
   /// Shows the given inventory to the client. The title will be shown in the
-  /// top left of the window.
-  pub fn show_inventory(&self, win: Window, title: &Chat) {
+  /// top left of the window. Plugins can cancel this with the `window_open`
+  /// event, in which case nothing is sent, and this returns `false`.
+  pub fn show_inventory(self: &Arc<Self>, win: Window, title: &Chat) -> bool {
+    if self
+      .world()
+      .events()
+      .player_request(event::OpenWindow { player: self.clone(), window: win.clone() })
+      .is_handled()
+    {
+      return false;
+    }
     self.send(cb::packet::WindowOpen {
       wid:   1,
       ty:    win.ty().into(),
@@ -364,25 +549,34 @@ impl Player {
       title: title.to_json(),
     });
     self.send(cb::packet::WindowItems {
-      wid:   1,
-      items: win.items().map(|i| i.to_item()).collect(),
-      held:  Stack::empty().to_item(),
+      wid:      1,
+      items:    win.items().map(|i| i.to_item()).collect(),
+      held:     Stack::empty().to_item(),
+      revision: 0,
     });
     self.lock_inventory().open_window(win);
+    true
+  }
+
+  /// Opens a merchant window with the given trades. Any trades used by the
+  /// player will update `trades`, so this should be the list stored on the
+  /// entity the player is trading with.
+  pub fn show_trades(self: &Arc<Self>, trades: Arc<Mutex<Vec<Trade>>>, title: &Chat) {
+    let win = MerchantWindow::new(trades);
+    let offers = win.offers();
+    if self.show_inventory(Window::Merchant(win), title) {
+      self.send(cb::packet::TradeList { wid: 1, offers });
+    }
   }
 
   /// Disconnects the player. The given chat message will be shown on the
   /// loading screen.
   ///
-  /// This may not have an effect immediately. This only sends a disconnect
-  /// packet. Assuming normal operation, the client will then disconnect after
-  /// they have received this packet.
-  ///
-  /// TODO: This should terminate the connection after this packet is sent.
-  /// Closing the channel will drop the packet before it can be sent, so we need
-  /// some other way of closing it later.
-  pub fn disconnect<C: Into<Chat>>(&self, _msg: C) {
-    // self.send(cb::packet::KickDisconnect { reason: msg.into().to_json() });
+  /// The player is removed from the world immediately. The proxy will close
+  /// the connection once it receives the kick packet, so the client will still
+  /// see the message.
+  pub fn disconnect<C: Into<Chat>>(&self, msg: C) {
+    self.send(cb::packet::Kick { reason: msg.into() });
     self.remove();
   }
 
@@ -391,7 +585,7 @@ impl Player {
   ///
   /// This is used when a player disconnects on their own, and they need to be
   /// removed from the players list in the world.
-  pub(crate) fn remove(&self) { self.world.world_manager().remove_player(self.uuid); }
+  pub(crate) fn remove(&self) { self.world().world_manager().remove_player(self.uuid); }
 
   /// Returns the status byte for entity metadata. The bits are as follows:
   ///
@@ -405,7 +599,7 @@ impl Player {
   /// - `0x80`: Is flying with elytra
   pub fn status_byte(&self) -> i8 {
     let pos = self.pos.lock();
-    ((pos.crouching as i8) << 1) | ((pos.sprinting as i8) << 4) | ((pos.swimming as i8) << 5)
+    ((pos.crouching as i8) << 1) | ((pos.sprinting as i8) << 3) | ((pos.swimming as i8) << 4)
   }
 
   /// Generates the player's metadata for the given version. This will include
@@ -416,8 +610,10 @@ impl Player {
     // Status byte (fire, invisible, etc):
     meta.set_byte(0, self.status_byte());
     // Custom names aren't present for players, so we don't set fields 2 and 3.
+    // Pose (only sent to 1.14+ clients):
+    meta.set_pose(6, self.pose());
     // Skin parts:
-    meta.set_byte(17, 0x01 | 0x02 | 0x04 | 0x08 | 0x10 | 0x20 | 0x40);
+    meta.set_byte(17, self.skin_parts.lock().0 as i8);
     meta
   }
 
@@ -434,16 +630,15 @@ impl Player {
   }
 
   // Returns the player's eyesight offset in the Y axis. This depends on the
-  // player's client version and whether the player is crouching.
-  pub fn view_offset(&self) -> f64 {
-    if self.is_crouching() {
-      if self.ver >= ProtocolVersion::V1_14 {
-        1.27
-      } else {
-        1.54
-      }
-    } else {
-      1.62
+  // player's client version and their pose.
+  pub fn view_offset(&self) -> f64 { self.pose_view_offset(self.pose()) }
+  fn pose_view_offset(&self, pose: Pose) -> f64 {
+    match pose {
+      Pose::Sneaking if self.ver >= ProtocolVersion::V1_14 => 1.27,
+      Pose::Sneaking => 1.54,
+      Pose::Swimming | Pose::FallFlying | Pose::SpinAttack => 0.4,
+      Pose::Sleeping | Pose::Dying => 0.2,
+      Pose::Standing => 1.62,
     }
   }
 
@@ -451,9 +646,8 @@ impl Player {
   // version and whether the player is crouching.
   pub fn view_pos(&self) -> FPos { self.pos() + FPos::new(0.0, self.view_offset(), 0.0) }
 
-  /// Returns `true` if the player is on the ground. This is both sent
-  /// to us by the client, and validated by collision detection each
-  /// tick.
+  /// Returns `true` if the player is on the ground. This is sent to us by the
+  /// client, so it cannot be trusted.
   pub fn on_ground(&self) -> bool {
     let pos = self.pos.lock();
     pos.on_ground
@@ -497,12 +691,7 @@ impl Player {
   /// Returns a unit vector which is the direction this player is facing.
   pub fn look_as_vec(&self) -> Vec3 {
     let (pitch, yaw) = self.look();
-    let pitch = (pitch as f64) / 180.0 * consts::PI;
-    let yaw = (yaw as f64) / 180.0 * consts::PI;
-    let m = pitch.cos();
-    // The coordinate system of minecraft means that we need to do this hell to get
-    // the axis to line up correctly.
-    Vec3::new(-yaw.sin() * m, -pitch.sin(), yaw.cos() * m)
+    FPos::from_yaw_pitch(yaw, pitch).into()
   }
 
   /// Returns true if the player is within render distance of the given chunk
@@ -511,6 +700,15 @@ impl Player {
     delta.x().unsigned_abs() <= self.view_distance && delta.z().unsigned_abs() <= self.view_distance
   }
 
+  /// Returns `true` if the given entity has been spawned for this player.
+  pub fn is_tracking(&self, eid: i32) -> bool { self.tracked.lock().contains(&eid) }
+  /// Records that the given entity has been spawned for this player. Returns
+  /// `false` if it was already spawned.
+  pub(crate) fn track(&self, eid: i32) -> bool { self.tracked.lock().insert(eid) }
+  /// Records that the given entity has been removed for this player. Returns
+  /// `false` if it wasn't spawned.
+  pub(crate) fn untrack(&self, eid: i32) -> bool { self.tracked.lock().remove(&eid) }
+
   /// Sends the abilities of this player to the client.
   pub(crate) fn send_abilities(&self) {
     let out = {
@@ -596,6 +794,70 @@ impl Player {
   /// Returns the current tab list name.
   pub fn tab_name(&self) -> MutexGuard<'_, Option<Chat>> { self.tab_name.lock() }
 
+  /// Returns this player's entry in the tab list. This includes their skin.
+  pub fn list_entry(&self) -> cb::PlayerListAdd {
+    cb::PlayerListAdd {
+      id:           self.id(),
+      name:         self.username().clone(),
+      game_mode:    GameMode::Creative,
+      ping:         50,
+      display_name: self.tab_name().clone().map(|c| c.to_json()),
+      properties:   self.skin().map(|s| vec![s.to_property()]).unwrap_or_default(),
+    }
+  }
+
+  /// Returns the player's current skin. This is `None` for offline mode
+  /// players, unless a plugin has set their skin.
+  pub fn skin(&self) -> Option<Skin> { self.skin.lock().clone() }
+  /// Changes this player's skin. If `skin` is `None`, the client will show the
+  /// default skin for this player's UUID.
+  ///
+  /// Other players will see the change immediately, as the player is respawned
+  /// for them. This player won't see their own skin change until they
+  /// rejoin.
+  pub fn set_skin(&self, skin: Option<Skin>) {
+    *self.skin.lock() = skin;
+    // Clients only read skins when a player is added to the tab list, so we
+    // remove and re-add them.
+    let remove = cb::packet::PlayerList {
+      action: cb::PlayerListAction::Remove(vec![cb::PlayerListRemove { id: self.id() }]),
+    };
+    let add = cb::packet::PlayerList { action: cb::PlayerListAction::Add(vec![self.list_entry()]) };
+    for w in self.world().world_manager().worlds().iter() {
+      for p in w.players().iter() {
+        p.send(remove.clone());
+        p.send(add.clone());
+      }
+    }
+    self.world().respawn_player(self);
+  }
+  /// Resets this player's skin to the one from their Mojang profile.
+  pub fn reset_skin(&self) { self.set_skin(self.profile_skin.lock().clone()) }
+  /// Sets the skin from the player's profile. This is used when the skin is
+  /// fetched after the player joins. If a plugin hasn't changed the player's
+  /// skin, the new skin is shown right away.
+  pub(crate) fn set_profile_skin(&self, skin: Skin) {
+    *self.profile_skin.lock() = Some(skin.clone());
+    if self.skin().is_none() {
+      self.set_skin(Some(skin));
+    }
+  }
+
+  /// Returns the skin layers that are shown for this player.
+  pub fn skin_parts(&self) -> SkinParts { *self.skin_parts.lock() }
+  /// Changes which skin layers are shown for this player. This is visible to
+  /// everyone, including this player in third person.
+  pub fn set_skin_parts(&self, parts: SkinParts) {
+    *self.skin_parts.lock() = parts;
+    let mut meta = Metadata::new();
+    meta.set_byte(17, parts.0 as i8);
+    self.send_all_in_view(cb::packet::EntityMetadata {
+      eid: self.eid,
+      ty: entity::Type::Player.id(),
+      meta,
+    });
+  }
+
   /// Sends a block update packet for the block at the given position. This
   /// ensures that the client sees what the server sees at that position.
   ///
@@ -620,25 +882,65 @@ impl Player {
   /// technically can result in deadlocks, but the way the threads are setup
   /// right now mean that no channel will block another channel, so in practice
   /// this will only produce slow downs, never deadlocks.
-  pub fn send(&self, p: impl Into<cb::Packet>) { self.conn.send(p.into()); }
+  ///
+  /// Any packets about disguised entities will be changed before being sent,
+  /// so that this player sees the disguise. Packets about players hidden from
+  /// this player will not be sent.
+  pub fn send(&self, p: impl Into<cb::Packet>) {
+    let mut p = p.into();
+    if !self.should_send(&mut p) {
+      return;
+    }
+    if let Some(p) = self.world().disguises.remap(self, p) {
+      self.conn.send(p);
+    }
+  }
+
+  /// Sends an already serialized packet to this player. This skips all the
+  /// checks in [`send`](Self::send), so [`can_share`](Self::can_share) must be
+  /// checked first.
+  pub(crate) fn send_encoded(&self, data: Arc<[u8]>, priority: Priority) {
+    self.conn.send_encoded(data, priority);
+  }
+
+  /// Returns `true` if this player would be sent `p` unchanged by
+  /// [`send`](Self::send). If this is `true`, then the same serialized packet
+  /// can be sent to this player and any others.
+  pub(crate) fn can_share(&self, p: &cb::Packet) -> bool {
+    !self.hides_anyone() && !self.world().disguises.remaps(self, p)
+  }
+
+  /// Returns which packets can be dropped if this player falls behind.
+  pub fn drop_policy(&self) -> DropPolicy { self.conn.drop_policy() }
+  /// Sets which packets can be dropped if this player falls behind. Chunk
+  /// resends and particles can be dropped by default.
+  pub fn set_drop_policy(&self, policy: DropPolicy) { self.conn.set_drop_policy(policy); }
+  /// Returns the number of packets that were dropped because this player fell
+  /// behind.
+  pub fn dropped_packets(&self) -> u64 { self.conn.dropped() }
+  /// Returns how long this player's connection has been behind for, or `None`
+  /// if they are keeping up with the packets they are sent.
+  pub fn behind_for(&self) -> Option<Duration> { self.conn.behind_for() }
+  /// Closes this player's connection. Once the connection is closed, the
+  /// player will be removed.
+  pub(crate) fn close_connection(&self) { self.conn.close(); }
 
   /// Sends the given packet to all players in view of this player, *not
   /// including* `self`. If you want to also send the packet to `self`,
   /// call [`send_all_in_view`](Self::send_all_in_view).
   pub fn send_to_in_view(&self, p: impl Into<cb::Packet>) {
-    let p = p.into();
-    for other in self.world.players().iter().in_view(self.pos().chunk()).not(self.uuid) {
-      other.send(p.clone());
+    let mut b = Broadcast::new(p);
+    for other in self.world().viewers(self.pos().chunk()) {
+      if other.id() != self.uuid {
+        b.send(&other);
+      }
     }
   }
   /// Sends the given packet to all players in view of this player, *including*
   /// `self`. If you don't wnat to send the packet to `self`, call
   /// [`send_to_in_view`](Self::send_to_in_view).
   pub fn send_all_in_view(&self, p: impl Into<cb::Packet>) {
-    let p = p.into();
-    for other in self.world.players().iter().in_view(self.pos().chunk()) {
-      other.send(p.clone());
-    }
+    broadcast(self.world().viewers(self.pos().chunk()), p);
   }
 
   /// Returns true if the player's connection is closed.
@@ -737,7 +1039,16 @@ impl Player {
   /// will ignore every packet sent after this one. Therefore, this packet must
   /// be the last one sent to the destination.
   pub fn switch_to(&self, mode: SwitchMode, ips: Vec<SocketAddr>) {
-    self.send(cb::packet::SwitchServer { mode, ips });
+    self.send(cb::packet::SwitchServer { mode, ips, host: None });
+  }
+  /// Sends a server switch packet to the proxy, with a hostname instead of a
+  /// list of ips. The proxy will resolve the hostname in the background (using
+  /// the `_minecraft._tcp` SRV record if no port is given), and then switch
+  /// servers like [`switch_to`](Self::switch_to).
+  ///
+  /// Because the lookup happens on the proxy, this will never block.
+  pub fn switch_to_host(&self, mode: SwitchMode, host: String) {
+    self.send(cb::packet::SwitchServer { mode, ips: vec![], host: Some(host) });
   }
 
   pub(super) fn handle_command(&self, command: PlayerCommand) {
@@ -758,13 +1069,8 @@ impl Player {
       _ => {}
     }
     if needs_update {
-      let mut meta = Metadata::new();
-      meta.set_byte(0, self.status_byte());
-      self.send_to_in_view(cb::packet::EntityMetadata {
-        eid: self.eid(),
-        ty: entity::Type::Player.id(),
-        meta,
-      });
+      // Sprinting changes whether the player is swimming.
+      self.update_pose(true);
     }
   }
 
@@ -774,25 +1080,83 @@ impl Player {
     p.crouching = crouching;
     drop(p);
     if needs_update {
-      let mut meta = Metadata::new();
-      meta.set_byte(0, self.status_byte());
-      meta.set_pose(6, if crouching { Pose::Sneaking } else { Pose::Standing });
-      self.send_to_in_view(cb::packet::EntityMetadata {
-        eid: self.eid(),
-        ty: entity::Type::Player.id(),
-        meta,
-      });
+      self.update_pose(true);
     }
   }
 
   /// Attacks the given player. This is private to the crate, as it should only
   /// be called when we get an attack packet.
-  pub(super) fn attack(&self, other: EntityRef) {
-    // Handles base damage and enchantments
-    let damage = self.lock_inventory().main_hand().attack_damage();
-    // TODO: Strength
+  pub(super) fn attack(self: &Arc<Player>, other: EntityRef) {
+    if let EntityRef::Player(other) = &other {
+      if let Some(team) = self.world().world_manager().team_of(self.id()) {
+        let team = team.lock();
+        if !team.friendly_fire() && team.contains(other.id()) {
+          return;
+        }
+      }
+    }
+    // Handles the held item, and effects like strength.
+    let damage = self.attribute(Attribute::AttackDamage) as f32;
     let v = self.look_as_vec() * 0.4;
-    other.damage(damage, true, Vec3::new(v.x, 0.4, v.z));
+    let attack =
+      Arc::new(Mutex::new(event::Attack { damage, knockback: Vec3::new(v.x, 0.4, v.z) }));
+    if self
+      .world()
+      .events()
+      .player_request(event::EntityAttack {
+        player: self.clone(),
+        entity: other.handle(),
+        attack: attack.clone(),
+      })
+      .is_handled()
+    {
+      return;
+    }
+    let attack = attack.lock().clone();
+    other.damage(attack.damage, true, attack.knockback);
+  }
+
+  /// Called when this player right clicks on another entity. `cursor` is the
+  /// position of the click, relative to the entity.
+  ///
+  /// Clicking on an entity with trades will open a merchant window. Clicking on
+  /// an armor stand will swap the held item with one of the armor stand's
+  /// equipment slots.
+  pub(super) fn interact_entity(self: &Arc<Player>, eid: i32, cursor: FPos) {
+    // We don't hold the entities lock while calling plugins, as they may want to
+    // add or remove entities.
+    let entity = match self.world().entities().get(eid) {
+      Some(ent) => ent.handle(),
+      None => return,
+    };
+    if self
+      .world()
+      .events()
+      .player_request(event::InteractEntity { player: self.clone(), entity, cursor })
+      .is_handled()
+    {
+      return;
+    }
+    let ent = match self.world().entities().get_ent(eid) {
+      Some(ent) => ent.clone(),
+      None => return,
+    };
+    if self.game_mode() == GameMode::Spectator {
+      return;
+    }
+    if ent.has_trades() {
+      self.show_trades(ent.trades_handle(), &Chat::empty());
+      return;
+    }
+    if ent.ty() != entity::Type::ArmorStand {
+      return;
+    }
+    let mut inv = self.lock_inventory();
+    let held = inv.main_hand().clone();
+    if let Some(slot) = ent.armor_stand_clicked_slot(&held, cursor.y()) {
+      let old = ent.set_equipment(slot, held);
+      inv.replace(inv.selected_index() as i32 + 36, old);
+    }
   }
 
   /// Returns true if the player can be damaged. This will return `false` if
@@ -817,7 +1181,7 @@ impl Player {
     }
 
     if self
-      .world
+      .world()
       .events()
       .player_request(event::PlayerDamage { player: self.clone(), amount, blockable, knockback })
       .is_handled()
@@ -825,6 +1189,10 @@ impl Player {
       return false;
     };
 
+    // This needs to lock the inventory, so we get it before locking health.
+    let (armor, toughness) =
+      (self.attribute(Attribute::Armor), self.attribute(Attribute::ArmorToughness));
+
     let mut health = self.health.lock();
     let food = self.food.lock();
 
@@ -839,11 +1207,8 @@ impl Player {
         damage = (1.0 + damage) * 0.5;
       }
       */
-      // TODO: Armor damage and armor reducing damage
-      /*
-      let armor_damage = 25.0 - other.armor_total();
-      damage = (damage * armor_damage) / 25.0;
-       */
+      // TODO: Armor damage
+      amount = item::damage_after_armor(amount, armor, toughness);
     }
     // Here, vanilla would send a metadata update for self's health. This
     // doesn't make any sense, as other players (with a hacked client) can see
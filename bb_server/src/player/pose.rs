@@ -0,0 +1,116 @@
+//! Player poses. On 1.13+, players can swim, and on 1.14+, players can crawl
+//! through 1 block gaps. The pose changes the player's bounding box, and is
+//! sent to other clients in the player's metadata.
+
+use super::Player;
+use crate::{block, entity, math::AABB};
+use bb_common::{
+  math::FPos,
+  metadata::{Metadata, Pose},
+  net::cb,
+  version::ProtocolVersion,
+};
+
+/// Used to shrink a bounding box when checking if a pose fits, so that
+/// standing on the ground (or touching a wall) doesn't count as a collision.
+const EPSILON: f64 = 1e-3;
+
+impl Player {
+  /// Returns the player's current pose. This is updated once per tick.
+  pub fn pose(&self) -> Pose { self.pos.lock().pose }
+  /// Returns `true` if the player is swimming. Only 1.13+ clients can swim.
+  pub fn is_swimming(&self) -> bool { self.pos.lock().swimming }
+
+  /// Returns the width and height of the player's bounding box in the given
+  /// pose.
+  fn pose_size(&self, pose: Pose) -> (f64, f64) {
+    match pose {
+      Pose::Standing => (0.6, 1.8),
+      Pose::Sneaking if self.ver >= ProtocolVersion::V1_14 => (0.6, 1.5),
+      Pose::Sneaking if self.ver >= ProtocolVersion::V1_9 => (0.6, 1.65),
+      Pose::Sneaking => (0.6, 1.8),
+      Pose::Swimming | Pose::FallFlying | Pose::SpinAttack => (0.6, 0.6),
+      Pose::Sleeping | Pose::Dying => (0.2, 0.2),
+    }
+  }
+
+  /// Returns the player's bounding box. The size of this box depends on the
+  /// player's pose.
+  pub fn hitbox(&self) -> AABB {
+    let (pos, pose) = {
+      let p = self.pos.lock();
+      (p.curr, p.pose)
+    };
+    let (width, height) = self.pose_size(pose);
+    AABB::new(pos, FPos::new(width, height, width))
+  }
+
+  /// Returns `true` if the player could be in the given pose at `pos` without
+  /// colliding with any blocks.
+  fn pose_fits(&self, pos: FPos, pose: Pose) -> bool {
+    let (width, height) = self.pose_size(pose);
+    let half = width / 2.0 - EPSILON;
+    let min = pos + FPos::new(-half, EPSILON, -half);
+    let max = pos + FPos::new(half, height - EPSILON, half);
+    self.world().nearby_colliders(min, max, 3.0, true).iter().all(|c| !c.intersects(min, max))
+  }
+
+  /// Works out which pose the player should be in, and if they should be
+  /// swimming.
+  fn next_pose(&self) -> (Pose, bool) {
+    let (pos, crouching, sprinting, swimming) = {
+      let p = self.pos.lock();
+      (p.curr, p.crouching, p.sprinting, p.swimming)
+    };
+    let in_water = |y: f64| {
+      self.world().get_kind((pos + FPos::new(0.0, y, 0.0)).block()).ok() == Some(block::Kind::Water)
+    };
+    // Vanilla starts swimming once the player is sprinting with their head
+    // underwater, and keeps swimming until they stop sprinting or leave the
+    // water.
+    let swimming = self.ver >= ProtocolVersion::V1_13
+      && sprinting
+      && in_water(0.0)
+      && (swimming || in_water(self.view_offset()));
+    let pose = if swimming {
+      Pose::Swimming
+    } else if crouching {
+      Pose::Sneaking
+    } else {
+      Pose::Standing
+    };
+    // Only 1.14+ clients will crouch or crawl when they don't fit.
+    if self.ver < ProtocolVersion::V1_14 || pose == Pose::Swimming || self.pose_fits(pos, pose) {
+      return (pose, swimming);
+    }
+    if pose != Pose::Sneaking && self.pose_fits(pos, Pose::Sneaking) {
+      (Pose::Sneaking, swimming)
+    } else {
+      (Pose::Swimming, swimming)
+    }
+  }
+
+  /// Updates the player's pose and swimming state. If either changed, or if
+  /// `force` is set, the player's status byte and pose are sent to everyone in
+  /// view.
+  pub(super) fn update_pose(&self, force: bool) {
+    let (pose, swimming) = self.next_pose();
+    let changed = {
+      let mut p = self.pos.lock();
+      let changed = p.pose != pose || p.swimming != swimming;
+      p.pose = pose;
+      p.swimming = swimming;
+      changed
+    };
+    if changed || force {
+      let mut meta = Metadata::new();
+      meta.set_byte(0, self.status_byte());
+      meta.set_pose(6, pose);
+      self.send_to_in_view(cb::packet::EntityMetadata {
+        eid: self.eid(),
+        ty: entity::Type::Player.id(),
+        meta,
+      });
+    }
+  }
+}
@@ -0,0 +1,114 @@
+//! Reach checks. Clients only let players interact with things close to them,
+//! but a modified client can send a dig, place, or attack packet for anything
+//! in view. These make sure that the target is actually within reach.
+
+use super::Player;
+use crate::{event, math::AABB};
+use bb_common::{
+  math::{FPos, Pos},
+  util::GameMode,
+};
+use std::{fmt, sync::Arc};
+
+/// Something a player can only do within reach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReachAction {
+  Dig,
+  Place,
+  Attack,
+}
+
+impl fmt::Display for ReachAction {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Dig => write!(f, "dig"),
+      Self::Place => write!(f, "place"),
+      Self::Attack => write!(f, "attack"),
+    }
+  }
+}
+
+impl Player {
+  /// Returns how far this player can reach, in blocks, not including the
+  /// tolerance from the config. This is the same as vanilla, and depends on
+  /// the player's game mode.
+  pub fn reach(&self, action: ReachAction) -> f64 {
+    match (self.game_mode(), action) {
+      (GameMode::Creative, ReachAction::Attack) => 6.0,
+      (GameMode::Creative, _) => 5.0,
+      (_, ReachAction::Attack) => 3.0,
+      (_, _) => 4.5,
+    }
+  }
+
+  /// Returns `true` if this player can reach the block at `pos`. If they
+  /// can't, a [`ReachViolation`](event::ReachViolation) is sent to plugins,
+  /// which may allow the action anyway.
+  pub(crate) fn can_reach_block(self: &Arc<Self>, pos: Pos, action: ReachAction) -> bool {
+    let min = FPos::from(pos);
+    self.can_reach(min, min + FPos::new(1.0, 1.0, 1.0), action)
+  }
+
+  /// Returns `true` if this player can reach the given hitbox. If they can't,
+  /// a [`ReachViolation`](event::ReachViolation) is sent to plugins, which may
+  /// allow the action anyway.
+  pub(crate) fn can_reach_entity(self: &Arc<Self>, hitbox: AABB) -> bool {
+    let min = FPos::new(hitbox.min_x(), hitbox.min_y(), hitbox.min_z());
+    let max = FPos::new(hitbox.max_x(), hitbox.max_y(), hitbox.max_z());
+    self.can_reach(min, max, ReachAction::Attack)
+  }
+
+  fn can_reach(self: &Arc<Self>, min: FPos, max: FPos, action: ReachAction) -> bool {
+    let world = self.world();
+    let config = &world.world_manager().config().reach;
+    if !config.enabled {
+      return true;
+    }
+    let eyes = self.view_pos();
+    let target = closest_point(eyes, min, max);
+    let distance = eyes.dist(target);
+    let max = self.reach(action) + config.tolerance;
+    if distance <= max {
+      return true;
+    }
+    let allowed = self
+      .world()
+      .events()
+      .player_request(event::ReachViolation {
+        player: self.clone(),
+        action: action.to_string(),
+        target,
+        distance,
+        max,
+      })
+      .is_handled();
+    if !allowed {
+      debug!("{} tried to {action} {distance:.2} blocks away (max {max:.2})", self.username);
+    }
+    allowed
+  }
+}
+
+/// Returns the point within the box from `min` to `max` that is closest to
+/// `pos`.
+fn closest_point(pos: FPos, min: FPos, max: FPos) -> FPos {
+  FPos::new(
+    pos.x().clamp(min.x(), max.x()),
+    pos.y().clamp(min.y(), max.y()),
+    pos.z().clamp(min.z(), max.z()),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn closest() {
+    let min = FPos::new(0.0, 0.0, 0.0);
+    let max = FPos::new(1.0, 1.0, 1.0);
+    assert_eq!(closest_point(FPos::new(0.5, 0.5, 0.5), min, max), FPos::new(0.5, 0.5, 0.5));
+    assert_eq!(closest_point(FPos::new(3.0, 0.5, -2.0), min, max), FPos::new(1.0, 0.5, 0.0));
+    assert_eq!(closest_point(FPos::new(-1.0, 5.0, 0.2), min, max), FPos::new(0.0, 1.0, 0.2));
+  }
+}
@@ -0,0 +1,174 @@
+//! Player skins. A skin is stored in the `textures` profile property, which is
+//! a base64 encoded JSON object, signed by Mojang. The client downloads the
+//! skin from the url in that object.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bb_common::util::ProfileProperty;
+use serde::Deserialize;
+
+/// A player's skin and cape. This is the raw `textures` property, which is
+/// sent to clients as-is.
+///
+/// Clients will only show a skin if the signature is valid, so the value and
+/// signature need to come from Mojang. Services like mineskin.org can be used
+/// to get signed textures for any skin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skin {
+  value:     String,
+  signature: Option<String>,
+}
+
+/// The arm width of a skin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SkinModel {
+  /// The 4 pixel wide arms of Steve.
+  #[default]
+  Classic,
+  /// The 3 pixel wide arms of Alex.
+  Slim,
+}
+
+/// The decoded contents of a [`Skin`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Textures {
+  /// The url of the skin texture. If this is `None`, the client will use the
+  /// default skin for the player's UUID.
+  pub skin:  Option<String>,
+  /// The url of the cape texture.
+  pub cape:  Option<String>,
+  pub model: SkinModel,
+}
+
+#[derive(Deserialize)]
+struct JsonTextures {
+  textures: JsonTextureMap,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct JsonTextureMap {
+  skin: Option<JsonTexture>,
+  cape: Option<JsonTexture>,
+}
+#[derive(Deserialize)]
+struct JsonTexture {
+  url:      String,
+  metadata: Option<JsonTextureMetadata>,
+}
+#[derive(Deserialize)]
+struct JsonTextureMetadata {
+  model: String,
+}
+
+impl Skin {
+  /// Creates a skin from the value and signature of a `textures` property.
+  pub fn new(value: String, signature: Option<String>) -> Self { Skin { value, signature } }
+
+  /// Finds the `textures` property in the given profile properties.
+  pub fn from_properties(properties: &[ProfileProperty]) -> Option<Self> {
+    properties
+      .iter()
+      .find(|p| p.name == "textures")
+      .map(|p| Skin::new(p.value.clone(), p.signature.clone()))
+  }
+  /// Converts this skin into a profile property, which can be sent to
+  /// clients.
+  pub fn to_property(&self) -> ProfileProperty {
+    ProfileProperty {
+      name:      "textures".into(),
+      value:     self.value.clone(),
+      signature: self.signature.clone(),
+    }
+  }
+
+  /// Returns the base64 encoded value of this skin.
+  pub fn value(&self) -> &str { &self.value }
+  /// Returns the base64 encoded signature of this skin.
+  pub fn signature(&self) -> Option<&str> { self.signature.as_deref() }
+
+  /// Decodes the textures in this skin. Returns `None` if the value is not
+  /// valid base64 encoded JSON.
+  pub fn textures(&self) -> Option<Textures> {
+    let json = STANDARD.decode(&self.value).ok()?;
+    let textures: JsonTextures = serde_json::from_slice(&json).ok()?;
+    let skin = textures.textures.skin;
+    Some(Textures {
+      model: match skin.as_ref().and_then(|s| s.metadata.as_ref()) {
+        Some(meta) if meta.model == "slim" => SkinModel::Slim,
+        _ => SkinModel::Classic,
+      },
+      skin:  skin.map(|s| s.url),
+      cape:  textures.textures.cape.map(|c| c.url),
+    })
+  }
+}
+
+/// The outer layers of a skin that are shown. These can be toggled by the
+/// client in the skin customization menu, or changed by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkinParts(pub u8);
+
+impl Default for SkinParts {
+  fn default() -> Self { SkinParts::ALL }
+}
+
+impl SkinParts {
+  pub const CAPE: SkinParts = SkinParts(0x01);
+  pub const JACKET: SkinParts = SkinParts(0x02);
+  pub const LEFT_SLEEVE: SkinParts = SkinParts(0x04);
+  pub const RIGHT_SLEEVE: SkinParts = SkinParts(0x08);
+  pub const LEFT_PANTS: SkinParts = SkinParts(0x10);
+  pub const RIGHT_PANTS: SkinParts = SkinParts(0x20);
+  pub const HAT: SkinParts = SkinParts(0x40);
+  pub const ALL: SkinParts = SkinParts(0x7f);
+
+  /// Parses a single part name, like `left_sleeve`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "cape" => Self::CAPE,
+      "jacket" => Self::JACKET,
+      "left_sleeve" => Self::LEFT_SLEEVE,
+      "right_sleeve" => Self::RIGHT_SLEEVE,
+      "left_pants" => Self::LEFT_PANTS,
+      "right_pants" => Self::RIGHT_PANTS,
+      "hat" => Self::HAT,
+      _ => return None,
+    })
+  }
+
+  /// Returns `true` if all the parts in `other` are shown.
+  pub fn contains(&self, other: SkinParts) -> bool { self.0 & other.0 == other.0 }
+  /// Shows or hides all the parts in `other`.
+  pub fn set(&mut self, other: SkinParts, shown: bool) {
+    if shown {
+      self.0 |= other.0;
+    } else {
+      self.0 &= !other.0;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn textures() {
+    let json = r#"{"textures":{"SKIN":{"url":"http://textures.minecraft.net/texture/abc","metadata":{"model":"slim"}},"CAPE":{"url":"http://textures.minecraft.net/texture/def"}}}"#;
+    let skin = Skin::new(STANDARD.encode(json), None);
+    assert_eq!(
+      skin.textures(),
+      Some(Textures {
+        skin:  Some("http://textures.minecraft.net/texture/abc".into()),
+        cape:  Some("http://textures.minecraft.net/texture/def".into()),
+        model: SkinModel::Slim,
+      })
+    );
+    assert_eq!(Skin::new("not base64!".into(), None).textures(), None);
+
+    let mut parts = SkinParts::ALL;
+    parts.set(SkinParts::HAT, false);
+    assert!(!parts.contains(SkinParts::HAT));
+    assert!(parts.contains(SkinParts::JACKET));
+    assert_eq!(parts.0, 0x3f);
+  }
+}
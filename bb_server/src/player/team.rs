@@ -30,8 +30,6 @@ pub struct Team {
 
 impl Team {
   pub fn new(wm: Arc<WorldManager>, name: String) -> Self {
-    let mut prefix = Chat::empty();
-    prefix.add("[BIG] ").color(Color::Red);
     let t = Team {
       name: name.clone(),
       members: HashSet::new(),
@@ -41,8 +39,8 @@ impl Team {
       name_tag_rule: TeamRule::Always,
       collision_rule: TeamRule::Always,
       color: Color::White,
-      prefix,
-      postfix: Chat::new(" whaa"),
+      prefix: Chat::empty(),
+      postfix: Chat::empty(),
       wm,
     };
     let out = cb::packet::Teams {
@@ -56,27 +54,94 @@ impl Team {
   /// Returns the name of the team.
   pub fn name(&self) -> &String { &self.name }
 
+  /// Sets the name of this team, as shown to clients. This doesn't change
+  /// the name used to lookup this team.
+  pub fn set_display_name(&mut self, name: Chat) {
+    self.display_name = name;
+    self.update_info();
+  }
+  /// Sets the text shown before the name of every player on this team.
   pub fn set_prefix(&mut self, prefix: Chat) {
     self.prefix = prefix;
     self.update_info();
   }
+  /// Sets the text shown after the name of every player on this team.
   pub fn set_postfix(&mut self, postfix: Chat) {
     self.postfix = postfix;
     self.update_info();
   }
+  /// Sets the color of the names of every player on this team.
   pub fn set_color(&mut self, color: Color) {
     self.color = color;
     self.update_info();
   }
+  /// If `friendly_fire` is false, players on this team will not be able to
+  /// attack each other.
+  pub fn set_friendly_fire(&mut self, friendly_fire: bool) {
+    self.friendly_fire = friendly_fire;
+    self.update_info();
+  }
+  /// If `see_invis` is true, players on this team will see invisible
+  /// teammates as transparent, instead of not seeing them at all.
+  pub fn set_see_invisible(&mut self, see_invis: bool) {
+    self.see_invis = see_invis;
+    self.update_info();
+  }
+  /// Sets who can see the name tags of players on this team. For example,
+  /// [`TeamRule::ForOwnTeam`] will hide name tags from teammates.
+  pub fn set_name_tag_rule(&mut self, rule: TeamRule) {
+    self.name_tag_rule = rule;
+    self.update_info();
+  }
+  /// Sets who players on this team will collide with. For example,
+  /// [`TeamRule::ForOwnTeam`] will only push teammates. This is not shown on
+  /// 1.8 clients.
+  pub fn set_collision_rule(&mut self, rule: TeamRule) {
+    self.collision_rule = rule;
+    self.update_info();
+  }
+
+  pub fn display_name(&self) -> &Chat { &self.display_name }
+  pub fn prefix(&self) -> &Chat { &self.prefix }
+  pub fn postfix(&self) -> &Chat { &self.postfix }
+  pub fn color(&self) -> &Color { &self.color }
+  pub fn friendly_fire(&self) -> bool { self.friendly_fire }
+  pub fn see_invisible(&self) -> bool { self.see_invis }
+  pub fn name_tag_rule(&self) -> TeamRule { self.name_tag_rule }
+  pub fn collision_rule(&self) -> TeamRule { self.collision_rule }
 
+  /// Adds the player to this team. This does nothing if the player is already
+  /// on this team.
+  ///
+  /// Players should only be on one team at a time, so they should be removed
+  /// from their old team first. See [`WorldManager::team_of`].
   pub fn add(&mut self, player: &Player) {
-    self.members.insert(player.id());
+    if !self.members.insert(player.id()) {
+      return;
+    }
     let out = cb::packet::Teams {
       team:   self.name.clone(),
       action: TeamAction::AddEntities { entities: vec![player.username().clone()] },
     };
     self.wm.send_to_all(out);
   }
+  /// Removes the player from this team. Returns `false` if the player wasn't
+  /// on this team.
+  pub fn remove(&mut self, player: &Player) -> bool {
+    if !self.members.remove(&player.id()) {
+      return false;
+    }
+    let out = cb::packet::Teams {
+      team:   self.name.clone(),
+      action: TeamAction::RemoveEntities { entities: vec![player.username().clone()] },
+    };
+    self.wm.send_to_all(out);
+    true
+  }
+  /// Returns `true` if the given player is on this team.
+  pub fn contains(&self, id: UUID) -> bool { self.members.contains(&id) }
+  /// Returns the UUIDs of every player on this team.
+  pub fn members(&self) -> &HashSet<UUID> { &self.members }
 
   fn update_info(&self) {
     let out = cb::packet::Teams {
@@ -98,9 +163,18 @@ impl Team {
     }
   }
 
-  pub(crate) fn player_disconnect(&mut self, id: UUID) { self.members.remove(&id); }
+  /// Removes the player from this team when they leave. This must be called
+  /// before the player is removed from the [`WorldManager`], so that we can
+  /// still look up their username.
+  pub(crate) fn player_disconnect(&mut self, id: UUID) {
+    if let Some(player) = self.wm.get_player(id) {
+      self.remove(&player);
+    }
+  }
 
-  /// Creates the team for the player that has just joined.
+  /// Creates the team for the player that has just joined. This includes all
+  /// the current members, and all the options that have been changed since
+  /// the team was created.
   pub(crate) fn send_join(&self, player: &Player) {
     player.send(cb::packet::Teams {
       team:   self.name.clone(),
@@ -109,9 +183,17 @@ impl Team {
         entities: self
           .members
           .iter()
-          .map(|id| self.wm.get_player(*id).unwrap().username().clone())
+          .filter_map(|id| self.wm.get_player(*id))
+          .map(|p| p.username().clone())
           .collect(),
       },
     });
   }
+  /// Removes this team from all clients. This is called when the team is
+  /// removed from the [`WorldManager`].
+  pub(crate) fn send_remove(&self) {
+    self
+      .wm
+      .send_to_all(cb::packet::Teams { team: self.name.clone(), action: TeamAction::Remove });
+  }
 }
@@ -1,7 +1,9 @@
 use super::{DigProgress, Player, PlayerPosition};
-use crate::{block, event, event::EventFlow, math::Vec3};
+use crate::{
+  block, enchantment, entity::Effect, event, event::EventFlow, math::Vec3, world::Action,
+};
 use bb_common::{
-  math::{ChunkPos, Pos},
+  math::{ChunkPos, FPos, Pos, PosError},
   net::cb,
   version::ProtocolVersion,
 };
@@ -21,7 +23,7 @@ impl Player {
   /// Updates the player's position/velocity. This will apply gravity, and do
   /// collision checks. Should never be called at a different rate than the
   /// global tick rate.
-  pub(crate) fn tick(self: &Arc<Self>) {
+  pub fn tick(self: &Arc<Self>) {
     let mut health = self.health.lock();
     let old_chunk;
     let new_chunk;
@@ -130,7 +132,10 @@ impl Player {
     // Handle edge case for players sending dig finish too early.
     self.check_dig_wants_finish();
     if pos_changed || look_changed {
-      for other in self.world.players().iter().in_view(pos.curr.chunk()).not(self.uuid) {
+      for other in self.world().viewers(pos.curr.chunk()) {
+        if other.id() == self.uuid || !other.is_tracking(self.eid) {
+          continue;
+        }
         // Make player move for other
         let yaw;
         let pitch;
@@ -217,6 +222,8 @@ impl Player {
       }
     }
     if old_chunk != new_chunk {
+      self.world().move_entity(self.eid, old_chunk, new_chunk);
+      self.world().move_view(self, old_chunk, new_chunk);
       if self.ver() >= ProtocolVersion::V1_14 {
         self.send(cb::packet::UpdateViewPos { pos: new_chunk });
       }
@@ -285,6 +292,15 @@ impl Player {
     if health.hit_delay > 0 {
       health.hit_delay -= 1;
     }
+    drop(health);
+    self.tick_cooldowns();
+    self.tick_debug();
+    self.update_pose(false);
+
+    let mut attrs = self.attrs.lock();
+    if attrs.tick() {
+      self.send_attributes(&attrs);
+    }
   }
 
   /// Loads the chunks between min and max, inclusive.
@@ -300,10 +316,10 @@ impl Player {
     for x in min.x()..=max.x() {
       for z in min.z()..=max.z() {
         let pos = ChunkPos::new(x, z);
-        if self.world.has_loaded_chunk(pos) {
-          self.send_chunk(pos, || self.world.serialize_chunk(pos).into());
+        if self.world().has_loaded_chunk(pos) {
+          self.send_chunk(pos, || self.world().serialize_chunk(pos).into());
         } else {
-          self.world.queue_chunk(pos, self);
+          self.world().queue_chunk(pos, self);
         }
       }
     }
@@ -316,18 +332,35 @@ impl Player {
       if !lock.contains(&pos) {
         lock.insert(pos);
         drop(lock);
-        self.world.inc_view(pos);
+        self.world().inc_view(pos);
         self.send(f());
       }
     }
   }
+  /// Called after the proxy resumes this player's connection. Anything sent
+  /// while the connection was down may have been lost, so this sends every
+  /// chunk the client should have again, and moves the client back to where
+  /// we think they are.
+  pub(crate) fn resync(&self) {
+    let loaded: Vec<ChunkPos> = self.loaded_chunks.lock().iter().copied().collect();
+    for pos in loaded {
+      if self.world().has_loaded_chunk(pos) {
+        self.send(self.world().serialize_chunk(pos));
+      }
+    }
+    let (pos, yaw, pitch) = {
+      let p = self.pos.lock();
+      (p.curr, p.yaw, p.pitch)
+    };
+    self.teleport(pos, yaw, pitch);
+  }
   /// Sends the unload packet for this chunk to the client, and records that the
   /// client no longer has that chunk in memory.
   fn send_unload_chunk(&self, pos: ChunkPos) {
     let mut lock = self.loaded_chunks.lock();
     if lock.remove(&pos) {
       drop(lock);
-      self.world.dec_view(pos);
+      self.world().dec_view(pos);
       self.send(cb::packet::UnloadChunk { pos });
     }
   }
@@ -349,7 +382,7 @@ impl Player {
     let min = chunk - ChunkPos::new(v, v);
     for x in min.x()..=max.x() {
       for z in min.z()..=max.z() {
-        self.world.dec_view(ChunkPos::new(x, z));
+        self.world().dec_view(ChunkPos::new(x, z));
       }
     }
   }
@@ -360,7 +393,7 @@ impl Player {
     for x in min.x()..=max.x() {
       for z in min.z()..=max.z() {
         let pos = ChunkPos::new(x, z);
-        self.world.unqueue_chunk(pos, self);
+        self.world().unqueue_chunk(pos, self);
         self.send_unload_chunk(pos);
       }
     }
@@ -368,16 +401,16 @@ impl Player {
 
   pub(crate) fn start_digging(self: &Arc<Self>, pos: Pos) {
     // Silently ignore dig packets outside the world.
-    if let Ok(kind) = self.world.get_kind(pos) {
+    if let Ok(kind) = self.world().get_kind(pos) {
       let mut ppos = self.pos.lock();
-      let speed = self.mining_speed(ppos.curr.block(), kind);
+      let speed = self.mining_speed(&ppos, kind);
       if speed >= 1.0 {
         // Insta-break the block
         drop(ppos); // drop the player position lock, so this can clear ppos.dig_progress
         if self.block_break_event(pos).is_handled() {
           self.sync_block_at(pos);
         } else {
-          let _ = self.world.break_block(pos);
+          let _ = self.break_block(pos);
         }
       } else {
         let mut progress = DigProgress::new(pos, kind);
@@ -415,7 +448,7 @@ impl Player {
     }
     if finished {
       if self.block_break_event(pos).is_continue() {
-        if !self.world().break_block(pos).unwrap() {
+        if !self.break_block(pos).unwrap() {
           self.sync_block_at(pos);
         }
       } else {
@@ -426,6 +459,9 @@ impl Player {
     }
   }
   pub(crate) fn block_break_event(self: &Arc<Player>, pos: Pos) -> EventFlow {
+    if !self.world().allows(Some(self), pos, Action::Break) {
+      return EventFlow::Handled;
+    }
     self.world().events().player_request(event::BlockBreak {
       player: self.clone(),
       pos,
@@ -433,44 +469,55 @@ impl Player {
     })
   }
 
-  fn mining_speed(&self, curr_pos: Pos, kind: block::Kind) -> f64 {
-    // Handles block/item type, and efficiency levels
-    let mut speed = self
-      .lock_inventory()
-      .main_hand()
-      .mining_speed(self.world.world_manager().block_converter().get(kind));
+  /// Breaks the block at the given position using the item in the player's
+  /// main hand. This does not fire any events.
+  fn break_block(self: &Arc<Player>, pos: Pos) -> Result<bool, PosError> {
+    let tool = self.lock_inventory().main_hand().clone();
+    self.world().break_block_with(pos, Some(&tool))
+  }
 
-    // TODO: Multiply by haste:
-    // if self.has_haste() {
-    //   speed *= 1.0 + (haste_level + 1) * 0.2;
-    // }
+  /// Returns the fraction of a block of the given kind that this player
+  /// breaks each tick. This is the same formula the client uses, so that the
+  /// client and server agree on when a block is broken.
+  fn mining_speed(&self, pos: &PlayerPosition, kind: block::Kind) -> f64 {
+    let (mut speed, aqua_affinity) = {
+      let inv = self.lock_inventory();
+      // Handles block/item type, and efficiency levels
+      let speed =
+        inv.main_hand().mining_speed(self.world().world_manager().block_converter().get(kind));
+      (speed, inv.helmet().enchantment(enchantment::Type::AquaAffinity) > 0)
+    };
 
-    // TODO: Multiply by mining fatigue:
-    // match mining_fatigue {
-    //   0 => speed * 0.3,
-    //   1 => speed * 0.09,
-    //   2 => speed * 0.0027,
-    //   _ => speed * 0.00081,
-    // }
+    let (haste, fatigue) = {
+      let attrs = self.attrs.lock();
+      (attrs.effect_level(Effect::Haste), attrs.effect_level(Effect::MiningFatigue))
+    };
+    if haste > 0 {
+      speed *= 1.0 + haste as f64 * 0.2;
+    }
+    if fatigue > 0 {
+      speed *= 0.3_f64.powi(fatigue.min(4) as i32);
+    }
 
-    // If we are standing in water, digging is 5 times slower.
-    if self.world.get_kind(curr_pos) == Ok(block::Kind::Water) {
+    // If our head is underwater, digging is 5 times slower.
+    let eyes = pos.curr + FPos::new(0.0, self.pose_view_offset(pos.pose), 0.0);
+    if !aqua_affinity && self.world().get_kind(eyes.block()) == Ok(block::Kind::Water) {
+      speed *= 0.2;
+    }
+    // This comes from the client, so a cheater could say they are always on
+    // ground. However, the client does the same check to predict when the block
+    // breaks, so we need to match it.
+    if !pos.on_ground {
       speed *= 0.2;
     }
-
-    // TODO: Multiply by on ground:
-    // if pos.on_ground {
-    //   speed *= 0.2;
-    // }
-    // We can't really trust the client for this, as they can say they're on
-    // ground at any point. This means we need to do a bit of physics on the
-    // player to figure out if they are on ground.
     speed
   }
 
   fn update_dig_progress(&self, pos: &mut PlayerPosition) {
+    let Some(kind) = pos.dig_progress.as_ref().map(|p| p.kind) else { return };
+    let speed = self.mining_speed(pos, kind);
     if let Some(p) = &mut pos.dig_progress {
-      p.progress += self.mining_speed(pos.curr.block(), p.kind);
+      p.progress += speed;
     }
   }
 
@@ -488,17 +535,8 @@ impl Player {
       }
     }
     if let Some(pos) = finish {
-      if self
-        .world()
-        .events()
-        .player_request(event::BlockBreak {
-          player: self.clone(),
-          pos,
-          block: self.world().get_block(pos).unwrap().ty().to_store(),
-        })
-        .is_continue()
-      {
-        if !self.world().break_block(pos).unwrap() {
+      if self.block_break_event(pos).is_continue() {
+        if !self.break_block(pos).unwrap() {
           self.sync_block_at(pos);
         }
       } else {
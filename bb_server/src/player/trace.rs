@@ -0,0 +1,61 @@
+use super::Player;
+use bb_common::{
+  net::cb,
+  util::{Trace, TraceLog},
+};
+use parking_lot::MutexGuard;
+use std::time::Duration;
+
+/// How long finished traces are kept for each player.
+pub(super) const TRACE_WINDOW: Duration = Duration::from_secs(30);
+
+impl Player {
+  /// Starts tracing the next packet received from this player. This is called
+  /// when the proxy sends a `Trace` packet.
+  pub(crate) fn start_trace(&self, id: u32) { *self.trace.lock() = Some(Trace::new(id, "")); }
+  /// Returns `true` if the packet currently being handled is traced.
+  pub(crate) fn is_tracing(&self) -> bool { self.trace.lock().is_some() }
+  /// Sets the name of the packet being traced.
+  pub(crate) fn set_trace_packet(&self, name: String) {
+    if let Some(trace) = self.trace.lock().as_mut() {
+      trace.packet = name;
+    }
+  }
+
+  /// Records a span with the given name in the packet that is currently being
+  /// traced. If no packet is being traced, this does nothing, so it is cheap
+  /// to call while handling any packet.
+  pub fn trace_span(&self, name: &str) {
+    if let Some(trace) = self.trace.lock().as_mut() {
+      trace.span(name);
+    }
+  }
+
+  /// Finishes the current trace. This sends the timings back to the proxy, and
+  /// stores the trace so that it can be viewed with
+  /// [`traces`](Self::traces).
+  pub(crate) fn finish_trace(&self) {
+    let mut trace = match self.trace.lock().take() {
+      Some(t) => t,
+      None => return,
+    };
+    trace.span("handle");
+    self.send(cb::packet::TraceEnd {
+      id:    trace.id,
+      spans: trace
+        .spans
+        .iter()
+        .map(|(name, elapsed)| cb::TraceSpan {
+          name:   name.clone(),
+          micros: elapsed.as_micros() as u64,
+        })
+        .collect(),
+    });
+    self.traces.lock().push(trace);
+  }
+
+  /// Returns all the packets from this player that have been traced in the
+  /// last 30 seconds. This will be empty unless tracing is enabled on the
+  /// proxy.
+  pub fn traces(&self) -> MutexGuard<'_, TraceLog> { self.traces.lock() }
+}
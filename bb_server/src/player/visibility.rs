@@ -0,0 +1,79 @@
+//! Per-player visibility. A player can hide other players, which removes them
+//! from the tab list, and stops all of their entity packets from being sent.
+
+use super::Player;
+use bb_common::net::cb::{self, packet, Packet};
+
+impl Player {
+  /// Hides `other` from this player. `other` will be removed from this
+  /// player's tab list, and will no longer be shown in the world. This is
+  /// not sent to `other`, so `other` can still see this player.
+  ///
+  /// Players in other worlds are never shown, so this does nothing if `other`
+  /// is in a different world, or if `other` is this player.
+  pub fn hide(&self, other: &Player) {
+    if other.id() == self.id() || !std::ptr::eq(self.world().as_ref(), other.world().as_ref()) {
+      return;
+    }
+    if self.hidden.lock().insert(other.eid(), other.id()).is_some() {
+      return;
+    }
+    self.send(packet::RemoveEntities { eids: vec![other.eid()] });
+    self.send(packet::PlayerList {
+      action: cb::PlayerListAction::Remove(vec![cb::PlayerListRemove { id: other.id() }]),
+    });
+  }
+  /// Shows `other` to this player, after they have been hidden with
+  /// [`hide`](Self::hide). This does nothing if `other` isn't hidden.
+  pub fn show(&self, other: &Player) {
+    if self.hidden.lock().remove(&other.eid()).is_none() {
+      return;
+    }
+    self.send(packet::PlayerList { action: cb::PlayerListAction::Add(vec![other.list_entry()]) });
+    // Hidden players are still tracked, the spawn packet was just never sent.
+    if self.is_tracking(other.eid()) {
+      self.world().send_player_spawn(self, other);
+    }
+  }
+  /// Returns `true` if this player can see `other`. This only checks if
+  /// `other` has been hidden, so it will still return `true` for players that
+  /// are far away.
+  pub fn can_see(&self, other: &Player) -> bool { !self.hidden.lock().contains_key(&other.eid()) }
+
+  /// Returns `true` if this player has hidden any other players.
+  pub(super) fn hides_anyone(&self) -> bool { !self.hidden.lock().is_empty() }
+
+  /// Removes `other` from the players hidden by this player. This is called
+  /// when `other` leaves, so that the hidden list doesn't grow forever.
+  pub(crate) fn forget_hidden(&self, other: &Player) { self.hidden.lock().remove(&other.eid()); }
+
+  /// Returns `false` if the given packet is about a player that is hidden from
+  /// this player, in which case the packet shouldn't be sent.
+  pub(super) fn should_send(&self, p: &mut Packet) -> bool {
+    let hidden = self.hidden.lock();
+    if hidden.is_empty() {
+      return true;
+    }
+    let eid = match p {
+      Packet::Animation(packet::Animation { eid, .. })
+      | Packet::CollectItem(packet::CollectItem { player_eid: eid, .. })
+      | Packet::EntityEquipment(packet::EntityEquipment { eid, .. })
+      | Packet::EntityHeadLook(packet::EntityHeadLook { eid, .. })
+      | Packet::EntityLook(packet::EntityLook { eid, .. })
+      | Packet::EntityMove(packet::EntityMove { eid, .. })
+      | Packet::EntityMoveLook(packet::EntityMoveLook { eid, .. })
+      | Packet::EntityPos(packet::EntityPos { eid, .. })
+      | Packet::EntityStatus(packet::EntityStatus { eid, .. })
+      | Packet::EntityVelocity(packet::EntityVelocity { eid, .. })
+      | Packet::EntityMetadata(packet::EntityMetadata { eid, .. })
+      | Packet::EntityProperties(packet::EntityProperties { eid, .. })
+      | Packet::SpawnPlayer(packet::SpawnPlayer { eid, .. }) => *eid,
+      Packet::PlayerList(packet::PlayerList { action: cb::PlayerListAction::Add(players) }) => {
+        players.retain(|p| !hidden.values().any(|id| *id == p.id));
+        return !players.is_empty();
+      }
+      _ => return true,
+    };
+    !hidden.contains_key(&eid)
+  }
+}
@@ -1,11 +1,16 @@
 use crate::{
+  entity::Trade,
   item::{SharedInventory, Stack},
   player::ConnSender,
   world::WorldManager,
 };
-use bb_common::util::UUID;
+use bb_common::{net::cb, util::UUID};
 use bb_server_macros::Window;
-use std::sync::Arc;
+use parking_lot::Mutex;
+use std::sync::{
+  atomic::{AtomicUsize, Ordering},
+  Arc,
+};
 
 trait WindowData {
   fn sync(&self, index: u32);
@@ -48,6 +53,19 @@ pub struct CraftingWindow {
   pub wm:     Arc<WorldManager>,
 }
 
+#[derive(Window, Debug, Clone)]
+pub struct MerchantWindow {
+  pub inputs:   SharedInventory<2>,
+  #[output]
+  pub output:   SharedInventory<1>,
+  /// The trades of the entity we are trading with.
+  #[not_inv]
+  pub trades:   Arc<Mutex<Vec<Trade>>>,
+  /// The trade the player has selected.
+  #[not_inv]
+  pub selected: Arc<AtomicUsize>,
+}
+
 impl<const N: usize> WindowHandler for GenericWindow<N> {}
 impl WindowHandler for SmeltingWindow {}
 
@@ -70,6 +88,65 @@ impl WindowHandler for CraftingWindow {
   }
 }
 
+impl MerchantWindow {
+  pub fn new(trades: Arc<Mutex<Vec<Trade>>>) -> Self {
+    MerchantWindow {
+      inputs: SharedInventory::new(),
+      output: SharedInventory::new(),
+      trades,
+      selected: Arc::new(AtomicUsize::new(0)),
+    }
+  }
+
+  /// Returns the offers to send in a `TradeList` packet.
+  pub fn offers(&self) -> Vec<cb::TradeOffer> {
+    self.trades.lock().iter().map(|t| t.to_offer()).collect()
+  }
+
+  /// Selects the given trade. This is sent by the client whenever they click
+  /// on a trade in the list. Invalid indices are ignored.
+  pub fn select(&self, index: usize) {
+    if index < self.trades.lock().len() {
+      self.selected.store(index, Ordering::SeqCst);
+      self.on_update(None);
+    }
+  }
+
+  /// Removes and returns everything in the input slots. This is used to give
+  /// the items back to the player when the window is closed.
+  pub fn take_inputs(&self) -> [Stack; 2] {
+    let mut lock = self.inputs.lock();
+    let items = [lock.get(0).unwrap().clone(), lock.get(1).unwrap().clone()];
+    lock.set(0, Stack::empty());
+    lock.set(1, Stack::empty());
+    items
+  }
+}
+
+impl WindowHandler for MerchantWindow {
+  fn on_update(&self, clicked: Option<u32>) {
+    let mut trades = self.trades.lock();
+    let trade = match trades.get_mut(self.selected.load(Ordering::SeqCst)) {
+      Some(t) => t,
+      None => return,
+    };
+    let mut inputs = self.inputs.lock();
+    let mut a = inputs.get(0).unwrap().clone();
+    let mut b = inputs.get(1).unwrap().clone();
+    if clicked == Some(2) && self.output.lock().get(0).unwrap().is_empty() {
+      // The output was taken, so the trade happened.
+      trade.apply(&mut a, &mut b);
+      inputs.set(0, a.clone());
+      inputs.set(1, b.clone());
+    }
+    if trade.matches(&a, &b) && !trade.is_disabled() {
+      self.output.lock().set(0, trade.output.clone());
+    } else {
+      self.output.lock().set(0, Stack::empty());
+    }
+  }
+}
+
 #[derive(bb_server_macros::WindowEnum, Debug, Clone)]
 pub enum Window {
   #[name("minecraft:generic_9x1")]
@@ -88,6 +165,8 @@ pub enum Window {
   Generic3x3(GenericWindow<9>),
   #[name("minecraft:crafting")]
   Crafting(CraftingWindow),
+  #[name("minecraft:merchant")]
+  Merchant(MerchantWindow),
   /*
   #[name("minecraft:anvil")]
   Anvil(Anvil),
@@ -129,8 +208,6 @@ pub enum Window {
     #[output]
     output:  SharedInventory<1>,
   },
-  #[name("minecraft:merchant")]
-  Merchant { inv: SharedInventory<1> },
   #[name("minecraft:shulker_box")]
   ShulkerBox { inv: SharedInventory<27> },
   #[name("minecraft:smithing")]
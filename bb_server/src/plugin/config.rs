@@ -12,6 +12,11 @@ pub struct Config {
   #[default(true)]
   pub enabled: bool,
 
+  /// The names of the worlds this plugin is allowed to change blocks in. If
+  /// this is empty, the plugin can change any world. This is currently only
+  /// enforced for wasm plugins.
+  pub worlds: Vec<String>,
+
   /// Socket-specific configs
   pub socket: SocketConfig,
 
@@ -39,6 +44,9 @@ pub struct WasmConfig {
   pub compile: String,
   /// The path to the compiled wasm.
   pub output:  String,
+  /// The wasm runtime to use, either `wasmer` or `wasmtime`. If empty,
+  /// wasmer is used if the server was built with it.
+  pub runtime: String,
 }
 #[derive(Clone, Debug, Config, Default, PartialEq)]
 pub struct PandaConfig {}
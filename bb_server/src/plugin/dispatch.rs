@@ -0,0 +1,234 @@
+//! Runs plugin event handlers on a shared pool of threads.
+//!
+//! Every plugin has its own queue of [`ServerMessage`]s. A queue is only ever
+//! run by one thread at a time, so each plugin sees messages in the order they
+//! were sent, which means every player's events stay in order. Separate
+//! plugins are run in parallel.
+//!
+//! Messages are split into two classes (see [`EventClass`]). Sync messages
+//! are requests, which the server waits on, and which plugins can cancel.
+//! Async messages are notify-only. Sending a message never blocks the caller,
+//! so a slow plugin can't stall the tick loop. If a plugin falls too far
+//! behind, async messages are dropped, but sync messages are always queued.
+
+use crate::event::ServerMessage;
+use parking_lot::Mutex;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::{
+  collections::VecDeque,
+  error::Error,
+  fmt,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+};
+
+/// The number of messages a queue can hold before async messages are dropped.
+const MAX_BACKLOG: usize = 1024;
+/// The number of messages a single plugin will handle before giving other
+/// plugins a chance to run.
+const BATCH_SIZE: usize = 32;
+
+/// How a message is dispatched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventClass {
+  /// The server waits for a reply, and plugins can cancel it. These are
+  /// [`PlayerRequest`](crate::event::PlayerRequest)s and
+  /// [`GlobalRequest`](crate::event::GlobalRequest)s.
+  Sync,
+  /// The server doesn't wait for plugins to handle this. These are
+  /// [`PlayerEvent`](crate::event::PlayerEvent)s and
+  /// [`GlobalEvent`](crate::event::GlobalEvent)s.
+  Async,
+}
+
+impl ServerMessage {
+  pub fn class(&self) -> EventClass {
+    match self {
+      Self::PlayerRequest { .. } | Self::GlobalRequest { .. } | Self::Reply { .. } => {
+        EventClass::Sync
+      }
+      Self::PlayerEvent { .. } | Self::GlobalEvent { .. } => EventClass::Async,
+    }
+  }
+}
+
+/// Returned when sending a message to a plugin that has failed.
+#[derive(Debug)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "plugin has stopped") }
+}
+
+impl Error for Closed {}
+
+/// The thread pool that all plugins run on.
+pub struct Dispatcher {
+  pool: Arc<ThreadPool>,
+}
+
+/// Handles a single message. Returns `false` if the plugin has failed, and
+/// should not be sent any more messages.
+type Handler = Box<dyn Fn(ServerMessage) -> bool + Send + Sync>;
+
+/// The messages for a single plugin.
+pub struct Queue {
+  name:     String,
+  pool:     Arc<ThreadPool>,
+  messages: Mutex<VecDeque<ServerMessage>>,
+  handler:  Handler,
+  /// Set while this queue is waiting for a thread, or running on one.
+  running:  AtomicBool,
+  /// Set once the handler fails. No more messages will be handled.
+  closed:   AtomicBool,
+  /// Set while async messages are being dropped, so that we only warn once.
+  dropping: AtomicBool,
+}
+
+impl Dispatcher {
+  /// Creates a dispatcher with one thread for each cpu.
+  #[allow(clippy::new_without_default)]
+  pub fn new() -> Self {
+    let pool = ThreadPoolBuilder::new()
+      .thread_name(|i| format!("plugin dispatch {i}"))
+      .build()
+      .expect("could not create plugin thread pool");
+    Dispatcher { pool: Arc::new(pool) }
+  }
+
+  /// Creates a queue for a plugin. `handler` will be called for every message,
+  /// in order, on one of the dispatch threads.
+  pub fn queue(
+    &self,
+    name: String,
+    handler: impl Fn(ServerMessage) -> bool + Send + Sync + 'static,
+  ) -> Arc<Queue> {
+    Arc::new(Queue {
+      name,
+      pool: self.pool.clone(),
+      messages: Mutex::new(VecDeque::new()),
+      handler: Box::new(handler),
+      running: AtomicBool::new(false),
+      closed: AtomicBool::new(false),
+      dropping: AtomicBool::new(false),
+    })
+  }
+}
+
+impl Queue {
+  /// Adds a message to the queue. This never blocks. Returns an error if the
+  /// plugin has failed. If the plugin is too far behind, async messages are
+  /// dropped, and this still returns `Ok`.
+  pub fn send(self: &Arc<Self>, msg: ServerMessage) -> Result<(), Closed> {
+    if self.is_closed() {
+      return Err(Closed);
+    }
+    {
+      let mut messages = self.messages.lock();
+      if messages.len() >= MAX_BACKLOG && msg.class() == EventClass::Async {
+        if !self.dropping.swap(true, Ordering::Relaxed) {
+          warn!("plugin {} is falling behind, dropping events", self.name);
+        }
+        return Ok(());
+      }
+      messages.push_back(msg);
+    }
+    self.schedule();
+    Ok(())
+  }
+
+  /// Returns `true` if the handler has failed.
+  pub fn is_closed(&self) -> bool { self.closed.load(Ordering::Acquire) }
+
+  fn schedule(self: &Arc<Self>) {
+    if !self.running.swap(true, Ordering::AcqRel) {
+      let queue = self.clone();
+      self.pool.spawn_fifo(move || queue.run());
+    }
+  }
+
+  fn run(self: Arc<Self>) {
+    for _ in 0..BATCH_SIZE {
+      let Some(msg) = self.messages.lock().pop_front() else { break };
+      if !(self.handler)(msg) {
+        self.closed.store(true, Ordering::Release);
+        self.messages.lock().clear();
+        break;
+      }
+    }
+    let messages = self.messages.lock();
+    if messages.len() < MAX_BACKLOG / 2 {
+      self.dropping.store(false, Ordering::Relaxed);
+    }
+    if messages.is_empty() || self.is_closed() {
+      self.running.store(false, Ordering::Release);
+    } else {
+      // There are more messages, so we run again. This goes to the back of the
+      // pool's queue, so that other plugins get a chance to run.
+      drop(messages);
+      let queue = self.clone();
+      self.pool.spawn_fifo(move || queue.run());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::event::GenerateChunk;
+  use bb_common::math::ChunkPos;
+  use std::time::Duration;
+
+  fn msg(i: usize) -> ServerMessage {
+    ServerMessage::GlobalEvent {
+      event: GenerateChunk { generator: i.to_string(), pos: ChunkPos::new(0, 0) }.into(),
+    }
+  }
+  fn generator(msg: ServerMessage) -> usize {
+    match msg {
+      ServerMessage::GlobalEvent { event: crate::event::GlobalEvent::GenerateChunk(ev) } => {
+        ev.generator.parse().unwrap()
+      }
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn ordering() {
+    let dispatcher = Dispatcher::new();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let queue = dispatcher.queue("test".into(), move |msg| {
+      tx.send(generator(msg)).unwrap();
+      true
+    });
+    for i in 0..200 {
+      queue.send(msg(i)).unwrap();
+    }
+    for i in 0..200 {
+      assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(i));
+    }
+  }
+
+  #[test]
+  fn closed() {
+    let dispatcher = Dispatcher::new();
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let queue = dispatcher.queue("test".into(), move |msg| {
+      let i = generator(msg);
+      tx.send(i).unwrap();
+      i < 3
+    });
+    for i in 0..10 {
+      queue.send(msg(i)).unwrap();
+    }
+    for i in 0..4 {
+      assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(i));
+    }
+    // Nothing after the failed message is handled.
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    assert!(queue.is_closed());
+    assert!(queue.send(msg(10)).is_err());
+  }
+}
@@ -1,15 +1,19 @@
 #[cfg(feature = "panda_plugins")]
 use super::PandaPlugin;
 
-use super::{config::Config, GlobalEvent, PlayerEvent, PlayerRequest, Plugin};
+use super::{
+  config::Config, dispatch::Dispatcher, timings::PluginTimings, CallError, GlobalEvent,
+  GlobalRequest, PlayerEvent, PlayerRequest, Plugin,
+};
 use crate::{event::EventFlow, world::WorldManager};
 use crossbeam_channel::Select;
 use panda::Panda;
 use parking_lot::Mutex;
 use std::{
   fs,
+  path::PathBuf,
   sync::Arc,
-  time::{Duration, Instant},
+  time::{Duration, Instant, SystemTime},
 };
 
 /// A struct that manages all plugins. This will handle re-loading all the
@@ -19,6 +23,11 @@ pub struct PluginManager {
   start:                    Instant,
   pub(super) panda_preload: Mutex<Option<Box<dyn Fn(&mut Panda) + Send + Sync>>>,
   pub(super) plugins:       Mutex<Vec<Plugin>>,
+  /// A list of compiled wasm files, and when they were last modified. If any
+  /// of these change, all plugins will be reloaded.
+  watched:                  Mutex<Vec<(PathBuf, Option<SystemTime>)>>,
+  timings:                  Arc<PluginTimings>,
+  dispatcher:               Dispatcher,
 }
 
 impl PluginManager {
@@ -30,6 +39,9 @@ impl PluginManager {
       start:         Instant::now(),
       panda_preload: None.into(),
       plugins:       Mutex::new(vec![]),
+      watched:       Mutex::new(vec![]),
+      timings:       Arc::new(PluginTimings::new()),
+      dispatcher:    Dispatcher::new(),
     }
   }
 
@@ -42,10 +54,31 @@ impl PluginManager {
     *self.panda_preload.lock() = Some(Box::new(func));
   }
 
+  /// Returns how long each plugin has spent handling each event, since
+  /// plugins were last loaded.
+  pub fn timings(&self) -> &PluginTimings { &self.timings }
+
+  /// Returns the number of plugins loaded.
+  pub fn count(&self) -> usize { self.plugins.lock().len() }
+
   /// Ticks all plugins. This will run scheduled events.
   pub fn tick(&self) {
     for plugin in self.plugins.lock().iter() {
+      let start = Instant::now();
       plugin.tick();
+      self.timings.record(plugin.name(), "scheduled", start.elapsed());
+    }
+  }
+
+  /// Checks if any wasm plugins have been rebuilt since they were loaded. If
+  /// they have, this will reload all plugins. This is how `cargo bb dev`
+  /// hot reloads plugins.
+  pub fn reload_changed(&self, wm: &Arc<WorldManager>) {
+    let changed =
+      self.watched.lock().iter().any(|(path, modified)| modified_time(path) != *modified);
+    if changed {
+      info!("a plugin has changed, reloading all plugins");
+      self.load(wm.clone());
     }
   }
 
@@ -53,6 +86,9 @@ impl PluginManager {
   pub fn load(&self, wm: Arc<WorldManager>) {
     let mut plugins = self.plugins.lock();
     plugins.clear();
+    self.timings.clear();
+    let mut watched = self.watched.lock();
+    watched.clear();
 
     #[cfg(feature = "socket_plugins")]
     let mut sockets = super::socket::SocketManager::new(wm.clone());
@@ -67,6 +103,11 @@ impl PluginManager {
     for f in iter {
       let f = f.unwrap();
       let m = fs::metadata(f.path()).unwrap();
+      // Hidden directories, like `.cache`, are used by the server, and are not
+      // plugins.
+      if f.file_name().to_string_lossy().starts_with('.') {
+        continue;
+      }
       if m.is_dir() {
         let path = f.path();
         let config: Config = bb_common::config::new_at_write_default_to(
@@ -84,7 +125,13 @@ impl PluginManager {
             #[cfg(feature = "socket_plugins")]
             {
               if let Some(plugin) = sockets.add(name.clone(), f.path()) {
-                plugins.push(Plugin::new(name.clone(), config, plugin));
+                plugins.push(Plugin::new(
+                  name.clone(),
+                  config,
+                  plugin,
+                  &self.dispatcher,
+                  self.timings.clone(),
+                ));
               }
             }
             #[cfg(not(feature = "socket_plugins"))]
@@ -100,7 +147,13 @@ impl PluginManager {
               if main_path.exists() && main_path.is_file() {
                 let plugin =
                   super::python::Plugin::new(plugins.len(), name.clone(), main_path, wm.clone());
-                plugins.push(Plugin::new(name.clone(), config, plugin));
+                plugins.push(Plugin::new(
+                  name.clone(),
+                  config,
+                  plugin,
+                  &self.dispatcher,
+                  self.timings.clone(),
+                ));
               } else {
                 error!("plugin `{name}` does not have a `main.py` file");
               }
@@ -112,20 +165,30 @@ impl PluginManager {
           }
           "wasm" => {
             info!("found wasm plugin at {}", path.to_str().unwrap());
-            #[cfg(feature = "wasm_plugins")]
+            #[cfg(feature = "wasm_host")]
             {
+              let output = path.join(&config.wasm.output);
+              watched.push((output.clone(), modified_time(&output)));
               match super::wasm::Plugin::new(
                 name.clone(),
                 &path,
                 config.get_at(["wasm", "compile"].into_iter()),
                 config.get_at(["wasm", "output"].into_iter()),
+                config.get_at(["wasm", "runtime"].into_iter()),
+                config.worlds.clone(),
                 wm.clone(),
               ) {
-                Ok(p) => plugins.push(Plugin::new(name.clone(), config, p)),
+                Ok(p) => plugins.push(Plugin::new(
+                  name.clone(),
+                  config,
+                  p,
+                  &self.dispatcher,
+                  self.timings.clone(),
+                )),
                 Err(e) => error!("error loading {name}: {e}"),
               }
             }
-            #[cfg(not(feature = "wasm_plugins"))]
+            #[cfg(not(feature = "wasm_host"))]
             {
               info!("wasm plugins are disabling, skipping {}", path.to_str().unwrap());
             }
@@ -141,7 +204,13 @@ impl PluginManager {
 
                 p.load_from_dir(&f.path(), self);
                 p.call_init();
-                plugins.push(Plugin::new(name.clone(), config, p));
+                plugins.push(Plugin::new(
+                  name.clone(),
+                  config,
+                  p,
+                  &self.dispatcher,
+                  self.timings.clone(),
+                ));
               } else {
                 error!("plugin `{name}` does not have a `main.pand` file");
               }
@@ -182,10 +251,19 @@ impl PluginManager {
     });
   }
   pub(crate) fn player_request(&self, request: PlayerRequest) -> EventFlow {
+    self.request(|p, reply_id| p.req(reply_id, request.clone()))
+  }
+  pub(crate) fn global_request(&self, request: GlobalRequest) -> EventFlow {
+    self.request(|p, reply_id| p.global_req(reply_id, request.clone()))
+  }
+  /// Sends a request to every plugin with `send`, and then waits for all of
+  /// them to reply. If any plugin cancels the request, this returns
+  /// [`EventFlow::Handled`].
+  fn request(&self, send: impl Fn(&Plugin, u32) -> Result<(), CallError>) -> EventFlow {
     let reply_id = self.start.elapsed().as_micros() as u32;
     let mut plugins = self.plugins.lock();
     // Send all the events first.
-    plugins.retain(|p| match p.req(reply_id, request.clone()) {
+    plugins.retain(|p| match send(p, reply_id) {
       Ok(_) => true,
       Err(e) => e.keep,
     });
@@ -225,3 +303,7 @@ impl PluginManager {
     }
   }
 }
+
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+  fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -4,9 +4,10 @@ pub mod panda;
 pub mod python;
 #[cfg(feature = "socket_plugins")]
 pub mod socket;
-#[cfg(feature = "wasm_plugins")]
+#[cfg(feature = "wasm_host")]
 pub mod wasm;
 
+pub mod timings;
 #[cfg(not(doctest))]
 pub mod types;
 
@@ -38,6 +39,7 @@ pub mod types {
 }
 
 pub mod config;
+pub mod dispatch;
 mod manager;
 
 pub use self::panda::IntoPanda;
@@ -47,14 +49,19 @@ pub use manager::PluginManager;
 use socket::SocketManager;
 
 use crate::{
-  event::{GlobalEvent, PlayerEvent, PlayerRequest, PluginMessage, PluginReply, ServerMessage},
+  event::{
+    GlobalEvent, GlobalRequest, PlayerEvent, PlayerRequest, PluginMessage, PluginReply,
+    ServerMessage,
+  },
   world::WorldManager,
 };
 use ::panda::runtime::{tree::Closure, LockedEnv, VarSend};
 use config::Config;
 use crossbeam_channel::{Receiver, Sender};
+use dispatch::{Dispatcher, Queue};
 use parking_lot::{Mutex, MutexGuard};
-use std::{error::Error, fmt, sync::Arc, thread};
+use std::{error::Error, fmt, sync::Arc, time::Instant};
+use timings::PluginTimings;
 
 #[derive(Debug)]
 struct Scheduled {
@@ -134,6 +141,8 @@ pub trait PluginImpl: std::any::Any {
   fn call(&self, event: PlayerEvent) -> Result<(), CallError>;
   /// Calls an event. This should block until it gets a reply.
   fn req(&self, event: PlayerRequest) -> Result<PluginReply, CallError>;
+  /// Calls an event. This should block until it gets a reply.
+  fn global_req(&self, event: GlobalRequest) -> Result<PluginReply, CallError>;
   #[cfg(feature = "panda_plugins")]
   fn panda(&mut self) -> Option<&mut PandaPlugin> { None }
 }
@@ -142,10 +151,9 @@ pub struct Plugin {
   // This will be useful in the future. Probably.
   #[allow(unused)]
   config:    Config,
-  #[allow(unused)]
   name:      String,
   imp:       Arc<Mutex<dyn PluginImpl + Send + Sync>>,
-  tx:        Sender<ServerMessage>,
+  queue:     Arc<Queue>,
   rx:        Receiver<PluginMessage>,
   /// Used to recycled events we don't care about back into the queue.
   plugin_tx: Sender<PluginMessage>,
@@ -186,36 +194,49 @@ impl CallError {
 }
 
 impl Plugin {
-  pub fn new(name: String, config: Config, imp: impl PluginImpl + Send + Sync + 'static) -> Self {
-    let (server_tx, server_rx) = crossbeam_channel::bounded(128);
+  pub fn new(
+    name: String,
+    config: Config,
+    imp: impl PluginImpl + Send + Sync + 'static,
+    dispatcher: &Dispatcher,
+    timings: Arc<PluginTimings>,
+  ) -> Self {
     let (plugin_tx, plugin_rx) = crossbeam_channel::bounded(128);
     let imp = Arc::new(Mutex::new(imp));
     let i = Arc::clone(&imp);
     let ptx = plugin_tx.clone();
-    thread::spawn(move || {
-      while let Ok(ev) = server_rx.recv() {
-        let res = match ev {
-          ServerMessage::PlayerRequest { reply_id, request } => i
-            .lock()
+    let n = name.clone();
+    let queue = dispatcher.queue(name.clone(), move |ev| {
+      let start = Instant::now();
+      let (event, res) = match ev {
+        ServerMessage::PlayerRequest { reply_id, request } => (
+          request.name(),
+          i.lock()
             .req(request)
             .map(|reply| plugin_tx.send(PluginMessage::Reply { reply_id, reply }).unwrap()),
-          ServerMessage::PlayerEvent { event } => i.lock().call(event),
-          ServerMessage::GlobalEvent { event } => i.lock().call_global(event),
-          ServerMessage::Reply { .. } => Ok(()),
-        };
-        match res {
-          Ok(()) => (),
-          Err(e) => {
-            e.log();
-            if !e.keep {
-              return;
-            }
-          }
+        ),
+        ServerMessage::GlobalRequest { reply_id, request } => (
+          request.name(),
+          i.lock()
+            .global_req(request)
+            .map(|reply| plugin_tx.send(PluginMessage::Reply { reply_id, reply }).unwrap()),
+        ),
+        ServerMessage::PlayerEvent { event } => (event.name(), i.lock().call(event)),
+        ServerMessage::GlobalEvent { event } => (event.name(), i.lock().call_global(event)),
+        ServerMessage::Reply { .. } => return true,
+      };
+      timings.record(&n, event, start.elapsed());
+      match res {
+        Ok(()) => true,
+        Err(e) => {
+          e.log();
+          e.keep
         }
       }
     });
-    Plugin { config, name, imp, tx: server_tx, rx: plugin_rx, plugin_tx: ptx }
+    Plugin { config, name, imp, queue, rx: plugin_rx, plugin_tx: ptx }
   }
+  pub fn name(&self) -> &str { &self.name }
   pub fn tick(&self) {
     if let Some(pd) = self.imp.lock().panda() {
       let bb = pd.bb();
@@ -243,16 +264,16 @@ impl Plugin {
     }
   }
   pub fn call_global(&self, event: GlobalEvent) -> Result<(), CallError> {
-    self.tx.send(ServerMessage::GlobalEvent { event }).unwrap();
-    Ok(())
+    self.queue.send(ServerMessage::GlobalEvent { event }).map_err(CallError::no_keep)
   }
   pub fn call(&self, event: PlayerEvent) -> Result<(), CallError> {
-    self.tx.send(ServerMessage::PlayerEvent { event }).unwrap();
-    Ok(())
+    self.queue.send(ServerMessage::PlayerEvent { event }).map_err(CallError::no_keep)
   }
   pub fn req(&self, reply_id: u32, request: PlayerRequest) -> Result<(), CallError> {
-    self.tx.send(ServerMessage::PlayerRequest { reply_id, request }).unwrap();
-    Ok(())
+    self.queue.send(ServerMessage::PlayerRequest { reply_id, request }).map_err(CallError::no_keep)
+  }
+  pub fn global_req(&self, reply_id: u32, request: GlobalRequest) -> Result<(), CallError> {
+    self.queue.send(ServerMessage::GlobalRequest { reply_id, request }).map_err(CallError::no_keep)
   }
   pub fn rx(&self) -> &Receiver<PluginMessage> { &self.rx }
   /// `Some(true)` means we allow.
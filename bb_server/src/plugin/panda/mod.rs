@@ -1,6 +1,6 @@
 use super::{
-  types::Callback as BCallback, Bamboo, CallError, GlobalEvent, PlayerEvent, PlayerRequest,
-  PluginImpl, PluginManager, PluginReply,
+  types::Callback as BCallback, Bamboo, CallError, GlobalEvent, GlobalRequest, PlayerEvent,
+  PlayerRequest, PluginImpl, PluginManager, PluginReply,
 };
 use crate::world::WorldManager;
 use panda::{
@@ -150,5 +150,8 @@ impl PluginImpl for PandaPlugin {
   fn req(&self, req: PlayerRequest) -> Result<PluginReply, CallError> {
     Ok(PluginReply::Cancel { allow: self.req(req.name(), vec![req.into_panda()]) })
   }
+  fn global_req(&self, req: GlobalRequest) -> Result<PluginReply, CallError> {
+    Ok(PluginReply::Cancel { allow: self.req(req.name(), vec![req.into_panda()]) })
+  }
   fn panda(&mut self) -> Option<&mut PandaPlugin> { Some(self) }
 }
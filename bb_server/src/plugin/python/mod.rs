@@ -1,8 +1,8 @@
 use super::{
   panda::PandaPlugin,
   types::{Callback, Callback as BCallback},
-  Bamboo, CallError, GlobalEvent, PlayerEvent, PlayerRequest, PluginImpl, PluginManager,
-  PluginReply,
+  Bamboo, CallError, GlobalEvent, GlobalRequest, PlayerEvent, PlayerRequest, PluginImpl,
+  PluginManager, PluginReply,
 };
 use crate::world::WorldManager;
 use crossbeam_channel::{Receiver, Sender};
@@ -95,5 +95,8 @@ impl PluginImpl for Plugin {
     // vec![req.into_panda()]) })
     Ok(PluginReply::Cancel { allow: true })
   }
+  fn global_req(&self, _req: GlobalRequest) -> Result<PluginReply, CallError> {
+    Ok(PluginReply::Cancel { allow: true })
+  }
   fn panda(&mut self) -> Option<&mut PandaPlugin> { None }
 }
@@ -1,6 +1,6 @@
 use super::{
-  CallError, PluginEvent, PluginImpl, PluginMessage, PluginRequest, ServerMessage, ServerReply,
-  ServerRequest,
+  CallError, GlobalRequest, PluginEvent, PluginImpl, PluginMessage, PluginRequest, ServerMessage,
+  ServerReply, ServerRequest,
 };
 use crate::{player::Player, world::WorldManager};
 use crossbeam_channel::{Receiver, Sender};
@@ -368,4 +368,7 @@ impl PluginImpl for Arc<SocketPlugin> {
   ) -> Result<PluginReply, CallError> {
     self.send(ServerMessage::Request { player, reply_id, request }).map_err(CallError::no_keep)
   }
+  fn global_req(&self, reply_id: u32, request: GlobalRequest) -> Result<PluginReply, CallError> {
+    self.send(ServerMessage::GlobalRequest { reply_id, request }).map_err(CallError::no_keep)
+  }
 }
@@ -0,0 +1,92 @@
+use parking_lot::Mutex;
+use std::{collections::HashMap, time::Duration};
+
+/// How long each plugin has spent handling each event. This is cleared
+/// whenever plugins are reloaded.
+#[derive(Default)]
+pub struct PluginTimings {
+  /// A map of event names to plugin names to calls.
+  events: Mutex<HashMap<&'static str, HashMap<String, Calls>>>,
+}
+
+/// All the calls of a single event, for a single plugin.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Calls {
+  pub count: u64,
+  pub total: Duration,
+  pub max:   Duration,
+}
+
+impl Calls {
+  pub fn average(&self) -> Duration {
+    if self.count == 0 {
+      Duration::ZERO
+    } else {
+      self.total / self.count as u32
+    }
+  }
+}
+
+impl PluginTimings {
+  pub fn new() -> Self { PluginTimings::default() }
+
+  /// Records that `plugin` took `time` to handle `event`.
+  pub fn record(&self, plugin: &str, event: &'static str, time: Duration) {
+    let mut events = self.events.lock();
+    let plugins = events.entry(event).or_default();
+    let calls = match plugins.get_mut(plugin) {
+      Some(c) => c,
+      None => plugins.entry(plugin.into()).or_default(),
+    };
+    calls.count += 1;
+    calls.total += time;
+    calls.max = calls.max.max(time);
+  }
+
+  /// Clears all the timings.
+  pub fn clear(&self) { self.events.lock().clear(); }
+
+  /// Returns up to `n` of the slowest plugins for every event, sorted by
+  /// average time. Events are sorted by name.
+  pub fn slowest(&self, n: usize) -> Vec<(&'static str, Vec<(String, Calls)>)> {
+    let events = self.events.lock();
+    let mut out: Vec<_> = events
+      .iter()
+      .map(|(&event, plugins)| {
+        let mut plugins: Vec<_> = plugins.iter().map(|(name, c)| (name.clone(), *c)).collect();
+        plugins.sort_unstable_by(|a, b| b.1.average().cmp(&a.1.average()));
+        plugins.truncate(n);
+        (event, plugins)
+      })
+      .collect();
+    out.sort_unstable_by_key(|(event, _)| *event);
+    out
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn slowest() {
+    let timings = PluginTimings::new();
+    timings.record("fast", "tick", Duration::from_millis(1));
+    timings.record("slow", "tick", Duration::from_millis(4));
+    timings.record("slow", "tick", Duration::from_millis(2));
+    timings.record("fast", "block_place", Duration::from_millis(1));
+
+    let slowest = timings.slowest(1);
+    assert_eq!(slowest.len(), 2);
+    assert_eq!(slowest[0].0, "block_place");
+    assert_eq!(slowest[1].0, "tick");
+    assert_eq!(
+      slowest[1].1,
+      [(
+        "slow".to_string(),
+        Calls { count: 2, total: Duration::from_millis(6), max: Duration::from_millis(4) }
+      )]
+    );
+    assert_eq!(slowest[1].1[0].1.average(), Duration::from_millis(3));
+  }
+}
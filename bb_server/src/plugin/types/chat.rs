@@ -40,6 +40,18 @@ impl PChat {
 
   /// Creates a new chat message with the given text.
   pub fn new(text: &str) -> PChat { PChat { inner: Arc::new(Mutex::new(Chat::new(text))) } }
+  /// Creates a chat message from text with color codes, like `§a` for green
+  /// or `§l` for bold.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// chat = Chat::from_codes("§aHello §lworld")
+  /// ```
+  pub fn from_codes(text: &str) -> PChat { Chat::from_codes(text).into() }
+  /// Returns this chat message as text with color codes. This is how chat
+  /// messages are shown to 1.8 clients in places like scoreboards.
+  pub fn to_codes(&self) -> String { self.inner.lock().to_codes() }
   /// Sets the color of the chat message. This won't do anything if the chat
   /// message has multiple sections.
   ///
@@ -60,6 +72,19 @@ impl PChat {
     }
     Ok(self.clone())
   }
+  /// Creates a chat message that the client translates. `key` is a key in the
+  /// client's language file, and each argument can be a string or another
+  /// chat message.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// chat = Chat::translate("death.attack.player", ["macmv", "Notch"])
+  /// // Shows "macmv was slain by Notch"
+  /// ```
+  pub fn translate(key: &str, args: Vec<Var>) -> PChat {
+    Chat::translate(key, args.into_iter().map(PChat::from_var)).into()
+  }
   /// Creates an empty chat message. This can have sections added using `add`.
   pub fn empty() -> PChat { PChat { inner: Arc::new(Mutex::new(Chat::empty())) } }
   /// Adds a new chat section. This will return the section that was just added,
@@ -119,4 +144,41 @@ impl PChatSection {
     );
     Ok(self.clone())
   }
+  /// Makes this section run a command when it is clicked. A `/` is added to
+  /// the start of the command if it is missing.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// chat.add("[Spawn]").run_command("/spawn")
+  /// ```
+  pub fn run_command(&self, command: &str) -> Self {
+    self.inner.lock().get_section(self.idx).unwrap().run_command(command);
+    self.clone()
+  }
+  /// Makes this section put the given text in the player's chat box when it
+  /// is clicked.
+  pub fn suggest_command(&self, text: &str) -> Self {
+    self.inner.lock().get_section(self.idx).unwrap().suggest_command(text);
+    self.clone()
+  }
+  /// Makes this section open the given url when it is clicked. The player
+  /// will be asked to confirm before the url is opened.
+  pub fn open_url(&self, url: &str) -> Self {
+    self.inner.lock().get_section(self.idx).unwrap().open_url(url);
+    self.clone()
+  }
+  /// Shows the given text when a player hovers over this section. The text
+  /// can be a string or another chat message.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// chat.add("hover me").show_text(Chat::new("hello").color("red"))
+  /// ```
+  pub fn show_text(&self, text: Var) -> Self {
+    let text = PChat::from_var(text);
+    self.inner.lock().get_section(self.idx).unwrap().show_text(text);
+    self.clone()
+  }
 }
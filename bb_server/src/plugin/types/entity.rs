@@ -0,0 +1,31 @@
+use crate::entity::EntityHandle;
+use bb_server_macros::define_ty;
+
+/// A handle to an entity in a world. This is returned when spawning entities,
+/// and can be passed to functions like `World::set_equipment`.
+///
+/// If the entity is removed, the handle becomes invalid, and any function
+/// called with it will return an error. A handle will never refer to a
+/// different entity, even if that entity is given the same id.
+#[define_ty]
+impl PEntity {
+  info! {
+    wrap: EntityHandle,
+    eq: true,
+
+    panda: {
+      path: "bamboo::entity::Entity",
+      map_key: true,
+    },
+    python: {
+      class: "Entity",
+    },
+  }
+
+  /// Returns the id of this entity. This id may be reused by another entity
+  /// in a different world, so the handle itself should be stored instead.
+  pub fn eid(&self) -> i32 { self.inner.eid }
+
+  /// Returns the handle as a string, in the form `eid` `v` `generation`.
+  pub fn to_s(&self) -> String { self.inner.to_string() }
+}
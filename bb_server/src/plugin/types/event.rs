@@ -1,4 +1,9 @@
-use crate::event::EventFlow;
+use super::{
+  chat::PChat,
+  util::{PFPos, PVec3},
+  world::PWorld,
+};
+use crate::event::{Attack, EventFlow, PreJoin};
 use bb_server_macros::define_ty;
 use parking_lot::Mutex;
 use std::sync::Arc;
@@ -44,3 +49,79 @@ impl PEventFlow {
   /// then calling `cancel` or `allow` will throw an error.
   pub fn is_allowed(&self) -> bool { self.cancelled.lock().is_some() }
 }
+
+/// Where a player will spawn, and whether they are allowed to join. This is
+/// passed to `player_pre_join`.
+///
+/// # Example
+///
+/// ```
+/// on player_pre_join(event, flow) {
+///   if event.username == "griefer" {
+///     event.join.deny(Chat::new("Go away").color("red"))
+///   } else {
+///     event.join.set_pos(FPos::new(0.5, 100.0, 0.5))
+///   }
+/// }
+/// ```
+#[define_ty]
+impl PPreJoin {
+  info! {
+    wrap: Arc<Mutex<PreJoin>>,
+
+    panda: {
+      path: "bamboo::event::PreJoin",
+    },
+    python: {
+      class: "PreJoin",
+    },
+  }
+
+  /// Stops the player from joining. They will be kicked with the given
+  /// message.
+  pub fn deny(&self, reason: &PChat) { self.inner.lock().deny = Some(reason.inner.lock().clone()); }
+  /// Returns `true` if a plugin has called `deny`.
+  pub fn is_denied(&self) -> bool { self.inner.lock().deny.is_some() }
+  /// Returns the world the player will spawn in.
+  pub fn world(&self) -> PWorld { self.inner.lock().world.clone().into() }
+  /// Changes the world the player will spawn in.
+  pub fn set_world(&self, world: &PWorld) { self.inner.lock().world = world.inner.clone(); }
+  /// Returns the position the player will spawn at.
+  pub fn pos(&self) -> PFPos { self.inner.lock().pos.into() }
+  /// Changes the position the player will spawn at.
+  pub fn set_pos(&self, pos: &PFPos) { self.inner.lock().pos = pos.inner; }
+}
+
+/// The damage and knockback of an attack. This is passed to `entity_attack`.
+///
+/// # Example
+///
+/// ```
+/// on entity_attack(event, flow) {
+///   // Double all damage
+///   event.attack.set_damage(event.attack.damage() * 2.0)
+/// }
+/// ```
+#[define_ty]
+impl PAttack {
+  info! {
+    wrap: Arc<Mutex<Attack>>,
+
+    panda: {
+      path: "bamboo::event::Attack",
+    },
+    python: {
+      class: "Attack",
+    },
+  }
+
+  /// Returns the damage of this attack, before armor is applied.
+  pub fn damage(&self) -> f32 { self.inner.lock().damage }
+  /// Changes the damage of this attack.
+  pub fn set_damage(&self, damage: f32) { self.inner.lock().damage = damage; }
+  /// Returns the knockback of this attack. This is added to the velocity of
+  /// the entity being attacked.
+  pub fn knockback(&self) -> PVec3 { self.inner.lock().knockback.into() }
+  /// Changes the knockback of this attack.
+  pub fn set_knockback(&self, knockback: &PVec3) { self.inner.lock().knockback = knockback.inner; }
+}
@@ -1,9 +1,13 @@
 use super::chat::PChat;
 use crate::{
+  entity::Trade,
   item,
   item::{Inventory, Stack, UI},
 };
-use bb_common::net::sb::ClickWindow;
+use bb_common::{
+  net::sb::ClickWindow,
+  util::{AttributeModifier, AttributeOperation, AttributeSlot},
+};
 use bb_server_macros::define_ty;
 use panda::{
   parse::token::Span,
@@ -80,16 +84,130 @@ impl PStack {
   /// Sets the given enchantment to the given level for this stack. If set to 0,
   /// the enchantment will be removed.
   pub fn set_enchantment(&mut self, enchantment: &str, level: u8) -> Result<(), RuntimeError> {
-    let enchantment = crate::enchantment::Type::from_str(enchantment)
-      .map_err(|e| RuntimeError::Custom(e.to_string(), Span::call_site()))?;
-    let enchantments = self.inner.data_mut().enchantments_mut();
-    if let Some(level) = std::num::NonZeroU8::new(level) {
-      enchantments.insert(enchantment.id(), level);
-    } else {
-      enchantments.remove(&enchantment.id());
-    }
+    self.inner.set_enchantment(parse_enchantment(enchantment)?, level);
+    Ok(())
+  }
+  /// Adds an attribute modifier to this stack. The attribute is a name like
+  /// `generic.attack_damage`. The operation is one of `add`, `multiply_base`,
+  /// or `multiply`. The slot is one of `mainhand`, `offhand`, `head`, `chest`,
+  /// `legs`, or `feet`. If the slot is empty, the modifier applies in any slot.
+  pub fn add_attribute(
+    &mut self,
+    attribute: &str,
+    amount: f64,
+    operation: &str,
+    slot: &str,
+  ) -> Result<(), RuntimeError> {
+    let modifier = parse_attribute(attribute, amount, operation, slot)?;
+    self.inner.data_mut().attributes.push(modifier);
     Ok(())
   }
+
+  /// Returns a copy of this stack with the given display name.
+  pub fn with_display_name(&self, name: Var) -> Self {
+    PStack { inner: self.inner.clone().with_name(PChat::from_var(name)) }
+  }
+  /// Returns a copy of this stack with the given line of lore added.
+  pub fn with_lore(&self, line: Var) -> Self {
+    PStack { inner: self.inner.clone().with_lore(PChat::from_var(line)) }
+  }
+  /// Returns a copy of this stack with the given enchantment set. See
+  /// `set_enchantment`.
+  pub fn with_enchantment(&self, enchantment: &str, level: u8) -> Result<Self, RuntimeError> {
+    Ok(PStack {
+      inner: self.inner.clone().with_enchantment(parse_enchantment(enchantment)?, level),
+    })
+  }
+  /// Returns a copy of this stack with the given attribute modifier added. See
+  /// `add_attribute`.
+  pub fn with_attribute(
+    &self,
+    attribute: &str,
+    amount: f64,
+    operation: &str,
+    slot: &str,
+  ) -> Result<Self, RuntimeError> {
+    let modifier = parse_attribute(attribute, amount, operation, slot)?;
+    Ok(PStack { inner: self.inner.clone().with_attribute(modifier) })
+  }
+  /// Returns a copy of this stack, which is unbreakable if `unbreakable` is
+  /// `true`.
+  pub fn with_unbreakable(&self, unbreakable: bool) -> Self {
+    PStack { inner: self.inner.clone().with_unbreakable(unbreakable) }
+  }
+}
+
+fn parse_enchantment(name: &str) -> Result<crate::enchantment::Type, RuntimeError> {
+  crate::enchantment::Type::from_str(name)
+    .map_err(|e| RuntimeError::Custom(e.to_string(), Span::call_site()))
+}
+
+fn parse_attribute(
+  attribute: &str,
+  amount: f64,
+  operation: &str,
+  slot: &str,
+) -> Result<AttributeModifier, RuntimeError> {
+  let operation = match operation {
+    "add" => AttributeOperation::Add,
+    "multiply_base" => AttributeOperation::MultiplyBase,
+    "multiply" => AttributeOperation::Multiply,
+    _ => {
+      return Err(RuntimeError::Custom(
+        format!("invalid attribute operation: {operation}"),
+        Span::call_site(),
+      ))
+    }
+  };
+  let modifier = AttributeModifier::new(attribute, amount, operation);
+  if slot.is_empty() {
+    return Ok(modifier);
+  }
+  match AttributeSlot::from_name(slot) {
+    Some(slot) => Ok(modifier.in_slot(slot)),
+    None => Err(RuntimeError::Custom(format!("invalid attribute slot: {slot}"), Span::call_site())),
+  }
+}
+
+/// A trade offered by an entity. The player pays with the input stacks, and
+/// gets the output stack.
+///
+/// # Example
+///
+/// ```
+/// shop = world.spawn_villager(FPos::new(0.5, 65.0, 0.5))
+/// trade = Trade::new(Stack::new("emerald").with_amount(3), Stack::new("bread"))
+/// world.add_trade(shop, trade.with_max_uses(10))
+/// ```
+#[define_ty]
+impl PTrade {
+  info! {
+    wrap: Trade,
+
+    panda: {
+      path: "bamboo::item::Trade",
+    },
+  }
+
+  /// Creates a trade, which takes `input` and gives `output`. It can be used
+  /// any number of times.
+  pub fn new(input: &PStack, output: &PStack) -> Self {
+    PTrade { inner: Trade::new(input.inner.clone(), output.inner.clone()) }
+  }
+
+  /// Returns a copy of this trade, which also requires the given stack.
+  pub fn with_second_input(&self, input: &PStack) -> Self {
+    PTrade { inner: self.inner.clone().with_second_input(input.inner.clone()) }
+  }
+  /// Returns a copy of this trade, which can only be used `max_uses` times.
+  pub fn with_max_uses(&self, max_uses: u32) -> Self {
+    PTrade { inner: self.inner.clone().with_max_uses(max_uses) }
+  }
+  /// Returns a copy of this trade, which shows the given amount of experience.
+  pub fn with_xp(&self, xp: u32) -> Self { PTrade { inner: self.inner.clone().with_xp(xp) } }
+
+  /// Returns the number of times this trade has been used.
+  pub fn uses(&self) -> u32 { self.inner.uses }
 }
 
 /// An inventory UI.
@@ -10,19 +10,27 @@
 use self::player::PPlayer;
 
 use super::{Bamboo, PandaPlugin};
-use bb_common::util::{chat::Color, Chat};
+use crate::{
+  player::Player,
+  world::{Action, RegionGuard, World},
+};
+use bb_common::{
+  math::Pos,
+  util::{chat::Color, Chat},
+};
 use bb_server_macros::define_ty;
 use panda::{
   docs::markdown,
   parse::token::Span,
   path,
-  runtime::{LockedEnv, RuntimeError, Var},
+  runtime::{tree::Closure, LockedEnv, RuntimeError, Var},
   Panda,
 };
 
 pub mod block;
 pub mod chat;
 pub mod command;
+pub mod entity;
 pub mod event;
 pub mod item;
 pub mod player;
@@ -112,6 +120,9 @@ impl Bamboo {
       .map(|team| PTeam { inner: team })
       .ok_or_else(|| RuntimeError::custom("Team doesn't exist", Span::call_site()))
   }
+  /// Removes the given team. All of its players will be removed from the
+  /// team. Returns `false` if the team doesn't exist.
+  pub fn remove_team(&self, name: &str) -> bool { self.wm.remove_team(name) }
 
   /// Adds a command to the server.
   ///
@@ -154,20 +165,14 @@ impl Bamboo {
           let plugin = &mut lock[idx];
           let mut imp = plugin.lock_imp();
           let panda = imp.panda().unwrap();
-          let default_world = wm.default_world();
+          let world = player.map(|p| p.world()).unwrap_or_else(|| wm.default_world());
           if let Err(e) = cb.call_panda(
             &mut panda.lock_env(),
             vec![
               player.map(|p| player::PPlayer::from(p.clone()).into()).unwrap_or(Var::None),
               args
                 .iter()
-                .map(|arg| {
-                  command::sl_from_arg(
-                    arg.clone(),
-                    player.map(|p| p.world()).unwrap_or(&default_world),
-                    player,
-                  )
-                })
+                .map(|arg| command::sl_from_arg(arg.clone(), &world, player))
                 .collect::<Vec<Var>>()
                 .into(),
             ],
@@ -277,6 +282,61 @@ impl Bamboo {
   pub fn block_behaviors(&self) -> PBlockBehaviors {
     block::Behaviors { bb: self.clone(), wm: self.wm.clone() }.into()
   }
+
+  /// Adds a region guard. Every time a player breaks, places, or interacts
+  /// with a block, and every time an explosion destroys a block, the closure
+  /// is called with the player (or `none` for explosions), the position of the
+  /// block, and the action (one of `break`, `place`, `interact`, or
+  /// `explode`). If the closure returns `false`, the action is stopped.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// fn init() {
+  ///   // Nothing can change blocks below y=10
+  ///   bamboo::instance().add_region_guard(|player, pos, action| {
+  ///     pos.y >= 10
+  ///   })
+  /// }
+  /// ```
+  pub fn add_region_guard(&self, closure: Var) -> Result<(), RuntimeError> {
+    let closure = closure.closure(Span::call_site())?.clone();
+    self.wm.add_region_guard(PluginGuard { bb: self.clone(), closure });
+    Ok(())
+  }
+}
+
+/// A region guard added by a plugin with `add_region_guard`.
+struct PluginGuard {
+  bb:      Bamboo,
+  closure: Closure,
+}
+
+impl RegionGuard for PluginGuard {
+  fn allows(
+    &self,
+    _world: &Arc<World>,
+    player: Option<&Arc<Player>>,
+    pos: Pos,
+    action: Action,
+  ) -> bool {
+    let plugins = self.bb.wm.plugins().plugins.lock();
+    let mut imp = plugins[self.bb.idx].lock_imp();
+    let pd = imp.panda().unwrap();
+    let args = vec![
+      player.map(|p| player::PPlayer::from(p.clone()).into()).unwrap_or(Var::None),
+      util::PPos::from(pos).into(),
+      action.name().to_string().into(),
+    ];
+    let res = self.closure.call(&mut pd.lock_env(), args);
+    match res {
+      Ok(v) => !matches!(v, Var::Bool(false)),
+      Err(e) => {
+        pd.print_err(e);
+        true
+      }
+    }
+  }
 }
 
 fn format(args: &[Var]) -> String {
@@ -304,6 +364,7 @@ impl PandaPlugin {
     crate::event::GlobalEvent::add_builtins(sl);
     crate::event::PlayerEvent::add_builtins(sl);
     crate::event::PlayerRequest::add_builtins(sl);
+    crate::event::GlobalRequest::add_builtins(sl);
 
     {
       let bb = self.bb();
@@ -426,6 +487,8 @@ impl PandaPlugin {
     sl.add_builtin_ty::<util::PCountdown>();
     sl.add_builtin_ty::<util::PDuration>();
     sl.add_builtin_ty::<event::PEventFlow>();
+    sl.add_builtin_ty::<event::PPreJoin>();
+    sl.add_builtin_ty::<event::PAttack>();
     sl.add_builtin_ty::<block::PBlockData>();
     sl.add_builtin_ty::<block::PBlockKind>();
     sl.add_builtin_ty::<block::PBlockType>();
@@ -435,12 +498,15 @@ impl PandaPlugin {
     sl.add_builtin_ty::<item::PClickWindow>();
     sl.add_builtin_ty::<item::PInventory>();
     sl.add_builtin_ty::<item::PStack>();
+    sl.add_builtin_ty::<item::PTrade>();
     sl.add_builtin_ty::<item::PUI>();
     sl.add_builtin_ty::<command::PCommand>();
+    sl.add_builtin_ty::<entity::PEntity>();
     sl.add_builtin_ty::<player::PBlockClick>();
     sl.add_builtin_ty::<player::PPlayer>();
     sl.add_builtin_ty::<player::PTeam>();
     sl.add_builtin_ty::<player::PWindow>();
+    sl.add_builtin_ty::<world::PChangeSet>();
     sl.add_builtin_ty::<world::PWorld>();
     sl.add_builtin_ty::<world::gen::PBiome>();
   }
@@ -1,14 +1,23 @@
 use super::{
+  block::{PBlockKind, PBlockType},
   chat::PChat,
+  entity::PEntity,
   item::PStack,
-  util::{PFPos, PUUID},
-  world::PWorld,
+  util::{PFPos, PPos, PUUID},
+  world::{PChangeSet, PWorld},
 };
 use crate::{
+  entity::{Attribute, Effect, Modifier, Operation},
+  item,
   item::Stack,
-  player::{Player, Team, Window},
+  player::{Player, Skin, SkinModel, SkinParts, Team, Textures, Window},
+  world::{EditError, RegionEdit},
+};
+use bb_common::{
+  metadata::Pose,
+  net::cb::TeamRule,
+  util::{chat::Color, ClientKind, GameMode, SwitchMode, UUID},
 };
-use bb_common::util::{chat::Color, GameMode, SwitchMode, UUID};
 use bb_server_macros::define_ty;
 use panda::{
   parse::token::Span,
@@ -16,7 +25,7 @@ use panda::{
 };
 use parking_lot::Mutex;
 use std::{
-  net::{SocketAddr, ToSocketAddrs},
+  net::SocketAddr,
   str::FromStr,
   sync::{Arc, Weak},
 };
@@ -33,6 +42,38 @@ impl PPlayer {
       RuntimeError::custom(format!("`{}` is offline", self.username), Span::call_site())
     })
   }
+  /// Returns the decoded skin of this player. Players without a skin (or with
+  /// an invalid one) will return the default textures.
+  fn textures(&self) -> Result<Textures> {
+    Ok(self.inner()?.skin().and_then(|s| s.textures()).unwrap_or_default())
+  }
+}
+
+fn item_type(name: &str) -> Result<item::Type> {
+  item::Type::from_str(name).map_err(|e| RuntimeError::custom(e.to_string(), Span::call_site()))
+}
+
+fn edit_err(e: EditError) -> RuntimeError { RuntimeError::custom(e.to_string(), Span::call_site()) }
+
+fn parse_attribute(name: &str) -> Result<Attribute> {
+  Attribute::from_name(name)
+    .ok_or_else(|| RuntimeError::custom(format!("unknown attribute `{name}`"), Span::call_site()))
+}
+fn parse_team_rule(rule: &str) -> Result<TeamRule> {
+  Ok(match rule {
+    "always" => TeamRule::Always,
+    "never" => TeamRule::Never,
+    "own_team" => TeamRule::ForOwnTeam,
+    "other_teams" => TeamRule::ForOtherTeams,
+    _ => {
+      return Err(RuntimeError::custom(format!("unknown team rule `{rule}`"), Span::call_site()))
+    }
+  })
+}
+fn parse_op(op: &str) -> Result<Operation> {
+  Operation::from_name(op).ok_or_else(|| {
+    RuntimeError::custom(format!("unknown attribute operation `{op}`"), Span::call_site())
+  })
 }
 
 impl crate::plugin::IntoPanda for Arc<Player> {
@@ -95,6 +136,14 @@ impl PPlayer {
   /// online mode.
   pub fn uuid(&self) -> PUUID { PUUID { inner: self.uuid } }
 
+  /// Returns a handle to this player as an entity. This can be compared with
+  /// the entities in events like `entity_attack`, to check if a player was
+  /// attacked.
+  ///
+  /// This will return an error if the player is offline. The handle changes
+  /// every time the player joins.
+  pub fn entity(&self) -> Result<PEntity> { Ok(self.inner()?.handle().into()) }
+
   /// Returns the game mode of the player.
   ///
   /// This will return "survival" if they are offline.
@@ -139,6 +188,17 @@ impl PPlayer {
       i.teleport(pos.inner, 0.0, 0.0);
     }
   }
+  /// Moves the player into another world, at the given position. The player
+  /// will see a loading screen while the new world is sent.
+  ///
+  /// This will return an error if the player is offline.
+  pub fn teleport_world(&self, world: &PWorld, pos: &PFPos) -> Result<()> {
+    if self.inner()?.teleport_world(&world.inner, pos.inner) {
+      Ok(())
+    } else {
+      Err(RuntimeError::custom("player is not online", Span::call_site()))
+    }
+  }
 
   /// Returns `true` if the player is touching the ground. This is verified by
   /// server, so the result can be trusted. The result will only change once per
@@ -146,6 +206,27 @@ impl PPlayer {
   ///
   /// Returns `false` if the player is offline.
   pub fn on_ground(&self) -> bool { self.inner().map(|p| p.on_ground()).unwrap_or(false) }
+  /// Returns `true` if the player is swimming. Only 1.13+ clients can swim.
+  ///
+  /// Returns `false` if the player is offline.
+  pub fn is_swimming(&self) -> bool { self.inner().map(|p| p.is_swimming()).unwrap_or(false) }
+  /// Returns the player's pose. This is one of `standing`, `sneaking`,
+  /// `swimming`, `fall_flying`, `spin_attack`, `sleeping`, or `dying`. Players
+  /// who are crawling through a 1 block gap are in the `swimming` pose.
+  pub fn pose(&self) -> Result<String> {
+    Ok(
+      match self.inner()?.pose() {
+        Pose::Standing => "standing",
+        Pose::Sneaking => "sneaking",
+        Pose::Swimming => "swimming",
+        Pose::FallFlying => "fall_flying",
+        Pose::SpinAttack => "spin_attack",
+        Pose::Sleeping => "sleeping",
+        Pose::Dying => "dying",
+      }
+      .into(),
+    )
+  }
 
   /// Returns the player's current position.
   ///
@@ -165,6 +246,337 @@ impl PPlayer {
     }
   }
 
+  /// Returns the value of the given attribute, after all modifiers (including
+  /// armor and held items) have been applied. The attribute should be a name
+  /// like `generic.max_health`.
+  ///
+  /// Returns an error if the attribute doesn't exist, or if the player is
+  /// offline.
+  pub fn attribute(&self, name: &str) -> Result<f64> {
+    Ok(self.inner()?.attribute(parse_attribute(name)?))
+  }
+  /// Sets the base value of the given attribute. This will stay until it is
+  /// changed again.
+  ///
+  /// Returns an error if the attribute doesn't exist, or if the player is
+  /// offline.
+  pub fn set_attribute_base(&self, name: &str, value: f64) -> Result<()> {
+    let attr = parse_attribute(name)?;
+    self.inner()?.modify_attributes(|a| a.set_base(attr, value));
+    Ok(())
+  }
+  /// Adds a modifier to the given attribute. `op` must be one of `add`,
+  /// `multiply_base`, or `multiply_total`. The returned id can be passed to
+  /// `remove_attribute_modifier` to remove this modifier.
+  ///
+  /// Returns an error if the attribute or operation doesn't exist, or if the
+  /// player is offline.
+  pub fn add_attribute_modifier(&self, name: &str, amount: f64, op: &str) -> Result<PUUID> {
+    let (attr, m) = (parse_attribute(name)?, Modifier::random(amount, parse_op(op)?));
+    self.inner()?.modify_attributes(|a| a.add_modifier(attr, m));
+    Ok(PUUID { inner: m.id })
+  }
+  /// Adds a modifier to the given attribute, which will be removed after
+  /// `ticks` ticks. See `add_attribute_modifier` for more.
+  pub fn add_temp_attribute_modifier(
+    &self,
+    name: &str,
+    amount: f64,
+    op: &str,
+    ticks: u32,
+  ) -> Result<PUUID> {
+    let (attr, m) = (parse_attribute(name)?, Modifier::random(amount, parse_op(op)?));
+    self.inner()?.modify_attributes(|a| a.add_modifier(attr, m.with_duration(ticks)));
+    Ok(PUUID { inner: m.id })
+  }
+  /// Removes a modifier that was added with `add_attribute_modifier`. Returns
+  /// `true` if the modifier existed.
+  pub fn remove_attribute_modifier(&self, name: &str, id: &PUUID) -> Result<bool> {
+    let attr = parse_attribute(name)?;
+    let mut removed = false;
+    self.inner()?.modify_attributes(|a| removed = a.remove_modifier(attr, id.inner));
+    Ok(removed)
+  }
+  /// Gives the player a potion effect for `ticks` ticks. `level` starts at 1,
+  /// so Speed II has a level of 2. Only effects that change attributes are
+  /// supported, like `speed`, `strength`, or `health_boost`.
+  ///
+  /// Returns an error if the effect doesn't exist, or if the player is
+  /// offline.
+  pub fn add_effect(&self, effect: &str, level: u32, ticks: u32) -> Result<()> {
+    let effect = Effect::from_name(effect).ok_or_else(|| {
+      RuntimeError::custom(format!("unknown effect `{effect}`"), Span::call_site())
+    })?;
+    self.inner()?.add_effect(effect, level, ticks);
+    Ok(())
+  }
+
+  /// Puts an item on cooldown for `ticks` ticks, like an ender pearl. The
+  /// player will see the cooldown over every stack of the item, and won't be
+  /// able to use the item until it is over. Setting `ticks` to 0 removes the
+  /// cooldown.
+  ///
+  /// Returns an error if the item doesn't exist, or if the player is offline.
+  pub fn set_cooldown(&self, item: &str, ticks: u32) -> Result<()> {
+    self.inner()?.set_cooldown(item_type(item)?, ticks);
+    Ok(())
+  }
+  /// Returns the number of ticks left on the cooldown for the given item. This
+  /// is 0 if the item isn't on cooldown.
+  ///
+  /// Returns an error if the item doesn't exist, or if the player is offline.
+  pub fn cooldown(&self, item: &str) -> Result<u32> { Ok(self.inner()?.cooldown(item_type(item)?)) }
+  /// Checks the cooldown with the given name. If it is still running, this
+  /// returns `false`. Otherwise, the cooldown is started for `ticks` ticks, and
+  /// this returns `true`. These cooldowns aren't shown to the player.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// on interact(event, flow) {
+  ///   if event.player.try_cooldown("dash", 100) {
+  ///     // Dash, and then wait 5 seconds before the next dash.
+  ///   }
+  /// }
+  /// ```
+  pub fn try_cooldown(&self, name: &str, ticks: u32) -> Result<bool> {
+    Ok(self.inner()?.try_cooldown(name, ticks))
+  }
+  /// Returns the number of times this player has right clicked in the last
+  /// second.
+  pub fn interact_rate(&self) -> Result<u32> { Ok(self.inner()?.interact_rate()) }
+
+  /// Returns the url of the player's skin texture. This will be empty if the
+  /// player is using a default skin.
+  ///
+  /// Returns an error if the player is offline.
+  pub fn skin_url(&self) -> Result<String> { Ok(self.textures()?.skin.unwrap_or_default()) }
+  /// Returns the url of the player's cape texture. This will be empty if the
+  /// player doesn't have a cape.
+  ///
+  /// Returns an error if the player is offline.
+  pub fn cape_url(&self) -> Result<String> { Ok(self.textures()?.cape.unwrap_or_default()) }
+  /// Returns the model of the player's skin, which is either `classic` or
+  /// `slim`.
+  ///
+  /// Returns an error if the player is offline.
+  pub fn skin_model(&self) -> Result<String> {
+    Ok(
+      match self.textures()?.model {
+        SkinModel::Classic => "classic",
+        SkinModel::Slim => "slim",
+      }
+      .into(),
+    )
+  }
+  /// Changes the player's skin. `value` and `signature` are the base64 encoded
+  /// `textures` property, which can be found from the Mojang API, or from
+  /// services like mineskin.org. Clients will not show skins that aren't
+  /// signed by Mojang.
+  ///
+  /// Other players will see the new skin immediately. This player will only
+  /// see it after they rejoin.
+  ///
+  /// Returns an error if the player is offline.
+  pub fn set_skin(&self, value: &str, signature: &str) -> Result<()> {
+    let signature = if signature.is_empty() { None } else { Some(signature.into()) };
+    self.inner()?.set_skin(Some(Skin::new(value.into(), signature)));
+    Ok(())
+  }
+  /// Changes the player's skin to be the same as `other`'s skin.
+  ///
+  /// Returns an error if either player is offline.
+  pub fn copy_skin(&self, other: &PPlayer) -> Result<()> {
+    self.inner()?.set_skin(other.inner()?.skin());
+    Ok(())
+  }
+  /// Resets the player's skin to the one from their Mojang profile.
+  ///
+  /// Returns an error if the player is offline.
+  pub fn reset_skin(&self) -> Result<()> {
+    self.inner()?.reset_skin();
+    Ok(())
+  }
+  /// Returns the mod loader the player's client is running. This is one of
+  /// `vanilla`, `forge`, or `fabric`. Fabric clients are detected from their
+  /// brand, so they will show up as `vanilla` for a moment after joining.
+  ///
+  /// Returns an error if the player is offline.
+  pub fn client_kind(&self) -> Result<String> {
+    Ok(
+      match self.inner()?.client_kind() {
+        ClientKind::Vanilla => "vanilla",
+        ClientKind::Forge => "forge",
+        ClientKind::Fabric => "fabric",
+      }
+      .into(),
+    )
+  }
+  /// Returns the brand the player's client sent, like `vanilla` or `fabric`.
+  /// This will be empty until the client sends it.
+  ///
+  /// Returns an error if the player is offline.
+  pub fn brand(&self) -> Result<String> { Ok(self.inner()?.brand().unwrap_or_default()) }
+  /// Returns the ids of the mods the player has installed. This is only
+  /// known for Forge clients, when the proxy has `forge-mod-list` enabled,
+  /// and will be empty otherwise.
+  ///
+  /// Returns an error if the player is offline.
+  pub fn mods(&self) -> Result<Vec<Var>> {
+    Ok(self.inner()?.mods().iter().map(|m| m.clone().into()).collect())
+  }
+  /// Returns `true` if the player has the given mod installed. See
+  /// [`mods`](Self::mods).
+  ///
+  /// Returns an error if the player is offline.
+  pub fn has_mod(&self, id: &str) -> Result<bool> { Ok(self.inner()?.has_mod(id)) }
+
+  /// Shows or hides one of the outer layers of the player's skin. The part is
+  /// one of `cape`, `jacket`, `left_sleeve`, `right_sleeve`, `left_pants`,
+  /// `right_pants`, or `hat`.
+  ///
+  /// Returns an error if the part doesn't exist, or if the player is offline.
+  pub fn set_skin_part(&self, part: &str, shown: bool) -> Result<()> {
+    let part = SkinParts::from_name(part).ok_or_else(|| {
+      RuntimeError::custom(format!("unknown skin part `{part}`"), Span::call_site())
+    })?;
+    let player = self.inner()?;
+    let mut parts = player.skin_parts();
+    parts.set(part, shown);
+    player.set_skin_parts(parts);
+    Ok(())
+  }
+
+  /// Disguises this player as another entity type, like `zombie`. Every other
+  /// player will see the disguise. This player will still see their own hands
+  /// and items as normal.
+  ///
+  /// Returns an error if the type is invalid, or if the player is offline.
+  /// Players cannot be disguised as other players.
+  pub fn disguise(&self, entity: &str) -> Result<()> {
+    let player = self.inner()?;
+    PWorld::from(player.world()).disguise_with(player.eid(), entity, 0, None)
+  }
+  /// Disguises this player as another entity type, but only for `viewer`.
+  /// This can be called multiple times to show the same disguise to multiple
+  /// players. Everyone else will see this player as normal.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// // Only the seekers will see `hider` as a cow.
+  /// for seeker in seekers {
+  ///   hider.disguise_for("cow", seeker)
+  /// }
+  /// ```
+  pub fn disguise_for(&self, entity: &str, viewer: &PPlayer) -> Result<()> {
+    let player = self.inner()?;
+    PWorld::from(player.world()).disguise_with(player.eid(), entity, 0, Some(viewer.uuid))
+  }
+  /// Disguises this player as a block. Other players will see a falling block
+  /// that follows this player around.
+  pub fn disguise_as_block(&self, kind: &PBlockKind) -> Result<()> {
+    let player = self.inner()?;
+    let world = player.world();
+    let id = world.block_converter().get(kind.inner).default_type().id();
+    PWorld::from(world).disguise_with(player.eid(), "falling_block", id as i32, None)
+  }
+  /// Removes this player's disguise. This does nothing if the player is not
+  /// disguised.
+  pub fn undisguise(&self) -> Result<()> {
+    let player = self.inner()?;
+    player.world().remove_disguise(player.eid());
+    Ok(())
+  }
+
+  /// Hides `other` from this player. `other` will be removed from the tab
+  /// list, and will no longer be shown in the world. `other` will still be
+  /// able to see this player.
+  ///
+  /// This does nothing if `other` is in a different world, or if either player
+  /// is offline.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// // `a` can no longer see `b`, but `b` can still see `a`.
+  /// a.hide(b)
+  /// ```
+  pub fn hide(&self, other: &PPlayer) -> Result<()> {
+    self.inner()?.hide(&other.inner()?);
+    Ok(())
+  }
+  /// Shows `other` to this player, after they were hidden with `hide`. This
+  /// does nothing if `other` isn't hidden.
+  pub fn show(&self, other: &PPlayer) -> Result<()> {
+    self.inner()?.show(&other.inner()?);
+    Ok(())
+  }
+  /// Returns `true` if this player can see `other`. This only checks if
+  /// `other` has been hidden, so this will still return `true` if `other` is
+  /// far away.
+  pub fn can_see(&self, other: &PPlayer) -> Result<bool> {
+    Ok(self.inner()?.can_see(&other.inner()?))
+  }
+
+  /// Sets the first corner of this player's selection. The selection is used
+  /// by functions like `set_selection` and `replace_selection`.
+  pub fn set_pos1(&self, pos: &PPos) -> Result<()> {
+    self.inner()?.set_pos1(pos.inner);
+    Ok(())
+  }
+  /// Sets the second corner of this player's selection.
+  pub fn set_pos2(&self, pos: &PPos) -> Result<()> {
+    self.inner()?.set_pos2(pos.inner);
+    Ok(())
+  }
+  /// Sets every block in this player's selection to the given type. This can
+  /// be undone with `undo`.
+  ///
+  /// Returns the number of blocks changed, or an error if this player hasn't
+  /// selected a region.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// p.set_pos1(Pos::new(0, 64, 0))
+  /// p.set_pos2(Pos::new(10, 70, 10))
+  /// // Fills the selection with whatever block is at 0, 60, 0.
+  /// p.set_selection(p.world().get_block(Pos::new(0, 60, 0)))
+  /// p.undo()
+  /// ```
+  pub fn set_selection(&self, ty: &PBlockType) -> Result<u64> {
+    self.inner()?.edit_selection(RegionEdit::Set(ty.inner.ty())).map_err(edit_err)
+  }
+  /// Replaces every block of kind `from` in this player's selection with
+  /// `to`. Returns the number of blocks changed.
+  pub fn replace_selection(&self, from: &PBlockKind, to: &PBlockType) -> Result<u64> {
+    self.inner()?.edit_selection(RegionEdit::Replace(from.inner, to.inner.ty())).map_err(edit_err)
+  }
+  /// Sets the four vertical sides of this player's selection to the given
+  /// type. Returns the number of blocks changed.
+  pub fn walls_selection(&self, ty: &PBlockType) -> Result<u64> {
+    self.inner()?.edit_selection(RegionEdit::Walls(ty.inner.ty())).map_err(edit_err)
+  }
+  /// Sets all six sides of this player's selection to the given type, and
+  /// fills the inside with air. Returns the number of blocks changed.
+  pub fn hollow_selection(&self, ty: &PBlockType) -> Result<u64> {
+    self.inner()?.edit_selection(RegionEdit::Hollow(ty.inner.ty())).map_err(edit_err)
+  }
+  /// Undoes this player's last edit. Returns `false` if there was nothing to
+  /// undo.
+  pub fn undo(&self) -> Result<bool> { Ok(self.inner()?.undo().map_err(edit_err)?.is_some()) }
+  /// Redoes the last edit this player undid. Returns `false` if there was
+  /// nothing to redo.
+  pub fn redo(&self) -> Result<bool> { Ok(self.inner()?.redo().map_err(edit_err)?.is_some()) }
+  /// Adds the given changes to this player's history, so that the player can
+  /// undo them with `/undo`. This should be called after the blocks have been
+  /// changed.
+  pub fn remember(&self, changes: &PChangeSet) -> Result<()> {
+    self.inner()?.remember(changes.inner.lock().clone());
+    Ok(())
+  }
+
   /// Sends the given chat message to a player. This accepts exactly one
   /// argument, which can be any type. If it is a `PChat`, then it will be
   /// formatted correctly. Anything else will show up with debug formatting.
@@ -224,22 +636,21 @@ impl PPlayer {
   /// blocks, access other players, and modify entities.
   ///
   /// This will return an error if the player is offline.
-  pub fn world(&self) -> Result<PWorld> { Ok(self.inner()?.world().clone().into()) }
+  pub fn world(&self) -> Result<PWorld> { Ok(self.inner()?.world().into()) }
 
-  /// Switches the player to a new server. If the server is found, the player
-  /// will be disconnected after this call. If the server is not found, an error
-  /// will be returned.
+  /// Switches the player to a new server. `ip` can be an ip address or a
+  /// hostname. Hostnames are resolved by the proxy, so this will never block.
+  /// If the server is found, the player will be disconnected after this call.
+  /// If the server is not found, the player will stay on this server.
   ///
   /// This will do nothing if the player is offline.
-  pub fn switch_to(&self, ip: &str) -> Result<()> {
-    let ips: Vec<SocketAddr> = ip
-      .to_socket_addrs()
-      .map_err(|e| RuntimeError::custom(format!("invalid ip '{ip}': {e}"), Span::call_site()))?
-      .collect();
+  pub fn switch_to(&self, ip: &str) {
     if let Ok(i) = self.inner() {
-      i.switch_to(SwitchMode::Loading, ips);
+      match ip.parse::<SocketAddr>() {
+        Ok(addr) => i.switch_to(SwitchMode::Loading, vec![addr]),
+        Err(_) => i.switch_to_host(SwitchMode::Loading, ip.into()),
+      }
     }
-    Ok(())
   }
 
   /// Shows an inventory to the player.
@@ -430,14 +841,66 @@ impl PTeam {
     Ok(())
   }
 
-  /// Adds the player to this team.
+  /// Sets the text shown before the name of every player on this team.
+  pub fn set_prefix(&self, prefix: &PChat) {
+    self.inner.lock().set_prefix(prefix.inner.lock().clone());
+  }
+  /// Sets the text shown after the name of every player on this team.
+  pub fn set_postfix(&self, postfix: &PChat) {
+    self.inner.lock().set_postfix(postfix.inner.lock().clone());
+  }
+  /// Sets the name of this team, as shown to clients.
+  pub fn set_display_name(&self, name: &PChat) {
+    self.inner.lock().set_display_name(name.inner.lock().clone());
+  }
+  /// If `friendly_fire` is false, players on this team will not be able to
+  /// attack each other.
+  pub fn set_friendly_fire(&self, friendly_fire: bool) {
+    self.inner.lock().set_friendly_fire(friendly_fire);
+  }
+  /// If `see_invisible` is true, players on this team will be able to see
+  /// invisible teammates.
+  pub fn set_see_invisible(&self, see_invisible: bool) {
+    self.inner.lock().set_see_invisible(see_invisible);
+  }
+  /// Sets who can see the name tags of players on this team. This is one of
+  /// `always`, `never`, `own_team` (hidden from teammates), or `other_teams`
+  /// (hidden from other teams).
+  pub fn set_name_tag_visibility(&self, rule: &str) -> Result<()> {
+    self.inner.lock().set_name_tag_rule(parse_team_rule(rule)?);
+    Ok(())
+  }
+  /// Sets who players on this team will push. This is one of `always`,
+  /// `never`, `own_team` (only push teammates), or `other_teams` (only push
+  /// other teams).
+  pub fn set_collision_rule(&self, rule: &str) -> Result<()> {
+    self.inner.lock().set_collision_rule(parse_team_rule(rule)?);
+    Ok(())
+  }
+
+  /// Adds the player to this team. If the player is on another team, they
+  /// will be removed from that team first.
   ///
   /// This will do nothing if the player is offline.
   pub fn add_player(&self, player: &PPlayer) {
     if let Ok(i) = player.inner() {
+      let old = i.world().world_manager().team_of(i.id());
+      if let Some(old) = old.filter(|old| !Arc::ptr_eq(old, &self.inner)) {
+        old.lock().remove(&i);
+      }
       self.inner.lock().add(i.as_ref());
     }
   }
+  /// Removes the player from this team. Returns `false` if the player wasn't
+  /// on this team, or if the player is offline.
+  pub fn remove_player(&self, player: &PPlayer) -> bool {
+    match player.inner() {
+      Ok(i) => self.inner.lock().remove(&i),
+      Err(_) => false,
+    }
+  }
+  /// Returns `true` if the given player is on this team.
+  pub fn has_player(&self, player: &PPlayer) -> bool { self.inner.lock().contains(player.uuid) }
 }
 
 #[derive(Clone, Debug)]
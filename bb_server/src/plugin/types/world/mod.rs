@@ -1,12 +1,26 @@
 use super::{
   block::{PBlockKind, PBlockType},
-  item::PStack,
-  util::{PFPos, PPos},
+  chat::PChat,
+  entity::PEntity,
+  item::{PStack, PTrade},
+  util::{PChunkPos, PFPos, PPos},
 };
-use crate::{entity, world::World};
-use bb_common::{math::Pos, metadata::Metadata, net::cb::SoundCategory};
+use crate::{
+  block::SpawnerData,
+  entity,
+  entity::{ArmorStandFlag, ArmorStandPart, EntityData, EntityHandle},
+  world::{
+    schematic::{Mirror, Rotation, Schematic},
+    ChangeSet, Disguise, World,
+  },
+};
+use bb_common::{math::Pos, metadata::Metadata, net::cb::SoundCategory, util::UUID};
 use bb_server_macros::define_ty;
-use panda::{parse::token::Span, runtime::RuntimeError};
+use panda::{
+  parse::token::Span,
+  runtime::{RuntimeError, Var},
+};
+use parking_lot::Mutex;
 use std::{fmt, sync::Arc};
 
 pub mod gen;
@@ -21,6 +35,62 @@ impl PWorld {
       RuntimeError::custom(format!("invalid position {}: {}", p.pos, p.msg), Span::call_site())
     })
   }
+  /// Returns an error if the world is locked. This should be checked before
+  /// changing any blocks.
+  pub fn check_unlocked(&self) -> Result<(), RuntimeError> {
+    if self.inner.is_locked() {
+      Err(RuntimeError::custom("this world is locked", Span::call_site()))
+    } else {
+      Ok(())
+    }
+  }
+  /// Returns the entity for the given handle, or an error if it doesn't exist,
+  /// if the handle is stale, or if it is a player.
+  pub fn entity(&self, handle: EntityHandle) -> Result<Arc<EntityData>, RuntimeError> {
+    self
+      .inner
+      .entities()
+      .get_ent_handle(handle)
+      .cloned()
+      .map_err(|e| RuntimeError::custom(e.to_string(), Span::call_site()))
+  }
+  /// Returns an error if the handle doesn't refer to an entity or player in
+  /// this world.
+  pub fn check_handle(&self, handle: EntityHandle) -> Result<(), RuntimeError> {
+    match self.inner.entities().get_handle(handle) {
+      Ok(_) => Ok(()),
+      Err(e) => Err(RuntimeError::custom(e.to_string(), Span::call_site())),
+    }
+  }
+  /// Disguises the given entity or player. If `viewer` is set, the viewer is
+  /// added to the existing disguise (if it is the same type), so that calling
+  /// this multiple times will show the disguise to multiple players.
+  pub fn disguise_with(
+    &self,
+    eid: i32,
+    entity: &str,
+    data: i32,
+    viewer: Option<UUID>,
+  ) -> Result<(), RuntimeError> {
+    let ty = entity
+      .parse::<entity::Type>()
+      .map_err(|err| RuntimeError::custom(err.to_string(), Span::call_site()))?;
+    let mut disguise = match (self.inner.disguise(eid), viewer) {
+      (Some(d), Some(_)) if d.ty() == ty && d.viewers().is_some() => d,
+      _ => Disguise::new(ty).with_data(data),
+    };
+    if let Some(viewer) = viewer {
+      disguise = disguise.show_to(viewer);
+    }
+    if self.inner.set_disguise(eid, disguise) {
+      Ok(())
+    } else {
+      Err(RuntimeError::custom(
+        format!("cannot disguise entity {eid} as `{entity}`"),
+        Span::call_site(),
+      ))
+    }
+  }
 }
 
 /// A Minecraft world. This stores all of the information about blocks,
@@ -39,7 +109,7 @@ impl PWorld {
     },
   }
   /// Sets a single block in the world. This will return an error if the block
-  /// is outside of the world.
+  /// is outside of the world, or if the world is locked.
   ///
   /// If you need to set multiple blocks at all, you should always use
   /// `fill_rect` instead. It is faster in every situation except for single
@@ -50,17 +120,19 @@ impl PWorld {
   /// render distance.
   pub fn set_block(&self, pos: &PPos, kind: &PBlockKind) -> Result<(), RuntimeError> {
     self.check_pos(pos.inner)?;
+    self.check_unlocked()?;
     self.inner.set_kind(pos.inner, kind.inner).unwrap();
     Ok(())
   }
   /// Tries to set a block at the given position. This will return `false` if
-  /// the block is outside the world, and `true` if the block is in the world.
+  /// the block is outside the world or the world is locked, and `true` if the
+  /// block was set.
   pub fn try_set_block(&self, pos: &PPos, kind: &PBlockKind) -> bool {
-    self.inner.set_kind(pos.inner, kind.inner).is_ok()
+    matches!(self.inner.set_kind(pos.inner, kind.inner), Ok(true))
   }
 
   /// Fills a rectangle of blocks in the world. This will return an error if the
-  /// min or max are outside of the world.
+  /// min or max are outside of the world, or if the world is locked.
   ///
   /// This function will do everything you want when filling blocks.. It will
   /// update the blocks stored in the world, and send block updates to all
@@ -68,10 +140,202 @@ impl PWorld {
   pub fn fill_rect(&self, min: &PPos, max: &PPos, kind: &PBlockKind) -> Result<(), RuntimeError> {
     self.check_pos(min.inner)?;
     self.check_pos(max.inner)?;
+    self.check_unlocked()?;
     self.inner.fill_rect_kind(min.inner, max.inner, kind.inner).unwrap();
     Ok(())
   }
 
+  /// Pastes the schematic at `path` into the world. Both Sponge `.schem` and
+  /// legacy `.schematic` files can be pasted. The schematic is mirrored along
+  /// `mirror` (one of `none`, `x`, or `z`), and then rotated clockwise around
+  /// `pos` by `rotation` degrees, which must be a multiple of 90. If
+  /// `skip_air` is true, air in the schematic will not replace any blocks.
+  ///
+  /// This returns the number of blocks changed, or an error if the file
+  /// couldn't be read, if any of the blocks are outside of the world, or if
+  /// the world is locked.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// world.paste_schematic("schematics/house.schem", Pos::new(0, 64, 0), 90, "none", true)
+  /// ```
+  pub fn paste_schematic(
+    &self,
+    path: &str,
+    pos: &PPos,
+    rotation: i32,
+    mirror: &str,
+    skip_air: bool,
+  ) -> Result<u64, RuntimeError> {
+    self.check_unlocked()?;
+    let rotation = Rotation::from_degrees(rotation).ok_or_else(|| {
+      RuntimeError::custom(format!("invalid rotation {rotation}"), Span::call_site())
+    })?;
+    let mirror = Mirror::from_name(mirror).ok_or_else(|| {
+      RuntimeError::custom(format!("unknown mirror `{mirror}`"), Span::call_site())
+    })?;
+    let schem = Schematic::read_file(path, self.inner.block_converter()).map_err(|e| {
+      RuntimeError::custom(format!("could not load schematic {path}: {e}"), Span::call_site())
+    })?;
+    self.inner.paste_schematic(&schem, pos.inner, rotation, mirror, skip_air).map_err(|p| {
+      RuntimeError::custom(format!("invalid position {}: {}", p.pos, p.msg), Span::call_site())
+    })
+  }
+  /// Saves the blocks between `min` and `max` to a schematic file at `path`.
+  /// If the path ends in `.schematic`, the legacy format is used. Otherwise,
+  /// this writes a Sponge `.schem` file. Pasting the schematic at `origin`
+  /// will place the blocks back where they were.
+  pub fn save_schematic(
+    &self,
+    path: &str,
+    min: &PPos,
+    max: &PPos,
+    origin: &PPos,
+  ) -> Result<(), RuntimeError> {
+    self.check_pos(min.inner)?;
+    self.check_pos(max.inner)?;
+    let schem = self.inner.copy_schematic(min.inner, max.inner, origin.inner).unwrap();
+    schem.write_file(path, self.inner.block_converter()).map_err(|e| {
+      RuntimeError::custom(format!("could not save schematic {path}: {e}"), Span::call_site())
+    })
+  }
+
+  /// Copies the blocks between `min` and `max`, and stores them as a named
+  /// structure. Structures are kept in memory, and are shared between all
+  /// worlds. Pasting the structure at `origin` will place the blocks back
+  /// where they were.
+  pub fn save_structure(
+    &self,
+    name: &str,
+    min: &PPos,
+    max: &PPos,
+    origin: &PPos,
+  ) -> Result<(), RuntimeError> {
+    self.check_pos(min.inner)?;
+    self.check_pos(max.inner)?;
+    self.inner.save_structure(name, min.inner, max.inner, origin.inner).unwrap();
+    Ok(())
+  }
+  /// Pastes a named structure, like a structure block would. This mirrors and
+  /// rotates the structure like `paste_schematic`. Structure voids are
+  /// skipped, and every other block is only placed `integrity` of the time
+  /// (between 0 and 1). The same `seed` will always place the same blocks.
+  ///
+  /// This returns the number of blocks changed, or an error if there is no
+  /// structure with this name, if any of the blocks are outside of the
+  /// world, or if the world is locked.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// world.save_structure("arena", Pos::new(0, 60, 0), Pos::new(31, 80, 31), Pos::new(0, 60, 0))
+  /// // Later, to reset the arena:
+  /// world.paste_structure("arena", Pos::new(0, 60, 0), 0, "none", 1.0, 0)
+  /// ```
+  pub fn paste_structure(
+    &self,
+    name: &str,
+    pos: &PPos,
+    rotation: i32,
+    mirror: &str,
+    integrity: f32,
+    seed: i64,
+  ) -> Result<u64, RuntimeError> {
+    self.check_unlocked()?;
+    let rotation = Rotation::from_degrees(rotation).ok_or_else(|| {
+      RuntimeError::custom(format!("invalid rotation {rotation}"), Span::call_site())
+    })?;
+    let mirror = Mirror::from_name(mirror).ok_or_else(|| {
+      RuntimeError::custom(format!("unknown mirror `{mirror}`"), Span::call_site())
+    })?;
+    let schem = self.inner.world_manager().structure(name).ok_or_else(|| {
+      RuntimeError::custom(format!("no structure named {name}"), Span::call_site())
+    })?;
+    self.inner.paste_structure(&schem, pos.inner, rotation, mirror, integrity, seed as u64).map_err(
+      |p| RuntimeError::custom(format!("invalid position {}: {}", p.pos, p.msg), Span::call_site()),
+    )
+  }
+  /// Reads a schematic or structure file, and stores it as a named structure.
+  /// Sponge `.schem`, legacy `.schematic`, and vanilla `.nbt` files are
+  /// supported.
+  pub fn load_structure(&self, name: &str, path: &str) -> Result<(), RuntimeError> {
+    self.inner.world_manager().load_structure(name, path).map_err(|e| {
+      RuntimeError::custom(format!("could not load structure {path}: {e}"), Span::call_site())
+    })
+  }
+  /// Writes a named structure to a file. If the path ends in `.nbt`, this
+  /// writes a vanilla structure. See `save_schematic` for the other formats.
+  pub fn write_structure(&self, name: &str, path: &str) -> Result<(), RuntimeError> {
+    match self.inner.world_manager().write_structure(name, path) {
+      Ok(true) => Ok(()),
+      Ok(false) => {
+        Err(RuntimeError::custom(format!("no structure named {name}"), Span::call_site()))
+      }
+      Err(e) => Err(RuntimeError::custom(
+        format!("could not save structure {path}: {e}"),
+        Span::call_site(),
+      )),
+    }
+  }
+
+  /// Copies every chunk that contains part of the region between `min` and
+  /// `max`, and stores them as a named snapshot. Restoring a snapshot is much
+  /// faster than pasting a structure, so this is the best way to reset a
+  /// minigame arena between rounds. Entities are not saved.
+  pub fn snapshot_region(&self, name: &str, min: &PPos, max: &PPos) -> Result<(), RuntimeError> {
+    self.check_pos(min.inner)?;
+    self.check_pos(max.inner)?;
+    let snapshot = self.inner.snapshot_region(min.inner, max.inner).unwrap();
+    self.inner.add_snapshot(name, snapshot);
+    Ok(())
+  }
+  /// Restores a snapshot saved with `snapshot_region`. This returns an error
+  /// if there is no snapshot with this name, or if the world is locked.
+  pub fn restore_snapshot(&self, name: &str) -> Result<(), RuntimeError> {
+    self.check_unlocked()?;
+    let snapshot = self.inner.snapshot(name).ok_or_else(|| {
+      RuntimeError::custom(format!("no snapshot named {name}"), Span::call_site())
+    })?;
+    self.inner.restore_snapshot(&snapshot);
+    Ok(())
+  }
+  /// Removes a snapshot saved with `snapshot_region`. Returns `false` if there
+  /// was no snapshot with this name.
+  pub fn remove_snapshot(&self, name: &str) -> bool { self.inner.remove_snapshot(name) }
+
+  /// Fills the nether portal frame around `pos` with portal blocks, like
+  /// using flint and steel. Returns `false` if there is no valid frame.
+  ///
+  /// This will return an error if the world is locked.
+  pub fn light_portal(&self, pos: &PPos) -> Result<bool, RuntimeError> {
+    self.check_unlocked()?;
+    Ok(self.inner.light_portal(pos.inner))
+  }
+
+  /// Adds a named ticket to the given chunk, which keeps it loaded, even if
+  /// no players are nearby. The ticket is removed after `ttl` ticks, or it
+  /// lasts until it is removed if `ttl` is 0. Adding a ticket with the same
+  /// name again replaces it.
+  pub fn add_chunk_ticket(&self, chunk: &PChunkPos, name: &str, ttl: i32) {
+    let ttl = if ttl > 0 { Some(ttl as u32) } else { None };
+    self.inner.add_chunk_ticket(chunk.inner, name, ttl);
+  }
+  /// Removes a ticket added with `add_chunk_ticket`. Returns `false` if the
+  /// chunk has no ticket with this name.
+  pub fn remove_chunk_ticket(&self, chunk: &PChunkPos, name: &str) -> bool {
+    self.inner.remove_chunk_ticket(chunk.inner, name)
+  }
+  /// Sets if the given chunk is force loaded, like `/forceload`. Forced chunks
+  /// stay loaded across restarts. Returns `false` if nothing changed.
+  pub fn set_force_loaded(&self, chunk: &PChunkPos, forced: bool) -> bool {
+    self.inner.set_force_loaded(chunk.inner, forced)
+  }
+  /// Returns `true` if the given chunk is force loaded.
+  pub fn is_force_loaded(&self, chunk: &PChunkPos) -> bool {
+    self.inner.is_force_loaded(chunk.inner)
+  }
+
   /// Returns the block type at the given position.
   ///
   /// This will return an error if the position is outside the world.
@@ -80,6 +344,58 @@ impl PWorld {
     Ok(self.inner.get_block(pos.inner).unwrap().into())
   }
 
+  /// Sets the block at the given position to a mob spawner, which will spawn
+  /// the given entity. The entity should be a name like `zombie`. This will
+  /// return an error if the position is outside the world, if the entity
+  /// does not exist, or if the world is locked.
+  pub fn set_spawner(&self, pos: &PPos, entity: &str) -> Result<(), RuntimeError> {
+    self.check_pos(pos.inner)?;
+    self.check_unlocked()?;
+    entity
+      .parse::<entity::Type>()
+      .map_err(|err| RuntimeError::custom(err.to_string(), Span::call_site()))?;
+    let data = SpawnerData { entity: entity.into(), ..Default::default() };
+    self.inner.set_spawner(pos.inner, data).unwrap();
+    Ok(())
+  }
+  /// Changes how often the spawner at the given position spawns entities.
+  /// After spawning, the spawner will wait a random number of ticks between
+  /// `min` and `max` before spawning again. This will return an error if there
+  /// is no spawner at the given position, or if the world is locked.
+  pub fn set_spawner_delay(&self, pos: &PPos, min: i32, max: i32) -> Result<(), RuntimeError> {
+    self.check_pos(pos.inner)?;
+    self.check_unlocked()?;
+    let found = self
+      .inner
+      .modify_spawner(pos.inner, |data| {
+        data.min_delay = min.clamp(0, i16::MAX.into()) as i16;
+        data.max_delay = max.clamp(0, i16::MAX.into()) as i16;
+      })
+      .unwrap();
+    if found {
+      Ok(())
+    } else {
+      Err(RuntimeError::custom(format!("no spawner at {}", pos.inner), Span::call_site()))
+    }
+  }
+  /// Returns the entity that the spawner at the given position will spawn.
+  /// This will return an error if there is no spawner at the given position.
+  /// If the spawner is empty, this returns an empty string.
+  pub fn spawner_entity(&self, pos: &PPos) -> Result<String, RuntimeError> {
+    self.check_pos(pos.inner)?;
+    match self.inner.spawner(pos.inner).unwrap() {
+      Some(data) => Ok(data.entity),
+      None => Err(RuntimeError::custom(format!("no spawner at {}", pos.inner), Span::call_site())),
+    }
+  }
+
+  /// Returns the seed of this world. This is set with `world.seed` in the
+  /// server config.
+  pub fn seed(&self) -> i64 { self.inner.seed() as i64 }
+  /// Returns a seed derived from the world seed and `name`. Use this for any
+  /// random numbers that should be the same every time the world is loaded.
+  pub fn seed_for(&self, name: &str) -> i64 { self.inner.seed_for(name) as i64 }
+
   /// Summons a dropped item at the given position.
   pub fn summon_item(&self, pos: &PFPos, stack: &PStack) {
     let mut meta = Metadata::new();
@@ -87,6 +403,219 @@ impl PWorld {
     self.inner.summon_meta(entity::Type::Item, pos.inner, meta);
   }
 
+  /// Spawns floating text at the given position. This returns the id of the
+  /// text, which can be used to change or remove the text later.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// text = world.spawn_text(FPos::new(0.5, 65.0, 0.5), Chat::new("Welcome!"))
+  /// world.set_text(text, Chat::new("Goodbye!"))
+  /// world.remove_entity(text)
+  /// ```
+  pub fn spawn_text(&self, pos: &PFPos, text: &PChat) -> PEntity {
+    self.inner.spawn_text(pos.inner, text.inner.lock().clone()).into()
+  }
+  /// Changes the text of floating text created with `spawn_text`. This will
+  /// return an error if the entity doesn't exist.
+  pub fn set_text(&self, entity: &PEntity, text: &PChat) -> Result<(), RuntimeError> {
+    self.entity(entity.inner)?.set_custom_name(Some(text.inner.lock().clone()));
+    Ok(())
+  }
+  /// Removes the given entity from the world. Returns `false` if the entity
+  /// doesn't exist, or if it has already been removed.
+  pub fn remove_entity(&self, entity: &PEntity) -> bool {
+    self.entity(entity.inner).is_ok() && self.inner.remove_entity(entity.inner.eid)
+  }
+
+  /// Spawns an armor stand at the given position, and returns its id. The
+  /// armor stand can then be changed with functions like
+  /// `set_armor_stand_pose` and `set_equipment`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// stand = world.spawn_armor_stand(FPos::new(0.5, 65.0, 0.5))
+  /// world.set_armor_stand_flag(stand, "arms", true)
+  /// world.set_armor_stand_pose(stand, "right_arm", -90.0, 0.0, 0.0)
+  /// world.set_equipment(stand, "main_hand", Stack::new("diamond_sword"))
+  /// ```
+  pub fn spawn_armor_stand(&self, pos: &PFPos) -> PEntity {
+    self.inner.summon_handle(entity::Type::ArmorStand, pos.inner).into()
+  }
+  /// Rotates part of an armor stand. The part is one of `head`, `body`,
+  /// `left_arm`, `right_arm`, `left_leg`, or `right_leg`. The angles are in
+  /// degrees.
+  pub fn set_armor_stand_pose(
+    &self,
+    entity: &PEntity,
+    part: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+  ) -> Result<(), RuntimeError> {
+    let part = ArmorStandPart::from_name(part).ok_or_else(|| {
+      RuntimeError::custom(format!("unknown armor stand part `{part}`"), Span::call_site())
+    })?;
+    self.entity(entity.inner)?.set_armor_stand_pose(part, x, y, z);
+    Ok(())
+  }
+  /// Sets or clears an armor stand flag. The flag is one of `small`, `arms`,
+  /// `no_base_plate`, or `marker`.
+  pub fn set_armor_stand_flag(
+    &self,
+    entity: &PEntity,
+    flag: &str,
+    value: bool,
+  ) -> Result<(), RuntimeError> {
+    let flag = ArmorStandFlag::from_name(flag).ok_or_else(|| {
+      RuntimeError::custom(format!("unknown armor stand flag `{flag}`"), Span::call_site())
+    })?;
+    self.entity(entity.inner)?.set_armor_stand_flag(flag, value);
+    Ok(())
+  }
+  /// Makes the given entity invisible. Its equipment and name will still be
+  /// shown.
+  pub fn set_invisible(&self, entity: &PEntity, invisible: bool) -> Result<(), RuntimeError> {
+    self.entity(entity.inner)?.set_invisible(invisible);
+    Ok(())
+  }
+  /// If `no_gravity` is true, the given entity will no longer fall.
+  pub fn set_no_gravity(&self, entity: &PEntity, no_gravity: bool) -> Result<(), RuntimeError> {
+    self.entity(entity.inner)?.set_no_gravity(no_gravity);
+    Ok(())
+  }
+  /// Sets the item held or worn by the given entity. The slot is one of
+  /// `main_hand`, `off_hand`, `helmet`, `chestplate`, `leggings`, or `boots`.
+  pub fn set_equipment(
+    &self,
+    entity: &PEntity,
+    slot: &str,
+    stack: &PStack,
+  ) -> Result<(), RuntimeError> {
+    let slot = entity::slot_from_name(slot).ok_or_else(|| {
+      RuntimeError::custom(format!("unknown equipment slot `{slot}`"), Span::call_site())
+    })?;
+    self.entity(entity.inner)?.set_equipment(slot, stack.inner.clone());
+    Ok(())
+  }
+
+  /// Spawns an entity of the given type, like `chicken`, at the given
+  /// position, and returns its id. This returns an error if the type is
+  /// invalid, or if it is `player`.
+  pub fn spawn_entity(&self, ty: &str, pos: &PFPos) -> Result<PEntity, RuntimeError> {
+    let ty = ty
+      .parse::<entity::Type>()
+      .map_err(|err| RuntimeError::custom(err.to_string(), Span::call_site()))?;
+    if ty == entity::Type::Player {
+      return Err(RuntimeError::custom("cannot spawn a player", Span::call_site()));
+    }
+    Ok(self.inner.summon_handle(ty, pos.inner).into())
+  }
+
+  /// Makes `passenger` ride `vehicle`. Both can be entities or players.
+  /// Passengers can be stacked, so a player can ride a chicken which is
+  /// riding a boat. If `vehicle` already has passengers, `passenger` is added
+  /// after them. If `passenger` is already riding something, it gets off
+  /// first.
+  ///
+  /// This returns an error if either entity doesn't exist, or if `vehicle` is
+  /// riding `passenger`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// boat = world.spawn_entity("boat", FPos::new(0.5, 65.0, 0.5))
+  /// chicken = world.spawn_entity("chicken", FPos::new(0.5, 65.0, 0.5))
+  /// world.mount(chicken, boat)
+  /// world.mount(player.entity(), chicken)
+  /// ```
+  pub fn mount(&self, passenger: &PEntity, vehicle: &PEntity) -> Result<(), RuntimeError> {
+    self.check_handle(passenger.inner)?;
+    self.check_handle(vehicle.inner)?;
+    if self.inner.mount(passenger.inner.eid, vehicle.inner.eid) {
+      Ok(())
+    } else {
+      Err(RuntimeError::custom("an entity cannot ride itself", Span::call_site()))
+    }
+  }
+  /// Makes the given entity stop riding anything. Anything riding it will
+  /// keep riding it. Returns `false` if it wasn't riding anything.
+  pub fn dismount(&self, entity: &PEntity) -> Result<bool, RuntimeError> {
+    self.check_handle(entity.inner)?;
+    Ok(self.inner.dismount(entity.inner.eid))
+  }
+  /// Returns the entities riding the given entity. The first one is the one
+  /// controlling it.
+  pub fn passengers(&self, entity: &PEntity) -> Result<Vec<Var>, RuntimeError> {
+    self.check_handle(entity.inner)?;
+    let entities = self.inner.entities();
+    Ok(
+      self
+        .inner
+        .passengers(entity.inner.eid)
+        .into_iter()
+        .filter_map(|eid| entities.get(eid).map(|e| PEntity::from(e.handle()).into()))
+        .collect(),
+    )
+  }
+  /// Returns the entity the given entity is riding, or `None` if it isn't
+  /// riding anything.
+  pub fn vehicle(&self, entity: &PEntity) -> Result<Var, RuntimeError> {
+    self.check_handle(entity.inner)?;
+    let entities = self.inner.entities();
+    Ok(
+      self
+        .inner
+        .vehicle(entity.inner.eid)
+        .and_then(|eid| entities.get(eid))
+        .map(|e| PEntity::from(e.handle()).into())
+        .unwrap_or(Var::None),
+    )
+  }
+
+  /// Spawns a villager at the given position, and returns its id. Trades can
+  /// be added to it with `add_trade`.
+  pub fn spawn_villager(&self, pos: &PFPos) -> PEntity {
+    self.inner.summon_handle(entity::Type::Villager, pos.inner).into()
+  }
+  /// Adds a trade to the given entity. Any entity with trades will open a
+  /// merchant window when a player right clicks on it.
+  pub fn add_trade(&self, entity: &PEntity, trade: &PTrade) -> Result<(), RuntimeError> {
+    let ent = self.entity(entity.inner)?;
+    let mut trades = ent.trades();
+    trades.push(trade.inner.clone());
+    ent.set_trades(trades);
+    Ok(())
+  }
+  /// Removes all the trades from the given entity. Right clicking on it will
+  /// no longer open a merchant window.
+  pub fn clear_trades(&self, entity: &PEntity) -> Result<(), RuntimeError> {
+    self.entity(entity.inner)?.set_trades(vec![]);
+    Ok(())
+  }
+
+  /// Disguises the given entity as another entity type, like `zombie`. Every
+  /// player will see the disguise, but the entity will still behave the same
+  /// on the server. This will return an error if the entity doesn't exist, or
+  /// if the type is invalid. Entities cannot be disguised as players.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// stand = world.spawn_armor_stand(FPos::new(0.5, 65.0, 0.5))
+  /// world.disguise(stand, "cow")
+  /// ```
+  pub fn disguise(&self, entity: &PEntity, ty: &str) -> Result<(), RuntimeError> {
+    self.check_handle(entity.inner)?;
+    self.disguise_with(entity.inner.eid, ty, 0, None)
+  }
+  /// Removes the disguise from the given entity. Returns `false` if the entity
+  /// wasn't disguised, or if it no longer exists.
+  pub fn undisguise(&self, entity: &PEntity) -> bool {
+    self.check_handle(entity.inner).is_ok() && self.inner.remove_disguise(entity.inner.eid)
+  }
+
   /// Plays the given sound at the given positions. All nearby players will be
   /// able to hear it.
   pub fn play_sound(
@@ -128,3 +657,47 @@ impl PWorld {
   /// saved.
   pub fn save(&self) { self.inner.save(); }
 }
+
+impl Default for PChangeSet {
+  fn default() -> Self { PChangeSet::new() }
+}
+
+/// A list of block changes. Plugins can use this to add their own edits to a
+/// player's history, so that the player can undo them with `/undo`.
+///
+/// # Example
+///
+/// ```
+/// pos = Pos::new(0, 64, 0)
+/// old = world.get_block(pos)
+/// world.set_block(pos, block::Kind::from_s("stone"))
+///
+/// changes = ChangeSet::new()
+/// changes.add(pos, old, world.get_block(pos))
+/// player.remember(changes)
+/// ```
+#[define_ty]
+impl PChangeSet {
+  info! {
+    wrap: Arc<Mutex<ChangeSet>>,
+
+    panda: {
+      path: "bamboo::world::ChangeSet",
+    },
+    python: {
+      class: "ChangeSet",
+    },
+  }
+
+  /// Creates an empty change set.
+  pub fn new() -> PChangeSet { PChangeSet { inner: Arc::new(Mutex::new(ChangeSet::new())) } }
+  /// Adds a change to this set. `old` is the block before the change, and
+  /// `new` is the block after the change.
+  pub fn add(&self, pos: &PPos, old: &PBlockType, new: &PBlockType) {
+    self.inner.lock().push(pos.inner, old.inner.ty().id(), new.inner.ty().id());
+  }
+  /// Returns the number of changes in this set.
+  pub fn len(&self) -> u32 { self.inner.lock().len() as u32 }
+  /// Returns `true` if there are no changes in this set.
+  pub fn is_empty(&self) -> bool { self.inner.lock().is_empty() }
+}
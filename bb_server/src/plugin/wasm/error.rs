@@ -0,0 +1,149 @@
+//! Errors returned to wasm plugins. Host functions that can fail return a
+//! [`CError`] or a [`CResult`], so that plugins can tell why a call failed.
+
+use super::{Env, FromFfi, ToFfi};
+use crate::{
+  entity::{EntityData, EntityHandle},
+  player::Player,
+  world::{EditError, HandleError, World},
+};
+use bb_common::{math::PosError, util::UUID};
+use bb_ffi::{Array, CError, CErrorCode, CResult, ValueType, WasmPtr, CUUID};
+use std::{mem, ptr, sync::Arc};
+
+/// An error from a host function. This is converted to a [`CError`] before it
+/// is passed to the plugin.
+#[derive(Debug)]
+pub struct FfiError {
+  pub code:    CErrorCode,
+  pub message: String,
+}
+
+impl FfiError {
+  pub fn new(code: CErrorCode, message: impl Into<String>) -> Self {
+    FfiError { code, message: message.into() }
+  }
+  /// Creates an [`InvalidArgument`](CErrorCode::InvalidArgument) error.
+  pub fn invalid(message: impl Into<String>) -> Self {
+    FfiError::new(CErrorCode::InvalidArgument, message)
+  }
+}
+
+impl From<PosError> for FfiError {
+  fn from(e: PosError) -> Self { FfiError::new(CErrorCode::InvalidPos, e.to_string()) }
+}
+impl From<EditError> for FfiError {
+  fn from(e: EditError) -> Self {
+    match e {
+      EditError::Pos(e) => e.into(),
+      EditError::Locked => FfiError::new(CErrorCode::Locked, e.to_string()),
+      EditError::Overlap => FfiError::invalid(e.to_string()),
+      EditError::NoSelection => FfiError::new(CErrorCode::NoSelection, e.to_string()),
+    }
+  }
+}
+
+impl From<HandleError> for FfiError {
+  fn from(e: HandleError) -> Self {
+    match e {
+      HandleError::Stale(_) => FfiError::new(CErrorCode::StaleEntity, e.to_string()),
+      HandleError::Missing(_) | HandleError::Player(_) => {
+        FfiError::new(CErrorCode::NoEntity, e.to_string())
+      }
+    }
+  }
+}
+
+impl ToFfi for FfiError {
+  type Ffi = CError;
+
+  fn to_ffi(&self, env: &Env) -> CError {
+    CError { code: self.code as i32, message: self.message.as_str().to_ffi(env) }
+  }
+}
+
+impl Env<'_> {
+  /// Runs `f`, and returns a pointer to the error it returned. If `f`
+  /// succeeded, this returns null.
+  pub fn status(&self, f: impl FnOnce() -> Result<(), FfiError>) -> u32 {
+    match f() {
+      Ok(()) => 0,
+      Err(e) => {
+        let cerr = e.to_ffi(self);
+        self.malloc_store(cerr).offset()
+      }
+    }
+  }
+  /// Runs `f`, and returns a pointer to a [`CResult`] with the value or error
+  /// it returned.
+  pub fn result<T: ValueType>(&self, f: impl FnOnce() -> Result<T, FfiError>) -> u32 {
+    let cres = match f() {
+      Ok(v) => CResult::ok(v),
+      Err(e) => CResult::err(e.to_ffi(self)),
+    };
+    self.malloc_store(cres).offset()
+  }
+
+  /// Reads a value from the plugin's memory.
+  pub fn read<T: ValueType>(&self, ptr: WasmPtr<T>) -> Result<T, FfiError> {
+    let mut buf = vec![0; mem::size_of::<T>()];
+    if !self.mem().read(ptr.offset(), &mut buf) {
+      return Err(FfiError::invalid("invalid pointer"));
+    }
+    // SAFETY: `buf` is the size of `T`, and `T` is a `ValueType`, so any bytes
+    // are a valid `T`.
+    Ok(unsafe { ptr::read_unaligned(buf.as_ptr() as *const T) })
+  }
+  /// Reads a utf8 string from the plugin's memory.
+  pub fn read_str(&self, ptr: WasmPtr<u8, Array>, len: u32) -> Result<String, FfiError> {
+    let mut buf = vec![0; len as usize];
+    if !self.mem().read(ptr.offset(), &mut buf) {
+      return Err(FfiError::invalid("invalid string"));
+    }
+    String::from_utf8(buf).map_err(|_| FfiError::invalid("invalid string"))
+  }
+
+  /// Returns the online player with the given id.
+  pub fn player(&self, id: WasmPtr<CUUID>) -> Result<Arc<Player>, FfiError> {
+    let id = UUID::from_ffi(self, self.read(id)?);
+    self.wm.get_player(id).ok_or_else(|| {
+      FfiError::new(CErrorCode::NoPlayer, format!("player {} is not online", id.as_dashed_str()))
+    })
+  }
+  /// Returns the world with the given id.
+  pub fn world(&self, wid: u32) -> Result<Arc<World>, FfiError> {
+    self
+      .wm
+      .worlds()
+      .get(wid as usize)
+      .cloned()
+      .ok_or_else(|| FfiError::new(CErrorCode::NoWorld, format!("no world with id {wid}")))
+  }
+  /// Returns the world with the given id, if this plugin is allowed to change
+  /// blocks in it.
+  pub fn world_mut(&self, wid: u32) -> Result<Arc<World>, FfiError> {
+    let world = self.world(wid)?;
+    let name = &world.config().name;
+    if !self.worlds.is_empty() && !self.worlds.contains(name) {
+      warn!("plugin {} tried to change world {name}, which is not in its `worlds`", self.name);
+      return Err(FfiError::new(
+        CErrorCode::NotAllowed,
+        format!("plugin {} cannot change world {name}", self.name),
+      ));
+    }
+    if world.is_locked() {
+      return Err(FfiError::new(CErrorCode::Locked, format!("world {name} is locked")));
+    }
+    Ok(world)
+  }
+  /// Returns the entity for the given handle. Players cannot be found this
+  /// way.
+  pub fn entity(&self, world: &Arc<World>, handle: u64) -> Result<Arc<EntityData>, FfiError> {
+    Ok(world.entities().get_ent_handle(EntityHandle::from_u64(handle))?.clone())
+  }
+  /// Returns the entity id for the given handle. This works for players as
+  /// well, but fails if the handle is stale.
+  pub fn eid(&self, world: &Arc<World>, handle: u64) -> Result<i32, FfiError> {
+    Ok(world.entities().get_handle(EntityHandle::from_u64(handle))?.eid())
+  }
+}
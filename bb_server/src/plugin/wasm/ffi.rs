@@ -4,16 +4,16 @@ use bb_common::{
   math::{FPos, Pos},
   util::UUID,
 };
-use bb_ffi::{CBool, CFPos, CList, COpt, CPos, CStr, CUUID};
-use std::mem;
+use bb_ffi::{CBool, CFPos, CList, COpt, CPos, CStr, ValueType, CUUID};
+use std::{mem, ptr};
 
 pub trait FromFfi {
-  type Ffi: wasmer::ValueType;
+  type Ffi: ValueType;
 
   fn from_ffi(env: &Env, ffi: Self::Ffi) -> Self;
 }
 pub trait ToFfi {
-  type Ffi: wasmer::ValueType;
+  type Ffi: ValueType;
 
   fn to_ffi(&self, env: &Env) -> Self::Ffi;
 }
@@ -88,14 +88,22 @@ impl ToFfi for bool {
 }
 impl<T> FromFfi for Vec<T>
 where
-  T: wasmer::ValueType,
+  T: ValueType,
 {
   type Ffi = CList<T>;
 
   fn from_ffi(env: &Env, ffi: CList<T>) -> Self {
-    let view = env.mem().view::<T>();
-    let ptr = (ffi.first.offset() as usize) / mem::size_of::<T>();
-    view[ptr..ptr + ffi.len as usize].iter().map(|it| it.get()).collect()
+    let size = mem::size_of::<T>();
+    let mut buf = vec![0; size * ffi.len as usize];
+    if !env.mem().read(ffi.first.offset(), &mut buf) {
+      panic!("invalid ptr");
+    }
+    buf
+      .chunks_exact(size)
+      // SAFETY: Each chunk is the size of `T`, and `T` is a `ValueType`, so any
+      // bytes are valid.
+      .map(|chunk| unsafe { ptr::read_unaligned(chunk.as_ptr() as *const T) })
+      .collect()
   }
 }
 impl<T> ToFfi for &[T]
@@ -106,42 +114,25 @@ where
   type Ffi = CList<T::Ffi>;
 
   fn to_ffi(&self, env: &Env) -> CList<T::Ffi> {
-    // Using malloc_store gives us a single copy, but we would need to allocate. So,
-    // I just iterate through and write every value after calling to_ffi.
-    let ptr = env.malloc_array::<T::Ffi>(self.len() as u32);
+    // The plugin's memory isn't directly accessible, so we convert everything
+    // first, and then copy it in all at once.
+    let values: Vec<_> = self.iter().map(|elem| elem.to_ffi(env)).collect();
+    let ptr = env.malloc_array_store(&values);
     if ptr.offset() == 0 {
       panic!("plugin oom");
     }
-    if ptr.offset() as usize + mem::size_of::<T::Ffi>() * self.len()
-      > env.mem().data_size() as usize
-    {
-      panic!("invalid ptr");
-    }
-    // SAFETY: We just validated all of ptr..ptr + self.len is valid.
-    unsafe {
-      // Call add on *mut u8, not *mut T::Ffi.
-      let data_ptr = env.mem().data_ptr().add(ptr.offset() as usize) as *mut T::Ffi;
-      for (i, elem) in self.iter().enumerate() {
-        let ptr = data_ptr.add(i);
-        std::ptr::write(ptr, elem.to_ffi(env));
-      }
-    }
     CList { first: ptr, len: self.len() as u32 }
   }
 }
 impl ToFfi for UUID {
   type Ffi = CUUID;
 
-  fn to_ffi(&self, _env: &Env) -> CUUID {
-    CUUID {
-      bytes: [
-        self.as_u128() as u32,
-        (self.as_u128() >> 32) as u32,
-        (self.as_u128() >> (2 * 32)) as u32,
-        (self.as_u128() >> (3 * 32)) as u32,
-      ],
-    }
-  }
+  fn to_ffi(&self, _env: &Env) -> CUUID { CUUID::from_u128(self.as_u128()) }
+}
+impl FromFfi for UUID {
+  type Ffi = CUUID;
+
+  fn from_ffi(_env: &Env, ffi: CUUID) -> Self { UUID::from_u128(ffi.as_u128()) }
 }
 impl ToFfi for &'_ str {
   type Ffi = CStr;
@@ -157,7 +148,5 @@ impl ToFfi for &'_ str {
 impl FromFfi for String {
   type Ffi = CStr;
 
-  fn from_ffi(env: &Env, cstr: CStr) -> String {
-    cstr.ptr.get_utf8_string(env.mem(), cstr.len).unwrap()
-  }
+  fn from_ffi(env: &Env, cstr: CStr) -> String { env.read_str(cstr.ptr, cstr.len).unwrap() }
 }
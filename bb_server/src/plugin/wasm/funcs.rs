@@ -1,88 +1,115 @@
-use super::{FromFfi, ToFfi};
+use super::{
+  error::FfiError,
+  host::{HostFunc, IntoHostFunc},
+  runtime::{Memory, Runtime, Val},
+  FromFfi, ToFfi,
+};
 use crate::{
   block,
+  block::SpawnerData,
   command::{Command, NodeType, Parser},
+  entity,
+  entity::{ArmorStandFlag, ArmorStandPart, Attribute, EntityHandle},
+  item,
+  item::Stack,
   particle::Particle,
-  world::WorldManager,
+  player::Skin,
+  world::{
+    schematic::{Mirror, Rotation, Schematic},
+    ChangeSet, Disguise, RegionEdit, WorldManager,
+  },
 };
 use bb_common::{
-  math::{FPos, Pos},
-  util::Chat,
+  ban::{Ban, BanTarget},
+  math::{ChunkPos, FPos, Pos},
+  util::{Chat, UUID},
   version::BlockVersion,
 };
-use bb_ffi::{CBlockPropValue, CChat, CCommand, CCommandArg, CFPos, CList, CParticle, CPos, CUUID};
+use bb_ffi::{
+  Array, CBlockChange, CBlockPropValue, CChat, CCommand, CErrorCode, CFPos, CList, CParticle, CPos,
+  CRaycastHit, ValueType, WasmPtr, CUUID,
+};
 use log::Level;
-use std::{mem, sync::Arc};
-use wasmer::{
-  imports, Array, Function, ImportObject, LazyInit, Memory, NativeFunc, Store, WasmPtr, WasmerEnv,
+use std::{
+  mem,
+  ops::Deref,
+  slice,
+  sync::{Arc, OnceLock, Weak},
+  time::Duration,
 };
 
-type OnCommand = NativeFunc<(WasmPtr<CUUID>, WasmPtr<CList<CCommandArg>>), ()>;
-type WasmMalloc = NativeFunc<(u32, u32), u32>;
-
-#[derive(WasmerEnv, Clone)]
-pub struct Env {
-  #[wasmer(export)]
-  pub memory:      LazyInit<Memory>,
-  #[wasmer(export)]
-  pub wasm_malloc: LazyInit<WasmMalloc>,
-  #[wasmer(export)]
-  pub on_command:  LazyInit<OnCommand>,
-  pub wm:          Arc<WorldManager>,
+/// Everything a plugin was created with. This is shared between the runtime,
+/// and every host function call.
+pub struct PluginData {
+  pub wm:      Arc<WorldManager>,
   /// The version of this plugin. Plugins will send us things like block ids,
   /// and we need to know how to convert them to the server's version. This
   /// allows us to load out-of-date plugins on a newer server.
-  pub ver:         BlockVersion,
-  pub name:        Arc<String>,
+  pub ver:     BlockVersion,
+  pub name:    String,
+  /// The worlds this plugin can change. If empty, every world can be changed.
+  pub worlds:  Vec<String>,
+  /// The runtime this plugin is running on. This is set once the plugin is
+  /// loaded, and is used to call the plugin outside of a host function, such
+  /// as when a command is run.
+  pub runtime: OnceLock<Weak<dyn Runtime>>,
+}
+
+impl PluginData {
+  pub fn new(wm: Arc<WorldManager>, name: String, worlds: Vec<String>) -> Self {
+    PluginData {
+      wm,
+      // TODO: Fetch this from the plugin
+      ver: BlockVersion::latest(),
+      name,
+      worlds,
+      runtime: OnceLock::new(),
+    }
+  }
+}
+
+/// Passed to every host function. This gives access to the plugin's memory,
+/// and derefs to the [`PluginData`].
+pub struct Env<'a> {
+  data: &'a PluginData,
+  mem:  &'a dyn Memory,
+}
+
+impl Deref for Env<'_> {
+  type Target = PluginData;
+
+  fn deref(&self) -> &PluginData { self.data }
 }
 
-impl Env {
-  pub fn mem(&self) -> &Memory { self.memory.get_ref().expect("Env not initialized") }
+impl<'a> Env<'a> {
+  pub fn new(data: &'a PluginData, mem: &'a dyn Memory) -> Self { Env { data, mem } }
+
+  pub fn mem(&self) -> &dyn Memory { self.mem }
   pub fn malloc<T: Copy>(&self) -> WasmPtr<T> {
-    let ptr = self
-      .wasm_malloc
-      .get_ref()
-      .expect("Env not initialized")
-      .call(mem::size_of::<T>() as u32, mem::align_of::<T>() as u32)
-      .unwrap_or_else(|e| panic!("{e}"));
-    WasmPtr::new(ptr)
+    WasmPtr::new(self.mem.malloc(mem::size_of::<T>() as u32, mem::align_of::<T>() as u32))
   }
   pub fn malloc_array<T: Copy>(&self, len: u32) -> WasmPtr<T, Array> {
-    let ptr = self
-      .wasm_malloc
-      .get_ref()
-      .expect("Env not initialized")
-      .call(mem::size_of::<T>() as u32 * len, mem::align_of::<T>() as u32)
-      .unwrap();
-    WasmPtr::new(ptr)
+    WasmPtr::new(self.mem.malloc(mem::size_of::<T>() as u32 * len, mem::align_of::<T>() as u32))
   }
-  pub fn malloc_store<T: Copy>(&self, value: T) -> WasmPtr<T> {
+  pub fn malloc_store<T: ValueType>(&self, value: T) -> WasmPtr<T> {
     let ptr = self.malloc::<T>();
-    // This checks that the last byte in our allocated region is valid for writes.
-    if u64::from(ptr.offset()) + mem::size_of::<T>() as u64 - 1 > self.mem().data_size() {
+    // SAFETY: `T` is a `ValueType`, so it is plain data, and can be copied as
+    // bytes.
+    let bytes =
+      unsafe { slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>()) };
+    if !self.mem.write(ptr.offset(), bytes) {
       panic!("invalid ptr");
     }
-    // SAFETY: We just validated the `write` call will write to valid memory.
-    unsafe {
-      let ptr = self.mem().data_ptr().add(ptr.offset() as usize);
-      std::ptr::write(ptr as *mut T, value);
-    }
     ptr
   }
-  pub fn malloc_array_store<T: Copy>(&self, value: &[T]) -> WasmPtr<T, Array> {
+  pub fn malloc_array_store<T: ValueType>(&self, value: &[T]) -> WasmPtr<T, Array> {
     let ptr = self.malloc_array::<T>(value.len().try_into().unwrap());
-    // This checks that the last byte in the array is within out data.
-    if u64::from(ptr.offset()) + mem::size_of::<T>() as u64 * value.len() as u64 - 1
-      > self.mem().data_size()
-    {
+    // SAFETY: See `malloc_store`.
+    let bytes =
+      unsafe { slice::from_raw_parts(value.as_ptr() as *const u8, mem::size_of_val(value)) };
+    if !self.mem.write(ptr.offset(), bytes) {
       panic!("invalid ptr");
     }
-    // SAFETY: We just validated the `write` call will write to valid memory.
-    unsafe {
-      // We want to call add on *mut u8, because ptr.offset() gives bytes.
-      let ptr = self.mem().data_ptr().add(ptr.offset() as usize) as *mut T;
-      std::ptr::copy(value.as_ptr(), ptr, value.len());
-    }
     ptr
   }
 }
@@ -116,201 +143,671 @@ fn log(
     Some(l) => l,
     None => return,
   };
-  // SAFETY: We aren't using the string outside this function,
-  // so this is safe. It also avoids allocating, so that's why
-  // we use this instead of `get_utf8_string_with_nul`.
-
-  unsafe {
-    let s = if u64::from(message_ptr.offset() + message_len) > env.mem().data_size() {
-      std::borrow::Cow::Borrowed("")
-    } else {
-      let ptr = env.mem().data_ptr().add(message_ptr.offset() as usize) as *const u8;
-      let slice = std::slice::from_raw_parts(ptr, message_len as usize);
-      String::from_utf8_lossy(slice)
-    };
-    log::logger().log(
-      &log::Record::builder()
-        .args(format_args!("{s}"))
-        .level(level)
-        .target(target_ptr.get_utf8_str(env.mem(), target_len).unwrap())
-        .module_path(Some(module_path_ptr.get_utf8_str(env.mem(), module_path_len).unwrap()))
-        .file(Some(file_ptr.get_utf8_str(env.mem(), file_len).unwrap()))
-        .line(Some(line))
-        .build(),
-    );
+  let mut message = vec![0; message_len as usize];
+  if !env.mem().read(message_ptr.offset(), &mut message) {
+    message.clear();
   }
+  let s = String::from_utf8_lossy(&message);
+  let target = env.read_str(target_ptr, target_len).unwrap_or_default();
+  let module_path = env.read_str(module_path_ptr, module_path_len).unwrap_or_default();
+  let file = env.read_str(file_ptr, file_len).unwrap_or_default();
+  log::logger().log(
+    &log::Record::builder()
+      .args(format_args!("{s}"))
+      .level(level)
+      .target(&target)
+      .module_path(Some(&module_path))
+      .file(Some(&file))
+      .line(Some(line))
+      .build(),
+  );
 }
 
 fn broadcast(env: &Env, message: WasmPtr<CChat>) {
-  let chat = message.deref(env.mem()).unwrap().get();
-  let s = chat.message.ptr.get_utf8_string_with_nul(env.mem()).unwrap();
+  let chat = env.read(message).unwrap();
+  let s = env.read_str(chat.message.ptr, chat.message.len).unwrap();
   env.wm.broadcast(Chat::new(s));
 }
 
 fn player_username(env: &Env, id: WasmPtr<CUUID>) -> u32 {
-  let mem = env.mem();
-  let uuid = match id.deref(mem) {
-    Some(id) => id.get(),
-    None => return 1,
-  };
-  let player = match env.wm.get_player(bb_common::util::UUID::from_u128(
-    (uuid.bytes[3] as u128) << (3 * 32)
-      | (uuid.bytes[2] as u128) << (2 * 32)
-      | (uuid.bytes[1] as u128) << 32
-      | uuid.bytes[0] as u128,
-  )) {
-    Some(p) => p,
-    None => return 1,
-  };
-  let cusername = player.username().as_str().to_ffi(env);
-  let ptr = env.malloc_store(cusername);
-  ptr.offset()
+  env.result(|| Ok(env.player(id)?.username().as_str().to_ffi(env)))
 }
 fn player_pos(env: &Env, id: WasmPtr<CUUID>) -> u32 {
-  let mem = env.mem();
-  let uuid = match id.deref(mem) {
-    Some(id) => id.get(),
-    None => return 0,
-  };
-  let player = match env.wm.get_player(bb_common::util::UUID::from_u128(
-    (uuid.bytes[3] as u128) << (3 * 32)
-      | (uuid.bytes[2] as u128) << (2 * 32)
-      | (uuid.bytes[1] as u128) << 32
-      | uuid.bytes[0] as u128,
-  )) {
-    Some(p) => p,
-    None => return 0,
-  };
-  let cpos = player.pos().to_ffi(env);
-  let ptr = env.malloc_store(cpos);
-  ptr.offset()
+  env.result(|| Ok(env.player(id)?.pos().to_ffi(env)))
 }
 fn player_look_as_vec(env: &Env, id: WasmPtr<CUUID>) -> u32 {
-  let mem = env.mem();
-  let uuid = match id.deref(mem) {
-    Some(id) => id.get(),
-    None => return 0,
-  };
-  let player = match env.wm.get_player(bb_common::util::UUID::from_u128(
-    (uuid.bytes[3] as u128) << (3 * 32)
-      | (uuid.bytes[2] as u128) << (2 * 32)
-      | (uuid.bytes[1] as u128) << 32
-      | uuid.bytes[0] as u128,
-  )) {
-    Some(p) => p,
-    None => return 0,
-  };
-  let cpos = FPos::from(player.look_as_vec()).to_ffi(env);
-  let ptr = env.malloc_store(cpos);
-  ptr.offset()
+  env.result(|| Ok(FPos::from(env.player(id)?.look_as_vec()).to_ffi(env)))
 }
 fn player_send_particle(env: &Env, id: WasmPtr<CUUID>, particle: WasmPtr<CParticle>) {
-  let mem = env.mem();
-  let uuid = match id.deref(mem) {
-    Some(id) => id.get(),
-    None => return,
-  };
-  let player = match env.wm.get_player(bb_common::util::UUID::from_u128(
-    (uuid.bytes[3] as u128) << (3 * 32)
-      | (uuid.bytes[2] as u128) << (2 * 32)
-      | (uuid.bytes[1] as u128) << 32
-      | uuid.bytes[0] as u128,
-  )) {
-    Some(p) => p,
-    None => return,
+  let (player, cparticle) = match (env.player(id), env.read(particle)) {
+    (Ok(p), Ok(c)) => (p, c),
+    _ => return,
   };
-  let cparticle = match particle.deref(mem) {
-    Some(p) => p.get(),
-    None => return,
-  };
-  let particle = Particle::from_ffi(env, cparticle);
-  player.send_particle(particle);
+  player.send_particle(Particle::from_ffi(env, cparticle));
 }
 
-fn player_world(env: &Env, player: WasmPtr<CUUID>) -> i32 {
-  let mem = env.mem();
-  let uuid = match player.deref(mem) {
-    Some(p) => p.get(),
-    None => return -1,
+fn player_attribute(
+  env: &Env,
+  id: WasmPtr<CUUID>,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+) -> u32 {
+  env.result(|| {
+    let player = env.player(id)?;
+    let name = env.read_str(name_ptr, name_len)?;
+    let attr = Attribute::from_name(&name)
+      .ok_or_else(|| FfiError::invalid(format!("unknown attribute `{name}`")))?;
+    Ok(player.attribute(attr))
+  })
+}
+fn player_set_attribute_base(
+  env: &Env,
+  id: WasmPtr<CUUID>,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+  value: f64,
+) -> u32 {
+  env.status(|| {
+    let player = env.player(id)?;
+    let name = env.read_str(name_ptr, name_len)?;
+    let attr = Attribute::from_name(&name)
+      .ok_or_else(|| FfiError::invalid(format!("unknown attribute `{name}`")))?;
+    player.modify_attributes(|a| a.set_base(attr, value));
+    Ok(())
+  })
+}
+
+fn player_set_skin(
+  env: &Env,
+  id: WasmPtr<CUUID>,
+  value_ptr: WasmPtr<u8, Array>,
+  value_len: u32,
+  sig_ptr: WasmPtr<u8, Array>,
+  sig_len: u32,
+) -> u32 {
+  env.status(|| {
+    let player = env.player(id)?;
+    let value = env.read_str(value_ptr, value_len)?;
+    let signature = if sig_len == 0 { None } else { Some(env.read_str(sig_ptr, sig_len)?) };
+    player.set_skin(Some(Skin::new(value, signature)));
+    Ok(())
+  })
+}
+fn player_reset_skin(env: &Env, id: WasmPtr<CUUID>) -> u32 {
+  env.status(|| {
+    env.player(id)?.reset_skin();
+    Ok(())
+  })
+}
+
+fn player_brand(env: &Env, id: WasmPtr<CUUID>) -> u32 {
+  env.result(|| Ok(env.player(id)?.brand().unwrap_or_default().as_str().to_ffi(env)))
+}
+fn player_mods(env: &Env, id: WasmPtr<CUUID>) -> u32 {
+  env.result(|| {
+    let player = env.player(id)?;
+    let mods: Vec<_> = player.mods().iter().map(|m| m.as_str()).collect();
+    Ok(mods.as_slice().to_ffi(env))
+  })
+}
+
+fn player_set_hidden(env: &Env, id: WasmPtr<CUUID>, other: WasmPtr<CUUID>, hidden: u8) -> u32 {
+  env.status(|| {
+    let player = env.player(id)?;
+    let other = env.player(other)?;
+    if hidden != 0 {
+      player.hide(&other);
+    } else {
+      player.show(&other);
+    }
+    Ok(())
+  })
+}
+
+fn player_set_selection(
+  env: &Env,
+  id: WasmPtr<CUUID>,
+  pos1: WasmPtr<CPos>,
+  pos2: WasmPtr<CPos>,
+) -> u32 {
+  env.status(|| {
+    let player = env.player(id)?;
+    let pos1 = Pos::from_ffi(env, env.read(pos1)?);
+    let pos2 = Pos::from_ffi(env, env.read(pos2)?);
+    player.set_pos1(pos1);
+    player.set_pos2(pos2);
+    Ok(())
+  })
+}
+fn player_edit_selection(env: &Env, id: WasmPtr<CUUID>, op: u8, ty: u32, filter: u32) -> u32 {
+  env.result(|| {
+    let player = env.player(id)?;
+    let ty = env.wm.block_converter().type_from_id(ty, env.ver);
+    let edit = match op {
+      0 => RegionEdit::Set(ty),
+      1 => match block::Kind::from_id(filter) {
+        Some(kind) => RegionEdit::Replace(kind, ty),
+        None => return Err(FfiError::invalid(format!("invalid block kind {filter}"))),
+      },
+      2 => RegionEdit::Walls(ty),
+      3 => RegionEdit::Hollow(ty),
+      _ => return Err(FfiError::invalid(format!("invalid edit {op}"))),
+    };
+    Ok(player.edit_selection(edit)?)
+  })
+}
+fn player_remember(env: &Env, id: WasmPtr<CUUID>, changes: WasmPtr<CList<CBlockChange>>) -> u32 {
+  env.status(|| {
+    let player = env.player(id)?;
+    let list = env.read(changes)?;
+    let conv = env.wm.block_converter();
+    let mut changes = ChangeSet::new();
+    for c in Vec::<CBlockChange>::from_ffi(env, list) {
+      changes.push(
+        Pos::new(c.pos.x, c.pos.y, c.pos.z),
+        conv.to_latest(c.old, env.ver),
+        conv.to_latest(c.new, env.ver),
+      );
+    }
+    player.remember(changes);
+    Ok(())
+  })
+}
+fn player_undo(env: &Env, id: WasmPtr<CUUID>) -> u32 {
+  env.result(|| Ok(env.player(id)?.undo()?.to_ffi(env)))
+}
+fn player_redo(env: &Env, id: WasmPtr<CUUID>) -> u32 {
+  env.result(|| Ok(env.player(id)?.redo()?.to_ffi(env)))
+}
+
+fn player_ban(
+  env: &Env,
+  id: WasmPtr<CUUID>,
+  reason_ptr: WasmPtr<u8, Array>,
+  reason_len: u32,
+  duration: u64,
+) -> i32 {
+  let uuid = match env.read(id) {
+    Ok(id) => UUID::from_ffi(env, id),
+    Err(_) => return -1,
   };
-  let _player = match env.wm.get_player(bb_common::util::UUID::from_u128(
-    (uuid.bytes[3] as u128) << (3 * 32)
-      | (uuid.bytes[2] as u128) << (2 * 32)
-      | (uuid.bytes[1] as u128) << 32
-      | uuid.bytes[0] as u128,
-  )) {
-    Some(p) => p,
-    None => return -1,
+  let reason = match env.read_str(reason_ptr, reason_len) {
+    Ok(v) => v,
+    Err(_) => return -1,
   };
+  let duration = if duration == 0 { None } else { Some(Duration::from_secs(duration)) };
+  let mut ban = Ban::new(BanTarget::Uuid(uuid), reason, "Plugin".into(), duration);
+  if let Some(p) = env.wm.get_player(uuid) {
+    ban = ban.with_name(p.username().as_str());
+  }
+  env.wm.ban(ban);
   0
 }
-
-fn world_set_block(env: &Env, _wid: u32, pos: WasmPtr<CPos>, id: u32) -> i32 {
-  let mem = env.mem();
-  let pos = match pos.deref(mem) {
-    Some(p) => p.get(),
-    None => return -1,
+fn player_pardon(env: &Env, id: WasmPtr<CUUID>) -> i32 {
+  let uuid = match env.read(id) {
+    Ok(id) => UUID::from_ffi(env, id),
+    Err(_) => return -1,
   };
-  let world = env.wm.default_world();
-  let ty = env.wm.block_converter().type_from_id(id, env.ver);
-  match world.set_block(Pos::new(pos.x, pos.y, pos.z), ty) {
-    Ok(_) => 0,
-    Err(_) => -1,
+  match env.wm.pardon(BanTarget::Uuid(uuid)) {
+    Some(_) => 0,
+    None => -1,
   }
 }
-fn world_set_block_kind(env: &Env, _wid: u32, pos: WasmPtr<CPos>, kind: u32) -> i32 {
-  let mem = env.mem();
-  let pos = match pos.deref(mem) {
-    Some(p) => p.get(),
+fn player_ban_reason(env: &Env, id: WasmPtr<CUUID>) -> u32 {
+  let uuid = match env.read(id) {
+    Ok(id) => UUID::from_ffi(env, id),
+    Err(_) => return 0,
+  };
+  let ban = match env.wm.bans().get(BanTarget::Uuid(uuid)) {
+    Some(ban) => ban.clone(),
+    None => return 0,
+  };
+  let creason = ban.reason.as_str().to_ffi(env);
+  env.malloc_store(creason).offset()
+}
+fn player_ban_expires(env: &Env, id: WasmPtr<CUUID>) -> i64 {
+  let uuid = match env.read(id) {
+    Ok(id) => UUID::from_ffi(env, id),
+    Err(_) => return -1,
+  };
+  let bans = env.wm.bans();
+  let ban = match bans.get(BanTarget::Uuid(uuid)) {
+    Some(ban) => ban,
     None => return -1,
   };
-  let world = env.wm.default_world();
-  let kind = block::Kind::from_id(kind).unwrap_or(block::Kind::Air);
-  match world.set_kind(Pos::new(pos.x, pos.y, pos.z), kind) {
-    Ok(_) => 0,
-    Err(_) => -1,
+  match ban.remaining() {
+    // A ban that expires this second would look permanent, so round it up.
+    Some(time) => time.as_secs().max(1) as i64,
+    None => 0,
   }
 }
-fn world_get_block(env: &Env, _wid: u32, pos: WasmPtr<CPos>) -> u32 {
-  let mem = env.mem();
-  let pos = match pos.deref(mem) {
-    Some(p) => p.get(),
-    None => return u32::MAX,
-  };
-  let world = env.wm.default_world();
-  match world.get_block(Pos::new(pos.x, pos.y, pos.z)) {
-    Ok(ty) => ty.id(),
-    Err(_) => u32::MAX,
+
+fn player_world(env: &Env, id: WasmPtr<CUUID>) -> u32 {
+  env.result(|| {
+    let player = env.player(id)?;
+    let wid = env.wm.worlds().iter().position(|w| Arc::ptr_eq(w, &player.world()));
+    // This can only happen if the world was removed while the player was in it.
+    wid
+      .map(|wid| wid as u32)
+      .ok_or_else(|| FfiError::new(CErrorCode::NoWorld, "player is not in a world"))
+  })
+}
+
+fn player_teleport_world(env: &Env, id: WasmPtr<CUUID>, wid: u32, pos: WasmPtr<CFPos>) -> u32 {
+  env.status(|| {
+    let player = env.player(id)?;
+    let pos = FPos::from_ffi(env, env.read(pos)?);
+    let world = env.world(wid)?;
+    if !player.teleport_world(&world, pos) {
+      return Err(FfiError::new(CErrorCode::NoPlayer, "player is not online"));
+    }
+    Ok(())
+  })
+}
+
+fn world_set_block(env: &Env, wid: u32, pos: WasmPtr<CPos>, id: u32) -> u32 {
+  env.status(|| {
+    let pos = Pos::from_ffi(env, env.read(pos)?);
+    let world = env.world_mut(wid)?;
+    let ty = env.wm.block_converter().type_from_id(id, env.ver);
+    world.set_block(pos, ty)?;
+    Ok(())
+  })
+}
+fn world_set_block_kind(env: &Env, wid: u32, pos: WasmPtr<CPos>, kind: u32) -> u32 {
+  env.status(|| {
+    let pos = Pos::from_ffi(env, env.read(pos)?);
+    let world = env.world_mut(wid)?;
+    let kind = block::Kind::from_id(kind)
+      .ok_or_else(|| FfiError::invalid(format!("invalid block kind {kind}")))?;
+    world.set_kind(pos, kind)?;
+    Ok(())
+  })
+}
+fn world_get_block(env: &Env, wid: u32, pos: WasmPtr<CPos>) -> u32 {
+  env.result(|| {
+    let pos = Pos::from_ffi(env, env.read(pos)?);
+    Ok(env.world(wid)?.get_block(pos)?.id())
+  })
+}
+fn world_set_spawner(
+  env: &Env,
+  wid: u32,
+  pos: WasmPtr<CPos>,
+  entity_ptr: WasmPtr<u8, Array>,
+  entity_len: u32,
+) -> u32 {
+  env.status(|| {
+    let pos = Pos::from_ffi(env, env.read(pos)?);
+    let entity = env.read_str(entity_ptr, entity_len)?;
+    entity.parse::<entity::Type>().map_err(|e| FfiError::invalid(e.to_string()))?;
+    let world = env.world_mut(wid)?;
+    world.set_spawner(pos, SpawnerData { entity: entity.into(), ..Default::default() })?;
+    Ok(())
+  })
+}
+fn world_set_spawner_delay(env: &Env, wid: u32, pos: WasmPtr<CPos>, min: u32, max: u32) -> u32 {
+  env.status(|| {
+    let pos = Pos::from_ffi(env, env.read(pos)?);
+    let world = env.world_mut(wid)?;
+    let found = world.modify_spawner(pos, |data| {
+      data.min_delay = min.min(i16::MAX as u32) as i16;
+      data.max_delay = max.min(i16::MAX as u32) as i16;
+    })?;
+    if found {
+      Ok(())
+    } else {
+      Err(FfiError::invalid(format!("no spawner at {pos}")))
+    }
+  })
+}
+fn world_players(env: &Env, wid: u32) -> u32 {
+  env.result(|| {
+    let players: Vec<_> = env.world(wid)?.players().iter().map(|p| p.id()).collect();
+    Ok(players.as_slice().to_ffi(env))
+  })
+}
+fn world_spawn_text(env: &Env, wid: u32, pos: WasmPtr<CFPos>, text: WasmPtr<CChat>) -> u32 {
+  env.result(|| {
+    let pos = FPos::from_ffi(env, env.read(pos)?);
+    let text = env.read(text)?;
+    let text = env.read_str(text.message.ptr, text.message.len)?;
+    Ok(env.world(wid)?.spawn_text(pos, Chat::new(text)).to_u64())
+  })
+}
+fn world_set_text(env: &Env, wid: u32, entity: u64, text: WasmPtr<CChat>) -> u32 {
+  env.status(|| {
+    let text = env.read(text)?;
+    let text = env.read_str(text.message.ptr, text.message.len)?;
+    env.entity(&env.world(wid)?, entity)?.set_custom_name(Some(Chat::new(text)));
+    Ok(())
+  })
+}
+fn world_remove_entity(env: &Env, wid: u32, entity: u64) -> u32 {
+  env.status(|| {
+    let world = env.world(wid)?;
+    let eid = env.entity(&world, entity)?.eid();
+    if world.remove_entity(eid) {
+      Ok(())
+    } else {
+      Err(FfiError::new(CErrorCode::NoEntity, format!("no entity with id {eid}")))
+    }
+  })
+}
+fn world_spawn_armor_stand(env: &Env, wid: u32, pos: WasmPtr<CFPos>) -> u32 {
+  env.result(|| {
+    let pos = FPos::from_ffi(env, env.read(pos)?);
+    Ok(env.world(wid)?.summon_handle(entity::Type::ArmorStand, pos).to_u64())
+  })
+}
+#[allow(clippy::too_many_arguments)]
+fn world_set_armor_stand_pose(
+  env: &Env,
+  wid: u32,
+  entity: u64,
+  part_ptr: WasmPtr<u8, Array>,
+  part_len: u32,
+  x: f32,
+  y: f32,
+  z: f32,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(part_ptr, part_len)?;
+    let part = ArmorStandPart::from_name(&name)
+      .ok_or_else(|| FfiError::invalid(format!("unknown armor stand part `{name}`")))?;
+    env.entity(&env.world(wid)?, entity)?.set_armor_stand_pose(part, x, y, z);
+    Ok(())
+  })
+}
+fn world_set_armor_stand_flag(
+  env: &Env,
+  wid: u32,
+  entity: u64,
+  flag_ptr: WasmPtr<u8, Array>,
+  flag_len: u32,
+  value: u8,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(flag_ptr, flag_len)?;
+    let flag = ArmorStandFlag::from_name(&name)
+      .ok_or_else(|| FfiError::invalid(format!("unknown armor stand flag `{name}`")))?;
+    env.entity(&env.world(wid)?, entity)?.set_armor_stand_flag(flag, value != 0);
+    Ok(())
+  })
+}
+#[allow(clippy::too_many_arguments)]
+fn world_set_equipment(
+  env: &Env,
+  wid: u32,
+  entity: u64,
+  slot_ptr: WasmPtr<u8, Array>,
+  slot_len: u32,
+  item_ptr: WasmPtr<u8, Array>,
+  item_len: u32,
+  amount: u8,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(slot_ptr, slot_len)?;
+    let slot = entity::slot_from_name(&name)
+      .ok_or_else(|| FfiError::invalid(format!("unknown equipment slot `{name}`")))?;
+    let item = env
+      .read_str(item_ptr, item_len)?
+      .parse::<item::Type>()
+      .map_err(|e| FfiError::invalid(e.to_string()))?;
+    env.entity(&env.world(wid)?, entity)?.set_equipment(slot, Stack::new(item).with_amount(amount));
+    Ok(())
+  })
+}
+fn world_disguise(
+  env: &Env,
+  wid: u32,
+  entity: u64,
+  ty_ptr: WasmPtr<u8, Array>,
+  ty_len: u32,
+) -> u32 {
+  env.status(|| {
+    let ty = env
+      .read_str(ty_ptr, ty_len)?
+      .parse::<entity::Type>()
+      .map_err(|e| FfiError::invalid(e.to_string()))?;
+    let world = env.world(wid)?;
+    let eid = env.eid(&world, entity)?;
+    if world.set_disguise(eid, Disguise::new(ty)) {
+      Ok(())
+    } else {
+      Err(FfiError::new(CErrorCode::NoEntity, format!("cannot disguise entity {eid}")))
+    }
+  })
+}
+fn world_undisguise(env: &Env, wid: u32, entity: u64) -> u32 {
+  env.status(|| {
+    let world = env.world(wid)?;
+    let eid = env.eid(&world, entity)?;
+    if world.remove_disguise(eid) {
+      Ok(())
+    } else {
+      Err(FfiError::invalid(format!("entity {eid} is not disguised")))
+    }
+  })
+}
+#[allow(clippy::too_many_arguments)]
+fn world_paste_schematic(
+  env: &Env,
+  wid: u32,
+  path_ptr: WasmPtr<u8, Array>,
+  path_len: u32,
+  pos: WasmPtr<CPos>,
+  rotation: i32,
+  mirror: u8,
+  skip_air: u8,
+) -> u32 {
+  env.result(|| {
+    let path = env.read_str(path_ptr, path_len)?;
+    let pos = Pos::from_ffi(env, env.read(pos)?);
+    let rotation = Rotation::from_degrees(rotation)
+      .ok_or_else(|| FfiError::invalid(format!("invalid rotation {rotation}")))?;
+    let mirror = mirror_from_ffi(mirror)?;
+    let world = env.world_mut(wid)?;
+    let schem = Schematic::read_file(&path, world.block_converter()).map_err(|e| {
+      FfiError::new(CErrorCode::Io, format!("could not load schematic {path}: {e}"))
+    })?;
+    Ok(world.paste_schematic(&schem, pos, rotation, mirror, skip_air != 0)?)
+  })
+}
+fn mirror_from_ffi(mirror: u8) -> Result<Mirror, FfiError> {
+  match mirror {
+    0 => Ok(Mirror::None),
+    1 => Ok(Mirror::X),
+    2 => Ok(Mirror::Z),
+    _ => Err(FfiError::invalid(format!("invalid mirror {mirror}"))),
   }
 }
-fn world_players(env: &Env, _wid: u32) -> u32 {
-  // TODO: Use the world id.
-  let world = env.wm.default_world();
-  let players: Vec<_> = world.players().iter().map(|p| p.id()).collect();
-  let cplayers = players.as_slice().to_ffi(env);
-  let ptr = env.malloc_store(cplayers);
-  ptr.offset()
+fn world_save_structure(
+  env: &Env,
+  wid: u32,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+  min: WasmPtr<CPos>,
+  max: WasmPtr<CPos>,
+  origin: WasmPtr<CPos>,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    let min = Pos::from_ffi(env, env.read(min)?);
+    let max = Pos::from_ffi(env, env.read(max)?);
+    let origin = Pos::from_ffi(env, env.read(origin)?);
+    Ok(env.world(wid)?.save_structure(name, min, max, origin)?)
+  })
 }
-fn world_spawn_particle(env: &Env, _wid: u32, particle: WasmPtr<CParticle>) {
-  // TODO: Use the world id.
-  let world = env.wm.default_world();
-  let mem = env.mem();
-  let cparticle = match particle.deref(mem) {
-    Some(p) => p.get(),
-    None => return,
+#[allow(clippy::too_many_arguments)]
+fn world_paste_structure(
+  env: &Env,
+  wid: u32,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+  pos: WasmPtr<CPos>,
+  rotation: i32,
+  mirror: u8,
+  integrity: f32,
+  seed: u64,
+) -> u32 {
+  env.result(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    let pos = Pos::from_ffi(env, env.read(pos)?);
+    let rotation = Rotation::from_degrees(rotation)
+      .ok_or_else(|| FfiError::invalid(format!("invalid rotation {rotation}")))?;
+    let mirror = mirror_from_ffi(mirror)?;
+    let world = env.world_mut(wid)?;
+    let schem = world
+      .world_manager()
+      .structure(&name)
+      .ok_or_else(|| FfiError::invalid(format!("no structure named {name}")))?;
+    Ok(world.paste_structure(&schem, pos, rotation, mirror, integrity, seed)?)
+  })
+}
+fn load_structure(
+  env: &Env,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+  path_ptr: WasmPtr<u8, Array>,
+  path_len: u32,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    let path = env.read_str(path_ptr, path_len)?;
+    env
+      .wm
+      .load_structure(name, &path)
+      .map_err(|e| FfiError::new(CErrorCode::Io, format!("could not load structure {path}: {e}")))
+  })
+}
+fn write_structure(
+  env: &Env,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+  path_ptr: WasmPtr<u8, Array>,
+  path_len: u32,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    let path = env.read_str(path_ptr, path_len)?;
+    match env.wm.write_structure(&name, &path) {
+      Ok(true) => Ok(()),
+      Ok(false) => Err(FfiError::invalid(format!("no structure named {name}"))),
+      Err(e) => Err(FfiError::new(CErrorCode::Io, format!("could not save structure {path}: {e}"))),
+    }
+  })
+}
+fn world_snapshot_region(
+  env: &Env,
+  wid: u32,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+  min: WasmPtr<CPos>,
+  max: WasmPtr<CPos>,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    let min = Pos::from_ffi(env, env.read(min)?);
+    let max = Pos::from_ffi(env, env.read(max)?);
+    let world = env.world(wid)?;
+    let snapshot = world.snapshot_region(min, max)?;
+    world.add_snapshot(name, snapshot);
+    Ok(())
+  })
+}
+fn world_restore_snapshot(env: &Env, wid: u32, name_ptr: WasmPtr<u8, Array>, name_len: u32) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    let world = env.world_mut(wid)?;
+    let snapshot = world
+      .snapshot(&name)
+      .ok_or_else(|| FfiError::invalid(format!("no snapshot named {name}")))?;
+    // `world_mut` already checked that the world isn't locked.
+    world.restore_snapshot(&snapshot);
+    Ok(())
+  })
+}
+fn world_light_portal(env: &Env, wid: u32, pos: WasmPtr<CPos>) -> u32 {
+  env.status(|| {
+    let pos = Pos::from_ffi(env, env.read(pos)?);
+    if env.world_mut(wid)?.light_portal(pos) {
+      Ok(())
+    } else {
+      Err(FfiError::invalid(format!("there is no portal frame at {pos}")))
+    }
+  })
+}
+fn world_add_chunk_ticket(
+  env: &Env,
+  wid: u32,
+  x: i32,
+  z: i32,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+  ttl: u32,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    let ttl = if ttl == 0 { None } else { Some(ttl) };
+    env.world(wid)?.add_chunk_ticket(ChunkPos::new(x, z), &name, ttl);
+    Ok(())
+  })
+}
+fn world_remove_chunk_ticket(
+  env: &Env,
+  wid: u32,
+  x: i32,
+  z: i32,
+  name_ptr: WasmPtr<u8, Array>,
+  name_len: u32,
+) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    if env.world(wid)?.remove_chunk_ticket(ChunkPos::new(x, z), &name) {
+      Ok(())
+    } else {
+      Err(FfiError::invalid(format!("no ticket named {name} in chunk {x} {z}")))
+    }
+  })
+}
+fn world_set_force_loaded(env: &Env, wid: u32, x: i32, z: i32, forced: u8) -> u32 {
+  env.status(|| {
+    env.world(wid)?.set_force_loaded(ChunkPos::new(x, z), forced != 0);
+    Ok(())
+  })
+}
+fn world_remove_snapshot(env: &Env, wid: u32, name_ptr: WasmPtr<u8, Array>, name_len: u32) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    if env.world(wid)?.remove_snapshot(&name) {
+      Ok(())
+    } else {
+      Err(FfiError::invalid(format!("no snapshot named {name}")))
+    }
+  })
+}
+fn remove_structure(env: &Env, name_ptr: WasmPtr<u8, Array>, name_len: u32) -> u32 {
+  env.status(|| {
+    let name = env.read_str(name_ptr, name_len)?;
+    if env.wm.remove_structure(&name) {
+      Ok(())
+    } else {
+      Err(FfiError::invalid(format!("no structure named {name}")))
+    }
+  })
+}
+fn world_spawn_particle(env: &Env, wid: u32, particle: WasmPtr<CParticle>) {
+  let (world, cparticle) = match (env.world(wid), env.read(particle)) {
+    (Ok(w), Ok(c)) => (w, c),
+    _ => return,
   };
-  let particle = Particle::from_ffi(env, cparticle);
-  world.spawn_particle(particle);
+  world.spawn_particle(Particle::from_ffi(env, cparticle));
 }
 fn world_raycast(env: &Env, from: WasmPtr<CFPos>, to: WasmPtr<CFPos>, water: u8) -> u32 {
-  let mem = env.mem();
-  let from = match from.deref(mem) {
-    Some(p) => FPos::from_ffi(env, p.get()),
-    None => return 0,
-  };
-  let to = match to.deref(mem) {
-    Some(p) => FPos::from_ffi(env, p.get()),
-    None => return 0,
+  let (from, to) = match (env.read(from), env.read(to)) {
+    (Ok(from), Ok(to)) => (FPos::from_ffi(env, from), FPos::from_ffi(env, to)),
+    _ => return 0,
   };
   let water = water == 1;
   let world = env.wm.default_world();
@@ -323,6 +820,51 @@ fn world_raycast(env: &Env, from: WasmPtr<CFPos>, to: WasmPtr<CFPos>, water: u8)
     None => 0,
   }
 }
+fn world_raycast_entity(env: &Env, from: WasmPtr<CFPos>, to: WasmPtr<CFPos>, exclude: u64) -> u64 {
+  let (from, to) = match (env.read(from), env.read(to)) {
+    (Ok(from), Ok(to)) => (FPos::from_ffi(env, from), FPos::from_ffi(env, to)),
+    _ => return 0,
+  };
+  let exclude = if exclude == 0 { None } else { Some(EntityHandle::from_u64(exclude).eid) };
+  let world = env.wm.default_world();
+  match world.raycast_entity(from, to, exclude) {
+    Some((_, ent)) => ent.as_entity_ref(&world).map(|e| e.handle().to_u64()).unwrap_or(0),
+    None => 0,
+  }
+}
+fn world_raycast_hit(
+  env: &Env,
+  from: WasmPtr<CFPos>,
+  to: WasmPtr<CFPos>,
+  mode: u8,
+  water: u8,
+  exclude: u64,
+) -> u32 {
+  let (from, to) = match (env.read(from), env.read(to)) {
+    (Ok(from), Ok(to)) => (FPos::from_ffi(env, from), FPos::from_ffi(env, to)),
+    _ => return 0,
+  };
+  let water = water == 1;
+  let exclude = if exclude == 0 { None } else { Some(EntityHandle::from_u64(exclude).eid) };
+  let world = env.wm.default_world();
+  let hit = match mode {
+    0 => world.raycast(from, to, water).map(|(pos, _)| (pos, None)),
+    1 => world.raycast_entity(from, to, exclude).map(|(pos, ent)| (pos, Some(ent))),
+    2 => world.raycast_any(from, to, water, exclude),
+    _ => return 0,
+  };
+  let Some((pos, ent)) = hit else { return 0 };
+  let entity = match ent {
+    Some(ent) => match ent.as_entity_ref(&world) {
+      Some(e) => e.handle().to_u64(),
+      // The entity was removed after it was hit.
+      None => return 0,
+    },
+    None => 0,
+  };
+  env.malloc_store(CRaycastHit { pos: pos.to_ffi(env), entity }).offset()
+}
+fn world_seed(env: &Env, wid: u32) -> u32 { env.result(|| Ok(env.world(wid)?.seed())) }
 fn block_data_for_kind(env: &Env, kind: u32) -> u32 {
   // TODO: Convert kind to server version
   let data = env.wm.block_converter().get(match block::Kind::from_id(kind) {
@@ -339,8 +881,8 @@ fn block_kind_for_type(env: &Env, ty: u32) -> u32 {
 }
 fn block_prop(env: &Env, ty: u32, name_ptr: WasmPtr<u8, Array>, name_len: u32) -> u32 {
   let ty = env.wm.block_converter().type_from_id(ty, env.ver);
-  let name = unsafe { name_ptr.get_utf8_str(env.mem(), name_len).unwrap() };
-  match ty.try_prop(name) {
+  let name = env.read_str(name_ptr, name_len).unwrap();
+  match ty.try_prop(&name) {
     Ok(prop) => {
       let cprop = prop.to_ffi(env);
       let ptr = env.malloc_store(cprop);
@@ -360,13 +902,12 @@ fn block_set_prop(
   prop: WasmPtr<CBlockPropValue>,
 ) -> u32 {
   let mut ty = env.wm.block_converter().type_from_id(ty, env.ver);
-  let name = unsafe { name_ptr.get_utf8_str(env.mem(), name_len).unwrap() };
-  let mem = env.mem();
-  let prop = match prop.deref(mem) {
-    Some(p) => p.get(),
-    None => return ty.id(),
+  let name = env.read_str(name_ptr, name_len).unwrap();
+  let prop = match env.read(prop) {
+    Ok(p) => p,
+    Err(_) => return ty.id(),
   };
-  match ty.try_set_prop(name, &block::PropValueStore::from_ffi(env, prop)) {
+  match ty.try_set_prop(&name, &block::PropValueStore::from_ffi(env, prop)) {
     Ok(()) => env.wm.block_converter().to_old(ty.id(), env.ver),
     Err(e) => {
       error!("plugin tried to set invalid property: {e}");
@@ -377,40 +918,35 @@ fn block_set_prop(
 
 fn add_command(env: &Env, cmd: WasmPtr<CCommand>) {
   fn command_from_env(env: &Env, cmd: WasmPtr<CCommand>) -> Option<Command> {
-    unsafe {
-      let mem = env.mem();
-      let cmd = match cmd.deref(mem) {
-        Some(c) => c.get(),
-        None => return None,
-      };
-      let name = cmd.name.ptr.get_utf8_str(mem, cmd.name.len)?.into();
-      let parser = <Option<Parser>>::from_ffi(env, cmd.parser);
-      let ty = match cmd.node_type {
-        0 => NodeType::Literal,
-        1 => NodeType::Argument(parser.unwrap()),
-        _ => return None,
-      };
-      let mut children = Vec::with_capacity(cmd.children.len as usize);
-      for i in 0..cmd.children.len {
-        children
-          .push(command_from_env(env, WasmPtr::new(cmd.children.get_ptr(i).unwrap() as u32))?);
-      }
-
-      Some(Command::new_from_plugin(name, ty, children, cmd.optional.as_bool()))
+    let cmd = env.read(cmd).ok()?;
+    let name = env.read_str(cmd.name.ptr, cmd.name.len).ok()?;
+    let parser = <Option<Parser>>::from_ffi(env, cmd.parser);
+    let ty = match cmd.node_type {
+      0 => NodeType::Literal,
+      1 => NodeType::Argument(parser.unwrap()),
+      _ => return None,
+    };
+    let mut children = Vec::with_capacity(cmd.children.len as usize);
+    for i in 0..cmd.children.len {
+      children.push(command_from_env(env, WasmPtr::new(cmd.children.get_ptr(i).unwrap() as u32))?);
     }
+
+    Some(Command::new_from_plugin(name, ty, children, cmd.optional.as_bool()))
   }
+  let runtime = env.runtime.get().expect("plugin runtime not set").clone();
   if let Some(cmd) = command_from_env(env, cmd) {
-    let e = env;
-    let env = env.clone();
-    e.wm.commands().add(cmd, move |_, player, args| {
+    env.wm.commands().add(cmd, move |_, player, args| {
+      // The plugin has been unloaded.
+      let Some(rt) = runtime.upgrade() else { return };
+      let env = Env::new(rt.data(), rt.memory());
       let id = match player {
         Some(p) => env.malloc_store(p.id().to_ffi(&env)),
         None => WasmPtr::new(0),
       };
       let args = env.malloc_store(args.as_slice().to_ffi(&env));
-      match env.on_command.get_ref().unwrap().call(id, args) {
-        Ok(()) => {}
-        Err(e) => error!("couldn't execute command on wasm: {e}"),
+      let args = [Val::I32(id.offset() as i32), Val::I32(args.offset() as i32)];
+      if let Err(e) = rt.call("on_command", &args) {
+        error!("couldn't execute command on wasm: {e}");
       }
     });
   }
@@ -432,37 +968,72 @@ fn time_since_start(_env: &Env) -> u64 {
   }
 }
 
-pub fn imports(store: &Store, wm: Arc<WorldManager>, name: String) -> ImportObject {
-  let env = Env {
-    memory: LazyInit::new(),
-    wasm_malloc: LazyInit::new(),
-    on_command: LazyInit::new(),
-    wm,
-    // TODO: Fetch this from the plugin
-    ver: BlockVersion::latest(),
-    name: Arc::new(name),
-  };
-  imports! {
-    "env" => {
-      "bb_log" => Function::new_native_with_env(store, env.clone(), log),
-      "bb_add_command" => Function::new_native_with_env(store, env.clone(), add_command),
-      "bb_block_data_for_kind" => Function::new_native_with_env(store, env.clone(), block_data_for_kind),
-      "bb_block_kind_for_type" => Function::new_native_with_env(store, env.clone(), block_kind_for_type),
-      "bb_block_prop" => Function::new_native_with_env(store, env.clone(), block_prop),
-      "bb_block_set_prop" => Function::new_native_with_env(store, env.clone(), block_set_prop),
-      "bb_broadcast" => Function::new_native_with_env(store, env.clone(), broadcast),
-      "bb_player_username" => Function::new_native_with_env(store, env.clone(), player_username),
-      "bb_player_pos" => Function::new_native_with_env(store, env.clone(), player_pos),
-      "bb_player_look_as_vec" => Function::new_native_with_env(store, env.clone(), player_look_as_vec),
-      "bb_player_world" => Function::new_native_with_env(store, env.clone(), player_world),
-      "bb_player_send_particle" => Function::new_native_with_env(store, env.clone(), player_send_particle),
-      "bb_world_set_block" => Function::new_native_with_env(store, env.clone(), world_set_block),
-      "bb_world_set_block_kind" => Function::new_native_with_env(store, env.clone(), world_set_block_kind),
-      "bb_world_get_block" => Function::new_native_with_env(store, env.clone(), world_get_block),
-      "bb_world_players" => Function::new_native_with_env(store, env.clone(), world_players),
-      "bb_world_spawn_particle" => Function::new_native_with_env(store, env.clone(), world_spawn_particle),
-      "bb_world_raycast" => Function::new_native_with_env(store, env.clone(), world_raycast),
-      "bb_time_since_start" => Function::new_native_with_env(store, env, time_since_start),
-    }
-  }
+/// Returns every host function, along with the name plugins import it with.
+/// These are all in the `env` module.
+pub fn imports() -> Vec<(&'static str, Box<dyn HostFunc>)> {
+  vec![
+    ("bb_log", log.into_host_func()),
+    ("bb_add_command", add_command.into_host_func()),
+    ("bb_block_data_for_kind", block_data_for_kind.into_host_func()),
+    ("bb_block_kind_for_type", block_kind_for_type.into_host_func()),
+    ("bb_block_prop", block_prop.into_host_func()),
+    ("bb_block_set_prop", block_set_prop.into_host_func()),
+    ("bb_broadcast", broadcast.into_host_func()),
+    ("bb_player_username", player_username.into_host_func()),
+    ("bb_player_pos", player_pos.into_host_func()),
+    ("bb_player_look_as_vec", player_look_as_vec.into_host_func()),
+    ("bb_player_world", player_world.into_host_func()),
+    ("bb_player_teleport_world", player_teleport_world.into_host_func()),
+    ("bb_player_send_particle", player_send_particle.into_host_func()),
+    ("bb_player_attribute", player_attribute.into_host_func()),
+    ("bb_player_set_attribute_base", player_set_attribute_base.into_host_func()),
+    ("bb_player_set_skin", player_set_skin.into_host_func()),
+    ("bb_player_reset_skin", player_reset_skin.into_host_func()),
+    ("bb_player_brand", player_brand.into_host_func()),
+    ("bb_player_mods", player_mods.into_host_func()),
+    ("bb_player_set_hidden", player_set_hidden.into_host_func()),
+    ("bb_player_set_selection", player_set_selection.into_host_func()),
+    ("bb_player_edit_selection", player_edit_selection.into_host_func()),
+    ("bb_player_remember", player_remember.into_host_func()),
+    ("bb_player_undo", player_undo.into_host_func()),
+    ("bb_player_redo", player_redo.into_host_func()),
+    ("bb_player_ban", player_ban.into_host_func()),
+    ("bb_player_pardon", player_pardon.into_host_func()),
+    ("bb_player_ban_reason", player_ban_reason.into_host_func()),
+    ("bb_player_ban_expires", player_ban_expires.into_host_func()),
+    ("bb_world_set_block", world_set_block.into_host_func()),
+    ("bb_world_set_block_kind", world_set_block_kind.into_host_func()),
+    ("bb_world_get_block", world_get_block.into_host_func()),
+    ("bb_world_set_spawner", world_set_spawner.into_host_func()),
+    ("bb_world_set_spawner_delay", world_set_spawner_delay.into_host_func()),
+    ("bb_world_players", world_players.into_host_func()),
+    ("bb_world_spawn_text", world_spawn_text.into_host_func()),
+    ("bb_world_set_text", world_set_text.into_host_func()),
+    ("bb_world_remove_entity", world_remove_entity.into_host_func()),
+    ("bb_world_spawn_armor_stand", world_spawn_armor_stand.into_host_func()),
+    ("bb_world_set_armor_stand_pose", world_set_armor_stand_pose.into_host_func()),
+    ("bb_world_set_armor_stand_flag", world_set_armor_stand_flag.into_host_func()),
+    ("bb_world_set_equipment", world_set_equipment.into_host_func()),
+    ("bb_world_disguise", world_disguise.into_host_func()),
+    ("bb_world_undisguise", world_undisguise.into_host_func()),
+    ("bb_world_paste_schematic", world_paste_schematic.into_host_func()),
+    ("bb_world_save_structure", world_save_structure.into_host_func()),
+    ("bb_world_paste_structure", world_paste_structure.into_host_func()),
+    ("bb_load_structure", load_structure.into_host_func()),
+    ("bb_write_structure", write_structure.into_host_func()),
+    ("bb_remove_structure", remove_structure.into_host_func()),
+    ("bb_world_snapshot_region", world_snapshot_region.into_host_func()),
+    ("bb_world_restore_snapshot", world_restore_snapshot.into_host_func()),
+    ("bb_world_remove_snapshot", world_remove_snapshot.into_host_func()),
+    ("bb_world_light_portal", world_light_portal.into_host_func()),
+    ("bb_world_add_chunk_ticket", world_add_chunk_ticket.into_host_func()),
+    ("bb_world_remove_chunk_ticket", world_remove_chunk_ticket.into_host_func()),
+    ("bb_world_set_force_loaded", world_set_force_loaded.into_host_func()),
+    ("bb_world_spawn_particle", world_spawn_particle.into_host_func()),
+    ("bb_world_raycast", world_raycast.into_host_func()),
+    ("bb_world_raycast_entity", world_raycast_entity.into_host_func()),
+    ("bb_world_raycast_hit", world_raycast_hit.into_host_func()),
+    ("bb_world_seed", world_seed.into_host_func()),
+    ("bb_time_since_start", time_since_start.into_host_func()),
+  ]
 }
@@ -0,0 +1,140 @@
+//! Converts the host functions in [`funcs`](super::funcs) into a form that
+//! any [`Runtime`](super::runtime::Runtime) can call. Each runtime registers
+//! functions by their wasm signature, and passes arguments as a list of
+//! [`Val`]s, so this handles converting those to and from the rust types.
+
+use super::{
+  runtime::{Val, ValType},
+  Env,
+};
+use bb_ffi::WasmPtr;
+use std::marker::PhantomData;
+
+/// An argument to a host function.
+pub trait Param: Sized {
+  const TY: ValType;
+
+  fn from_val(val: Val) -> Option<Self>;
+}
+/// The return value of a host function.
+pub trait Ret {
+  const TY: Option<ValType>;
+
+  fn into_val(self) -> Option<Val>;
+}
+
+macro_rules! param {
+  ( $($ty:ty => $variant:ident),* ) => {
+    $(
+      impl Param for $ty {
+        const TY: ValType = ValType::$variant;
+
+        fn from_val(val: Val) -> Option<Self> {
+          match val {
+            Val::$variant(v) => Some(v as $ty),
+            _ => None,
+          }
+        }
+      }
+    )*
+  }
+}
+macro_rules! ret {
+  ( $($ty:ty => $variant:ident as $cast:ty),* ) => {
+    $(
+      impl Ret for $ty {
+        const TY: Option<ValType> = Some(ValType::$variant);
+
+        fn into_val(self) -> Option<Val> { Some(Val::$variant(self as $cast)) }
+      }
+    )*
+  }
+}
+
+param!(u8 => I32, u32 => I32, i32 => I32, u64 => I64, i64 => I64, f32 => F32, f64 => F64);
+ret!(u32 => I32 as i32, i32 => I32 as i32, u64 => I64 as i64, i64 => I64 as i64);
+
+impl<T, Ty> Param for WasmPtr<T, Ty> {
+  const TY: ValType = ValType::I32;
+
+  fn from_val(val: Val) -> Option<Self> {
+    match val {
+      Val::I32(v) => Some(WasmPtr::new(v as u32)),
+      _ => None,
+    }
+  }
+}
+impl Ret for () {
+  const TY: Option<ValType> = None;
+
+  fn into_val(self) -> Option<Val> { None }
+}
+
+/// A function that plugins can import.
+pub trait HostFunc: Send + Sync {
+  fn params(&self) -> Vec<ValType>;
+  fn results(&self) -> Vec<ValType>;
+  /// Calls this function. The runtime must pass arguments that match
+  /// [`params`](Self::params).
+  fn call(&self, env: &Env, args: &[Val]) -> Option<Val>;
+}
+
+/// Implemented for every function that takes an [`Env`], followed by wasm
+/// arguments.
+pub trait IntoHostFunc<Args, R> {
+  fn into_host_func(self) -> Box<dyn HostFunc>;
+}
+
+struct Func<F, M> {
+  f:       F,
+  _marker: PhantomData<M>,
+}
+
+macro_rules! host_func {
+  ( $($arg:ident),* ) => {
+    impl<F, R, $($arg),*> HostFunc for Func<F, fn($($arg),*) -> R>
+    where
+      F: Fn(&Env<'_>, $($arg),*) -> R + Send + Sync + 'static,
+      R: Ret + 'static,
+      $($arg: Param + 'static),*
+    {
+      fn params(&self) -> Vec<ValType> { vec![$($arg::TY),*] }
+      fn results(&self) -> Vec<ValType> { R::TY.into_iter().collect() }
+      #[allow(non_snake_case, unused_variables, unused_mut)]
+      fn call(&self, env: &Env, args: &[Val]) -> Option<Val> {
+        let mut args = args.iter();
+        $(
+          let $arg = args
+            .next()
+            .copied()
+            .and_then($arg::from_val)
+            .expect("runtime passed the wrong argument types");
+        )*
+        (self.f)(env, $($arg),*).into_val()
+      }
+    }
+    impl<F, R, $($arg),*> IntoHostFunc<($($arg,)*), R> for F
+    where
+      F: Fn(&Env<'_>, $($arg),*) -> R + Send + Sync + 'static,
+      R: Ret + 'static,
+      $($arg: Param + 'static),*
+    {
+      fn into_host_func(self) -> Box<dyn HostFunc> {
+        Box::new(Func::<F, fn($($arg),*) -> R> { f: self, _marker: PhantomData })
+      }
+    }
+  }
+}
+
+host_func!();
+host_func!(A);
+host_func!(A, B);
+host_func!(A, B, C);
+host_func!(A, B, C, D);
+host_func!(A, B, C, D, E);
+host_func!(A, B, C, D, E, G);
+host_func!(A, B, C, D, E, G, H);
+host_func!(A, B, C, D, E, G, H, I);
+host_func!(A, B, C, D, E, G, H, I, J);
+host_func!(A, B, C, D, E, G, H, I, J, K);
+host_func!(A, B, C, D, E, G, H, I, J, K, L);
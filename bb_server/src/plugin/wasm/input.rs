@@ -1,102 +1,51 @@
-use super::Input;
-use bb_ffi::CUUID;
-use wasmer::{FromToNativeWasmType, NativeFunc, WasmPtr, WasmTypeList};
-
-type Result<T> = std::result::Result<T, wasmer::RuntimeError>;
+use super::{runtime::Val, Input};
+use bb_ffi::{WasmPtr, CUUID};
 
 impl Input for () {
-  type WasmArgs = ();
-  fn call_native<Rets: WasmTypeList>(
-    &self,
-    native: &NativeFunc<Self::WasmArgs, Rets>,
-  ) -> Result<Rets> {
-    native.call()
-  }
+  fn args(&self, _: &mut Vec<Val>) {}
 }
 
-impl<A, B> Input for (A, B)
-where
-  A: Input + FromToNativeWasmType + Copy,
-  B: Input + FromToNativeWasmType + Copy,
-{
-  type WasmArgs = (A, B);
-  fn call_native<Rets: WasmTypeList>(
-    &self,
-    native: &NativeFunc<Self::WasmArgs, Rets>,
-  ) -> Result<Rets> {
-    native.call(self.0, self.1)
+impl<A: Input, B: Input> Input for (A, B) {
+  fn args(&self, out: &mut Vec<Val>) {
+    self.0.args(out);
+    self.1.args(out);
   }
 }
 
-impl<A, B, C> Input for (A, B, C)
-where
-  A: Input + FromToNativeWasmType + Copy,
-  B: Input + FromToNativeWasmType + Copy,
-  C: Input + FromToNativeWasmType + Copy,
-{
-  type WasmArgs = (A, B, C);
-  fn call_native<Rets: WasmTypeList>(
-    &self,
-    native: &NativeFunc<Self::WasmArgs, Rets>,
-  ) -> Result<Rets> {
-    native.call(self.0, self.1, self.2)
+impl<A: Input, B: Input, C: Input> Input for (A, B, C) {
+  fn args(&self, out: &mut Vec<Val>) {
+    self.0.args(out);
+    self.1.args(out);
+    self.2.args(out);
   }
 }
 
-impl<A, B, C, D> Input for (A, B, C, D)
-where
-  A: Input + FromToNativeWasmType + Copy,
-  B: Input + FromToNativeWasmType + Copy,
-  C: Input + FromToNativeWasmType + Copy,
-  D: Input + FromToNativeWasmType + Copy,
-{
-  type WasmArgs = (A, B, C, D);
-  fn call_native<Rets: WasmTypeList>(
-    &self,
-    native: &NativeFunc<Self::WasmArgs, Rets>,
-  ) -> Result<Rets> {
-    native.call(self.0, self.1, self.2, self.3)
+impl<A: Input, B: Input, C: Input, D: Input> Input for (A, B, C, D) {
+  fn args(&self, out: &mut Vec<Val>) {
+    self.0.args(out);
+    self.1.args(out);
+    self.2.args(out);
+    self.3.args(out);
   }
 }
 
-impl<B, C, D> Input for (CUUID, B, C, D)
-where
-  B: Input + FromToNativeWasmType + Copy,
-  C: Input + FromToNativeWasmType + Copy,
-  D: Input + FromToNativeWasmType + Copy,
-{
-  type WasmArgs = (u32, u32, u32, u32, B, C, D);
-  fn call_native<Rets: WasmTypeList>(
-    &self,
-    native: &NativeFunc<Self::WasmArgs, Rets>,
-  ) -> Result<Rets> {
-    native.call(
-      self.0.bytes[0],
-      self.0.bytes[1],
-      self.0.bytes[2],
-      self.0.bytes[3],
-      self.1,
-      self.2,
-      self.3,
-    )
-  }
+/// UUIDs are passed as 4 separate arguments.
+impl Input for CUUID {
+  fn args(&self, out: &mut Vec<Val>) { out.extend(self.bytes.iter().map(|&b| Val::I32(b as i32))); }
 }
 
 impl Input for i32 {
-  type WasmArgs = i32;
-  fn call_native<Rets: WasmTypeList>(
-    &self,
-    native: &NativeFunc<Self::WasmArgs, Rets>,
-  ) -> Result<Rets> {
-    native.call(*self)
-  }
+  fn args(&self, out: &mut Vec<Val>) { out.push(Val::I32(*self)); }
 }
-impl<T: Copy> Input for WasmPtr<T> {
-  type WasmArgs = WasmPtr<T>;
-  fn call_native<Rets: WasmTypeList>(
-    &self,
-    native: &NativeFunc<Self::WasmArgs, Rets>,
-  ) -> Result<Rets> {
-    native.call(*self)
-  }
+impl Input for u32 {
+  fn args(&self, out: &mut Vec<Val>) { out.push(Val::I32(*self as i32)); }
+}
+impl Input for u64 {
+  fn args(&self, out: &mut Vec<Val>) { out.push(Val::I64(*self as i64)); }
+}
+impl Input for f32 {
+  fn args(&self, out: &mut Vec<Val>) { out.push(Val::F32(*self)); }
+}
+impl<T> Input for WasmPtr<T> {
+  fn args(&self, out: &mut Vec<Val>) { out.push(Val::I32(self.offset() as i32)); }
 }
@@ -1,25 +1,31 @@
+mod error;
 mod ffi;
 mod funcs;
+mod host;
 mod input;
 mod output;
+mod runtime;
 
 pub use ffi::{FromFfi, ToFfi};
-pub use funcs::Env;
+pub use funcs::{Env, PluginData};
+pub use runtime::RuntimeKind;
 
-use super::{CallError, GlobalServerEvent, PluginImpl, PluginReply, ServerEvent, ServerRequest};
+use super::{
+  CallError, GlobalRequest, GlobalServerEvent, PluginImpl, PluginReply, ServerEvent, ServerRequest,
+};
 use crate::{
   player::Player,
   world::{MultiChunk, WorldManager},
 };
-use bb_ffi::CUUID;
+use bb_common::util::UUID;
+use bb_ffi::{WasmPtr, CUUID};
 use parking_lot::Mutex;
+use runtime::{Memory, Runtime, RuntimeError, Val};
 use std::{fs, io, path::Path, process::Command, sync::Arc};
 use thiserror::Error;
-use wasmer::{Instance, Memory, Module, NativeFunc, Store, WasmPtr, WasmTypeList};
 
 pub struct Plugin {
-  inst_mem_lock: Mutex<()>,
-  inst:          Instance,
+  rt: Arc<dyn Runtime>,
 }
 
 /// This is the last argument to every wasm function. It is the pointer type (we
@@ -27,20 +33,19 @@ pub struct Plugin {
 /// into.
 type OUT = u32;
 
+/// Converts a UUID into the form that is passed to wasm functions.
+fn cuuid(id: UUID) -> CUUID { CUUID::from_u128(id.as_u128()) }
+
 trait Input {
-  type WasmArgs: WasmTypeList;
-  /// Calls native, and passes the pointer as the last argument.
-  fn call_native<Rets: WasmTypeList>(
-    &self,
-    native: &NativeFunc<Self::WasmArgs, Rets>,
-  ) -> Result<Rets, wasmer::RuntimeError>;
+  /// Appends the wasm arguments for this value to `out`.
+  fn args(&self, out: &mut Vec<Val>);
 }
 trait Output {
   /// Returns the size in pointers. This keeps everything aligned, and means we
   /// use `MemoryView<i32>` everywhere.
   fn size() -> u32;
   /// Returns `Self`, given the address in pointers.
-  fn from_addr(mem: &Memory, addr: OUT) -> Self;
+  fn from_addr(mem: &dyn Memory, addr: OUT) -> Self;
 }
 
 #[derive(Error, Debug)]
@@ -51,18 +56,23 @@ pub enum PluginCreateError {
   CompileFailed(String),
   #[error("couldn't find source: {0}")]
   Missing(io::Error),
-  #[error("could not instantiate plugin: {0}")]
-  InstantiationError(#[from] wasmer::InstantiationError),
-  #[error("could not compile plugin: {0}")]
-  CompileError(#[from] wasmer::CompileError),
+  #[error("could not load plugin: {0}")]
+  Load(Box<dyn std::error::Error + Send + Sync>),
+  #[error("unknown wasm runtime `{0}`, expected `wasmer` or `wasmtime`")]
+  UnknownRuntime(String),
+  #[error("the server was not built with {0} support")]
+  Disabled(RuntimeKind),
 }
 
 impl Plugin {
+  #[allow(clippy::too_many_arguments)]
   pub fn new(
     name: String,
     path: &Path,
     compile: String,
     output: String,
+    runtime: String,
+    worlds: Vec<String>,
     wm: Arc<WorldManager>,
   ) -> Result<Self, PluginCreateError> {
     if !compile.is_empty() {
@@ -79,49 +89,51 @@ impl Plugin {
         return Err(PluginCreateError::CompileFailed(String::from_utf8_lossy(&out.stderr).into()));
       }
     }
-    let store = Store::default();
-    let module =
-      Module::new(&store, fs::read(path.join(output)).map_err(PluginCreateError::Missing)?)?;
-    let import_object = funcs::imports(&store, wm, name);
-    let inst = Instance::new(&module, &import_object)?;
-    let plug = Plugin { inst_mem_lock: Mutex::new(()), inst };
+    let kind = RuntimeKind::from_config(&runtime)?;
+    let module = fs::read(path.join(output)).map_err(PluginCreateError::Missing)?;
+    let data = Arc::new(PluginData::new(wm, name, worlds));
+    let rt = kind.load(&module, data.clone())?;
+    // This can only fail if it was already set, and we just created `data`.
+    let _ = data.runtime.set(Arc::downgrade(&rt));
+    let plug = Plugin { rt };
     plug.call("init", ()).unwrap();
     Ok(plug)
   }
 
   fn call_bool<I: Input>(&self, name: &str, input: I) -> Result<bool, CallError> {
-    // Try to get function with int. If this fails, error.
-    // If the function doesn't exist, we error.
-    match self.inst.exports.get_native_function::<I::WasmArgs, u8>(name) {
-      Ok(func) => input.call_native(&func).map(|v| v != 0).map_err(CallError::keep),
-      Err(e) => Err(CallError::no_keep(e)),
+    match self.call_val(name, input)? {
+      Some(Val::I32(v)) => Ok(v != 0),
+      _ => Err(CallError::keep(RuntimeError::WrongType(name.into()))),
     }
   }
   fn call_int<I: Input>(&self, name: &str, input: I) -> Result<i32, CallError> {
-    // Try to get function with int. If this fails, error.
-    // If the function doesn't exist, we error.
-    match self.inst.exports.get_native_function::<I::WasmArgs, i32>(name) {
-      Ok(func) => input.call_native(&func).map_err(CallError::keep),
-      Err(e) => Err(CallError::no_keep(e)),
+    match self.call_val(name, input)? {
+      Some(Val::I32(v)) => Ok(v),
+      _ => Err(CallError::keep(RuntimeError::WrongType(name.into()))),
     }
   }
   fn call<I: Input>(&self, name: &str, input: I) -> Result<(), CallError> {
-    // Try to get function with int. If this fails, error.
-    // If the function doesn't exist, we error.
-    match self.inst.exports.get_native_function::<I::WasmArgs, ()>(name) {
-      Ok(func) => input.call_native(&func).map_err(CallError::keep),
-      Err(e) => Err(CallError::no_keep(e)),
+    match self.call_val(name, input)? {
+      None => Ok(()),
+      Some(_) => Err(CallError::keep(RuntimeError::WrongType(name.into()))),
     }
   }
+  fn call_val<I: Input>(&self, name: &str, input: I) -> Result<Option<Val>, CallError> {
+    let mut args = vec![];
+    input.args(&mut args);
+    self.rt.call(name, &args)
+  }
 
   fn malloc_str(&self, text: &str) -> Result<WasmPtr<u8>, CallError> {
     let ptr = self.call_int("wasm_malloc", (text.len() as i32 + 1, 1))? as u32;
-    let mem = self.inst.exports.get_memory("memory").unwrap();
-    unsafe {
-      let _guard = self.inst_mem_lock.lock();
-      let view = mem.data_unchecked_mut();
-      view[ptr as usize..ptr as usize + text.len()].copy_from_slice(text.as_bytes());
-      view[ptr as usize + text.len()] = 0; // Write the nul byte
+    let mut bytes = Vec::with_capacity(text.len() + 1);
+    bytes.extend_from_slice(text.as_bytes());
+    bytes.push(0); // Write the nul byte
+    if !self.rt.write(ptr, &bytes) {
+      return Err(CallError::keep(RuntimeError::Trap(
+        "wasm_malloc".into(),
+        "returned an invalid pointer".into(),
+      )));
     }
     Ok(WasmPtr::new(ptr))
   }
@@ -141,16 +153,17 @@ impl Plugin {
     if ptr == 0 {
       return Ok(());
     }
-    let mem = self.inst.exports.get_memory("memory").unwrap();
-    // SAFETY: The plugin has locked the generated chunk buffer, so we can read from
-    // it. Accessing this memory is safe for the plugin until we call
-    // `unlock_generated_chunk`. In order for the server to not get UB for a
-    // malicious plugin, we also lock `inst_mem_lock` for the duration that we have
-    // a reference to `data_unchecked`.
-    unsafe {
-      let _guard = self.inst_mem_lock.lock();
-      let view = mem.data_unchecked();
-      let chunk_data = &view[ptr as usize..];
+    // The plugin has locked the generated chunk buffer, so it won't change until
+    // we call `unlock_generated_chunk`. The runtime also makes sure the plugin
+    // can't run while we are reading its memory.
+    self.rt.with_memory(&mut |view| {
+      let chunk_data = match view.get(ptr as usize..) {
+        Some(data) => data,
+        None => {
+          error!("bad chunk: invalid pointer");
+          return;
+        }
+      };
       let mut reader = bb_transfer::MessageReader::new(chunk_data);
       match reader.read::<Vec<bb_common::chunk::paletted::Section>>() {
         Ok(sections) => {
@@ -161,7 +174,7 @@ impl Plugin {
         }
         Err(e) => error!("bad chunk: {e}"),
       }
-    }
+    });
     self.call("unlock_generated_chunk", ())?;
 
     Ok(())
@@ -191,24 +204,35 @@ impl PluginImpl for Plugin {
   fn req(&self, player: Arc<Player>, request: ServerRequest) -> Result<PluginReply, CallError> {
     Ok(PluginReply::Cancel {
       allow: match request {
-        ServerRequest::BlockPlace { pos, .. } => self.call_bool(
-          "on_block_place",
-          (
-            CUUID {
-              bytes: [
-                u32::from_ne_bytes(player.id().as_le_bytes()[0..4].try_into().unwrap()),
-                u32::from_ne_bytes(player.id().as_le_bytes()[4..8].try_into().unwrap()),
-                u32::from_ne_bytes(player.id().as_le_bytes()[8..12].try_into().unwrap()),
-                u32::from_ne_bytes(player.id().as_le_bytes()[12..16].try_into().unwrap()),
-              ],
-            },
-            pos.x(),
-            pos.y(),
-            pos.z(),
-          ),
+        ServerRequest::BlockPlace { pos, .. } => {
+          self.call_bool("on_block_place", (cuuid(player.id()), pos.x(), pos.y(), pos.z()))?
+        }
+        ServerRequest::EntityAttack(req) => {
+          let damage = req.attack.lock().damage;
+          self.call_bool("on_entity_attack", (cuuid(player.id()), req.entity.to_u64(), damage))?
+        }
+        ServerRequest::ItemPickup(req) => self.call_bool(
+          "on_item_pickup",
+          (cuuid(player.id()), req.entity.to_u64(), req.stack.amount() as i32),
         )?,
         _ => true,
       },
     })
   }
+  fn global_req(&self, request: GlobalRequest) -> Result<PluginReply, CallError> {
+    Ok(PluginReply::Cancel {
+      allow: match request {
+        GlobalRequest::PlayerPreJoin(req) => {
+          let name_ptr = self.malloc_str(&req.username)?;
+          let allow = self
+            .call_bool("on_player_pre_join", (cuuid(req.id), name_ptr, req.username.len() as i32));
+          self.free_str(name_ptr, &req.username)?;
+          allow?
+        }
+        GlobalRequest::ProjectileHit(req) => {
+          self.call_bool("on_projectile_hit", (req.projectile.to_u64(), req.hit.to_u64()))?
+        }
+      },
+    })
+  }
 }
@@ -0,0 +1,55 @@
+//! Compiling a large plugin can take a few seconds, so compiled modules are
+//! stored in `plugins/.cache`. Each file is keyed by a hash of the wasm and
+//! the compiler that built it, so a changed plugin or a server upgrade will
+//! just cause a cache miss.
+
+use super::RuntimeKind;
+use sha1::{Digest, Sha1};
+use std::{fs, io, path::PathBuf};
+
+/// A single cached module.
+pub struct ModuleCache {
+  dir:    PathBuf,
+  /// The start of every file for this plugin and runtime. This is used to
+  /// remove old versions of the plugin.
+  prefix: String,
+  name:   String,
+}
+
+impl ModuleCache {
+  /// Creates a cache entry for the given plugin. `compiler` should change
+  /// whenever the runtime's compiled output would change, such as the runtime
+  /// version.
+  pub fn new(plugin: &str, kind: RuntimeKind, compiler: &str, module: &[u8]) -> Self {
+    let mut hash = Sha1::new();
+    hash.update(compiler.as_bytes());
+    hash.update(module);
+    let prefix = format!("{plugin}-{kind}-");
+    let name = format!("{prefix}{:x}.bin", hash.finalize());
+    ModuleCache { dir: PathBuf::new().join("plugins").join(".cache"), prefix, name }
+  }
+
+  /// Returns the compiled module, if it has been cached.
+  pub fn load(&self) -> Option<Vec<u8>> { fs::read(self.dir.join(&self.name)).ok() }
+
+  /// Stores the compiled module, and removes any older versions of this
+  /// plugin. Errors are logged, as the plugin can still be loaded without the
+  /// cache.
+  pub fn store(&self, compiled: &[u8]) {
+    if let Err(e) = self.try_store(compiled) {
+      warn!("could not cache compiled plugin: {e}");
+    }
+  }
+
+  fn try_store(&self, compiled: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(&self.dir)?;
+    for entry in fs::read_dir(&self.dir)? {
+      let name = entry?.file_name();
+      let name = name.to_string_lossy();
+      if name.starts_with(&self.prefix) && name != self.name {
+        fs::remove_file(self.dir.join(&*name))?;
+      }
+    }
+    fs::write(self.dir.join(&self.name), compiled)
+  }
+}
@@ -0,0 +1,141 @@
+//! The wasm runtimes that plugins can run on. The rest of the wasm plugin
+//! host only talks to plugins through [`Runtime`] and [`Memory`], so that
+//! plugins can run on either wasmer or wasmtime.
+//!
+//! Which runtimes are available depends on the features the server was
+//! built with: `wasm_plugins` adds wasmer, and `wasmtime_plugins` adds
+//! wasmtime. If both are enabled, each plugin can choose one with the
+//! `wasm.runtime` option in its `plugin.toml`.
+
+mod cache;
+#[cfg(feature = "wasmer")]
+mod wasmer;
+#[cfg(feature = "wasmtime")]
+mod wasmtime;
+
+use super::{funcs::PluginData, PluginCreateError};
+use crate::plugin::CallError;
+use std::{fmt, sync::Arc};
+use thiserror::Error;
+
+/// A value passed to or returned from a wasm function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Val {
+  I32(i32),
+  I64(i64),
+  F32(f32),
+  F64(f64),
+}
+
+/// The type of a [`Val`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValType {
+  I32,
+  I64,
+  F32,
+  F64,
+}
+
+impl Val {
+  pub fn ty(&self) -> ValType {
+    match self {
+      Val::I32(_) => ValType::I32,
+      Val::I64(_) => ValType::I64,
+      Val::F32(_) => ValType::F32,
+      Val::F64(_) => ValType::F64,
+    }
+  }
+}
+
+/// A plugin's linear memory, along with its allocator.
+pub trait Memory {
+  /// Returns the size of the memory, in bytes.
+  fn size(&self) -> u64;
+  /// Copies `buf.len()` bytes starting at `ptr` into `buf`. Returns `false` if
+  /// any of those bytes are out of bounds.
+  fn read(&self, ptr: u32, buf: &mut [u8]) -> bool;
+  /// Copies `data` into memory, starting at `ptr`. Returns `false` if any of
+  /// the bytes are out of bounds.
+  fn write(&self, ptr: u32, data: &[u8]) -> bool;
+  /// Calls the plugin's `wasm_malloc`, and returns the new pointer. This
+  /// panics if the plugin doesn't export `wasm_malloc`.
+  fn malloc(&self, size: u32, align: u32) -> u32;
+}
+
+/// A loaded plugin.
+pub trait Runtime: Memory + Send + Sync {
+  /// Returns the data the plugin was created with.
+  fn data(&self) -> &Arc<PluginData>;
+  /// Returns this runtime as a [`Memory`].
+  fn memory(&self) -> &dyn Memory;
+  /// Calls the exported function `name`, and returns its result, if it has
+  /// one.
+  ///
+  /// Host functions are given their own [`Memory`] while this is running, so
+  /// they must not call this (or any other method on the runtime), as some
+  /// runtimes lock while the plugin is running.
+  fn call(&self, name: &str, args: &[Val]) -> Result<Option<Val>, CallError>;
+  /// Calls `f` with the plugin's entire memory. The plugin cannot run while
+  /// `f` is running.
+  fn with_memory(&self, f: &mut dyn FnMut(&[u8]));
+}
+
+/// An error from calling a wasm function.
+#[derive(Error, Debug)]
+pub enum RuntimeError {
+  #[error("function `{0}` does not exist")]
+  Missing(String),
+  #[error("function `{0}` has the wrong signature")]
+  WrongType(String),
+  #[error("error calling `{0}`: {1}")]
+  Trap(String, String),
+}
+
+/// The runtimes a plugin can be loaded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeKind {
+  Wasmer,
+  Wasmtime,
+}
+
+impl RuntimeKind {
+  /// Parses the `wasm.runtime` option. An empty string will choose wasmer if
+  /// it is available, and wasmtime otherwise.
+  pub fn from_config(name: &str) -> Result<Self, PluginCreateError> {
+    match name {
+      "" if cfg!(feature = "wasmer") => Ok(RuntimeKind::Wasmer),
+      "" => Ok(RuntimeKind::Wasmtime),
+      "wasmer" => Ok(RuntimeKind::Wasmer),
+      "wasmtime" => Ok(RuntimeKind::Wasmtime),
+      _ => Err(PluginCreateError::UnknownRuntime(name.into())),
+    }
+  }
+
+  /// Compiles and instantiates the given module. This does not call `init`.
+  ///
+  /// The compiled module is cached, so the next time the same module is
+  /// loaded, it won't need to be compiled again.
+  pub fn load(
+    self,
+    module: &[u8],
+    data: Arc<PluginData>,
+  ) -> Result<Arc<dyn Runtime>, PluginCreateError> {
+    match self {
+      #[cfg(feature = "wasmer")]
+      RuntimeKind::Wasmer => Ok(Arc::new(wasmer::WasmerRuntime::new(module, data)?)),
+      #[cfg(feature = "wasmtime")]
+      RuntimeKind::Wasmtime => Ok(Arc::new(wasmtime::WasmtimeRuntime::new(module, data)?)),
+      #[allow(unreachable_patterns)]
+      _ => Err(PluginCreateError::Disabled(self)),
+    }
+  }
+}
+
+impl fmt::Display for RuntimeKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Wasmer => write!(f, "wasmer"),
+      Self::Wasmtime => write!(f, "wasmtime"),
+    }
+  }
+}
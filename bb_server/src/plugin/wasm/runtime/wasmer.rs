@@ -0,0 +1,199 @@
+use super::{cache::ModuleCache, Memory, Runtime, RuntimeError, RuntimeKind, Val, ValType};
+use crate::plugin::{
+  wasm::{funcs, Env, PluginCreateError, PluginData},
+  CallError,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use wasmer::{
+  Exports, Function, FunctionType, ImportObject, Instance, LazyInit, Module, NativeFunc, Store,
+  Type, WasmerEnv,
+};
+
+/// The env passed to every host function. This is cloned for every function,
+/// and wasmer fills in the exports once the plugin is instantiated.
+#[derive(WasmerEnv, Clone)]
+struct HostEnv {
+  #[wasmer(export)]
+  memory:      LazyInit<wasmer::Memory>,
+  #[wasmer(export)]
+  wasm_malloc: LazyInit<NativeFunc<(u32, u32), u32>>,
+  data:        Arc<PluginData>,
+}
+
+pub struct WasmerRuntime {
+  inst:   Instance,
+  memory: wasmer::Memory,
+  malloc: NativeFunc<(u32, u32), u32>,
+  data:   Arc<PluginData>,
+  /// Locked whenever the server reads or writes the plugin's memory outside of
+  /// a host function.
+  lock:   Mutex<()>,
+}
+
+fn to_type(ty: ValType) -> Type {
+  match ty {
+    ValType::I32 => Type::I32,
+    ValType::I64 => Type::I64,
+    ValType::F32 => Type::F32,
+    ValType::F64 => Type::F64,
+  }
+}
+fn to_val(val: Val) -> wasmer::Val {
+  match val {
+    Val::I32(v) => wasmer::Val::I32(v),
+    Val::I64(v) => wasmer::Val::I64(v),
+    Val::F32(v) => wasmer::Val::F32(v),
+    Val::F64(v) => wasmer::Val::F64(v),
+  }
+}
+fn from_val(val: &wasmer::Val) -> Option<Val> {
+  Some(match *val {
+    wasmer::Val::I32(v) => Val::I32(v),
+    wasmer::Val::I64(v) => Val::I64(v),
+    wasmer::Val::F32(v) => Val::F32(v),
+    wasmer::Val::F64(v) => Val::F64(v),
+    _ => return None,
+  })
+}
+
+fn read(mem: &wasmer::Memory, ptr: u32, buf: &mut [u8]) -> bool {
+  // SAFETY: The caller makes sure the plugin isn't running on another thread,
+  // either by being inside a host function, or by holding `lock`.
+  let data = unsafe { mem.data_unchecked() };
+  match data.get(ptr as usize..ptr as usize + buf.len()) {
+    Some(src) => {
+      buf.copy_from_slice(src);
+      true
+    }
+    None => false,
+  }
+}
+fn write(mem: &wasmer::Memory, ptr: u32, data: &[u8]) -> bool {
+  // SAFETY: See `read`.
+  let mem = unsafe { mem.data_unchecked_mut() };
+  match mem.get_mut(ptr as usize..ptr as usize + data.len()) {
+    Some(dst) => {
+      dst.copy_from_slice(data);
+      true
+    }
+    None => false,
+  }
+}
+
+impl HostEnv {
+  fn mem(&self) -> &wasmer::Memory { self.memory.get_ref().expect("Env not initialized") }
+}
+
+impl Memory for HostEnv {
+  fn size(&self) -> u64 { self.mem().data_size() }
+  fn read(&self, ptr: u32, buf: &mut [u8]) -> bool { read(self.mem(), ptr, buf) }
+  fn write(&self, ptr: u32, data: &[u8]) -> bool { write(self.mem(), ptr, data) }
+  fn malloc(&self, size: u32, align: u32) -> u32 {
+    self
+      .wasm_malloc
+      .get_ref()
+      .expect("Env not initialized")
+      .call(size, align)
+      .unwrap_or_else(|e| panic!("{e}"))
+  }
+}
+
+impl WasmerRuntime {
+  pub fn new(module: &[u8], data: Arc<PluginData>) -> Result<Self, PluginCreateError> {
+    let store = Store::default();
+    let cache = ModuleCache::new(&data.name, RuntimeKind::Wasmer, wasmer::VERSION, module);
+    // SAFETY: The cache is only written by the server, with the output of
+    // `Module::serialize`. Anyone who can change the cache could also change the
+    // plugin itself.
+    let module = match cache.load().and_then(|b| unsafe { Module::deserialize(&store, &b) }.ok()) {
+      Some(module) => module,
+      None => {
+        let module = Module::new(&store, module).map_err(|e| PluginCreateError::Load(e.into()))?;
+        match module.serialize() {
+          Ok(compiled) => cache.store(&compiled),
+          Err(e) => warn!("could not serialize plugin {}: {e}", data.name),
+        }
+        module
+      }
+    };
+
+    let env = HostEnv {
+      memory:      LazyInit::new(),
+      wasm_malloc: LazyInit::new(),
+      data:        data.clone(),
+    };
+    let mut ns = Exports::new();
+    for (name, func) in funcs::imports() {
+      let ty = FunctionType::new(
+        func.params().into_iter().map(to_type).collect::<Vec<_>>(),
+        func.results().into_iter().map(to_type).collect::<Vec<_>>(),
+      );
+      let f = move |env: &HostEnv, args: &[wasmer::Val]| {
+        let args: Vec<Val> = args.iter().filter_map(from_val).collect();
+        let ret = func.call(&Env::new(&env.data, env), &args);
+        Ok(ret.into_iter().map(to_val).collect())
+      };
+      ns.insert(name, Function::new_with_env(&store, ty, env.clone(), f));
+    }
+    let mut import_object = ImportObject::new();
+    import_object.register("env", ns);
+
+    let inst =
+      Instance::new(&module, &import_object).map_err(|e| PluginCreateError::Load(e.into()))?;
+    let memory =
+      inst.exports.get_memory("memory").map_err(|e| PluginCreateError::Load(e.into()))?.clone();
+    let malloc = inst
+      .exports
+      .get_native_function::<(u32, u32), u32>("wasm_malloc")
+      .map_err(|e| PluginCreateError::Load(e.into()))?;
+    Ok(WasmerRuntime { inst, memory, malloc, data, lock: Mutex::new(()) })
+  }
+}
+
+impl Memory for WasmerRuntime {
+  fn size(&self) -> u64 { self.memory.data_size() }
+  fn read(&self, ptr: u32, buf: &mut [u8]) -> bool {
+    let _guard = self.lock.lock();
+    read(&self.memory, ptr, buf)
+  }
+  fn write(&self, ptr: u32, data: &[u8]) -> bool {
+    let _guard = self.lock.lock();
+    write(&self.memory, ptr, data)
+  }
+  fn malloc(&self, size: u32, align: u32) -> u32 {
+    self.malloc.call(size, align).unwrap_or_else(|e| panic!("{e}"))
+  }
+}
+
+impl Runtime for WasmerRuntime {
+  fn data(&self) -> &Arc<PluginData> { &self.data }
+  fn memory(&self) -> &dyn Memory { self }
+
+  fn call(&self, name: &str, args: &[Val]) -> Result<Option<Val>, CallError> {
+    let func = match self.inst.exports.get_function(name) {
+      Ok(f) => f,
+      Err(_) => return Err(CallError::no_keep(RuntimeError::Missing(name.into()))),
+    };
+    let params: Vec<_> = args.iter().map(|v| to_type(v.ty())).collect();
+    if func.ty().params() != params.as_slice() {
+      return Err(CallError::no_keep(RuntimeError::WrongType(name.into())));
+    }
+    let args: Vec<_> = args.iter().copied().map(to_val).collect();
+    let ret = func
+      .call(&args)
+      .map_err(|e| CallError::keep(RuntimeError::Trap(name.into(), e.to_string())))?;
+    match &*ret {
+      [] => Ok(None),
+      [v] => Ok(from_val(v)),
+      _ => Err(CallError::keep(RuntimeError::WrongType(name.into()))),
+    }
+  }
+
+  fn with_memory(&self, f: &mut dyn FnMut(&[u8])) {
+    let _guard = self.lock.lock();
+    // SAFETY: We hold `lock`, so nothing else on the server is accessing this
+    // memory.
+    f(unsafe { self.memory.data_unchecked() });
+  }
+}
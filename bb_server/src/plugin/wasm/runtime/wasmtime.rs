@@ -0,0 +1,192 @@
+use super::{cache::ModuleCache, Memory, Runtime, RuntimeError, RuntimeKind, Val, ValType};
+use crate::plugin::{
+  wasm::{funcs, Env, PluginCreateError, PluginData},
+  CallError,
+};
+use parking_lot::Mutex;
+use std::{
+  cell::RefCell,
+  collections::hash_map::DefaultHasher,
+  hash::{Hash, Hasher},
+  sync::Arc,
+};
+use wasmtime::{Caller, Engine, Extern, FuncType, Instance, Linker, Module, Store, TypedFunc};
+
+type Data = Arc<PluginData>;
+
+pub struct WasmtimeRuntime {
+  /// Everything in wasmtime needs a store, so this is locked for every call,
+  /// and for every memory access outside of a host function.
+  store:    Mutex<Store<Data>>,
+  instance: Instance,
+  memory:   wasmtime::Memory,
+  malloc:   TypedFunc<(u32, u32), u32>,
+  data:     Data,
+}
+
+/// The plugin's memory, as seen from inside a host function.
+struct CallerMemory<'a> {
+  caller: RefCell<Caller<'a, Data>>,
+  memory: wasmtime::Memory,
+  malloc: TypedFunc<(u32, u32), u32>,
+}
+
+fn to_type(ty: ValType) -> wasmtime::ValType {
+  match ty {
+    ValType::I32 => wasmtime::ValType::I32,
+    ValType::I64 => wasmtime::ValType::I64,
+    ValType::F32 => wasmtime::ValType::F32,
+    ValType::F64 => wasmtime::ValType::F64,
+  }
+}
+fn to_val(val: Val) -> wasmtime::Val {
+  match val {
+    Val::I32(v) => wasmtime::Val::I32(v),
+    Val::I64(v) => wasmtime::Val::I64(v),
+    Val::F32(v) => wasmtime::Val::F32(v.to_bits()),
+    Val::F64(v) => wasmtime::Val::F64(v.to_bits()),
+  }
+}
+fn from_val(val: &wasmtime::Val) -> Option<Val> {
+  Some(match *val {
+    wasmtime::Val::I32(v) => Val::I32(v),
+    wasmtime::Val::I64(v) => Val::I64(v),
+    wasmtime::Val::F32(v) => Val::F32(f32::from_bits(v)),
+    wasmtime::Val::F64(v) => Val::F64(f64::from_bits(v)),
+    _ => return None,
+  })
+}
+
+impl<'a> CallerMemory<'a> {
+  fn new(mut caller: Caller<'a, Data>) -> wasmtime::Result<Self> {
+    let memory = caller
+      .get_export("memory")
+      .and_then(Extern::into_memory)
+      .ok_or_else(|| wasmtime::Error::msg("plugin does not export `memory`"))?;
+    let malloc = caller
+      .get_export("wasm_malloc")
+      .and_then(Extern::into_func)
+      .ok_or_else(|| wasmtime::Error::msg("plugin does not export `wasm_malloc`"))?
+      .typed::<(u32, u32), u32>(&caller)?;
+    Ok(CallerMemory { caller: RefCell::new(caller), memory, malloc })
+  }
+}
+
+impl Memory for CallerMemory<'_> {
+  fn size(&self) -> u64 { self.memory.data_size(&*self.caller.borrow()) as u64 }
+  fn read(&self, ptr: u32, buf: &mut [u8]) -> bool {
+    self.memory.read(&*self.caller.borrow(), ptr as usize, buf).is_ok()
+  }
+  fn write(&self, ptr: u32, data: &[u8]) -> bool {
+    self.memory.write(&mut *self.caller.borrow_mut(), ptr as usize, data).is_ok()
+  }
+  fn malloc(&self, size: u32, align: u32) -> u32 {
+    self
+      .malloc
+      .call(&mut *self.caller.borrow_mut(), (size, align))
+      .unwrap_or_else(|e| panic!("{e}"))
+  }
+}
+
+impl WasmtimeRuntime {
+  pub fn new(module: &[u8], data: Data) -> Result<Self, PluginCreateError> {
+    let engine = Engine::default();
+    // This changes whenever wasmtime can't load modules compiled by a different
+    // engine, such as after a version change.
+    let mut hasher = DefaultHasher::new();
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    let compiler = format!("wasmtime-{:x}", hasher.finish());
+    let cache = ModuleCache::new(&data.name, RuntimeKind::Wasmtime, &compiler, module);
+    // SAFETY: The cache is only written by the server, with the output of
+    // `Module::serialize`. Anyone who can change the cache could also change the
+    // plugin itself.
+    let module = match cache.load().and_then(|b| unsafe { Module::deserialize(&engine, b) }.ok()) {
+      Some(module) => module,
+      None => {
+        let module = Module::new(&engine, module).map_err(|e| PluginCreateError::Load(e.into()))?;
+        match module.serialize() {
+          Ok(compiled) => cache.store(&compiled),
+          Err(e) => warn!("could not serialize plugin {}: {e}", data.name),
+        }
+        module
+      }
+    };
+
+    let mut linker = Linker::new(&engine);
+    for (name, func) in funcs::imports() {
+      let ty = FuncType::new(
+        func.params().into_iter().map(to_type),
+        func.results().into_iter().map(to_type),
+      );
+      linker
+        .func_new("env", name, ty, move |caller: Caller<'_, Data>, params, results| {
+          let data = caller.data().clone();
+          let mem = CallerMemory::new(caller)?;
+          let args: Vec<Val> = params.iter().filter_map(from_val).collect();
+          if let (Some(ret), Some(out)) =
+            (func.call(&Env::new(&data, &mem), &args), results.first_mut())
+          {
+            *out = to_val(ret);
+          }
+          Ok(())
+        })
+        .map_err(|e| PluginCreateError::Load(e.into()))?;
+    }
+
+    let mut store = Store::new(&engine, data.clone());
+    let instance =
+      linker.instantiate(&mut store, &module).map_err(|e| PluginCreateError::Load(e.into()))?;
+    let memory = instance
+      .get_memory(&mut store, "memory")
+      .ok_or_else(|| PluginCreateError::Load("plugin does not export `memory`".into()))?;
+    let malloc = instance
+      .get_typed_func::<(u32, u32), u32>(&mut store, "wasm_malloc")
+      .map_err(|e| PluginCreateError::Load(e.into()))?;
+    Ok(WasmtimeRuntime { store: Mutex::new(store), instance, memory, malloc, data })
+  }
+}
+
+impl Memory for WasmtimeRuntime {
+  fn size(&self) -> u64 { self.memory.data_size(&*self.store.lock()) as u64 }
+  fn read(&self, ptr: u32, buf: &mut [u8]) -> bool {
+    self.memory.read(&*self.store.lock(), ptr as usize, buf).is_ok()
+  }
+  fn write(&self, ptr: u32, data: &[u8]) -> bool {
+    self.memory.write(&mut *self.store.lock(), ptr as usize, data).is_ok()
+  }
+  fn malloc(&self, size: u32, align: u32) -> u32 {
+    self.malloc.call(&mut *self.store.lock(), (size, align)).unwrap_or_else(|e| panic!("{e}"))
+  }
+}
+
+impl Runtime for WasmtimeRuntime {
+  fn data(&self) -> &Arc<PluginData> { &self.data }
+  fn memory(&self) -> &dyn Memory { self }
+
+  fn call(&self, name: &str, args: &[Val]) -> Result<Option<Val>, CallError> {
+    let mut store = self.store.lock();
+    let func = match self.instance.get_func(&mut *store, name) {
+      Some(f) => f,
+      None => return Err(CallError::no_keep(RuntimeError::Missing(name.into()))),
+    };
+    let ty = func.ty(&*store);
+    if !ty.params().eq(args.iter().map(|v| to_type(v.ty()))) {
+      return Err(CallError::no_keep(RuntimeError::WrongType(name.into())));
+    }
+    let args: Vec<_> = args.iter().copied().map(to_val).collect();
+    let mut results = vec![wasmtime::Val::I32(0); ty.results().len()];
+    func
+      .call(&mut *store, &args, &mut results)
+      .map_err(|e| CallError::keep(RuntimeError::Trap(name.into(), e.to_string())))?;
+    match results.as_slice() {
+      [] => Ok(None),
+      [v] => Ok(from_val(v)),
+      _ => Err(CallError::keep(RuntimeError::WrongType(name.into()))),
+    }
+  }
+
+  fn with_memory(&self, f: &mut dyn FnMut(&[u8])) {
+    let store = self.store.lock();
+    f(self.memory.data(&*store));
+  }
+}
@@ -1,14 +1,43 @@
 use crate::{block, entity, item};
-use bb_common::net::cb;
+use bb_common::{
+  net::cb,
+  version::{BlockVersion, ProtocolVersion},
+};
 use std::{collections::HashMap, str::FromStr};
 
 include!(concat!(env!("OUT_DIR"), "/tag/tags.rs"));
+include!(concat!(env!("OUT_DIR"), "/tag/registries.rs"));
 
 pub struct Tags {
   categories: TagCategories,
+  /// Lookup tables for each tag, using latest ids. Used for fast queries on
+  /// the server.
+  sets:       TagSets,
+  /// Name to id lookups for every version that supports tags.
+  versions:   Vec<VersionLookup>,
   // TODO: Add custom tags here
 }
 
+/// A set of ids within a single tag. This is a bitset, so checking if an id is
+/// in a tag is very fast.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagSet {
+  bits: Vec<u64>,
+}
+
+struct TagSets {
+  block:       HashMap<&'static str, TagSet>,
+  item:        HashMap<&'static str, TagSet>,
+  entity_type: HashMap<&'static str, TagSet>,
+}
+
+struct VersionLookup {
+  ver:         BlockVersion,
+  block:       HashMap<&'static str, i32>,
+  item:        HashMap<&'static str, i32>,
+  entity_type: HashMap<&'static str, i32>,
+}
+
 #[derive(Debug, Clone, Copy)]
 enum TagKind {
   Block,
@@ -20,71 +49,223 @@ enum TagKind {
 
 impl Tags {
   #[allow(clippy::new_without_default)]
-  pub fn new() -> Self { Tags { categories: generate_tags() } }
+  pub fn new() -> Self {
+    let categories = generate_tags();
+    let sets = TagSets {
+      block:       categories.block.build_sets(TagKind::Block),
+      item:        categories.item.build_sets(TagKind::Item),
+      entity_type: categories.entity_type.build_sets(TagKind::Entity),
+    };
+    let versions = generate_registries().iter().map(VersionLookup::new).collect();
+    Tags { categories, sets, versions }
+  }
 
-  pub fn serialize(&self) -> cb::packet::Tags {
+  /// Serializes all tags for the given version. Any ids that the client
+  /// doesn't know about are removed, and any tags that end up empty are
+  /// removed as well. The ids in the resulting packet are for the given
+  /// version, not the latest version.
+  pub fn serialize(&self, ver: ProtocolVersion) -> cb::packet::Tags {
+    let lookup = self.versions.iter().find(|l| l.ver == ver.block());
     cb::packet::Tags {
-      block:       self.categories.block.serialize(TagKind::Block),
-      item:        self.categories.item.serialize(TagKind::Item),
-      fluid:       self.categories.fluid.serialize(TagKind::Fluid),
-      entity_type: self.categories.entity_type.serialize(TagKind::Entity),
-      game_event:  self.categories.game_event.serialize(TagKind::GameEvent),
+      block:       self.categories.block.serialize(TagKind::Block, lookup),
+      item:        self.categories.item.serialize(TagKind::Item, lookup),
+      fluid:       self.categories.fluid.serialize(TagKind::Fluid, lookup),
+      entity_type: self.categories.entity_type.serialize(TagKind::Entity, lookup),
+      game_event:  self.categories.game_event.serialize(TagKind::GameEvent, lookup),
+    }
+  }
+
+  /// Returns the block tag with the given name. The name should not include
+  /// the leading `#`.
+  pub fn block_tag(&self, name: &str) -> Option<&TagSet> { self.sets.block.get(name) }
+  /// Returns the item tag with the given name. The name should not include
+  /// the leading `#`.
+  pub fn item_tag(&self, name: &str) -> Option<&TagSet> { self.sets.item.get(name) }
+  /// Returns the entity tag with the given name. The name should not include
+  /// the leading `#`.
+  pub fn entity_tag(&self, name: &str) -> Option<&TagSet> { self.sets.entity_type.get(name) }
+
+  /// Returns `true` if the given block kind is within the given tag. If the tag
+  /// doesn't exist, this returns `false`.
+  pub fn block_has(&self, tag: &str, kind: block::Kind) -> bool {
+    self.block_tag(tag).map(|t| t.contains(kind.id())).unwrap_or(false)
+  }
+  /// Returns `true` if the given item type is within the given tag. If the tag
+  /// doesn't exist, this returns `false`.
+  pub fn item_has(&self, tag: &str, ty: item::Type) -> bool {
+    self.item_tag(tag).map(|t| t.contains(ty.id())).unwrap_or(false)
+  }
+  /// Returns `true` if the given entity type is within the given tag. If the
+  /// tag doesn't exist, this returns `false`.
+  pub fn entity_has(&self, tag: &str, ty: entity::Type) -> bool {
+    self.entity_tag(tag).map(|t| t.contains(ty.id())).unwrap_or(false)
+  }
+}
+
+impl TagSet {
+  /// Creates an empty tag set.
+  pub fn new() -> Self { TagSet::default() }
+
+  /// Adds the given id to this set.
+  pub fn insert(&mut self, id: u32) {
+    let idx = id as usize / 64;
+    if idx >= self.bits.len() {
+      self.bits.resize(idx + 1, 0);
+    }
+    self.bits[idx] |= 1 << (id % 64);
+  }
+
+  /// Returns `true` if the given id is in this set.
+  pub fn contains(&self, id: u32) -> bool {
+    match self.bits.get(id as usize / 64) {
+      Some(v) => v & (1 << (id % 64)) != 0,
+      None => false,
+    }
+  }
+
+  /// Returns the number of ids in this set.
+  pub fn len(&self) -> usize { self.bits.iter().map(|v| v.count_ones() as usize).sum() }
+  /// Returns `true` if this set contains no ids.
+  pub fn is_empty(&self) -> bool { self.bits.iter().all(|v| *v == 0) }
+
+  /// Iterates through all the ids in this set, in ascending order.
+  pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+    self.bits.iter().enumerate().flat_map(|(i, v)| {
+      (0..64).filter(move |bit| v & (1 << bit) != 0).map(move |bit| i as u32 * 64 + bit)
+    })
+  }
+}
+
+impl VersionLookup {
+  fn new(reg: &Registries) -> Self {
+    fn index(names: &'static [&'static str]) -> HashMap<&'static str, i32> {
+      names.iter().enumerate().filter(|(_, n)| !n.is_empty()).map(|(i, n)| (*n, i as i32)).collect()
+    }
+    VersionLookup {
+      ver:         reg.ver,
+      block:       index(reg.block),
+      item:        index(reg.item),
+      entity_type: index(reg.entity_type),
     }
   }
 }
 
 impl TagCategory {
-  fn serialize(&self, kind: TagKind) -> HashMap<String, Vec<i32>> {
+  fn build_sets(&self, kind: TagKind) -> HashMap<&'static str, TagSet> {
     self
       .tags
       .iter()
       .map(|tag| {
-        (
-          tag.name.strip_prefix('#').unwrap().to_string(),
-          tag.values.iter().copied().flat_map(|elem| self.expand_tag(elem, kind)).collect(),
-        )
+        let mut set = TagSet::new();
+        for name in tag.values.iter().flat_map(|elem| self.expand_tag(*elem)) {
+          if let Some(id) = kind.latest_id(name) {
+            set.insert(id as u32);
+          }
+        }
+        (tag.name.strip_prefix('#').unwrap_or(tag.name), set)
       })
       .collect()
   }
 
-  fn expand_tag(&self, name: &str, kind: TagKind) -> Vec<i32> {
-    if name.starts_with('#') {
-      for tag in self.tags {
-        if name == tag.name {
-          return tag.values.iter().copied().flat_map(|elem| self.expand_tag(elem, kind)).collect();
+  fn serialize(&self, kind: TagKind, lookup: Option<&VersionLookup>) -> HashMap<String, Vec<i32>> {
+    self
+      .tags
+      .iter()
+      .filter_map(|tag| {
+        let ids: Vec<i32> = tag
+          .values
+          .iter()
+          .flat_map(|elem| self.expand_tag(*elem))
+          .filter_map(|name| kind.id_for(name, lookup))
+          .collect();
+        if ids.is_empty() && !tag.values.is_empty() {
+          None
+        } else {
+          Some((tag.name.strip_prefix('#').unwrap_or(tag.name).to_string(), ids))
         }
+      })
+      .collect()
+  }
+
+  /// Expands the given tag element into a list of names. If the element
+  /// references another tag, that tag is expanded. Tags that don't exist are
+  /// skipped.
+  fn expand_tag(&self, name: &'static str) -> Vec<&'static str> {
+    if name.starts_with('#') {
+      match self.tags.iter().find(|tag| tag.name == name) {
+        Some(tag) => tag.values.iter().flat_map(|elem| self.expand_tag(*elem)).collect(),
+        None => vec![],
       }
-      panic!();
     } else {
-      kind.expand_str(name)
+      vec![name]
     }
   }
 }
 
 impl TagKind {
-  pub fn expand_str(&self, name: &str) -> Vec<i32> {
+  /// Returns the latest id of the given name.
+  fn latest_id(&self, name: &str) -> Option<i32> {
     match self {
-      Self::Block => match block::Kind::from_str(name) {
-        Ok(kind) => vec![kind.id() as i32],
-        Err(_) => vec![],
-      },
-      Self::Item => match item::Type::from_str(name) {
-        Ok(id) => vec![id as i32],
-        Err(_) => vec![],
-      },
-      Self::Entity => match entity::Type::from_str(name) {
-        Ok(id) => vec![id as i32],
-        Err(_) => vec![],
-      },
+      Self::Block => block::Kind::from_str(name).ok().map(|kind| kind.id() as i32),
+      Self::Item => item::Type::from_str(name).ok().map(|ty| ty.id() as i32),
+      Self::Entity => entity::Type::from_str(name).ok().map(|ty| ty.id() as i32),
       Self::Fluid => match name {
         // fuild 0 is `empty`
-        "water" => vec![1],
-        "flowing_water" => vec![2],
-        "lava" => vec![3],
-        "flowing_lava" => vec![4],
-        _ => panic!("invalid fluid {name}"),
+        "water" => Some(1),
+        "flowing_water" => Some(2),
+        "lava" => Some(3),
+        "flowing_lava" => Some(4),
+        _ => None,
       },
-      Self::GameEvent => vec![],
+      Self::GameEvent => None,
     }
   }
+
+  /// Returns the id of the given name for the given version. If `lookup` is
+  /// `None`, this will return the latest id.
+  fn id_for(&self, name: &str, lookup: Option<&VersionLookup>) -> Option<i32> {
+    let lookup = match lookup {
+      Some(l) => l,
+      None => return self.latest_id(name),
+    };
+    match self {
+      Self::Block => lookup.block.get(name).copied(),
+      Self::Item => lookup.item.get(name).copied(),
+      Self::Entity => lookup.entity_type.get(name).copied(),
+      Self::Fluid | Self::GameEvent => self.latest_id(name),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tag_set() {
+    let mut set = TagSet::new();
+    assert!(set.is_empty());
+    set.insert(3);
+    set.insert(64);
+    set.insert(200);
+    assert!(set.contains(3));
+    assert!(set.contains(64));
+    assert!(set.contains(200));
+    assert!(!set.contains(4));
+    assert!(!set.contains(1000));
+    assert_eq!(set.len(), 3);
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 64, 200]);
+  }
+
+  #[test]
+  fn version_filtering() {
+    let tags = Tags::new();
+    assert!(tags.block_has("logs", block::Kind::OakLog));
+    assert!(!tags.block_has("logs", block::Kind::Stone));
+
+    let latest = tags.serialize(ProtocolVersion::latest());
+    let old = tags.serialize(ProtocolVersion::V1_14_4);
+    // Cherry logs don't exist in 1.14, so the old version should have fewer logs.
+    assert!(old.block["logs"].len() < latest.block["logs"].len());
+  }
 }
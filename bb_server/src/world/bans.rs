@@ -0,0 +1,71 @@
+//! Banning players. The ban list itself lives in [`bb_common::ban`], so that
+//! the proxy can read it as well.
+
+use super::WorldManager;
+use crate::player::Player;
+use bb_common::{
+  ban::{Ban, BanList, BanTarget},
+  util::UUID,
+};
+use parking_lot::MutexGuard;
+use std::net::IpAddr;
+
+impl WorldManager {
+  /// Returns the ban list. The list is read from disk again if the file has
+  /// changed. Any changes made to the list will not be saved; use
+  /// [`ban`](Self::ban) and [`pardon`](Self::pardon) to change it.
+  pub fn bans(&self) -> MutexGuard<'_, BanList> {
+    let mut bans = self.bans.lock();
+    bans.reload_if_changed();
+    bans
+  }
+
+  /// Returns the ban that applies to a player with the given UUID and address,
+  /// if there is one.
+  pub fn find_ban(&self, id: UUID, ip: Option<IpAddr>) -> Option<Ban> {
+    self.bans().find(id, ip).cloned()
+  }
+
+  /// Adds a ban, and saves the ban list. Anyone online that the ban applies to
+  /// will be kicked.
+  pub fn ban(&self, ban: Ban) {
+    let msg = ban.kick_message();
+    let target = ban.target;
+    {
+      let mut bans = self.bans();
+      bans.add(ban);
+      if let Err(e) = bans.save() {
+        error!("could not save ban list: {e}");
+      }
+    }
+    let kicked: Vec<_> = self
+      .all_players()
+      .values()
+      .map(|(_, p)| p)
+      .filter(|p| ban_applies(target, p))
+      .cloned()
+      .collect();
+    for p in kicked {
+      info!("kicking {}, as they were banned", p.username());
+      p.disconnect(msg.clone());
+    }
+  }
+
+  /// Removes the ban for the given target, and saves the ban list. Returns the
+  /// ban that was removed, if there was one.
+  pub fn pardon(&self, target: BanTarget) -> Option<Ban> {
+    let mut bans = self.bans();
+    let ban = bans.remove(target)?;
+    if let Err(e) = bans.save() {
+      error!("could not save ban list: {e}");
+    }
+    Some(ban)
+  }
+}
+
+fn ban_applies(target: BanTarget, p: &Player) -> bool {
+  match target {
+    BanTarget::Uuid(id) => p.id() == id,
+    BanTarget::Ip(ip) => p.addr().map(|a| a.ip()) == Some(ip),
+  }
+}
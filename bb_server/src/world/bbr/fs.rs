@@ -1,7 +1,7 @@
 //! Implements `MessageWrite` and `MessageRead` for `Region`, `Region::save`,
 //! and `Region::load`.
 
-use super::Region;
+use super::{migrate, Region};
 use crate::world::CountedChunk;
 use bb_common::{
   chunk::{paletted, Section},
@@ -9,7 +9,7 @@ use bb_common::{
   math::{Pos, RelPos},
   version::BlockVersion,
 };
-use bb_transfer::{MessageReader, MessageWriter, ReadError, WriteError};
+use bb_transfer::{MessageReader, MessageWriter, ReadError, ReadLimits, WriteError};
 use std::{
   cell::RefCell,
   fs,
@@ -37,6 +37,7 @@ impl Region {
       self.write(&mut writer).unwrap();
 
       compression_cache.clear();
+      migrate::write_header(&mut compression_cache);
       let mut encoder =
         GzEncoder::<&mut Vec<u8>>::new(&mut compression_cache, Compression::default());
       encoder.write_all(&region_cache).unwrap();
@@ -62,19 +63,25 @@ impl Region {
       if path.exists() {
         debug!("loading region from {}", path.display());
         compression_cache.clear();
-        let n = File::open(path).unwrap().read_to_end(&mut compression_cache).unwrap();
+        File::open(path).unwrap().read_to_end(&mut compression_cache).unwrap();
 
-        let mut decoder = GzDecoder::<&[u8]>::new(&compression_cache[..n]);
+        let (version, compressed) = migrate::read_header(&compression_cache);
+        let mut decoder = GzDecoder::<&[u8]>::new(compressed);
         region_cache.clear();
-        let n = match decoder.read_to_end(&mut region_cache) {
-          Ok(n) => n,
-          Err(e) => {
-            warn!("couldn't read chunk: {e}");
-            return;
-          }
-        };
+        if let Err(e) = decoder.read_to_end(&mut region_cache) {
+          warn!("couldn't read chunk: {e}");
+          return;
+        }
+        if let Err(e) = migrate::upgrade(version, &mut region_cache) {
+          // We don't want to overwrite a region we couldn't read, as this might
+          // just be an older server loading a newer world.
+          error!("could not load region {}: {e}", self.fname().display());
+          self.save = false;
+          return;
+        }
 
-        let mut reader = MessageReader::new(&region_cache[..n]);
+        // A corrupted region shouldn't be able to allocate a huge amount of memory.
+        let mut reader = MessageReader::with_limits(&region_cache, ReadLimits::untrusted());
         let res = reader.read_struct_with(|mut s| {
           for i in 0_usize..1024 {
             s.read_with(i as u64, |r| {
@@ -150,9 +157,7 @@ impl Region {
     */
   }
 
-  fn fname(&self) -> PathBuf {
-    PathBuf::new().join("world").join("chunks").join(format!("{}.{}.bbr", self.pos.x, self.pos.z))
-  }
+  fn fname(&self) -> PathBuf { self.dir.join(format!("{}.{}.bbr", self.pos.x, self.pos.z)) }
 }
 
 impl Region {
@@ -313,7 +318,8 @@ mod tests {
     let world = wm.new_world();
     let world = Arc::new(world);
     // we're testing saving, so we pass `true` to save this.
-    let region = Region::new_no_load(RegionPos::new(ChunkPos::new(0, 0)), true);
+    let region =
+      Region::new_no_load(RegionPos::new(ChunkPos::new(0, 0)), true, PathBuf::from("world/chunks"));
     for x in 0..16 {
       for y in 0..2 {
         for z in 0..16 {
@@ -334,7 +340,11 @@ mod tests {
     });
     drop(region);
     // this one only loads, so don't save it when it drops
-    let mut region = Region::new_no_load(RegionPos::new(ChunkPos::new(0, 0)), false);
+    let mut region = Region::new_no_load(
+      RegionPos::new(ChunkPos::new(0, 0)),
+      false,
+      PathBuf::from("world/chunks"),
+    );
     region.load(|| world.new_chunk());
     world.chunk(ChunkPos::new(0, 0), |c| {
       let section = c.inner().section(0).unwrap();
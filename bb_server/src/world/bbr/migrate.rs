@@ -0,0 +1,205 @@
+//! Versioning for `.bbr` files.
+//!
+//! Every region file starts with a short header: the bytes `BBR\0`, followed
+//! by the format version as a big endian `u32`. The rest of the file is the
+//! gzipped region. Files written before the header was added start with the
+//! gzip magic number instead, and are treated as version 0.
+//!
+//! When the format changes, [`VERSION`] is increased, and a [`Migration`] is
+//! added to [`MIGRATIONS`]. Old regions are upgraded when they are loaded, and
+//! [`migrate_world`] can be used to upgrade every region ahead of time.
+
+use bb_common::flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+  fmt, fs,
+  io::{self, Read, Write},
+  path::Path,
+  time::Instant,
+};
+
+/// The start of every versioned region file.
+const MAGIC: [u8; 4] = *b"BBR\0";
+
+/// The current format version. This is written to every saved region.
+pub const VERSION: u32 = 1;
+
+/// Upgrades a region from one format version to the next.
+pub struct Migration {
+  /// The version this migration upgrades from. It produces `from + 1`.
+  pub from:    u32,
+  /// A short description of what changed, used in logs.
+  pub name:    &'static str,
+  /// Converts the uncompressed region data.
+  pub migrate: fn(Vec<u8>) -> Result<Vec<u8>, MigrateError>,
+}
+
+/// Every migration, in order. There must be exactly one migration for every
+/// version below [`VERSION`].
+pub static MIGRATIONS: &[Migration] = &[Migration {
+  from:    0,
+  name:    "add version header",
+  // The data itself didn't change, it just gains a header when saved.
+  migrate: Ok,
+}];
+
+#[derive(Debug)]
+pub enum MigrateError {
+  Io(io::Error),
+  /// The region was saved by a newer server, which uses the given version.
+  Newer(u32),
+  /// There is no migration from the given version.
+  Missing(u32),
+  /// A migration couldn't understand the region data.
+  Invalid(String),
+}
+
+impl From<io::Error> for MigrateError {
+  fn from(e: io::Error) -> Self { MigrateError::Io(e) }
+}
+
+impl fmt::Display for MigrateError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Io(e) => write!(f, "{e}"),
+      Self::Newer(v) => {
+        write!(f, "region has format version {v}, but this server only supports up to {VERSION}")
+      }
+      Self::Missing(v) => write!(f, "no migration from format version {v}"),
+      Self::Invalid(msg) => write!(f, "invalid region data: {msg}"),
+    }
+  }
+}
+
+impl std::error::Error for MigrateError {}
+
+/// Splits a region file into its format version and the compressed data.
+pub fn read_header(file: &[u8]) -> (u32, &[u8]) {
+  match file.strip_prefix(&MAGIC) {
+    Some(rest) if rest.len() >= 4 => {
+      let version = u32::from_be_bytes(rest[..4].try_into().unwrap());
+      (version, &rest[4..])
+    }
+    _ => (0, file),
+  }
+}
+
+/// Writes the header for the current version.
+pub fn write_header(out: &mut Vec<u8>) {
+  out.extend_from_slice(&MAGIC);
+  out.extend_from_slice(&VERSION.to_be_bytes());
+}
+
+/// Runs every migration needed to upgrade `data` from `version` to
+/// [`VERSION`]. Returns `true` if anything was changed.
+pub fn upgrade(version: u32, data: &mut Vec<u8>) -> Result<bool, MigrateError> {
+  if version > VERSION {
+    return Err(MigrateError::Newer(version));
+  }
+  for v in version..VERSION {
+    let m = MIGRATIONS.iter().find(|m| m.from == v).ok_or(MigrateError::Missing(v))?;
+    debug!("migrating region from version {v}: {}", m.name);
+    *data = (m.migrate)(std::mem::take(data))?;
+  }
+  Ok(version != VERSION)
+}
+
+/// The result of [`migrate_world`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MigrateSummary {
+  /// The number of regions that were upgraded.
+  pub migrated: usize,
+  /// The number of regions that were already up to date.
+  pub current:  usize,
+  /// The number of regions that couldn't be upgraded. These are left
+  /// unchanged.
+  pub failed:   usize,
+}
+
+/// Upgrades every region in `dir` (usually `world/chunks`) to the current
+/// format version. This should be run while the server is stopped. Regions
+/// that fail to upgrade are logged and left alone.
+pub fn migrate_world(dir: &Path) -> io::Result<MigrateSummary> {
+  let mut paths = vec![];
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.extension().is_some_and(|ext| ext == "bbr") {
+      paths.push(path);
+    }
+  }
+  paths.sort();
+  info!("migrating {} regions in {} to version {VERSION}", paths.len(), dir.display());
+
+  let mut summary = MigrateSummary::default();
+  let mut last_log = Instant::now();
+  for (i, path) in paths.iter().enumerate() {
+    match migrate_file(path) {
+      Ok(true) => summary.migrated += 1,
+      Ok(false) => summary.current += 1,
+      Err(e) => {
+        error!("could not migrate {}: {e}", path.display());
+        summary.failed += 1;
+      }
+    }
+    if last_log.elapsed().as_secs() >= 1 || i + 1 == paths.len() {
+      last_log = Instant::now();
+      info!("{}/{} regions ({}%)", i + 1, paths.len(), (i + 1) * 100 / paths.len());
+    }
+  }
+  info!(
+    "done: {} migrated, {} already up to date, {} failed",
+    summary.migrated, summary.current, summary.failed
+  );
+  Ok(summary)
+}
+
+/// Upgrades a single region file. Returns `false` if it was already up to
+/// date. The new file is written next to the old one, and then renamed, so a
+/// crash won't leave a half written region.
+fn migrate_file(path: &Path) -> Result<bool, MigrateError> {
+  let file = fs::read(path)?;
+  let (version, compressed) = read_header(&file);
+  if version == VERSION {
+    return Ok(false);
+  }
+  let mut data = vec![];
+  GzDecoder::new(compressed).read_to_end(&mut data)?;
+  upgrade(version, &mut data)?;
+
+  let mut out = vec![];
+  write_header(&mut out);
+  let mut encoder = GzEncoder::new(&mut out, Compression::default());
+  encoder.write_all(&data)?;
+  encoder.finish()?;
+  let tmp = path.with_extension("bbr.tmp");
+  fs::write(&tmp, &out)?;
+  fs::rename(&tmp, path)?;
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn header() {
+    let mut file = vec![];
+    write_header(&mut file);
+    file.extend_from_slice(&[1, 2, 3]);
+    assert_eq!(read_header(&file), (VERSION, &[1, 2, 3][..]));
+    // Old regions are just gzip data.
+    let old = [0x1f, 0x8b, 8, 0];
+    assert_eq!(read_header(&old), (0, &old[..]));
+  }
+
+  #[test]
+  fn migrations() {
+    for v in 0..VERSION {
+      assert_eq!(MIGRATIONS.iter().filter(|m| m.from == v).count(), 1, "version {v}");
+    }
+    let mut data = vec![1, 2, 3];
+    assert!(upgrade(0, &mut data).unwrap());
+    assert_eq!(data, [1, 2, 3]);
+    assert!(!upgrade(VERSION, &mut data).unwrap());
+    assert!(matches!(upgrade(VERSION + 1, &mut data), Err(MigrateError::Newer(_))));
+  }
+}
@@ -1,11 +1,17 @@
 //! Bamboo region storing in memory, reading, and writing to disk.
 
 mod fs;
+mod migrate;
 
-use super::CountedChunk;
+use super::{
+  lock_order::{self, Level},
+  CountedChunk,
+};
 use bb_common::math::ChunkPos;
-use parking_lot::{Mutex, MutexGuard, RwLock, RwLockWriteGuard};
-use std::collections::HashMap;
+use parking_lot::{RwLock, RwLockWriteGuard};
+use std::{collections::HashMap, path::PathBuf};
+
+pub use migrate::{migrate_world, MigrateSummary};
 
 /// The same structure as a chunk position, but used to index into a region. Can
 /// be converted to/from a `ChunkPos` by multiplying/dividing its coordinates by
@@ -23,9 +29,20 @@ pub struct RegionRelPos {
   z: u8,
 }
 
+/// The number of shards in a [`RegionMap`].
+const SHARDS: usize = 16;
+
+/// All the loaded regions in a world. Regions are spread across a number of
+/// shards by position, so adding or removing a region only blocks access to
+/// the regions in the same shard.
+///
+/// See [`lock_order`](super::lock_order) for the order these locks must be
+/// taken in.
 pub struct RegionMap {
-  regions: RwLock<HashMap<RegionPos, Mutex<Region>>>,
-  save:    bool,
+  shards: Box<[RwLock<HashMap<RegionPos, RwLock<Region>>>]>,
+  save:   bool,
+  /// The directory that regions are saved in.
+  dir:    PathBuf,
 }
 
 pub struct Region {
@@ -33,104 +50,239 @@ pub struct Region {
   /// An array of `32*32 = 1024` chunks. The index is `x + z * 32`.
   chunks: Box<[Option<CountedChunk>; 1024]>,
   save:   bool,
+  dir:    PathBuf,
 }
 
 impl RegionMap {
-  pub fn new(save: bool) -> Self { RegionMap { regions: RwLock::new(HashMap::new()), save } }
+  pub fn new(save: bool, dir: PathBuf) -> Self {
+    RegionMap { shards: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(), save, dir }
+  }
 
-  pub fn region<F: FnOnce(MutexGuard<Region>) -> R, R>(
+  fn shard(&self, pos: RegionPos) -> &RwLock<HashMap<RegionPos, RwLock<Region>>> {
+    let hash = pos.x.wrapping_mul(31) ^ pos.z;
+    &self.shards[hash.rem_euclid(SHARDS as i32) as usize]
+  }
+
+  /// Finds the region at `pos`, and calls `f` with it. If the region doesn't
+  /// exist, it is created with `new_region`. The shard is locked for reading
+  /// while `f` is running.
+  fn with_region<R>(
     &self,
-    pos: ChunkPos,
-    new_chunk: impl Fn() -> CountedChunk,
-    f: F,
+    pos: RegionPos,
+    new_region: impl FnOnce() -> Region,
+    f: impl FnOnce(&RwLock<Region>) -> R,
   ) -> R {
-    let lock = self.regions.read();
-    let region_pos = RegionPos::new(pos);
-    let rlock = if !lock.contains_key(&region_pos) {
+    let _held = lock_order::acquire(Level::Shard);
+    let shard = self.shard(pos);
+    let lock = shard.read();
+    let lock = if lock.contains_key(&pos) {
+      lock
+    } else {
       drop(lock);
-      let mut write = self.regions.write();
+      let mut write = shard.write();
       // If someone else got the write lock, and wrote this region, we don't
       // want to write it twice.
-      write
-        .entry(region_pos)
-        .or_insert_with(|| Mutex::new(Region::new_load(new_chunk, region_pos, self.save)));
+      write.entry(pos).or_insert_with(|| RwLock::new(new_region()));
       RwLockWriteGuard::downgrade(write)
-    } else {
-      lock
     };
-    let region = rlock.get(&region_pos).unwrap();
-    f(region.lock())
+    f(lock.get(&pos).unwrap())
+  }
+
+  /// Locks the region containing `pos` for writing, and calls `f`. If the
+  /// region isn't loaded, it will be loaded from disk, and any chunks missing
+  /// from disk will be created with `new_chunk`.
+  ///
+  /// This blocks access to every chunk in the region, so prefer
+  /// [`chunk`](Self::chunk) if you only need a single chunk.
+  pub fn region<F: FnOnce(&mut Region) -> R, R>(
+    &self,
+    pos: ChunkPos,
+    new_chunk: impl Fn() -> CountedChunk,
+    f: F,
+  ) -> R {
+    let region_pos = RegionPos::new(pos);
+    self.with_region(
+      region_pos,
+      || Region::new_load(new_chunk, region_pos, self.save, self.dir.clone()),
+      |region| {
+        let _held = lock_order::acquire(Level::Region);
+        f(&mut region.write())
+      },
+    )
   }
 
   /// Fetches a region, and calls `f`. If the region doesn't exist, this will
   /// generate an empty region.
-  pub fn region_no_load<F: FnOnce(MutexGuard<Region>) -> R, R>(&self, pos: ChunkPos, f: F) -> R {
-    let lock = self.regions.read();
+  pub fn region_no_load<F: FnOnce(&mut Region) -> R, R>(&self, pos: ChunkPos, f: F) -> R {
     let region_pos = RegionPos::new(pos);
-    let rlock = if !lock.contains_key(&region_pos) {
-      drop(lock);
-      let mut write = self.regions.write();
-      // If someone else got the write lock, and wrote this region, we don't
-      // want to write it twice.
-      write
-        .entry(region_pos)
-        .or_insert_with(|| Mutex::new(Region::new_no_load(region_pos, self.save)));
-      RwLockWriteGuard::downgrade(write)
-    } else {
-      lock
-    };
-    let region = rlock.get(&region_pos).unwrap();
-    f(region.lock())
+    self.with_region(
+      region_pos,
+      || Region::new_no_load(region_pos, self.save, self.dir.clone()),
+      |region| {
+        let _held = lock_order::acquire(Level::Region);
+        f(&mut region.write())
+      },
+    )
+  }
+
+  /// Calls `f` with the chunk at `pos`. If the region isn't loaded, it is
+  /// loaded like in [`region`](Self::region). If the chunk doesn't exist, it
+  /// is created with `gen`.
+  ///
+  /// The region is only locked for reading while `f` is running, so other
+  /// chunks in the same region can be used at the same time. `gen` is called
+  /// without any locks held, so two threads might generate the same chunk. In
+  /// that case, whichever chunk is stored first is kept.
+  pub fn chunk<F: FnOnce(&CountedChunk) -> R, R>(
+    &self,
+    pos: ChunkPos,
+    new_chunk: impl Fn() -> CountedChunk,
+    gen: impl FnOnce() -> CountedChunk,
+    f: F,
+  ) -> R {
+    let region_pos = RegionPos::new(pos);
+    let rel = RegionRelPos::new(pos);
+    let mut f = Some(f);
+    let res = self.with_region(
+      region_pos,
+      || Region::new_load(&new_chunk, region_pos, self.save, self.dir.clone()),
+      |region| {
+        let _held = lock_order::acquire(Level::Region);
+        region.read().get(rel).as_ref().map(|c| f.take().unwrap()(c))
+      },
+    );
+    if let Some(res) = res {
+      return res;
+    }
+    let chunk = gen();
+    self.with_region(
+      region_pos,
+      || Region::new_load(&new_chunk, region_pos, self.save, self.dir.clone()),
+      |region| {
+        let _held = lock_order::acquire(Level::Region);
+        let mut write = region.write();
+        write.get_or_generate(rel, || chunk);
+        let read = RwLockWriteGuard::downgrade(write);
+        f.take().unwrap()(read.get(rel).as_ref().unwrap())
+      },
+    )
   }
 
   pub fn has_chunk(&self, pos: ChunkPos) -> bool {
-    let lock = self.regions.read();
-    if let Some(region) = lock.get(&RegionPos::new(pos)) {
-      region.lock().has_chunk(RegionRelPos::new(pos))
+    let region_pos = RegionPos::new(pos);
+    let _held = lock_order::acquire(Level::Shard);
+    let lock = self.shard(region_pos).read();
+    if let Some(region) = lock.get(&region_pos) {
+      let _held = lock_order::acquire(Level::Region);
+      region.read().has_chunk(RegionRelPos::new(pos))
     } else {
       false
     }
   }
 
-  pub fn unload_chunks(&self) {
-    let mut unloadable = vec![];
-    {
-      let rl = self.regions.read();
-      for (pos, region) in rl.iter() {
-        if region.lock().unload_chunks() {
-          unloadable.push(*pos);
-        }
+  /// Returns the view count of the given chunk, or 0 if it isn't loaded. This
+  /// never loads the chunk.
+  pub fn view_count(&self, pos: ChunkPos) -> u32 {
+    let region_pos = RegionPos::new(pos);
+    let _held = lock_order::acquire(Level::Shard);
+    let lock = self.shard(region_pos).read();
+    if let Some(region) = lock.get(&region_pos) {
+      let _held = lock_order::acquire(Level::Region);
+      match region.read().get(RegionRelPos::new(pos)) {
+        Some(c) => c.count.load(std::sync::atomic::Ordering::Acquire),
+        None => 0,
       }
+    } else {
+      0
     }
-    if !unloadable.is_empty() {
-      let mut wl = self.regions.write();
-      for pos in unloadable {
-        wl.remove(&pos);
+  }
+
+  /// Returns the number of loaded regions, and the number of chunks loaded in
+  /// all of those regions.
+  pub fn loaded(&self) -> (usize, usize) {
+    let mut regions = 0;
+    let mut chunks = 0;
+    for shard in self.shards.iter() {
+      let _held = lock_order::acquire(Level::Shard);
+      let lock = shard.read();
+      regions += lock.len();
+      for region in lock.values() {
+        let _held = lock_order::acquire(Level::Region);
+        chunks += region.read().chunks.iter().flatten().count();
+      }
+    }
+    (regions, chunks)
+  }
+
+  pub fn unload_chunks(&self) {
+    for shard in self.shards.iter() {
+      let _held = lock_order::acquire(Level::Shard);
+      let unloadable: Vec<_> = shard
+        .read()
+        .iter()
+        .filter(|(_, region)| {
+          let _held = lock_order::acquire(Level::Region);
+          region.read().can_unload()
+        })
+        .map(|(pos, _)| *pos)
+        .collect();
+      if unloadable.is_empty() {
+        continue;
+      }
+      let mut removed = vec![];
+      {
+        let mut wl = shard.write();
+        for pos in unloadable {
+          // A player might have started viewing one of these chunks since we checked,
+          // so we check again now that nobody else can lock the region.
+          if wl.get_mut(&pos).is_some_and(|r| r.get_mut().can_unload()) {
+            removed.extend(wl.remove(&pos));
+          }
+        }
       }
+      // Dropping a region saves it, so we do that after unlocking the shard.
+      drop(removed);
     }
   }
 
+  /// Saves and unloads the region at `pos`, even if players are viewing it.
+  /// This is only safe to use when nobody can be viewing the region, such as
+  /// when importing a world.
+  pub fn unload_region(&self, pos: RegionPos) {
+    let _held = lock_order::acquire(Level::Shard);
+    let removed = self.shard(pos).write().remove(&pos);
+    drop(removed);
+  }
+
   pub fn save(&self) {
     if !self.save {
       info!("saving disabled, skipping");
       return;
     }
     info!("saving world...");
-    let lock = self.regions.read();
-    for region in lock.values() {
-      region.lock().save();
+    for shard in self.shards.iter() {
+      let _held = lock_order::acquire(Level::Shard);
+      for region in shard.read().values() {
+        let _held = lock_order::acquire(Level::Region);
+        region.read().save();
+      }
     }
     info!("saved");
   }
 }
 
 impl Region {
-  pub fn new_no_load(pos: RegionPos, save: bool) -> Self {
+  pub fn new_no_load(pos: RegionPos, save: bool, dir: PathBuf) -> Self {
     const NONE: Option<CountedChunk> = None;
-    Region { pos, chunks: Box::new([NONE; 1024]), save }
+    Region { pos, chunks: Box::new([NONE; 1024]), save, dir }
   }
-  pub fn new_load(new_chunk: impl Fn() -> CountedChunk, pos: RegionPos, save: bool) -> Self {
-    let mut region = Region::new_no_load(pos, save);
+  pub fn new_load(
+    new_chunk: impl Fn() -> CountedChunk,
+    pos: RegionPos,
+    save: bool,
+    dir: PathBuf,
+  ) -> Self {
+    let mut region = Region::new_no_load(pos, save, dir);
     region.load(new_chunk);
     region
   }
@@ -156,7 +308,7 @@ impl Region {
   }
   pub fn has_chunk(&self, pos: impl Into<RegionRelPos>) -> bool { self.get(pos.into()).is_some() }
   /// Returns true if this region can be unloaded.
-  pub fn unload_chunks(&mut self) -> bool {
+  pub fn can_unload(&self) -> bool {
     // If all the chunks are either `None` or viewed by nobody, we can unload this
     // region.
     for c in self.chunks.iter().flatten() {
@@ -1,11 +1,11 @@
 use crate::{
   block,
-  block::Block,
-  entity, item,
+  block::{Block, BlockDrops, TileEntity},
+  entity,
   item::Stack,
   math::{CollisionResult, Vec3, AABB},
-  world::World,
-  RNG,
+  net::broadcast::broadcast,
+  world::{ChangeSet, RngStream, World},
 };
 use bb_common::{
   math::{ChunkPos, FPos, Pos, PosError, RelPos},
@@ -13,7 +13,11 @@ use bb_common::{
   net::cb,
 };
 use rand::Rng;
-use std::{cmp::Ordering, str::FromStr, sync::Arc};
+use std::{
+  cmp::Ordering,
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
 
 /// General block manipulation functions
 impl World {
@@ -31,36 +35,55 @@ impl World {
   /// Returns `false` if the world is locked. In this case, a sync should be
   /// sent back to the client.
   pub fn break_block(self: &Arc<Self>, pos: Pos) -> Result<bool, PosError> {
+    self.break_block_with(pos, None)
+  }
+  /// Breaks the block at the given position, as if it was broken using the
+  /// given tool. The tool is used to check if the block can be harvested, and
+  /// to apply silk touch and fortune to the drops. If `tool` is `None`, the
+  /// block's drops are spawned without any enchantments applied.
+  ///
+  /// Returns `false` if the world is locked. In this case, a sync should be
+  /// sent back to the client.
+  pub fn break_block_with(
+    self: &Arc<Self>,
+    pos: Pos,
+    tool: Option<&Stack>,
+  ) -> Result<bool, PosError> {
     let old_type = self.get_block(pos)?;
     let old_block = self.block_converter.get(old_type.kind());
+    let drops = self
+      .world_manager()
+      .block_behaviors()
+      .call(old_type.kind(), |b| b.drops(Block::new(self, pos, old_type.ty())));
     let res = self.set_kind(pos, block::Kind::Air)?;
-    if old_block.drops.is_empty() {
-      return Ok(res);
-    }
     if res {
-      let drop = old_block.drops[0];
-      let item = match item::Type::from_str(drop.item) {
-        Ok(it) => it,
-        Err(_) => return Ok(res),
+      let items = match drops {
+        BlockDrops::Normal => old_block.loot.roll(old_block, tool, &mut self.rng(RngStream::Loot)),
+        BlockDrops::Custom(drops) => drops.items,
       };
-      let mut meta = Metadata::new();
-      meta.set_item(8, Stack::new(item).with_amount(drop.max as u8).to_item());
-      RNG.with(|rng_ref| {
-        let mut rng = rng_ref.borrow_mut();
-        self.summon_meta(
-          entity::Type::Item,
-          FPos::new(
-            pos.x as f64 + rng.gen_range(0.25f64..0.75f64),
-            pos.y as f64 + rng.gen_range(0.25f64..0.75f64) - 0.125,
-            pos.z as f64 + rng.gen_range(0.25f64..0.75f64),
-          ),
-          meta,
-        );
-      });
+      for stack in items {
+        self.drop_item(pos, stack);
+      }
     }
     Ok(res)
   }
 
+  /// Spawns a dropped item entity at a random position within the given block.
+  pub(crate) fn drop_item(self: &Arc<Self>, pos: Pos, stack: Stack) {
+    let mut meta = Metadata::new();
+    meta.set_item(8, stack.to_item());
+    let mut rng = self.rng(RngStream::Loot);
+    self.summon_meta(
+      entity::Type::Item,
+      FPos::new(
+        pos.x as f64 + rng.gen_range(0.25f64..0.75f64),
+        pos.y as f64 + rng.gen_range(0.25f64..0.75f64) - 0.125,
+        pos.z as f64 + rng.gen_range(0.25f64..0.75f64),
+      ),
+      meta,
+    );
+  }
+
   /// This sets a block within the world. It will return an error if the
   /// position is outside of the world. This will send packets to anyone within
   /// render distance of the given chunk.
@@ -170,6 +193,93 @@ impl World {
     self.set_block(pos, self.block_converter.get(kind).default_type())
   }
 
+  /// Sets many blocks at once. The changes are grouped by chunk, so that each
+  /// chunk is only locked once, and players get a single multi block change
+  /// for each chunk section, instead of a block update for every block.
+  ///
+  /// Unlike [`set_block`](Self::set_block), this will not call block update
+  /// handlers. Returns the number of blocks that changed; positions that
+  /// already contain the given type are skipped. If any of the positions are
+  /// outside the world, nothing will be changed. If the world is locked, this
+  /// will return `Ok(0)`.
+  pub fn set_blocks<'a>(
+    &self,
+    blocks: impl IntoIterator<Item = (Pos, block::Type<'a>)>,
+  ) -> Result<u64, PosError> {
+    self.set_blocks_inner(blocks, None)
+  }
+  /// Sets many blocks at once, like [`set_blocks`](Self::set_blocks). Every
+  /// block that changed is added to `changes`, so that the edit can be undone
+  /// later.
+  pub fn set_blocks_recorded<'a>(
+    &self,
+    blocks: impl IntoIterator<Item = (Pos, block::Type<'a>)>,
+    changes: &mut ChangeSet,
+  ) -> Result<u64, PosError> {
+    self.set_blocks_inner(blocks, Some(changes))
+  }
+  fn set_blocks_inner<'a>(
+    &self,
+    blocks: impl IntoIterator<Item = (Pos, block::Type<'a>)>,
+    mut record: Option<&mut ChangeSet>,
+  ) -> Result<u64, PosError> {
+    if self.is_locked() {
+      return Ok(0);
+    }
+    let mut chunks: HashMap<ChunkPos, Vec<(Pos, block::Type)>> = HashMap::new();
+    for (pos, ty) in blocks {
+      let pos = self.check_pos(pos)?;
+      chunks.entry(pos.chunk()).or_default().push((pos, ty));
+    }
+    let mut changed = 0;
+    for (pos, blocks) in chunks {
+      let changes = self.chunk(pos, |mut c| {
+        let mut changes = vec![];
+        for (p, ty) in blocks {
+          let old = c.get_type(p.chunk_rel())?.id();
+          if old != ty.id() {
+            c.set_type(p.chunk_rel(), ty)?;
+            if let Some(record) = &mut record {
+              record.push(p, old, ty.id());
+            }
+            changes.push((p.chunk_rel(), ty.id()));
+          }
+        }
+        Ok(changes)
+      })?;
+      changed += changes.len() as u64;
+      self.send_chunk_changes(pos, &changes);
+    }
+    Ok(changed)
+  }
+
+  /// Sends the given block changes within a single chunk to everyone in view.
+  fn send_chunk_changes(&self, pos: ChunkPos, changes: &[(RelPos, u32)]) {
+    if changes.is_empty() {
+      return;
+    }
+    let min_y = changes.iter().map(|(p, _)| p.chunk_y()).min().unwrap();
+    let max_y = changes.iter().map(|(p, _)| p.chunk_y()).max().unwrap();
+    // See `fill_rect` for why this limit is so low.
+    if changes.len() > 128 {
+      let serialized = self.serialize_partial_chunk(pos, min_y as u32, max_y as u32);
+      broadcast(self.players().iter().in_view(pos), serialized);
+    } else {
+      for y in min_y..=max_y {
+        let section: Vec<_> = changes
+          .iter()
+          .filter(|(p, _)| p.chunk_y() == y)
+          .map(|(p, id)| (p.section_rel(), *id))
+          .collect();
+        if section.is_empty() {
+          continue;
+        }
+        let serialized = self.serialize_multi_block_change(pos, y, section.into_iter());
+        broadcast(self.players().iter().in_view(pos), serialized);
+      }
+    }
+  }
+
   /// Fills the given region with the given block type. Min must be less than or
   /// equal to max. Use [`min_max`](Pos::min_max) to convert two corners of a
   /// cube into a min and max.
@@ -206,9 +316,7 @@ impl World {
         if num_blocks_changed > 128 {
           let serialized =
             self.serialize_partial_chunk(pos, min.chunk_y() as u32, max.chunk_y() as u32);
-          for p in self.players().iter().in_view(pos) {
-            p.send(serialized.clone());
-          }
+          broadcast(self.players().iter().in_view(pos), serialized);
         } else {
           for y in min.chunk_y()..=max.chunk_y() {
             let serialized = self.serialize_multi_block_change(
@@ -222,9 +330,7 @@ impl World {
                 }
               }),
             );
-            for p in self.players().iter().in_view(pos) {
-              p.send(serialized.clone());
-            }
+            broadcast(self.players().iter().in_view(pos), serialized);
           }
         }
       }
@@ -537,4 +643,42 @@ impl World {
     let res = from_vec.move_towards(to_vec - from_vec, &colliders);
     res.map(|res| (from_vec.into(), res))
   }
+
+  /// Returns all the tile entities in loaded chunks within `radius` chunks of
+  /// a player, or in chunks with a plugin or forced ticket, where `filter`
+  /// returns `true`.
+  pub(super) fn tes_near_players(
+    &self,
+    radius: u32,
+    filter: impl Fn(&dyn TileEntity) -> bool,
+  ) -> Vec<(Pos, Arc<dyn TileEntity>)> {
+    let mut chunks = HashSet::new();
+    for p in self.players().values() {
+      chunks.extend(p.pos().chunk().around(radius));
+    }
+    chunks.extend(self.ticketed_chunks());
+    let mut tes = vec![];
+    for chunk in chunks {
+      // We don't want to load chunks just to tick tile entities.
+      if !self.has_loaded_chunk(chunk) {
+        continue;
+      }
+      self.chunk(chunk, |c| {
+        for (p, te) in c.tes_with_pos() {
+          if filter(te.as_ref()) {
+            tes.push((chunk.block() + Pos::new(p.x().into(), p.y(), p.z().into()), te.clone()));
+          }
+        }
+      });
+    }
+    tes
+  }
+
+  /// Resends the chunk section containing `pos`, so that players can see the
+  /// changes to the tile entity at `pos`.
+  pub(super) fn send_te_update(&self, pos: Pos) {
+    let y = pos.chunk_rel().chunk_y() as u32;
+    let serialized = self.serialize_partial_chunk(pos.chunk(), y, y);
+    broadcast(self.players().iter().in_view(pos.chunk()), serialized);
+  }
 }
@@ -8,7 +8,8 @@ use crate::{
 };
 use bb_common::{
   chunk::{paletted::Section as PalettedSection, Chunk},
-  math::{PosError, RelPos},
+  math::{ChunkPos, Pos, PosError, RelPos},
+  net::cb,
   version::BlockVersion,
 };
 use parking_lot::{Mutex, MutexGuard};
@@ -94,6 +95,8 @@ impl BlockData {
     let behaviors = self.wm.block_behaviors();
     if let Some(te) = behaviors.call(kind, |b| b.create_te()) {
       self.tes.insert(p, te);
+    } else {
+      self.tes.remove(&p);
     }
     Ok(())
   }
@@ -307,6 +310,39 @@ impl MultiChunk {
     let p = self.transform_pos(p)?;
     Ok(self.block.tes.get(&p).cloned())
   }
+  /// Replaces the tile entity at the given position. This should only be used
+  /// when the block at `p` already has a tile entity of the same type.
+  pub fn set_te(&mut self, p: RelPos, te: Arc<dyn TileEntity>) -> Result<(), PosError> {
+    let p = self.transform_pos(p)?;
+    self.block.tes.insert(p, te);
+    Ok(())
+  }
+  /// Returns an iterator over all the tile entities in this chunk. Unlike
+  /// [`tes`](Self::tes), the positions returned are the same positions that
+  /// are passed to [`get_te`](Self::get_te).
+  pub fn tes_with_pos(&self) -> impl Iterator<Item = (RelPos, &Arc<dyn TileEntity>)> {
+    let min_y = self.block.min_y;
+    self.block.tes.iter().map(move |(p, te)| (p.add_y(-min_y), te))
+  }
+  /// Returns the block entities that should be sent to clients along with this
+  /// chunk. `pos` is the position of this chunk, and only tile entities where
+  /// `filter` returns `true` will be included.
+  pub fn block_entities(
+    &self,
+    pos: ChunkPos,
+    filter: impl Fn(RelPos) -> bool,
+  ) -> Vec<cb::BlockEntity> {
+    self
+      .tes_with_pos()
+      .filter(|(p, _)| filter(*p))
+      .filter_map(|(p, te)| {
+        Some(cb::BlockEntity {
+          pos:  pos.block() + Pos::new(p.x().into(), p.y(), p.z().into()),
+          kind: te.client_data()?,
+        })
+      })
+      .collect()
+  }
 
   /// Transforms the given position to be used directly in a `Chunk`. This is
   /// because a `Chunk` cannot accept positions with a negative Y value, but
@@ -344,6 +380,19 @@ impl MultiChunk {
   /// Returns the block light information for this chunk. Used to send lighting
   /// data to clients.
   pub fn block_light(&self) -> &BlockLightChunk { &self.block_light }
+  /// Returns the block light level at the given position. Any sections that
+  /// have no lighting information are treated as dark.
+  pub fn block_light_at(&self, p: RelPos) -> u8 {
+    match self.block_light.data.get_section_opt(p.chunk_y() as usize) {
+      Some(section) => section.get(p.section_rel()),
+      None => 0,
+    }
+  }
+
+  /// Returns the height of this chunk, in blocks.
+  pub fn height(&self) -> u32 { self.block.height }
+  /// Returns the minimum Y level of this chunk.
+  pub fn min_y(&self) -> i32 { self.block.min_y }
 
   /// Will enable/disable lighting. Chunks have lighting enabled by default. If
   /// enabled, and if it was previously disabled, all the lighting information
@@ -126,6 +126,7 @@ impl World {
           if !s.world.chunks_to_load.lock().needs_pos(pos) {
             return;
           }
+          let _span = tracing::info_span!("chunk gen", x = pos.x(), z = pos.z()).entered();
           let chunk = s.world.pre_generate_chunk(pos);
           s.world.store_chunks_no_overwrite(vec![(pos, chunk)]);
           let mut queue_lock = s.world.chunks_to_load.lock();
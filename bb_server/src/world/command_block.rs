@@ -0,0 +1,271 @@
+//! Command blocks. Impulse command blocks run once when they are powered,
+//! repeating command blocks run every tick while powered, and chain command
+//! blocks run after the command block pointing into them runs.
+
+use super::World;
+use crate::{
+  block,
+  block::{CommandBlockData, CommandBlockTE, TileEntity},
+  command::{CommandSender, ErrorFormat},
+};
+use bb_common::{
+  math::{Pos, PosError},
+  net::sb::CommandBlockMode,
+  util::{Chat, Face},
+};
+use std::sync::Arc;
+
+/// Command blocks are only ticked in chunks this close to a player.
+const COMMAND_BLOCK_CHUNK_RADIUS: u32 = 8;
+/// The maximum number of command blocks that can run in a single chain. This
+/// matches vanilla's default `maxCommandChainLength`.
+const MAX_CHAIN_LENGTH: u32 = 65536;
+
+/// Runs commands for a command block, and stores the last message sent.
+struct CommandBlockSender {
+  pos:    Pos,
+  output: Option<Chat>,
+}
+
+impl CommandSender for CommandBlockSender {
+  fn block_pos(&self) -> Option<Pos> { Some(self.pos) }
+  fn send_message(&mut self, msg: Chat) { self.output = Some(msg); }
+  fn error_format(&self) -> ErrorFormat { ErrorFormat::Minecraft }
+}
+
+impl World {
+  /// Returns the data of the command block at `pos`. If there is no command
+  /// block at `pos`, this returns `None`.
+  pub fn command_block(&self, pos: Pos) -> Result<Option<CommandBlockData>, PosError> {
+    Ok(self.command_block_te(pos)?.map(|te| command_block_ref(&te).data().clone()))
+  }
+
+  /// Modifies the command block at `pos`, and sends the changes to all nearby
+  /// players. Returns `false` if there is no command block at `pos`.
+  pub fn modify_command_block(
+    self: &Arc<Self>,
+    pos: Pos,
+    f: impl FnOnce(&mut CommandBlockData),
+  ) -> Result<bool, PosError> {
+    let te = match self.command_block_te(pos)? {
+      Some(te) => te,
+      None => return Ok(false),
+    };
+    let impulse = self.get_kind(pos)? == block::Kind::CommandBlock;
+    {
+      let mut data = command_block_ref(&te).data();
+      let was_active = data.powered || data.auto;
+      f(&mut data);
+      if impulse && !was_active && (data.powered || data.auto) {
+        data.pending = true;
+      }
+    }
+    self.send_te_update(pos);
+    Ok(true)
+  }
+
+  /// Changes the mode of the command block at `pos`, and then modifies its
+  /// data. The command block will keep the direction it is facing. This is
+  /// used when a player edits a command block.
+  ///
+  /// Returns `false` if there is no command block at `pos`, or if the world is
+  /// locked.
+  pub fn update_command_block(
+    self: &Arc<Self>,
+    pos: Pos,
+    mode: CommandBlockMode,
+    conditional: bool,
+    f: impl FnOnce(&mut CommandBlockData),
+  ) -> Result<bool, PosError> {
+    if self.is_locked() {
+      return Ok(false);
+    }
+    let data = match self.command_block(pos)? {
+      Some(data) => data,
+      None => return Ok(false),
+    };
+    let old = self.get_block(pos)?;
+    let kind = match mode {
+      CommandBlockMode::Chain => block::Kind::ChainCommandBlock,
+      CommandBlockMode::Repeating => block::Kind::RepeatingCommandBlock,
+      CommandBlockMode::Impulse => block::Kind::CommandBlock,
+    };
+    let ty = self
+      .block_converter()
+      .get(kind)
+      .default_type()
+      .with("facing", old.prop("facing").as_enum())
+      .with("conditional", conditional);
+    if ty.id() != old.id() {
+      // Changing the block will replace the tile entity, so we need to put the old
+      // data back afterwards.
+      if !self.set_block(pos, ty)? {
+        return Ok(false);
+      }
+      self.chunk(pos.chunk(), |mut c| {
+        c.set_te(
+          pos.chunk_rel(),
+          Arc::new(CommandBlockTE::new(CommandBlockData { powered: false, ..data })),
+        )
+      })?;
+    }
+    let powered = self.is_powered(pos);
+    self.modify_command_block(pos, |data| {
+      f(data);
+      data.powered = powered;
+    })
+  }
+
+  /// Checks if the command block at `pos` is powered, and activates it if it
+  /// just became powered. This is called whenever a block next to a command
+  /// block changes.
+  pub(crate) fn update_command_block_power(self: &Arc<Self>, pos: Pos) {
+    // This needs to be checked before locking the command block, as serializing a
+    // chunk will lock the chunk and then the command block.
+    let powered = self.is_powered(pos);
+    if let Ok(Some(data)) = self.command_block(pos) {
+      if data.powered != powered {
+        let _ = self.modify_command_block(pos, |data| data.powered = powered);
+      }
+    }
+  }
+
+  /// Runs all the impulse and repeating command blocks that need to run. This
+  /// is called every tick from the world tick loop.
+  pub(super) fn tick_command_blocks(self: &Arc<Self>) {
+    let tes =
+      self.tes_near_players(COMMAND_BLOCK_CHUNK_RADIUS, |te| te.as_any().is::<CommandBlockTE>());
+    for (pos, te) in tes {
+      let run = match self.get_kind(pos) {
+        Ok(block::Kind::CommandBlock) => std::mem::take(&mut command_block_ref(&te).data().pending),
+        Ok(block::Kind::RepeatingCommandBlock) => {
+          let data = command_block_ref(&te).data();
+          data.powered || data.auto
+        }
+        _ => false,
+      };
+      if run {
+        self.run_command_block(pos);
+      }
+    }
+  }
+
+  /// Runs the command block at `pos`, and then any chain command blocks after
+  /// it.
+  fn run_command_block(self: &Arc<Self>, mut pos: Pos) {
+    for i in 0..MAX_CHAIN_LENGTH {
+      let ty = match self.get_block(pos) {
+        Ok(ty) => ty,
+        Err(_) => return,
+      };
+      let te = match self.command_block_te(pos) {
+        Ok(Some(te)) => te,
+        _ => return,
+      };
+      let facing = facing(ty.prop("facing").as_enum());
+      let data = command_block_ref(&te).data().clone();
+      // The first block is always run, as it was powered. Chain command blocks
+      // still pass the chain on if they aren't active.
+      if i == 0 || data.powered || data.auto {
+        let success =
+          if ty.prop("conditional").bool() && !self.command_block_succeeded(pos - facing) {
+            None
+          } else {
+            Some(self.run_command(pos, &data.command))
+          };
+        let changed = {
+          let mut data = command_block_ref(&te).data();
+          let old = (data.success_count, data.last_output.clone());
+          match success {
+            Some((success, output)) => {
+              data.success_count = success as i32;
+              data.last_output = if data.track_output { output } else { None };
+            }
+            None => data.success_count = 0,
+          }
+          old != (data.success_count, data.last_output.clone())
+        };
+        if changed {
+          self.send_te_update(pos);
+        }
+      }
+      pos = pos + facing;
+      if self.get_kind(pos) != Ok(block::Kind::ChainCommandBlock) {
+        return;
+      }
+    }
+  }
+
+  /// Runs a single command as a command block. Returns `true` if the command
+  /// succeeded, along with the last message sent by the command.
+  fn run_command(self: &Arc<Self>, pos: Pos, command: &str) -> (bool, Option<Chat>) {
+    let command = command.strip_prefix('/').unwrap_or(command);
+    if command.is_empty() {
+      return (false, None);
+    }
+    let mut sender = CommandBlockSender { pos, output: None };
+    let success = self.commands().execute(self.world_manager(), &mut sender, command);
+    (success, sender.output)
+  }
+
+  /// Returns `true` if there is a command block at `pos`, and the last command
+  /// it ran succeeded.
+  fn command_block_succeeded(&self, pos: Pos) -> bool {
+    matches!(self.command_block(pos), Ok(Some(data)) if data.success_count > 0)
+  }
+
+  fn command_block_te(&self, pos: Pos) -> Result<Option<Arc<dyn TileEntity>>, PosError> {
+    let te = self.chunk(pos.chunk(), |c| c.get_te(pos.chunk_rel()))?;
+    Ok(te.filter(|te| te.as_any().is::<CommandBlockTE>()))
+  }
+}
+
+/// Parses the `facing` property of a command block.
+fn facing(facing: &str) -> Face {
+  match facing {
+    "up" => Face::Top,
+    "down" => Face::Bottom,
+    v => Face::from(v),
+  }
+}
+
+/// Downcasts the given tile entity to a command block. This should only be
+/// called after checking that the tile entity is a command block.
+fn command_block_ref(te: &Arc<dyn TileEntity>) -> &CommandBlockTE {
+  te.as_any().downcast_ref().expect("tile entity is not a command block")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bb_transfer::{MessageRead, MessageReader};
+
+  #[test]
+  fn command_block_data() {
+    let data = CommandBlockData {
+      command: "say hi".into(),
+      auto: true,
+      track_output: true,
+      last_output: Some(Chat::new("hi")),
+      success_count: 1,
+      ..Default::default()
+    };
+    let te = CommandBlockTE::new(data.clone());
+    let mut buf = vec![];
+    te.save(&mut bb_transfer::MessageWriter::new(&mut buf)).unwrap();
+    let mut r = MessageReader::new(&buf);
+    assert_eq!(CommandBlockData::read(&mut r).unwrap(), data);
+    assert_eq!(
+      te.client_data(),
+      Some(bb_common::net::cb::BlockEntityKind::CommandBlock {
+        command:      "say hi".into(),
+        auto:         true,
+        track_output: true,
+        last_output:  Some(Chat::new("hi")),
+      })
+    );
+
+    assert_eq!(facing("up"), Face::Top);
+    assert_eq!(facing("west"), Face::West);
+  }
+}
@@ -0,0 +1,232 @@
+//! Disguises. A disguised entity is shown to other players as a different
+//! entity type. This only changes the packets sent to clients, so the entity
+//! still has the same hitbox and behavior on the server.
+
+use super::World;
+use crate::{entity, player::Player};
+use bb_common::{
+  metadata::Metadata,
+  net::cb::{packet, Packet},
+  util::UUID,
+};
+use parking_lot::RwLock;
+use std::{
+  collections::{HashMap, HashSet},
+  sync::Arc,
+};
+
+/// How an entity should look to other players.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disguise {
+  ty:      entity::Type,
+  data:    i32,
+  viewers: Option<HashSet<UUID>>,
+}
+
+impl Disguise {
+  /// Creates a disguise which will be shown to every player.
+  pub fn new(ty: entity::Type) -> Self { Disguise { ty, data: 0, viewers: None } }
+  /// Sets the spawn data of the disguise. For falling blocks, this is the
+  /// block id that will be shown.
+  pub fn with_data(mut self, data: i32) -> Self {
+    self.data = data;
+    self
+  }
+  /// Only shows this disguise to the given player. This can be called multiple
+  /// times to show the disguise to multiple players. Everyone else will see the
+  /// entity as normal.
+  pub fn show_to(mut self, viewer: UUID) -> Self {
+    self.viewers.get_or_insert_with(HashSet::new).insert(viewer);
+    self
+  }
+
+  /// Returns the type this disguise will look like.
+  pub fn ty(&self) -> entity::Type { self.ty }
+  /// Returns the players who will see this disguise. If this is `None`, every
+  /// player except the disguised one will see it.
+  pub fn viewers(&self) -> Option<&HashSet<UUID>> { self.viewers.as_ref() }
+  /// Returns `true` if the given player will see this disguise.
+  pub fn shown_to(&self, viewer: UUID) -> bool {
+    self.viewers.as_ref().map(|v| v.contains(&viewer)).unwrap_or(true)
+  }
+
+  /// Removes everything from the metadata that is specific to the original
+  /// entity type. Only the fields shared by all entities (indices 0 through
+  /// 7) are kept.
+  fn filter_meta(&self, meta: Metadata) -> Metadata {
+    let mut meta =
+      Metadata { fields: meta.fields.into_iter().filter(|(idx, _)| *idx < 8).collect() };
+    // The client simulates falling blocks, so they would fall through the floor
+    // without this.
+    if self.ty == entity::Type::FallingBlock {
+      meta.set_bool(5, true);
+    }
+    meta
+  }
+}
+
+/// All the disguised entities in a world.
+#[derive(Debug, Default)]
+pub struct Disguises {
+  inner: RwLock<HashMap<i32, Disguise>>,
+}
+
+impl Disguises {
+  pub fn new() -> Self { Disguises::default() }
+
+  /// Returns `true` if [`remap`](Self::remap) would change the given packet
+  /// for `viewer`.
+  pub(crate) fn remaps(&self, viewer: &Player, p: &Packet) -> bool {
+    let disguises = self.inner.read();
+    if disguises.is_empty() {
+      return false;
+    }
+    match disguised_eid(p) {
+      Some(eid) => {
+        eid != viewer.eid() && disguises.get(&eid).is_some_and(|d| d.shown_to(viewer.id()))
+      }
+      None => false,
+    }
+  }
+
+  /// Changes a packet that is about to be sent to `viewer`, so that any
+  /// disguised entities look like their disguise. If this returns `None`, the
+  /// packet should not be sent.
+  pub(crate) fn remap(&self, viewer: &Player, p: Packet) -> Option<Packet> {
+    let disguises = self.inner.read();
+    if disguises.is_empty() {
+      return Some(p);
+    }
+    let eid = match disguised_eid(&p) {
+      Some(eid) => eid,
+      None => return Some(p),
+    };
+    let d = match disguises.get(&eid) {
+      Some(d) if eid != viewer.eid() && d.shown_to(viewer.id()) => d,
+      _ => return Some(p),
+    };
+    Some(match p {
+      Packet::SpawnEntity(mut spawn) => {
+        spawn.ty = d.ty.id();
+        spawn.meta = d.filter_meta(spawn.meta);
+        spawn.living = d.ty.is_living();
+        spawn.data = super::entities::spawn_data(viewer, d.ty, d.data);
+        spawn.into()
+      }
+      Packet::SpawnPlayer(spawn) => packet::SpawnEntity {
+        eid:      spawn.eid,
+        // Players have a tab list entry for their UUID, so we need a new one here.
+        id:       UUID::random(),
+        ty:       d.ty.id(),
+        pos:      spawn.pos,
+        yaw:      spawn.yaw,
+        pitch:    spawn.pitch,
+        vel_x:    0,
+        vel_y:    0,
+        vel_z:    0,
+        meta:     d.filter_meta(spawn.meta),
+        living:   d.ty.is_living(),
+        head_yaw: spawn.yaw,
+        data:     super::entities::spawn_data(viewer, d.ty, d.data),
+      }
+      .into(),
+      Packet::EntityMetadata(mut meta) => {
+        meta.ty = d.ty.id();
+        meta.meta = d.filter_meta(meta.meta);
+        meta.into()
+      }
+      // Clients will disconnect if they get attributes for a non-living entity.
+      Packet::EntityProperties(_) if !d.ty.is_living() => return None,
+      p => p,
+    })
+  }
+}
+
+/// Returns the entity a packet is about, if it is a packet that is changed by
+/// disguises.
+fn disguised_eid(p: &Packet) -> Option<i32> {
+  match p {
+    Packet::SpawnEntity(packet::SpawnEntity { eid, .. })
+    | Packet::SpawnPlayer(packet::SpawnPlayer { eid, .. })
+    | Packet::EntityMetadata(packet::EntityMetadata { eid, .. })
+    | Packet::EntityProperties(packet::EntityProperties { eid, .. }) => Some(*eid),
+    _ => None,
+  }
+}
+
+impl World {
+  /// Returns the disguise of the given entity, if it is disguised.
+  pub fn disguise(&self, eid: i32) -> Option<Disguise> {
+    self.disguises.inner.read().get(&eid).cloned()
+  }
+
+  /// Disguises the given entity, and respawns it for everyone in view. Any
+  /// existing disguise will be replaced. Entities cannot be disguised as
+  /// players, as players need a tab list entry to be shown.
+  ///
+  /// Returns `false` if the entity doesn't exist, or if the disguise is a
+  /// player.
+  pub fn set_disguise(self: &Arc<Self>, eid: i32, disguise: Disguise) -> bool {
+    if disguise.ty == entity::Type::Player || self.entities().get(eid).is_none() {
+      return false;
+    }
+    self.disguises.inner.write().insert(eid, disguise);
+    self.respawn_entity(eid);
+    true
+  }
+  /// Removes the disguise from the given entity. Returns `false` if the entity
+  /// wasn't disguised.
+  pub fn remove_disguise(self: &Arc<Self>, eid: i32) -> bool {
+    if self.disguises.inner.write().remove(&eid).is_none() {
+      return false;
+    }
+    self.respawn_entity(eid);
+    true
+  }
+  /// Clears the disguise of an entity that is being removed from this world.
+  /// This doesn't need to respawn anything, as the entity is already gone.
+  pub(crate) fn clear_disguise(&self, eid: i32) { self.disguises.inner.write().remove(&eid); }
+
+  /// Removes and spawns the given entity for every player in view.
+  fn respawn_entity(self: &Arc<Self>, eid: i32) {
+    let player = self.entities().get_player(eid);
+    if let Some(player) = player {
+      self.respawn_player(&player);
+      return;
+    }
+    let entities = self.entities();
+    if let Some(ent) = entities.get(eid) {
+      let remove = packet::RemoveEntities { eids: vec![eid] };
+      for p in self.viewers(ent.pos().block().chunk()) {
+        if p.is_tracking(eid) {
+          p.send(remove.clone());
+          self.send_entity_spawn(&p, &ent);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn viewers() {
+    let a = UUID::from_u128(1);
+    let b = UUID::from_u128(2);
+    let d = Disguise::new(entity::Type::Zombie);
+    assert!(d.shown_to(a));
+    let d = d.show_to(a);
+    assert!(d.shown_to(a));
+    assert!(!d.shown_to(b));
+
+    let mut meta = Metadata::new();
+    meta.set_byte(0, 0x20);
+    meta.set_byte(17, 0x7f);
+    let meta = Disguise::new(entity::Type::FallingBlock).filter_meta(meta);
+    assert_eq!(meta.fields.len(), 2);
+    assert!(meta.fields.contains_key(&0));
+    assert!(meta.fields.contains_key(&5));
+  }
+}
@@ -0,0 +1,263 @@
+//! Large block edits, like `/fill` and `/clone`. These are built on top of
+//! [`set_blocks`](World::set_blocks), and are split into batches, so that the
+//! caller can show progress for large edits.
+
+use super::{ChangeSet, World};
+use crate::block;
+use bb_common::math::{Pos, PosError};
+use bb_transfer::{MessageReader, MessageWriter};
+use std::{fmt, sync::Arc};
+
+/// The number of blocks changed in each batch of an edit. The progress
+/// callback is called after every batch.
+pub const EDIT_BATCH_SIZE: u64 = 4096;
+
+/// How `/fill` should treat the blocks that are already in the region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillMode {
+  /// Replaces every block. If a filter is given, only blocks of that kind are
+  /// replaced.
+  Replace(Option<block::Kind>),
+  /// Breaks every block (dropping items), and then replaces it.
+  Destroy,
+  /// Fills the edges of the region, and replaces the inside with air.
+  Hollow,
+  /// Only replaces air blocks.
+  Keep,
+  /// Fills the edges of the region, and leaves the inside alone.
+  Outline,
+}
+
+/// Which blocks `/clone` should copy from the source region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneMask {
+  /// Copies every block.
+  Replace,
+  /// Copies every block, except for air.
+  Masked,
+  /// Only copies blocks of the given kind.
+  Filtered(block::Kind),
+}
+
+/// How `/clone` should treat the source region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneMode {
+  /// Copies the blocks. The source and destination cannot overlap.
+  Normal,
+  /// Copies the blocks, even if the source and destination overlap.
+  Force,
+  /// Copies the blocks, and replaces the source blocks with air. The source and
+  /// destination cannot overlap.
+  Move,
+}
+
+/// An error from a block edit.
+#[derive(Debug, PartialEq)]
+pub enum EditError {
+  /// Part of the region is outside of the world.
+  Pos(PosError),
+  /// The world is locked, so no blocks can be changed.
+  Locked,
+  /// The source and destination of a clone overlap.
+  Overlap,
+  /// A player tried to edit their selection, but they haven't selected a
+  /// region.
+  NoSelection,
+}
+
+impl From<PosError> for EditError {
+  fn from(e: PosError) -> Self { EditError::Pos(e) }
+}
+
+impl fmt::Display for EditError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Pos(e) => write!(f, "{e}"),
+      Self::Locked => write!(f, "this world cannot be modified"),
+      Self::Overlap => write!(f, "the source and destination areas cannot overlap"),
+      Self::NoSelection => write!(f, "you must select a region first"),
+    }
+  }
+}
+
+impl std::error::Error for EditError {}
+
+/// Returns the number of blocks in the region between the two corners.
+pub fn volume(a: Pos, b: Pos) -> u64 {
+  let (min, max) = a.min_max(b);
+  (max.x - min.x + 1) as u64 * (max.y - min.y + 1) as u64 * (max.z - min.z + 1) as u64
+}
+
+impl World {
+  /// Fills the region between the two corners with `ty`, using the given mode.
+  /// After every [`EDIT_BATCH_SIZE`] blocks, `progress` is called with the
+  /// number of blocks processed so far, and the total number of blocks.
+  ///
+  /// Returns every block that changed, so that the fill can be undone. Block
+  /// update handlers are not called for the changed blocks.
+  pub fn fill(
+    self: &Arc<Self>,
+    a: Pos,
+    b: Pos,
+    ty: block::Type,
+    mode: FillMode,
+    mut progress: impl FnMut(u64, u64),
+  ) -> Result<ChangeSet, EditError> {
+    let (min, max) = a.min_max(b);
+    self.check_edit(min, max)?;
+    let air = self.block_converter().get(block::Kind::Air).default_type();
+    let total = volume(min, max);
+    let mut done = 0;
+    let mut changes = ChangeSet::new();
+    let mut batch = vec![];
+    for pos in min.to(max) {
+      let edge = pos.x == min.x
+        || pos.x == max.x
+        || pos.y == min.y
+        || pos.y == max.y
+        || pos.z == min.z
+        || pos.z == max.z;
+      let new = match mode {
+        FillMode::Replace(None) => Some(ty),
+        FillMode::Replace(Some(filter)) => (self.get_kind(pos)? == filter).then_some(ty),
+        FillMode::Destroy => {
+          let old = self.get_block(pos)?;
+          if old.kind() != block::Kind::Air && self.break_block(pos)? {
+            changes.push(pos, old.id(), air.id());
+          }
+          Some(ty)
+        }
+        FillMode::Hollow => Some(if edge { ty } else { air }),
+        FillMode::Keep => (self.get_kind(pos)? == block::Kind::Air).then_some(ty),
+        FillMode::Outline => edge.then_some(ty),
+      };
+      batch.extend(new.map(|ty| (pos, ty)));
+      done += 1;
+      if done % EDIT_BATCH_SIZE == 0 || done == total {
+        self.set_blocks_recorded(batch.drain(..), &mut changes)?;
+        progress(done, total);
+      }
+    }
+    Ok(changes)
+  }
+
+  /// Copies the region between the two corners, so that the lowest corner of
+  /// the region ends up at `dest`. Tile entities are copied along with the
+  /// blocks. After every [`EDIT_BATCH_SIZE`] blocks, `progress` is called with
+  /// the number of blocks copied so far, and the total number of blocks to
+  /// copy.
+  ///
+  /// Returns every block that changed, so that the clone can be undone. Tile
+  /// entities are not stored in the returned changes. Block update handlers
+  /// are not called for the changed blocks.
+  pub fn clone_region(
+    self: &Arc<Self>,
+    a: Pos,
+    b: Pos,
+    dest: Pos,
+    mask: CloneMask,
+    mode: CloneMode,
+    mut progress: impl FnMut(u64, u64),
+  ) -> Result<ChangeSet, EditError> {
+    let (min, max) = a.min_max(b);
+    let offset = dest - min;
+    self.check_edit(min, max)?;
+    self.check_edit(min + offset, max + offset)?;
+    let dest_max = max + offset;
+    let overlaps = dest.x <= max.x
+      && dest_max.x >= min.x
+      && dest.y <= max.y
+      && dest_max.y >= min.y
+      && dest.z <= max.z
+      && dest_max.z >= min.z;
+    if overlaps && mode != CloneMode::Force {
+      return Err(EditError::Overlap);
+    }
+
+    // Everything is read before anything is placed, so that overlapping clones
+    // copy the original blocks.
+    let mut blocks = vec![];
+    for pos in min.to(max) {
+      let ty = self.get_block(pos)?;
+      let copy = match mask {
+        CloneMask::Replace => true,
+        CloneMask::Masked => ty.kind() != block::Kind::Air,
+        CloneMask::Filtered(kind) => ty.kind() == kind,
+      };
+      if copy {
+        let te = self.save_te(pos)?;
+        blocks.push((pos, ty, te));
+      }
+    }
+
+    let mut changes = ChangeSet::new();
+    if mode == CloneMode::Move {
+      let air = self.block_converter().get(block::Kind::Air).default_type();
+      self.set_blocks_recorded(blocks.iter().map(|(pos, _, _)| (*pos, air)), &mut changes)?;
+    }
+
+    let total = blocks.len() as u64;
+    let mut done = 0;
+    for batch in blocks.chunks(EDIT_BATCH_SIZE as usize) {
+      self.set_blocks_recorded(
+        batch.iter().map(|(pos, ty, _)| (*pos + offset, ty.ty())),
+        &mut changes,
+      )?;
+      for (pos, ty, te) in batch {
+        if let Some(te) = te {
+          self.load_te(*pos + offset, ty.kind(), te)?;
+        }
+      }
+      done += batch.len() as u64;
+      progress(done, total);
+    }
+    Ok(changes)
+  }
+
+  /// Makes sure that every block between `min` and `max` can be changed.
+  pub(super) fn check_edit(&self, min: Pos, max: Pos) -> Result<(), EditError> {
+    self.check_pos(min)?;
+    self.check_pos(max)?;
+    if self.is_locked() {
+      return Err(EditError::Locked);
+    }
+    Ok(())
+  }
+
+  /// Serializes the tile entity at `pos`, so that it can be copied with
+  /// [`load_te`](Self::load_te).
+  fn save_te(&self, pos: Pos) -> Result<Option<Vec<u8>>, PosError> {
+    let te = self.chunk(pos.chunk(), |c| c.get_te(pos.chunk_rel()))?;
+    Ok(te.and_then(|te| {
+      let mut data = vec![];
+      te.save(&mut MessageWriter::new(&mut data)).ok()?;
+      Some(data)
+    }))
+  }
+
+  /// Loads a tile entity saved with [`save_te`](Self::save_te) at `pos`, and
+  /// sends it to all nearby players.
+  fn load_te(&self, pos: Pos, kind: block::Kind, data: &[u8]) -> Result<(), PosError> {
+    let te = self
+      .world_manager()
+      .block_behaviors()
+      .call(kind, |b| b.load_te(&mut MessageReader::new(data)));
+    if let Some(Ok(te)) = te {
+      self.chunk(pos.chunk(), |mut c| c.set_te(pos.chunk_rel(), te))?;
+      self.send_te_update(pos);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn volumes() {
+    assert_eq!(volume(Pos::new(0, 0, 0), Pos::new(0, 0, 0)), 1);
+    assert_eq!(volume(Pos::new(0, 0, 0), Pos::new(1, 1, 1)), 8);
+    assert_eq!(volume(Pos::new(5, 10, -3), Pos::new(-4, 1, 6)), 1000);
+  }
+}
@@ -1,15 +1,19 @@
-use super::World;
+use super::{
+  lock_order::{self, Level},
+  World,
+};
 use crate::{
   entity,
-  entity::{Entity, EntityData, EntityRef},
+  entity::{behavior::TextBehavior, Entity, EntityData, EntityHandle, EntityRef},
   math::Vec3,
+  net::broadcast::broadcast,
   player::Player,
 };
 use bb_common::{
   math::{ChunkPos, FPos},
   metadata::Metadata,
   net::cb,
-  util::UUID,
+  util::{Chat, UUID},
 };
 use parking_lot::RwLockReadGuard;
 use std::{
@@ -17,10 +21,41 @@ use std::{
     hash_map::{Iter, Keys, Values},
     HashMap,
   },
+  fmt,
   ops::{Deref, DerefMut},
   sync::Arc,
 };
 
+/// The widest entity hitbox, in blocks. Used to find which chunks an entity
+/// might overlap with. This is half the width of a ghast.
+const MAX_WIDTH: f64 = 2.0;
+
+/// An error from looking up an [`EntityHandle`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleError {
+  /// There is no entity with this id.
+  Missing(EntityHandle),
+  /// The entity with this id is a different entity. This happens when the
+  /// entity the handle was created for has been removed, or when the handle
+  /// came from another world.
+  Stale(EntityHandle),
+  /// The handle refers to a player, but only server-controlled entities were
+  /// allowed.
+  Player(EntityHandle),
+}
+
+impl fmt::Display for HandleError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Missing(h) => write!(f, "entity {h} does not exist"),
+      Self::Stale(h) => write!(f, "entity {h} is stale (it was removed, or is in another world)"),
+      Self::Player(h) => write!(f, "entity {h} is a player"),
+    }
+  }
+}
+
+impl std::error::Error for HandleError {}
+
 pub struct EntitiesMap {
   inner: HashMap<i32, Entity>,
 }
@@ -69,6 +104,29 @@ impl EntitiesMapRef<'_> {
   pub fn get_player(&self, eid: i32) -> Option<Arc<Player>> {
     self.inner.get(&eid)?.as_player(self.world)
   }
+
+  /// Returns the entity for the given handle. This will fail if the entity has
+  /// been removed, even if another entity now has the same id.
+  pub fn get_handle(&self, handle: EntityHandle) -> Result<EntityRef, HandleError> {
+    let ent = self.get(handle.eid).ok_or(HandleError::Missing(handle))?;
+    if ent.handle() != handle {
+      return Err(HandleError::Stale(handle));
+    }
+    Ok(ent)
+  }
+  /// Returns the entity for the given handle. This is the same as
+  /// [`get_handle`](Self::get_handle), but fails if the entity is a player.
+  pub fn get_ent_handle(&self, handle: EntityHandle) -> Result<&Arc<EntityData>, HandleError> {
+    let ent = match self.inner.get(&handle.eid) {
+      Some(Entity::Entity(ent)) => ent,
+      Some(Entity::Player(_)) => return Err(HandleError::Player(handle)),
+      None => return Err(HandleError::Missing(handle)),
+    };
+    if ent.handle() != handle {
+      return Err(HandleError::Stale(handle));
+    }
+    Ok(ent)
+  }
 }
 
 impl Deref for EntitiesMap {
@@ -113,6 +171,7 @@ impl<'a> Iterator for KeysIter<'a> {
 
 impl World {
   pub fn entities<'a>(self: &'a Arc<Self>) -> EntitiesMapRef<'a> {
+    super::lock_order::check(super::lock_order::Level::Entities);
     EntitiesMapRef { inner: self.entities.read(), world: self }
   }
 
@@ -133,31 +192,98 @@ impl World {
     data: i32,
   ) -> i32 {
     let eid = self.new_eid();
-    let ent = Entity::Entity(Arc::new(EntityData::new(eid, ty, self.clone(), pos, meta, data)));
-    self.add_entity(eid, ent.clone());
-    let entity_ref = ent.as_entity_ref(self).unwrap();
-
-    for p in self.players().iter().in_view(pos.chunk()) {
-      self.send_entity_spawn(p, &entity_ref);
-    }
+    self.spawn_entity(EntityData::new(eid, ty, self.clone(), pos, meta, data));
     eid
   }
+  /// Summons an entity, and returns a handle to it. This is the same as
+  /// [`summon`](Self::summon), but the handle can be stored by plugins.
+  pub fn summon_handle(self: &Arc<Self>, ty: entity::Type, pos: FPos) -> EntityHandle {
+    let eid = self.new_eid();
+    self.spawn_entity(EntityData::new(eid, ty, self.clone(), pos, Metadata::new(), 1))
+  }
+
+  /// Spawns floating text at the given position. This is an invisible armor
+  /// stand with a custom name, which works on every version. Returns a handle
+  /// to the entity, whose id can be passed to [`set_text`](Self::set_text) or
+  /// [`remove_entity`](Self::remove_entity).
+  pub fn spawn_text(self: &Arc<Self>, pos: FPos, text: Chat) -> EntityHandle {
+    let mut meta = Metadata::new();
+    // Invisible
+    meta.set_byte(0, 0x20);
+    meta.set_opt_chat(2, Some(text));
+    meta.set_bool(3, true);
+    // No gravity
+    meta.set_bool(5, true);
+    // Marker, which removes the hitbox, so the text shows up at `pos`.
+    meta.set_byte(15, 0x10);
+    let eid = self.new_eid();
+    self.spawn_entity(EntityData::new_custom(
+      eid,
+      entity::Type::ArmorStand,
+      pos,
+      self.clone(),
+      TextBehavior,
+      meta,
+      0,
+    ))
+  }
+  /// Changes the text of an entity created with
+  /// [`spawn_text`](Self::spawn_text). This will also set the custom name of
+  /// any other entity. Returns `false` if the entity doesn't exist, or if it is
+  /// a player.
+  pub fn set_text(&self, eid: i32, text: Chat) -> bool {
+    let ent = match self.entities.read().get(&eid) {
+      Some(Entity::Entity(ent)) => ent.clone(),
+      _ => return false,
+    };
+    ent.set_custom_name(Some(text));
+    true
+  }
+
+  /// Adds the entity to the world, and sends it to everyone in view. Returns
+  /// a handle to the entity.
+  fn spawn_entity(self: &Arc<Self>, ent: EntityData) -> EntityHandle {
+    let handle = ent.handle();
+    let pos = ent.fpos();
+    let ent = Entity::Entity(Arc::new(ent));
+    self.add_entity(handle.eid, pos.chunk(), ent);
+    self.spawn_for_viewers(handle.eid, pos.chunk());
+    handle
+  }
+
+  /// Removes the given entity from the world, and sends a remove packet to
+  /// everyone in view of it. Returns `false` if the entity doesn't exist, or if
+  /// it is a player.
+  pub fn remove_entity(&self, eid: i32) -> bool {
+    let pos = {
+      let mut entities = self.entities.write();
+      match entities.get(&eid) {
+        Some(Entity::Entity(ent)) => {
+          let pos = ent.fpos();
+          entities.remove(&eid);
+          pos
+        }
+        _ => return false,
+      }
+    };
+    self.clear_passengers(eid);
+    self.unindex_entity(eid);
+    self.clear_disguise(eid);
+    self.remove_for_viewers(eid, pos.chunk());
+    true
+  }
 
   /// Sends entity velocity packets to everyone in view of `pos`.
   pub(crate) fn send_entity_vel(&self, pos: ChunkPos, eid: i32, vel: Vec3) {
-    for p in self.players().iter().in_view(pos) {
-      p.send(cb::packet::EntityVelocity {
-        eid,
-        x: vel.fixed_x(),
-        y: vel.fixed_y(),
-        z: vel.fixed_z(),
-      });
-    }
+    broadcast(
+      self.viewers(pos),
+      cb::packet::EntityVelocity { eid, x: vel.fixed_x(), y: vel.fixed_y(), z: vel.fixed_z() },
+    );
   }
 
   /// Sends entity position packets to everyone in view of `old`.
   pub(crate) fn send_entity_pos(&self, eid: i32, old: FPos, new: FPos, on_ground: bool) {
-    for p in self.players().iter().in_view(old.chunk()) {
+    for p in self.viewers(old.chunk()) {
       let x = new.x() - old.x();
       let y = new.y() - old.y();
       let z = new.z() - old.z();
@@ -202,12 +328,22 @@ impl World {
   /// Sends packets to respawn the player for all clients in render distance.
   /// This is used when custom names are set, because I cannot, for the life
   /// of me, figure out how to get the clients to update a custom name for a
-  /// player.
+  /// player. It is also used when skins change.
   pub fn respawn_player(self: &Arc<Self>, player: &Player) {
-    let (pos, pitch, yaw) = player.pos_look();
-    let chunk = pos.block().chunk();
+    let chunk = player.pos().block().chunk();
     let remove = cb::packet::RemoveEntities { eids: vec![player.eid()] };
-    let add = cb::packet::SpawnPlayer {
+    for p in self.viewers(chunk) {
+      if p.id() != player.id() && p.is_tracking(player.eid()) {
+        p.send(remove.clone());
+        self.send_player_spawn(&p, player);
+      }
+    }
+  }
+
+  /// Spawns `player` for `viewer`. This also sends the player's attributes.
+  pub(crate) fn send_player_spawn(&self, viewer: &Player, player: &Player) {
+    let (pos, pitch, yaw) = player.pos_look();
+    viewer.send(cb::packet::SpawnPlayer {
       eid: player.eid(),
       id: player.id(),
       ty: entity::Type::Player.id(),
@@ -215,14 +351,109 @@ impl World {
       yaw: yaw as i8,
       pitch: pitch as i8,
       meta: player.metadata(),
-    };
-    for p in self.players().iter().in_view(chunk).not(player.id()) {
-      p.send(remove.clone());
-      p.send(add.clone());
+    });
+    if let Some(attrs) = player.attributes().to_packet(player.eid()) {
+      viewer.send(attrs);
     }
+    self.send_passengers_to(viewer, player.eid());
+  }
+
+  fn add_entity(&self, eid: i32, chunk: ChunkPos, entity: Entity) {
+    self.entities.write().insert(eid, entity);
+    self.index_entity(eid, chunk);
+  }
+
+  /// Adds the entity to the spatial index, or moves it if it is already
+  /// present. This should be called whenever an entity moves between chunks.
+  pub(crate) fn index_entity(&self, eid: i32, chunk: ChunkPos) {
+    let _held = lock_order::acquire(Level::EntityIndex);
+    self.entity_index.lock().insert(eid, chunk);
+  }
+  /// Removes the entity from the spatial index.
+  pub(crate) fn unindex_entity(&self, eid: i32) {
+    let _held = lock_order::acquire(Level::EntityIndex);
+    self.entity_index.lock().remove(eid);
+  }
+
+  /// Returns every entity whose bounding box is at least partially within the
+  /// box from `min` to `max`. This includes players.
+  ///
+  /// This only looks at the chunks that the box covers, so it is fast for
+  /// small boxes, even if there are a lot of entities in the world.
+  pub fn entities_in_box(self: &Arc<Self>, min: FPos, max: FPos) -> Vec<Entity> {
+    let (min, max) = min.min_max(max);
+    // Entities are indexed by their feet position, and might be a bit wider than
+    // that, so we need to check the chunks around the edges as well.
+    let min_chunk = (min - FPos::new(MAX_WIDTH, 0.0, MAX_WIDTH)).chunk();
+    let max_chunk = (max + FPos::new(MAX_WIDTH, 0.0, MAX_WIDTH)).chunk();
+    let eids = {
+      let _held = lock_order::acquire(Level::EntityIndex);
+      self.entity_index.lock().in_chunks(min_chunk, max_chunk)
+    };
+    // Looking up players needs the players map, so we don't want to hold onto the
+    // entities map while doing that.
+    let entities: Vec<_> = {
+      let entities = self.entities();
+      eids.into_iter().filter_map(|eid| entities.inner.get(&eid).cloned()).collect()
+    };
+    entities
+      .into_iter()
+      .filter(|ent| ent.as_entity_ref(self).is_some_and(|e| e.hitbox().intersects(min, max)))
+      .collect()
+  }
+
+  /// Returns every entity whose feet are within `radius` of `center`. This
+  /// includes players.
+  pub fn entities_in_radius(self: &Arc<Self>, center: FPos, radius: f64) -> Vec<Entity> {
+    let r = FPos::new(radius, radius, radius);
+    let mut out = self.entities_in_box(center - r, center + r);
+    out.retain(|e| {
+      e.as_entity_ref(self).is_some_and(|e| e.pos().dist_squared(center) <= radius * radius)
+    });
+    out
   }
 
-  fn add_entity(&self, eid: i32, entity: Entity) { self.entities.write().insert(eid, entity); }
+  /// Finds the first entity that the line from `from` to `to` passes through.
+  /// The entity `exclude` is skipped, which is useful when raycasting from an
+  /// entity's eyes. Returns the position the line hit, and the entity hit.
+  pub fn raycast_entity(
+    self: &Arc<Self>,
+    from: FPos,
+    to: FPos,
+    exclude: Option<i32>,
+  ) -> Option<(FPos, Entity)> {
+    let mut closest: Option<(f64, Entity)> = None;
+    for ent in self.entities_in_box(from, to) {
+      let Some(r) = ent.as_entity_ref(self) else { continue };
+      if Some(r.eid()) == exclude {
+        continue;
+      }
+      if let Some(t) = r.hitbox().raycast(from, to) {
+        if closest.as_ref().map_or(true, |(c, _)| t < *c) {
+          closest = Some((t, ent));
+        }
+      }
+    }
+    closest.map(|(t, ent)| (from + (to - from) * t, ent))
+  }
+  /// Finds the first block or entity that the line from `from` to `to` passes
+  /// through. This is the same as [`raycast`](Self::raycast) and
+  /// [`raycast_entity`](Self::raycast_entity), but only returns whichever one
+  /// is closer. The entity is `None` if a block was hit.
+  pub fn raycast_any(
+    self: &Arc<Self>,
+    from: FPos,
+    to: FPos,
+    water: bool,
+    exclude: Option<i32>,
+  ) -> Option<(FPos, Option<Entity>)> {
+    let block = self.raycast(from, to, water).map(|(pos, _)| pos);
+    // Anything behind the block can't be hit, so we only check up to the block.
+    match self.raycast_entity(from, block.unwrap_or(to), exclude) {
+      Some((pos, ent)) => Some((pos, Some(ent))),
+      None => block.map(|pos| (pos, None)),
+    }
+  }
 
   #[allow(clippy::if_same_then_else)]
   pub(super) fn send_entity_spawn(&self, player: &Player, ent: &EntityRef) {
@@ -268,22 +499,34 @@ impl World {
         meta:     ent.metadata().clone(),
         living:   ent.ty().is_living(),
         head_yaw: (p.yaw / 360.0 * 256.0) as i8,
-        // We can't really check if its a falling block on the proxy, so we do cross-versioning
-        // this here.
-        data:     if ent.ty() == entity::Type::FallingBlock {
-          let bid = player.world().block_converter().to_old(data as u32, player.ver().block());
-          if player.ver().block() <= bb_common::version::BlockVersion::V1_12 {
-            // Reason for switching metadata in this one location: no idea whatsoever.
-            let meta = bid & 0xf;
-            let block_id = bid >> 4;
-            (block_id | meta << 12) as i32
-          } else {
-            bid as i32
-          }
-        } else {
-          data
-        },
+        data:     spawn_data(player, ent.ty(), data),
       });
+      if let EntityRef::Entity(e) = ent {
+        if let Some(packet) = e.attributes().to_packet(e.eid()) {
+          player.send(packet);
+        }
+        e.send_equipment(player);
+      }
+      self.send_passengers_to(player, ent.eid());
+    }
+  }
+}
+
+/// Converts the data sent in a spawn entity packet for the given player.
+pub(super) fn spawn_data(player: &Player, ty: entity::Type, data: i32) -> i32 {
+  // We can't really check if its a falling block on the proxy, so we do
+  // cross-versioning this here.
+  if ty == entity::Type::FallingBlock {
+    let bid = player.world().block_converter().to_old(data as u32, player.ver().block());
+    if player.ver().block() <= bb_common::version::BlockVersion::V1_12 {
+      // Reason for switching metadata in this one location: no idea whatsoever.
+      let meta = bid & 0xf;
+      let block_id = bid >> 4;
+      (block_id | meta << 12) as i32
+    } else {
+      bid as i32
     }
+  } else {
+    data
   }
 }
@@ -0,0 +1,96 @@
+use bb_common::math::ChunkPos;
+use std::collections::HashMap;
+
+/// A spatial index of every entity in a world. This stores which chunk each
+/// entity is in, so that finding the entities near a position only needs to
+/// look at a few chunks, instead of every entity in the world.
+///
+/// Players are included in this index as well.
+#[derive(Debug, Default)]
+pub struct EntityIndex {
+  chunks: HashMap<ChunkPos, Vec<i32>>,
+  eids:   HashMap<i32, ChunkPos>,
+}
+
+impl EntityIndex {
+  pub fn new() -> Self { EntityIndex::default() }
+
+  /// Adds an entity to the index. If the entity is already present, it will be
+  /// moved to `chunk`.
+  pub fn insert(&mut self, eid: i32, chunk: ChunkPos) {
+    if let Some(old) = self.eids.insert(eid, chunk) {
+      if old == chunk {
+        return;
+      }
+      self.remove_from_chunk(eid, old);
+    }
+    self.chunks.entry(chunk).or_default().push(eid);
+  }
+
+  /// Removes an entity from the index. This does nothing if the entity isn't
+  /// present.
+  pub fn remove(&mut self, eid: i32) {
+    if let Some(old) = self.eids.remove(&eid) {
+      self.remove_from_chunk(eid, old);
+    }
+  }
+
+  fn remove_from_chunk(&mut self, eid: i32, chunk: ChunkPos) {
+    if let Some(list) = self.chunks.get_mut(&chunk) {
+      list.retain(|&e| e != eid);
+      if list.is_empty() {
+        self.chunks.remove(&chunk);
+      }
+    }
+  }
+
+  /// Returns the chunk the given entity is in.
+  pub fn chunk_of(&self, eid: i32) -> Option<ChunkPos> { self.eids.get(&eid).copied() }
+
+  /// Returns all the entities in the given chunk.
+  pub fn in_chunk(&self, chunk: ChunkPos) -> &[i32] {
+    self.chunks.get(&chunk).map(|v| v.as_slice()).unwrap_or(&[])
+  }
+
+  /// Returns all the entities in every chunk from `min` to `max`, inclusive.
+  pub fn in_chunks(&self, min: ChunkPos, max: ChunkPos) -> Vec<i32> {
+    let mut out = vec![];
+    for pos in min.to(max) {
+      out.extend_from_slice(self.in_chunk(pos));
+    }
+    out
+  }
+
+  /// Returns the number of entities in the index.
+  pub fn len(&self) -> usize { self.eids.len() }
+  /// Returns `true` if there are no entities in the index.
+  pub fn is_empty(&self) -> bool { self.eids.is_empty() }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn move_entities() {
+    let mut index = EntityIndex::new();
+    index.insert(1, ChunkPos::new(0, 0));
+    index.insert(2, ChunkPos::new(0, 0));
+    index.insert(3, ChunkPos::new(2, -1));
+    assert_eq!(index.in_chunk(ChunkPos::new(0, 0)), [1, 2]);
+
+    index.insert(1, ChunkPos::new(1, 0));
+    assert_eq!(index.in_chunk(ChunkPos::new(0, 0)), [2]);
+    assert_eq!(index.chunk_of(1), Some(ChunkPos::new(1, 0)));
+
+    let mut found = index.in_chunks(ChunkPos::new(0, -1), ChunkPos::new(2, 0));
+    found.sort_unstable();
+    assert_eq!(found, [1, 2, 3]);
+    assert_eq!(index.in_chunks(ChunkPos::new(0, 0), ChunkPos::new(0, 0)), [2]);
+
+    index.remove(2);
+    index.remove(2);
+    assert!(index.in_chunk(ChunkPos::new(0, 0)).is_empty());
+    assert_eq!(index.len(), 2);
+  }
+}
@@ -22,6 +22,7 @@ impl Gen {
 impl BiomeGen for Gen {
   fn new(id: usize) -> Gen { Gen { id, cacti: PointGrid::new(12345, 16, 10) } }
   fn id(&self) -> usize { self.id }
+  fn name(&self) -> &'static str { "desert" }
   fn layers(&self) -> BiomeLayers {
     let mut layers = BiomeLayers::new(block::Kind::Stone);
     layers.add(block::Kind::Sandstone, 5);
@@ -58,6 +58,7 @@ impl Gen {
 impl BiomeGen for Gen {
   fn new(id: usize) -> Gen { Gen { id, trees: PointGrid::new(12345, 16, 5) } }
   fn id(&self) -> usize { self.id }
+  fn name(&self) -> &'static str { "forest" }
   fn decorate(
     &self,
     world: &WorldGen,
@@ -12,6 +12,7 @@ pub struct Gen {
 impl BiomeGen for Gen {
   fn new(id: usize) -> Gen { Gen { id } }
   fn id(&self) -> usize { self.id }
+  fn name(&self) -> &'static str { "mountains" }
   fn layers(&self) -> BiomeLayers {
     let mut layers = BiomeLayers::new(block::Kind::Stone);
     layers.add(block::Kind::SnowBlock, 3);
@@ -9,6 +9,7 @@ pub struct Gen {
 impl BiomeGen for Gen {
   fn new(id: usize) -> Gen { Gen { id } }
   fn id(&self) -> usize { self.id }
+  fn name(&self) -> &'static str { "plains" }
   fn decorate(
     &self,
     world: &WorldGen,
@@ -0,0 +1,101 @@
+use super::{MultiChunk, StageData, StageGen, WorldGen};
+use crate::block;
+use bb_common::math::{ChunkPos, RelPos};
+use std::{error::Error, fmt, str::FromStr};
+
+/// The layers of a superflat world, from the bottom up. These are parsed from
+/// a comma separated list of blocks, where each block can be prefixed with a
+/// count, like `bedrock,2*dirt,grass_block`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlatLayers {
+  layers: Vec<(block::Kind, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlatLayersError {
+  /// The count before a `*` was not a number.
+  InvalidCount(String),
+  /// The block name was not a known block.
+  UnknownBlock(String),
+}
+
+impl fmt::Display for FlatLayersError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::InvalidCount(s) => write!(f, "invalid layer count: {s}"),
+      Self::UnknownBlock(s) => write!(f, "unknown block: {s}"),
+    }
+  }
+}
+
+impl Error for FlatLayersError {}
+
+impl FlatLayers {
+  /// Returns the layers, from the bottom up, with the number of blocks in
+  /// each layer.
+  pub fn layers(&self) -> &[(block::Kind, u32)] { &self.layers }
+  /// Returns the total height of all the layers.
+  pub fn height(&self) -> u32 { self.layers.iter().map(|(_, count)| count).sum() }
+}
+
+impl FromStr for FlatLayers {
+  type Err = FlatLayersError;
+
+  fn from_str(s: &str) -> Result<Self, FlatLayersError> {
+    let mut layers = vec![];
+    for layer in s.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+      let (count, name) = match layer.split_once('*') {
+        Some((count, name)) => (
+          count.trim().parse().map_err(|_| FlatLayersError::InvalidCount(count.into()))?,
+          name.trim(),
+        ),
+        None => (1, layer),
+      };
+      let name = name.strip_prefix("minecraft:").unwrap_or(name);
+      let kind =
+        block::Kind::from_str(name).map_err(|_| FlatLayersError::UnknownBlock(name.into()))?;
+      layers.push((kind, count));
+    }
+    Ok(FlatLayers { layers })
+  }
+}
+
+/// Fills each chunk with the layers, starting at the bottom of the world.
+impl StageGen for FlatLayers {
+  fn generate(&self, _: &WorldGen, _: ChunkPos, c: &mut MultiChunk, data: &mut StageData) {
+    let max_y = c.min_y() + c.height() as i32 - 1;
+    let mut y = c.min_y();
+    for &(kind, count) in &self.layers {
+      if count == 0 {
+        continue;
+      }
+      let top = (y + count as i32 - 1).min(max_y);
+      c.fill_kind(RelPos::new(0, y, 0), RelPos::new(15, top, 15), kind).unwrap();
+      y = top + 1;
+      if y > max_y {
+        break;
+      }
+    }
+    data.heights = [y - 1; 256];
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse() {
+    assert_eq!(
+      "bedrock, 2*dirt,minecraft:grass_block".parse::<FlatLayers>().unwrap().layers(),
+      [(block::Kind::Bedrock, 1), (block::Kind::Dirt, 2), (block::Kind::GrassBlock, 1)]
+    );
+    assert_eq!("".parse::<FlatLayers>().unwrap().height(), 0);
+    assert_eq!("3*stone,60*air".parse::<FlatLayers>().unwrap().height(), 63);
+    assert_eq!("x*stone".parse::<FlatLayers>(), Err(FlatLayersError::InvalidCount("x".into())));
+    assert_eq!(
+      "2*not_a_block".parse::<FlatLayers>(),
+      Err(FlatLayersError::UnknownBlock("not_a_block".into()))
+    );
+  }
+}
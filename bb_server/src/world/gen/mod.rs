@@ -1,19 +1,28 @@
 use super::chunk::MultiChunk;
-use crate::{block, config::WorldConfig, math::WarpedVoronoi};
-use bb_common::math::{ChunkPos, Pos, RelPos, RngCore, WyhashRng};
-use noise::{BasicMulti, NoiseFn};
-use std::{
-  cmp::Ordering,
-  collections::{HashMap, HashSet},
+use crate::{
+  block,
+  config::{FlatConfig, WorldConfig},
+  math::WarpedVoronoi,
 };
+use bb_common::{
+  chunk::Stage,
+  math::{ChunkPos, Pos, RelPos, RngCore, WyhashRng},
+};
+use noise::BasicMulti;
+use parking_lot::RwLock;
+use std::{cmp::Ordering, collections::HashMap, sync::Arc};
 
 mod biomes;
 mod debug;
+pub mod flat;
 mod plugin;
+pub mod stage;
 mod underground;
 pub mod util;
 
+pub use flat::FlatLayers;
 pub use plugin::PBiomeGen;
+pub use stage::{StageData, StageGen};
 
 use underground::Underground;
 
@@ -88,6 +97,9 @@ pub trait BiomeGen {
   /// Returns this biome's id. This is used to check if a type is the correct
   /// biome, so returning the wrong thing here will break things.
   fn id(&self) -> usize;
+  /// Returns the vanilla name of this biome. This is used to find the natural
+  /// spawn list for this biome. Defaults to `plains`.
+  fn name(&self) -> &'static str { "plains" }
   /// This fills an entire chunk with the given biome. This will fill the chunk
   /// with stone, up to the height at the middle. It will then carve/add blocks
   /// to the other columns of the chunk. Finally, it will call
@@ -194,7 +206,7 @@ pub trait BiomeGen {
 }
 
 impl Default for WorldGen {
-  fn default() -> Self { Self::new() }
+  fn default() -> Self { Self::new(WorldConfig::default().seed as u64) }
 }
 
 pub struct WorldGen {
@@ -206,15 +218,21 @@ pub struct WorldGen {
   #[allow(unused)]
   underground: Underground,
   debug:       bool,
+  /// If set, this biome is used everywhere, instead of the biome map. This is
+  /// used for superflat worlds.
+  fixed_biome: Option<String>,
+  /// The generators for each stage, indexed by [`Stage`].
+  stages:      RwLock<[Vec<Arc<dyn StageGen>>; Stage::ALL.len()]>,
 }
 
 impl WorldGen {
-  pub fn new() -> Self {
+  /// Creates a void world generator with the given seed. Use
+  /// [`from_config`](Self::from_config) to get a generator with biomes.
+  pub fn new(seed: u64) -> Self {
     let mut stone = BasicMulti::new();
     stone.octaves = 3;
     let mut max_height = BasicMulti::new();
     max_height.octaves = 1;
-    let seed = 3210471203948712039;
     WorldGen {
       seed,
       biome_map: WarpedVoronoi::new(seed),
@@ -223,17 +241,31 @@ impl WorldGen {
       max_height,
       underground: Underground::new(seed),
       debug: false,
+      fixed_biome: None,
+      stages: RwLock::new(Default::default()),
     }
   }
   pub fn from_config(config: &WorldConfig) -> Self {
     if config.debug {
-      let mut gen = WorldGen::new();
+      let mut gen = WorldGen::new(config.seed as u64);
       gen.debug = true;
       gen
-    } else if config.void {
-      WorldGen::new()
+    } else if config.void || config.generator == "void" {
+      WorldGen::new(config.seed as u64)
+    } else if config.generator == "flat" {
+      let mut gen = WorldGen::new(config.seed as u64);
+      let layers = match config.flat.layers.parse::<FlatLayers>() {
+        Ok(l) => l,
+        Err(e) => {
+          warn!("invalid flat world layers '{}': {e}, using the default", config.flat.layers);
+          FlatConfig::default().layers.parse().unwrap()
+        }
+      };
+      gen.add_stage(Stage::Shape, layers);
+      gen.fixed_biome = Some(config.flat.biome.clone());
+      gen
     } else {
-      let mut gen = WorldGen::new();
+      let mut gen = WorldGen::new(config.seed as u64);
       /*
       for biome in config.get::<_, Vec<&str>>("biomes") {
       */
@@ -242,6 +274,7 @@ impl WorldGen {
           warn!("unknown biome '{}', skipping", biome);
         }
       }
+      stage::add_defaults(&gen);
       gen
     }
   }
@@ -256,68 +289,48 @@ impl WorldGen {
       c.block_light.update_all(&c.block);
       return;
     }
+    let stages = self.stages.read().clone();
     // Fast path for void worlds
-    if self.biomes.is_empty() {
+    if stages.iter().all(|s| s.is_empty()) {
       return;
     }
     c.enable_lighting(false);
-    let div = 32.0;
-    let min_height = 40.0_f64;
-    let b_min_height = min_height.floor() as i32;
-    c.fill_kind(RelPos::new(0, 0, 0), RelPos::new(15, b_min_height, 15), block::Kind::Stone)
-      .unwrap();
-    let mut biomes = HashSet::new();
-    let mut tops = HashMap::new();
-    for p in pos.columns() {
-      let x = p.x() as f64 / 64.0;
-      let z = p.z() as f64 / 64.0;
-      let max_height = (self.max_height.get([x, z]) * 1.0) / 2.0 * 50.0 + 200.0;
-      let b_max_height = max_height.ceil() as i32;
-      let biome = self.biome_id_at(p);
-      let layers = self.biomes[biome].layers();
-      biomes.insert(biome);
-      let mut depth = 0;
-      for y in (b_min_height..=b_max_height).rev() {
-        let rel = p.chunk_rel().with_y(y);
-        let val = {
-          let x = p.x() as f64 / div / 2.0;
-          let y = y as f64 / div;
-          let z = p.z() as f64 / div / 2.0;
-          self.stone.get([x, y, z])
-        };
-        let mut min = (y as f64 - min_height) / (max_height - min_height);
-        min = min * 2.0 - 1.0;
-        if val > min {
-          if depth == 0 {
-            tops.insert(p.with_y(y), biome);
-          }
-          c.set_kind(rel, layers.get(depth)).unwrap();
-          depth += 1;
-        } else {
-          depth = 0;
-        }
-      }
-    }
-    for b in &biomes {
-      self.biomes[*b].decorate(self, pos, c, &tops);
+    let mut data = StageData::default();
+    for stage in stages.iter().flatten() {
+      stage.generate(self, pos, c, &mut data);
     }
     c.enable_lighting(true);
-    /*
-    if biomes.len() == 1 {
-      for b in &biomes {
-        self.biomes[*b].fill_chunk(self, pos, c);
-      }
-    } else {
-      for p in pos.columns() {
-        let biome = self.biome_map.get(p.into()) as usize % self.biomes.len();
-        self.biomes[biome].fill_column(self, p, c);
-      }
-    }
-    */
   }
+
+  /// Adds a generator to the given stage. It will run after all the
+  /// generators already in that stage. This can be called at any time, and
+  /// will only change chunks that are generated afterwards.
+  pub fn add_stage(&self, stage: Stage, gen: impl StageGen + 'static) {
+    self.stages.write()[stage as usize].push(Arc::new(gen));
+  }
+  /// Replaces all the generators in the given stage with `gen`. For example,
+  /// replacing [`Stage::Features`] will remove the default trees and grass,
+  /// and leave the rest of the terrain as-is.
+  pub fn replace_stage(&self, stage: Stage, gen: impl StageGen + 'static) {
+    self.stages.write()[stage as usize] = vec![Arc::new(gen)];
+  }
+  /// Removes all the generators in the given stage.
+  pub fn clear_stage(&self, stage: Stage) { self.stages.write()[stage as usize].clear(); }
+
   pub fn biome_id_at(&self, pos: Pos) -> usize {
     self.biome_map.get(pos.into()) as usize % self.biomes.len()
   }
+  /// Returns the name of the biome at the given position. Returns `None` in
+  /// void worlds, as there are no biomes there.
+  pub fn biome_name_at(&self, pos: Pos) -> Option<&str> {
+    if let Some(biome) = &self.fixed_biome {
+      Some(biome)
+    } else if self.biomes.is_empty() {
+      None
+    } else {
+      Some(self.biomes[self.biome_id_at(pos)].name())
+    }
+  }
   pub fn dist_to_border(&self, pos: Pos) -> f64 { self.biome_map.dist_to_border(pos.into()) }
   pub fn is_biome<B: BiomeGen>(&self, b: &B, pos: Pos) -> bool {
     let actual = self.biome_id_at(pos);
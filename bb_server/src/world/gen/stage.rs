@@ -0,0 +1,154 @@
+//! The built-in generation stages. See [`Stage`] for the order these run in,
+//! and [`WorldGen::add_stage`] to add your own.
+
+use super::{MultiChunk, WorldGen};
+use crate::block;
+use bb_common::{
+  chunk::Stage,
+  math::{ChunkPos, Pos, RelPos},
+};
+use noise::NoiseFn;
+use std::collections::{HashMap, HashSet};
+
+/// Everything below this Y level is filled with stone by [`Shape`].
+const MIN_HEIGHT: i32 = 40;
+
+/// A single stage of a generator. This is called once for every chunk, with
+/// the chunk that the previous stages produced.
+///
+/// This is implemented for closures, so a stage can be added like so:
+///
+/// ```
+/// use bb_common::{
+///   chunk::Stage,
+///   math::{ChunkPos, RelPos},
+/// };
+/// use bb_server::{
+///   block,
+///   world::{
+///     gen::{StageData, WorldGen},
+///     MultiChunk,
+///   },
+/// };
+///
+/// let gen = WorldGen::new(0);
+/// // Places a layer of bedrock at the bottom of every chunk.
+/// gen.add_stage(Stage::Features, |_: &WorldGen, _: ChunkPos, c: &mut MultiChunk, _: &mut StageData| {
+///   c.fill_kind(RelPos::new(0, 0, 0), RelPos::new(15, 0, 15), block::Kind::Bedrock).unwrap();
+/// });
+/// ```
+pub trait StageGen: Send + Sync {
+  /// Generates this stage in the given chunk. `data` is shared between all of
+  /// the stages of this chunk.
+  fn generate(&self, world: &WorldGen, pos: ChunkPos, c: &mut MultiChunk, data: &mut StageData);
+}
+
+impl<F> StageGen for F
+where
+  F: Fn(&WorldGen, ChunkPos, &mut MultiChunk, &mut StageData) + Send + Sync,
+{
+  fn generate(&self, world: &WorldGen, pos: ChunkPos, c: &mut MultiChunk, data: &mut StageData) {
+    self(world, pos, c, data)
+  }
+}
+
+/// Information about a chunk, which is passed between each stage of
+/// generation.
+#[derive(Debug, Clone)]
+pub struct StageData {
+  /// The maximum height of the terrain in each column, indexed by `x + z *
+  /// 16`. This is set by the [`Shape`](Stage::Shape) stage, and the built-in
+  /// surface stage only looks at blocks below this, so any stage that
+  /// replaces the shape stage should set this as well.
+  pub heights: [i32; 256],
+  /// Every block that has air above it, mapped to the biome it is in. This is
+  /// set by the [`Surface`](Stage::Surface) stage, and is passed to
+  /// [`BiomeGen::decorate`](super::BiomeGen::decorate).
+  pub tops:    HashMap<Pos, usize>,
+  /// The biomes that are in this chunk. This is set by the
+  /// [`Surface`](Stage::Surface) stage.
+  pub biomes:  HashSet<usize>,
+}
+
+impl Default for StageData {
+  fn default() -> Self {
+    StageData { heights: [0; 256], tops: HashMap::new(), biomes: HashSet::new() }
+  }
+}
+
+/// Fills in the terrain with stone, using 3D noise.
+pub struct Shape;
+/// Replaces the top layers of stone with the layers from each biome.
+pub struct Surface;
+/// Calls [`BiomeGen::decorate`](super::BiomeGen::decorate) for every biome in
+/// the chunk.
+pub struct Decorate;
+
+/// Adds the built-in stages to the given generator.
+pub(super) fn add_defaults(gen: &WorldGen) {
+  gen.add_stage(Stage::Shape, Shape);
+  gen.add_stage(Stage::Surface, Surface);
+  gen.add_stage(Stage::Features, Decorate);
+}
+
+impl StageGen for Shape {
+  fn generate(&self, world: &WorldGen, pos: ChunkPos, c: &mut MultiChunk, data: &mut StageData) {
+    let div = 32.0;
+    let min_height = MIN_HEIGHT as f64;
+    c.fill_kind(RelPos::new(0, 0, 0), RelPos::new(15, MIN_HEIGHT, 15), block::Kind::Stone).unwrap();
+    for p in pos.columns() {
+      let x = p.x() as f64 / 64.0;
+      let z = p.z() as f64 / 64.0;
+      let max_height = (world.max_height.get([x, z]) * 1.0) / 2.0 * 50.0 + 200.0;
+      let b_max_height = max_height.ceil() as i32;
+      let rel = p.chunk_rel();
+      data.heights[rel.x() as usize + rel.z() as usize * 16] = b_max_height;
+      for y in MIN_HEIGHT..=b_max_height {
+        let val = {
+          let x = p.x() as f64 / div / 2.0;
+          let y = y as f64 / div;
+          let z = p.z() as f64 / div / 2.0;
+          world.stone.get([x, y, z])
+        };
+        let mut min = (y as f64 - min_height) / (max_height - min_height);
+        min = min * 2.0 - 1.0;
+        if val > min {
+          c.set_kind(rel.with_y(y), block::Kind::Stone).unwrap();
+        }
+      }
+    }
+  }
+}
+
+impl StageGen for Surface {
+  fn generate(&self, world: &WorldGen, pos: ChunkPos, c: &mut MultiChunk, data: &mut StageData) {
+    for p in pos.columns() {
+      let biome = world.biome_id_at(p);
+      let layers = world.biomes[biome].layers();
+      data.biomes.insert(biome);
+      let rel = p.chunk_rel();
+      let height = data.heights[rel.x() as usize + rel.z() as usize * 16];
+      let mut depth = 0;
+      for y in (MIN_HEIGHT..=height).rev() {
+        let rel = rel.with_y(y);
+        if c.get_kind(rel).unwrap() != block::Kind::Air {
+          if depth == 0 {
+            data.tops.insert(p.with_y(y), biome);
+          }
+          c.set_kind(rel, layers.get(depth)).unwrap();
+          depth += 1;
+        } else {
+          depth = 0;
+        }
+      }
+    }
+  }
+}
+
+impl StageGen for Decorate {
+  fn generate(&self, world: &WorldGen, pos: ChunkPos, c: &mut MultiChunk, data: &mut StageData) {
+    for b in &data.biomes {
+      world.biomes[*b].decorate(world, pos, c, &data.tops);
+    }
+  }
+}
@@ -1,22 +1,51 @@
-use super::{World, WorldManager};
+use super::{
+  logs,
+  schematic::{Mirror, Rotation, Schematic},
+  throttle::MAX_LEVEL,
+  tick_rate, volume, ChangeSet, Claim, CloneMask, CloneMode, Cuboid, EditError, FillMode,
+  ProfileError, TickTimings, World, WorldManager, EDIT_BATCH_SIZE,
+};
 use crate::{
   command::{Arg, Command, Parser, StringType},
-  entity,
+  net::broadcast,
   player::Player,
+  profile,
 };
 use bb_common::{
-  math::ChunkPos,
+  ban::{fmt_duration, parse_duration, Ban, BanTarget},
+  logger,
+  math::{ChunkPos, Pos},
   net::cb,
-  util::{Buffer, Chat, GameMode, JoinInfo, JoinMode, SwitchMode},
+  util::{chat::Color, fmt_bytes, Buffer, Chat, GameMode, JoinMode, SwitchMode, UUID},
   version::ProtocolVersion,
+  whitelist::WhitelistEntry,
+};
+use std::{
+  net::IpAddr,
+  path::PathBuf,
+  sync::Arc,
+  thread,
+  time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use std::sync::Arc;
 
 impl World {
   pub fn init(self: &Arc<World>) {
     if self.config().vanilla.enabled {
       self.load_from_disk(&std::path::PathBuf::new().join(&self.config().vanilla.path)).unwrap();
     }
+    if self.config().schematic.enabled {
+      let path = &self.config().schematic.path;
+      match Schematic::read_file(path, self.block_converter()) {
+        Ok(schem) => {
+          if let Err(e) =
+            self.paste_schematic(&schem, Pos::new(0, 0, 0), Rotation::None, Mirror::None, true)
+          {
+            error!("could not paste schematic {path}: {e}");
+          }
+        }
+        Err(e) => error!("could not load schematic file {path}: {e}"),
+      }
+    }
 
     let mut c = Command::new("say");
     c.add_arg("text", Parser::String(StringType::Greedy));
@@ -37,8 +66,20 @@ impl World {
       .add_arg("center", Parser::BlockPos)
       .add_arg("radius", Parser::Float { min: Some(0.0), max: None })
       .add_arg("block", Parser::BlockState);
-    self.commands().add(c, |world, _, args| {
+    let block = c
+      .add_arg("from", Parser::BlockPos)
+      .add_arg("to", Parser::BlockPos)
+      .add_arg("block", Parser::BlockState);
+    for mode in ["destroy", "hollow", "keep", "outline"] {
+      block.add_lit_opt(mode);
+    }
+    block.add_lit_opt("replace").add_arg_opt("filter", Parser::BlockState);
+    self.commands().add(c, |world, player, args| {
       // args[0] is `fill`
+      if let Arg::BlockPos(_) = args[1] {
+        handle_fill(world, player, args);
+        return;
+      }
       match args[1].lit() {
         "rect" => {
           let min = args[2].pos();
@@ -65,6 +106,75 @@ impl World {
         _ => unreachable!(),
       }
     });
+    let mut c = Command::new("clone");
+    let dest = c
+      .add_arg("begin", Parser::BlockPos)
+      .add_arg("end", Parser::BlockPos)
+      .add_arg("destination", Parser::BlockPos);
+    for mask in ["replace", "masked"] {
+      let mask = dest.add_lit_opt(mask);
+      for mode in ["force", "move", "normal"] {
+        mask.add_lit_opt(mode);
+      }
+    }
+    let filtered = dest.add_lit("filtered").add_arg("filter", Parser::BlockState);
+    for mode in ["force", "move", "normal"] {
+      filtered.add_lit_opt(mode);
+    }
+    self.commands().add(c, |world, player, args| {
+      // args[0] is `clone`
+      let (begin, end, dest) = (args[1].pos(), args[2].pos(), args[3].pos());
+      let (mask, mode) = match args.get(4).map(|a| a.lit()) {
+        None | Some("replace") => (CloneMask::Replace, args.get(5)),
+        Some("masked") => (CloneMask::Masked, args.get(5)),
+        Some("filtered") => (CloneMask::Filtered(args[5].block()), args.get(6)),
+        _ => unreachable!(),
+      };
+      let mode = match mode.map(|a| a.lit()) {
+        None | Some("normal") => CloneMode::Normal,
+        Some("force") => CloneMode::Force,
+        Some("move") => CloneMode::Move,
+        _ => unreachable!(),
+      };
+      run_edit(world, player, begin, end, "cloned", move |w, progress| {
+        w.clone_region(begin, end, dest, mask, mode, progress)
+      });
+    });
+
+    for name in ["undo", "redo"] {
+      let mut c = Command::new(name);
+      c.add_arg_opt("count", Parser::Int { min: Some(1), max: None });
+      self.commands().add(c, move |_, player, args| {
+        let p = match player {
+          Some(p) => p,
+          None => return,
+        };
+        let count = args.get(1).map(|a| a.int()).unwrap_or(1);
+        let mut edits = 0;
+        let mut changed = 0;
+        for _ in 0..count {
+          let res = if name == "undo" { p.undo() } else { p.redo() };
+          match res {
+            Ok(Some(c)) => {
+              edits += 1;
+              changed += c;
+            }
+            Ok(None) => break,
+            Err(e) => {
+              p.send_message(error(format!("Could not {name}: {e}")));
+              return;
+            }
+          }
+        }
+        if edits == 0 {
+          p.send_message(error(format!("Nothing to {name}")));
+        } else {
+          let verb = if name == "undo" { "Undid" } else { "Redid" };
+          p.send_message(Chat::new(format!("{verb} {edits} edit(s), changing {changed} block(s)")));
+        }
+      });
+    }
+
     fn handle_gamemode(wm: &Arc<WorldManager>, runner: Option<&Arc<Player>>, args: Vec<Arg>) {
       let gm = match &args[1] {
         Arg::Literal(lit) => match lit.as_str() {
@@ -166,17 +276,397 @@ impl World {
     c.add_arg("addr", Parser::String(StringType::Word));
     self.commands().add(c, |_, player, args| {
       if let Some(p) = player {
-        let addr = match args[1].str().parse::<std::net::SocketAddr>() {
-          Ok(a) => a,
-          Err(e) => {
-            p.send_message(Chat::new(e.to_string()));
-            return;
+        match args[1].str().parse::<std::net::SocketAddr>() {
+          Ok(addr) => p.switch_to(SwitchMode::Loading, vec![addr]),
+          Err(_) => p.switch_to_host(SwitchMode::Loading, args[1].str().into()),
+        }
+      }
+    });
+
+    let c = Command::new("traces");
+    self.commands().add(c, |_, player, _| {
+      if let Some(p) = player {
+        let traces = p.traces();
+        let slowest = traces.slowest(10);
+        if slowest.is_empty() {
+          p.send_message(Chat::new("no traced packets (is tracing enabled on the proxy?)"));
+        }
+        for trace in slowest {
+          p.send_message(Chat::new(trace.to_string()));
+        }
+      }
+    });
+
+    let mut c = Command::new("debug");
+    c.add_lit("chunks");
+    c.add_lit("hitboxes");
+    c.add_lit("path").add_arg_opt("entity", Parser::Entity { single: true, only_players: false });
+    self.commands().add(c, |_, player, args| {
+      // These are all particles, so they can only be shown to a player.
+      let Some(p) = player else { return };
+      let on_off = |on: bool| if on { "on" } else { "off" };
+      let msg = match args[1].lit() {
+        "chunks" => format!("chunk borders {}", on_off(p.toggle_debug_chunks())),
+        "hitboxes" => format!("hitboxes {}", on_off(p.toggle_debug_hitboxes())),
+        "path" => match args.get(2) {
+          Some(arg) => {
+            let world = p.world();
+            let entities = world.entities();
+            // Players never pathfind, so they are skipped.
+            let found = arg
+              .entity()
+              .iter(&entities, Some(p))
+              .find(|e| e.as_player().is_none())
+              .map(|e| (e.handle(), e.path_debug().is_some()));
+            match found {
+              Some((handle, pathfinding)) => {
+                p.set_debug_path(Some(handle));
+                if pathfinding {
+                  format!("showing the path of entity {handle}")
+                } else {
+                  format!("showing the path of entity {handle} (it is not pathfinding right now)")
+                }
+              }
+              None => "no entity found".into(),
+            }
+          }
+          None => {
+            p.set_debug_path(None);
+            "path overlay off".into()
           }
+        },
+        _ => unreachable!(),
+      };
+      p.send_message(Chat::new(msg));
+    });
+
+    let mut c = Command::new("profile");
+    c.add_arg("seconds", Parser::Int { min: Some(1), max: Some(60) });
+    self.commands().add(c, |_, player, args| {
+      // args[0] is `profile`
+      let secs = args[1].int() as u64;
+      let player = player.cloned();
+      let reply = move |msg: String| match &player {
+        Some(p) => p.send_message(Chat::new(msg)),
+        None => info!("{msg}"),
+      };
+      if !profile::start() {
+        reply("a profile is already running".into());
+        return;
+      }
+      reply(format!("profiling for {secs} seconds..."));
+      thread::spawn(move || {
+        thread::sleep(Duration::from_secs(secs));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let path = PathBuf::from(format!("profile-{}.json", now.as_secs()));
+        match profile::stop(&path) {
+          Ok(spans) => reply(format!("wrote {spans} spans to {}", path.display())),
+          Err(e) => reply(format!("could not write profile: {e}")),
+        }
+      });
+    });
+
+    let c = Command::new("tps");
+    self.commands().add(c, |wm, player, _| {
+      let mut lines = vec![];
+      for w in wm.worlds().iter() {
+        let mut msg = Chat::empty();
+        msg.add(format!("{}: ", w.config().name));
+        add_tps(&mut msg, w.timings.tps(20 * 5));
+        msg.add(" (5s), ");
+        add_tps(&mut msg, w.timings.tps(20 * 60));
+        msg.add(" (1m)");
+        if w.throttle.enabled() {
+          let level = w.throttle.level();
+          msg.add(format!(", throttle level {level}/{MAX_LEVEL}")).color(if level == 0 {
+            Color::BrightGreen
+          } else {
+            Color::Gold
+          });
+        }
+        lines.push(msg);
+      }
+      reply_lines(player, lines);
+    });
+
+    let c = Command::new("status");
+    self.commands().add(c, |wm, player, _| {
+      let usage = wm.usage();
+      let mut lines = vec![Chat::new("Server status:")];
+      lines.extend(usage.lines().into_iter().map(Chat::new));
+      reply_lines(player, lines);
+    });
+
+    let c = Command::new("timings");
+    self.commands().add(c, |wm, player, _| {
+      let mut lines = vec![Chat::new("Average tick times (hover for details):")];
+      for w in wm.worlds().iter() {
+        let (regions, chunks) = w.regions.loaded();
+        let mut msg = Chat::empty();
+        msg.add(format!("{}: ", w.config().name));
+        add_phases(&mut msg, &w.timings);
+        msg.add(format!(
+          ", {chunks} chunks in {regions} regions, {} entities, {} players",
+          w.entities().iter_values().len(),
+          w.players().len(),
+        ));
+        lines.push(msg);
+      }
+      let mut msg = Chat::empty();
+      msg.add("plugins: ");
+      add_phases(&mut msg, wm.timings());
+      lines.push(msg);
+
+      let stats = broadcast::stats();
+      lines.push(Chat::new(format!(
+        "Broadcasts: {} packets sent from {} serializations, {} saved",
+        stats.sent,
+        stats.encoded,
+        fmt_bytes(stats.bytes_saved),
+      )));
+
+      let slowest = wm.plugins().timings().slowest(5);
+      if !slowest.is_empty() {
+        lines.push(Chat::new("Slowest plugins per event (hover for details):"));
+      }
+      for (event, plugins) in slowest {
+        let Some((name, calls)) = plugins.first() else { continue };
+        let details = plugins
+          .iter()
+          .map(|(name, c)| {
+            format!(
+              "{name}: {} avg, {} max, {} calls",
+              fmt_time(c.average()),
+              fmt_time(c.max),
+              c.count
+            )
+          })
+          .collect::<Vec<_>>()
+          .join("\n");
+        let mut msg = Chat::empty();
+        msg.add(format!("{event}: "));
+        msg
+          .add(format!("{name} ({})", fmt_time(calls.average())))
+          .color(tick_color(calls.average()))
+          .show_text(details);
+        lines.push(msg);
+      }
+      reply_lines(player, lines);
+    });
+
+    let mut c = Command::new("logs");
+    c.add_lit("tail").add_arg_opt("count", Parser::Int { min: Some(1), max: Some(100) });
+    self.commands().add(c, |wm, player, args| {
+      if let Some(p) = player {
+        if !wm.can_see_logs(p) {
+          p.send_message(error("You are not allowed to view the logs".into()));
+          return;
+        }
+      }
+      let count = args.get(2).map(|a| a.int() as usize).unwrap_or(10);
+      let lines = logger::recent(count).iter().map(logs::log_chat).collect();
+      reply_lines(player, lines);
+    });
+
+    for name in ["ban", "tempban"] {
+      let mut c = Command::new(name);
+      let target = c.add_arg("target", Parser::String(StringType::Word));
+      let target = if name == "tempban" {
+        target.add_arg("duration", Parser::String(StringType::Word))
+      } else {
+        target
+      };
+      target.add_arg_opt("reason", Parser::String(StringType::Greedy));
+      self.commands().add(c, move |wm, player, args| {
+        let Some((target, username)) = ban_target(wm, args[1].str()) else {
+          reply_lines(player, vec![error(format!("Unknown player {}", args[1].str()))]);
+          return;
+        };
+        let (duration, reason) = if name == "tempban" {
+          match parse_duration(args[2].str()) {
+            Some(d) => (Some(d), args.get(3)),
+            None => {
+              reply_lines(player, vec![error(format!("Invalid duration {}", args[2].str()))]);
+              return;
+            }
+          }
+        } else {
+          (None, args.get(2))
+        };
+        let reason = reason.map(|a| a.str().to_string()).unwrap_or_default();
+        let source = player.map(|p| p.username().clone()).unwrap_or_else(|| "Server".into());
+        let mut ban = Ban::new(target, reason, source, duration);
+        if let Some(name) = username {
+          ban = ban.with_name(name);
+        }
+        let msg = match duration {
+          Some(d) => format!("Banned {} for {}", ban.display_name(), fmt_duration(d)),
+          None => format!("Banned {}", ban.display_name()),
+        };
+        wm.ban(ban);
+        reply_lines(player, vec![Chat::new(msg)]);
+      });
+    }
+
+    let c = Command::new("banlist");
+    self.commands().add(c, |wm, player, _| {
+      let bans = wm.bans();
+      let mut lines = vec![Chat::new(format!("There are {} ban(s):", bans.iter().count()))];
+      for ban in bans.iter() {
+        let mut msg = Chat::empty();
+        msg
+          .add(ban.display_name())
+          .color(Color::Yellow)
+          .show_text(format!("{}\nBanned by {}", ban.target, ban.source));
+        if !ban.reason.is_empty() {
+          msg.add(format!(": {}", ban.reason));
+        }
+        let expires = match ban.remaining() {
+          Some(time) => format!(" (expires in {})", fmt_duration(time)),
+          None => " (permanent)".into(),
         };
-        p.switch_to(SwitchMode::Loading, vec![addr]);
+        msg.add(expires).color(Color::Gray);
+        lines.push(msg);
+      }
+      drop(bans);
+      reply_lines(player, lines);
+    });
+
+    let mut c = Command::new("pardon");
+    c.add_arg("target", Parser::String(StringType::Word));
+    self.commands().add(c, |wm, player, args| {
+      let name = args[1].str();
+      // Banned players aren't online, so we need to look them up in the ban list.
+      let target = wm.bans().find_name(name).map(|b| b.target);
+      let target = match target.or_else(|| ban_target(wm, name).map(|(t, _)| t)) {
+        Some(t) => t,
+        None => {
+          reply_lines(player, vec![error(format!("{name} is not banned"))]);
+          return;
+        }
+      };
+      match wm.pardon(target) {
+        Some(ban) => {
+          reply_lines(player, vec![Chat::new(format!("Pardoned {}", ban.display_name()))])
+        }
+        None => reply_lines(player, vec![error(format!("{name} is not banned"))]),
       }
     });
 
+    let mut c = Command::new("whitelist");
+    c.add_lit("on");
+    c.add_lit("off");
+    c.add_lit("list");
+    c.add_lit("add").add_arg("player", Parser::String(StringType::Word));
+    c.add_lit("remove").add_arg("player", Parser::String(StringType::Word));
+    self.commands().add(c, |wm, player, args| {
+      let msg = match args[1].lit() {
+        "on" | "off" => {
+          let enabled = args[1].lit() == "on";
+          wm.edit_whitelist(|w| w.set_enabled(enabled));
+          Chat::new(format!("Turned the whitelist {}", args[1].lit()))
+        }
+        "list" => {
+          let whitelist = wm.whitelist();
+          let names: Vec<_> = whitelist.entries().iter().map(|e| e.display_name()).collect();
+          let state = if whitelist.enabled() { "on" } else { "off" };
+          Chat::new(format!(
+            "The whitelist is {state}, and has {} player(s): {}",
+            names.len(),
+            names.join(", ")
+          ))
+        }
+        "add" => {
+          let name = args[2].str();
+          let entry = match wm.get_player_username(&name.to_string()) {
+            Some(p) => WhitelistEntry { uuid: Some(p.id()), name: Some(p.username().clone()) },
+            None => match UUID::from_dashed_str(name).or_else(|_| name.parse()) {
+              Ok(id) => WhitelistEntry { uuid: Some(id), name: None },
+              Err(_) => match wm.profiles().lookup(name) {
+                Ok(p) => WhitelistEntry { uuid: Some(p.id), name: Some(p.name) },
+                Err(ProfileError::NotFound) => {
+                  reply_lines(player, vec![error(format!("Unknown player {name}"))]);
+                  return;
+                }
+                // The UUID will be filled in when they join.
+                Err(e) => {
+                  warn!("could not look up {name}: {e}");
+                  WhitelistEntry { uuid: None, name: Some(name.into()) }
+                }
+              },
+            },
+          };
+          if wm.edit_whitelist(|w| w.add(entry)) {
+            Chat::new(format!("Added {name} to the whitelist"))
+          } else {
+            error(format!("{name} is already on the whitelist"))
+          }
+        }
+        "remove" => {
+          let name = args[2].str();
+          match wm.edit_whitelist(|w| w.remove(name)) {
+            Some(e) => Chat::new(format!("Removed {} from the whitelist", e.display_name())),
+            None => error(format!("{name} is not on the whitelist")),
+          }
+        }
+        _ => unreachable!(),
+      };
+      reply_lines(player, vec![msg]);
+    });
+
+    let mut c = Command::new("maintenance");
+    c.add_lit("on").add_arg_opt("message", Parser::String(StringType::Greedy));
+    c.add_lit("off");
+    self.commands().add(c, |wm, player, args| {
+      let maintenance = args[1].lit() == "on";
+      wm.set_maintenance(maintenance, args.get(2).map(|a| a.str().to_string()));
+      let msg = if maintenance {
+        "Turned on maintenance mode. Only players on the whitelist can join."
+      } else {
+        "Turned off maintenance mode"
+      };
+      reply_lines(player, vec![Chat::new(msg)]);
+    });
+
+    let mut c = Command::new("claim");
+    c.add_lit("create")
+      .add_arg("name", Parser::String(StringType::Word))
+      .add_arg_opt("from", Parser::BlockPos)
+      .add_arg("to", Parser::BlockPos);
+    c.add_lit("remove").add_arg("name", Parser::String(StringType::Word));
+    c.add_lit("list");
+    c.add_lit("info");
+    c.add_lit("trust")
+      .add_arg("name", Parser::String(StringType::Word))
+      .add_arg("player", Parser::String(StringType::Word));
+    c.add_lit("untrust")
+      .add_arg("name", Parser::String(StringType::Word))
+      .add_arg("player", Parser::String(StringType::Word));
+    self.commands().add(c, handle_claim);
+
+    let mut c = Command::new("forceload");
+    c.add_lit("add").add_arg("from", Parser::ColumnPos).add_arg_opt("to", Parser::ColumnPos);
+    let remove = c.add_lit("remove");
+    remove.add_lit("all");
+    remove.add_arg("from", Parser::ColumnPos).add_arg_opt("to", Parser::ColumnPos);
+    c.add_lit("query").add_arg_opt("pos", Parser::ColumnPos);
+    self.commands().add(c, handle_forceload);
+
+    let mut c = Command::new("tick");
+    c.add_lit("query");
+    c.add_lit("rate").add_arg(
+      "rate",
+      Parser::Float {
+        min: Some(tick_rate::MIN_RATE as f32),
+        max: Some(tick_rate::MAX_RATE as f32),
+      },
+    );
+    c.add_lit("freeze");
+    c.add_lit("unfreeze");
+    c.add_lit("step").add_arg_opt("ticks", Parser::Int { min: Some(1), max: None });
+    c.add_lit("sprint").add_arg("ticks", Parser::Int { min: Some(0), max: None });
+    self.commands().add(c, handle_tick);
+
     info!("generating terrain...");
     /*
     let chunks = Mutex::new(vec![]);
@@ -212,8 +702,11 @@ impl World {
     info!("done generating terrain");
   }
 
-  pub(super) fn player_init(self: &Arc<Self>, player: &Player, info: JoinInfo) {
-    match info.mode {
+  /// Sends everything a player needs after being added to this world. For
+  /// anything other than [`JoinMode::New`], the client is still in another
+  /// world, so it is sent a respawn to clear out the old world first.
+  pub(super) fn player_init(self: &Arc<Self>, player: &Arc<Player>, mode: JoinMode) {
+    match mode {
       JoinMode::New => {
         let out = cb::packet::JoinGame {
           // entity_id:                self.eid(),
@@ -264,35 +757,42 @@ impl World {
 
         player.send(out);
       }
-      JoinMode::Switch(SwitchMode::Loading) => {
+      // If we get here for a resume, then we weren't holding onto the player
+      // (usually because the server restarted). The client is still in the old
+      // world, so this is the same as a switch.
+      JoinMode::Switch(SwitchMode::Loading) | JoinMode::Resume => {
+        // Clients only clear their chunks when the dimension changes, so we move
+        // them to the end and back.
         player.send(cb::packet::Respawn {
-          difficulty: 1,
-          dimension:  1,
-          game_mode:  player.game_mode(),
-          level_type: "default".into(),
-          reset_meta: true,
+          difficulty:   1,
+          dimension:    1,
+          game_mode:    player.game_mode(),
+          level_type:   "default".into(),
+          reset_meta:   true,
+          world_height: self.height,
+          world_min_y:  self.min_y,
         });
         player.send(cb::packet::Respawn {
-          difficulty: 1,
-          dimension:  0,
-          game_mode:  player.game_mode(),
-          level_type: "default".into(),
-          reset_meta: true,
+          difficulty:   1,
+          dimension:    0,
+          game_mode:    player.game_mode(),
+          level_type:   "default".into(),
+          reset_meta:   true,
+          world_height: self.height,
+          world_min_y:  self.min_y,
         });
         let pos = player.pos().chunk();
-        for x in -(player.view_distance() as i32)..=player.view_distance() as i32 {
-          for z in -(player.view_distance() as i32)..=player.view_distance() as i32 {
-            player.send(cb::packet::UnloadChunk { pos: pos + ChunkPos::new(x, z) });
-          }
+        for pos in pos.around(player.view_distance()) {
+          player.send(cb::packet::UnloadChunk { pos });
         }
       }
       JoinMode::Switch(SwitchMode::Seemless) => {}
     }
 
     if player.ver() >= ProtocolVersion::V1_13 {
-      // TODO: Fix tags for older versions.
-      if player.ver() >= ProtocolVersion::V1_18_2 {
-        player.send(self.world_manager().tags().serialize());
+      // There is no 1.13 block data, so we can't send tags to 1.13 clients.
+      if player.ver() >= ProtocolVersion::V1_14_4 {
+        player.send(self.world_manager().tags().serialize(player.ver()));
       }
       player.send(self.commands().serialize());
     }
@@ -305,13 +805,9 @@ impl World {
       status: 28,
     });
 
-    let d = player.view_distance() as i32;
-    for x in -d..=d {
-      for z in -d..=d {
-        let pos = ChunkPos::new(x, z);
-        self.inc_view(pos);
-        player.send_chunk(pos, || self.serialize_chunk(pos).into());
-      }
+    for pos in player.pos().chunk().around(player.view_distance()) {
+      self.inc_view(pos);
+      player.send_chunk(pos, || self.serialize_chunk(pos).into());
     }
 
     let mut data = vec![];
@@ -329,13 +825,7 @@ impl World {
       should_dismount: true,
     });
 
-    let my_info = cb::PlayerListAdd {
-      id:           player.id(),
-      name:         player.username().clone(),
-      game_mode:    GameMode::Creative,
-      ping:         50,
-      display_name: player.tab_name().clone().map(|c| c.to_json()),
-    };
+    let my_info = player.list_entry();
     let my_info_packet =
       cb::packet::PlayerList { action: cb::PlayerListAction::Add(vec![my_info.clone()]) };
 
@@ -347,57 +837,362 @@ impl World {
       other.send(my_info_packet.clone());
 
       // Add `other` to the list of players that I know about
-      info.push(cb::PlayerListAdd {
-        id:           other.id(),
-        name:         other.username().clone(),
-        game_mode:    GameMode::Creative,
-        ping:         50,
-        display_name: other.tab_name().clone().map(|c| c.to_json()),
-      });
+      info.push(other.list_entry());
     }
     player.send(cb::packet::PlayerList { action: cb::PlayerListAction::Add(info) });
 
-    for other in self.entities().iter() {
-      if !player.in_view(other.pos().block().chunk()) {
-        continue;
+    // Spawn everything in view for me, and spawn me for everyone that can see me.
+    self.track_player(player);
+
+    for (_, team) in self.wm.teams().iter() {
+      team.lock().send_join(player);
+    }
+  }
+}
+
+/// Handles the vanilla form of `/fill`, which is `/fill <from> <to> <block>
+/// [mode]`.
+fn handle_fill(wm: &Arc<WorldManager>, player: Option<&Arc<Player>>, args: Vec<Arg>) {
+  let (from, to, kind) = (args[1].pos(), args[2].pos(), args[3].block());
+  let mode = match args.get(4).map(|a| a.lit()) {
+    None | Some("replace") => FillMode::Replace(args.get(5).map(|a| a.block())),
+    Some("destroy") => FillMode::Destroy,
+    Some("hollow") => FillMode::Hollow,
+    Some("keep") => FillMode::Keep,
+    Some("outline") => FillMode::Outline,
+    _ => unreachable!(),
+  };
+  run_edit(wm, player, from, to, "filled", move |w, progress| {
+    w.fill(from, to, w.block_converter().get(kind).default_type(), mode, progress)
+  });
+}
+
+/// Runs a `/fill` or `/clone` in the player's world. Edits larger than the
+/// `max-fill-volume` in the config are rejected. Edits larger than a single
+/// batch are run on another thread, and their progress is shown in the
+/// player's hotbar. The changes are added to the player's history, so that
+/// they can be undone with `/undo`.
+fn run_edit<F>(
+  wm: &Arc<WorldManager>,
+  player: Option<&Arc<Player>>,
+  from: Pos,
+  to: Pos,
+  verb: &'static str,
+  edit: F,
+) where
+  F: FnOnce(&Arc<World>, &mut dyn FnMut(u64, u64)) -> Result<ChangeSet, EditError> + Send + 'static,
+{
+  let volume = volume(from, to);
+  let max = u64::from(wm.config().max_fill_volume);
+  if volume > max {
+    if let Some(p) = player {
+      p.send_message(error(format!(
+        "Too many blocks in the specified area (maximum {max}, specified {volume})"
+      )));
+    }
+    return;
+  }
+  let world = player.map(|p| p.world()).unwrap_or_else(|| wm.default_world());
+  let player = player.cloned();
+  let run = move || {
+    let mut progress = |done: u64, total: u64| {
+      if let Some(p) = &player {
+        if total > EDIT_BATCH_SIZE {
+          p.send_hotbar(Chat::new(format!("{done}/{total} blocks {verb}")));
+        }
       }
-      if let Some(other) = other.as_player() {
-        // We don't want either packet if this is the same player.
-        if other.id() == player.id() {
-          continue;
+    };
+    let msg = match edit(&world, &mut progress) {
+      Ok(changes) if changes.is_empty() => error(format!("No blocks were {verb}")),
+      Ok(changes) => {
+        let changed = changes.len();
+        if let Some(p) = &player {
+          p.remember(changes);
         }
-        // Create a packet that will spawn me for `other`
-        let (pos, pitch, yaw) = player.pos_look();
-        other.send(cb::packet::SpawnPlayer {
-          eid: player.eid(),
-          id: player.id(),
-          ty: entity::Type::Player.id(),
-          pos,
-          yaw: yaw as i8,
-          pitch: pitch as i8,
-          meta: player.metadata(),
-        });
+        Chat::new(format!("Successfully {verb} {changed} block(s)"))
+      }
+      Err(e) => error(format!("Could not edit blocks: {e}")),
+    };
+    if let Some(p) = &player {
+      p.send_message(msg);
+    }
+  };
+  if volume > EDIT_BATCH_SIZE {
+    std::thread::spawn(run);
+  } else {
+    run();
+  }
+}
 
-        // Create a packet that will spawn `other` for me
-        let (pos, pitch, yaw) = other.pos_look();
-        player.send(cb::packet::SpawnPlayer {
-          eid: other.eid(),
-          id: other.id(),
-          ty: entity::Type::Player.id(),
-          pos,
-          yaw: yaw as i8,
-          pitch: pitch as i8,
-          meta: other.metadata(),
-        });
+/// Handles `/claim`. Claims can only be made by players, and are made in the
+/// player's world. If no corners are given, the player's selection is used.
+fn handle_claim(wm: &Arc<WorldManager>, player: Option<&Arc<Player>>, args: Vec<Arg>) {
+  let Some(p) = player else {
+    info!("only players can use /claim");
+    return;
+  };
+  let world = p.world();
+  if !world.config().claims {
+    p.send_message(error("Claims are disabled in this world".into()));
+    return;
+  }
+  // Only the owner of a claim can remove it, or change who can build in it.
+  let owned = |claim: Option<&Claim>, name: &str| match claim {
+    Some(c) if c.owner == p.id() => Ok(()),
+    Some(_) => Err(error(format!("You don't own the claim {name}"))),
+    None => Err(error(format!("There is no claim named {name}"))),
+  };
+  let msg = match args[1].lit() {
+    "create" => {
+      let name = args[2].str();
+      let area = match (args.get(3), args.get(4)) {
+        (Some(from), Some(to)) => Some(Cuboid::new(from.pos(), to.pos())),
+        _ => p.selection(),
+      };
+      match area {
+        Some(area) => {
+          let claim = Claim::new(name.into(), p.id(), p.username().clone(), area);
+          let mut claims = world.claims();
+          match claims.add(claim) {
+            Ok(()) => {
+              claims.save();
+              Chat::new(format!("Claimed {} block(s) as {name}", area.volume()))
+            }
+            Err(e) => error(format!("Could not claim that area: {e}")),
+          }
+        }
+        None => error("Select an area, or give two corners, to claim it".into()),
+      }
+    }
+    "remove" => {
+      let name = args[2].str();
+      let mut claims = world.claims();
+      match owned(claims.get(name), name) {
+        Ok(()) => {
+          claims.remove(name);
+          claims.save();
+          Chat::new(format!("Removed the claim {name}"))
+        }
+        Err(msg) => msg,
+      }
+    }
+    "list" => {
+      let claims = world.claims();
+      let names: Vec<_> =
+        claims.iter().filter(|c| c.owner == p.id()).map(|c| c.name.clone()).collect();
+      Chat::new(format!("You have {} claim(s): {}", names.len(), names.join(", ")))
+    }
+    "info" => match world.claims().at(p.pos().block()) {
+      Some(c) => {
+        let area = c.area();
+        Chat::new(format!(
+          "You are in {}, owned by {}, from {} to {}",
+          c.name,
+          c.owner_name,
+          area.min(),
+          area.max()
+        ))
       }
-      if other.as_entity().is_some() {
-        // Create a packet that will spawn `other` for me
-        self.send_entity_spawn(player, &other);
+      None => Chat::new("This area isn't claimed"),
+    },
+    "trust" | "untrust" => {
+      let (name, target) = (args[2].str(), args[3].str());
+      let Some(target) = wm.get_player_username(&target.to_string()) else {
+        p.send_message(error(format!("Unknown player {target}")));
+        return;
+      };
+      let mut claims = world.claims();
+      match owned(claims.get(name), name) {
+        Ok(()) => {
+          let claim = claims.get_mut(name).unwrap();
+          claim.members.retain(|&id| id != target.id());
+          let msg = if args[1].lit() == "trust" {
+            claim.members.push(target.id());
+            format!("{} can now build in {name}", target.username())
+          } else {
+            format!("{} can no longer build in {name}", target.username())
+          };
+          claims.save();
+          Chat::new(msg)
+        }
+        Err(msg) => msg,
       }
     }
+    _ => unreachable!(),
+  };
+  p.send_message(msg);
+}
 
-    for (_, team) in self.wm.teams().iter() {
-      team.lock().send_join(player);
+/// The most chunks that can be changed with a single `/forceload`. This is
+/// the same as vanilla.
+const FORCELOAD_LIMIT: usize = 256;
+
+/// Handles `/forceload`. This changes the sender's world, or the default world
+/// when run from the console.
+fn handle_forceload(wm: &Arc<WorldManager>, player: Option<&Arc<Player>>, args: Vec<Arg>) {
+  let world = player.map(|p| p.world()).unwrap_or_else(|| wm.default_world());
+  let msg = match (args[1].lit(), args.get(2)) {
+    ("remove", Some(Arg::Literal(_))) => {
+      let chunks = world.force_loaded_chunks();
+      for &pos in &chunks {
+        world.set_force_loaded(pos, false);
+      }
+      Chat::new(format!("Removed {} forced chunk(s)", chunks.len()))
+    }
+    ("add" | "remove", _) => {
+      let forced = args[1].lit() == "add";
+      let from = args[2].column();
+      let to = args.get(3).map(|a| a.column()).unwrap_or(from);
+      let count = from.to(to).count();
+      if count > FORCELOAD_LIMIT {
+        error(format!("Too many chunks ({count}), the limit is {FORCELOAD_LIMIT}"))
+      } else {
+        let changed = from.to(to).filter(|&pos| world.set_force_loaded(pos, forced)).count();
+        let verb = if forced { "Force loaded" } else { "Stopped force loading" };
+        Chat::new(format!("{verb} {changed} chunk(s)"))
+      }
     }
+    ("query", Some(pos)) => {
+      let pos = pos.column();
+      if world.is_force_loaded(pos) {
+        Chat::new(format!("Chunk {} {} is force loaded", pos.x(), pos.z()))
+      } else {
+        Chat::new(format!("Chunk {} {} is not force loaded", pos.x(), pos.z()))
+      }
+    }
+    ("query", None) => {
+      let mut chunks = world.force_loaded_chunks();
+      chunks.sort_unstable_by_key(|pos| (pos.x(), pos.z()));
+      let list: Vec<_> = chunks.iter().map(|pos| format!("[{}, {}]", pos.x(), pos.z())).collect();
+      Chat::new(format!("{} chunk(s) are force loaded: {}", chunks.len(), list.join(", ")))
+    }
+    _ => unreachable!(),
+  };
+  reply_lines(player, vec![msg]);
+}
+
+/// Handles `/tick`. This changes the sender's world, or the default world when
+/// run from the console.
+fn handle_tick(wm: &Arc<WorldManager>, player: Option<&Arc<Player>>, args: Vec<Arg>) {
+  let world = player.map(|p| p.world()).unwrap_or_else(|| wm.default_world());
+  let rate = &world.tick_rate;
+  let name = &world.config().name;
+  let msg = match args[1].lit() {
+    "query" => {
+      let state = if rate.is_frozen() {
+        "frozen"
+      } else if rate.sprinting() > 0 {
+        "sprinting"
+      } else {
+        "running"
+      };
+      let mut msg =
+        Chat::new(format!("{name} is {state}, with a target of {:.1} TPS. Current: ", rate.rate()));
+      add_tps(&mut msg, world.timings.tps(20 * 5));
+      msg
+    }
+    "rate" => {
+      let new = rate.set_rate(args[2].float().into());
+      Chat::new(format!("Set the tick rate of {name} to {new:.1}"))
+    }
+    "freeze" | "unfreeze" => {
+      let frozen = args[1].lit() == "freeze";
+      rate.set_frozen(frozen);
+      Chat::new(format!("{} {name}", if frozen { "Froze" } else { "Unfroze" }))
+    }
+    "step" => {
+      let ticks = args.get(2).map(|a| a.int() as u32).unwrap_or(1);
+      if rate.step(ticks) {
+        Chat::new(format!("Stepping {name} by {ticks} tick(s)"))
+      } else {
+        error(format!("{name} isn't frozen, use /tick freeze first"))
+      }
+    }
+    "sprint" => {
+      let ticks = args[2].int() as u32;
+      rate.sprint(ticks);
+      if ticks == 0 {
+        Chat::new(format!("Stopped sprinting {name}"))
+      } else {
+        Chat::new(format!("Sprinting {name} for {ticks} tick(s)"))
+      }
+    }
+    _ => unreachable!(),
+  };
+  reply_lines(player, vec![msg]);
+}
+
+/// Finds who `name` refers to, for `/ban` and `/pardon`. This can be an IP
+/// address, a UUID, or the name of someone online. If it is someone online,
+/// their username is returned as well.
+fn ban_target(wm: &WorldManager, name: &str) -> Option<(BanTarget, Option<String>)> {
+  if let Ok(ip) = name.parse::<IpAddr>() {
+    return Some((BanTarget::Ip(ip), None));
+  }
+  if let Ok(id) = UUID::from_dashed_str(name).or_else(|_| name.parse()) {
+    let username = wm.get_player(id).map(|p| p.username().clone());
+    return Some((BanTarget::Uuid(id), username));
+  }
+  if let Some(p) = wm.get_player_username(&name.to_string()) {
+    return Some((BanTarget::Uuid(p.id()), Some(p.username().clone())));
+  }
+  match wm.profiles().lookup(name) {
+    Ok(profile) => Some((BanTarget::Uuid(profile.id), Some(profile.name))),
+    Err(ProfileError::NotFound) => None,
+    Err(e) => {
+      warn!("could not look up {name}: {e}");
+      None
+    }
+  }
+}
+
+/// Sends all the lines to the player, or logs them if the command was run
+/// from the console.
+fn reply_lines(player: Option<&Arc<Player>>, lines: Vec<Chat>) {
+  for line in lines {
+    match player {
+      Some(p) => p.send_message(line),
+      None => info!("{}", line.to_plain()),
+    }
+  }
+}
+
+fn add_tps(msg: &mut Chat, tps: f64) {
+  msg.add(format!("{tps:.1}")).color(if tps >= 19.5 {
+    Color::BrightGreen
+  } else if tps >= 15.0 {
+    Color::Yellow
+  } else {
+    Color::Red
+  });
+}
+
+/// Adds the average tick time to `msg`. Hovering over it will show the time
+/// of each phase.
+fn add_phases(msg: &mut Chat, timings: &TickTimings) {
+  let average = timings.average();
+  let details = timings
+    .phases()
+    .into_iter()
+    .map(|(name, time)| format!("{name}: {}", fmt_time(time)))
+    .collect::<Vec<_>>()
+    .join("\n");
+  msg.add(format!("{}/tick", fmt_time(average))).color(tick_color(average)).show_text(details);
+}
+
+fn tick_color(time: Duration) -> Color {
+  match time.as_millis() {
+    50.. => Color::Red,
+    20.. => Color::Gold,
+    10.. => Color::Yellow,
+    _ => Color::BrightGreen,
   }
 }
+
+fn fmt_time(time: Duration) -> String { format!("{:.2} ms", time.as_secs_f64() * 1000.0) }
+
+fn error(text: String) -> Chat {
+  let mut msg = Chat::empty();
+  msg.add(text).color(Color::Red);
+  msg
+}
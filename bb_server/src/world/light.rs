@@ -223,6 +223,7 @@ impl super::World {
 
     self.chunk(pos.chunk(), |mut c| {
       let c: &mut MultiChunk = &mut c;
+      let _held = super::lock_order::acquire(super::lock_order::Level::Light);
       let mut light = self.block_light.lock();
       let mut chunk_prop = light.chunk(&c.block, &mut c.block_light.data, false);
 
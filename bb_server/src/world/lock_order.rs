@@ -0,0 +1,136 @@
+//! The lock hierarchy for a [`World`](super::World).
+//!
+//! Every lock in a world has a [`Level`]. While holding a lock, you may only
+//! lock something at the same level or a higher level. Locking something at a
+//! lower level can deadlock, as another thread might be waiting on the lock
+//! you hold while holding the one you want. The levels are, from lowest to
+//! highest:
+//!
+//! - [`Level::Entities`]: The players map and the entities map. These are
+//!   `RwLock`s, and are mostly locked for reading, so they are on the same
+//!   level.
+//! - [`Level::Shard`]: One of the shards of the region map.
+//! - [`Level::Region`]: A single region. This is locked for reading whenever a
+//!   chunk in it is accessed, and is only locked for writing when a chunk is
+//!   added.
+//! - [`Level::Chunk`]: A single chunk. If you need to lock multiple chunks at
+//!   once, lock them in order of [`ChunkPos`](bb_common::math::ChunkPos).
+//! - [`Level::Light`]: The block light propogator.
+//! - [`Level::Components`]: One of the entity component storages (see
+//!   [`entity::Components`](crate::entity::Components)). Nothing is locked
+//!   while holding this.
+//! - [`Level::EntityIndex`]: The chunk each entity is in. Nothing is locked
+//!   while holding this.
+//! - [`Level::Viewers`]: The players that can see each chunk. Nothing is locked
+//!   while holding this.
+//!
+//! In practice, this means you should never call [`World::players`] or
+//! [`World::entities`] from within [`World::chunk`]. Find what you need within
+//! the chunk, and then send packets to players afterwards.
+//!
+//! With debug assertions enabled, all of these locks are tracked for each
+//! thread, and acquiring a lock out of order will panic. In release builds,
+//! this does nothing.
+//!
+//! [`World::players`]: super::World::players
+//! [`World::entities`]: super::World::entities
+//! [`World::chunk`]: super::World::chunk
+
+#[cfg(debug_assertions)]
+use std::cell::RefCell;
+
+/// A level in the lock hierarchy. See the [module docs](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+  Entities,
+  Shard,
+  Region,
+  Chunk,
+  Light,
+  Components,
+  EntityIndex,
+  Viewers,
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+  static HELD: RefCell<Vec<Level>> = RefCell::new(vec![]);
+}
+
+/// Marks a lock as held on this thread, until this is dropped. This should be
+/// created right before locking, and dropped after the lock guard.
+#[must_use]
+pub struct Held {
+  #[cfg(debug_assertions)]
+  level: Level,
+}
+
+/// Checks that it is valid to lock something at `level`, and marks it as held.
+#[track_caller]
+pub fn acquire(level: Level) -> Held {
+  check(level);
+  #[cfg(debug_assertions)]
+  HELD.with(|h| h.borrow_mut().push(level));
+  Held {
+    #[cfg(debug_assertions)]
+    level,
+  }
+}
+
+/// Checks that it is valid to lock something at `level`. This is used for
+/// locks where the guard is returned to the caller, such as
+/// [`World::players`](super::World::players), as we can't tell when those
+/// guards are dropped.
+#[track_caller]
+pub fn check(level: Level) {
+  #[cfg(debug_assertions)]
+  if let Some(highest) = HELD.with(|h| h.borrow().iter().max().copied()) {
+    assert!(
+      level >= highest,
+      "lock order violation: tried to lock {level:?} while holding {highest:?}"
+    );
+  }
+  #[cfg(not(debug_assertions))]
+  let _ = level;
+}
+
+impl Drop for Held {
+  fn drop(&mut self) {
+    #[cfg(debug_assertions)]
+    HELD.with(|h| {
+      let mut h = h.borrow_mut();
+      // Guards are usually dropped in reverse order, but they don't have to be.
+      if let Some(i) = h.iter().rposition(|l| *l == self.level) {
+        h.remove(i);
+      }
+    });
+  }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn in_order() {
+    let _shard = acquire(Level::Shard);
+    let _region = acquire(Level::Region);
+    let _a = acquire(Level::Chunk);
+    let _b = acquire(Level::Chunk);
+  }
+
+  #[test]
+  fn released() {
+    {
+      let _chunk = acquire(Level::Chunk);
+    }
+    check(Level::Entities);
+  }
+
+  #[test]
+  #[should_panic(expected = "tried to lock Entities while holding Chunk")]
+  fn out_of_order() {
+    let _chunk = acquire(Level::Chunk);
+    check(Level::Entities);
+  }
+}
@@ -0,0 +1,64 @@
+//! Showing server logs in chat. Players in the `log-forward` list are sent any
+//! warnings and errors as they happen, and can read recent logs with `/logs`.
+
+use super::WorldManager;
+use crate::player::Player;
+use bb_common::{
+  logger::{self, LogLine},
+  util::{chat::Color, Chat, UUID},
+};
+use crossbeam_channel::Receiver;
+use log::{Level, LevelFilter};
+
+/// Subscribes to the logger if anyone should be sent logs.
+pub(super) fn subscribe(forward: &[String], level: LevelFilter) -> Option<Receiver<LogLine>> {
+  if forward.is_empty() || level == LevelFilter::Off {
+    None
+  } else {
+    Some(logger::subscribe(level))
+  }
+}
+
+impl WorldManager {
+  /// Returns `true` if the given player is in the `log-forward` list. These
+  /// players are sent warnings and errors in chat, and can use `/logs`.
+  pub fn can_see_logs(&self, player: &Player) -> bool {
+    self.config.log_forward.iter().any(|entry| {
+      entry.eq_ignore_ascii_case(player.username())
+        || entry.parse::<UUID>().ok() == Some(player.id())
+    })
+  }
+
+  /// Sends any new log lines to the players who can see them. This is called
+  /// every tick.
+  pub(super) fn forward_logs(&self) {
+    let Some(rx) = &self.logs else { return };
+    let lines: Vec<_> = rx.try_iter().map(|l| log_chat(&l)).collect();
+    if lines.is_empty() {
+      return;
+    }
+    for (_, p) in self.players.read().values() {
+      if self.can_see_logs(p) {
+        for line in &lines {
+          p.send_message(line.clone());
+        }
+      }
+    }
+  }
+}
+
+/// Formats a log line for chat. The level is colored, and hovering over the
+/// message shows when it was logged and where it came from.
+pub(super) fn log_chat(line: &LogLine) -> Chat {
+  let color = match line.level {
+    Level::Error => Color::Red,
+    Level::Warn => Color::Gold,
+    Level::Info => Color::BrightGreen,
+    Level::Debug => Color::Blue,
+    Level::Trace => Color::Cyan,
+  };
+  let mut msg = Chat::empty();
+  msg.add(format!("[{}] ", line.level)).color(color);
+  msg.add(&line.msg).show_text(format!("{}\n{}", line.time, line.target));
+  msg
+}
@@ -11,34 +11,64 @@
 //! players joining, and players leaving. Lastly, it also contains a global tick
 //! loop, which is currently only used for plugins.
 
+mod bans;
 mod bbr;
 mod blocks;
 mod chunk;
 mod chunks;
+mod command_block;
+mod disguise;
+mod edit;
 mod entities;
+mod entity_index;
 pub mod gen;
 mod init;
 mod light;
+pub mod lock_order;
+mod logs;
 mod players;
+mod portal;
+mod profiles;
+mod protect;
+mod redstone;
 mod region;
+mod rng;
 pub mod schematic;
+mod selection;
+mod snapshot;
+mod spawn;
+mod spawner;
+mod throttle;
+mod tick_rate;
+mod tickets;
+mod timings;
+mod tracker;
+mod usage;
+mod whitelist;
+
+pub use throttle::Throttle;
+pub use tick_rate::TickRate;
+pub use timings::TickTimings;
 
 use crate::config::{Config, WorldConfig};
 use bb_common::{
+  ban::BanList,
   math::{ChunkPos, FPos, Pos, SectionRelPos},
   net::cb,
   util::{
     chat::{Chat, Color},
-    GameMode, JoinInfo, ThreadPool, UUID,
+    GameMode, JoinInfo, JoinMode, SwitchMode, ThreadPool, UUID,
   },
+  whitelist::Whitelist,
 };
 use parking_lot::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::{
-  collections::HashMap,
+  collections::{HashMap, HashSet},
   convert::TryInto,
   fmt,
+  path::PathBuf,
   sync::{
-    atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering},
+    atomic::{AtomicBool, AtomicI32, Ordering},
     Arc,
   },
   thread,
@@ -50,22 +80,38 @@ use crate::{
   command::CommandTree,
   data::Data,
   entity,
-  entity::Entity,
+  entity::{Entity, EntityRef},
   event, item,
-  net::ConnSender,
+  net::{broadcast::broadcast, ConnSender},
   particle::Particle,
   player::{Player, Team},
   plugin,
   tags::Tags,
 };
 
+pub use bbr::{migrate_world, MigrateSummary};
 pub use chunk::{BlockData, CountedChunk, MultiChunk};
-pub use entities::{EntitiesIter, EntitiesMap, EntitiesMapRef};
+pub use disguise::{Disguise, Disguises};
+pub use edit::{volume, CloneMask, CloneMode, EditError, FillMode, EDIT_BATCH_SIZE};
+pub use entities::{EntitiesIter, EntitiesMap, EntitiesMapRef, HandleError};
+pub use passengers::Passengers;
 pub use players::{PlayersIter, PlayersMap};
-
-use bbr::{RegionMap, RegionRelPos};
+pub use portal::PortalFrame;
+pub use profiles::{Profile, ProfileCache, ProfileError};
+pub use protect::{Action, Claim, ClaimError, Claims, RegionGuard};
+pub use region::{import_world, ImportSummary};
+pub use rng::RngStream;
+pub use selection::{BlockChange, ChangeSet, Cuboid, RegionEdit};
+pub use snapshot::ChunkSnapshot;
+pub use tickets::{ChunkLoadState, TicketKind, Tickets};
+
+use bbr::RegionMap;
 use chunks::ChunksToLoad;
+use entity_index::EntityIndex;
 use gen::WorldGen;
+use lock_order::Level;
+use rng::WorldRng;
+use tracker::ViewTracker;
 
 // pub struct ChunkRef<'a> {
 //   pos:    ChunkPos,
@@ -89,13 +135,20 @@ pub struct World {
   pub gen:              WorldGen,
   pub players:          RwLock<PlayersMap>,
   pub entities:         RwLock<EntitiesMap>,
-  eid:                  AtomicI32,
+  /// The positions and metadata of every entity in `entities`, other than
+  /// players.
+  components:           Arc<entity::Components>,
   pub block_converter:  Arc<block::TypeConverter>,
   pub item_converter:   Arc<item::TypeConverter>,
   pub entity_converter: Arc<entity::TypeConverter>,
   pub plugins:          Arc<plugin::PluginManager>,
   pub commands:         Arc<CommandTree>,
-  pub uspt:             Arc<AtomicU32>,
+  /// How long each phase of the tick loop takes.
+  pub timings:          TickTimings,
+  /// How fast this world ticks, and if it is frozen.
+  pub tick_rate:        TickRate,
+  /// How often optional work runs, when the world is under load.
+  pub throttle:         Throttle,
   pub wm:               Arc<WorldManager>,
   pub config:           WorldConfig,
   // If set, then the world cannot be modified.
@@ -109,6 +162,37 @@ pub struct World {
   min_y:  i32,
 
   block_light: Mutex<light::LightPropogator>,
+
+  /// All the entities that were spawned by natural spawning. Only these
+  /// entities will be despawned when they are far away from players.
+  natural_mobs: Mutex<HashSet<i32>>,
+
+  /// Which chunk every entity is in. Used for finding nearby entities.
+  entity_index: Mutex<EntityIndex>,
+  /// Which players can see each chunk. Used to send entity packets.
+  viewers:      Mutex<ViewTracker>,
+
+  /// All the disguised entities in this world. This is used to change the
+  /// packets sent to each player in [`Player::send`].
+  pub disguises: Disguises,
+  /// Which entities are riding which.
+  passengers:    Passengers,
+
+  /// The areas players have claimed with `/claim`.
+  claims: Mutex<protect::Claims>,
+
+  /// The seed and RNG streams for this world.
+  rng: WorldRng,
+
+  /// Named chunk snapshots, which plugins use to reset areas. See
+  /// [`World::snapshot`].
+  snapshots: Mutex<HashMap<String, Arc<ChunkSnapshot>>>,
+
+  /// The plugin and forced chunk tickets. See [`World::add_chunk_ticket`].
+  tickets: Mutex<Tickets>,
+
+  /// The size of this world on disk. See [`World::usage`].
+  disk_usage: usage::DiskUsage,
 }
 
 /// The world manager. This is essentially a Bamboo type. It stores all the
@@ -134,17 +218,49 @@ pub struct WorldManager {
   block_behaviors:  RwLock<block::BehaviorStore>,
   item_behaviors:   RwLock<item::BehaviorStore>,
   data:             Arc<Data>,
+  /// How long each phase of the global tick loop takes.
+  timings:          TickTimings,
+  bans:             Mutex<BanList>,
+  whitelist:        Mutex<Whitelist>,
+  /// Usernames and UUIDs of players who aren't online. See [`ProfileCache`].
+  profiles:         Arc<ProfileCache>,
+  /// Checked every time a block is changed. See [`RegionGuard`].
+  region_guards:    RwLock<Vec<Box<dyn RegionGuard>>>,
+  /// Named structures, which can be pasted into any world. See
+  /// [`WorldManager::structure`].
+  structures:       RwLock<HashMap<String, Arc<schematic::Schematic>>>,
+  /// The next entity id. See [`World::new_eid`].
+  eid:              AtomicI32,
+  /// New log lines, which are sent to the players in `log-forward`. This is
+  /// `None` if no one should be sent logs.
+  logs:             Option<crossbeam_channel::Receiver<bb_common::logger::LogLine>>,
 
   default_game_mode: GameMode,
   spawn_point:       FPos,
 }
 
 struct State {
-  uspt:  Arc<AtomicU32>,
   world: Arc<World>,
 }
 
+/// How long each tick of the global tick loop takes. Each world has its own
+/// [`TickRate`].
 const TICK_TIME: Duration = Duration::from_millis(50);
+/// How often the [`ServerStatus`](cb::packet::ServerStatus) is sent to the
+/// proxy, in ticks.
+const STATUS_INTERVAL: u64 = 20 * 5;
+/// The most players listed in the [`ServerStatus`](cb::packet::ServerStatus).
+/// Clients won't show more than this.
+const STATUS_SAMPLE: usize = 12;
+
+/// Runs `$e` as a phase of the tick loop. This records how long it took in the
+/// given [`TickTimings`], and wraps it in a `tracing` span for profiles.
+macro_rules! phase {
+  ($timings:expr, $name:literal, $e:expr) => {{
+    let _span = tracing::info_span!($name).entered();
+    $timings.time($name, || $e)
+  }};
+}
 
 impl World {
   /// Creates a new world. See also [`WorldManager::add_world`].
@@ -158,37 +274,38 @@ impl World {
     wm: Arc<WorldManager>,
   ) -> Self {
     let gen = WorldGen::from_config(&config);
-    /*
-    for schematic in config.get::<_, Vec<String>>("schematics") {
-      let path = schematic.get("path");
-      let pos = Pos::new(schematic.get("x"), schematic.get("y"), schematic.get("z"));
-      schematic::load_from_file(&mut chunks, path, &block_converter, || {
-        CountedChunk::new(MultiChunk::new(block_converter.clone(), true))
-      })
-      .unwrap_or_else(|err| error!("could not load schematic file {}: {}", path, err));
-    }
-    */
     World {
-      regions: RegionMap::new(config.save),
+      regions: RegionMap::new(config.save, PathBuf::from(&config.chunks)),
       // generator: config.get("generator"),
       gen,
       players: RwLock::new(PlayersMap::new()),
       entities: RwLock::new(EntitiesMap::new()),
-      // All player's think they are EID 1, so we start at 2. EID 0 is invalid.
-      eid: 2.into(),
+      components: Arc::new(entity::Components::new()),
       block_converter,
       item_converter,
       entity_converter,
       plugins,
       commands,
-      uspt: Arc::new(0.into()),
+      timings: TickTimings::new(),
+      tick_rate: TickRate::new(config.tick_rate),
+      throttle: Throttle::new(config.adaptive_throttle),
       locked: config.locked.into(),
       height: config.height,
       min_y: config.min_y,
+      claims: Mutex::new(protect::Claims::load(&config.name, config.save)),
+      rng: WorldRng::new(config.seed as u64),
+      snapshots: Mutex::new(HashMap::new()),
+      tickets: Mutex::new(Tickets::load(&config.name, config.save)),
+      disk_usage: usage::DiskUsage::new(&config.chunks),
       config,
       wm,
       chunks_to_load: Mutex::new(ChunksToLoad::new()),
       block_light: Mutex::new(light::LightPropogator::new()),
+      natural_mobs: Mutex::new(HashSet::new()),
+      entity_index: Mutex::new(EntityIndex::new()),
+      viewers: Mutex::new(ViewTracker::new()),
+      disguises: Disguises::new(),
+      passengers: Passengers::new(),
     }
   }
 
@@ -196,10 +313,7 @@ impl World {
   pub fn config(&self) -> &WorldConfig { &self.config }
 
   fn global_tick_loop(self: Arc<Self>) {
-    let pool = ThreadPool::auto("global tick loop", || State {
-      uspt:  self.uspt.clone(),
-      world: Arc::clone(&self),
-    });
+    let pool = ThreadPool::auto("global tick loop", || State { world: Arc::clone(&self) });
     // We set a limit to double the number of cores. This means that we will only
     // hit an artificial limit if we can generate a chunk in 10 ms. The more we
     // increase this, the worse the ordering for generating chunks gets.
@@ -207,20 +321,22 @@ impl World {
     // TODO: Make this configurable.
     let chunk_pool =
       ThreadPool::auto_with_limit("chunk generator", bb_common::util::num_cpus() * 5, || State {
-        uspt:  self.uspt.clone(),
         world: Arc::clone(&self),
       });
     let mut tick = 0;
-    let mut start = Instant::now();
+    let mut clock = tick_rate::TickClock::new(Instant::now(), self.config().max_catch_up);
     let mut needs_to_unload = false;
+    self.load_forced_chunks();
     loop {
+      let tick_start = Instant::now();
+      let span = tracing::info_span!("tick", tick).entered();
       if self.wm.config.debug_playerlist && tick % 20 == 0 {
         let mut header = Chat::empty();
         let mut footer = Chat::empty();
 
         header.add("big gaming\n").color(Color::Blue);
         footer.add("\nuspt: ");
-        let uspt = self.uspt.swap(0, Ordering::SeqCst) / 20;
+        let uspt = self.timings.average().as_micros();
         footer.add(uspt.to_string()).color(if uspt > 50_000 {
           Color::Red
         } else if uspt > 20_000 {
@@ -237,6 +353,10 @@ impl World {
         }
       }
 
+      if tick % 20 == 0 {
+        self.kick_slow_players();
+      }
+
       // Every 30 seconds, try to unload chunks we don't need. We do this on another
       // thread, as unloading chunks is expensive. If the chunk pool is full, we just
       // try again next tick. We do all of this before `check_chunks_queue`, as this
@@ -247,13 +367,26 @@ impl World {
       }
       if needs_to_unload {
         let res = chunk_pool.try_execute(|s| {
+          let _span = tracing::info_span!("unload chunks").entered();
           s.world.unload_chunks();
         });
         if res.is_ok() {
           needs_to_unload = false
         }
       }
-      self.check_chunks_queue(&chunk_pool);
+      // While frozen, players are still ticked, but nothing else is.
+      let full_tick = self.tick_rate.should_tick();
+      if full_tick {
+        phase!(self.timings, "tickets", self.tick_tickets());
+      }
+      phase!(self.timings, "chunk queue", self.check_chunks_queue(&chunk_pool));
+      if full_tick {
+        if self.throttle.runs_on(tick) {
+          phase!(self.timings, "spawning", self.tick_spawning(tick));
+        }
+        phase!(self.timings, "spawners", self.tick_spawners());
+        phase!(self.timings, "command blocks", self.tick_command_blocks());
+      }
       /*
       for p in self.players().iter() {
         let p = p.clone();
@@ -271,30 +404,85 @@ impl World {
         });
       }
       */
-      for (&eid, ent) in self.entities().iter_values() {
-        let ent = ent.clone();
-        let w = self.clone();
-        pool.execute(move |s| {
-          if let Some(ent) = ent.as_entity_ref(w.as_ref()) {
-            let start = Instant::now();
-            if ent.tick() {
-              s.world.entities.write().remove(&eid);
-              for p in s.world.players().iter().in_view(ent.pos().block().chunk()) {
-                p.send(cb::packet::RemoveEntities { eids: vec![eid] });
+      phase!(self.timings, "entities", {
+        // Every position is read at once before ticking, and written back at once
+        // afterwards, so the position storage is only locked twice per tick.
+        let mut positions: HashMap<_, _> =
+          self.components.pos().iter().map(|(eid, p)| (eid, *p)).collect();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        for (&eid, ent) in self.entities().iter_values() {
+          let ent = ent.clone();
+          let w = self.clone();
+          let pos = positions.remove(&eid);
+          let tx = tx.clone();
+          pool.execute(move |s| {
+            if let Some(ent) = ent.as_entity_ref(w.as_ref()) {
+              let _span = tracing::info_span!("entity tick", eid).entered();
+              let despawn = match (&ent, pos) {
+                (EntityRef::Entity(_), _) if !full_tick => false,
+                // Mobs are spread out across ticks, so that they don't all skip
+                // the same ticks while throttled.
+                (EntityRef::Entity(e), _)
+                  if e.ty().is_living() && !w.throttle.runs_on(tick.wrapping_add(eid as u32)) =>
+                {
+                  false
+                }
+                (EntityRef::Entity(e), Some(pos)) => match e.tick(pos) {
+                  Some(pos) => {
+                    tx.send((eid, pos)).unwrap();
+                    false
+                  }
+                  None => true,
+                },
+                // This entity was spawned after we read the positions, so it will be
+                // ticked next tick.
+                (EntityRef::Entity(_), None) => false,
+                (EntityRef::Player(p), _) => {
+                  p.tick();
+                  false
+                }
+              };
+              if despawn {
+                s.world.entities.write().remove(&eid);
+                s.world.clear_passengers(eid);
+                s.world.unindex_entity(eid);
+                s.world.clear_disguise(eid);
+                s.world.remove_for_viewers(eid, ent.pos().block().chunk());
               }
             }
-            s.uspt.fetch_add(start.elapsed().as_micros().try_into().unwrap(), Ordering::SeqCst);
+          });
+        }
+        drop(tx);
+        // We don't want overlapping tick loops
+        pool.wait();
+        let mut positions = self.components.pos_mut();
+        for (eid, pos) in rx.try_iter() {
+          // Entities that were removed during the tick won't be present.
+          if let Some(p) = positions.get_mut(eid) {
+            *p = pos;
           }
-        });
-      }
-      // We don't want overlapping tick loops
-      pool.wait();
+        }
+      });
+      drop(span);
+      let passed = tick_start.elapsed();
+      self.timings.finish_tick(passed);
       tick += 1;
-      let passed = Instant::now().duration_since(start);
-      start += TICK_TIME;
-      match TICK_TIME.checked_sub(passed) {
-        Some(t) => spin_sleep::sleep(t),
-        None => warn!("tick took {passed:?} (more than 50 ms)"),
+      let now = Instant::now();
+      let interval = self.tick_rate.interval();
+      self.throttle.update(self.timings.average(), interval);
+      if self.tick_rate.should_sprint() {
+        clock.reset(now);
+        continue;
+      }
+      if passed > interval {
+        warn!("tick took {passed:?} (more than {interval:?})");
+      }
+      match clock.next(now, interval) {
+        tick_rate::Wait::Sleep(t) => spin_sleep::sleep(t),
+        tick_rate::Wait::CatchUp => {}
+        tick_rate::Wait::Skipped(ticks) => {
+          warn!("world {} is {ticks} ticks behind, skipping them", self.config().name)
+        }
       }
     }
   }
@@ -320,10 +508,7 @@ impl World {
         return;
       }
       drop(players);
-      let mut players = self.players.write();
-      players.insert(player.id(), player.clone());
-      let mut entities = self.entities.write();
-      entities.insert(player.eid(), Entity::Player(player.id()));
+      self.attach_player(&player);
     }
     info!("{} has joined the game", player.username());
 
@@ -335,15 +520,24 @@ impl World {
       self.world_manager().broadcast(msg);
     }
 
-    self.player_init(&player, info);
+    self.player_init(&player, info.mode);
     // We want our plugin stuff to trigger after the player has received all the
     // chunks and whatever other initialization stuff. This means we can't screw
     // anything up with the loading process (like trying to teleport the player).
     self.events().player_event(event::PlayerJoin { player });
   }
 
-  /// Returns a new, unique EID.
-  pub fn new_eid(&self) -> i32 { self.eid.fetch_add(1, Ordering::SeqCst) }
+  /// Adds the player to this world's players and entities. This doesn't send
+  /// anything to the player. See [`player_init`](Self::player_init).
+  fn attach_player(&self, player: &Arc<Player>) {
+    self.players.write().insert(player.id(), player.clone());
+    self.entities.write().insert(player.eid(), Entity::Player(player.id()));
+    self.index_entity(player.eid(), player.pos().chunk());
+  }
+
+  /// Returns a new, unique EID. EIDs are unique across all worlds, so that
+  /// players can move between worlds without changing their EID.
+  pub fn new_eid(&self) -> i32 { self.wm.new_eid() }
 
   /// Returns the current block converter. This can be used to convert old block
   /// ids to new ones, and vice versa. This can also be used to convert block
@@ -355,6 +549,9 @@ impl World {
   /// Returns the current entity converter. This can be used to convert old
   /// entity ids to new ones, and vice versa.
   pub fn entity_converter(&self) -> &Arc<entity::TypeConverter> { &self.entity_converter }
+  /// Returns the components of every entity in this world. See
+  /// [`entity::Components`].
+  pub fn components(&self) -> &Arc<entity::Components> { &self.components }
   /// Returns the plugin manager. This is how events can be sent to plugins.
   /// This is the same plugin manager returned by the [`WorldManager`], and by
   /// other worlds.
@@ -411,7 +608,7 @@ impl World {
       self.regions.region(
         pos,
         || self.new_chunk(),
-        |mut region| {
+        |region| {
           region.get_or_generate(pos, || CountedChunk::new(chunk));
         },
       );
@@ -426,17 +623,19 @@ impl World {
   /// would have needed to call two functions to get it working. I tried my best
   /// with the [`Deref`](std::ops::Deref) trait, but I couldn't get it to work
   /// the way I liked.
+  ///
+  /// Do not lock the players or entities from within `f`. See [`lock_order`]
+  /// for more.
   pub fn chunk<F, R>(&self, pos: ChunkPos, f: F) -> R
   where
     F: FnOnce(MutexGuard<MultiChunk>) -> R,
   {
-    self.regions.region(
+    self.regions.chunk(
       pos,
       || self.new_chunk(),
-      |mut region| {
-        let chunk = region.get_or_generate(RegionRelPos::new(pos), || {
-          CountedChunk::new(self.pre_generate_chunk(pos))
-        });
+      || CountedChunk::new(self.pre_generate_chunk(pos)),
+      |chunk| {
+        let _held = lock_order::acquire(Level::Chunk);
         f(chunk.lock())
       },
     )
@@ -463,6 +662,7 @@ impl World {
         sections,
         sky_light: c.sky_light().as_ref().map(|c| c.data.clone()),
         block_light: c.block_light().data.clone(),
+        block_entities: c.block_entities(pos, |_| true),
       }
     })
   }
@@ -495,6 +695,7 @@ impl World {
         // TODO: Only clone the sections we care about
         sky_light: c.sky_light().as_ref().map(|c| c.data.clone()),
         block_light: c.block_light().data.clone(),
+        block_entities: c.block_entities(pos, |p| (min..=max).contains(&(p.chunk_y() as u32))),
       }
     })
   }
@@ -526,13 +727,11 @@ impl World {
   /// used to track when a chunk should be loaded/unloaded. This will load the
   /// chunk if it is not already present.
   pub fn inc_view(&self, pos: ChunkPos) {
-    self.regions.region(
+    self.regions.chunk(
       pos,
       || self.new_chunk(),
-      |mut region| {
-        let chunk = region.get_or_generate(RegionRelPos::new(pos), || {
-          CountedChunk::new(self.pre_generate_chunk(pos))
-        });
+      || CountedChunk::new(self.pre_generate_chunk(pos)),
+      |chunk| {
         chunk.count.fetch_add(1, Ordering::SeqCst);
       },
     )
@@ -542,13 +741,11 @@ impl World {
   /// used to track when a chunk should be loaded/unloaded. If this chunk does
   /// not exist, this will do nothing.
   pub fn dec_view(&self, pos: ChunkPos) {
-    self.regions.region(
+    self.regions.chunk(
       pos,
       || self.new_chunk(),
-      |mut region| {
-        let chunk = region.get_or_generate(RegionRelPos::new(pos), || {
-          CountedChunk::new(self.pre_generate_chunk(pos))
-        });
+      || CountedChunk::new(self.pre_generate_chunk(pos)),
+      |chunk| {
         chunk.count.fetch_sub(1, Ordering::SeqCst);
       },
     )
@@ -572,19 +769,17 @@ impl World {
   }
 
   /// Returns a read lock on the players map.
-  pub fn players(&self) -> RwLockReadGuard<'_, PlayersMap> { self.players.read() }
+  pub fn players(&self) -> RwLockReadGuard<'_, PlayersMap> {
+    lock_order::check(Level::Entities);
+    self.players.read()
+  }
 
   /// Removes the given player from this world. This should be called from
   /// WorldManagger, so that the world managger's table of players to worlds
   /// stays synced.
   fn remove_player(&self, id: UUID) {
-    let mut lock = self.players.write();
     // If the player is not present, this player has already been removed.
-    if let Some(p) = lock.remove(&id) {
-      let players_is_empty = lock.is_empty();
-      drop(lock);
-
-      self.entities.write().remove(&p.eid());
+    if let Some(p) = self.detach_player(id) {
       self.events().player_event(event::PlayerLeave { player: p.clone() });
       info!("{} left the game", p.username());
 
@@ -595,32 +790,72 @@ impl World {
         msg.add(" has left").color(Color::Gray);
         self.world_manager().broadcast(msg);
       }
+    }
+  }
 
-      let entity_remove = cb::packet::RemoveEntities { eids: vec![p.eid()] };
-      let list_remove = cb::packet::PlayerList {
-        action: cb::PlayerListAction::Remove(vec![cb::PlayerListRemove { id: p.id() }]),
-      };
-      for other in p.world().players().iter().in_view(p.pos().block().chunk()).not(p.id()) {
-        other.send(entity_remove.clone());
-        other.send(list_remove.clone());
-      }
-      p.unload_all();
-
-      if players_is_empty {
-        self.unload_chunks();
-        /*
-        let len = self.chunks.read().len();
-        if len != 0 {
-          warn!("chunks remaining after last player logged off: {}", len);
-        }
-        */
+  /// Removes the player from this world's players and entities, and despawns
+  /// them for everyone else in this world. This is used both when a player
+  /// leaves, and when they move to another world. Returns `None` if the
+  /// player isn't in this world.
+  fn detach_player(&self, id: UUID) -> Option<Arc<Player>> {
+    let mut lock = self.players.write();
+    let p = lock.remove(&id)?;
+    let players_is_empty = lock.is_empty();
+    drop(lock);
+
+    self.entities.write().remove(&p.eid());
+    self.clear_passengers(p.eid());
+    self.unindex_entity(p.eid());
+    self.clear_disguise(p.eid());
+    self.untrack_player(&p);
+    let list_remove = cb::packet::PlayerList {
+      action: cb::PlayerListAction::Remove(vec![cb::PlayerListRemove { id: p.id() }]),
+    };
+    for other in self.players().iter() {
+      other.send(list_remove.clone());
+      other.forget_hidden(&p);
+    }
+    p.unload_all();
+
+    if players_is_empty {
+      self.unload_chunks();
+      /*
+      let len = self.chunks.read().len();
+      if len != 0 {
+        warn!("chunks remaining after last player logged off: {}", len);
       }
+      */
     }
+    Some(p)
   }
 
   // Unloads all the chunks that are cached for unloading.
   pub fn unload_chunks(&self) { self.regions.unload_chunks(); }
 
+  /// Kicks every player that has been behind for longer than the configured
+  /// time. See [`net::backpressure`](crate::net::backpressure).
+  fn kick_slow_players(&self) {
+    let kick_after = self.wm.config().backpressure.kick_after;
+    if kick_after == 0 {
+      return;
+    }
+    let limit = Duration::from_secs(kick_after.into());
+    let slow: Vec<_> = self
+      .players()
+      .values()
+      .filter(|p| p.behind_for().is_some_and(|t| t > limit))
+      .cloned()
+      .collect();
+    for p in slow {
+      warn!(
+        "kicking {}, as they have been behind for more than {kick_after} seconds ({} packets dropped)",
+        p.username(),
+        p.dropped_packets(),
+      );
+      p.close_connection();
+    }
+  }
+
   /// Returns true if the world is locked. This is an atomic load, so it will
   /// always be a race condition. However, whenever you modify the world, this
   /// is also checked, so it won't end up being a problem.
@@ -637,9 +872,7 @@ impl World {
     pitch: f32,
   ) {
     let out = cb::packet::PlaySound { name: sound, category, pos, volume, pitch };
-    for p in self.players().iter().in_view(pos.block().chunk()) {
-      p.send(out.clone());
-    }
+    broadcast(self.players().iter().in_view(pos.block().chunk()), out);
   }
 
   pub fn spawn_particle(&self, particle: Particle) {
@@ -667,6 +900,12 @@ impl World {
   pub fn save(&self) { self.regions.save(); }
 }
 
+impl fmt::Debug for World {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("World").field("name", &self.config.name).finish()
+  }
+}
+
 impl fmt::Debug for WorldManager {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     f.debug_struct("WorldManager").field("players", &self.players.read().len()).finish()
@@ -697,6 +936,15 @@ impl WorldManager {
       worlds:            RwLock::new(vec![]),
       players:           RwLock::new(HashMap::new()),
       teams:             RwLock::new(HashMap::new()),
+      timings:           TickTimings::new(),
+      bans:              Mutex::new(BanList::load(&conf.bans)),
+      whitelist:         Mutex::new(Whitelist::load(&conf.whitelist)),
+      profiles:          Arc::new(ProfileCache::new(&conf.profiles)),
+      region_guards:     RwLock::new(vec![]),
+      structures:        RwLock::new(HashMap::new()),
+      // All player's think they are EID 1, so we start at 2. EID 0 is invalid.
+      eid:               2.into(),
+      logs:              logs::subscribe(&conf.log_forward, conf.log_forward_level),
       default_game_mode: conf.default_gamemode,
       spawn_point:       conf.spawn_point,
       config:            conf,
@@ -721,6 +969,10 @@ impl WorldManager {
   /// Returns the config used in the whole server.
   pub fn config(&self) -> &Config { &self.config }
 
+  /// Returns the timings for the global tick loop. This loop only runs plugin
+  /// ticks, so see [`World::timings`] for the timings of each world.
+  pub fn timings(&self) -> &TickTimings { &self.timings }
+
   /// Runs a global tick loop. This is used for plugin events. This is a
   /// blocking call.
   pub fn run(self: Arc<Self>) { self.global_tick_loop(); }
@@ -775,6 +1027,22 @@ impl WorldManager {
     let rl = self.teams.read();
     rl.get(name).cloned()
   }
+  /// Removes the team with the given name, and removes it from all clients.
+  /// Returns `false` if the team doesn't exist.
+  pub fn remove_team(&self, name: &str) -> bool {
+    match self.teams.write().remove(name) {
+      Some(team) => {
+        team.lock().send_remove();
+        true
+      }
+      None => false,
+    }
+  }
+  /// Returns the team the given player is on, if any. This locks every team,
+  /// so this must not be called while holding a lock on a team.
+  pub fn team_of(&self, id: UUID) -> Option<Arc<Mutex<Team>>> {
+    self.teams.read().values().find(|team| team.lock().contains(id)).cloned()
+  }
 
   /// Returns the current block converter. This can be used to convert old block
   /// ids to new ones, and vice versa. This can also be used to convert block
@@ -839,6 +1107,9 @@ impl WorldManager {
   /// any other context.
   pub fn default_world(&self) -> Arc<World> { self.worlds.read()[0].clone() }
 
+  /// Returns a new, unique EID. See [`World::new_eid`].
+  pub fn new_eid(&self) -> i32 { self.eid.fetch_add(1, Ordering::SeqCst) }
+
   // /// Adds a new player into the game. This should be called when a new grpc
   // /// proxy connects.
   // pub async fn new_player(&self, req: Streaming<Packet>, tx:
@@ -858,6 +1129,12 @@ impl WorldManager {
   // }
   /// Adds a new player into the game. This should be called when a new grpc
   /// proxy connects.
+  ///
+  /// Before the player is created, this checks the ban list and whitelist, and
+  /// then sends a [`PlayerPreJoin`](event::PlayerPreJoin) request to plugins,
+  /// which may deny the join or change where the player spawns. If the player
+  /// isn't allowed to join, they are kicked, and the returned player is never
+  /// added to a world.
   pub fn new_player(&self, conn: ConnSender, info: JoinInfo) -> Arc<Player> {
     let w = self.worlds.read()[0].clone();
     let spawn = if self.config().find_spawn {
@@ -865,12 +1142,106 @@ impl WorldManager {
     } else {
       self.spawn_point
     };
+    // The proxy should have already checked this, but it might be using a
+    // different ban list, or the ban might have been added while they were
+    // logging in.
+    let deny = if let Some(ban) = self.find_ban(info.uuid, info.addr.map(|a| a.ip())) {
+      info!("{} tried to join, but is banned", info.username);
+      Some(ban.kick_message())
+    } else if let Some(msg) = self.check_whitelist(info.uuid, &info.username) {
+      info!("{} tried to join, but is not whitelisted", info.username);
+      Some(msg)
+    } else if let Some(msg) = self.check_required_mods(&info.mods) {
+      info!("{} tried to join, but is missing required mods", info.username);
+      Some(msg)
+    } else {
+      None
+    };
+    let full = deny.is_none() && self.is_full(info.uuid, &info.username);
+    if full {
+      info!("{} tried to join, but the server is full", info.username);
+    }
+    let join = Arc::new(Mutex::new(event::PreJoin { deny, world: w, pos: spawn }));
+    if join.lock().deny.is_none() && !full {
+      let flow = self.events().global_request(event::PlayerPreJoin {
+        username: info.username.clone(),
+        id:       info.uuid,
+        join:     join.clone(),
+      });
+      let mut join = join.lock();
+      if flow.is_handled() && join.deny.is_none() {
+        let mut msg = Chat::empty();
+        msg.add("You are not allowed to join this server.").color(Color::Red);
+        join.deny = Some(msg);
+      }
+      if join.deny.is_some() {
+        info!("{} tried to join, but was denied by a plugin", info.username);
+      }
+    }
+    let event::PreJoin { deny, world: mut w, pos: mut spawn } = join.lock().clone();
+    let idx = self.worlds.read().iter().position(|other| Arc::ptr_eq(other, &w));
+    let idx = match idx {
+      Some(idx) => idx,
+      None => {
+        warn!("{} was sent to a world that isn't loaded, using the default world", info.username);
+        w = self.default_world();
+        spawn = self.spawn_point;
+        0
+      }
+    };
     let player = Player::new(w.new_eid(), conn, info.clone(), w.clone(), spawn);
-    self.players.write().insert(info.uuid, (0, player.clone()));
+    if full {
+      let mut msg = Chat::empty();
+      msg.add("The server is full.").color(Color::Red);
+      // The proxy will either disconnect them, or put them in the queue.
+      player.send(cb::packet::ServerFull { reason: msg });
+      return player;
+    }
+    if let Some(msg) = deny {
+      player.disconnect(msg);
+      return player;
+    }
+    self.players.write().insert(info.uuid, (idx, player.clone()));
+    self.profiles.insert(&info.username, info.uuid, player.skin().as_ref());
+    if player.skin().is_none() && self.config.profiles.offline_skins {
+      self.fetch_offline_skin(&player);
+    }
     w.new_player(player.clone(), info);
     player
   }
 
+  /// Returns the message to kick a player with, if they are missing any of
+  /// the mods in `required-mods`.
+  fn check_required_mods(&self, mods: &[String]) -> Option<Chat> {
+    let missing: Vec<_> =
+      self.config.required_mods.iter().filter(|m| !mods.contains(m)).map(|m| m.as_str()).collect();
+    if missing.is_empty() {
+      return None;
+    }
+    let mut msg = Chat::empty();
+    msg.add(format!("This server requires these mods: {}", missing.join(", "))).color(Color::Red);
+    Some(msg)
+  }
+
+  /// Returns `true` if a player with the given id and username can't join,
+  /// because the server is full. Players in the `full-bypass` list can always
+  /// join.
+  fn is_full(&self, id: UUID, username: &str) -> bool {
+    let max = self.config.max_players;
+    if max == 0 {
+      return false;
+    }
+    let players = self.players.read();
+    if players.len() < max as usize || players.contains_key(&id) {
+      return false;
+    }
+    !self
+      .config
+      .full_bypass
+      .iter()
+      .any(|entry| entry.eq_ignore_ascii_case(username) || entry.parse::<UUID>().ok() == Some(id))
+  }
+
   /// Removes the player. This is not part of the public API because it does not
   /// terminate their connection. This is called after their connection is
   /// terminated.
@@ -893,6 +1264,50 @@ impl WorldManager {
     self.players.write().remove(&id);
   }
 
+  /// Moves the player into `world`, at the given position. See
+  /// [`Player::teleport_world`].
+  pub fn move_player(&self, player: &Arc<Player>, world: &Arc<World>, pos: FPos) -> bool {
+    let idx = match self.worlds.read().iter().position(|w| Arc::ptr_eq(w, world)) {
+      Some(idx) => idx,
+      None => return false,
+    };
+    let old_idx = match self.players.read().get(&player.id()) {
+      Some(v) => v.0,
+      None => return false,
+    };
+    if old_idx == idx {
+      let (pitch, yaw) = player.look();
+      player.teleport(pos, yaw, pitch);
+      return true;
+    }
+    let old = self.worlds.read()[old_idx].clone();
+    if old.detach_player(player.id()).is_none() {
+      return false;
+    }
+    // The respawn doesn't clear the tab list, so we remove everyone from the
+    // old world. `player_init` will add everyone in the new world.
+    let list_remove = cb::packet::PlayerList {
+      action: cb::PlayerListAction::Remove(
+        old.players().values().map(|p| cb::PlayerListRemove { id: p.id() }).collect(),
+      ),
+    };
+    player.send(list_remove);
+    player.set_world(world.clone(), pos);
+    match self.players.write().get_mut(&player.id()) {
+      Some(v) => v.0 = idx,
+      // The player disconnected while we were moving them.
+      None => return false,
+    }
+    world.attach_player(player);
+    world.player_init(player, JoinMode::Switch(SwitchMode::Loading));
+    self.events().player_event(event::PlayerChangeWorld {
+      player:    player.clone(),
+      old_world: old,
+      new_world: world.clone(),
+    });
+    true
+  }
+
   /// Returns a read lock on the players map.
   pub fn all_players(&self) -> RwLockReadGuard<'_, HashMap<UUID, (usize, Arc<Player>)>> {
     self.players.read()
@@ -900,11 +1315,24 @@ impl WorldManager {
 
   fn global_tick_loop(self: Arc<Self>) {
     let mut start = Instant::now();
+    let mut tick = 0_u64;
     loop {
+      let tick_start = Instant::now();
       // runs on tick() for plugins
-      self.events().global_event(event::Tick {});
+      phase!(self.timings, "tick event", self.events().global_event(event::Tick {}));
       // updates after() things
-      self.plugins().tick();
+      phase!(self.timings, "plugin tick", self.plugins().tick());
+      // Checking for changed plugins touches the filesystem, so only do it once a
+      // second.
+      if tick % 20 == 0 {
+        phase!(self.timings, "reload", self.plugins().reload_changed(&self));
+      }
+      if tick % STATUS_INTERVAL == 0 {
+        self.send_status();
+      }
+      self.forward_logs();
+      self.timings.finish_tick(tick_start.elapsed());
+      tick += 1;
       let passed = Instant::now().duration_since(start);
       start += TICK_TIME;
       match TICK_TIME.checked_sub(passed) {
@@ -923,6 +1351,30 @@ impl WorldManager {
     }
   }
 
+  /// Sends the player count and resource usage to the proxy, so that they can
+  /// be shown on the server list.
+  fn send_status(&self) {
+    if self.players.read().is_empty() {
+      return;
+    }
+    // This locks every world, so it needs to happen before locking `players`.
+    let usage = self.usage();
+    let players = self.players.read();
+    let out = cb::packet::ServerStatus {
+      online: players.len() as u32,
+      max:    self.config.max_players,
+      sample: players
+        .values()
+        .take(STATUS_SAMPLE)
+        .map(|(_, p)| cb::StatusPlayer { name: p.username().clone(), id: p.id() })
+        .collect(),
+      usage:  Some(usage),
+    };
+    for (_, p) in players.values() {
+      p.send(out.clone());
+    }
+  }
+
   pub fn get_player(&self, id: UUID) -> Option<Arc<Player>> {
     self.players.read().get(&id).map(|v| v.1.clone())
   }
@@ -0,0 +1,219 @@
+//! Passengers. Any entity or player can ride any other entity or player, and
+//! entities riding something can be ridden themselves, so that a player can
+//! ride a chicken which is riding a boat.
+
+use super::{
+  lock_order::{self, Level},
+  World,
+};
+use crate::player::Player;
+use bb_common::net::cb;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+
+/// Which entities are riding which in a world.
+#[derive(Debug, Default)]
+pub struct Passengers {
+  inner: RwLock<Riders>,
+}
+
+#[derive(Debug, Default)]
+struct Riders {
+  /// A map of vehicles to the entities riding them, in the order they got on.
+  passengers: HashMap<i32, Vec<i32>>,
+  /// A map of passengers to the entity they are riding.
+  vehicles:   HashMap<i32, i32>,
+}
+
+impl Riders {
+  /// Returns `true` if `eid` is `vehicle`, or is somewhere below it in a stack
+  /// of passengers.
+  fn is_below(&self, eid: i32, mut vehicle: i32) -> bool {
+    loop {
+      if vehicle == eid {
+        return true;
+      }
+      match self.vehicles.get(&vehicle) {
+        Some(&below) => vehicle = below,
+        None => return false,
+      }
+    }
+  }
+
+  fn mount(&mut self, passenger: i32, vehicle: i32) -> Option<i32> {
+    let old = self.dismount(passenger);
+    self.passengers.entry(vehicle).or_default().push(passenger);
+    self.vehicles.insert(passenger, vehicle);
+    old
+  }
+
+  fn dismount(&mut self, passenger: i32) -> Option<i32> {
+    let vehicle = self.vehicles.remove(&passenger)?;
+    if let Some(list) = self.passengers.get_mut(&vehicle) {
+      list.retain(|&eid| eid != passenger);
+      if list.is_empty() {
+        self.passengers.remove(&vehicle);
+      }
+    }
+    Some(vehicle)
+  }
+}
+
+impl Passengers {
+  pub fn new() -> Self { Passengers::default() }
+
+  /// Returns the entities riding `vehicle`.
+  pub fn passengers(&self, vehicle: i32) -> Vec<i32> {
+    self.inner.read().passengers.get(&vehicle).cloned().unwrap_or_default()
+  }
+  /// Returns the entity `passenger` is riding, if any.
+  pub fn vehicle(&self, passenger: i32) -> Option<i32> {
+    self.inner.read().vehicles.get(&passenger).copied()
+  }
+
+  /// Makes `passenger` ride `vehicle`. If `passenger` was riding something
+  /// else, it is dismounted first, and the old vehicle is returned in `Ok`.
+  /// This returns `Err` if `vehicle` is `passenger`, or is riding `passenger`
+  /// (directly or through other entities).
+  fn mount(&self, passenger: i32, vehicle: i32) -> Result<Option<i32>, ()> {
+    let mut riders = self.inner.write();
+    if riders.is_below(passenger, vehicle) {
+      return Err(());
+    }
+    Ok(riders.mount(passenger, vehicle))
+  }
+  /// Stops `passenger` from riding anything. Returns the vehicle it was
+  /// riding.
+  fn dismount(&self, passenger: i32) -> Option<i32> { self.inner.write().dismount(passenger) }
+
+  /// Removes everything about the given entity. Returns the entity it was
+  /// riding, and the entities that were riding it.
+  fn remove(&self, eid: i32) -> (Option<i32>, Vec<i32>) {
+    let mut riders = self.inner.write();
+    let vehicle = riders.dismount(eid);
+    let passengers = riders.passengers.remove(&eid).unwrap_or_default();
+    for p in &passengers {
+      riders.vehicles.remove(p);
+    }
+    (vehicle, passengers)
+  }
+}
+
+impl World {
+  /// Makes `passenger` ride `vehicle`, and sends the change to everyone in
+  /// view. If `vehicle` already has passengers, `passenger` is added after
+  /// them. Both can be players or entities. If `passenger` is already riding
+  /// something, it will be dismounted first.
+  ///
+  /// Returns `false` if either entity doesn't exist, or if this would make an
+  /// entity ride itself (for example, if `vehicle` is riding `passenger`).
+  pub fn mount(&self, passenger: i32, vehicle: i32) -> bool {
+    {
+      let entities = self.entities.read();
+      if !entities.contains_key(&passenger) || !entities.contains_key(&vehicle) {
+        return false;
+      }
+    }
+    let old = match self.passengers.mount(passenger, vehicle) {
+      Ok(old) => old,
+      Err(()) => return false,
+    };
+    if let Some(old) = old {
+      self.send_passengers(old, vec![passenger]);
+    }
+    self.send_passengers(vehicle, vec![]);
+    true
+  }
+  /// Stops `passenger` from riding anything, and sends the change to everyone
+  /// in view. Returns `false` if it wasn't riding anything.
+  pub fn dismount(&self, passenger: i32) -> bool {
+    match self.passengers.dismount(passenger) {
+      Some(vehicle) => {
+        self.send_passengers(vehicle, vec![passenger]);
+        true
+      }
+      None => false,
+    }
+  }
+  /// Returns the entities riding `vehicle`. The first entity is the one that
+  /// controls it.
+  pub fn passengers(&self, vehicle: i32) -> Vec<i32> { self.passengers.passengers(vehicle) }
+  /// Returns the entity `passenger` is riding, if any.
+  pub fn vehicle(&self, passenger: i32) -> Option<i32> { self.passengers.vehicle(passenger) }
+
+  /// Dismounts an entity that is being removed from this world, and dismounts
+  /// everything riding it. This must be called before the entity is removed
+  /// from the entity index.
+  pub(crate) fn clear_passengers(&self, eid: i32) {
+    let (vehicle, passengers) = self.passengers.remove(eid);
+    if let Some(vehicle) = vehicle {
+      self.send_passengers(vehicle, vec![eid]);
+    }
+    if !passengers.is_empty() {
+      // 1.8 clients need to detach each passenger.
+      self.send_near(eid, cb::packet::SetPassengers { eid, passengers: vec![], removed: passengers });
+    }
+  }
+
+  /// Sends the passengers of `vehicle` to `player`, along with the passengers
+  /// of whatever `vehicle` is riding. This is called when `vehicle` is spawned
+  /// for `player`, as clients ignore passengers that they haven't spawned yet.
+  pub(crate) fn send_passengers_to(&self, player: &Player, vehicle: i32) {
+    let (passengers, below) = {
+      let riders = self.passengers.inner.read();
+      (riders.passengers.get(&vehicle).cloned(), riders.vehicles.get(&vehicle).copied())
+    };
+    if let Some(passengers) = passengers {
+      player.send(cb::packet::SetPassengers { eid: vehicle, passengers, removed: vec![] });
+    }
+    if let Some(below) = below {
+      let passengers = self.passengers.passengers(below);
+      player.send(cb::packet::SetPassengers { eid: below, passengers, removed: vec![] });
+    }
+  }
+
+  /// Sends the passengers of `vehicle` to everyone who can see it.
+  fn send_passengers(&self, vehicle: i32, removed: Vec<i32>) {
+    let passengers = self.passengers.passengers(vehicle);
+    self.send_near(vehicle, cb::packet::SetPassengers { eid: vehicle, passengers, removed });
+  }
+  /// Sends the packet to everyone in view of the given entity.
+  fn send_near(&self, eid: i32, packet: cb::packet::SetPassengers) {
+    let chunk = {
+      let _held = lock_order::acquire(Level::EntityIndex);
+      self.entity_index.lock().chunk_of(eid)
+    };
+    if let Some(chunk) = chunk {
+      for p in self.viewers(chunk) {
+        p.send(packet.clone());
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn stacking() {
+    let p = Passengers::new();
+    // A player (1) on a chicken (2) on a boat (3).
+    assert_eq!(p.mount(2, 3), Ok(None));
+    assert_eq!(p.mount(1, 2), Ok(None));
+    assert_eq!(p.passengers(3), vec![2]);
+    assert_eq!(p.passengers(2), vec![1]);
+    // The boat can't ride anything on top of it.
+    assert_eq!(p.mount(3, 1), Err(()));
+    assert_eq!(p.mount(3, 3), Err(()));
+
+    // Moving the player to the boat dismounts them from the chicken.
+    assert_eq!(p.mount(1, 3), Ok(Some(2)));
+    assert_eq!(p.passengers(3), vec![2, 1]);
+    assert_eq!(p.passengers(2), vec![]);
+
+    assert_eq!(p.remove(3), (None, vec![2, 1]));
+    assert_eq!(p.vehicle(1), None);
+    assert_eq!(p.dismount(2), None);
+  }
+}
@@ -0,0 +1,174 @@
+//! Nether portals. A portal is lit by using flint and steel inside of an
+//! obsidian frame, which fills the inside of the frame with portal blocks. The
+//! portal blocks break as soon as any part of the frame is removed, which is
+//! handled by the portal block's behavior.
+
+use super::World;
+use crate::block;
+use bb_common::{math::Pos, util::Face};
+
+/// The smallest width of the inside of a portal frame.
+pub const MIN_WIDTH: u32 = 2;
+/// The smallest height of the inside of a portal frame.
+pub const MIN_HEIGHT: u32 = 3;
+/// The largest width or height of the inside of a portal frame.
+pub const MAX_SIZE: u32 = 21;
+
+/// The inside of a valid nether portal frame. The corners of the frame don't
+/// need to be obsidian, just like vanilla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortalFrame {
+  /// The bottom corner of the inside of the frame, with the lowest X or Z.
+  pub min:    Pos,
+  /// The direction the frame extends in. This is [`Face::East`] for portals
+  /// along the X axis, and [`Face::South`] for portals along the Z axis.
+  pub dir:    Face,
+  pub width:  u32,
+  pub height: u32,
+}
+
+/// Returns the position `n` blocks away from `pos` in the given direction.
+fn along(pos: Pos, dir: Face, n: i32) -> Pos {
+  let d = dir.as_dir();
+  Pos::new(pos.x + d.x * n, pos.y, pos.z + d.z * n)
+}
+
+impl PortalFrame {
+  /// Finds the frame around `pos`, where the inside of the frame extends along
+  /// `dir`. `kind` should return the block at the given position, or `None` if
+  /// the position is outside of the world.
+  ///
+  /// Returns `None` if `pos` isn't empty, or if the frame is incomplete, too
+  /// small, or too large.
+  pub fn find(pos: Pos, dir: Face, kind: impl Fn(Pos) -> Option<block::Kind>) -> Option<Self> {
+    let empty =
+      |p: Pos| matches!(kind(p), Some(block::Kind::Air | block::Kind::CaveAir | block::Kind::Fire));
+    let frame = |p: Pos| kind(p) == Some(block::Kind::Obsidian);
+    if !empty(pos) {
+      return None;
+    }
+
+    // Find the bottom corner, with the lowest X or Z.
+    let mut min = pos;
+    for _ in 0..MAX_SIZE {
+      if !empty(min.add_y(-1)) {
+        break;
+      }
+      min = min.add_y(-1);
+    }
+    for _ in 0..MAX_SIZE {
+      if !empty(along(min, dir, -1)) {
+        break;
+      }
+      min = along(min, dir, -1);
+    }
+
+    // Every block along the bottom must have obsidian below it, and the bottom
+    // row must end with obsidian on both sides.
+    let mut width = 0;
+    while width < MAX_SIZE
+      && empty(along(min, dir, width as i32))
+      && frame(along(min, dir, width as i32).add_y(-1))
+    {
+      width += 1;
+    }
+    if width < MIN_WIDTH || !frame(along(min, dir, -1)) || !frame(along(min, dir, width as i32)) {
+      return None;
+    }
+
+    // Each row is either completely empty, with obsidian on both sides, or
+    // completely obsidian, which is the top of the frame.
+    let mut height = 0;
+    loop {
+      let row = min.add_y(height as i32);
+      if (0..width as i32).all(|i| frame(along(row, dir, i))) {
+        break;
+      }
+      if height == MAX_SIZE
+        || !(0..width as i32).all(|i| empty(along(row, dir, i)))
+        || !frame(along(row, dir, -1))
+        || !frame(along(row, dir, width as i32))
+      {
+        return None;
+      }
+      height += 1;
+    }
+    if height < MIN_HEIGHT {
+      return None;
+    }
+    Some(PortalFrame { min, dir, width, height })
+  }
+
+  /// Returns the `axis` property of the portal blocks inside this frame.
+  pub fn axis(&self) -> &'static str {
+    if self.dir == Face::East {
+      "x"
+    } else {
+      "z"
+    }
+  }
+
+  /// Returns the maximum corner of the inside of the frame.
+  pub fn max(&self) -> Pos {
+    along(self.min, self.dir, self.width as i32 - 1).add_y(self.height as i32 - 1)
+  }
+}
+
+impl World {
+  /// Finds the nether portal frame around `pos`. Frames along the X axis are
+  /// checked first. See [`PortalFrame::find`].
+  pub fn portal_frame(&self, pos: Pos) -> Option<PortalFrame> {
+    let kind = |p| self.get_kind(p).ok();
+    PortalFrame::find(pos, Face::East, kind).or_else(|| PortalFrame::find(pos, Face::South, kind))
+  }
+
+  /// Fills the portal frame around `pos` with portal blocks. Returns `false`
+  /// if there is no valid frame, or if the world is locked.
+  pub fn light_portal(&self, pos: Pos) -> bool {
+    let frame = match self.portal_frame(pos) {
+      Some(frame) => frame,
+      None => return false,
+    };
+    let ty = self.block_converter().ty(block::Kind::NetherPortal).with("axis", frame.axis());
+    matches!(self.set_blocks(frame.min.to(frame.max()).map(|p| (p, ty))), Ok(n) if n > 0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  /// Builds an obsidian frame around the given inside, and returns a function
+  /// that looks up blocks in it.
+  fn frame(min: Pos, max: Pos) -> impl Fn(Pos) -> Option<block::Kind> {
+    let mut blocks = HashSet::new();
+    for p in min.add_x(-1).add_y(-1).to(max.add_x(1).add_y(1)) {
+      if !min.to(max).contains(p) {
+        blocks.insert(p);
+      }
+    }
+    move |p| Some(if blocks.contains(&p) { block::Kind::Obsidian } else { block::Kind::Air })
+  }
+
+  #[test]
+  fn find_frame() {
+    let min = Pos::new(0, 64, 0);
+    let max = Pos::new(1, 66, 0);
+    let kind = frame(min, max);
+    let expected = Some(PortalFrame { min, dir: Face::East, width: 2, height: 3 });
+    assert_eq!(PortalFrame::find(Pos::new(1, 66, 0), Face::East, &kind), expected);
+    assert_eq!(PortalFrame::find(Pos::new(0, 64, 0), Face::East, &kind), expected);
+    assert_eq!(expected.unwrap().max(), max);
+    assert_eq!(PortalFrame::find(Pos::new(0, 64, 0), Face::South, &kind), None);
+    assert_eq!(PortalFrame::find(Pos::new(0, 63, 0), Face::East, &kind), None);
+
+    // A missing side breaks the frame.
+    let missing = |p: Pos| if p == Pos::new(2, 65, 0) { Some(block::Kind::Air) } else { kind(p) };
+    assert_eq!(PortalFrame::find(Pos::new(0, 64, 0), Face::East, missing), None);
+
+    // Too short.
+    let kind = frame(min, Pos::new(3, 65, 0));
+    assert_eq!(PortalFrame::find(Pos::new(0, 64, 0), Face::East, kind), None);
+  }
+}
@@ -0,0 +1,299 @@
+//! The profile cache. This maps usernames to UUIDs (and skins), so that
+//! commands like `/whitelist add` and `/ban` work for players who aren't
+//! online.
+//!
+//! Every player who joins is added to the cache. Names that aren't cached are
+//! looked up with the Mojang API, which is rate limited with
+//! `profiles.requests-per-minute`. In offline mode, names never need to be
+//! looked up, as the UUID is derived from the name, just like vanilla (see
+//! [`UUID::offline`]).
+//!
+//! The cache is stored in `profiles.cache`, which is `usercache.json` by
+//! default.
+
+use super::WorldManager;
+use crate::{
+  config::ProfileConfig,
+  player::{Player, Skin},
+};
+use bb_common::util::{JsonFile, UUID};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::VecDeque,
+  fmt,
+  sync::Arc,
+  thread,
+  time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How long to wait for the Mojang API before giving up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A cached player profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+  pub name:   String,
+  pub id:     UUID,
+  /// The `textures` property of this profile, if it is known.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  textures:   Option<Textures>,
+  /// When this profile was added to the cache, in seconds since the unix
+  /// epoch.
+  pub cached: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Textures {
+  value:     String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  signature: Option<String>,
+}
+
+/// An error from looking up a profile.
+#[derive(Debug)]
+pub enum ProfileError {
+  /// Mojang doesn't know about a player with this name.
+  NotFound,
+  /// Too many lookups were made recently, either by us or by someone else
+  /// with the same IP.
+  RateLimited,
+  /// The request failed, or the response was invalid.
+  Http(String),
+}
+
+impl fmt::Display for ProfileError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::NotFound => write!(f, "no such player"),
+      Self::RateLimited => write!(f, "too many profile lookups, try again later"),
+      Self::Http(e) => write!(f, "could not reach the Mojang API: {e}"),
+    }
+  }
+}
+
+impl std::error::Error for ProfileError {}
+
+/// The response from the Mojang API.
+#[derive(Deserialize)]
+struct JsonProfile {
+  id:         UUID,
+  name:       String,
+  #[serde(default)]
+  properties: Vec<JsonProperty>,
+}
+#[derive(Deserialize)]
+struct JsonProperty {
+  name:      String,
+  value:     String,
+  signature: Option<String>,
+}
+
+impl Profile {
+  fn new(name: &str, id: UUID, skin: Option<&Skin>) -> Self {
+    Profile {
+      name: name.into(),
+      id,
+      textures: skin.map(|s| {
+        let prop = s.to_property();
+        Textures { value: prop.value, signature: prop.signature }
+      }),
+      cached: now(),
+    }
+  }
+
+  /// Returns the skin from this profile, if it is known.
+  pub fn skin(&self) -> Option<Skin> {
+    self.textures.as_ref().map(|t| Skin::new(t.value.clone(), t.signature.clone()))
+  }
+}
+
+/// A cache of usernames to profiles. See the [module docs](self).
+#[derive(Debug)]
+pub struct ProfileCache {
+  config:   ProfileConfig,
+  file:     Mutex<JsonFile<Vec<Profile>>>,
+  /// The time of each request made in the last minute.
+  requests: Mutex<VecDeque<Instant>>,
+}
+
+impl ProfileCache {
+  /// Loads the cache from `config.cache`.
+  pub fn new(config: &ProfileConfig) -> Self {
+    ProfileCache {
+      config:   config.clone(),
+      file:     Mutex::new(JsonFile::load(&config.cache)),
+      requests: Mutex::new(VecDeque::new()),
+    }
+  }
+
+  fn is_fresh(&self, profile: &Profile) -> bool {
+    let expire = u64::from(self.config.expire_days) * 24 * 60 * 60;
+    expire == 0 || profile.cached + expire > now()
+  }
+
+  /// Returns the cached profile with the given name. Names are not case
+  /// sensitive. This never makes any requests.
+  pub fn get(&self, name: &str) -> Option<Profile> {
+    let file = self.file.lock();
+    file.iter().find(|p| p.name.eq_ignore_ascii_case(name) && self.is_fresh(p)).cloned()
+  }
+  /// Returns the cached profile with the given UUID. This never makes any
+  /// requests.
+  pub fn get_id(&self, id: UUID) -> Option<Profile> {
+    let file = self.file.lock();
+    file.iter().find(|p| p.id == id && self.is_fresh(p)).cloned()
+  }
+
+  /// Stores a profile, replacing any profile with the same name or UUID, and
+  /// saves the cache. If `skin` is `None`, the skin already stored for this
+  /// UUID is kept.
+  pub fn insert(&self, name: &str, id: UUID, skin: Option<&Skin>) {
+    let mut file = self.file.lock();
+    let mut profile = Profile::new(name, id, skin);
+    if profile.textures.is_none() {
+      profile.textures = file.iter().find(|p| p.id == id).and_then(|p| p.textures.clone());
+    }
+    file.retain(|p| p.id != id && !p.name.eq_ignore_ascii_case(name));
+    file.push(profile);
+    if let Err(e) = file.save() {
+      error!("could not save profile cache: {e}");
+    }
+  }
+
+  /// Finds the profile for the given name. If it isn't cached, this looks it
+  /// up with the Mojang API, which blocks for up to a few seconds. In offline
+  /// mode, this returns the offline UUID for the name, without making any
+  /// requests.
+  pub fn lookup(&self, name: &str) -> Result<Profile, ProfileError> {
+    if let Some(profile) = self.get(name) {
+      return Ok(profile);
+    }
+    if !self.config.online_mode {
+      return Ok(Profile::new(name, UUID::offline(name), None));
+    }
+    let json = self.request_name(name)?;
+    self.insert(&json.name, json.id, None);
+    Ok(Profile::new(&json.name, json.id, None))
+  }
+
+  /// Fetches the skin of the Mojang account with the given name. This is
+  /// used to show skins in offline mode, so the account's UUID doesn't need to
+  /// match the player's UUID. The skin is stored on the cached profile with
+  /// this name, so it only needs to be fetched once.
+  ///
+  /// This blocks for up to a few seconds, so it should not be called on a
+  /// tick thread.
+  pub fn fetch_skin(&self, name: &str) -> Result<Option<Skin>, ProfileError> {
+    let cached = self.get(name);
+    if let Some(skin) = cached.as_ref().and_then(|p| p.skin()) {
+      return Ok(Some(skin));
+    }
+    let account = self.request_name(name)?;
+    let json: JsonProfile = self.request(&format!(
+      "https://sessionserver.mojang.com/session/minecraft/profile/{}?unsigned=false",
+      account.id.as_str()
+    ))?;
+    let skin = json
+      .properties
+      .into_iter()
+      .find(|p| p.name == "textures")
+      .map(|p| Skin::new(p.value, p.signature));
+    if let Some(profile) = cached {
+      self.insert(&profile.name, profile.id, skin.as_ref());
+    }
+    Ok(skin)
+  }
+
+  fn request_name(&self, name: &str) -> Result<JsonProfile, ProfileError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+      return Err(ProfileError::NotFound);
+    }
+    self.request(&format!("https://api.mojang.com/users/profiles/minecraft/{name}"))
+  }
+
+  fn request(&self, url: &str) -> Result<JsonProfile, ProfileError> {
+    self.take_request()?;
+    let res = match ureq::get(url).timeout(TIMEOUT).call() {
+      Ok(res) => res,
+      Err(ureq::Error::Status(404, _)) => return Err(ProfileError::NotFound),
+      Err(ureq::Error::Status(429, _)) => return Err(ProfileError::RateLimited),
+      Err(e) => return Err(ProfileError::Http(e.to_string())),
+    };
+    // Unknown names used to return 204, so we handle that as well.
+    if res.status() == 204 {
+      return Err(ProfileError::NotFound);
+    }
+    serde_json::from_reader(res.into_reader()).map_err(|e| ProfileError::Http(e.to_string()))
+  }
+
+  /// Uses up one request from the rate limit. Returns an error if there were
+  /// already `requests_per_minute` requests in the last minute.
+  fn take_request(&self) -> Result<(), ProfileError> {
+    let mut requests = self.requests.lock();
+    let now = Instant::now();
+    while requests.front().is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(60)) {
+      requests.pop_front();
+    }
+    if requests.len() >= self.config.requests_per_minute as usize {
+      return Err(ProfileError::RateLimited);
+    }
+    requests.push_back(now);
+    Ok(())
+  }
+}
+
+fn now() -> u64 { SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() }
+
+impl WorldManager {
+  /// Returns the profile cache.
+  pub fn profiles(&self) -> &ProfileCache { &self.profiles }
+
+  /// Fetches a skin for a player who doesn't have one, from the Mojang
+  /// account with the same name. This runs on another thread, and sets the
+  /// player's skin once it is done. See [`ProfileCache::fetch_skin`].
+  pub(super) fn fetch_offline_skin(&self, player: &Arc<Player>) {
+    let profiles = self.profiles.clone();
+    let name = player.username().clone();
+    let player = Arc::downgrade(player);
+    thread::spawn(move || match profiles.fetch_skin(&name) {
+      Ok(Some(skin)) => {
+        if let Some(player) = player.upgrade() {
+          player.set_profile_skin(skin);
+        }
+      }
+      Ok(None) | Err(ProfileError::NotFound) => {}
+      Err(e) => warn!("could not fetch skin for {name}: {e}"),
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cache(online_mode: bool) -> ProfileCache {
+    let config = ProfileConfig { online_mode, requests_per_minute: 2, ..Default::default() };
+    ProfileCache {
+      config,
+      file: Mutex::new(JsonFile::new("unused")),
+      requests: Mutex::new(VecDeque::new()),
+    }
+  }
+
+  #[test]
+  fn offline_lookup() {
+    let cache = cache(false);
+    let profile = cache.lookup("Notch").unwrap();
+    assert_eq!(profile.id, UUID::offline("Notch"));
+    assert_eq!(profile.name, "Notch");
+  }
+
+  #[test]
+  fn rate_limit() {
+    let cache = cache(true);
+    assert!(cache.take_request().is_ok());
+    assert!(cache.take_request().is_ok());
+    assert!(matches!(cache.take_request(), Err(ProfileError::RateLimited)));
+  }
+}
@@ -0,0 +1,253 @@
+//! Region protection. Plugins can add a [`RegionGuard`], which is checked
+//! every time a player breaks, places, or interacts with a block, and every
+//! time an explosion would destroy a block.
+//!
+//! There is also a built-in claim system, which can be enabled per world with
+//! the `claims` option. Players can protect cuboids with `/claim`, and only
+//! the owner (and anyone they trust) can change blocks inside. Claims are
+//! stored in `world/claims/<world name>.json`.
+
+use super::{Cuboid, World, WorldManager};
+use crate::player::Player;
+use bb_common::{
+  math::Pos,
+  util::{
+    chat::{Chat, Color},
+    JsonFile, UUID,
+  },
+};
+use parking_lot::MutexGuard;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::PathBuf, sync::Arc};
+
+/// Something that can be stopped by a [`RegionGuard`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+  /// A player breaking a block.
+  Break,
+  /// A player placing a block.
+  Place,
+  /// A player right clicking on a block, such as opening a crafting table.
+  Interact,
+  /// An explosion destroying a block. There is no player for this action.
+  Explode,
+}
+
+impl Action {
+  /// Returns the lowercase name of this action, as passed to plugins.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Break => "break",
+      Self::Place => "place",
+      Self::Interact => "interact",
+      Self::Explode => "explode",
+    }
+  }
+}
+
+/// Decides which actions are allowed in which parts of the world. Guards are
+/// added with [`WorldManager::add_region_guard`].
+pub trait RegionGuard: Send + Sync {
+  /// Returns `false` if the given action should be stopped. `player` is `None`
+  /// for explosions.
+  ///
+  /// If a player is stopped, the guard should tell them why.
+  fn allows(
+    &self,
+    world: &Arc<World>,
+    player: Option<&Arc<Player>>,
+    pos: Pos,
+    action: Action,
+  ) -> bool;
+}
+
+/// A protected cuboid in a world.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claim {
+  /// The name of this claim. This is unique within a world.
+  pub name:       String,
+  pub owner:      UUID,
+  /// The username of the owner when the claim was created. This is only used
+  /// for display.
+  pub owner_name: String,
+  min:            [i32; 3],
+  max:            [i32; 3],
+  /// Players who can build in this claim, other than the owner.
+  #[serde(default)]
+  pub members:    Vec<UUID>,
+}
+
+/// An error from creating a claim.
+#[derive(Debug, PartialEq)]
+pub enum ClaimError {
+  /// There is already a claim with this name.
+  NameTaken,
+  /// The area overlaps with the given claim.
+  Overlaps(String),
+}
+
+impl fmt::Display for ClaimError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::NameTaken => write!(f, "there is already a claim with that name"),
+      Self::Overlaps(name) => write!(f, "that area overlaps with the claim {name}"),
+    }
+  }
+}
+
+impl Claim {
+  /// Creates a claim over the given area.
+  pub fn new(name: String, owner: UUID, owner_name: String, area: Cuboid) -> Self {
+    let (min, max) = (area.min(), area.max());
+    Claim {
+      name,
+      owner,
+      owner_name,
+      min: [min.x, min.y, min.z],
+      max: [max.x, max.y, max.z],
+      members: vec![],
+    }
+  }
+
+  /// Returns the area this claim protects.
+  pub fn area(&self) -> Cuboid {
+    Cuboid::new(
+      Pos::new(self.min[0], self.min[1], self.min[2]),
+      Pos::new(self.max[0], self.max[1], self.max[2]),
+    )
+  }
+
+  /// Returns `true` if the given player can change blocks in this claim.
+  pub fn can_build(&self, id: UUID) -> bool { self.owner == id || self.members.contains(&id) }
+}
+
+/// All the claims in a single world.
+#[derive(Debug)]
+pub struct Claims {
+  file: JsonFile<Vec<Claim>>,
+  /// If `false`, claims are never written to disk.
+  save: bool,
+}
+
+impl Claims {
+  /// Loads the claims for the world with the given name. If `save` is
+  /// `false`, changes will never be written to disk.
+  pub(super) fn load(world: &str, save: bool) -> Self {
+    let path = PathBuf::new().join("world").join("claims").join(format!("{world}.json"));
+    Claims { file: JsonFile::load(path), save }
+  }
+
+  /// Returns the claim that contains the given position.
+  pub fn at(&self, pos: Pos) -> Option<&Claim> { self.file.iter().find(|c| c.area().contains(pos)) }
+  /// Returns the claim with the given name.
+  pub fn get(&self, name: &str) -> Option<&Claim> { self.file.iter().find(|c| c.name == name) }
+  /// Returns the claim with the given name.
+  pub fn get_mut(&mut self, name: &str) -> Option<&mut Claim> {
+    self.file.iter_mut().find(|c| c.name == name)
+  }
+  /// Returns every claim in this world.
+  pub fn iter(&self) -> impl Iterator<Item = &Claim> { self.file.iter() }
+
+  /// Adds a claim. This fails if the name is taken, or if it overlaps with
+  /// another claim. This does not save the claims.
+  pub fn add(&mut self, claim: Claim) -> Result<(), ClaimError> {
+    if self.get(&claim.name).is_some() {
+      return Err(ClaimError::NameTaken);
+    }
+    let area = claim.area();
+    if let Some(other) = self.file.iter().find(|c| c.area().intersects(&area)) {
+      return Err(ClaimError::Overlaps(other.name.clone()));
+    }
+    self.file.push(claim);
+    Ok(())
+  }
+  /// Removes the claim with the given name. This does not save the claims.
+  pub fn remove(&mut self, name: &str) -> Option<Claim> {
+    let idx = self.file.iter().position(|c| c.name == name)?;
+    Some(self.file.remove(idx))
+  }
+
+  /// Writes all the claims to disk, if this world is saved.
+  pub fn save(&mut self) {
+    if !self.save {
+      return;
+    }
+    if let Err(e) = fs::create_dir_all("world/claims").and_then(|_| self.file.save()) {
+      error!("could not save claims: {e}");
+    }
+  }
+}
+
+impl World {
+  /// Returns the claims in this world. The claims are only checked if the
+  /// `claims` option is set for this world.
+  pub fn claims(&self) -> MutexGuard<'_, Claims> { self.claims.lock() }
+
+  /// Returns `true` if the given action is allowed at `pos`. This checks the
+  /// built-in claims, and then every [`RegionGuard`]. If the action isn't
+  /// allowed, the player will be told why.
+  pub fn allows(self: &Arc<World>, player: Option<&Arc<Player>>, pos: Pos, action: Action) -> bool {
+    if self.config().claims {
+      let claims = self.claims();
+      if let Some(claim) = claims.at(pos) {
+        if !player.is_some_and(|p| claim.can_build(p.id())) {
+          if let Some(p) = player {
+            let mut msg = Chat::empty();
+            msg.add(format!("This area is claimed by {}", claim.owner_name)).color(Color::Red);
+            p.send_hotbar(msg);
+          }
+          return false;
+        }
+      }
+    }
+    self.wm.region_guards.read().iter().all(|g| g.allows(self, player, pos, action))
+  }
+  /// Returns `true` if an explosion can destroy the block at `pos`.
+  pub fn can_explode(self: &Arc<World>, pos: Pos) -> bool {
+    self.allows(None, pos, Action::Explode)
+  }
+}
+
+impl WorldManager {
+  /// Adds a region guard. This will be checked in every world, after the
+  /// built-in claims.
+  pub fn add_region_guard(&self, guard: impl RegionGuard + 'static) {
+    self.region_guards.write().push(Box::new(guard));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn overlap() {
+    let owner = UUID::from_u128(0x1234);
+    let mut claims = Claims::load("test", false);
+    let claim = |name: &str, a: Pos, b: Pos| {
+      Claim::new(name.into(), owner, "macmv".into(), Cuboid::new(a, b))
+    };
+    assert_eq!(claims.add(claim("home", Pos::new(0, 0, 0), Pos::new(10, 255, 10))), Ok(()));
+    assert_eq!(
+      claims.add(claim("home", Pos::new(20, 0, 20), Pos::new(30, 255, 30))),
+      Err(ClaimError::NameTaken)
+    );
+    assert_eq!(
+      claims.add(claim("farm", Pos::new(10, 0, 10), Pos::new(30, 255, 30))),
+      Err(ClaimError::Overlaps("home".into()))
+    );
+    assert_eq!(claims.add(claim("farm", Pos::new(11, 0, 11), Pos::new(30, 255, 30))), Ok(()));
+
+    assert_eq!(claims.at(Pos::new(5, 64, 5)).unwrap().name, "home");
+    assert_eq!(claims.at(Pos::new(11, 64, 11)).unwrap().name, "farm");
+    assert!(claims.at(Pos::new(-1, 64, 5)).is_none());
+
+    let home = claims.get_mut("home").unwrap();
+    assert!(!home.can_build(UUID::from_u128(1)));
+    home.members.push(UUID::from_u128(1));
+    assert!(home.can_build(UUID::from_u128(1)));
+
+    assert!(claims.remove("home").is_some());
+    assert!(claims.at(Pos::new(5, 64, 5)).is_none());
+  }
+}
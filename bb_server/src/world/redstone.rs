@@ -0,0 +1,39 @@
+//! Redstone power. There is no redstone simulation yet, so this only checks
+//! for blocks that are next to a power source, like a lever or a redstone
+//! block. Power does not travel through solid blocks.
+
+use super::World;
+use crate::block;
+use bb_common::{math::Pos, util::Face};
+
+impl World {
+  /// Returns `true` if any of the blocks next to `pos` are emitting redstone
+  /// power.
+  pub fn is_powered(&self, pos: Pos) -> bool {
+    [Face::Top, Face::Bottom, Face::North, Face::South, Face::East, Face::West]
+      .into_iter()
+      .any(|face| self.get_block(pos + face).map(|ty| self.emits_power(ty.ty())).unwrap_or(false))
+  }
+
+  /// Returns `true` if the given block is a power source, and is currently on.
+  fn emits_power(&self, ty: block::Type) -> bool {
+    match ty.kind() {
+      block::Kind::RedstoneBlock => true,
+      block::Kind::RedstoneTorch | block::Kind::RedstoneWallTorch => ty.prop("lit").bool(),
+      block::Kind::RedstoneWire => ty.prop("power").int() > 0,
+      kind => match self.block_converter().get(kind).name {
+        "lever" | "repeater" | "comparator" | "observer" | "tripwire_hook" | "detector_rail" => {
+          ty.prop("powered").bool()
+        }
+        // Weighted pressure plates have a power level instead of a `powered` property.
+        name if name.ends_with("_button") || name.ends_with("_pressure_plate") => {
+          match ty.try_prop("powered") {
+            Ok(powered) => powered.bool(),
+            Err(_) => ty.prop("power").int() > 0,
+          }
+        }
+        _ => false,
+      },
+    }
+  }
+}
@@ -0,0 +1,184 @@
+//! Converts an entire vanilla world to bamboo's region format ahead of time.
+//! See [`import_world`].
+
+use super::{for_each_chunk, parse_region_name, RegionError, RegionStats};
+use crate::world::{bbr::RegionPos, World, WorldManager};
+use bb_common::util::ThreadPool;
+use parking_lot::Mutex;
+use std::{
+  fs, io,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+};
+
+/// A dimension in a vanilla world.
+struct Dimension {
+  name: &'static str,
+  /// The folder of this dimension, within the vanilla world.
+  path: &'static str,
+}
+
+const DIMENSIONS: &[Dimension] = &[
+  Dimension { name: "overworld", path: "" },
+  Dimension { name: "nether", path: "DIM-1" },
+  Dimension { name: "end", path: "DIM1" },
+];
+
+impl Dimension {
+  /// Returns the directory that this dimension's regions are saved to. The
+  /// overworld is saved to `chunks`, which is the `world.chunks` option.
+  fn dest(&self, chunks: &str) -> PathBuf {
+    match self.path {
+      "" => chunks.into(),
+      _ => Path::new("world").join(self.name).join("chunks"),
+    }
+  }
+}
+
+/// The result of [`import_world`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportSummary {
+  /// The number of regions that were imported.
+  pub regions:                usize,
+  /// The number of regions that couldn't be read. Any chunks that were loaded
+  /// before the error are still saved.
+  pub failed:                 usize,
+  /// The number of chunks that were imported.
+  pub chunks:                 usize,
+  /// The number of block entities that were imported.
+  pub block_entities:         usize,
+  /// The number of block entities that bamboo doesn't support.
+  pub skipped_block_entities: usize,
+  /// The number of entities in the world. Bamboo doesn't save entities, so
+  /// these are never imported.
+  pub skipped_entities:       usize,
+}
+
+impl ImportSummary {
+  fn add(&mut self, stats: RegionStats) {
+    self.regions += 1;
+    self.chunks += stats.chunks;
+    self.block_entities += stats.block_entities;
+    self.skipped_block_entities += stats.skipped_block_entities;
+    self.skipped_entities += stats.entities;
+  }
+}
+
+/// Converts the vanilla world at `path` into bamboo regions. The overworld is
+/// saved to the `world.chunks` directory, and the nether and end are saved to
+/// `world/nether/chunks` and `world/end/chunks`.
+///
+/// This should be run while the server is stopped. Regions are saved as soon
+/// as they are converted, so this never keeps more than a few regions in
+/// memory. Regions that fail to load are logged, and the rest of the world is
+/// still imported.
+pub fn import_world(wm: &Arc<WorldManager>, path: &Path) -> io::Result<ImportSummary> {
+  if !path.join("region").is_dir() {
+    return Err(io::Error::new(
+      io::ErrorKind::NotFound,
+      format!("{} is not a vanilla world (it has no `region` folder)", path.display()),
+    ));
+  }
+  let summary = Arc::new(Mutex::new(ImportSummary::default()));
+  for dim in DIMENSIONS {
+    let regions = path.join(dim.path).join("region");
+    if !regions.is_dir() {
+      continue;
+    }
+    let mut config = wm.config().world.clone();
+    config.save = true;
+    config.chunks = dim.dest(&wm.config().world.chunks).to_string_lossy().into_owned();
+    // Every chunk is replaced by the vanilla world, so there is no point
+    // generating anything.
+    config.void = true;
+    config.debug = false;
+    config.vanilla.enabled = false;
+    config.schematic.enabled = false;
+    let world = Arc::new(wm.new_world_config(config));
+    import_dimension(&world, dim, &regions, &path.join(dim.path).join("entities"), &summary)?;
+  }
+  let summary = *summary.lock();
+  info!(
+    "done: {} regions imported, {} failed ({} chunks, {} block entities)",
+    summary.regions, summary.failed, summary.chunks, summary.block_entities,
+  );
+  if summary.skipped_block_entities > 0 {
+    warn!("skipped {} unsupported block entities", summary.skipped_block_entities);
+  }
+  if summary.skipped_entities > 0 {
+    warn!("skipped {} entities, as bamboo does not save entities", summary.skipped_entities);
+  }
+  Ok(summary)
+}
+
+fn import_dimension(
+  world: &Arc<World>,
+  dim: &'static Dimension,
+  regions: &Path,
+  entities: &Path,
+  summary: &Arc<Mutex<ImportSummary>>,
+) -> io::Result<()> {
+  let mut files = vec![];
+  for f in fs::read_dir(regions)? {
+    let path = f?.path();
+    if let Some(pos) = path.file_name().and_then(|n| n.to_str()).and_then(parse_region_name) {
+      files.push((pos, path));
+    }
+  }
+  files.sort();
+  let total = files.len();
+  info!("importing {total} regions from the {} into {}", dim.name, world.config().chunks);
+
+  let done = Arc::new(AtomicUsize::new(0));
+  let pool = ThreadPool::auto("world import", || ());
+  for ((x, z), path) in files {
+    let world = world.clone();
+    let entities = entities.join(path.file_name().unwrap());
+    let done = done.clone();
+    let summary = summary.clone();
+    pool.execute(move |_| {
+      let res = world.load_region_file(&path).and_then(|mut stats| {
+        stats.entities += count_entities(&entities)?;
+        Ok(stats)
+      });
+      // Vanilla regions are the same size as ours, so this saves everything we just
+      // loaded.
+      world.regions.unload_region(RegionPos { x, z });
+
+      let i = done.fetch_add(1, Ordering::Relaxed) + 1;
+      let name = path.file_name().unwrap().to_string_lossy();
+      match res {
+        Ok(stats) => {
+          summary.lock().add(stats);
+          info!("[{}] {i}/{total} {name}: {} chunks", dim.name, stats.chunks);
+        }
+        Err(e) => {
+          summary.lock().failed += 1;
+          error!("[{}] {i}/{total} could not import {name}: {e}", dim.name);
+        }
+      }
+    });
+  }
+  pool.wait();
+  Ok(())
+}
+
+/// Counts the entities in a region file from the `entities` folder. Since
+/// 1.17, entities are stored here instead of in the chunk.
+fn count_entities(path: &Path) -> Result<usize, RegionError> {
+  if !path.exists() {
+    return Ok(0);
+  }
+  let data = fs::read(path)?;
+  let mut count = 0;
+  for_each_chunk(&data, |nbt| {
+    if let Some(entities) = nbt.compound()?.inner.get("Entities") {
+      count += entities.list()?.len();
+    }
+    Ok(())
+  })?;
+  Ok(count)
+}
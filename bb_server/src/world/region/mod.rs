@@ -5,25 +5,34 @@
 //! Not to be confused with `bbr` (bamboo region), which is for a custom world
 //! format.
 
-use crate::block;
+mod import;
+
+use crate::block::{self, CommandBlockData, CommandBlockTE, SpawnerData, SpawnerTE, TileEntity};
 use bb_common::{
   chunk::Section,
-  math::{ChunkPos, SectionRelPos},
-  nbt::{Tag, WrongTag, NBT},
+  math::{ChunkPos, Pos, SectionRelPos},
+  nbt::{Compound, ParseError, Tag, WrongTag, NBT},
   util::ThreadPool,
 };
 use std::{fmt, fs, io, path::Path, str::FromStr, sync::Arc};
 
 use super::World;
 
+pub use import::{import_world, ImportSummary};
+
 pub enum RegionError {
   IO(io::Error),
+  Parse(ParseError),
   WrongTag(WrongTag),
+  Invalid(String),
 }
 
 impl From<io::Error> for RegionError {
   fn from(e: io::Error) -> Self { RegionError::IO(e) }
 }
+impl From<ParseError> for RegionError {
+  fn from(e: ParseError) -> Self { RegionError::Parse(e) }
+}
 impl From<WrongTag> for RegionError {
   fn from(e: WrongTag) -> Self { RegionError::WrongTag(e) }
 }
@@ -32,11 +41,28 @@ impl fmt::Display for RegionError {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
       Self::IO(e) => write!(f, "{e}"),
+      Self::Parse(e) => write!(f, "{e}"),
       Self::WrongTag(e) => write!(f, "{e}"),
+      Self::Invalid(msg) => write!(f, "{msg}"),
     }
   }
 }
 
+/// What was loaded from a single vanilla region file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RegionStats {
+  /// The number of chunks that were loaded.
+  pub chunks:                 usize,
+  /// The number of block entities that were loaded.
+  pub block_entities:         usize,
+  /// The number of block entities that don't exist in bamboo, which were
+  /// skipped.
+  pub skipped_block_entities: usize,
+  /// The number of entities in the region. Bamboo doesn't save entities, so
+  /// these are always skipped.
+  pub entities:               usize,
+}
+
 fn parse_region_name(name: &str) -> Option<(i32, i32)> {
   let mut sections = name.split('.');
   if sections.next()? != "r" {
@@ -53,6 +79,52 @@ fn parse_region_name(name: &str) -> Option<(i32, i32)> {
   Some((x, z))
 }
 
+/// Calls `f` with the nbt data of every chunk in a region file. This is used
+/// for both the `region` and `entities` folders.
+fn for_each_chunk(
+  data: &[u8],
+  mut f: impl FnMut(Tag) -> Result<(), RegionError>,
+) -> Result<(), RegionError> {
+  if data.len() < 8192 {
+    return Err(RegionError::Invalid("region file is missing its header".into()));
+  }
+  let header = &data[..8192];
+  // `offset` is an offset into the file, not an offset into the chunks table.
+  let chunks = data;
+  for id in 0..1024 {
+    let start = id * 4;
+    let num = u32::from_be_bytes(header[start..start + 4].try_into().unwrap());
+    let offset: usize = ((num >> 8) & 0xffffff) as usize * 4096;
+    let mut size: usize = (num & 0xff) as usize * 4096;
+    if size == 0 {
+      continue;
+    }
+
+    if offset >= chunks.len() {
+      error!("section had invalid index: {offset:#x} size {size:#x} (len: {:#x})", chunks.len());
+      continue;
+    } else if offset + size > chunks.len() {
+      size = chunks.len() - offset;
+    }
+
+    let chunk = &chunks[offset..offset + size];
+    if chunk.len() < 5 {
+      return Err(RegionError::Invalid(format!("chunk {id} is missing its header")));
+    }
+    let len = u32::from_be_bytes(chunk[..4].try_into().unwrap()) as usize;
+    let _compression = chunk[4];
+    if len == 0 || len + 4 > chunk.len() {
+      return Err(RegionError::Invalid(format!("chunk {id} has an invalid length {len}")));
+    }
+    f(NBT::deserialize_file(chunk[5..5 + len - 1].to_vec())?.into_tag())?;
+  }
+  Ok(())
+}
+
+fn get<'a>(compound: &'a Compound, key: &str) -> Result<&'a Tag, RegionError> {
+  compound.inner.get(key).ok_or_else(|| RegionError::Invalid(format!("missing `{key}`")))
+}
+
 impl World {
   pub fn load_from_disk(self: &Arc<Self>, path: &Path) -> io::Result<()> {
     let chunks = path.join("region");
@@ -80,40 +152,17 @@ impl World {
     Ok(())
   }
 
-  fn load_region_file(&self, path: &Path) -> Result<(), RegionError> {
+  fn load_region_file(&self, path: &Path) -> Result<RegionStats, RegionError> {
     let data = fs::read(path)?;
-    let header = &data[..8192];
-    // `offset` is an offset into the file, not an offset into the chunks table.
-    let chunks = &data;
-    for id in 0..1024 {
-      let start = id * 4;
-      let num = u32::from_be_bytes(header[start..start + 4].try_into().unwrap());
-      let offset: usize = ((num >> 8) & 0xffffff) as usize * 4096;
-      let mut size: usize = (num & 0xff) as usize * 4096;
-      if size == 0 {
-        continue;
-      }
-
-      if offset + size > chunks.len() {
-        size = chunks.len() - offset;
-      } else if offset >= chunks.len() {
-        error!("section had invalid index: {offset:#x} size {size:#x} (len: {:#x})", chunks.len());
-        continue;
-      }
-
-      let chunk = &chunks[offset..offset + size];
-      let header = &chunk[..5];
-      let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
-      let _compression = header[4];
-      let nbt = NBT::deserialize_file(chunk[5..5 + len - 1].to_vec()).unwrap().into_tag();
-
+    let mut stats = RegionStats::default();
+    for_each_chunk(&data, |nbt| {
       // 1.8 uses capitalized names
       // 1.12.2 uses lowercase names.
       // 1.18 uses a mix of both (wtf???)
       let is_capital_names;
       let nbt = if nbt.compound()?.contains_key("Level") {
         is_capital_names = true;
-        &nbt.compound()?["Level"]
+        get(nbt.compound()?, "Level")?
       } else {
         is_capital_names = false;
         &nbt
@@ -121,19 +170,25 @@ impl World {
       let level = nbt.compound()?;
       let sections_key = if is_capital_names { "Sections" } else { "sections" };
       if !level.contains_key(sections_key) {
-        continue;
+        return Ok(());
       }
 
       // the chunk_x and chunk_z values are absolute.
-      let chunk_x = level["xPos"].int()?;
-      let chunk_z = level["zPos"].int()?;
+      let chunk_x = get(level, "xPos")?.int()?;
+      let chunk_z = get(level, "zPos")?.int()?;
       let pos = ChunkPos::new(chunk_x, chunk_z);
+      stats.chunks += 1;
+
+      // Since 1.17, entities are stored in a separate `entities` folder.
+      if let Some(entities) = level.inner.get("Entities") {
+        stats.entities += entities.list()?.len();
+      }
 
       // TODO: Light updates!
       self.chunk(pos, |mut chunk| {
-        for s in level[sections_key].list()? {
+        for s in get(level, sections_key)?.list()? {
           let section = s.compound()?;
-          let y = section["Y"].byte()?;
+          let y = get(section, "Y")?.byte()?;
           if y < 0 {
             // TODO: Handle negative chunks
             continue;
@@ -142,8 +197,8 @@ impl World {
           if is_capital_names {
             if section.contains_key("Blocks") {
               // is 1.8
-              let blocks = section["Blocks"].byte_arr()?;
-              let data = section["Data"].byte_arr()?;
+              let blocks = get(section, "Blocks")?.byte_arr()?;
+              let data = get(section, "Data")?.byte_arr()?;
               let section = chunk.inner_mut().section_mut(y);
               for y in 0..16 {
                 for z in 0..16 {
@@ -164,8 +219,8 @@ impl World {
                 continue;
               }
               let block_states =
-                section["BlockStates"].long_arr()?.iter().map(|v| *v as u64).collect();
-              let palette: Vec<_> = section["Palette"]
+                get(section, "BlockStates")?.long_arr()?.iter().map(|v| *v as u64).collect();
+              let palette: Vec<_> = get(section, "Palette")?
                 .list()?
                 .iter()
                 .map(|it| parse_state(self.block_converter(), it))
@@ -175,8 +230,8 @@ impl World {
             }
           } else {
             // is 1.12+
-            let block_states = section["block_states"].compound()?;
-            let palette: Vec<_> = block_states["palette"]
+            let block_states = get(section, "block_states")?.compound()?;
+            let palette: Vec<_> = get(block_states, "palette")?
               .list()?
               .iter()
               .map(|it| parse_state(self.block_converter(), it))
@@ -187,26 +242,120 @@ impl World {
               assert_eq!(palette.len(), 1);
               section.fill(SectionRelPos::new(0, 0, 0), SectionRelPos::new(15, 15, 15), palette[0]);
             } else {
-              let data = block_states["data"].long_arr()?.iter().map(|v| *v as u64).collect();
+              let data = get(block_states, "data")?.long_arr()?.iter().map(|v| *v as u64).collect();
               section.set_from(palette, data);
             }
           }
         }
+
+        // Setting sections directly doesn't create any tile entities, so we create them
+        // all here.
+        let key = if is_capital_names { "TileEntities" } else { "block_entities" };
+        if let Some(block_entities) = level.inner.get(key) {
+          for te in block_entities.list()? {
+            let te = te.compound()?;
+            let pos = Pos::new(get(te, "x")?.int()?, get(te, "y")?.int()?, get(te, "z")?.int()?);
+            let te = match chunk.get_kind(pos.chunk_rel()) {
+              Ok(kind) => self.load_block_entity(kind, te),
+              // This is in a negative section, which we skipped above.
+              Err(_) => None,
+            };
+            match te {
+              Some(te) => {
+                chunk.set_te(pos.chunk_rel(), te).unwrap();
+                stats.block_entities += 1;
+              }
+              None => stats.skipped_block_entities += 1,
+            }
+          }
+        }
         Ok::<(), RegionError>(())
-      })?;
-    }
-    Ok(())
+      })
+    })?;
+    Ok(stats)
+  }
+
+  /// Creates the tile entity for a vanilla block entity. This returns `None`
+  /// if bamboo doesn't have a tile entity for the block.
+  ///
+  /// Only spawners and command blocks keep their settings. Everything else
+  /// (like the items in a chest) is reset.
+  fn load_block_entity(&self, kind: block::Kind, nbt: &Compound) -> Option<Arc<dyn TileEntity>> {
+    Some(match kind {
+      block::Kind::Spawner => Arc::new(SpawnerTE::new(spawner_data(nbt))),
+      block::Kind::CommandBlock
+      | block::Kind::RepeatingCommandBlock
+      | block::Kind::ChainCommandBlock => Arc::new(CommandBlockTE::new(command_block_data(nbt))),
+      _ => self.world_manager().block_behaviors().call(kind, |b| b.create_te())?,
+    })
+  }
+}
+
+fn spawner_data(nbt: &Compound) -> SpawnerData {
+  let mut data = SpawnerData::default();
+  // 1.8 stores the entity in `EntityId`, 1.9 moved it to `SpawnData.id`, and
+  // 1.18 moved it to `SpawnData.entity.id`.
+  let spawn_data = nbt.inner.get("SpawnData").and_then(|t| t.compound().ok());
+  let entity = spawn_data
+    .and_then(|s| s.inner.get("entity").and_then(|t| t.compound().ok()).or(Some(s)))
+    .and_then(|s| s.inner.get("id"))
+    .or_else(|| nbt.inner.get("EntityId"))
+    .and_then(|t| t.string().ok());
+  if let Some(entity) = entity {
+    data.entity = entity.strip_prefix("minecraft:").unwrap_or(entity).to_lowercase();
+  }
+  let short = |key: &str| nbt.inner.get(key).and_then(|t| t.short().ok());
+  if let Some(v) = short("Delay") {
+    data.delay = v;
+  }
+  if let Some(v) = short("MinSpawnDelay") {
+    data.min_delay = v;
+  }
+  if let Some(v) = short("MaxSpawnDelay") {
+    data.max_delay = v;
+  }
+  if let Some(v) = short("SpawnCount") {
+    data.spawn_count = v as u16;
+  }
+  if let Some(v) = short("SpawnRange") {
+    data.spawn_range = v as u16;
+  }
+  if let Some(v) = short("RequiredPlayerRange") {
+    data.player_range = v as u16;
+  }
+  if let Some(v) = short("MaxNearbyEntities") {
+    data.max_nearby = v as u16;
+  }
+  data
+}
+
+fn command_block_data(nbt: &Compound) -> CommandBlockData {
+  let flag = |key: &str| nbt.inner.get(key).and_then(|t| t.byte().ok()).is_some_and(|v| v != 0);
+  CommandBlockData {
+    command: nbt.inner.get("Command").and_then(|t| t.string().ok()).unwrap_or_default().into(),
+    auto: flag("auto"),
+    track_output: flag("TrackOutput"),
+    success_count: nbt.inner.get("SuccessCount").and_then(|t| t.int().ok()).unwrap_or(0),
+    powered: flag("powered"),
+    ..Default::default()
   }
 }
 
 fn parse_state(conv: &block::TypeConverter, item: &Tag) -> Result<u32, RegionError> {
   let item = item.compound()?;
-  let name = item["Name"].string()?;
-  let name = name.strip_prefix("minecraft:").unwrap();
-  let mut ty = conv.get(block::Kind::from_str(name).unwrap()).default_type();
+  let name = get(item, "Name")?.string()?;
+  let name = name.strip_prefix("minecraft:").unwrap_or(name);
+  let kind = match block::Kind::from_str(name) {
+    Ok(kind) => kind,
+    Err(_) => {
+      warn!("unknown block {name} in vanilla world, replacing it with air");
+      return Ok(0);
+    }
+  };
+  let mut ty = conv.get(kind).default_type();
 
   if item.contains_key("Properties") {
-    let props = item["Properties"].compound()?;
+    let props = get(item, "Properties")?.compound()?;
     for (key, val) in props {
       match val {
         Tag::String(v) if v == "true" => ty.set_prop(key, true),
@@ -0,0 +1,92 @@
+//! Seeded random numbers for a world. Every world has a seed (`world.seed` in
+//! the config), and each part of the server that needs random numbers has its
+//! own stream derived from that seed. This means a world will behave the same
+//! way every time it is started with the same seed.
+
+use super::World;
+use bb_common::math::{derive_seed, RngCore, WyhashRng};
+use parking_lot::Mutex;
+
+/// A source of random numbers in a world. See [`World::rng`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+  /// Terrain generation. Generators need the same terrain no matter which
+  /// order chunks are generated in, so they should seed their own RNG from
+  /// the position being generated, using [`World::seed_for`].
+  Generation,
+  /// Natural mob spawning and spawners.
+  Spawning,
+  /// Block drops.
+  Loot,
+}
+
+impl RngStream {
+  /// The name used to derive this stream's seed. See [`derive_seed`].
+  pub fn name(&self) -> &'static str {
+    match self {
+      RngStream::Generation => "generation",
+      RngStream::Spawning => "spawning",
+      RngStream::Loot => "loot",
+    }
+  }
+}
+
+/// The RNG streams for a world.
+#[derive(Debug)]
+pub(super) struct WorldRng {
+  seed:     u64,
+  spawning: Mutex<WyhashRng>,
+  loot:     Mutex<WyhashRng>,
+}
+
+impl WorldRng {
+  pub fn new(seed: u64) -> Self {
+    WorldRng {
+      seed,
+      spawning: Mutex::new(WyhashRng::new(derive_seed(seed, RngStream::Spawning.name()))),
+      loot: Mutex::new(WyhashRng::new(derive_seed(seed, RngStream::Loot.name()))),
+    }
+  }
+}
+
+impl World {
+  /// Returns the seed of this world. This is set with `world.seed` in the
+  /// config.
+  pub fn seed(&self) -> u64 { self.rng.seed }
+
+  /// Returns a seed for something in this world. This is the world seed mixed
+  /// with `name`, so anything that needs its own random numbers (like a
+  /// custom generator) can get them without matching any other part of the
+  /// world.
+  pub fn seed_for(&self, name: &str) -> u64 { derive_seed(self.rng.seed, name) }
+
+  /// Returns a new RNG, seeded from the given stream. The stream only advances
+  /// once per call, so the returned RNG can be used for as long as needed
+  /// without holding any locks.
+  ///
+  /// For [`RngStream::Generation`], this always returns the same RNG. See the
+  /// docs on that variant.
+  pub fn rng(&self, stream: RngStream) -> WyhashRng {
+    let seed = match stream {
+      RngStream::Generation => self.seed_for(stream.name()),
+      RngStream::Spawning => self.rng.spawning.lock().next_u64(),
+      RngStream::Loot => self.rng.loot.lock().next_u64(),
+    };
+    WyhashRng::new(seed)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn streams() {
+    let a = WorldRng::new(1234);
+    let b = WorldRng::new(1234);
+    let spawning = a.spawning.lock().next_u64();
+    assert_eq!(spawning, b.spawning.lock().next_u64());
+    assert_ne!(spawning, a.loot.lock().next_u64());
+    assert_ne!(spawning, WorldRng::new(1235).spawning.lock().next_u64());
+  }
+}
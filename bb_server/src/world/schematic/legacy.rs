@@ -0,0 +1,167 @@
+//! The legacy `.schematic` format, used by MCEdit, Schematica, and WorldEdit
+//! before 1.13. Blocks are stored as 1.12 block ids and metadata.
+
+use super::{get, invalid, Schematic, SchematicError};
+use crate::block;
+use bb_common::{
+  math::Pos,
+  nbt::{Compound, Tag, NBT},
+  version::BlockVersion,
+};
+use std::{collections::HashMap, str::FromStr};
+
+pub(super) fn read(
+  root: &Compound,
+  types: &block::TypeConverter,
+) -> Result<Schematic, SchematicError> {
+  let material = get(root, "Materials")?.string()?;
+  if material != "Alpha" {
+    return Err(invalid(format!("unknown material {material}")));
+  }
+  let mut schem = Schematic::new(
+    get(root, "Width")?.short()? as u16 as u32,
+    get(root, "Height")?.short()? as u16 as u32,
+    get(root, "Length")?.short()? as u16 as u32,
+  );
+  // WorldEdit stores the offset from where the schematic was copied.
+  if let (Some(x), Some(y), Some(z)) =
+    (root.inner.get("WEOffsetX"), root.inner.get("WEOffsetY"), root.inner.get("WEOffsetZ"))
+  {
+    schem.offset = Pos::new(x.int()?, y.int()?, z.int()?);
+  }
+
+  let blocks = get(root, "Blocks")?.byte_arr()?;
+  let data = root.inner.get("Data").map(|tag| tag.byte_arr()).transpose()?;
+  // Block ids above 255 have their upper 4 bits stored in `AddBlocks`, with two
+  // blocks in each byte.
+  let add = root.inner.get("AddBlocks").map(|tag| tag.byte_arr()).transpose()?;
+  if blocks.len() < schem.blocks.len() || data.map_or(false, |d| d.len() < schem.blocks.len()) {
+    return Err(invalid("not enough block data"));
+  }
+  // Schematica stores its own mapping from ids to block names.
+  let names = match root.inner.get("SchematicaMapping") {
+    Some(mapping) => Some(
+      mapping
+        .compound()?
+        .iter()
+        .map(|(name, id)| {
+          let name = convert_alpha_name(name.strip_prefix("minecraft:").unwrap_or(name));
+          let kind = block::Kind::from_str(name).unwrap_or_else(|e| {
+            warn!("{e} in schematic, replacing it with air");
+            block::Kind::Air
+          });
+          Ok((id.short()?, types.get(kind).default_type().id()))
+        })
+        .collect::<Result<HashMap<_, _>, SchematicError>>()?,
+    ),
+    None => None,
+  };
+
+  for i in 0..schem.blocks.len() {
+    let mut id = u32::from(blocks[i]);
+    if let Some(add) = add.and_then(|add| add.get(i >> 1)) {
+      id |= if i & 1 == 0 { u32::from(add & 0x0f) << 8 } else { u32::from(add & 0xf0) << 4 };
+    }
+    schem.blocks[i] = match &names {
+      // TODO: Schematica mappings should use the block's metadata as well.
+      Some(names) => names.get(&(id as i16)).copied().unwrap_or(0),
+      None => {
+        let meta = data.map_or(0, |d| u32::from(d[i] & 0x0f));
+        types.to_latest(id << 4 | meta, BlockVersion::V1_12)
+      }
+    };
+  }
+
+  if let Some(tes) = root.inner.get("TileEntities") {
+    for tag in tes.list()? {
+      let mut data = tag.compound()?.clone();
+      let mut coord = |name: &str| match data.inner.remove(name) {
+        Some(Tag::Int(v)) => Ok(v),
+        _ => Err(invalid(format!("tile entity is missing `{name}`"))),
+      };
+      let pos = Pos::new(coord("x")?, coord("y")?, coord("z")?);
+      if let Some(id) = data.inner.remove("id") {
+        data.insert("Id", id);
+      }
+      schem.block_entities.push((pos, data));
+    }
+  }
+  Ok(schem)
+}
+
+pub(super) fn write(schem: &Schematic, types: &block::TypeConverter) -> NBT {
+  let mut blocks = Vec::with_capacity(schem.blocks.len());
+  let mut data = Vec::with_capacity(schem.blocks.len());
+  let mut add = vec![0; (schem.blocks.len() + 1) / 2];
+  for (i, &id) in schem.blocks.iter().enumerate() {
+    let old = types.to_old(id, BlockVersion::V1_12);
+    let (id, meta) = (old >> 4, old & 0x0f);
+    blocks.push(id as u8);
+    data.push(meta as u8);
+    let upper = (id >> 8) as u8 & 0x0f;
+    add[i >> 1] |= if i & 1 == 0 { upper } else { upper << 4 };
+  }
+
+  let mut root = Compound::new();
+  root.insert("Materials", "Alpha");
+  root.insert("Width", Tag::Short(schem.width as u16 as i16));
+  root.insert("Height", Tag::Short(schem.height as u16 as i16));
+  root.insert("Length", Tag::Short(schem.length as u16 as i16));
+  root.insert("WEOffsetX", Tag::Int(schem.offset.x));
+  root.insert("WEOffsetY", Tag::Int(schem.offset.y));
+  root.insert("WEOffsetZ", Tag::Int(schem.offset.z));
+  root.insert("Blocks", Tag::ByteArr(blocks));
+  root.insert("Data", Tag::ByteArr(data));
+  if add.iter().any(|&b| b != 0) {
+    root.insert("AddBlocks", Tag::ByteArr(add));
+  }
+  root.insert(
+    "TileEntities",
+    Tag::List(
+      schem
+        .block_entities
+        .iter()
+        .map(|(pos, data)| {
+          let mut out = data.clone();
+          if let Some(id) = out.inner.remove("Id") {
+            out.insert("id", id);
+          }
+          out.insert("x", Tag::Int(pos.x));
+          out.insert("y", Tag::Int(pos.y));
+          out.insert("z", Tag::Int(pos.z));
+          Tag::Compound(out)
+        })
+        .collect(),
+    ),
+  );
+  root.insert("Entities", Tag::List(vec![]));
+  NBT::new("Schematic", Tag::Compound(root))
+}
+
+fn convert_alpha_name(name: &str) -> &str {
+  match name {
+    "grass" => "grass_block",
+    _ => name,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn roundtrip() {
+    let types = block::TypeConverter::new();
+    let stone = types.get(block::Kind::Stone).default_type().id();
+    let mut schem = Schematic::new(3, 1, 2);
+    schem.set_offset(Pos::new(4, -5, 6));
+    schem.set(Pos::new(2, 0, 1), stone);
+    let mut sign = Compound::new();
+    sign.insert("Id", "Sign");
+    sign.insert("Text1", "hello");
+    schem.add_block_entity(Pos::new(0, 0, 1), sign);
+
+    let data = schem.write_legacy(&types);
+    assert_eq!(Schematic::read(data, &types).unwrap(), schem);
+  }
+}
@@ -0,0 +1,378 @@
+//! Schematics store a region of blocks in a file. This can read and write the
+//! Sponge `.schem` format (versions 2 and 3), the legacy `.schematic` format
+//! used by older versions of WorldEdit and MCEdit, and the vanilla `.nbt`
+//! structure format.
+
+mod legacy;
+mod sponge;
+mod structure;
+mod transform;
+
+pub use sponge::SpongeVersion;
+pub use transform::{Mirror, Rotation};
+
+use super::{World, WorldManager};
+use crate::block;
+use bb_common::{
+  math::{Pos, PosError, WyhashRng},
+  nbt::{Compound, ParseError, Tag, WrongTag, NBT},
+  version::BlockVersion,
+};
+use rand::Rng;
+use std::{fmt, fs, io, path::Path, sync::Arc};
+
+#[derive(Debug)]
+pub enum SchematicError {
+  IO(io::Error),
+  Parse(ParseError),
+  WrongTag(WrongTag),
+  /// The schematic is missing a tag, or a tag has an invalid value.
+  Invalid(String),
+}
+
+impl From<io::Error> for SchematicError {
+  fn from(e: io::Error) -> Self { SchematicError::IO(e) }
+}
+impl From<ParseError> for SchematicError {
+  fn from(e: ParseError) -> Self { SchematicError::Parse(e) }
+}
+impl From<WrongTag> for SchematicError {
+  fn from(e: WrongTag) -> Self { SchematicError::WrongTag(e) }
+}
+
+impl fmt::Display for SchematicError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::IO(e) => write!(f, "{e}"),
+      Self::Parse(e) => write!(f, "{e}"),
+      Self::WrongTag(e) => write!(f, "{e}"),
+      Self::Invalid(msg) => write!(f, "invalid schematic: {msg}"),
+    }
+  }
+}
+
+impl std::error::Error for SchematicError {}
+
+fn invalid(msg: impl Into<String>) -> SchematicError { SchematicError::Invalid(msg.into()) }
+
+/// Returns the given tag in a compound, or an error if it is missing.
+fn get<'a>(c: &'a Compound, key: &str) -> Result<&'a Tag, SchematicError> {
+  c.inner.get(key).ok_or_else(|| invalid(format!("missing tag `{key}`")))
+}
+
+/// A region of blocks, which can be read from and written to schematic files.
+/// The blocks are stored as block ids for the latest version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+  width:          u32,
+  height:         u32,
+  length:         u32,
+  offset:         Pos,
+  blocks:         Vec<u32>,
+  block_entities: Vec<(Pos, Compound)>,
+}
+
+impl Schematic {
+  /// Creates a schematic filled with air. The width is along the X axis, and
+  /// the length is along the Z axis.
+  pub fn new(width: u32, height: u32, length: u32) -> Self {
+    Schematic {
+      width,
+      height,
+      length,
+      offset: Pos::new(0, 0, 0),
+      blocks: vec![0; (width * height * length) as usize],
+      block_entities: vec![],
+    }
+  }
+
+  pub fn width(&self) -> u32 { self.width }
+  pub fn height(&self) -> u32 { self.height }
+  pub fn length(&self) -> u32 { self.length }
+
+  /// Returns the offset of this schematic. When this schematic is pasted, the
+  /// lowest corner will be placed at the paste position plus this offset.
+  pub fn offset(&self) -> Pos { self.offset }
+  /// Sets the offset of this schematic. See [`offset`](Self::offset).
+  pub fn set_offset(&mut self, offset: Pos) { self.offset = offset; }
+
+  fn index(&self, pos: Pos) -> Option<usize> {
+    if pos.x < 0
+      || pos.y < 0
+      || pos.z < 0
+      || pos.x as u32 >= self.width
+      || pos.y as u32 >= self.height
+      || pos.z as u32 >= self.length
+    {
+      None
+    } else {
+      Some(((pos.y as u32 * self.length + pos.z as u32) * self.width + pos.x as u32) as usize)
+    }
+  }
+
+  /// Returns the block id at the given position, relative to the lowest corner
+  /// of this schematic. Returns `None` if the position is outside of the
+  /// schematic.
+  pub fn get(&self, pos: Pos) -> Option<u32> { self.index(pos).map(|i| self.blocks[i]) }
+  /// Sets the block id at the given position, relative to the lowest corner of
+  /// this schematic. Returns `false` if the position is outside of the
+  /// schematic.
+  pub fn set(&mut self, pos: Pos, id: u32) -> bool {
+    match self.index(pos) {
+      Some(i) => {
+        self.blocks[i] = id;
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Returns every position in this schematic, relative to the lowest corner.
+  pub fn positions(&self) -> impl Iterator<Item = Pos> {
+    Pos::new(0, 0, 0).to(Pos::new(
+      self.width as i32 - 1,
+      self.height as i32 - 1,
+      self.length as i32 - 1,
+    ))
+  }
+
+  /// Returns all the block entities in this schematic. These are stored in the
+  /// Sponge v2 format: the compound contains an `Id` tag, along with all of
+  /// the block entity's data. Block entities are kept so that they can be
+  /// written back out, but they are not placed when the schematic is pasted.
+  pub fn block_entities(&self) -> &[(Pos, Compound)] { &self.block_entities }
+  /// Adds a block entity to this schematic. See
+  /// [`block_entities`](Self::block_entities).
+  pub fn add_block_entity(&mut self, pos: Pos, data: Compound) {
+    self.block_entities.push((pos, data));
+  }
+
+  /// Reads a schematic. The format is detected from the data, and the data may
+  /// be gzipped.
+  pub fn read(data: Vec<u8>, types: &block::TypeConverter) -> Result<Self, SchematicError> {
+    let nbt = NBT::deserialize_file(data)?;
+    let root = nbt.compound().ok_or_else(|| invalid("root tag is not a compound"))?;
+    // Sponge v3 schematics wrap everything in a `Schematic` tag.
+    let root = match root.inner.get("Schematic") {
+      Some(tag) => tag.compound()?,
+      None => root,
+    };
+    if root.contains_key("Materials") {
+      legacy::read(root, types)
+    } else if root.contains_key("size") {
+      structure::read(root, types)
+    } else {
+      sponge::read(root, types)
+    }
+  }
+  /// Reads the schematic at the given path. See [`read`](Self::read).
+  pub fn read_file(
+    path: impl AsRef<Path>,
+    types: &block::TypeConverter,
+  ) -> Result<Self, SchematicError> {
+    Self::read(fs::read(path)?, types)
+  }
+
+  /// Writes this schematic in the Sponge `.schem` format. The output is
+  /// gzipped.
+  pub fn write_sponge(&self, types: &block::TypeConverter, version: SpongeVersion) -> Vec<u8> {
+    sponge::write(self, types, version).serialize_file()
+  }
+  /// Writes this schematic in the legacy `.schematic` format. The output is
+  /// gzipped. Blocks that didn't exist in 1.12 will be written as air.
+  pub fn write_legacy(&self, types: &block::TypeConverter) -> Vec<u8> {
+    legacy::write(self, types).serialize_file()
+  }
+  /// Writes this schematic in the vanilla structure format. The output is
+  /// gzipped. Structure voids are left out, and everything else is written.
+  pub fn write_structure(&self, types: &block::TypeConverter) -> Vec<u8> {
+    structure::write(self, types).serialize_file()
+  }
+  /// Writes this schematic to the given path. If the path ends in
+  /// `.schematic`, this uses the legacy format, and if it ends in `.nbt`, this
+  /// writes a vanilla structure. Otherwise, this uses Sponge version 3.
+  pub fn write_file(
+    &self,
+    path: impl AsRef<Path>,
+    types: &block::TypeConverter,
+  ) -> Result<(), SchematicError> {
+    let path = path.as_ref();
+    let data = match path.extension().and_then(|ext| ext.to_str()) {
+      Some("schematic") => self.write_legacy(types),
+      Some("nbt") => self.write_structure(types),
+      _ => self.write_sponge(types, SpongeVersion::V3),
+    };
+    fs::write(path, data)?;
+    Ok(())
+  }
+}
+
+impl World {
+  /// Copies the blocks between the two corners into a schematic. The
+  /// schematic's offset is set so that pasting it at `origin` will place the
+  /// blocks back where they were.
+  pub fn copy_schematic(&self, a: Pos, b: Pos, origin: Pos) -> Result<Schematic, PosError> {
+    let (min, max) = a.min_max(b);
+    self.check_pos(min)?;
+    self.check_pos(max)?;
+    let size = max - min;
+    let mut schem = Schematic::new(size.x as u32 + 1, size.y as u32 + 1, size.z as u32 + 1);
+    schem.offset = min - origin;
+    for pos in min.to(max) {
+      schem.set(pos - min, self.get_block(pos)?.id());
+    }
+    Ok(schem)
+  }
+
+  /// Pastes the given schematic at `pos`. The schematic is mirrored, and then
+  /// rotated around `pos`. If `skip_air` is set, air blocks in the schematic
+  /// won't replace blocks in the world.
+  ///
+  /// Returns the number of blocks that changed. If any of the blocks are
+  /// outside of the world, nothing will be pasted.
+  pub fn paste_schematic(
+    self: &Arc<Self>,
+    schem: &Schematic,
+    pos: Pos,
+    rotation: Rotation,
+    mirror: Mirror,
+    skip_air: bool,
+  ) -> Result<u64, PosError> {
+    self.paste_filtered(schem, pos, rotation, mirror, |id| !skip_air || id != 0)
+  }
+
+  /// Pastes the given schematic at `pos`, like a structure block would.
+  /// Structure voids are never placed, and every other block is only placed
+  /// `integrity` of the time, where `integrity` is between 0 and 1. The blocks
+  /// to place are picked using `seed`, so pasting the same structure with the
+  /// same seed will always place the same blocks.
+  ///
+  /// Returns the number of blocks that changed. If any of the blocks are
+  /// outside of the world, nothing will be pasted.
+  pub fn paste_structure(
+    self: &Arc<Self>,
+    schem: &Schematic,
+    pos: Pos,
+    rotation: Rotation,
+    mirror: Mirror,
+    integrity: f32,
+    seed: u64,
+  ) -> Result<u64, PosError> {
+    let void = self.block_converter().get(block::Kind::StructureVoid).default_type().id();
+    let mut rng = WyhashRng::new(seed);
+    self.paste_filtered(schem, pos, rotation, mirror, |id| {
+      // The RNG is advanced for every block, so that the same blocks are
+      // picked no matter what the schematic contains.
+      let keep = integrity >= 1.0 || rng.gen::<f32>() < integrity;
+      keep && id != void
+    })
+  }
+
+  /// Pastes every block in the schematic where `keep` returns true.
+  fn paste_filtered(
+    self: &Arc<Self>,
+    schem: &Schematic,
+    pos: Pos,
+    rotation: Rotation,
+    mirror: Mirror,
+    mut keep: impl FnMut(u32) -> bool,
+  ) -> Result<u64, PosError> {
+    let types = self.block_converter();
+    let blocks: Vec<_> = schem
+      .positions()
+      .zip(&schem.blocks)
+      .filter(|(_, id)| keep(**id))
+      .map(|(rel, id)| {
+        let ty = types.type_from_id(*id, BlockVersion::latest());
+        (
+          pos + transform::pos(schem.offset + rel, rotation, mirror),
+          transform::ty(ty, rotation, mirror),
+        )
+      })
+      .collect();
+    self.set_blocks(blocks)
+  }
+
+  /// Copies the blocks between the two corners, and stores them as a named
+  /// structure. This replaces any structure with the same name. See
+  /// [`copy_schematic`](Self::copy_schematic) for details on `origin`.
+  pub fn save_structure(
+    &self,
+    name: impl Into<String>,
+    a: Pos,
+    b: Pos,
+    origin: Pos,
+  ) -> Result<(), PosError> {
+    let schem = self.copy_schematic(a, b, origin)?;
+    self.wm.add_structure(name, schem);
+    Ok(())
+  }
+}
+
+impl WorldManager {
+  /// Returns the structure with the given name. Structures are stored in
+  /// memory, and are shared between all worlds. They can be added with
+  /// [`World::save_structure`], [`add_structure`](Self::add_structure), or
+  /// [`load_structure`](Self::load_structure).
+  pub fn structure(&self, name: &str) -> Option<Arc<Schematic>> {
+    self.structures.read().get(name).cloned()
+  }
+  /// Adds a named structure. This replaces any structure with the same name.
+  pub fn add_structure(&self, name: impl Into<String>, schem: Schematic) {
+    self.structures.write().insert(name.into(), Arc::new(schem));
+  }
+  /// Removes the structure with the given name. Returns `false` if there was
+  /// no structure with that name.
+  pub fn remove_structure(&self, name: &str) -> bool {
+    self.structures.write().remove(name).is_some()
+  }
+  /// Returns the names of all the structures, in no particular order.
+  pub fn structure_names(&self) -> Vec<String> { self.structures.read().keys().cloned().collect() }
+
+  /// Reads the schematic or structure file at `path`, and stores it as a named
+  /// structure. See [`Schematic::read`] for the supported formats.
+  pub fn load_structure(
+    &self,
+    name: impl Into<String>,
+    path: impl AsRef<Path>,
+  ) -> Result<(), SchematicError> {
+    let schem = Schematic::read_file(path, self.block_converter())?;
+    self.add_structure(name, schem);
+    Ok(())
+  }
+  /// Writes the named structure to `path`. See [`Schematic::write_file`] for
+  /// how the format is picked. Returns `Ok(false)` if there is no structure
+  /// with that name.
+  pub fn write_structure(
+    &self,
+    name: &str,
+    path: impl AsRef<Path>,
+  ) -> Result<bool, SchematicError> {
+    match self.structure(name) {
+      Some(schem) => {
+        schem.write_file(path, self.block_converter())?;
+        Ok(true)
+      }
+      None => Ok(false),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn positions() {
+    let mut schem = Schematic::new(3, 2, 4);
+    assert_eq!(schem.positions().count(), 24);
+    // The positions should be in the same order as the blocks are stored.
+    for (i, pos) in schem.positions().enumerate() {
+      assert_eq!(schem.index(pos), Some(i));
+    }
+    assert!(schem.set(Pos::new(2, 1, 3), 5));
+    assert!(!schem.set(Pos::new(3, 0, 0), 5));
+    assert_eq!(schem.get(Pos::new(2, 1, 3)), Some(5));
+    assert_eq!(schem.get(Pos::new(0, -1, 0)), None);
+  }
+}
@@ -0,0 +1,188 @@
+//! The Sponge schematic format. See the [specification] for details.
+//!
+//! [specification]: https://github.com/SpongePowered/Schematic-Specification
+
+use super::{get, invalid, Schematic, SchematicError};
+use crate::block;
+use bb_common::{
+  math::Pos,
+  nbt::{Compound, Tag, NBT},
+  util::Buffer,
+  version::BlockVersion,
+};
+use std::collections::HashMap;
+
+/// The data version for 1.20. This is written to every schematic and
+/// structure, as all block ids are stored for the latest version.
+pub(super) const DATA_VERSION: i32 = 3463;
+
+/// A version of the Sponge schematic format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpongeVersion {
+  /// Version 2, which is used by WorldEdit for 1.13 through 1.20.
+  V2,
+  /// Version 3, which is used by WorldEdit for 1.20.4 and later.
+  V3,
+}
+
+pub(super) fn read(
+  root: &Compound,
+  types: &block::TypeConverter,
+) -> Result<Schematic, SchematicError> {
+  let version = get(root, "Version")?.int()?;
+  // Sizes are unsigned shorts.
+  let mut schem = Schematic::new(
+    get(root, "Width")?.short()? as u16 as u32,
+    get(root, "Height")?.short()? as u16 as u32,
+    get(root, "Length")?.short()? as u16 as u32,
+  );
+  if let Some(offset) = root.inner.get("Offset") {
+    match offset.int_arr()?.as_slice() {
+      [x, y, z] => schem.offset = Pos::new(*x, *y, *z),
+      _ => return Err(invalid("`Offset` must have 3 elements")),
+    }
+  }
+  let (palette, data, block_entities) = match version {
+    1 | 2 => (
+      get(root, "Palette")?.compound()?,
+      get(root, "BlockData")?.byte_arr()?,
+      // This was renamed in version 2.
+      root.inner.get("BlockEntities").or_else(|| root.inner.get("TileEntities")),
+    ),
+    3 => {
+      let blocks = get(root, "Blocks")?.compound()?;
+      (
+        get(blocks, "Palette")?.compound()?,
+        get(blocks, "Data")?.byte_arr()?,
+        blocks.inner.get("BlockEntities"),
+      )
+    }
+    v => return Err(invalid(format!("unknown version {v}"))),
+  };
+
+  let mut ids = HashMap::new();
+  for (name, index) in palette {
+    let id = match types.parse_type(name) {
+      Ok(ty) => ty.id(),
+      Err(e) => {
+        warn!("{e} in schematic, replacing it with air");
+        0
+      }
+    };
+    ids.insert(index.int()?, id);
+  }
+  let mut buf = Buffer::new(data);
+  for i in 0..schem.blocks.len() {
+    let index = buf.read_varint().map_err(|_| invalid("not enough block data"))?;
+    schem.blocks[i] =
+      *ids.get(&index).ok_or_else(|| invalid(format!("block {index} is not in the palette")))?;
+  }
+
+  if let Some(block_entities) = block_entities {
+    for tag in block_entities.list()? {
+      let mut data = tag.compound()?.clone();
+      let pos = match data.inner.remove("Pos") {
+        Some(Tag::IntArray(pos)) if pos.len() == 3 => Pos::new(pos[0], pos[1], pos[2]),
+        _ => return Err(invalid("block entity is missing `Pos`")),
+      };
+      // Version 3 moved the block entity's data into a `Data` tag.
+      if let Some(Tag::Compound(inner)) = data.inner.remove("Data") {
+        data.inner.extend(inner);
+      }
+      schem.block_entities.push((pos, data));
+    }
+  }
+  Ok(schem)
+}
+
+pub(super) fn write(
+  schem: &Schematic,
+  types: &block::TypeConverter,
+  version: SpongeVersion,
+) -> NBT {
+  let mut palette = Compound::new();
+  let mut indices = HashMap::new();
+  let mut data = Buffer::new(vec![]);
+  for &id in &schem.blocks {
+    let len = indices.len() as i32;
+    let index = *indices.entry(id).or_insert_with(|| {
+      let ty = types.type_from_id(id, BlockVersion::latest());
+      palette.insert(format!("minecraft:{ty}"), Tag::Int(len));
+      len
+    });
+    data.write_varint(index);
+  }
+  let block_entities = Tag::List(
+    schem
+      .block_entities
+      .iter()
+      .map(|(pos, data)| {
+        let mut out = match version {
+          SpongeVersion::V2 => data.clone(),
+          SpongeVersion::V3 => {
+            let mut inner = data.clone();
+            let mut out = Compound::new();
+            if let Some(id) = inner.inner.remove("Id") {
+              out.insert("Id", id);
+            }
+            out.insert("Data", Tag::Compound(inner));
+            out
+          }
+        };
+        out.insert("Pos", Tag::IntArray(vec![pos.x, pos.y, pos.z]));
+        Tag::Compound(out)
+      })
+      .collect(),
+  );
+
+  let mut root = Compound::new();
+  root.insert("DataVersion", Tag::Int(DATA_VERSION));
+  root.insert("Width", Tag::Short(schem.width as u16 as i16));
+  root.insert("Height", Tag::Short(schem.height as u16 as i16));
+  root.insert("Length", Tag::Short(schem.length as u16 as i16));
+  root.insert("Offset", Tag::IntArray(vec![schem.offset.x, schem.offset.y, schem.offset.z]));
+  match version {
+    SpongeVersion::V2 => {
+      root.insert("Version", Tag::Int(2));
+      root.insert("PaletteMax", Tag::Int(indices.len() as i32));
+      root.insert("Palette", Tag::Compound(palette));
+      root.insert("BlockData", Tag::ByteArr(data.into_inner()));
+      root.insert("BlockEntities", block_entities);
+      NBT::new("Schematic", Tag::Compound(root))
+    }
+    SpongeVersion::V3 => {
+      root.insert("Version", Tag::Int(3));
+      let mut blocks = Compound::new();
+      blocks.insert("Palette", Tag::Compound(palette));
+      blocks.insert("Data", Tag::ByteArr(data.into_inner()));
+      blocks.insert("BlockEntities", block_entities);
+      root.insert("Blocks", Tag::Compound(blocks));
+      NBT::new("", Tag::new_compound(&[("Schematic", Tag::Compound(root))]))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn roundtrip() {
+    let types = block::TypeConverter::new();
+    let stairs = types.parse_type("oak_stairs[facing=west,half=top]").unwrap().id();
+    let stone = types.get(block::Kind::Stone).default_type().id();
+    let mut schem = Schematic::new(2, 3, 4);
+    schem.set_offset(Pos::new(-1, 2, -3));
+    schem.set(Pos::new(1, 2, 3), stairs);
+    schem.set(Pos::new(0, 1, 0), stone);
+    let mut chest = Compound::new();
+    chest.insert("Id", "minecraft:chest");
+    chest.insert("CustomName", "{\"text\":\"hi\"}");
+    schem.add_block_entity(Pos::new(1, 0, 0), chest);
+
+    for version in [SpongeVersion::V2, SpongeVersion::V3] {
+      let data = schem.write_sponge(&types, version);
+      assert_eq!(Schematic::read(data, &types).unwrap(), schem);
+    }
+  }
+}
@@ -0,0 +1,165 @@
+//! The vanilla structure format, which is saved by structure blocks as `.nbt`
+//! files. Positions that aren't listed in the file are structure voids, which
+//! are stored as [`StructureVoid`](block::Kind::StructureVoid) blocks in the
+//! schematic. Entities are not read.
+
+use super::{get, invalid, sponge::DATA_VERSION, Schematic, SchematicError};
+use crate::block;
+use bb_common::{
+  math::Pos,
+  nbt::{Compound, Tag, NBT},
+  version::BlockVersion,
+};
+use std::collections::HashMap;
+
+/// Reads a list of 3 ints, like the `size` and `pos` tags.
+fn read_pos(tag: &Tag) -> Result<Pos, SchematicError> {
+  match tag.list()?.as_slice() {
+    [x, y, z] => Ok(Pos::new(x.int()?, y.int()?, z.int()?)),
+    _ => Err(invalid("positions must have 3 elements")),
+  }
+}
+fn write_pos(pos: Pos) -> Tag { Tag::List(vec![Tag::Int(pos.x), Tag::Int(pos.y), Tag::Int(pos.z)]) }
+
+pub(super) fn read(
+  root: &Compound,
+  types: &block::TypeConverter,
+) -> Result<Schematic, SchematicError> {
+  let size = read_pos(get(root, "size")?)?;
+  if size.x < 0 || size.y < 0 || size.z < 0 {
+    return Err(invalid(format!("invalid size {size}")));
+  }
+  let mut schem = Schematic::new(size.x as u32, size.y as u32, size.z as u32);
+  let void = types.get(block::Kind::StructureVoid).default_type().id();
+  schem.blocks.fill(void);
+
+  // Some structures (like shipwrecks) have multiple palettes, which are picked
+  // at random. We always use the first one.
+  let palette = match root.inner.get("palette") {
+    Some(palette) => palette.list()?,
+    None => match get(root, "palettes")?.list()?.first() {
+      Some(palette) => palette.list()?,
+      None => return Err(invalid("`palettes` is empty")),
+    },
+  };
+  let ids = palette
+    .iter()
+    .map(|tag| {
+      let tag = tag.compound()?;
+      let mut name = get(tag, "Name")?.string()?.to_string();
+      if let Some(props) = tag.inner.get("Properties") {
+        let props: Vec<_> = props
+          .compound()?
+          .iter()
+          .map(|(key, val)| Ok(format!("{key}={}", val.string()?)))
+          .collect::<Result<_, SchematicError>>()?;
+        name = format!("{name}[{}]", props.join(","));
+      }
+      Ok(match types.parse_type(&name) {
+        Ok(ty) => ty.id(),
+        Err(e) => {
+          warn!("{e} in structure, replacing it with air");
+          0
+        }
+      })
+    })
+    .collect::<Result<Vec<_>, SchematicError>>()?;
+
+  for tag in get(root, "blocks")?.list()? {
+    let tag = tag.compound()?;
+    let pos = read_pos(get(tag, "pos")?)?;
+    let state = get(tag, "state")?.int()?;
+    let id = *ids
+      .get(state as usize)
+      .ok_or_else(|| invalid(format!("block {state} is not in the palette")))?;
+    if !schem.set(pos, id) {
+      return Err(invalid(format!("block at {pos} is outside of the structure")));
+    }
+    if let Some(data) = tag.inner.get("nbt") {
+      let mut data = data.compound()?.clone();
+      // Block entities in schematics use `Id`, like the Sponge format.
+      if let Some(id) = data.inner.remove("id") {
+        data.insert("Id", id);
+      }
+      schem.block_entities.push((pos, data));
+    }
+  }
+  Ok(schem)
+}
+
+pub(super) fn write(schem: &Schematic, types: &block::TypeConverter) -> NBT {
+  let void = types.get(block::Kind::StructureVoid).default_type().id();
+  let block_entities: HashMap<_, _> =
+    schem.block_entities.iter().map(|(pos, data)| (*pos, data)).collect();
+  let mut palette = vec![];
+  let mut indices = HashMap::new();
+  let mut blocks = vec![];
+  for (pos, &id) in schem.positions().zip(&schem.blocks) {
+    if id == void {
+      continue;
+    }
+    let len = indices.len() as i32;
+    let state = *indices.entry(id).or_insert_with(|| {
+      let ty = types.type_from_id(id, BlockVersion::latest());
+      let mut entry = Compound::new();
+      entry.insert("Name", format!("minecraft:{}", ty.kind().to_str()));
+      let props = ty.props();
+      if !props.is_empty() {
+        let mut out = Compound::new();
+        for (key, val) in props {
+          out.insert(key, val);
+        }
+        entry.insert("Properties", Tag::Compound(out));
+      }
+      palette.push(Tag::Compound(entry));
+      len
+    });
+    let mut block = Compound::new();
+    block.insert("pos", write_pos(pos));
+    block.insert("state", Tag::Int(state));
+    if let Some(data) = block_entities.get(&pos) {
+      let mut data = (*data).clone();
+      if let Some(id) = data.inner.remove("Id") {
+        data.insert("id", id);
+      }
+      block.insert("nbt", Tag::Compound(data));
+    }
+    blocks.push(Tag::Compound(block));
+  }
+
+  let mut root = Compound::new();
+  root.insert("DataVersion", Tag::Int(DATA_VERSION));
+  root.insert(
+    "size",
+    write_pos(Pos::new(schem.width as i32, schem.height as i32, schem.length as i32)),
+  );
+  root.insert("palette", Tag::List(palette));
+  root.insert("blocks", Tag::List(blocks));
+  root.insert("entities", Tag::List(vec![]));
+  NBT::new("", Tag::Compound(root))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn roundtrip() {
+    let types = block::TypeConverter::new();
+    let stairs = types.parse_type("oak_stairs[facing=west,half=top]").unwrap().id();
+    let stone = types.get(block::Kind::Stone).default_type().id();
+    let void = types.get(block::Kind::StructureVoid).default_type().id();
+    let mut schem = Schematic::new(2, 3, 4);
+    schem.blocks.fill(void);
+    schem.set(Pos::new(1, 2, 3), stairs);
+    schem.set(Pos::new(0, 1, 0), stone);
+    schem.set(Pos::new(1, 0, 0), 0);
+    let mut chest = Compound::new();
+    chest.insert("Id", "minecraft:chest");
+    chest.insert("CustomName", "{\"text\":\"hi\"}");
+    schem.add_block_entity(Pos::new(1, 0, 0), chest);
+
+    let data = schem.write_structure(&types);
+    assert_eq!(Schematic::read(data, &types).unwrap(), schem);
+  }
+}
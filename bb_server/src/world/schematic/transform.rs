@@ -0,0 +1,208 @@
+//! Rotating and mirroring schematics. Positions are mirrored first, and then
+//! rotated around the paste position. Block states are changed to match, so
+//! that stairs, fences, signs, etc. face the right way.
+
+use crate::block;
+use bb_common::math::Pos;
+
+/// A rotation around the Y axis. This is clockwise when looking down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+  None,
+  Clockwise90,
+  Clockwise180,
+  CounterClockwise90,
+}
+
+/// Mirrors a schematic along an axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirror {
+  None,
+  /// Flips the X axis, so that east and west are swapped.
+  X,
+  /// Flips the Z axis, so that north and south are swapped.
+  Z,
+}
+
+impl Rotation {
+  /// Returns a rotation from the given number of degrees. This must be a
+  /// multiple of 90.
+  pub fn from_degrees(degrees: i32) -> Option<Self> {
+    match degrees.rem_euclid(360) {
+      0 => Some(Self::None),
+      90 => Some(Self::Clockwise90),
+      180 => Some(Self::Clockwise180),
+      270 => Some(Self::CounterClockwise90),
+      _ => None,
+    }
+  }
+  /// Returns the number of clockwise quarter turns in this rotation.
+  fn steps(self) -> u32 {
+    match self {
+      Self::None => 0,
+      Self::Clockwise90 => 1,
+      Self::Clockwise180 => 2,
+      Self::CounterClockwise90 => 3,
+    }
+  }
+}
+
+impl Mirror {
+  /// Parses a mirror from its name, which is one of `none`, `x`, or `z`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "none" => Some(Self::None),
+      "x" => Some(Self::X),
+      "z" => Some(Self::Z),
+      _ => None,
+    }
+  }
+}
+
+/// Horizontal directions, in clockwise order.
+const DIRECTIONS: [&str; 4] = ["north", "east", "south", "west"];
+
+/// Mirrors and rotates the given position around the origin.
+pub(super) fn pos(pos: Pos, rotation: Rotation, mirror: Mirror) -> Pos {
+  let (mut x, mut z) = match mirror {
+    Mirror::None => (pos.x, pos.z),
+    Mirror::X => (-pos.x, pos.z),
+    Mirror::Z => (pos.x, -pos.z),
+  };
+  for _ in 0..rotation.steps() {
+    (x, z) = (-z, x);
+  }
+  Pos::new(x, pos.y, z)
+}
+
+/// Mirrors and rotates a horizontal direction, like `north`. Anything else is
+/// returned as is.
+fn dir(d: &str, rotation: Rotation, mirror: Mirror) -> &str {
+  let d = match (mirror, d) {
+    (Mirror::X, "east") => "west",
+    (Mirror::X, "west") => "east",
+    (Mirror::Z, "north") => "south",
+    (Mirror::Z, "south") => "north",
+    _ => d,
+  };
+  match DIRECTIONS.iter().position(|v| *v == d) {
+    Some(i) => DIRECTIONS[(i + rotation.steps() as usize) % 4],
+    None => d,
+  }
+}
+
+/// Mirrors and rotates the value of a `shape` property. Stairs use `left` and
+/// `right`, and rails use directions, like `north_east` or `ascending_west`.
+fn shape(shape: &str, rotation: Rotation, mirror: Mirror) -> String {
+  if shape.ends_with("_left") || shape.ends_with("_right") {
+    return match mirror {
+      Mirror::None => shape.into(),
+      _ if shape.ends_with("_left") => shape.replace("_left", "_right"),
+      _ => shape.replace("_right", "_left"),
+    };
+  }
+  let mut parts: Vec<_> = shape.split('_').map(|part| dir(part, rotation, mirror)).collect();
+  // Curved rails always list north or south first, like `south_east`.
+  if parts.len() == 2 && matches!(parts[1], "north" | "south") {
+    parts.swap(0, 1);
+  }
+  if parts == ["west", "east"] {
+    parts.swap(0, 1);
+  }
+  parts.join("_")
+}
+
+/// Mirrors and rotates the given block type.
+pub(super) fn ty(ty: block::Type, rotation: Rotation, mirror: Mirror) -> block::Type {
+  if rotation == Rotation::None && mirror == Mirror::None {
+    return ty;
+  }
+  let mut out = ty;
+  for (key, val) in ty.props() {
+    let val = match key.as_str() {
+      "facing" => dir(&val, rotation, mirror).into(),
+      "axis" if rotation.steps() % 2 == 1 => match val.as_str() {
+        "x" => "z".into(),
+        "z" => "x".into(),
+        _ => val,
+      },
+      // Signs and banners have 16 rotations, where 0 is south, and 4 is west.
+      "rotation" => {
+        let mut r = val.parse::<u32>().unwrap_or(0);
+        r = match mirror {
+          Mirror::None => r,
+          Mirror::X => (16 - r) % 16,
+          Mirror::Z => (24 - r) % 16,
+        };
+        ((r + rotation.steps() * 4) % 16).to_string()
+      }
+      "shape" => shape(&val, rotation, mirror),
+      "hinge" if mirror != Mirror::None => match val.as_str() {
+        "left" => "right".into(),
+        "right" => "left".into(),
+        _ => val,
+      },
+      _ => val,
+    };
+    // Fences, walls, etc. store their connections in properties named after
+    // each direction.
+    out.set_prop_str(dir(&key, rotation, mirror), &val);
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn positions() {
+    let p = Pos::new(1, 5, -2);
+    assert_eq!(pos(p, Rotation::None, Mirror::None), p);
+    assert_eq!(pos(p, Rotation::Clockwise90, Mirror::None), Pos::new(2, 5, 1));
+    assert_eq!(pos(p, Rotation::Clockwise180, Mirror::None), Pos::new(-1, 5, 2));
+    assert_eq!(pos(p, Rotation::CounterClockwise90, Mirror::None), Pos::new(-2, 5, -1));
+    assert_eq!(pos(p, Rotation::None, Mirror::X), Pos::new(-1, 5, -2));
+    assert_eq!(pos(p, Rotation::None, Mirror::Z), Pos::new(1, 5, 2));
+    // North should rotate to east.
+    assert_eq!(pos(Pos::new(0, 0, -1), Rotation::Clockwise90, Mirror::None), Pos::new(1, 0, 0));
+  }
+
+  #[test]
+  fn directions() {
+    assert_eq!(dir("north", Rotation::Clockwise90, Mirror::None), "east");
+    assert_eq!(dir("west", Rotation::CounterClockwise90, Mirror::None), "south");
+    assert_eq!(dir("east", Rotation::None, Mirror::X), "west");
+    assert_eq!(dir("east", Rotation::Clockwise90, Mirror::Z), "south");
+    assert_eq!(dir("up", Rotation::Clockwise90, Mirror::X), "up");
+    assert_eq!(Rotation::from_degrees(-90), Some(Rotation::CounterClockwise90));
+    assert_eq!(Rotation::from_degrees(45), None);
+  }
+
+  #[test]
+  fn shapes() {
+    assert_eq!(shape("inner_left", Rotation::Clockwise90, Mirror::None), "inner_left");
+    assert_eq!(shape("inner_left", Rotation::None, Mirror::X), "inner_right");
+    assert_eq!(shape("north_south", Rotation::Clockwise90, Mirror::None), "east_west");
+    assert_eq!(shape("east_west", Rotation::Clockwise90, Mirror::None), "north_south");
+    assert_eq!(shape("north_east", Rotation::Clockwise90, Mirror::None), "south_east");
+    assert_eq!(shape("south_west", Rotation::Clockwise90, Mirror::None), "north_west");
+    assert_eq!(shape("ascending_north", Rotation::Clockwise180, Mirror::None), "ascending_south");
+  }
+
+  #[test]
+  fn types() {
+    let types = block::TypeConverter::new();
+    let stairs = types.parse_type("oak_stairs[facing=north,shape=outer_left]").unwrap();
+    let out = ty(stairs, Rotation::Clockwise90, Mirror::Z);
+    assert_eq!(out.prop("facing"), "west");
+    assert_eq!(out.prop("shape"), "outer_right");
+    let fence = types.parse_type("oak_fence[north=true]").unwrap();
+    let out = ty(fence, Rotation::Clockwise90, Mirror::None);
+    assert_eq!(out.prop("north"), false);
+    assert_eq!(out.prop("east"), true);
+    let sign = types.parse_type("oak_sign[rotation=0]").unwrap();
+    assert_eq!(ty(sign, Rotation::Clockwise90, Mirror::None).prop("rotation"), 4);
+    assert_eq!(ty(sign, Rotation::None, Mirror::Z).prop("rotation"), 8);
+  }
+}
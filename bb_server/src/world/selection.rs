@@ -0,0 +1,224 @@
+//! Cuboid selections, and the world-edit style operations that can be applied
+//! to them. Every operation returns a [`ChangeSet`], which can be applied in
+//! reverse to undo the operation.
+
+use super::{EditError, World};
+use crate::block;
+use bb_common::{math::Pos, version::BlockVersion};
+use std::mem;
+
+/// A box of blocks, between two corners (inclusive).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cuboid {
+  min: Pos,
+  max: Pos,
+}
+
+impl Cuboid {
+  /// Creates a cuboid between the two corners. The corners can be in any
+  /// order.
+  pub fn new(a: Pos, b: Pos) -> Self {
+    let (min, max) = a.min_max(b);
+    Cuboid { min, max }
+  }
+
+  pub fn min(&self) -> Pos { self.min }
+  pub fn max(&self) -> Pos { self.max }
+
+  /// Returns the number of blocks in this cuboid.
+  pub fn volume(&self) -> u64 { super::volume(self.min, self.max) }
+
+  /// Returns `true` if the given position is inside this cuboid.
+  pub fn contains(&self, pos: Pos) -> bool {
+    pos.x >= self.min.x
+      && pos.y >= self.min.y
+      && pos.z >= self.min.z
+      && pos.x <= self.max.x
+      && pos.y <= self.max.y
+      && pos.z <= self.max.z
+  }
+
+  /// Returns `true` if this cuboid shares any blocks with `other`.
+  pub fn intersects(&self, other: &Cuboid) -> bool {
+    self.min.x <= other.max.x
+      && self.max.x >= other.min.x
+      && self.min.y <= other.max.y
+      && self.max.y >= other.min.y
+      && self.min.z <= other.max.z
+      && self.max.z >= other.min.z
+  }
+
+  /// Returns `true` if the given position is on one of the four vertical sides
+  /// of this cuboid.
+  pub fn is_wall(&self, pos: Pos) -> bool {
+    self.contains(pos)
+      && (pos.x == self.min.x || pos.x == self.max.x || pos.z == self.min.z || pos.z == self.max.z)
+  }
+  /// Returns `true` if the given position is on any of the six sides of this
+  /// cuboid.
+  pub fn is_face(&self, pos: Pos) -> bool {
+    self.is_wall(pos) || (self.contains(pos) && (pos.y == self.min.y || pos.y == self.max.y))
+  }
+
+  /// Moves this cuboid by the given offset.
+  pub fn shift(self, offset: Pos) -> Self {
+    Cuboid { min: self.min + offset, max: self.max + offset }
+  }
+  /// Grows this cuboid by `amount` blocks in every direction. If `amount` is
+  /// negative, the cuboid will shrink, but it will never be smaller than a
+  /// single block.
+  pub fn expand(self, amount: i32) -> Self {
+    let grow = |min: i32, max: i32| {
+      if max - min + 2 * amount < 0 {
+        let mid = min + (max - min) / 2;
+        (mid, mid)
+      } else {
+        (min - amount, max + amount)
+      }
+    };
+    let (min_x, max_x) = grow(self.min.x, self.max.x);
+    let (min_y, max_y) = grow(self.min.y, self.max.y);
+    let (min_z, max_z) = grow(self.min.z, self.max.z);
+    Cuboid { min: Pos::new(min_x, min_y, min_z), max: Pos::new(max_x, max_y, max_z) }
+  }
+
+  /// Iterates through every position in this cuboid.
+  pub fn iter(&self) -> impl Iterator<Item = Pos> { self.min.to(self.max) }
+}
+
+/// An operation on every block in a [`Cuboid`].
+#[derive(Debug, Clone, Copy)]
+pub enum RegionEdit<'a> {
+  /// Sets every block to the given type.
+  Set(block::Type<'a>),
+  /// Replaces every block of the given kind.
+  Replace(block::Kind, block::Type<'a>),
+  /// Sets the four vertical sides, and leaves everything else alone.
+  Walls(block::Type<'a>),
+  /// Sets all six sides, and replaces the inside with air.
+  Hollow(block::Type<'a>),
+}
+
+/// A single block change, storing the block ids before and after the change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockChange {
+  pub pos: Pos,
+  pub old: u32,
+  pub new: u32,
+}
+
+/// A list of block changes. Applying the [`inverse`](Self::inverse) of a
+/// change set will undo it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeSet {
+  changes: Vec<BlockChange>,
+}
+
+impl ChangeSet {
+  pub fn new() -> Self { ChangeSet::default() }
+
+  /// Adds a change to this set. Changes that don't change anything are
+  /// skipped.
+  pub fn push(&mut self, pos: Pos, old: u32, new: u32) {
+    if old != new {
+      self.changes.push(BlockChange { pos, old, new });
+    }
+  }
+
+  pub fn len(&self) -> usize { self.changes.len() }
+  pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+  pub fn iter(&self) -> impl Iterator<Item = &BlockChange> { self.changes.iter() }
+  /// Returns the approximate amount of memory used by this change set, in
+  /// bytes.
+  pub fn memory(&self) -> usize { self.changes.len() * mem::size_of::<BlockChange>() }
+
+  /// Returns a change set that will undo this one. The changes are reversed,
+  /// so that a block changed multiple times ends up with its original type.
+  pub fn inverse(&self) -> ChangeSet {
+    ChangeSet {
+      changes: self
+        .changes
+        .iter()
+        .rev()
+        .map(|c| BlockChange { pos: c.pos, old: c.new, new: c.old })
+        .collect(),
+    }
+  }
+}
+
+impl World {
+  /// Applies the given edit to every block in `region`, and returns the blocks
+  /// that changed. Like [`set_blocks`](Self::set_blocks), block update handlers
+  /// are not called for the changed blocks.
+  pub fn edit_region(&self, region: Cuboid, edit: RegionEdit) -> Result<ChangeSet, EditError> {
+    self.check_edit(region.min, region.max)?;
+    let air = self.block_converter().get(block::Kind::Air).default_type();
+    let mut changes = ChangeSet::new();
+    for pos in region.iter() {
+      let old = self.get_block(pos)?;
+      let new = match edit {
+        RegionEdit::Set(ty) => Some(ty),
+        RegionEdit::Replace(kind, ty) => (old.kind() == kind).then_some(ty),
+        RegionEdit::Walls(ty) => region.is_wall(pos).then_some(ty),
+        RegionEdit::Hollow(ty) => Some(if region.is_face(pos) { ty } else { air }),
+      };
+      if let Some(new) = new {
+        changes.push(pos, old.id(), new.id());
+      }
+    }
+    self.apply_changes(&changes)?;
+    Ok(changes)
+  }
+
+  /// Sets every block in the change set to its new type. Returns the number of
+  /// blocks that changed.
+  pub fn apply_changes(&self, changes: &ChangeSet) -> Result<u64, EditError> {
+    if self.is_locked() {
+      return Err(EditError::Locked);
+    }
+    let types = self.block_converter();
+    let changed = self.set_blocks(
+      changes.iter().map(|c| (c.pos, types.type_from_id(c.new, BlockVersion::latest()))),
+    )?;
+    Ok(changed)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cuboid() {
+    let c = Cuboid::new(Pos::new(2, 5, -1), Pos::new(0, 3, 1));
+    assert_eq!(c.min(), Pos::new(0, 3, -1));
+    assert_eq!(c.max(), Pos::new(2, 5, 1));
+    assert_eq!(c.volume(), 27);
+    assert_eq!(c.iter().count(), 27);
+    assert!(c.is_wall(Pos::new(0, 4, 0)));
+    assert!(!c.is_wall(Pos::new(1, 5, 0)));
+    assert!(c.is_face(Pos::new(1, 5, 0)));
+    assert!(!c.is_face(Pos::new(1, 4, 0)));
+    assert_eq!(c.iter().filter(|p| c.is_face(*p)).count(), 26);
+    assert_eq!(c.expand(-5), Cuboid::new(Pos::new(1, 4, 0), Pos::new(1, 4, 0)));
+    assert_eq!(c.shift(Pos::new(1, 0, 0)).min(), Pos::new(1, 3, -1));
+  }
+
+  #[test]
+  fn inverse() {
+    let mut changes = ChangeSet::new();
+    changes.push(Pos::new(0, 0, 0), 0, 1);
+    changes.push(Pos::new(0, 0, 0), 1, 2);
+    changes.push(Pos::new(1, 0, 0), 3, 3);
+    assert_eq!(changes.len(), 2);
+    let inv = changes.inverse();
+    assert_eq!(
+      inv.iter().copied().collect::<Vec<_>>(),
+      vec![
+        BlockChange { pos: Pos::new(0, 0, 0), old: 2, new: 1 },
+        BlockChange { pos: Pos::new(0, 0, 0), old: 1, new: 0 },
+      ]
+    );
+    assert_eq!(inv.inverse(), changes);
+  }
+}
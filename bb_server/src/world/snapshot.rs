@@ -0,0 +1,157 @@
+//! Chunk snapshots. A snapshot stores a copy of a set of chunks, which can be
+//! restored later. Restoring replaces each chunk's sections all at once,
+//! instead of setting blocks one at a time, so resetting something like a
+//! minigame arena is cheap, even if most of the blocks have changed.
+
+use super::World;
+use crate::{
+  block,
+  block::light::{BlockLightChunk, SkyLightChunk},
+  net::broadcast::broadcast,
+};
+use bb_common::{
+  chunk::{paletted::Section as PalettedSection, Chunk, Section},
+  math::{ChunkPos, Pos, PosError, RelPos},
+};
+use bb_transfer::{MessageReader, MessageWriter};
+use std::{collections::HashMap, mem, sync::Arc};
+
+/// The largest bits per entry of a chunk section. This must match the chunks
+/// created in [`BlockData`](super::BlockData).
+const MAX_BPE: u8 = 15;
+
+/// A copy of some chunks in a world. See [`World::snapshot_chunks`].
+pub struct ChunkSnapshot {
+  chunks: HashMap<ChunkPos, SavedChunk>,
+}
+
+struct SavedChunk {
+  sections:    Vec<Option<PalettedSection>>,
+  sky_light:   Option<SkyLightChunk>,
+  block_light: BlockLightChunk,
+  /// Tile entities are mutable, so they are serialized instead of being
+  /// cloned.
+  tes:         Vec<(RelPos, block::Kind, Vec<u8>)>,
+}
+
+impl ChunkSnapshot {
+  /// Returns the positions of all the chunks in this snapshot, in no
+  /// particular order.
+  pub fn chunks(&self) -> impl Iterator<Item = ChunkPos> + '_ { self.chunks.keys().copied() }
+  /// Returns `true` if the given chunk is in this snapshot.
+  pub fn contains(&self, pos: ChunkPos) -> bool { self.chunks.contains_key(&pos) }
+  /// Returns the number of chunks in this snapshot.
+  pub fn len(&self) -> usize { self.chunks.len() }
+  /// Returns `true` if this snapshot has no chunks.
+  pub fn is_empty(&self) -> bool { self.chunks.is_empty() }
+}
+
+impl World {
+  /// Copies the given chunks into a snapshot. Chunks that aren't loaded will
+  /// be loaded or generated first. Entities are not saved.
+  pub fn snapshot_chunks(&self, chunks: impl IntoIterator<Item = ChunkPos>) -> ChunkSnapshot {
+    let mut out = HashMap::new();
+    for pos in chunks {
+      let saved = self.chunk(pos, |c| SavedChunk {
+        sections:    c.inner().sections().cloned().collect(),
+        sky_light:   c.sky_light.clone(),
+        block_light: c.block_light.clone(),
+        tes:         c
+          .tes_with_pos()
+          .filter_map(|(p, te)| {
+            let mut data = vec![];
+            te.save(&mut MessageWriter::new(&mut data)).ok()?;
+            Some((p, c.get_kind(p).ok()?, data))
+          })
+          .collect(),
+      });
+      out.insert(pos, saved);
+    }
+    ChunkSnapshot { chunks: out }
+  }
+
+  /// Copies every chunk that contains part of the region between `a` and `b`.
+  /// See [`snapshot_chunks`](Self::snapshot_chunks).
+  pub fn snapshot_region(&self, a: Pos, b: Pos) -> Result<ChunkSnapshot, PosError> {
+    let (min, max) = a.min_max(b);
+    self.check_pos(min)?;
+    self.check_pos(max)?;
+    Ok(self.snapshot_chunks(min.chunk().to(max.chunk())))
+  }
+
+  /// Restores all the chunks in the snapshot, and sends the new chunks to
+  /// everyone in view. The snapshot is not changed, so it can be restored any
+  /// number of times.
+  ///
+  /// Each chunk is only locked long enough to swap in the saved sections, so
+  /// this will not block the tick loop for long. Returns `false` if the world
+  /// is locked, in which case nothing is changed.
+  pub fn restore_snapshot(&self, snapshot: &ChunkSnapshot) -> bool {
+    if self.is_locked() {
+      return false;
+    }
+    for (&pos, saved) in &snapshot.chunks {
+      // Copy everything before locking the chunk.
+      let mut sections = saved.sections.clone();
+      let mut sky_light = saved.sky_light.clone();
+      let mut block_light = saved.block_light.clone();
+      let tes: Vec<_> = saved
+        .tes
+        .iter()
+        .filter_map(|(p, kind, data)| {
+          let behaviors = self.world_manager().block_behaviors();
+          match behaviors.call(*kind, |b| b.load_te(&mut MessageReader::new(data))) {
+            Some(Ok(te)) => Some((*p, te)),
+            _ => None,
+          }
+        })
+        .collect();
+
+      let (old, len) = self.chunk(pos, |mut c| {
+        // Any section that was added after the snapshot is replaced with air,
+        // so that every section can be sent in a single partial chunk.
+        let len = c.inner().sections().len();
+        if sections.len() < len {
+          sections.resize_with(len, || None);
+        }
+        for (new, old) in sections.iter_mut().zip(c.inner().sections()) {
+          if new.is_none() && old.is_some() {
+            *new = Some(PalettedSection::new(MAX_BPE));
+          }
+        }
+        let len = sections.len();
+        let old = mem::replace(c.inner_mut(), Chunk::from_sections(sections, MAX_BPE));
+        mem::swap(&mut c.sky_light, &mut sky_light);
+        mem::swap(&mut c.block_light, &mut block_light);
+        c.tes_mut().clear();
+        for (p, te) in tes {
+          // The position was valid when the snapshot was taken.
+          c.set_te(p, te).unwrap();
+        }
+        (old, len)
+      });
+      // The old chunk is dropped here, so that freeing it doesn't happen while
+      // the chunk is locked.
+      drop(old);
+      if len > 0 {
+        let serialized = self.serialize_partial_chunk(pos, 0, len as u32 - 1);
+        broadcast(self.players().iter().in_view(pos), serialized);
+      }
+    }
+    true
+  }
+
+  /// Stores a snapshot with the given name. This replaces any snapshot with
+  /// the same name. Named snapshots are used by plugins, which can't hold
+  /// onto a [`ChunkSnapshot`] directly.
+  pub fn add_snapshot(&self, name: impl Into<String>, snapshot: ChunkSnapshot) {
+    self.snapshots.lock().insert(name.into(), Arc::new(snapshot));
+  }
+  /// Returns the snapshot with the given name.
+  pub fn snapshot(&self, name: &str) -> Option<Arc<ChunkSnapshot>> {
+    self.snapshots.lock().get(name).cloned()
+  }
+  /// Removes the snapshot with the given name. Returns `false` if there was no
+  /// snapshot with that name.
+  pub fn remove_snapshot(&self, name: &str) -> bool { self.snapshots.lock().remove(name).is_some() }
+}
@@ -0,0 +1,265 @@
+//! Natural mob spawning. Every tick, each player has a chance to spawn a pack
+//! of mobs in a random chunk near them. Each spawn category has its own cap,
+//! which is counted per player. Any naturally spawned mobs that get too far
+//! away from every player will be despawned.
+
+use super::{MultiChunk, RngStream, World};
+use crate::{
+  block,
+  entity::{Category, Type},
+  player::Player,
+};
+use bb_common::math::{ChunkPos, FPos, Pos, RelPos};
+use rand::Rng;
+use std::{collections::HashMap, sync::Arc};
+
+include!(concat!(env!("OUT_DIR"), "/entity/spawn.rs"));
+
+/// A single entry in a biome's spawn list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpawnEntry {
+  pub ty:     Type,
+  /// The weight of this entry, relative to all the other entries in the same
+  /// category.
+  pub weight: u32,
+  /// The minimum size of a pack of this entity.
+  pub min:    u32,
+  /// The maximum size of a pack of this entity.
+  pub max:    u32,
+}
+
+/// The natural spawns for a single biome.
+#[derive(Debug, Clone, Copy)]
+pub struct BiomeSpawns {
+  pub biome:  &'static str,
+  pub spawns: &'static [SpawnEntry],
+}
+
+/// Mobs will never spawn closer than this many blocks to a player.
+const MIN_SPAWN_DIST: f64 = 24.0;
+/// Mobs that can despawn will always despawn past this many blocks from every
+/// player.
+const DESPAWN_DIST: f64 = 128.0;
+/// Mobs that can despawn have a random chance to despawn past this many blocks
+/// from every player.
+const RANDOM_DESPAWN_DIST: f64 = 32.0;
+/// The maximum distance in chunks that mobs will spawn from a player. If a
+/// player's view distance is lower than this, their view distance is used
+/// instead.
+const SIMULATION_DISTANCE: i32 = 8;
+/// Creatures (like cows and sheep) only try to spawn this often, in ticks.
+const CREATURE_INTERVAL: u32 = 400;
+
+/// Returns the natural spawns for the given biome. Unknown biomes use the
+/// plains spawn list.
+pub fn spawn_list(biome: &str) -> &'static [SpawnEntry] {
+  let lists = generate_spawn_lists();
+  lists.iter().find(|l| l.biome == biome).unwrap_or(&lists[0]).spawns
+}
+
+/// Picks a random entry from `spawns` within the given category, using the
+/// weight of each entry.
+fn pick_entry(
+  world: &World,
+  spawns: &[SpawnEntry],
+  category: Category,
+  rng: &mut impl Rng,
+) -> Option<SpawnEntry> {
+  let conv = world.entity_converter();
+  let entries: Vec<_> =
+    spawns.iter().filter(|e| conv.get_data(e.ty).category == category).collect();
+  let total: u32 = entries.iter().map(|e| e.weight).sum();
+  if total == 0 {
+    return None;
+  }
+  let mut choice = rng.gen_range(0..total);
+  for e in entries {
+    if choice < e.weight {
+      return Some(*e);
+    }
+    choice -= e.weight;
+  }
+  None
+}
+
+impl World {
+  /// Spawns and despawns mobs around all players. This is called every tick
+  /// from the world tick loop.
+  pub(super) fn tick_spawning(self: &Arc<Self>, tick: u32) {
+    if !self.config().mob_spawning {
+      return;
+    }
+    let players: Vec<Arc<Player>> = self.players().values().cloned().collect();
+    let mut rng = self.rng(RngStream::Spawning);
+    self.despawn_far_mobs(&players, &mut rng);
+
+    let counts = self.count_mobs(&players);
+    for (i, player) in players.iter().enumerate() {
+      for category in Category::NATURAL {
+        if category == Category::Creature && tick % CREATURE_INTERVAL != 0 {
+          continue;
+        }
+        let count = counts[i].get(&category).copied().unwrap_or(0);
+        if count < category.cap() {
+          self.spawn_pack(player, category, &mut rng);
+        }
+      }
+    }
+  }
+
+  /// Returns the number of naturally spawned mobs in each category, for each
+  /// player. A mob counts towards every player that is close enough to it.
+  fn count_mobs(&self, players: &[Arc<Player>]) -> Vec<HashMap<Category, u32>> {
+    let mut counts = vec![HashMap::new(); players.len()];
+    let natural = self.natural_mobs.lock();
+    let entities = self.entities.read();
+    for eid in natural.iter() {
+      let ent = match entities.get(eid).and_then(|e| e.as_entity()) {
+        Some(e) => e,
+        None => continue,
+      };
+      let pos = ent.fpos();
+      let category = self.entity_converter().get_data(ent.ty()).category;
+      for (i, p) in players.iter().enumerate() {
+        if p.pos().dist(pos) < DESPAWN_DIST {
+          *counts[i].entry(category).or_insert(0) += 1;
+        }
+      }
+    }
+    counts
+  }
+
+  /// Removes any naturally spawned mobs that are too far from every player.
+  fn despawn_far_mobs(self: &Arc<Self>, players: &[Arc<Player>], rng: &mut impl Rng) {
+    let mut remove = vec![];
+    {
+      let mut natural = self.natural_mobs.lock();
+      let entities = self.entities.read();
+      // Mobs that have died are no longer in the world.
+      natural.retain(|eid| entities.contains_key(eid));
+      for &eid in natural.iter() {
+        let ent = match entities.get(&eid).and_then(|e| e.as_entity()) {
+          Some(e) => e,
+          None => continue,
+        };
+        if !self.entity_converter().get_data(ent.ty()).category.despawns() {
+          continue;
+        }
+        let pos = ent.fpos();
+        let dist = players.iter().map(|p| p.pos().dist(pos)).fold(f64::INFINITY, f64::min);
+        if dist > DESPAWN_DIST || (dist > RANDOM_DESPAWN_DIST && rng.gen_range(0..800) == 0) {
+          remove.push(eid);
+        }
+      }
+      for eid in &remove {
+        natural.remove(eid);
+      }
+    }
+    for eid in remove {
+      self.remove_entity(eid);
+    }
+  }
+
+  /// Tries to spawn a pack of mobs in the given category, in a random chunk
+  /// near the given player.
+  fn spawn_pack(self: &Arc<Self>, player: &Player, category: Category, rng: &mut impl Rng) {
+    let dist = SIMULATION_DISTANCE.min(player.view_distance() as i32);
+    let center = player.pos().chunk();
+    let chunk = ChunkPos::new(
+      center.x() + rng.gen_range(-dist..=dist),
+      center.z() + rng.gen_range(-dist..=dist),
+    );
+    // We don't want to generate new chunks just to spawn mobs in them.
+    if !self.has_loaded_chunk(chunk) {
+      return;
+    }
+    let column = chunk.block() + Pos::new(rng.gen_range(0..16), 0, rng.gen_range(0..16));
+    let biome = match self.gen.biome_name_at(column) {
+      Some(b) => b,
+      None => return,
+    };
+    let entry = match pick_entry(self, spawn_list(biome), category, rng) {
+      Some(e) => e,
+      None => return,
+    };
+    let count = rng.gen_range(entry.min..=entry.max);
+    let mut spawned = vec![];
+    for _ in 0..count {
+      let pos = column + Pos::new(rng.gen_range(-4..=4), 0, rng.gen_range(-4..=4));
+      if pos.chunk() != chunk {
+        continue;
+      }
+      let pos = match self.chunk(chunk, |c| find_spawn(&c, pos.chunk_rel(), category, rng)) {
+        Some(y) => pos.with_y(y),
+        None => continue,
+      };
+      let fpos = FPos::new(pos.x() as f64 + 0.5, pos.y() as f64, pos.z() as f64 + 0.5);
+      let too_close = self.players().values().any(|p| p.pos().dist(fpos) < MIN_SPAWN_DIST);
+      if too_close {
+        continue;
+      }
+      spawned.push(self.summon(entry.ty, fpos));
+    }
+    self.natural_mobs.lock().extend(spawned);
+  }
+}
+
+/// Finds a Y level to spawn a mob of the given category in the given column.
+/// Returns `None` if there is no valid place to spawn.
+///
+/// A mob can spawn on any solid block, as long as the two blocks above it have
+/// no collision. Creatures need to spawn on grass under the open sky, and
+/// monsters need to spawn in the dark.
+fn find_spawn(c: &MultiChunk, pos: RelPos, category: Category, rng: &mut impl Rng) -> Option<i32> {
+  let conv = c.type_converter();
+  let min_y = c.min_y();
+  let max_y = min_y + c.height() as i32 - 2;
+  let solid = |y: i32| {
+    c.get_kind(pos.with_y(y))
+      .map(|k| conv.get(k).bounding_box == block::BoundingBoxKind::Block)
+      .unwrap_or(false)
+  };
+  let water = |y: i32| c.get_kind(pos.with_y(y)).map(|k| k == block::Kind::Water).unwrap_or(false);
+
+  // Find the highest solid block in this column.
+  let surface = (min_y..=max_y).rev().find(|&y| solid(y))?;
+  let y = match category {
+    Category::Creature => surface + 1,
+    Category::WaterCreature | Category::WaterAmbient => {
+      let y = rng.gen_range(min_y + 1..=max_y);
+      return if water(y) && water(y + 1) { Some(y) } else { None };
+    }
+    _ => rng.gen_range(min_y + 1..=surface + 1),
+  };
+  if !solid(y - 1) || solid(y) || solid(y + 1) || water(y) {
+    return None;
+  }
+  let sky = y > surface;
+  match category {
+    Category::Creature => {
+      let below = c.get_kind(pos.with_y(y - 1)).ok()?;
+      (below == block::Kind::GrassBlock && sky).then_some(y)
+    }
+    Category::Monster => (!sky && c.block_light_at(pos.with_y(y)) == 0).then_some(y),
+    // Bats and other ambient mobs spawn in caves.
+    Category::Ambient => (!sky && c.block_light_at(pos.with_y(y)) <= 3).then_some(y),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn spawn_lists() {
+    assert!(spawn_list("desert").iter().any(|e| e.ty == Type::Husk));
+    assert!(!spawn_list("plains").iter().any(|e| e.ty == Type::Husk));
+    assert_eq!(spawn_list("not_a_biome"), spawn_list("plains"));
+    for list in generate_spawn_lists() {
+      for e in list.spawns {
+        assert!(e.min <= e.max, "invalid pack size for {:?}", e.ty);
+      }
+    }
+  }
+}
@@ -0,0 +1,162 @@
+//! Mob spawners. Spawners are ticked whenever a player is nearby, and will
+//! spawn a few entities around them each time their delay runs out.
+
+use super::{RngStream, World};
+use crate::{
+  block,
+  block::{SpawnerData, SpawnerTE, TileEntity},
+  entity,
+};
+use bb_common::math::{FPos, Pos, PosError};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Spawners are only ticked in chunks this close to a player. Spawners with a
+/// player range larger than this many chunks will only be active once a player
+/// is this close to them.
+const SPAWNER_CHUNK_RADIUS: u32 = 2;
+
+impl World {
+  /// Sets the block at `pos` to a mob spawner with the given settings. If the
+  /// block is already a spawner, its settings will be replaced.
+  ///
+  /// Returns `false` if the world is locked.
+  pub fn set_spawner(self: &Arc<Self>, pos: Pos, data: SpawnerData) -> Result<bool, PosError> {
+    if self.get_kind(pos)? != block::Kind::Spawner && !self.set_kind(pos, block::Kind::Spawner)? {
+      return Ok(false);
+    }
+    self.chunk(pos.chunk(), |mut c| c.set_te(pos.chunk_rel(), Arc::new(SpawnerTE::new(data))))?;
+    self.send_te_update(pos);
+    Ok(true)
+  }
+
+  /// Returns the settings of the spawner at `pos`. If there is no spawner at
+  /// `pos`, this returns `None`.
+  pub fn spawner(&self, pos: Pos) -> Result<Option<SpawnerData>, PosError> {
+    Ok(self.spawner_te(pos)?.map(|te| spawner_ref(&te).data().clone()))
+  }
+
+  /// Modifies the spawner at `pos`, and sends the changes to all nearby
+  /// players. Returns `false` if there is no spawner at `pos`.
+  pub fn modify_spawner(
+    self: &Arc<Self>,
+    pos: Pos,
+    f: impl FnOnce(&mut SpawnerData),
+  ) -> Result<bool, PosError> {
+    let te = match self.spawner_te(pos)? {
+      Some(te) => te,
+      None => return Ok(false),
+    };
+    f(&mut spawner_ref(&te).data());
+    self.send_te_update(pos);
+    Ok(true)
+  }
+
+  fn spawner_te(&self, pos: Pos) -> Result<Option<Arc<dyn TileEntity>>, PosError> {
+    let te = self.chunk(pos.chunk(), |c| c.get_te(pos.chunk_rel()))?;
+    Ok(te.filter(|te| te.as_any().is::<SpawnerTE>()))
+  }
+
+  /// Ticks all the spawners near players. This is called every tick from the
+  /// world tick loop.
+  pub(super) fn tick_spawners(self: &Arc<Self>) {
+    let players: Vec<FPos> = self.players().values().map(|p| p.pos()).collect();
+    for (pos, te) in self.tes_near_players(SPAWNER_CHUNK_RADIUS, |te| te.as_any().is::<SpawnerTE>())
+    {
+      self.tick_spawner(pos, spawner_ref(&te), &players);
+    }
+  }
+
+  fn tick_spawner(self: &Arc<Self>, pos: Pos, te: &SpawnerTE, players: &[FPos]) {
+    // We copy the data out, so that we don't hold the spawner lock while
+    // accessing chunks. Serializing a chunk locks the chunk, and then the
+    // spawner, so doing the opposite here could deadlock.
+    let data = te.data().clone();
+    let center = pos.center();
+    if data.entity.is_empty() || !players.iter().any(|p| p.dist(center) <= data.player_range.into())
+    {
+      return;
+    }
+    if data.delay > 0 {
+      te.data().delay -= 1;
+      return;
+    }
+    let mut rng = self.rng(RngStream::Spawning);
+    te.data().delay = rng.gen_range(data.min_delay..=data.max_delay.max(data.min_delay));
+    let ty = match data.entity.parse::<entity::Type>() {
+      Ok(ty) => ty,
+      Err(_) => return,
+    };
+
+    let range = data.spawn_range as i32;
+    let mut nearby = self.count_nearby(ty, center, (range * 2 + 1) as f64);
+    for _ in 0..data.spawn_count {
+      if nearby >= data.max_nearby as u32 {
+        break;
+      }
+      let spawn_pos = pos
+        + Pos::new(
+          rng.gen_range(-range..=range),
+          rng.gen_range(-1..=1),
+          rng.gen_range(-range..=range),
+        );
+      if !self.is_empty(spawn_pos) || !self.is_empty(spawn_pos.add_y(1)) {
+        continue;
+      }
+      self.summon(
+        ty,
+        FPos::new(spawn_pos.x() as f64 + 0.5, spawn_pos.y() as f64, spawn_pos.z() as f64 + 0.5),
+      );
+      nearby += 1;
+    }
+  }
+
+  /// Returns the number of entities of the given type within `dist` of `pos`.
+  fn count_nearby(&self, ty: entity::Type, pos: FPos, dist: f64) -> u32 {
+    self
+      .entities
+      .read()
+      .values()
+      .filter_map(|e| e.as_entity())
+      .filter(|e| e.ty() == ty && e.fpos().dist(pos) <= dist)
+      .count() as u32
+  }
+
+  /// Returns `true` if the block at `pos` has no collision.
+  fn is_empty(&self, pos: Pos) -> bool {
+    self
+      .get_kind(pos)
+      .map(|k| self.block_converter().get(k).bounding_box == block::BoundingBoxKind::Empty)
+      .unwrap_or(false)
+  }
+}
+
+/// Downcasts the given tile entity to a spawner. This should only be called
+/// after checking that the tile entity is a spawner.
+fn spawner_ref(te: &Arc<dyn TileEntity>) -> &SpawnerTE {
+  te.as_any().downcast_ref().expect("tile entity is not a spawner")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bb_transfer::{MessageRead, MessageReader, MessageWrite, MessageWriter};
+
+  #[test]
+  fn spawner_data() {
+    let data = SpawnerData { entity: "zombie".into(), delay: 5, ..Default::default() };
+    let te = SpawnerTE::new(data.clone());
+    let mut buf = vec![];
+    te.save(&mut MessageWriter::new(&mut buf)).unwrap();
+    let mut r = MessageReader::new(&buf);
+    assert_eq!(SpawnerData::read(&mut r).unwrap(), data);
+    assert_eq!(
+      te.client_data(),
+      Some(bb_common::net::cb::BlockEntityKind::Spawner { entity: "zombie".into(), delay: 5 })
+    );
+
+    let mut buf = vec![];
+    SpawnerData::default().write(&mut MessageWriter::new(&mut buf)).unwrap();
+    assert!(!buf.is_empty());
+  }
+}
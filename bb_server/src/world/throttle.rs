@@ -0,0 +1,120 @@
+//! Adaptive simulation throttling. If `adaptive-throttle` is set in the world
+//! config, the world watches the average tick time. When it gets close to the
+//! tick budget, optional work (natural mob spawning, and ticking mobs) runs
+//! less often. Once the load drops, everything runs every tick again.
+//!
+//! The current throttle level is shown in `/tps`.
+
+use std::{
+  sync::atomic::{AtomicU32, AtomicU8, Ordering},
+  time::Duration,
+};
+
+/// The highest throttle level. At this level, throttled work runs once every
+/// 8 ticks.
+pub const MAX_LEVEL: u8 = 3;
+/// If the average tick takes more than this fraction of the budget, the level
+/// goes up.
+const RAISE_AT: f64 = 0.8;
+/// If the average tick takes less than this fraction of the budget, the level
+/// goes down.
+const LOWER_AT: f64 = 0.5;
+/// The number of ticks to wait after changing the level, before changing it
+/// again. Tick times are averaged over 100 ticks, so this gives the average
+/// time to catch up with the new level.
+const COOLDOWN: u32 = 100;
+
+/// The throttle level of a world. See the [module docs](self).
+#[derive(Debug)]
+pub struct Throttle {
+  enabled:  bool,
+  level:    AtomicU8,
+  /// The number of ticks until the level can change again.
+  cooldown: AtomicU32,
+}
+
+impl Throttle {
+  /// Creates a throttle at level 0. If `enabled` is `false`, the level will
+  /// never change.
+  pub fn new(enabled: bool) -> Self {
+    Throttle { enabled, level: AtomicU8::new(0), cooldown: AtomicU32::new(0) }
+  }
+
+  /// Returns `true` if adaptive throttling is enabled for this world.
+  pub fn enabled(&self) -> bool { self.enabled }
+  /// Returns the current throttle level, between 0 and [`MAX_LEVEL`].
+  pub fn level(&self) -> u8 { self.level.load(Ordering::Relaxed) }
+  /// Returns how often throttled work runs, in ticks. This is 1 at level 0,
+  /// and doubles with each level.
+  pub fn interval(&self) -> u32 { 1 << self.level() }
+  /// Returns `true` if throttled work should run on the given tick.
+  pub fn runs_on(&self, tick: u32) -> bool { tick % self.interval() == 0 }
+
+  /// Updates the level from the average tick time. `budget` is how long a
+  /// single tick is allowed to take. This should be called once per tick.
+  pub(super) fn update(&self, average: Duration, budget: Duration) {
+    if !self.enabled {
+      return;
+    }
+    if self
+      .cooldown
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| c.checked_sub(1))
+      .is_ok()
+    {
+      return;
+    }
+    let load = average.as_secs_f64() / budget.as_secs_f64();
+    let level = self.level();
+    let new = if load > RAISE_AT && level < MAX_LEVEL {
+      level + 1
+    } else if load < LOWER_AT && level > 0 {
+      level - 1
+    } else {
+      return;
+    };
+    self.level.store(new, Ordering::Relaxed);
+    self.cooldown.store(COOLDOWN, Ordering::Relaxed);
+    if new > level {
+      warn!("ticks are taking {average:?} on average, throttling to level {new}");
+    } else {
+      info!("ticks are taking {average:?} on average, lowering throttle to level {new}");
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const MS: Duration = Duration::from_millis(1);
+
+  #[test]
+  fn levels() {
+    let throttle = Throttle::new(true);
+    assert!(throttle.runs_on(7));
+    throttle.update(MS * 45, MS * 50);
+    assert_eq!(throttle.level(), 1);
+    assert!(!throttle.runs_on(7));
+    assert!(throttle.runs_on(8));
+
+    // The level can't change again until the cooldown is over.
+    for _ in 0..COOLDOWN {
+      throttle.update(MS * 45, MS * 50);
+    }
+    assert_eq!(throttle.level(), 1);
+    throttle.update(MS * 45, MS * 50);
+    assert_eq!(throttle.level(), 2);
+
+    // Somewhere in between the thresholds doesn't change anything.
+    for _ in 0..=COOLDOWN {
+      throttle.update(MS * 30, MS * 50);
+    }
+    assert_eq!(throttle.level(), 2);
+    throttle.update(MS * 10, MS * 50);
+    assert_eq!(throttle.level(), 1);
+
+    let disabled = Throttle::new(false);
+    disabled.update(MS * 100, MS * 50);
+    assert_eq!(disabled.level(), 0);
+  }
+}
@@ -0,0 +1,180 @@
+//! Tick rate control. Each world ticks at its own rate, which is set with
+//! `tick-rate` in the world config, and can be changed with `/tick rate`.
+//!
+//! A world can also be frozen with `/tick freeze`. While frozen, players are
+//! still ticked (so they can move around), but nothing else in the world is.
+//! `/tick step` runs a number of full ticks while frozen, and `/tick sprint`
+//! runs a number of ticks as fast as possible.
+
+use std::{
+  sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+  time::{Duration, Instant},
+};
+
+/// The slowest tick rate, in ticks per second. This is the same as vanilla.
+pub const MIN_RATE: f64 = 1.0;
+/// The fastest tick rate, in ticks per second. This is the same as vanilla.
+pub const MAX_RATE: f64 = 10000.0;
+
+/// How fast a world ticks, and if it is frozen. See the [module
+/// docs](self).
+#[derive(Debug)]
+pub struct TickRate {
+  /// Ticks per second, stored as the bits of an `f64`.
+  rate:   AtomicU64,
+  frozen: AtomicBool,
+  /// The number of full ticks left to run while frozen.
+  steps:  AtomicU32,
+  /// The number of ticks left to run without waiting between them.
+  sprint: AtomicU32,
+}
+
+impl TickRate {
+  /// Creates a tick rate with the given ticks per second. This is clamped
+  /// between [`MIN_RATE`] and [`MAX_RATE`].
+  pub fn new(rate: f64) -> Self {
+    let out = TickRate {
+      rate:   AtomicU64::new(0),
+      frozen: AtomicBool::new(false),
+      steps:  AtomicU32::new(0),
+      sprint: AtomicU32::new(0),
+    };
+    out.set_rate(rate);
+    out
+  }
+
+  /// Returns the number of ticks per second.
+  pub fn rate(&self) -> f64 { f64::from_bits(self.rate.load(Ordering::Relaxed)) }
+  /// Sets the number of ticks per second. This is clamped between
+  /// [`MIN_RATE`] and [`MAX_RATE`]. Returns the new rate.
+  pub fn set_rate(&self, rate: f64) -> f64 {
+    let rate = if rate.is_nan() { MIN_RATE } else { rate.clamp(MIN_RATE, MAX_RATE) };
+    self.rate.store(rate.to_bits(), Ordering::Relaxed);
+    rate
+  }
+  /// Returns how long a single tick should take.
+  pub fn interval(&self) -> Duration { Duration::from_secs_f64(1.0 / self.rate()) }
+
+  /// Returns `true` if the world is frozen.
+  pub fn is_frozen(&self) -> bool { self.frozen.load(Ordering::Relaxed) }
+  /// Freezes or unfreezes the world. Unfreezing cancels any steps that
+  /// haven't run yet.
+  pub fn set_frozen(&self, frozen: bool) {
+    self.frozen.store(frozen, Ordering::Relaxed);
+    if !frozen {
+      self.steps.store(0, Ordering::Relaxed);
+    }
+  }
+  /// Runs the given number of full ticks while frozen. Returns `false` if the
+  /// world isn't frozen, in which case nothing happens.
+  pub fn step(&self, ticks: u32) -> bool {
+    if !self.is_frozen() {
+      return false;
+    }
+    self.steps.fetch_add(ticks, Ordering::Relaxed);
+    true
+  }
+  /// Runs the given number of ticks as fast as possible. Passing 0 stops a
+  /// sprint.
+  pub fn sprint(&self, ticks: u32) { self.sprint.store(ticks, Ordering::Relaxed); }
+  /// Returns the number of ticks left in the current sprint.
+  pub fn sprinting(&self) -> u32 { self.sprint.load(Ordering::Relaxed) }
+
+  /// Returns `true` if the next tick should tick the whole world. If the world
+  /// is frozen, this uses up a step, if there is one.
+  pub(super) fn should_tick(&self) -> bool {
+    !self.is_frozen()
+      || self.steps.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| s.checked_sub(1)).is_ok()
+  }
+  /// Returns `true` if the tick loop should skip waiting before the next tick.
+  /// This uses up a sprint tick, if there is one.
+  pub(super) fn should_sprint(&self) -> bool {
+    self.sprint.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |s| s.checked_sub(1)).is_ok()
+  }
+}
+
+/// What the tick loop should do after a tick. See [`TickClock::next`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Wait {
+  /// Sleep for this long before the next tick.
+  Sleep(Duration),
+  /// The world is behind, so the next tick should start right away.
+  CatchUp,
+  /// The world was too far behind, so this many ticks were skipped.
+  Skipped(u32),
+}
+
+/// Decides when each tick should start. If a tick takes too long, the next
+/// ticks start right away, until the world has caught up. If the world falls
+/// more than `max_catch_up` ticks behind, the missed ticks are skipped
+/// instead, so that a long stall doesn't cause a burst of ticks afterwards.
+pub(super) struct TickClock {
+  /// When the next tick should start.
+  next:         Instant,
+  max_catch_up: u32,
+}
+
+impl TickClock {
+  pub fn new(now: Instant, max_catch_up: u32) -> Self { TickClock { next: now, max_catch_up } }
+
+  /// Schedules the next tick, `interval` after the start of the last one.
+  /// `now` should be the time the last tick finished.
+  pub fn next(&mut self, now: Instant, interval: Duration) -> Wait {
+    self.next += interval;
+    if self.next > now {
+      return Wait::Sleep(self.next - now);
+    }
+    let behind = now.duration_since(self.next).as_nanos() / interval.as_nanos().max(1);
+    let behind = behind.min(u32::MAX.into()) as u32;
+    if behind > self.max_catch_up {
+      self.next = now;
+      Wait::Skipped(behind)
+    } else {
+      Wait::CatchUp
+    }
+  }
+
+  /// Starts the next tick right away, without catching up on anything. This
+  /// is used while sprinting.
+  pub fn reset(&mut self, now: Instant) { self.next = now; }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const MS: Duration = Duration::from_millis(1);
+
+  #[test]
+  fn catch_up() {
+    let start = Instant::now();
+    let mut clock = TickClock::new(start, 3);
+    assert_eq!(clock.next(start + MS * 10, MS * 50), Wait::Sleep(MS * 40));
+    // A slow tick, which ends 2 ticks late.
+    assert_eq!(clock.next(start + MS * 200, MS * 50), Wait::CatchUp);
+    assert_eq!(clock.next(start + MS * 210, MS * 50), Wait::CatchUp);
+    assert_eq!(clock.next(start + MS * 220, MS * 50), Wait::CatchUp);
+    assert_eq!(clock.next(start + MS * 230, MS * 50), Wait::Sleep(MS * 20));
+    // A long stall, which is more than 3 ticks behind.
+    assert_eq!(clock.next(start + MS * 1000, MS * 50), Wait::Skipped(14));
+    assert_eq!(clock.next(start + MS * 1010, MS * 50), Wait::Sleep(MS * 40));
+  }
+
+  #[test]
+  fn freeze() {
+    let rate = TickRate::new(0.0);
+    assert_eq!(rate.rate(), MIN_RATE);
+    assert!(rate.should_tick());
+    assert!(!rate.step(1));
+    rate.set_frozen(true);
+    assert!(!rate.should_tick());
+    assert!(rate.step(2));
+    assert!(rate.should_tick());
+    assert!(rate.should_tick());
+    assert!(!rate.should_tick());
+    rate.step(5);
+    rate.set_frozen(false);
+    rate.set_frozen(true);
+    assert!(!rate.should_tick());
+  }
+}
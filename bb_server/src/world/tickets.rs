@@ -0,0 +1,296 @@
+//! Chunk tickets. A chunk stays loaded as long as something holds a ticket
+//! for it:
+//!
+//! - Players hold a ticket for every chunk in view. These are tracked by the
+//!   view count of each [`CountedChunk`](super::CountedChunk), not by
+//!   [`Tickets`].
+//! - Plugins can add named tickets, which can expire after a number of ticks.
+//! - `/forceload` adds tickets that last until they are removed. These are
+//!   stored in `world/forceload/<world name>.json`.
+//!
+//! Every chunk with a plugin or forced ticket holds a single view on the
+//! chunk, so it will not be unloaded. Tile entities (like command blocks) are
+//! ticked in these chunks as well, so machines keep running when no players
+//! are nearby.
+
+use super::World;
+use bb_common::{math::ChunkPos, util::JsonFile};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+/// Something that is keeping a chunk loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TicketKind {
+  /// At least one player can see the chunk.
+  Player,
+  /// A ticket added by a plugin, with the name it was added with.
+  Plugin(String),
+  /// The chunk was force loaded with `/forceload`.
+  Forced,
+}
+
+/// How loaded a chunk is. See [`World::chunk_load_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkLoadState {
+  /// The chunk is not in memory.
+  Unloaded,
+  /// The chunk is in memory, but has no tickets, so it will be unloaded soon.
+  Inactive,
+  /// The chunk has at least one ticket, so it will stay loaded.
+  Ticking,
+}
+
+#[derive(Debug, Default)]
+struct ChunkTickets {
+  /// Plugin tickets, with the tick they expire on.
+  plugin: HashMap<String, Option<u64>>,
+  forced: bool,
+}
+
+impl ChunkTickets {
+  fn is_empty(&self) -> bool { !self.forced && self.plugin.is_empty() }
+}
+
+/// The plugin and forced tickets in a single world.
+#[derive(Debug)]
+pub struct Tickets {
+  chunks: HashMap<ChunkPos, ChunkTickets>,
+  /// The forced chunks, as `[x, z]`.
+  file:   JsonFile<Vec<[i32; 2]>>,
+  /// If `false`, forced chunks are never written to disk.
+  save:   bool,
+  /// The number of times [`tick`](Self::tick) has been called. Plugin
+  /// tickets expire based on this.
+  tick:   u64,
+}
+
+impl Tickets {
+  /// Loads the forced chunks for the world with the given name. If `save` is
+  /// `false`, changes will never be written to disk.
+  pub(super) fn load(world: &str, save: bool) -> Self {
+    let path = PathBuf::new().join("world").join("forceload").join(format!("{world}.json"));
+    let file = JsonFile::<Vec<[i32; 2]>>::load(path);
+    let mut chunks = HashMap::new();
+    for &[x, z] in file.iter() {
+      chunks.entry(ChunkPos::new(x, z)).or_default().forced = true;
+    }
+    Tickets { chunks, file, save, tick: 0 }
+  }
+
+  /// Returns `true` if the chunk has any plugin or forced tickets.
+  pub fn has_tickets(&self, pos: ChunkPos) -> bool { self.chunks.contains_key(&pos) }
+  /// Returns `true` if the chunk is force loaded.
+  pub fn is_forced(&self, pos: ChunkPos) -> bool { self.chunks.get(&pos).is_some_and(|c| c.forced) }
+  /// Returns every chunk that has a plugin or forced ticket, in no particular
+  /// order.
+  pub fn chunks(&self) -> impl Iterator<Item = ChunkPos> + '_ { self.chunks.keys().copied() }
+  /// Returns every forced chunk, in no particular order.
+  pub fn forced(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+    self.chunks.iter().filter(|(_, c)| c.forced).map(|(pos, _)| *pos)
+  }
+  /// Returns the plugin and forced tickets for the given chunk.
+  pub fn get(&self, pos: ChunkPos) -> Vec<TicketKind> {
+    let Some(c) = self.chunks.get(&pos) else { return vec![] };
+    let mut out: Vec<_> = c.plugin.keys().map(|name| TicketKind::Plugin(name.clone())).collect();
+    if c.forced {
+      out.push(TicketKind::Forced);
+    }
+    out
+  }
+
+  /// Adds a plugin ticket. If the chunk already has a ticket with this name,
+  /// it is replaced, so adding a ticket again will reset how long it lasts. If
+  /// `ttl` is `None`, the ticket lasts until it is removed.
+  pub fn add(&mut self, pos: ChunkPos, name: &str, ttl: Option<u32>) {
+    let expires = ttl.map(|ttl| self.tick + u64::from(ttl));
+    self.chunks.entry(pos).or_default().plugin.insert(name.into(), expires);
+  }
+  /// Removes the plugin ticket with the given name. Returns `false` if there
+  /// was no such ticket.
+  pub fn remove(&mut self, pos: ChunkPos, name: &str) -> bool {
+    let Some(c) = self.chunks.get_mut(&pos) else { return false };
+    let removed = c.plugin.remove(name).is_some();
+    if c.is_empty() {
+      self.chunks.remove(&pos);
+    }
+    removed
+  }
+  /// Sets if the chunk is force loaded. Returns `false` if nothing changed.
+  /// This does not save the forced chunks.
+  pub fn set_forced(&mut self, pos: ChunkPos, forced: bool) -> bool {
+    let c = self.chunks.entry(pos).or_default();
+    let changed = c.forced != forced;
+    c.forced = forced;
+    if c.is_empty() {
+      self.chunks.remove(&pos);
+    }
+    changed
+  }
+
+  /// Removes every plugin ticket that has expired. Returns the chunks that no
+  /// longer have any tickets.
+  pub fn tick(&mut self) -> Vec<ChunkPos> {
+    self.tick += 1;
+    let tick = self.tick;
+    let mut out = vec![];
+    self.chunks.retain(|&pos, c| {
+      c.plugin.retain(|_, expires| expires.map_or(true, |t| t > tick));
+      if c.is_empty() {
+        out.push(pos);
+        false
+      } else {
+        true
+      }
+    });
+    out
+  }
+
+  /// Writes all the forced chunks to disk, if this world is saved.
+  pub fn save(&mut self) {
+    if !self.save {
+      return;
+    }
+    let mut forced: Vec<_> = self.forced().map(|pos| [pos.x(), pos.z()]).collect();
+    forced.sort_unstable();
+    *self.file = forced;
+    if let Err(e) = fs::create_dir_all("world/forceload").and_then(|_| self.file.save()) {
+      error!("could not save forced chunks: {e}");
+    }
+  }
+}
+
+impl World {
+  /// Adds a plugin ticket to the given chunk, which keeps it loaded. The chunk
+  /// is loaded if it isn't already. See [`Tickets::add`].
+  pub fn add_chunk_ticket(&self, pos: ChunkPos, name: &str, ttl: Option<u32>) {
+    // The tickets are locked while changing the view count, so that adding and
+    // removing the same chunk on two threads can't leave the count wrong.
+    let mut tickets = self.tickets.lock();
+    let had_tickets = tickets.has_tickets(pos);
+    tickets.add(pos, name, ttl);
+    if !had_tickets {
+      self.inc_view(pos);
+    }
+  }
+  /// Removes a plugin ticket from the given chunk. Returns `false` if there
+  /// was no ticket with that name.
+  pub fn remove_chunk_ticket(&self, pos: ChunkPos, name: &str) -> bool {
+    let mut tickets = self.tickets.lock();
+    let removed = tickets.remove(pos, name);
+    if removed && !tickets.has_tickets(pos) {
+      self.dec_view(pos);
+    }
+    removed
+  }
+
+  /// Sets if the given chunk is force loaded. Forced chunks stay loaded until
+  /// this is called again with `false`, even across restarts. Returns `false`
+  /// if nothing changed.
+  pub fn set_force_loaded(&self, pos: ChunkPos, forced: bool) -> bool {
+    let mut tickets = self.tickets.lock();
+    let had_tickets = tickets.has_tickets(pos);
+    if !tickets.set_forced(pos, forced) {
+      return false;
+    }
+    match (had_tickets, tickets.has_tickets(pos)) {
+      (false, true) => self.inc_view(pos),
+      (true, false) => self.dec_view(pos),
+      _ => {}
+    }
+    tickets.save();
+    true
+  }
+  /// Returns `true` if the given chunk is force loaded.
+  pub fn is_force_loaded(&self, pos: ChunkPos) -> bool { self.tickets.lock().is_forced(pos) }
+  /// Returns every force loaded chunk, in no particular order.
+  pub fn force_loaded_chunks(&self) -> Vec<ChunkPos> { self.tickets.lock().forced().collect() }
+
+  /// Returns everything keeping the given chunk loaded.
+  pub fn chunk_tickets(&self, pos: ChunkPos) -> Vec<TicketKind> {
+    let tickets = self.tickets.lock();
+    let mut out = tickets.get(pos);
+    // The plugin and forced tickets hold one view between them, and any other
+    // views are from players.
+    let held = u32::from(!out.is_empty());
+    if self.regions.view_count(pos) > held {
+      out.insert(0, TicketKind::Player);
+    }
+    out
+  }
+  /// Returns the load state of the given chunk. This never loads the chunk.
+  pub fn chunk_load_state(&self, pos: ChunkPos) -> ChunkLoadState {
+    if !self.has_loaded_chunk(pos) {
+      ChunkLoadState::Unloaded
+    } else if self.tickets.lock().has_tickets(pos) || self.regions.view_count(pos) > 0 {
+      ChunkLoadState::Ticking
+    } else {
+      ChunkLoadState::Inactive
+    }
+  }
+
+  /// Returns every chunk with a plugin or forced ticket.
+  pub(super) fn ticketed_chunks(&self) -> Vec<ChunkPos> { self.tickets.lock().chunks().collect() }
+
+  /// Loads all the forced chunks. This is called when the tick loop starts.
+  pub(super) fn load_forced_chunks(&self) {
+    let tickets = self.tickets.lock();
+    for pos in tickets.chunks() {
+      self.inc_view(pos);
+    }
+  }
+
+  /// Removes expired plugin tickets. This is called every tick.
+  pub(super) fn tick_tickets(&self) {
+    let mut tickets = self.tickets.lock();
+    for pos in tickets.tick() {
+      self.dec_view(pos);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tickets() -> Tickets {
+    Tickets { chunks: HashMap::new(), file: JsonFile::new("unused"), save: false, tick: 0 }
+  }
+
+  #[test]
+  fn expire() {
+    let mut t = tickets();
+    let pos = ChunkPos::new(3, -2);
+    t.add(pos, "farm", Some(2));
+    t.add(pos, "machine", None);
+    assert_eq!(t.tick(), vec![]);
+    assert_eq!(t.get(pos).len(), 2);
+    assert_eq!(t.tick(), vec![]);
+    assert_eq!(t.get(pos), vec![TicketKind::Plugin("machine".into())]);
+    assert!(t.remove(pos, "machine"));
+    assert!(!t.remove(pos, "machine"));
+    assert!(!t.has_tickets(pos));
+
+    // Adding a ticket again resets how long it lasts.
+    t.add(pos, "farm", Some(1));
+    t.add(pos, "farm", Some(3));
+    assert_eq!(t.tick(), vec![]);
+    assert_eq!(t.tick(), vec![]);
+    assert_eq!(t.tick(), vec![pos]);
+    assert!(!t.has_tickets(pos));
+  }
+
+  #[test]
+  fn forced() {
+    let mut t = tickets();
+    let pos = ChunkPos::new(0, 0);
+    assert!(t.set_forced(pos, true));
+    assert!(!t.set_forced(pos, true));
+    t.add(pos, "farm", Some(1));
+    assert_eq!(t.tick(), vec![]);
+    assert_eq!(t.get(pos), vec![TicketKind::Forced]);
+    assert_eq!(t.forced().collect::<Vec<_>>(), vec![pos]);
+    assert!(t.set_forced(pos, false));
+    assert!(!t.has_tickets(pos));
+    assert!(!t.set_forced(pos, false));
+    assert!(!t.has_tickets(pos));
+  }
+}
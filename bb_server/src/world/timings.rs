@@ -0,0 +1,163 @@
+use parking_lot::Mutex;
+use std::{
+  collections::VecDeque,
+  time::{Duration, Instant},
+};
+
+/// The number of ticks that phase times are averaged over.
+const SAMPLES: usize = 100;
+/// The number of tick times to keep for TPS. This is one minute of ticks.
+const TPS_WINDOW: usize = 20 * 60;
+
+/// Rolling timings for a tick loop. Each tick is split up into a number of
+/// phases, which are recorded with [`time`](Self::time). Once the tick is
+/// done, [`finish_tick`](Self::finish_tick) should be called.
+///
+/// Phase times are averaged over the last 100 ticks, and TPS is tracked over
+/// the last minute.
+pub struct TickTimings {
+  inner: Mutex<Inner>,
+}
+
+struct Inner {
+  /// When each of the last [`TPS_WINDOW`] ticks finished.
+  finished: VecDeque<Instant>,
+  /// The total time of the last [`SAMPLES`] ticks.
+  total:    VecDeque<Duration>,
+  /// Every phase, in the order they were first recorded.
+  phases:   Vec<Phase>,
+}
+
+struct Phase {
+  name:    &'static str,
+  /// The time spent in this phase during the current tick.
+  current: Duration,
+  /// The time spent in this phase during the last [`SAMPLES`] ticks.
+  samples: VecDeque<Duration>,
+}
+
+impl Default for TickTimings {
+  fn default() -> Self { TickTimings::new() }
+}
+
+impl TickTimings {
+  pub fn new() -> Self {
+    TickTimings {
+      inner: Mutex::new(Inner {
+        finished: VecDeque::with_capacity(TPS_WINDOW),
+        total:    VecDeque::with_capacity(SAMPLES),
+        phases:   vec![],
+      }),
+    }
+  }
+
+  /// Runs `f`, and adds the time it took to the given phase. A phase may be
+  /// timed multiple times in one tick, in which case the times are added
+  /// together.
+  pub fn time<R>(&self, phase: &'static str, f: impl FnOnce() -> R) -> R {
+    let start = Instant::now();
+    let res = f();
+    self.record(phase, start.elapsed());
+    res
+  }
+
+  /// Adds `time` to the given phase.
+  pub fn record(&self, phase: &'static str, time: Duration) {
+    let mut inner = self.inner.lock();
+    match inner.phases.iter_mut().find(|p| p.name == phase) {
+      Some(p) => p.current += time,
+      None => inner.phases.push(Phase {
+        name:    phase,
+        current: time,
+        samples: VecDeque::with_capacity(SAMPLES),
+      }),
+    }
+  }
+
+  /// Finishes the current tick. `total` is the time the whole tick took,
+  /// including anything that wasn't part of a phase.
+  pub fn finish_tick(&self, total: Duration) {
+    let mut inner = self.inner.lock();
+    push(&mut inner.finished, Instant::now(), TPS_WINDOW);
+    push(&mut inner.total, total, SAMPLES);
+    for phase in &mut inner.phases {
+      let time = std::mem::take(&mut phase.current);
+      push(&mut phase.samples, time, SAMPLES);
+    }
+  }
+
+  /// Returns the average ticks per second over the last `ticks` ticks. This
+  /// is capped at 20, as the tick loop will never run faster than that. If
+  /// there aren't enough ticks yet, this returns 20.
+  pub fn tps(&self, ticks: usize) -> f64 {
+    let inner = self.inner.lock();
+    let len = inner.finished.len();
+    let ticks = ticks.min(len);
+    if ticks < 2 {
+      return 20.0;
+    }
+    let first = inner.finished[len - ticks];
+    let last = inner.finished[len - 1];
+    let secs = (last - first).as_secs_f64();
+    if secs == 0.0 {
+      return 20.0;
+    }
+    ((ticks - 1) as f64 / secs).min(20.0)
+  }
+
+  /// Returns the average time of a whole tick.
+  pub fn average(&self) -> Duration { average(&self.inner.lock().total) }
+
+  /// Returns the average time of each phase, in the order they were first
+  /// recorded.
+  pub fn phases(&self) -> Vec<(&'static str, Duration)> {
+    self.inner.lock().phases.iter().map(|p| (p.name, average(&p.samples))).collect()
+  }
+}
+
+fn push<T>(list: &mut VecDeque<T>, value: T, max: usize) {
+  if list.len() >= max {
+    list.pop_front();
+  }
+  list.push_back(value);
+}
+
+fn average(samples: &VecDeque<Duration>) -> Duration {
+  if samples.is_empty() {
+    Duration::ZERO
+  } else {
+    samples.iter().sum::<Duration>() / samples.len() as u32
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn averages() {
+    let timings = TickTimings::new();
+    timings.record("a", Duration::from_millis(2));
+    timings.record("a", Duration::from_millis(2));
+    timings.record("b", Duration::from_millis(1));
+    timings.finish_tick(Duration::from_millis(6));
+    timings.record("a", Duration::from_millis(2));
+    timings.finish_tick(Duration::from_millis(4));
+
+    assert_eq!(timings.average(), Duration::from_millis(5));
+    assert_eq!(
+      timings.phases(),
+      [("a", Duration::from_millis(3)), ("b", Duration::from_micros(500))]
+    );
+  }
+
+  #[test]
+  fn window() {
+    let timings = TickTimings::new();
+    for _ in 0..SAMPLES {
+      timings.finish_tick(Duration::from_millis(10));
+    }
+    timings.finish_tick(Duration::from_millis(110));
+    assert_eq!(timings.average(), Duration::from_millis(11));
+  }
+}
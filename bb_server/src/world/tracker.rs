@@ -0,0 +1,252 @@
+//! Entity tracking. Every world knows which players can see each chunk, and
+//! every player knows which entities have been spawned for them. When a player
+//! or entity moves between chunks, only the chunks that changed are looked
+//! at, so sending an entity packet doesn't need to scan every player.
+
+use super::{
+  lock_order::{self, Level},
+  World,
+};
+use crate::{
+  entity::{Entity, EntityRef},
+  player::Player,
+};
+use bb_common::{math::ChunkPos, net::cb};
+use std::{collections::HashMap, sync::Arc};
+
+/// Stores the players that can see each chunk.
+#[derive(Default)]
+pub struct ViewTracker {
+  chunks: HashMap<ChunkPos, Vec<Arc<Player>>>,
+}
+
+impl ViewTracker {
+  pub fn new() -> Self { ViewTracker::default() }
+
+  /// Adds `player` as a viewer of `chunk`.
+  pub fn add(&mut self, chunk: ChunkPos, player: &Arc<Player>) {
+    let list = self.chunks.entry(chunk).or_default();
+    if !list.iter().any(|p| Arc::ptr_eq(p, player)) {
+      list.push(player.clone());
+    }
+  }
+
+  /// Removes `player` as a viewer of `chunk`.
+  pub fn remove(&mut self, chunk: ChunkPos, player: &Player) {
+    if let Some(list) = self.chunks.get_mut(&chunk) {
+      list.retain(|p| !std::ptr::eq(p.as_ref(), player));
+      if list.is_empty() {
+        self.chunks.remove(&chunk);
+      }
+    }
+  }
+
+  /// Returns all the players that can see `chunk`.
+  pub fn viewers(&self, chunk: ChunkPos) -> &[Arc<Player>] {
+    self.chunks.get(&chunk).map(|v| v.as_slice()).unwrap_or(&[])
+  }
+}
+
+/// Returns every chunk within `dist` of `center`. This is a square, to match
+/// the client's view distance.
+pub fn view_area(center: ChunkPos, dist: u32) -> impl Iterator<Item = ChunkPos> {
+  let d = dist as i32;
+  (-d..=d).flat_map(move |x| (-d..=d).map(move |z| center + ChunkPos::new(x, z)))
+}
+
+/// Finds which chunks come into view, and which chunks leave view, when a
+/// player with the given view distance moves from `old` to `new`. Returns the
+/// chunks added and the chunks removed.
+pub fn view_diff(old: ChunkPos, new: ChunkPos, dist: u32) -> (Vec<ChunkPos>, Vec<ChunkPos>) {
+  let in_view = |center: ChunkPos, pos: ChunkPos| {
+    let delta = pos - center;
+    delta.x().unsigned_abs() <= dist && delta.z().unsigned_abs() <= dist
+  };
+  let added = view_area(new, dist).filter(|&c| !in_view(old, c)).collect();
+  let removed = view_area(old, dist).filter(|&c| !in_view(new, c)).collect();
+  (added, removed)
+}
+
+impl World {
+  /// Returns every player that can see the given chunk. This is the set of
+  /// players that should be sent packets about anything in that chunk.
+  pub fn viewers(&self, chunk: ChunkPos) -> Vec<Arc<Player>> {
+    let _held = lock_order::acquire(Level::Viewers);
+    self.viewers.lock().viewers(chunk).to_vec()
+  }
+
+  /// Starts tracking a player that just joined. This adds them as a viewer of
+  /// every chunk in view, spawns everything in view for them, and spawns them
+  /// for everyone who can see them.
+  pub(crate) fn track_player(self: &Arc<Self>, player: &Arc<Player>) {
+    let chunk = player.pos().chunk();
+    let chunks: Vec<_> = view_area(chunk, player.view_distance()).collect();
+    self.show_chunks(player, &chunks);
+    let ent = Entity::Player(player.id());
+    for viewer in self.viewers(chunk) {
+      if viewer.id() != player.id() && viewer.track(player.eid()) {
+        self.spawn_for(&viewer, &ent);
+      }
+    }
+  }
+
+  /// Stops tracking a player that is leaving. This removes them as a viewer,
+  /// and removes them for everyone who could see them.
+  pub(crate) fn untrack_player(&self, player: &Player) {
+    let chunk = player.pos().chunk();
+    {
+      let _held = lock_order::acquire(Level::Viewers);
+      let mut viewers = self.viewers.lock();
+      for pos in view_area(chunk, player.view_distance()) {
+        viewers.remove(pos, player);
+      }
+    }
+    let remove = cb::packet::RemoveEntities { eids: vec![player.eid()] };
+    for viewer in self.viewers(chunk) {
+      if viewer.untrack(player.eid()) {
+        viewer.send(remove.clone());
+      }
+    }
+  }
+
+  /// Updates the chunks that `player` can see, after they moved from the
+  /// chunk `old` to `new`. Everything that came into view is spawned, and
+  /// everything that left view is removed.
+  pub(crate) fn move_view(self: &Arc<Self>, player: &Arc<Player>, old: ChunkPos, new: ChunkPos) {
+    let (added, removed) = view_diff(old, new, player.view_distance());
+    self.hide_chunks(player, &removed);
+    self.show_chunks(player, &added);
+  }
+
+  /// Updates the entity index, and spawns or removes the entity for anyone
+  /// that started or stopped seeing it. This should be called after any
+  /// movement packets for this entity have been sent, so that new viewers
+  /// don't get a relative move on top of the spawn position.
+  pub(crate) fn move_entity(&self, eid: i32, old: ChunkPos, new: ChunkPos) {
+    self.index_entity(eid, new);
+    let (old_viewers, new_viewers) = {
+      let _held = lock_order::acquire(Level::Viewers);
+      let viewers = self.viewers.lock();
+      (viewers.viewers(old).to_vec(), viewers.viewers(new).to_vec())
+    };
+    let ent = match self.entities.read().get(&eid) {
+      Some(e) => e.clone(),
+      None => return,
+    };
+    for p in &new_viewers {
+      if p.eid() != eid && p.track(eid) {
+        self.spawn_for(p, &ent);
+      }
+    }
+    let remove = cb::packet::RemoveEntities { eids: vec![eid] };
+    for p in old_viewers {
+      if !new_viewers.iter().any(|v| Arc::ptr_eq(v, &p)) && p.untrack(eid) {
+        p.send(remove.clone());
+      }
+    }
+  }
+
+  /// Spawns a new entity for everyone that can see `chunk`.
+  pub(super) fn spawn_for_viewers(&self, eid: i32, chunk: ChunkPos) {
+    let ent = match self.entities.read().get(&eid) {
+      Some(e) => e.clone(),
+      None => return,
+    };
+    for p in self.viewers(chunk) {
+      if p.eid() != eid && p.track(eid) {
+        self.spawn_for(&p, &ent);
+      }
+    }
+  }
+
+  /// Removes an entity for everyone that has it spawned.
+  pub(super) fn remove_for_viewers(&self, eid: i32, chunk: ChunkPos) {
+    let remove = cb::packet::RemoveEntities { eids: vec![eid] };
+    for p in self.viewers(chunk) {
+      if p.untrack(eid) {
+        p.send(remove.clone());
+      }
+    }
+  }
+
+  fn show_chunks(&self, player: &Arc<Player>, chunks: &[ChunkPos]) {
+    {
+      let _held = lock_order::acquire(Level::Viewers);
+      let mut viewers = self.viewers.lock();
+      for &chunk in chunks {
+        viewers.add(chunk, player);
+      }
+    }
+    let eids = self.eids_in(chunks);
+    // Don't hold onto the entities map while spawning, as spawning players needs
+    // to lock the players map.
+    let entities: Vec<_> = {
+      let entities = self.entities.read();
+      eids.into_iter().filter_map(|eid| Some((eid, entities.get(&eid)?.clone()))).collect()
+    };
+    for (eid, ent) in entities {
+      if eid != player.eid() && player.track(eid) {
+        self.spawn_for(player, &ent);
+      }
+    }
+  }
+
+  fn hide_chunks(&self, player: &Player, chunks: &[ChunkPos]) {
+    {
+      let _held = lock_order::acquire(Level::Viewers);
+      let mut viewers = self.viewers.lock();
+      for &chunk in chunks {
+        viewers.remove(chunk, player);
+      }
+    }
+    let eids: Vec<_> =
+      self.eids_in(chunks).into_iter().filter(|&eid| player.untrack(eid)).collect();
+    if !eids.is_empty() {
+      player.send(cb::packet::RemoveEntities { eids });
+    }
+  }
+
+  fn eids_in(&self, chunks: &[ChunkPos]) -> Vec<i32> {
+    let _held = lock_order::acquire(Level::EntityIndex);
+    let index = self.entity_index.lock();
+    chunks.iter().flat_map(|&c| index.in_chunk(c).iter().copied()).collect()
+  }
+
+  fn spawn_for(&self, viewer: &Player, ent: &Entity) {
+    match ent {
+      Entity::Entity(e) => self.send_entity_spawn(viewer, &EntityRef::Entity(e)),
+      Entity::Player(id) => {
+        let other = self.players.read().get(*id).cloned();
+        if let Some(other) = other {
+          self.send_player_spawn(viewer, &other);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn diff() {
+    let (added, removed) = view_diff(ChunkPos::new(0, 0), ChunkPos::new(0, 0), 2);
+    assert!(added.is_empty());
+    assert!(removed.is_empty());
+
+    let (added, removed) = view_diff(ChunkPos::new(0, 0), ChunkPos::new(1, 0), 2);
+    assert_eq!(added, (-2..=2).map(|z| ChunkPos::new(3, z)).collect::<Vec<_>>());
+    assert_eq!(removed, (-2..=2).map(|z| ChunkPos::new(-2, z)).collect::<Vec<_>>());
+
+    let (added, removed) = view_diff(ChunkPos::new(0, 0), ChunkPos::new(1, 1), 1);
+    assert_eq!(added.len(), 5);
+    assert_eq!(removed.len(), 5);
+    assert!(added.contains(&ChunkPos::new(2, 2)));
+    assert!(removed.contains(&ChunkPos::new(-1, -1)));
+
+    let (added, removed) = view_diff(ChunkPos::new(0, 0), ChunkPos::new(10, 0), 1);
+    assert_eq!(added.len(), 9);
+    assert_eq!(removed.len(), 9);
+  }
+}
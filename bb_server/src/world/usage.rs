@@ -0,0 +1,111 @@
+//! Resource usage reports. The server measures how much memory it is using,
+//! how many chunks and entities are loaded, and how large each world is on
+//! disk. This is sent to the proxy with every
+//! [`ServerStatus`](cb::packet::ServerStatus), and shown by `/status`.
+
+use super::{World, WorldManager};
+use bb_common::net::cb;
+use parking_lot::Mutex;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+  sync::Arc,
+  time::{Duration, Instant},
+};
+
+/// Walking a world's directory touches every file in it, so the size is only
+/// measured this often.
+const DISK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The size of a world's save directory. This is cached for
+/// [`DISK_INTERVAL`].
+#[derive(Debug)]
+pub(super) struct DiskUsage {
+  dir:  PathBuf,
+  last: Mutex<Option<(Instant, u64)>>,
+}
+
+impl DiskUsage {
+  pub fn new(dir: impl Into<PathBuf>) -> Self {
+    DiskUsage { dir: dir.into(), last: Mutex::new(None) }
+  }
+
+  /// Returns the size of the directory, in bytes. This only measures the
+  /// directory again if the last measurement is out of date.
+  pub fn get(&self) -> u64 {
+    let mut last = self.last.lock();
+    match *last {
+      Some((measured, size)) if measured.elapsed() < DISK_INTERVAL => size,
+      _ => {
+        let size = dir_size(&self.dir);
+        *last = Some((Instant::now(), size));
+        size
+      }
+    }
+  }
+}
+
+/// Returns the total size of every file in `path`. Anything that can't be
+/// read is skipped.
+fn dir_size(path: &Path) -> u64 {
+  let Ok(entries) = fs::read_dir(path) else { return 0 };
+  entries
+    .flatten()
+    .map(|e| match e.metadata() {
+      Ok(m) if m.is_dir() => dir_size(&e.path()),
+      Ok(m) => m.len(),
+      Err(_) => 0,
+    })
+    .sum()
+}
+
+/// Returns the resident memory of this process, in bytes, or 0 if it isn't
+/// known. This reads `VmRSS` from `/proc/self/status`, so it only works on
+/// Linux.
+fn rss() -> u64 {
+  let Ok(status) = fs::read_to_string("/proc/self/status") else { return 0 };
+  parse_rss(&status).unwrap_or(0)
+}
+
+fn parse_rss(status: &str) -> Option<u64> {
+  let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+  let kb = line["VmRSS:".len()..].trim().strip_suffix("kB")?.trim();
+  kb.parse::<u64>().ok().map(|kb| kb * 1024)
+}
+
+impl World {
+  /// Returns the resources used by this world.
+  pub fn usage(self: &Arc<Self>) -> cb::WorldUsage {
+    let (_, chunks) = self.regions.loaded();
+    cb::WorldUsage {
+      name:     self.config().name.clone(),
+      chunks:   chunks as u32,
+      entities: self.entities().iter_values().len() as u32,
+      players:  self.players().len() as u32,
+      disk:     self.disk_usage.get(),
+    }
+  }
+}
+
+impl WorldManager {
+  /// Returns the resources used by the whole server.
+  pub fn usage(&self) -> cb::ServerUsage {
+    cb::ServerUsage {
+      rss:     rss(),
+      plugins: self.plugins.count() as u32,
+      worlds:  self.worlds().iter().map(|w| w.usage()).collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn status_rss() {
+    let status = "Name:\tbamboo\nVmPeak:\t  200000 kB\nVmRSS:\t   51200 kB\nThreads:\t8\n";
+    assert_eq!(parse_rss(status), Some(50 * 1024 * 1024));
+    assert_eq!(parse_rss("Name:\tbamboo\n"), None);
+  }
+}
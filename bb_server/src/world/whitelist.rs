@@ -0,0 +1,67 @@
+//! The whitelist and maintenance mode. The whitelist itself lives in
+//! [`bb_common::whitelist`], so that the proxy can read it as well.
+
+use super::WorldManager;
+use bb_common::{
+  util::{Chat, UUID},
+  whitelist::Whitelist,
+};
+use parking_lot::MutexGuard;
+
+impl WorldManager {
+  /// Returns the whitelist. The whitelist is read from disk again if the file
+  /// has changed. Any changes made will not be saved; use
+  /// [`edit_whitelist`](Self::edit_whitelist) to change it.
+  pub fn whitelist(&self) -> MutexGuard<'_, Whitelist> {
+    let mut whitelist = self.whitelist.lock();
+    whitelist.reload_if_changed();
+    whitelist
+  }
+
+  /// Changes the whitelist, and then saves it.
+  pub fn edit_whitelist<R>(&self, f: impl FnOnce(&mut Whitelist) -> R) -> R {
+    let mut whitelist = self.whitelist();
+    let res = f(&mut whitelist);
+    if let Err(e) = whitelist.save() {
+      error!("could not save whitelist: {e}");
+    }
+    res
+  }
+
+  /// Turns maintenance mode on or off. If `message` is set, it will be shown
+  /// to anyone who can't join. When maintenance mode is turned on, everyone
+  /// online who isn't on the whitelist is kicked.
+  pub fn set_maintenance(&self, maintenance: bool, message: Option<String>) {
+    self.edit_whitelist(|w| w.set_maintenance(maintenance, message));
+    if !maintenance {
+      return;
+    }
+    let kicked: Vec<_> = {
+      let whitelist = self.whitelist();
+      self
+        .all_players()
+        .values()
+        .filter_map(|(_, p)| Some((p.clone(), whitelist.check(p.id(), p.username())?)))
+        .collect()
+    };
+    for (p, msg) in kicked {
+      info!("kicking {}, as maintenance mode was turned on", p.username());
+      p.disconnect(msg);
+    }
+  }
+
+  /// Checks if a player can join. Returns the message to kick them with if
+  /// they can't. If they can, their UUID and name are stored in the whitelist.
+  pub(crate) fn check_whitelist(&self, id: UUID, name: &str) -> Option<Chat> {
+    let mut whitelist = self.whitelist();
+    if let Some(msg) = whitelist.check(id, name) {
+      return Some(msg);
+    }
+    if whitelist.update(id, name) {
+      if let Err(e) = whitelist.save() {
+        error!("could not save whitelist: {e}");
+      }
+    }
+    None
+  }
+}
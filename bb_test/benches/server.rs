@@ -0,0 +1,46 @@
+use bb_common::{math::ChunkPos, net::cb};
+use bb_test::TestServer;
+use bb_transfer::{MessageWrite, MessageWriter};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pprof::criterion::{Output, PProfProfiler};
+
+pub fn serialize_chunk(c: &mut Criterion) {
+  let server = TestServer::new();
+  let world = server.world();
+  // Make sure the chunk is generated before we start.
+  world.serialize_chunk(ChunkPos::new(0, 0));
+  c.bench_function("serialize chunk", |b| {
+    b.iter(|| black_box(world.serialize_chunk(ChunkPos::new(0, 0))));
+  });
+  // This is everything the server does to send a chunk to the proxy.
+  c.bench_function("serialize and write chunk", |b| {
+    let mut data = vec![0; 1024 * 1024];
+    b.iter(|| {
+      let p: cb::Packet = world.serialize_chunk(ChunkPos::new(0, 0)).into();
+      let mut m = MessageWriter::new(data.as_mut_slice());
+      p.write(&mut m).unwrap();
+      black_box(m.index());
+    });
+  });
+}
+
+pub fn player_tick(c: &mut Criterion) {
+  let server = TestServer::new();
+  let clients: Vec<_> = (0..20).map(|i| server.join(&format!("player{i}"))).collect();
+  c.bench_function("tick 20 players", |b| {
+    b.iter(|| {
+      server.tick();
+      // Drop all the packets sent, so the channels don't fill up.
+      for client in &clients {
+        client.clear();
+      }
+    });
+  });
+}
+
+criterion_group! {
+  name = benches;
+  config = Criterion::default().with_profiler(PProfProfiler::new(100, Output::Flamegraph(None)));
+  targets = serialize_chunk, player_tick
+}
+criterion_main!(benches);
@@ -0,0 +1,225 @@
+//! A headless test harness for the server. This creates a [`WorldManager`]
+//! with a single world, and connects fake clients to it without a proxy or any
+//! sockets. Tests send serverbound packets as a client, and can then check the
+//! world state, or the packets that were sent back.
+//!
+//! ```no_run
+//! use bb_common::{math::Pos, net::sb, util::GameMode};
+//! use bb_server::block;
+//! use bb_test::TestServer;
+//!
+//! let server = TestServer::new();
+//! let client = server.join("macmv");
+//! client.command("gmc");
+//! assert_eq!(client.player().game_mode(), GameMode::Creative);
+//! ```
+//!
+//! Worlds created here don't have a tick loop. Call [`TestServer::tick`] to
+//! move time forward.
+
+#[macro_use]
+extern crate log;
+
+use bb_common::{
+  net::{cb, protocol::ProtocolInfo, sb},
+  util::{ClientKind, JoinInfo, JoinMode, UUID},
+  version::ProtocolVersion,
+};
+use bb_server::{
+  config::Config,
+  net::{packet, ConnSender, Outgoing, WakeEvent},
+  player::Player,
+  world::{World, WorldManager},
+};
+use crossbeam_channel::Receiver;
+use mio::Poll;
+use std::{
+  fmt,
+  sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+  },
+};
+
+pub struct TestServer {
+  wm:      Arc<WorldManager>,
+  world:   Arc<World>,
+  /// The wakers for every client are registered with this poll. Nothing reads
+  /// from it, but it must outlive the clients.
+  poll:    Poll,
+  next_id: AtomicU32,
+}
+
+/// A fake client, connected directly to a [`TestServer`].
+pub struct TestClient {
+  wm:      Arc<WorldManager>,
+  player:  Arc<Player>,
+  rx:      Receiver<Outgoing>,
+  wake_rx: Receiver<WakeEvent>,
+}
+
+impl Default for TestServer {
+  fn default() -> Self { TestServer::new() }
+}
+
+impl TestServer {
+  /// Creates a server with the default config. This doesn't read or write
+  /// `server.toml`.
+  pub fn new() -> Self { TestServer::with_config(Config::default()) }
+
+  /// Creates a server with the given config.
+  pub fn with_config(config: Config) -> Self {
+    bb_common::init("test");
+    let wm = Arc::new(WorldManager::new_with_config(config));
+    let world = wm.add_world_no_tick(wm.new_world());
+    world.init();
+    TestServer { wm, world, poll: Poll::new().unwrap(), next_id: 0.into() }
+  }
+
+  pub fn wm(&self) -> &Arc<WorldManager> { &self.wm }
+  /// Returns the world that all clients join.
+  pub fn world(&self) -> &Arc<World> { &self.world }
+
+  /// Connects a new 1.8 client. All the packets sent during login are cleared.
+  pub fn join(&self, username: &str) -> TestClient {
+    self.join_version(username, ProtocolVersion::V1_8)
+  }
+
+  /// Connects a new client with the given version. All the packets sent during
+  /// login are cleared.
+  pub fn join_version(&self, username: &str, ver: ProtocolVersion) -> TestClient {
+    let client = self.join_with_init(username, ver);
+    client.clear();
+    client
+  }
+
+  /// Connects a new client, and keeps all the login packets in the buffer.
+  pub fn join_with_init(&self, username: &str, ver: ProtocolVersion) -> TestClient {
+    let (rx, wake_rx, sender) = ConnSender::mock(&self.poll);
+    let info = JoinInfo {
+      mode:       JoinMode::New,
+      username:   username.into(),
+      uuid:       UUID::from_u128(self.next_id.fetch_add(1, Ordering::SeqCst).into()),
+      ver:        ver.id(),
+      properties: vec![],
+      addr:       None,
+      client:     ClientKind::Vanilla,
+      mods:       vec![],
+      protocol:   ProtocolInfo::current(),
+    };
+    let player = self.wm.new_player(sender, info);
+    TestClient { wm: self.wm.clone(), player, rx, wake_rx }
+  }
+
+  /// Ticks every player in the world once.
+  pub fn tick(&self) {
+    for p in self.world.players().iter() {
+      p.tick();
+    }
+  }
+  /// Ticks every player `n` times.
+  pub fn tick_n(&self, n: u32) {
+    for _ in 0..n {
+      self.tick();
+    }
+  }
+}
+
+impl TestClient {
+  pub fn player(&self) -> &Arc<Player> { &self.player }
+
+  /// Handles the given packet, as if the proxy had sent it. This blocks until
+  /// the server is done handling the packet.
+  pub fn send(&self, p: sb::Packet) { packet::handle(&self.wm, &self.player, p); }
+  /// Sends all the given packets in order.
+  pub fn script(&self, packets: impl IntoIterator<Item = sb::Packet>) {
+    for p in packets {
+      self.send(p);
+    }
+  }
+  /// Sends a chat message.
+  pub fn chat(&self, msg: &str) { self.send(sb::Packet::Chat { msg: msg.into() }); }
+  /// Runs a command. The command should not start with a `/`.
+  pub fn command(&self, command: &str) { self.chat(&format!("/{command}")); }
+
+  /// Returns all the packets the server has sent since the last call.
+  pub fn received(&self) -> Vec<cb::Packet> {
+    while self.wake_rx.try_recv().is_ok() {}
+    self.rx.try_iter().map(Outgoing::into_packet).collect()
+  }
+  /// Drops all the packets the server has sent.
+  pub fn clear(&self) { self.received(); }
+
+  /// Returns the plain text of every chat message the server has sent. Any
+  /// other packets are dropped.
+  pub fn messages(&self) -> Vec<String> {
+    self
+      .received()
+      .into_iter()
+      .filter_map(|p| match p {
+        cb::Packet::ChatMessage { msg, .. } => Some(msg.to_plain()),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Asserts that the server hasn't sent anything.
+  #[track_caller]
+  pub fn assert_empty(&self) {
+    let packets = self.received();
+    if !packets.is_empty() {
+      log_packets("packet", &packets);
+      panic!("expected no packets, got {}", packets.len());
+    }
+  }
+
+  /// Asserts that the server sent the given packet. Any other packets are
+  /// ignored, and all packets are dropped.
+  #[track_caller]
+  pub fn assert_received(&self, expected: impl Into<cb::Packet>) {
+    let expected = expected.into();
+    let packets = self.received();
+    if !packets.contains(&expected) {
+      log_packets("actual packet", &packets);
+      panic!("expected packet {expected:?}, but it was not sent");
+    }
+  }
+
+  /// Finds the first packet where `f` returns `Some`, and returns that value.
+  /// All packets are dropped. If no packets match, this panics.
+  ///
+  /// ```no_run
+  /// # use bb_common::net::cb;
+  /// # let client = bb_test::TestServer::new().join("macmv");
+  /// let state = client.expect("block update", |p| match p {
+  ///   cb::Packet::BlockUpdate { state, .. } => Some(*state),
+  ///   _ => None,
+  /// });
+  /// ```
+  #[track_caller]
+  pub fn expect<T>(&self, name: &str, f: impl FnMut(&cb::Packet) -> Option<T>) -> T {
+    let packets = self.received();
+    match packets.iter().find_map(f) {
+      Some(v) => v,
+      None => {
+        log_packets("actual packet", &packets);
+        panic!("expected a {name} packet, but none were sent");
+      }
+    }
+  }
+}
+
+impl fmt::Debug for TestClient {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("TestClient").field("username", self.player.username()).finish()
+  }
+}
+
+fn log_packets(prefix: &str, packets: &[cb::Packet]) {
+  if packets.is_empty() {
+    info!("no packets");
+  }
+  for p in packets {
+    info!("{prefix}: {p:?}");
+  }
+}
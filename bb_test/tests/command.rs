@@ -0,0 +1,31 @@
+use bb_common::{net::cb, util::GameMode};
+use bb_test::TestServer;
+
+#[test]
+fn game_mode() {
+  let server = TestServer::new();
+  let client = server.join("macmv");
+  client.command("gmc");
+  assert_eq!(client.player().game_mode(), GameMode::Creative);
+  client.assert_received(cb::packet::ChangeGameState {
+    action: cb::ChangeGameStateKind::GameMode(GameMode::Creative),
+  });
+}
+
+#[test]
+fn say() {
+  let server = TestServer::new();
+  let a = server.join("a");
+  let b = server.join("b");
+  a.command("say hello");
+  assert!(a.messages().contains(&"[Server] hello".to_string()));
+  assert!(b.messages().contains(&"[Server] hello".to_string()));
+}
+
+#[test]
+fn unknown_command() {
+  let server = TestServer::new();
+  let client = server.join("macmv");
+  client.command("foo");
+  assert_eq!(client.messages(), ["Unknown command: foo"]);
+}
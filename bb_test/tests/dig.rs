@@ -0,0 +1,63 @@
+use bb_common::{
+  math::Pos,
+  net::{cb, sb},
+  util::{Face, GameMode},
+};
+use bb_server::block;
+use bb_test::TestServer;
+
+fn dig(status: sb::DigStatus, pos: Pos) -> sb::Packet {
+  sb::Packet::BlockDig { pos, status, face: Face::Top, sequence: None }
+}
+
+#[test]
+fn creative_break() {
+  let server = TestServer::new();
+  let client = server.join("macmv");
+  client.player().set_game_mode(GameMode::Creative);
+  let pos = Pos::new(0, 60, 0);
+  server.world().set_kind(pos, block::Kind::Stone).unwrap();
+  client.clear();
+
+  client.send(dig(sb::DigStatus::Start, pos));
+  assert_eq!(server.world().get_kind(pos).unwrap(), block::Kind::Air);
+}
+
+#[test]
+fn survival_finish_too_early() {
+  let server = TestServer::new();
+  let client = server.join("macmv");
+  assert_eq!(client.player().game_mode(), GameMode::Survival);
+  let pos = Pos::new(0, 60, 0);
+  server.world().set_kind(pos, block::Kind::Stone).unwrap();
+  client.clear();
+
+  // Stone takes seconds to break by hand, so finishing right away shouldn't
+  // break it.
+  client.script([dig(sb::DigStatus::Start, pos), dig(sb::DigStatus::Finish, pos)]);
+  assert_eq!(server.world().get_kind(pos).unwrap(), block::Kind::Stone);
+}
+
+#[test]
+fn ack_after_block_update() {
+  let server = TestServer::new();
+  let client = server.join("macmv");
+  let pos = Pos::new(0, 60, 0);
+  server.world().set_kind(pos, block::Kind::Stone).unwrap();
+
+  // Adventure mode can't break anything, so the block is synced back to the
+  // client, and then the dig is acknowledged.
+  client.player().set_game_mode(GameMode::Adventure);
+  client.clear();
+  client.send(sb::Packet::BlockDig {
+    pos,
+    status: sb::DigStatus::Start,
+    face: Face::Top,
+    sequence: Some(3),
+  });
+  let packets = client.received();
+  let update = packets.iter().position(|p| matches!(p, cb::Packet::BlockUpdate { .. }));
+  let ack = packets.iter().position(|p| matches!(p, cb::Packet::AckBlockUpdate { sequence: 3 }));
+  assert!(update.is_some(), "block was not synced: {packets:?}");
+  assert!(ack > update, "dig was not acknowledged after the block update: {packets:?}");
+}
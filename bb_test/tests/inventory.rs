@@ -0,0 +1,64 @@
+use bb_common::net::{cb, sb};
+use bb_server::item;
+use bb_test::{TestClient, TestServer};
+
+fn left_click(slot: i16) -> sb::Packet { numbered_click(slot, None) }
+fn numbered_click(slot: i16, action: Option<i16>) -> sb::Packet {
+  sb::Packet::ClickWindow {
+    wid: 0,
+    slot,
+    mode: sb::ClickWindow::Click(sb::Button::Left),
+    revision: None,
+    action,
+  }
+}
+fn resynced(client: &TestClient) -> bool {
+  client.received().iter().any(|p| matches!(p, cb::Packet::WindowItems(_)))
+}
+
+#[test]
+fn move_stack() {
+  let server = TestServer::new();
+  let client = server.join("macmv");
+  let stack = item::Stack::new(item::Type::Stone).with_amount(5);
+  client.player().lock_inventory().set(36, stack.clone());
+  client.clear();
+
+  client.script([left_click(36), left_click(9)]);
+  let inv = client.player().lock_inventory();
+  assert_eq!(inv.get(36), Some(item::Stack::EMPTY));
+  assert_eq!(inv.get(9), Some(stack));
+  assert_eq!(inv.get(-999), Some(item::Stack::EMPTY));
+}
+
+#[test]
+fn merge_stacks() {
+  let server = TestServer::new();
+  let client = server.join("macmv");
+  let stack = item::Stack::new(item::Type::Stone);
+  client.player().lock_inventory().set(36, stack.clone().with_amount(40));
+  client.player().lock_inventory().set(37, stack.clone().with_amount(40));
+  client.clear();
+
+  // Merging two stacks of 40 leaves a full stack, and 16 items in the cursor.
+  client.script([left_click(36), left_click(37)]);
+  let inv = client.player().lock_inventory();
+  assert_eq!(inv.get(37), Some(stack.clone().with_amount(64)));
+  assert_eq!(inv.get(-999), Some(stack.with_amount(16)));
+}
+
+#[test]
+fn resync_after_missed_click() {
+  let server = TestServer::new();
+  let client = server.join("macmv");
+  client.player().lock_inventory().set(36, item::Stack::new(item::Type::Stone));
+  client.clear();
+
+  client.send(numbered_click(36, Some(1)));
+  assert!(!resynced(&client));
+  client.send(numbered_click(9, Some(2)));
+  assert!(!resynced(&client));
+  // Action 3 never arrived, so the client's inventory might not match.
+  client.send(numbered_click(36, Some(4)));
+  assert!(resynced(&client));
+}
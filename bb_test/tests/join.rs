@@ -0,0 +1,34 @@
+use bb_common::{net::cb, version::ProtocolVersion};
+use bb_server::config::Config;
+use bb_test::TestServer;
+
+fn full_server() -> TestServer {
+  TestServer::with_config(Config {
+    max_players: 1,
+    full_bypass: vec!["Admin".into()],
+    ..Default::default()
+  })
+}
+
+fn is_full(packets: &[cb::Packet]) -> bool {
+  packets.iter().any(|p| matches!(p, cb::Packet::ServerFull(_)))
+}
+
+#[test]
+fn server_full() {
+  let server = full_server();
+  let first = server.join_with_init("macmv", ProtocolVersion::V1_8);
+  assert!(!is_full(&first.received()));
+  let second = server.join_with_init("other", ProtocolVersion::V1_8);
+  assert!(is_full(&second.received()));
+  assert_eq!(server.wm().all_players().len(), 1);
+}
+
+#[test]
+fn full_bypass() {
+  let server = full_server();
+  let _first = server.join("macmv");
+  let admin = server.join_with_init("admin", ProtocolVersion::V1_8);
+  assert!(!is_full(&admin.received()));
+  assert_eq!(server.wm().all_players().len(), 2);
+}
@@ -41,9 +41,36 @@ num_impl!(u32, read_u32, write_u32);
 num_impl!(i32, read_i32, write_i32);
 num_impl!(u64, read_u64, write_u64);
 num_impl!(i64, read_i64, write_i64);
+num_impl!(u128, read_u128, write_u128);
+num_impl!(i128, read_i128, write_i128);
 num_impl!(f32, read_f32, write_f32);
 num_impl!(f64, read_f64, write_f64);
 
+/// A UUID. Any type that stores a UUID should read and write it with this, so
+/// that UUIDs are always encoded the same way.
+///
+/// This is written as a `u128`. UUIDs used to be written as 16 little endian
+/// bytes, so that is also accepted when reading.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Uuid(pub u128);
+
+impl MessageRead<'_> for Uuid {
+  fn read(m: &mut MessageReader) -> Result<Self, ReadError> {
+    if let Ok(bytes) = m.clone().read_bytes() {
+      if let Ok(bytes) = bytes.try_into() {
+        m.read_bytes()?;
+        return Ok(Uuid(u128::from_le_bytes(bytes)));
+      }
+    }
+    Ok(Uuid(m.read_u128()?))
+  }
+}
+impl MessageWrite for Uuid {
+  fn write<W: Write>(&self, m: &mut MessageWriter<W>) -> Result<(), WriteError> {
+    m.write_u128(self.0)
+  }
+}
+
 macro_rules! num_nonzero_impl {
   ($ty:ty, $read:ident, $write:ident) => {
     impl MessageRead<'_> for $ty {
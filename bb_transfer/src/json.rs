@@ -0,0 +1,309 @@
+//! A text representation of messages. This converts a message to and from
+//! JSON, without knowing anything about the type that wrote it. This is useful
+//! for inspecting recorded messages, and for writing test fixtures by hand.
+//!
+//! Every field keeps its [`Header`], so converting a message to JSON and back
+//! will produce the exact same bytes. Fields are represented like so:
+//!
+//! Header   | JSON
+//! ---------|-----
+//! `None`   | `null`
+//! `VarInt` | `5`, or `{ "varint": "<digits>" }` if it doesn't fit in a `u64`
+//! `Float`  | `{ "f32": 1.5 }`
+//! `Double` | `{ "f64": 1.5 }`
+//! `Struct` | `{ "0": 5, "1": "hello" }`
+//! `Enum`   | `{ "enum": 2, "data": { "0": 5 } }`
+//! `Bytes`  | `"hello"`, or `{ "bytes": [255, 0] }` if it isn't valid UTF-8
+//! `List`   | `[1, 2, 3]`
+//!
+//! Struct fields are keyed by their index. When converting from JSON, any
+//! fields that are left out are written as `None`, which readers treat as a
+//! missing field.
+//!
+//! Note that `VarInt`s are the number on the wire, so signed numbers are
+//! [zig-zag](crate::ZigZag) encoded. For example, `-1_i32` is written as `1`.
+//! Booleans are also `VarInt`s, so `true` and `false` in JSON are read as `1`
+//! and `0`.
+//!
+//! Floats that JSON can't represent (infinities and NaN) are written as
+//! strings, like `{ "f32": "NaN" }`.
+
+use super::{
+  Header, MessageRead, MessageReader, MessageWrite, MessageWriter, ReadError, WriteError,
+};
+use serde_json::{Map, Number, Value};
+use std::{error::Error, fmt, io::Write};
+
+/// An error while converting a message to or from JSON.
+#[derive(Debug)]
+pub enum JsonError {
+  /// The message couldn't be read, or the JSON couldn't be read as the
+  /// expected type.
+  Read(ReadError),
+  /// The message couldn't be written.
+  Write(WriteError),
+  /// The JSON doesn't describe a valid field. The string is a description of
+  /// the invalid JSON.
+  Invalid(String),
+}
+
+impl fmt::Display for JsonError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Read(e) => write!(f, "{e}"),
+      Self::Write(e) => write!(f, "{e}"),
+      Self::Invalid(e) => write!(f, "invalid json message: {e}"),
+    }
+  }
+}
+
+impl Error for JsonError {}
+
+impl From<ReadError> for JsonError {
+  fn from(e: ReadError) -> Self { JsonError::Read(e) }
+}
+impl From<WriteError> for JsonError {
+  fn from(e: WriteError) -> Self { JsonError::Write(e) }
+}
+
+/// Writes `value` as a message, and then converts that message to JSON. See
+/// the [module](self) docs for the format.
+pub fn to_json<T>(value: &T) -> Result<Value, JsonError>
+where
+  T: ?Sized + MessageWrite,
+{
+  let mut data = vec![];
+  MessageWriter::new(&mut data).write(value)?;
+  Ok(MessageReader::new(&data).read_json()?)
+}
+
+/// Converts the JSON to a message, and then reads a `T` from that message. See
+/// the [module](self) docs for the format.
+pub fn from_json<T>(json: &Value) -> Result<T, JsonError>
+where
+  T: for<'a> MessageRead<'a>,
+{
+  let mut data = vec![];
+  MessageWriter::new(&mut data).write_json(json)?;
+  Ok(MessageReader::new(&data).read()?)
+}
+
+fn float_json(v: f64) -> Value {
+  match Number::from_f64(v) {
+    Some(n) => Value::Number(n),
+    None => Value::String(v.to_string()),
+  }
+}
+
+fn json_float(json: &Value) -> Result<f64, JsonError> {
+  match json {
+    Value::Number(n) => n.as_f64(),
+    Value::String(s) => s.parse().ok(),
+    _ => None,
+  }
+  .ok_or_else(|| JsonError::Invalid(format!("expected a float, got {json}")))
+}
+
+impl MessageReader<'_> {
+  /// Reads a single field, and converts it to JSON. See the
+  /// [module](crate::json) docs for the format.
+  pub fn read_json(&mut self) -> Result<Value, ReadError> { self.read_json_at(0) }
+
+  fn read_json_at(&mut self, depth: u32) -> Result<Value, ReadError> {
+    let (header, extra) = self.read_header()?;
+    Ok(match header {
+      Header::None => Value::Null,
+      Header::VarInt => {
+        let v = self.read_varint128(extra)?;
+        match u64::try_from(v) {
+          Ok(v) => Value::Number(v.into()),
+          Err(_) => {
+            let mut map = Map::new();
+            map.insert("varint".into(), Value::String(v.to_string()));
+            Value::Object(map)
+          }
+        }
+      }
+      Header::Float => {
+        let mut map = Map::new();
+        map.insert("f32".into(), float_json(self.read_float()?.into()));
+        Value::Object(map)
+      }
+      Header::Double => {
+        let mut map = Map::new();
+        map.insert("f64".into(), float_json(self.read_double()?));
+        Value::Object(map)
+      }
+      Header::Struct => {
+        let num_fields = self.read_varint(extra)?;
+        self.check_depth(depth + 1)?;
+        let mut map = Map::new();
+        for i in 0..num_fields {
+          map.insert(i.to_string(), self.read_json_at(depth + 1)?);
+        }
+        Value::Object(map)
+      }
+      Header::Enum => {
+        let variant = self.read_varint(extra)?;
+        let mut map = Map::new();
+        map.insert("enum".into(), Value::Number(variant.into()));
+        self.check_depth(depth + 1)?;
+        map.insert("data".into(), self.read_json_at(depth + 1)?);
+        Value::Object(map)
+      }
+      Header::Bytes => {
+        let len = self.read_varint(extra)?;
+        self.check_bytes(len)?;
+        let data = self.read_buf(len as usize)?;
+        match std::str::from_utf8(data) {
+          Ok(s) => Value::String(s.into()),
+          Err(_) => {
+            let mut map = Map::new();
+            map.insert("bytes".into(), data.iter().map(|&b| Value::from(b)).collect());
+            Value::Object(map)
+          }
+        }
+      }
+      Header::List => {
+        let len = self.read_varint(extra)?;
+        self.check_list(len)?;
+        self.check_depth(depth + 1)?;
+        Value::Array((0..len).map(|_| self.read_json_at(depth + 1)).collect::<Result<_, _>>()?)
+      }
+    })
+  }
+}
+
+impl<W> MessageWriter<W>
+where
+  W: Write,
+{
+  /// Writes a single field from the given JSON. See the [module](crate::json)
+  /// docs for the format.
+  pub fn write_json(&mut self, json: &Value) -> Result<(), JsonError> {
+    match json {
+      Value::Null => self.write_header(Header::None, 0)?,
+      Value::Bool(v) => self.write_bool(*v)?,
+      Value::Number(n) => match n.as_u64() {
+        Some(v) => self.write_u64(v)?,
+        None => {
+          return Err(JsonError::Invalid(format!(
+            "{n} is not a varint (varints are unsigned, and floats are written as {{ \"f64\": {n} }})"
+          )))
+        }
+      },
+      Value::String(s) => self.write_str(s)?,
+      Value::Array(list) => {
+        self.write_header(Header::List, list.len() as u64)?;
+        self.write_varint(list.len() as u64)?;
+        for v in list {
+          self.write_json(v)?;
+        }
+      }
+      Value::Object(map) => {
+        if let Some(v) = map.get("varint") {
+          match v.as_str().and_then(|v| v.parse().ok()) {
+            Some(v) => self.write_u128(v)?,
+            None => return Err(JsonError::Invalid(format!("invalid varint {v}"))),
+          }
+        } else if let Some(v) = map.get("f32") {
+          self.write_f32(json_float(v)? as f32)?;
+        } else if let Some(v) = map.get("f64") {
+          self.write_f64(json_float(v)?)?;
+        } else if let Some(bytes) = map.get("bytes") {
+          let bytes: Option<Vec<u8>> = bytes.as_array().and_then(|list| {
+            list.iter().map(|v| v.as_u64().and_then(|v| v.try_into().ok())).collect()
+          });
+          match bytes {
+            Some(bytes) => self.write_bytes(&bytes)?,
+            None => return Err(JsonError::Invalid(format!("expected a list of bytes in {json}"))),
+          }
+        } else if let Some(variant) = map.get("enum") {
+          let Some(variant) = variant.as_u64() else {
+            return Err(JsonError::Invalid(format!("invalid enum variant {variant}")));
+          };
+          self.write_header(Header::Enum, variant)?;
+          self.write_varint(variant)?;
+          self.write_json(map.get("data").unwrap_or(&Value::Null))?;
+        } else {
+          self.write_json_struct(map)?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Writes a struct, where the keys of `map` are the field indices. Any
+  /// missing fields are written as `None`.
+  fn write_json_struct(&mut self, map: &Map<String, Value>) -> Result<(), JsonError> {
+    let mut fields = map
+      .iter()
+      .map(|(k, v)| match k.parse::<u64>() {
+        Ok(i) => Ok((i, v)),
+        Err(_) => Err(JsonError::Invalid(format!("invalid struct field index `{k}`"))),
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    fields.sort_by_key(|(i, _)| *i);
+    let num_fields = fields.last().map(|(i, _)| i + 1).unwrap_or(0);
+    self.write_header(Header::Struct, num_fields)?;
+    self.write_varint(num_fields)?;
+    let mut fields = fields.into_iter().peekable();
+    for i in 0..num_fields {
+      match fields.next_if(|(idx, _)| *idx == i) {
+        Some((_, v)) => self.write_json(v)?,
+        None => self.write_header(Header::None, 0)?,
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn round_trip(json: Value) {
+    let mut data = vec![];
+    MessageWriter::new(&mut data).write_json(&json).unwrap();
+    assert_eq!(MessageReader::new(&data).read_json().unwrap(), json);
+  }
+
+  #[test]
+  fn values() {
+    assert_eq!(to_json(&5_u32).unwrap(), json!(5));
+    assert_eq!(to_json(&-1_i32).unwrap(), json!(1));
+    assert_eq!(to_json(&1.5_f32).unwrap(), json!({ "f32": 1.5 }));
+    assert_eq!(to_json(&f64::INFINITY).unwrap(), json!({ "f64": "inf" }));
+    assert_eq!(to_json(&"hello").unwrap(), json!("hello"));
+    assert_eq!(to_json(&vec![1_u8, 2, 3]).unwrap(), json!([1, 2, 3]));
+    assert_eq!(to_json(&Some(3_u32)).unwrap(), json!({ "enum": 1, "data": { "0": 3 } }));
+    assert_eq!(to_json(&(2_u32, "a".to_string())).unwrap(), json!({ "0": 2, "1": "a" }));
+
+    assert_eq!(to_json(&u128::MAX).unwrap(), json!({ "varint": u128::MAX.to_string() }));
+
+    round_trip(json!({ "f64": 0.25 }));
+    round_trip(json!({ "bytes": [255, 0] }));
+    round_trip(json!({ "enum": 0, "data": {} }));
+    round_trip(json!([{ "0": null, "1": [] }]));
+  }
+
+  #[test]
+  fn fixtures() {
+    let v: Option<Vec<String>> =
+      from_json(&json!({ "enum": 1, "data": { "0": ["a", "b"] } })).unwrap();
+    assert_eq!(v, Some(vec!["a".to_string(), "b".to_string()]));
+    assert!(from_json::<f32>(&json!({ "f32": "NaN" })).unwrap().is_nan());
+
+    // Missing fields are written as `None`.
+    let mut data = vec![];
+    MessageWriter::new(&mut data).write_json(&json!({ "2": 7 })).unwrap();
+    assert_eq!(
+      MessageReader::new(&data).read_json().unwrap(),
+      json!({ "0": null, "1": null, "2": 7 })
+    );
+
+    assert!(matches!(from_json::<u32>(&json!(1.5)), Err(JsonError::Invalid(_))));
+    assert!(matches!(from_json::<u32>(&json!({ "x": 1 })), Err(JsonError::Invalid(_))));
+  }
+}
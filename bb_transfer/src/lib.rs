@@ -29,7 +29,8 @@
 // Each of the read/write functions here just writes those bytes to the buffer,
 // without any type of length or type prefix.
 //
-// Variable field encoding is used on `i32`, `u32`, `i64`, and `u64`. This
+// Variable field encoding is used on `i32`, `u32`, `i64`, `u64`, `i128`, and
+// `u128`. This
 // means that the field will only use a certain number of bytes depending on
 // how large it is. So a value of 0 will only use 1 byte, while a value of 500
 // uses 2 bytes. This uses varint encoding internally. See below for the
@@ -41,12 +42,15 @@
 // after that.
 
 mod generics;
+pub mod json;
 mod read;
 mod write;
 
+pub use generics::Uuid;
+pub use json::{from_json, to_json, JsonError};
 pub use read::{
-  EnumRead, EnumReader, InvalidReadError, MessageRead, MessageReader, ReadError, StructRead,
-  StructReader, ValidReadError,
+  EnumRead, EnumReader, InvalidReadError, Limit, MessageRead, MessageReader, ReadError, ReadLimits,
+  StructRead, StructReader, ValidReadError,
 };
 pub use write::{MessageWrite, MessageWriter, WriteError};
 
@@ -233,6 +237,15 @@ impl ZigZag for i64 {
   fn zag(n: u64) -> i64 { (n >> 1) as i64 ^ -((n & 1) as i64) }
 }
 
+impl ZigZag for i128 {
+  type Unsigned = u128;
+
+  #[inline(always)]
+  fn zig(n: i128) -> u128 { ((n << 1) ^ (n >> 127)) as u128 }
+  #[inline(always)]
+  fn zag(n: u128) -> i128 { (n >> 1) as i128 ^ -((n & 1) as i128) }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -267,6 +280,41 @@ mod tests {
     assert_eq!(m.read_f64().unwrap(), 3.456);
   }
 
+  #[test]
+  fn large_ints() {
+    for v in [0, 15, 16, u64::MAX as u128, u64::MAX as u128 + 1, u128::MAX] {
+      let mut buf = vec![];
+      MessageWriter::new(&mut buf).write_u128(v).unwrap();
+      assert_eq!(MessageReader::new(&buf).read_u128().unwrap(), v);
+    }
+    for v in [0, -1, i128::MIN, i128::MAX] {
+      let mut buf = vec![];
+      MessageWriter::new(&mut buf).write_i128(v).unwrap();
+      assert_eq!(MessageReader::new(&buf).read_i128().unwrap(), v);
+    }
+
+    // Small numbers are encoded the same way as a `u64`.
+    let mut buf = vec![];
+    MessageWriter::new(&mut buf).write_u128(123525).unwrap();
+    assert_eq!(MessageReader::new(&buf).read_u64().unwrap(), 123525);
+    let mut buf = vec![];
+    MessageWriter::new(&mut buf).write_u128(u128::MAX).unwrap();
+    assert!(MessageReader::new(&buf).read_u64().is_err());
+  }
+
+  #[test]
+  fn uuids() {
+    let id = Uuid(0x11111111222233334444555555555555);
+    let mut buf = vec![];
+    MessageWriter::new(&mut buf).write(&id).unwrap();
+    assert_eq!(MessageReader::new(&buf).read::<Uuid>().unwrap(), id);
+
+    // UUIDs used to be written as bytes, which can still be read.
+    let mut buf = vec![];
+    MessageWriter::new(&mut buf).write_bytes(&id.0.to_le_bytes()).unwrap();
+    assert_eq!(MessageReader::new(&buf).read::<Uuid>().unwrap(), id);
+  }
+
   #[test]
   fn read_write() {
     let mut buf = [0; 5];
@@ -60,6 +60,48 @@ pub enum InvalidReadError {
   InvalidHeader(u8),
   /// This happens if we try to read something and there are no bytes left.
   EOF,
+  /// A field went over one of the reader's [`ReadLimits`]. The number is the
+  /// length (or depth) of the field. This is returned before anything is
+  /// allocated for the field.
+  LimitExceeded(Limit, u64),
+}
+
+/// One of the limits in [`ReadLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+  Bytes,
+  List,
+  Depth,
+}
+
+/// Limits on what a [`MessageReader`] will read. Messages from another process
+/// (or from disk) could be corrupted, or could be sent by someone malicious, so
+/// these stop a small message from making the reader allocate a huge list, or
+/// recurse until the stack overflows.
+///
+/// The default only limits the depth, so that every reader is safe from stack
+/// overflows. Use [`untrusted`](Self::untrusted) for messages from the network
+/// or from disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadLimits {
+  /// The longest byte array or string that can be read, in bytes.
+  pub max_bytes: u64,
+  /// The longest list that can be read.
+  pub max_list:  u64,
+  /// The deepest that structs, enums, and lists can be nested.
+  pub max_depth: u32,
+}
+
+impl Default for ReadLimits {
+  fn default() -> Self { ReadLimits { max_bytes: u64::MAX, max_list: u64::MAX, max_depth: 128 } }
+}
+
+impl ReadLimits {
+  /// Limits that are large enough for any message we send, but small enough
+  /// that a malicious message can't use much memory.
+  pub fn untrusted() -> Self {
+    ReadLimits { max_bytes: 2 * 1024 * 1024, max_list: 1 << 16, max_depth: 64 }
+  }
 }
 
 impl fmt::Display for ReadError {
@@ -98,6 +140,16 @@ impl fmt::Display for InvalidReadError {
         write!(f, "failed to read field: invalid header {header:#x}")
       }
       Self::EOF => write!(f, "failed to read field: eof reached"),
+      Self::LimitExceeded(limit, len) => write!(f, "failed to read field: {limit} (got {len})"),
+    }
+  }
+}
+impl fmt::Display for Limit {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::Bytes => write!(f, "byte array is too long"),
+      Self::List => write!(f, "list is too long"),
+      Self::Depth => write!(f, "fields are nested too deep"),
     }
   }
 }
@@ -128,7 +180,7 @@ impl MessageReader<'_> {
     match header {
       Header::None => write!(f, "None").unwrap(),
       Header::VarInt => {
-        let v = self.read_varint(extra)?;
+        let v = self.read_varint128(extra)?;
         write!(f, "VarInt({v} {v:#x})").unwrap();
       }
       Header::Float => {
@@ -233,8 +285,11 @@ pub trait EnumRead<'a> {
 /// See the [crate] level docs for how fields are decoded.
 #[derive(Clone)]
 pub struct MessageReader<'a> {
-  data: &'a [u8],
-  idx:  usize,
+  data:   &'a [u8],
+  idx:    usize,
+  limits: ReadLimits,
+  /// How many structs, enums, or lists this reader is inside of.
+  depth:  u32,
 }
 
 /// Wrapper around a partially parsed struct. This will validate that all fields
@@ -282,7 +337,46 @@ impl<'a> MessageReader<'a> {
   /// can call `index`, and know that this will not have read any data past that
   /// index.
   #[inline(always)]
-  pub fn new(data: &[u8]) -> MessageReader { MessageReader { data, idx: 0 } }
+  pub fn new(data: &[u8]) -> MessageReader {
+    MessageReader::with_limits(data, ReadLimits::default())
+  }
+  /// Creates a new MessageReader, which will return
+  /// [`InvalidReadError::LimitExceeded`] if any field goes over the given
+  /// limits.
+  pub fn with_limits(data: &[u8], limits: ReadLimits) -> MessageReader {
+    MessageReader { data, idx: 0, limits, depth: 0 }
+  }
+
+  /// Returns the limits of this reader.
+  pub fn limits(&self) -> ReadLimits { self.limits }
+
+  /// Returns a reader for the fields inside a struct, enum, or list, starting
+  /// at `idx`.
+  fn nested(&self, idx: usize) -> InvalidResult<MessageReader<'a>> {
+    self.check_depth(self.depth + 1)?;
+    Ok(MessageReader { data: self.data, idx, limits: self.limits, depth: self.depth + 1 })
+  }
+  pub(crate) fn check_depth(&self, depth: u32) -> InvalidResult<()> {
+    if depth > self.limits.max_depth {
+      Err(InvalidReadError::LimitExceeded(Limit::Depth, depth.into()))
+    } else {
+      Ok(())
+    }
+  }
+  pub(crate) fn check_bytes(&self, len: u64) -> InvalidResult<()> {
+    if len > self.limits.max_bytes {
+      Err(InvalidReadError::LimitExceeded(Limit::Bytes, len))
+    } else {
+      Ok(())
+    }
+  }
+  pub(crate) fn check_list(&self, len: u64) -> InvalidResult<()> {
+    if len > self.limits.max_list {
+      Err(InvalidReadError::LimitExceeded(Limit::List, len))
+    } else {
+      Ok(())
+    }
+  }
 
   /// Returns the current index the reader is at. This byte has not been read,
   /// but will be read the next time any `read_` functions are called.
@@ -318,7 +412,7 @@ impl<'a> MessageReader<'a> {
   ///
   /// This is private, as the caller can break the state of this reader if they
   /// do not handle the result correctly.
-  fn read_header(&mut self) -> InvalidResult<(Header, u8)> {
+  pub(crate) fn read_header(&mut self) -> InvalidResult<(Header, u8)> {
     let val = self.read_byte()?;
     Ok((Header::from_id(val & 0x07).ok_or(InvalidReadError::InvalidHeader(val & 0x07))?, val >> 3))
   }
@@ -332,12 +426,15 @@ impl<'a> MessageReader<'a> {
   }
 
   /// Skips a single field.
-  pub fn skip_field(&mut self) -> InvalidResult<()> {
+  pub fn skip_field(&mut self) -> InvalidResult<()> { self.skip_field_at(self.depth) }
+
+  /// Skips a single field, which is nested `depth` deep.
+  fn skip_field_at(&mut self, depth: u32) -> InvalidResult<()> {
     let (header, extra) = self.read_header()?;
     match header {
       Header::None => {}
       Header::VarInt => {
-        self.read_varint(extra)?;
+        self.read_varint128(extra)?;
       }
       Header::Float => {
         self.read_float()?;
@@ -347,11 +444,15 @@ impl<'a> MessageReader<'a> {
       }
       Header::Struct => {
         let num_fields = self.read_varint(extra)?;
-        self.skip_fields(num_fields)?;
+        self.check_depth(depth + 1)?;
+        for _ in 0..num_fields {
+          self.skip_field_at(depth + 1)?;
+        }
       }
       Header::Enum => {
         let _variant = self.read_varint(extra)?;
-        self.skip_field()?;
+        self.check_depth(depth + 1)?;
+        self.skip_field_at(depth + 1)?;
       }
       Header::Bytes => {
         let len = self.read_varint(extra)? as usize;
@@ -359,7 +460,10 @@ impl<'a> MessageReader<'a> {
       }
       Header::List => {
         let len = self.read_varint(extra)?;
-        self.skip_fields(len)?;
+        self.check_depth(depth + 1)?;
+        for _ in 0..len {
+          self.skip_field_at(depth + 1)?;
+        }
       }
     }
     Ok(())
@@ -381,7 +485,7 @@ impl<'a> MessageReader<'a> {
   /// the 5th bit (0x10) is not set, this will not read anything.
   ///
   /// This is private, as this is doesn't read a `Header`.
-  fn read_varint(&mut self, header: u8) -> InvalidResult<u64> {
+  pub(crate) fn read_varint(&mut self, header: u8) -> InvalidResult<u64> {
     if header & 0x10 == 0 {
       return Ok(header.into());
     }
@@ -404,11 +508,39 @@ impl<'a> MessageReader<'a> {
     }
     Ok(out)
   }
+  /// Reads a varint that may be up to 128 bits. This is the same as
+  /// [`read_varint`](Self::read_varint), but it allows more bytes to be read.
+  /// Numbers that fit in a `u64` are encoded in the same way.
+  ///
+  /// This is private, as this is doesn't read a `Header`.
+  pub(crate) fn read_varint128(&mut self, header: u8) -> InvalidResult<u128> {
+    if header & 0x10 == 0 {
+      return Ok(header.into());
+    }
+
+    let mut out = header as u128 & 0x0f;
+    let mut i = 0;
+    let mut v;
+    loop {
+      v = self.read_byte()?;
+      let done = v & 0x80 == 0;
+      out |= ((v as u128) & !0x80) << (i * 7 + 4);
+      if done {
+        break;
+      }
+      i += 1;
+      // (128 - 5) / 7 = 17.57, so we need 18 bytes of space
+      if i >= 18 {
+        return Err(InvalidReadError::VarIntTooLong);
+      }
+    }
+    Ok(out)
+  }
   /// Reads a float from the buffer. This will simply read 4 bytes, and convert
   /// them into a float.
   ///
   /// This is private, as it doesn't read a `Header`.
-  fn read_float(&mut self) -> InvalidResult<f32> {
+  pub(crate) fn read_float(&mut self) -> InvalidResult<f32> {
     let n = self.read_byte()? as u32
       | (self.read_byte()? as u32) << 8
       | (self.read_byte()? as u32) << 16
@@ -419,7 +551,7 @@ impl<'a> MessageReader<'a> {
   /// them into a double.
   ///
   /// This is private, as it doesn't read a `Header`.
-  fn read_double(&mut self) -> InvalidResult<f64> {
+  pub(crate) fn read_double(&mut self) -> InvalidResult<f64> {
     let n = self.read_byte()? as u64
       | (self.read_byte()? as u64) << 8
       | (self.read_byte()? as u64) << 16
@@ -432,7 +564,7 @@ impl<'a> MessageReader<'a> {
   }
 
   /// Reads the given number of bytes from the buffer.
-  fn read_buf(&mut self, len: usize) -> InvalidResult<&'a [u8]> {
+  pub(crate) fn read_buf(&mut self, len: usize) -> InvalidResult<&'a [u8]> {
     if self.idx + len > self.data.len() {
       Err(InvalidReadError::InvalidBufLength)
     } else {
@@ -528,10 +660,24 @@ impl<'a> MessageReader<'a> {
     }
   }
 
+  /// Reads a `u128` from the internal buffer. This is the same as
+  /// [`read_u64`](Self::read_u64), but allows larger varints.
+  pub fn read_u128(&mut self) -> Result<u128> {
+    let (header, extra) = self.read_header()?;
+    if header != Header::VarInt {
+      Err(ValidReadError::WrongMessage(header, Header::VarInt).into())
+    } else {
+      self.read_varint128(extra).map_err(Into::into)
+    }
+  }
+
   read_signed!(read_i8, i8);
   read_signed!(read_i16, i16);
   read_signed!(read_i32, i32);
   read_signed!(read_i64, i64);
+  /// Reads an `i128`. This reads a `u128`, and then decodes it with zig zag
+  /// encoding.
+  pub fn read_i128(&mut self) -> Result<i128> { self.read_u128().map(zag) }
 
   /// Reads a float. This will return an error if the header read is not a
   /// `Float` header.
@@ -567,7 +713,7 @@ impl<'a> MessageReader<'a> {
         // StructReader is dropped before reading all fields.
         self.skip_fields(max_fields)?;
         S::read_struct(StructReader {
-          reader: MessageReader { data: self.data, idx: start_idx },
+          reader: self.nested(start_idx)?,
           current_field: 0,
           max_fields,
         })
@@ -597,11 +743,7 @@ impl<'a> MessageReader<'a> {
         // `read_struct`. This ensures that we stay in a valid state, even if the
         // StructReader is dropped before reading all fields.
         self.skip_fields(max_fields)?;
-        f(StructReader {
-          reader: MessageReader { data: self.data, idx: start_idx },
-          current_field: 0,
-          max_fields,
-        })
+        f(StructReader { reader: self.nested(start_idx)?, current_field: 0, max_fields })
       }
       m => {
         // We must keep the buffer at a valid state, so we undo the `read_header` call
@@ -628,7 +770,7 @@ impl<'a> MessageReader<'a> {
             // StructReader is dropped before reading all fields.
             self.skip_fields(max_fields)?;
             E::read_enum(EnumReader {
-              reader: MessageReader { data: self.data, idx: start_idx },
+              reader: self.nested(start_idx)?,
               variant,
               current_field: 0,
               max_fields,
@@ -666,12 +808,7 @@ impl<'a> MessageReader<'a> {
             // `read_struct`. This ensures that we stay in a valid state, even if the
             // StructReader is dropped before reading all fields.
             self.skip_fields(max_fields)?;
-            f(EnumReader {
-              reader: MessageReader { data: self.data, idx: start_idx },
-              variant,
-              current_field: 0,
-              max_fields,
-            })
+            f(EnumReader { reader: self.nested(start_idx)?, variant, current_field: 0, max_fields })
           }
           m => {
             // We must keep the buffer at a valid state, so we undo the `read_header` call
@@ -699,6 +836,7 @@ impl<'a> MessageReader<'a> {
       Err(ValidReadError::WrongMessage(header, Header::Bytes).into())
     } else {
       let len = self.read_varint(extra)?;
+      self.check_bytes(len)?;
       self.read_buf(len as usize).map_err(Into::into)
     }
   }
@@ -714,8 +852,9 @@ impl<'a> MessageReader<'a> {
       Err(ValidReadError::WrongMessage(header, Header::Bytes).into())
     } else {
       let len = self.read_varint(extra)?;
+      self.check_list(len)?;
       let reader = ListReader {
-        reader: MessageReader { data: self.data, idx: self.idx },
+        reader: self.nested(self.idx)?,
         current: 0,
         len,
         phantom: PhantomData::default(),
@@ -736,11 +875,16 @@ impl<'a> MessageReader<'a> {
       Err(ValidReadError::WrongMessage(header, Header::Bytes).into())
     } else {
       let len = self.read_varint(extra)?;
+      self.check_list(len)?;
+      self.check_depth(self.depth + 1)?;
+      self.depth += 1;
       let mut list = vec![];
-      for _ in 0..len {
+      let res = (0..len).try_for_each(|_| {
         list.push(f(self)?);
-      }
-      Ok(list)
+        Ok(())
+      });
+      self.depth -= 1;
+      res.map(|()| list)
     }
   }
 }
@@ -1257,4 +1401,41 @@ mod tests {
     assert_eq!(m.read_str().unwrap(), "hello");
     assert_eq!(m.index(), 6);
   }
+
+  #[test]
+  fn limits() {
+    let limits = ReadLimits { max_bytes: 4, max_list: 2, max_depth: 2 };
+
+    let msg = [0b110 | 5 << 3, b'h', b'e', b'l', b'l', b'o'];
+    let mut m = MessageReader::with_limits(&msg, limits);
+    assert!(matches!(
+      m.read_str().unwrap_err(),
+      ReadError::Invalid(InvalidReadError::LimitExceeded(Limit::Bytes, 5))
+    ));
+
+    let msg = [0b111 | 3 << 3, 0b001, 0b001, 0b001];
+    let mut m = MessageReader::with_limits(&msg, limits);
+    assert!(matches!(
+      m.read::<Vec<u8>>().unwrap_err(),
+      ReadError::Invalid(InvalidReadError::LimitExceeded(Limit::List, 3))
+    ));
+    let mut m = MessageReader::new(&msg);
+    assert_eq!(m.read::<Vec<u8>>().unwrap(), vec![0, 0, 0]);
+
+    // A list in a list in a list.
+    let msg = [0b111 | 1 << 3, 0b111 | 1 << 3, 0b111 | 1 << 3, 0b001];
+    let mut m = MessageReader::with_limits(&msg, limits);
+    assert!(matches!(
+      m.read::<Vec<Vec<Vec<u8>>>>().unwrap_err(),
+      ReadError::Invalid(InvalidReadError::LimitExceeded(Limit::Depth, 3))
+    ));
+    let mut m = MessageReader::with_limits(&msg, limits);
+    assert!(matches!(m.skip_field(), Err(InvalidReadError::LimitExceeded(Limit::Depth, 3))));
+
+    // Deeply nested messages shouldn't overflow the stack, even without any
+    // limits set.
+    let msg = vec![0b111 | 1 << 3; 100_000];
+    let mut m = MessageReader::new(&msg);
+    assert!(matches!(m.skip_field(), Err(InvalidReadError::LimitExceeded(Limit::Depth, _))));
+  }
 }
@@ -103,7 +103,7 @@ where
   ///
   /// This is private, as the caller can break the state of this reader if they
   /// do not handle the result correctly.
-  fn write_header(&mut self, header: Header, mut num: u64) -> Result {
+  pub(crate) fn write_header(&mut self, header: Header, mut num: u64) -> Result {
     if num >= 16 {
       num |= 0x10;
     } else {
@@ -126,7 +126,7 @@ where
   /// written using [`write_header`].
   ///
   /// This is private, as this is doesn't read a `Header`.
-  fn write_varint(&mut self, mut v: u64) -> Result {
+  pub(crate) fn write_varint(&mut self, mut v: u64) -> Result {
     if v < 16 {
       return Ok(());
     }
@@ -143,11 +143,32 @@ where
     }
     Ok(())
   }
+  /// Writes a varint that may be up to 128 bits. Numbers that fit in a `u64`
+  /// are written the same way as [`write_varint`](Self::write_varint).
+  ///
+  /// This is private, as this is doesn't read a `Header`.
+  pub(crate) fn write_varint128(&mut self, mut v: u128) -> Result {
+    if v < 16 {
+      return Ok(());
+    }
+    v >>= 4;
+
+    loop {
+      if v >= 128 {
+        self.write_byte(0x80 | v as u8 & !0x80)?;
+        v >>= 7;
+      } else {
+        self.write_byte(v as u8 & !0x80)?;
+        break;
+      }
+    }
+    Ok(())
+  }
   /// Writes a float to the buffer. This will simply write the 4 bytes of the
   /// float.
   ///
   /// This is private, as it doesn't read a `Header`.
-  fn write_float(&mut self, v: f32) -> Result {
+  pub(crate) fn write_float(&mut self, v: f32) -> Result {
     let n = v.to_bits();
     self.write_byte(n as u8)?;
     self.write_byte((n >> 8) as u8)?;
@@ -159,7 +180,7 @@ where
   /// bytes.
   ///
   /// This is private, as it doesn't read a `Header`.
-  fn write_double(&mut self, v: f64) -> Result {
+  pub(crate) fn write_double(&mut self, v: f64) -> Result {
     let n = v.to_bits();
     self.write_byte(n as u8)?;
     self.write_byte((n >> 8) as u8)?;
@@ -173,7 +194,7 @@ where
   }
 
   /// Writes the given number of bytes from the buffer.
-  fn write_buf(&mut self, buf: &[u8]) -> Result {
+  pub(crate) fn write_buf(&mut self, buf: &[u8]) -> Result {
     // We only use a MessageWriter<T> where T is either a &[u8] or a &mut Vec<u8>.
     // This means this write_all call should be fast, as it will simply call `write`
     // once.
@@ -207,6 +228,14 @@ where
     self.write_header(Header::VarInt, v)?;
     self.write_varint(v)
   }
+  /// Writes an unsigned 128 bit integer to the internal buffer. Numbers that
+  /// fit in a `u64` are written the same way as [`write_u64`](Self::write_u64).
+  pub fn write_u128(&mut self, v: u128) -> Result {
+    // `write_header` only uses the bottom 4 bits, and if the number is 16 or
+    // larger.
+    self.write_header(Header::VarInt, if v < 16 { v as u64 } else { 0x10 | (v as u64 & 0x0f) })?;
+    self.write_varint128(v)
+  }
   /// Writes a single signed byte to the internal buffer.
   pub fn write_i8(&mut self, v: i8) -> Result { self.write_u8(zig(v)) }
   /// Writes a signed 16 bit integer to the internal buffer.
@@ -217,6 +246,9 @@ where
   /// Writes a signed 64 bit integer to the internal buffer. This encodes the
   /// value with zig zag encoding, and then writes that as a u64.
   pub fn write_i64(&mut self, v: i64) -> Result { self.write_u64(zig(v)) }
+  /// Writes a signed 128 bit integer to the internal buffer. This encodes the
+  /// value with zig zag encoding, and then writes that as a u128.
+  pub fn write_i128(&mut self, v: i128) -> Result { self.write_u128(zig(v)) }
 
   pub fn write_f32(&mut self, v: f32) -> Result {
     self.write_header(Header::Float, 0)?;
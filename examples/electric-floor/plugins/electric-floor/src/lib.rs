@@ -25,10 +25,10 @@ extern "C" fn init() {
 
   let world = bb_plugin::world::World::new(0);
   for pos in MIN.to(MAX) {
-    world.set_block_kind(pos, block::Kind::WhiteStainedGlass);
-    world.set_block(pos.add_y(1), block::Type::air());
-    world.set_block(pos.add_y(2), block::Type::air());
-    world.set_block(pos.add_y(3), block::Type::air());
+    world.set_block_kind(pos, block::Kind::WhiteStainedGlass).unwrap();
+    world.set_block(pos.add_y(1), block::Type::air()).unwrap();
+    world.set_block(pos.add_y(2), block::Type::air()).unwrap();
+    world.set_block(pos.add_y(3), block::Type::air()).unwrap();
   }
 }
 
@@ -55,7 +55,7 @@ fn on_tick() {
     let ty = world.get_block(pos).unwrap();
     let new_kind = charge_kind(charge);
     if new_kind != ty.kind() {
-      world.set_block_kind(pos, new_kind);
+      world.set_block_kind(pos, new_kind).unwrap();
     }
     /*
     if ty.prop("age") != 0 && ty.prop("age").int() < 7 {
@@ -63,8 +63,8 @@ fn on_tick() {
     }
     */
   }
-  for p in world.players() {
-    let pos = p.pos();
+  for p in world.players().unwrap() {
+    let Ok(pos) = p.pos() else { continue };
     if pos.y > (MIN.y + 1) as f64 + 0.01 {
       break;
     }
@@ -87,8 +87,9 @@ fn on_tick() {
 }
 
 fn kill(world: &World, player: &Player) {
+  let Ok(pos) = player.pos() else { return };
   world.spawn_particle(particle::Particle {
-    pos:           player.pos(),
+    pos,
     offset:        FPos::new(0.5, 0.5, 0.5),
     ty:            particle::Type::Lava,
     count:         10,
@@ -90,45 +90,15 @@ fn cmd_decrease_brush(player: Option<Player>, args: Vec<Arg>) {
 
 fn on_tick() {
   let world = bb_plugin::world::World::new(0);
-  for player in world.players() {
+  for player in world.players().unwrap() {
     let mut store = bb_plugin::store();
     let info: &mut PlayerInfo = store.player(player.id());
-    let pos = player.pos();
-    let look = player.look_as_vec();
+    // The player may have logged off since the list was made.
+    let (Ok(pos), Ok(look)) = (player.pos(), player.look_as_vec()) else { continue };
+    let look = FPos::from(look);
     let from = pos + FPos::new(0.0, 1.5, 0.0);
     let to = from + look * 50.0;
 
-    /*
-    if let Some(pos) = world.raycast(from, to, true) {
-      let given_x = 1.0;
-      let given_y = 1.0;
-      let z = -(look.x * given_x + look.y * given_y) / look.z;
-      let vec_in_plane = FPos::new(given_x, given_y, z);
-      let unit = vec_in_plane / vec_in_plane.size();
-      let other_unit = unit.cross(look);
-
-      player.send_particle(Particle {
-        ty: particle::Type::Dust(Color { r: 255, g: 255, b: 255 }, 0.5),
-        pos,
-        offset: FPos::new(0.0, 0.0, 0.0),
-        count: 1,
-        data: 0.0,
-        long_distance: false,
-      });
-      for angle in 0..30 {
-        let angle = angle as f64 / 30.0 * 2.0 * std::f64::consts::PI;
-        player.send_particle(Particle {
-          ty:            particle::Type::Dust(Color { r: 255, g: 255, b: 255 }, 0.5),
-          pos:           pos + unit * angle.cos() + other_unit * angle.sin(),
-          offset:        FPos::new(0.0, 0.0, 0.0),
-          count:         1,
-          data:          0.0,
-          long_distance: false,
-        });
-      }
-    }
-    */
-
     if let Some(pos) = world.raycast(from, to, true) {
       player.send_particle(Particle {
         ty: particle::Type::Dust(Color { r: 255, g: 255, b: 255 }, 0.5),
@@ -142,12 +112,7 @@ fn on_tick() {
         return;
       }
 
-      let given_x = 1.0;
-      let given_y = 1.0;
-      let z = -(look.x * given_x + look.y * given_y) / look.z;
-      let vec_in_plane = FPos::new(given_x, given_y, z);
-      let unit = vec_in_plane / vec_in_plane.size();
-      let other_unit = unit.cross(look);
+      let unit = look.perpendicular();
 
       for angle in 0..30 {
         let angle = angle as f64 / 30.0 * 2.0 * std::f64::consts::PI;
@@ -158,13 +123,7 @@ fn on_tick() {
           // Brush size changes with distance
           info.brush_size * 10.0
         };
-        let to = from + unit * angle.cos() * r + other_unit * angle.sin() * r + look * 50.0;
-        /*
-        // Same as constant brush size, but the origin of each raycast is wrong
-        const R: f64 = 2.0;
-        let from = from + unit * angle.cos() * R + other_unit * angle.sin() * R;
-        let to = from + look * 50.0;
-        */
+        let to = from + unit.rotate_around(look, angle) * r + look * 50.0;
         if let Some(pos) = world.raycast(from, to, true) {
           player.send_particle(Particle {
             ty: particle::Type::Dust(Color { r: 255, g: 255, b: 255 }, 0.5),
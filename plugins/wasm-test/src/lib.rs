@@ -10,7 +10,7 @@ extern "C" fn init() {
   bb_plugin::init();
   bb_plugin::set_on_block_place(|player, pos| {
     let mut chat = Chat::new("player: ");
-    chat.add(&player.username());
+    chat.add(&player.username().unwrap());
     chat.add(", x: ").color(Color::Red);
     chat.add(&format!("{}, ", pos.x));
     chat.add("y: ").color(Color::Red);